@@ -0,0 +1,138 @@
+use std::io::Write;
+
+// `--annotate`: строка под паролем с символами, которые легко спутать,
+// например "note: position 3 is capital O (not zero)". Позиции считаются
+// по символам от 1, как их видно в напечатанном пароле. Помечаются символы
+// AMBIGUOUS и AMBIGUOUS_SYMBOLS, то есть ровно те, что убирает -B.
+
+// Символ, как его назвать и с чем его чаще всего путают
+const TABLE: &[(char, &str, &str)] = &[
+    ('B', "capital B", "eight"),
+    ('8', "eight", "capital B"),
+    ('G', "capital G", "six"),
+    ('6', "six", "capital G"),
+    ('I', "capital I", "lowercase l"),
+    ('1', "one", "lowercase l"),
+    ('l', "lowercase l", "one"),
+    ('0', "zero", "capital O"),
+    ('O', "capital O", "zero"),
+    ('Q', "capital Q", "capital O"),
+    ('D', "capital D", "capital O"),
+    ('S', "capital S", "five"),
+    ('5', "five", "capital S"),
+    ('Z', "capital Z", "two"),
+    ('2', "two", "capital Z"),
+    ('\'', "apostrophe", "backtick"),
+    ('`', "backtick", "apostrophe"),
+    ('"', "double quote", "two apostrophes"),
+    ('|', "pipe", "lowercase l"),
+    ('!', "exclamation mark", "pipe"),
+    (',', "comma", "period"),
+    ('.', "period", "comma"),
+    (';', "semicolon", "colon"),
+    (':', "colon", "semicolon"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Annotation {
+    // Позиция символа от 1
+    pub position: usize,
+    pub character: char,
+    pub description: &'static str,
+    pub confused_with: &'static str,
+}
+
+// Пометки по порядку позиций, по одной на каждое вхождение. Итератор, а
+// не Vec: символы пароля не остаются в куче.
+pub fn annotations(password: &str) -> impl Iterator<Item = Annotation> + '_ {
+    password.chars().enumerate().filter_map(|(i, c)| {
+        TABLE.iter().find(|(k, _, _)| *k == c).map(|&(character, description, confused_with)| Annotation {
+            position: i + 1,
+            character,
+            description,
+            confused_with,
+        })
+    })
+}
+
+// Сколько байтов занимает одна пометка вместе с ", " перед ней
+pub const MAX_NOTE: usize = 64;
+
+// "note: position 3 is capital O (not zero), ..."; false и пустая строка,
+// если помечать нечего
+pub fn push_note(line: &mut Vec<u8>, password: &str) -> bool {
+    let mut any = false;
+    for note in annotations(password) {
+        line.extend_from_slice(if any { b", " } else { b"note: " });
+        // Прямо в строку: описание выдает символ пароля
+        let _ = write!(line, "position {} is {} (not {})", note.position, note.description, note.confused_with);
+        any = true;
+    }
+    any
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AMBIGUOUS, AMBIGUOUS_SYMBOLS};
+
+    fn note(password: &str) -> String {
+        let mut line = Vec::new();
+        let any = push_note(&mut line, password);
+        assert_eq!(any, !line.is_empty());
+        String::from_utf8(line).unwrap()
+    }
+
+    #[test]
+    fn test_table() {
+        // Ровно символы -B, каждый по разу
+        let mut table: Vec<u8> = TABLE.iter().map(|(c, _, _)| *c as u8).collect();
+        let mut ambiguous = [AMBIGUOUS, AMBIGUOUS_SYMBOLS].concat();
+        table.sort_unstable();
+        ambiguous.sort_unstable();
+        assert_eq!(table, ambiguous);
+        for (c, description, confused_with) in TABLE {
+            assert_ne!(description, confused_with, "{:?}", c);
+            let longest = format!(", position 1024 is {} (not {})", description, confused_with);
+            assert!(longest.len() <= MAX_NOTE, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_annotations() {
+        let found: Vec<Annotation> = annotations("abO1").collect();
+        assert_eq!(
+            found,
+            [
+                Annotation {
+                    position: 3,
+                    character: 'O',
+                    description: "capital O",
+                    confused_with: "zero",
+                },
+                Annotation {
+                    position: 4,
+                    character: '1',
+                    description: "one",
+                    confused_with: "lowercase l",
+                },
+            ]
+        );
+        assert_eq!(annotations("xyzw").count(), 0);
+        // Позиции по символам, а не по байтам
+        assert_eq!(annotations("жжl").map(|a| a.position).collect::<Vec<_>>(), [3]);
+    }
+
+    #[test]
+    fn test_push_note() {
+        assert_eq!(
+            note("abOcdel9"),
+            "note: position 3 is capital O (not zero), position 7 is lowercase l (not one)"
+        );
+        // Каждое вхождение отдельно
+        assert_eq!(note("0x0"), "note: position 1 is zero (not capital O), position 3 is zero (not capital O)");
+        assert_eq!(note("a;b"), "note: position 2 is semicolon (not colon)");
+        assert_eq!(note("hxkw"), "");
+        assert_eq!(note(""), "");
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Config, MAX_LENGTH, RunError, check_settings, dry_run, passphrase};
+
+// `--bits N`: длина пароля - наименьшая, при которой энтропия не меньше N
+// бит. Энтропия считается так же, как в --dry-run, по набору после -B, -r
+// и -v: log2 размера набора на символ в режиме -s, таблицы фонем, модель
+// Маркова или слоги для запоминаемых паролей, блоки --mobile-friendly. У
+// фраз (--lang, --wordlist) длина - число слов, по биту на слово списка.
+
+pub const MAX_BITS: usize = 4096;
+
+// Погрешность произведения length x log2 n: 16 x log2 64 - ровно 96 бит
+const EPSILON: f64 = 1e-9;
+
+// Длина и энтропия пароля этой длины. Энтропия растет с длиной, так что
+// наименьшая подходящая длина ищется делением пополам.
+pub fn length_for(config: &Config, bits: usize) -> Result<(usize, f64), RunError> {
+    let reaches = |length: usize| dry_run::entropy(config, length).is_some_and(|got| got + EPSILON >= bits as f64);
+    let (longest, unit) = if config.words.is_some() { (passphrase::MAX_WORDS, "words") } else { (MAX_LENGTH, "characters") };
+    if !reaches(longest) {
+        // Пустой набор или список слов - та же ошибка, что и при генерации
+        check_settings(config)?;
+        return Err(RunError::Settings(format!(
+            "--bits {}: even {} {} give only {:.1} bits",
+            bits,
+            longest,
+            unit,
+            dry_run::entropy(config, longest).unwrap_or(0.0)
+        )));
+    }
+    let (mut low, mut high) = (1, longest);
+    while low < high {
+        let middle = (low + high) / 2;
+        if reaches(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Ok((high, dry_run::entropy(config, high).unwrap_or(0.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boundary_rounding() -> Result<(), RunError> {
+        // 64 символа - ровно 6 бит на символ: 96 бит дают 16 символов, 97 - уже 17
+        let config = Config {
+            secure: true,
+            include_only: Some(('0'..='9').chain('a'..='z').chain('A'..='Z').chain(['-', '_']).collect()),
+            ..Config::default()
+        };
+        assert_eq!(length_for(&config, 96)?, (16, 96.0));
+        assert_eq!(length_for(&config, 97)?, (17, 102.0));
+        assert_eq!(length_for(&config, 90)?, (15, 90.0));
+
+        // 62 символа: 16 x log2 62 = 95.27 бит - мало для 96
+        let config = Config { secure: true, ..Config::default() };
+        let (length, got) = length_for(&config, 96)?;
+        assert_eq!(length, 17);
+        assert!(got >= 96.0 && 16.0 * 62f64.log2() < 96.0, "{}", got);
+
+        // Шестнадцатеричные цифры: по 4 бита
+        let config = Config {
+            secure: true,
+            include_only: Some(('0'..='9').chain('a'..='f').collect()),
+            ..Config::default()
+        };
+        assert_eq!(length_for(&config, 128)?, (32, 128.0));
+        assert_eq!(length_for(&config, 1)?, (1, 4.0));
+
+        // -B убирает символы, и длина растет
+        let config = Config { secure: true, ambiguous: true, ..Config::default() };
+        let (length, _) = length_for(&config, 96)?;
+        assert!(length > 17, "{}", length);
+        Ok(())
+    }
+
+    #[test]
+    fn test_other_modes() -> Result<(), RunError> {
+        // В каждом режиме длина - наименьшая, которой хватает
+        let configs = [
+            Config::default(),
+            Config { no_vowels: true, ..Config::default() },
+            Config { mobile_friendly: true, ..Config::default() },
+            Config { secure: true, balanced: true, ..Config::default() },
+            Config { secure: true, weights: Some([8, 2, 3, 1]), ..Config::default() },
+            Config { pin: true, secure: true, ..Config::default() },
+        ];
+        for config in configs {
+            let (length, got) = length_for(&config, 60)?;
+            assert!(got >= 60.0, "{}: {}", length, got);
+            assert!(dry_run::entropy(&config, length - 1).unwrap() < 60.0, "{}", length);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_passphrase_words() -> Result<(), RunError> {
+        // 16 слов - ровно 4 бита на слово: 60 бит дают 15 слов, 61 - уже 16
+        let words: Vec<String> = (0..16).map(|i| format!("word{}", char::from(b'a' + i))).collect();
+        let config = Config {
+            words: Some(passphrase::DEFAULT_WORDS),
+            wordlist: Some(words),
+            ..Config::default()
+        };
+        assert_eq!(length_for(&config, 60)?, (15, 60.0));
+        assert_eq!(length_for(&config, 61)?, (16, 64.0));
+        // С --inject-digit цифра добавляет свои биты, и слов нужно меньше
+        let injected = Config { inject_digit: true, ..config.clone() };
+        let (count, got) = length_for(&injected, 60)?;
+        assert!(count < 15 && got >= 60.0, "{} {}", count, got);
+        let err = length_for(&config, 4000).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("--bits 4000: even {} words give only {:.1} bits", passphrase::MAX_WORDS, 4.0 * passphrase::MAX_WORDS as f64)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let config = Config {
+            secure: true,
+            include_only: Some(vec!['a', 'b']),
+            ..Config::default()
+        };
+        let err = length_for(&config, 2000).unwrap_err();
+        assert_eq!(err.to_string(), "--bits 2000: even 1024 characters give only 1024.0 bits");
+
+        let config = Config {
+            secure: true,
+            include_only: Some(Vec::new()),
+            ..Config::default()
+        };
+        assert!(matches!(length_for(&config, 10), Err(RunError::Settings(_))));
+    }
+}
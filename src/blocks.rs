@@ -0,0 +1,354 @@
+use crate::reject::MAX_ATTEMPTS;
+use crate::rng::{EntropySource, random_index};
+use crate::{
+    CONSONANTS_LOWER, Config, MAX_LENGTH, MemorableModel, NUMERALS, RunError, UPPERCASE, build_charset, class_chars,
+    dry_run, generate_memorable_password, is_allowed, markov, none_left_error, phoneme, required_classes, secret,
+};
+
+// `--blocks`: пароль из блоков вроде "Befoka-2846-Rilomu". w<N> - слово из
+// N букв от генератора запоминаемых паролей, d<N> - N цифр, все остальное -
+// разделители, которые попадают в пароль как есть. Каждый блок берется из
+// общего источника отдельно; -B и -r действуют внутри блоков. Пароль не
+// проходит через apply_requirements: обязательные классы гарантирует сама
+// структура (заглавная - в каждом слове, цифры - в блоке d, символ - в
+// разделителе), а если не гарантирует, это ошибка настроек.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Block {
+    Word(usize),
+    Digits(usize),
+    Literal(char),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    spec: String,
+    blocks: Vec<Block>,
+}
+
+// Из чего собираются блоки после -B и -r
+pub struct Parts {
+    // Настройки генератора для слов: только строчные буквы, так что
+    // apply_requirements внутри него ничего не добавляет
+    words: Config,
+    digits: Vec<char>,
+    // В каждом слове одна буква становится заглавной
+    capitalize: bool,
+}
+
+impl Spec {
+    // Ошибка - что ожидалось, со столбцом, если он есть
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut blocks = Vec::new();
+        let mut chars = spec.chars().enumerate().peekable();
+        while let Some((column, c)) = chars.next() {
+            if c != 'w' && c != 'd' {
+                blocks.push(Block::Literal(c));
+                continue;
+            }
+            let mut digits = String::new();
+            while let Some(&(_, d)) = chars.peek()
+                && d.is_ascii_digit()
+            {
+                digits.push(d);
+                chars.next();
+            }
+            let length = match digits.parse::<usize>() {
+                Ok(n) if (1..=MAX_LENGTH).contains(&n) => n,
+                _ => {
+                    return Err(format!("a block length from 1 to {} after '{}' at column {}", MAX_LENGTH, c, column + 1));
+                }
+            };
+            blocks.push(if c == 'w' { Block::Word(length) } else { Block::Digits(length) });
+        }
+        if blocks.iter().all(|block| matches!(block, Block::Literal(_))) {
+            return Err("blocks such as w6-d4-w6".to_string());
+        }
+        let spec = Self { spec: spec.to_string(), blocks };
+        if spec.len() > MAX_LENGTH {
+            return Err(format!("blocks of at most {} characters in total", MAX_LENGTH));
+        }
+        Ok(spec)
+    }
+
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    // Длина пароля по блокам
+    pub fn len(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|block| match *block {
+                Block::Word(n) | Block::Digits(n) => n,
+                Block::Literal(_) => 1,
+            })
+            .sum()
+    }
+
+    // Длина в байтах: разделители не обязаны быть ASCII
+    pub fn byte_len(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|block| match *block {
+                Block::Word(n) | Block::Digits(n) => n,
+                Block::Literal(c) => c.len_utf8(),
+            })
+            .sum()
+    }
+
+    fn words(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks.iter().filter_map(|block| match *block {
+            Block::Word(n) => Some(n),
+            _ => None,
+        })
+    }
+
+    fn digit_blocks(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks.iter().filter_map(|block| match *block {
+            Block::Digits(n) => Some(n),
+            _ => None,
+        })
+    }
+
+    // Проверяет, что блоки можно собрать и что они дают все обязательные классы
+    pub fn parts(&self, config: &Config) -> Result<Parts, RunError> {
+        let words = Config {
+            no_capitalize: true,
+            no_numerals: true,
+            symbols: false,
+            ..config.clone()
+        };
+        let has_words = self.words().next().is_some();
+        if has_words {
+            if words.no_vowels {
+                if !class_chars(CONSONANTS_LOWER).any(|c| is_allowed(c, &words)) {
+                    return Err(none_left_error("consonants"));
+                }
+            } else if words.syllables.is_none() {
+                match words.memorable_model {
+                    MemorableModel::Phoneme => {
+                        if let Some(name) = phoneme::missing(&words) {
+                            return Err(none_left_error(name));
+                        }
+                    }
+                    MemorableModel::Markov => {
+                        markov::Model::new(&words)?;
+                    }
+                }
+            }
+        }
+        let digits: Vec<char> = class_chars(NUMERALS).filter(|&c| is_allowed(c, config)).collect();
+        if digits.is_empty() && self.digit_blocks().next().is_some() {
+            return Err(RunError::Settings(format!(
+                "--blocks {}: no digit is left for the d blocks after applying --ambiguous/--remove-chars",
+                self.spec
+            )));
+        }
+
+        let charset = build_charset(config);
+        let mut capitalize = false;
+        for (name, class, _) in required_classes(config, &charset) {
+            let literal = self.blocks.iter().any(|block| match *block {
+                Block::Literal(c) => u8::try_from(c).is_ok_and(|b| class.contains(&b)),
+                _ => false,
+            });
+            let guaranteed = if class == UPPERCASE {
+                capitalize = has_words;
+                literal || has_words
+            } else if class == NUMERALS {
+                literal || self.digit_blocks().next().is_some()
+            } else {
+                literal
+            };
+            if !guaranteed {
+                let (what, hint) = match name {
+                    "uppercase" => ("an uppercase letter", "add a w block or use -A"),
+                    "digit" => ("a digit", "add a d block or use -0"),
+                    _ => ("a symbol", "add a symbol such as '-' between blocks or drop -y"),
+                };
+                return Err(RunError::Settings(format!("--blocks {} cannot guarantee {}; {}", self.spec, what, hint)));
+            }
+        }
+        Ok(Parts { words, digits, capitalize })
+    }
+
+    pub fn generate<R: EntropySource + ?Sized>(&self, parts: &Parts, rng: &mut R) -> Result<String, RunError> {
+        let mut password = String::with_capacity(self.byte_len());
+        for block in &self.blocks {
+            match *block {
+                Block::Literal(c) => password.push(c),
+                Block::Digits(n) => {
+                    for _ in 0..n {
+                        password.push(parts.digits[random_index(rng, parts.digits.len())?]);
+                    }
+                }
+                Block::Word(n) => {
+                    let mut word = word(n, parts, rng)?;
+                    password.push_str(&word);
+                    secret::wipe_str(&mut word);
+                }
+            }
+        }
+        Ok(password)
+    }
+
+    // Энтропия слов (оценка сверху, как в --dry-run) и цифр. Заглавная
+    // буква слова не добавляет бит: она всегда первая подходящая.
+    pub fn entropy(&self, parts: &Parts) -> (f64, f64) {
+        let words = self.words().map(|n| dry_run::entropy(&parts.words, n).unwrap_or(0.0)).sum();
+        let digits = self.digit_blocks().map(|n| n as f64 * (parts.digits.len() as f64).log2()).sum();
+        (words, digits)
+    }
+
+    // Число блоков слов и цифр и символов в них для отчета
+    pub fn counts(&self) -> ((usize, usize), (usize, usize)) {
+        let words: Vec<usize> = self.words().collect();
+        let digits: Vec<usize> = self.digit_blocks().collect();
+        ((words.len(), words.iter().sum()), (digits.len(), digits.iter().sum()))
+    }
+}
+
+impl Parts {
+    pub fn digits(&self) -> &[char] {
+        &self.digits
+    }
+}
+
+// Слово из генератора; заглавной становится первая буква, у которой
+// заглавная не исключена через -B и -r, а если такой нет - слово другое
+fn word<R: EntropySource + ?Sized>(length: usize, parts: &Parts, rng: &mut R) -> Result<String, RunError> {
+    for _ in 0..MAX_ATTEMPTS {
+        let mut word = generate_memorable_password(length, &parts.words, rng)?;
+        if !parts.capitalize {
+            return Ok(word);
+        }
+        let first = word
+            .char_indices()
+            .find(|&(_, c)| c.is_ascii_lowercase() && is_allowed(c.to_ascii_uppercase(), &parts.words));
+        if let Some((pos, _)) = first {
+            word[pos..=pos].make_ascii_uppercase();
+            return Ok(word);
+        }
+        secret::wipe_str(&mut word);
+    }
+    Err(RunError::Generation(format!(
+        "--blocks: no {}-letter word with a letter to capitalize in {} attempts; use -A",
+        length, MAX_ATTEMPTS
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+
+    #[test]
+    fn test_parse() {
+        let spec = Spec::parse("w6-d4-w6").unwrap();
+        assert_eq!(
+            spec.blocks,
+            [
+                Block::Word(6),
+                Block::Literal('-'),
+                Block::Digits(4),
+                Block::Literal('-'),
+                Block::Word(6),
+            ]
+        );
+        assert_eq!((spec.spec(), spec.len()), ("w6-d4-w6", 18));
+
+        let spec = Spec::parse("d2w10.=x").unwrap();
+        assert_eq!(
+            spec.blocks,
+            [Block::Digits(2), Block::Word(10), Block::Literal('.'), Block::Literal('='), Block::Literal('x')]
+        );
+        assert_eq!(spec.len(), 15);
+        assert_eq!(Spec::parse("w3·d3").unwrap().byte_len(), 8);
+
+        assert_eq!(Spec::parse("w6-d").unwrap_err(), "a block length from 1 to 1024 after 'd' at column 4");
+        assert!(Spec::parse("w0").is_err());
+        assert!(Spec::parse("wx").is_err());
+        assert!(Spec::parse("---").is_err());
+        assert!(Spec::parse("").is_err());
+        assert!(Spec::parse("w1000d100").is_err());
+        assert!(Spec::parse("w99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn test_block_boundaries() -> Result<(), RunError> {
+        let spec = Spec::parse("w6-d4-w6").unwrap();
+        let parts = spec.parts(&Config::default())?;
+        let mut rng = ChaCha20Rng::seeded(587);
+        for _ in 0..100 {
+            let password = spec.generate(&parts, &mut rng)?;
+            let blocks: Vec<&str> = password.split('-').collect();
+            assert_eq!(blocks.len(), 3, "{}", password);
+            assert!(blocks[1].len() == 4 && blocks[1].bytes().all(|b| b.is_ascii_digit()), "{}", password);
+            for word in [blocks[0], blocks[2]] {
+                assert_eq!(word.len(), 6, "{}", password);
+                assert!(word.bytes().all(|b| b.is_ascii_alphabetic()), "{}", password);
+                assert_eq!(word.bytes().filter(u8::is_ascii_uppercase).count(), 1, "{}", password);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclusions() -> Result<(), RunError> {
+        // -r убирает цифры и буквы внутри блоков, но не разделители
+        let config = Config {
+            remove_chars: Some(vec!['1', '2', '3', 'a', 'A', 'e', 'E', '-']),
+            ..Config::default()
+        };
+        let spec = Spec::parse("w8-d6").unwrap();
+        let parts = spec.parts(&config)?;
+        assert_eq!(parts.digits(), ['0', '4', '5', '6', '7', '8', '9']);
+        let mut rng = ChaCha20Rng::seeded(587);
+        for _ in 0..100 {
+            let password = spec.generate(&parts, &mut rng)?;
+            assert_eq!(password.find('-'), Some(8), "{}", password);
+            assert!(!password[..8].contains(['a', 'A', 'e', 'E']), "{}", password);
+            assert!(!password[9..].contains(['1', '2', '3']), "{}", password);
+        }
+
+        // Без цифр блок d собрать не из чего
+        let config = Config {
+            remove_chars: Some(NUMERALS.iter().map(|&c| char::from(c)).collect()),
+            ..Config::default()
+        };
+        assert!(matches!(spec.parts(&config), Err(RunError::Settings(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_required_classes() {
+        let check = |spec: &str, config: &Config| Spec::parse(spec).unwrap().parts(config).map(|_| ());
+        // По умолчанию нужны заглавная и цифра
+        assert!(check("w6-d4", &Config::default()).is_ok());
+        let err = check("w6-w6", &Config::default()).unwrap_err();
+        assert_eq!(err.to_string(), "--blocks w6-w6 cannot guarantee a digit; add a d block or use -0");
+        assert!(check("w6-w6-7", &Config::default()).is_ok());
+        let err = check("d4-d4", &Config::default()).unwrap_err();
+        assert!(err.to_string().contains("cannot guarantee an uppercase letter; add a w block"), "{}", err);
+        // Символ дает только разделитель
+        let symbols = Config { symbols: true, ..Config::default() };
+        assert!(check("w6-d4", &symbols).is_ok());
+        assert!(check("w6d4", &symbols).unwrap_err().to_string().contains("drop -y"));
+        // Без требований подходит любая структура
+        let none = Config { no_capitalize: true, no_numerals: true, ..Config::default() };
+        assert!(check("w6w6", &none).is_ok());
+    }
+
+    #[test]
+    fn test_entropy() -> Result<(), RunError> {
+        let spec = Spec::parse("w6-d4-w6").unwrap();
+        let parts = spec.parts(&Config::default())?;
+        let (words, digits) = spec.entropy(&parts);
+        assert!((digits - 4.0 * 10f64.log2()).abs() < 1e-9);
+        let word = dry_run::entropy(&parts.words, 6).unwrap();
+        assert!((words - 2.0 * word).abs() < 1e-9 && word > 0.0);
+        assert_eq!(spec.counts(), ((2, 12), (1, 4)));
+        Ok(())
+    }
+}
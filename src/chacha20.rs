@@ -0,0 +1,314 @@
+use std::io::{self, Read};
+
+// Сколько байтов гаммы выдается на одном ключе до повторного посева
+pub const RESEED_INTERVAL: u64 = 1 << 20;
+
+const KEY_SIZE: usize = 32;
+const BLOCK_SIZE: usize = 64;
+
+// "expand 32-byte k"
+const CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+// Блочная функция ChaCha20 (RFC 7539, раздел 2.3)
+pub fn block(key: &[u32; 8], counter: u32, nonce: &[u32; 3]) -> [u8; BLOCK_SIZE] {
+    let mut initial = [0u32; 16];
+    initial[..4].copy_from_slice(&CONSTANTS);
+    initial[4..12].copy_from_slice(key);
+    initial[12] = counter;
+    initial[13..].copy_from_slice(nonce);
+
+    let mut state = initial;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    let mut output = [0u8; BLOCK_SIZE];
+    for (i, chunk) in output.chunks_exact_mut(4).enumerate() {
+        chunk.copy_from_slice(&state[i].wrapping_add(initial[i]).to_le_bytes());
+    }
+    output
+}
+
+// Быстрый генератор для больших пакетов (--fast-rng): 32 байта ключа
+// читаются из системного источника, дальше гамма ChaCha20 считается в
+// userspace. Каждые RESEED_INTERVAL байтов ключ берется заново, так что
+// счетчик блоков никогда не повторяется на одном ключе, а выданные байты
+// стираются из буфера сразу после использования.
+pub struct ChaCha20Rng<S: Read> {
+    source: S,
+    key: [u32; 8],
+    counter: u32,
+    block: [u8; BLOCK_SIZE],
+    pos: usize,
+    generated: u64,
+    reseed_interval: u64,
+}
+
+impl<S: Read> ChaCha20Rng<S> {
+    pub fn new(source: S) -> io::Result<Self> {
+        Self::with_reseed_interval(source, RESEED_INTERVAL)
+    }
+
+    pub fn with_reseed_interval(source: S, reseed_interval: u64) -> io::Result<Self> {
+        let mut rng = Self {
+            source,
+            key: [0; 8],
+            counter: 0,
+            block: [0; BLOCK_SIZE],
+            pos: BLOCK_SIZE,
+            generated: 0,
+            reseed_interval,
+        };
+        rng.reseed()?;
+        Ok(rng)
+    }
+
+    fn reseed(&mut self) -> io::Result<()> {
+        let mut seed = [0u8; KEY_SIZE];
+        self.source.read_exact(&mut seed)?;
+        for (word, chunk) in self.key.iter_mut().zip(seed.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        seed.fill(0);
+        self.counter = 0;
+        self.generated = 0;
+        Ok(())
+    }
+
+    fn next_block(&mut self) -> io::Result<()> {
+        if self.generated >= self.reseed_interval {
+            self.reseed()?;
+        }
+        self.block = block(&self.key, self.counter, &[0; 3]);
+        self.counter += 1;
+        self.generated += BLOCK_SIZE as u64;
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl ChaCha20Rng<io::Empty> {
+    // Детерминированный поток для --seed: ключ - это seed в little-endian,
+    // дополненный нулями. Пересев отключен, поэтому внешний источник не нужен.
+    // Такой поток предсказуем и не годится для настоящих паролей.
+    pub fn seeded(seed: u64) -> Self {
+        let mut key = [0u32; 8];
+        key[0] = seed as u32;
+        key[1] = (seed >> 32) as u32;
+        Self {
+            source: io::empty(),
+            key,
+            counter: 0,
+            block: [0; BLOCK_SIZE],
+            pos: BLOCK_SIZE,
+            generated: 0,
+            reseed_interval: u64::MAX,
+        }
+    }
+
+    // Другой поток того же seed для случайных выборов помимо паролей
+    // (слова --mnemonic): третье слово ключа - номер потока, так что гамма
+    // не связана с гаммой паролей, но так же воспроизводима
+    pub fn seeded_stream(seed: u64, stream: u32) -> Self {
+        let mut rng = Self::seeded(seed);
+        rng.key[2] = stream;
+        rng
+    }
+}
+
+// Ключ и остаток гаммы позволяют восстановить сгенерированные пароли
+impl<S: Read> Drop for ChaCha20Rng<S> {
+    fn drop(&mut self) {
+        for word in self.key.iter_mut() {
+            // SAFETY: word - валидная ссылка на элемент ключа
+            unsafe { std::ptr::write_volatile(word, 0) };
+        }
+        crate::secret::wipe(&mut self.block);
+    }
+}
+
+impl<S: Read> Read for ChaCha20Rng<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        for byte in buf.iter_mut() {
+            if self.pos == BLOCK_SIZE {
+                self.next_block()?;
+            }
+            *byte = self.block[self.pos];
+            self.block[self.pos] = 0;
+            self.pos += 1;
+        }
+        Ok(buf.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::io::Cursor;
+
+    // Источник, считающий, сколько байтов из него прочитали
+    struct CountingSource {
+        inner: Cursor<Vec<u8>>,
+        consumed: usize,
+    }
+
+    impl Read for CountingSource {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.consumed += n;
+            Ok(n)
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_block_rfc7539_zero_key() {
+        // RFC 7539, приложение A.1, тестовый вектор #1
+        let output = block(&[0; 8], 0, &[0; 3]);
+        assert_eq!(
+            hex(&output),
+            "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7\
+             da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee6586"
+        );
+    }
+
+    #[test]
+    fn test_block_rfc7539_section_2_3_2() {
+        let key: Vec<u8> = (0..32).collect();
+        let mut words = [0u32; 8];
+        for (word, chunk) in words.iter_mut().zip(key.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        let nonce = [0x09000000, 0x4a000000, 0x00000000];
+
+        let output = block(&words, 1, &nonce);
+        assert_eq!(
+            hex(&output),
+            "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+             d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+        );
+    }
+
+    #[test]
+    fn test_rng_seeded_from_source() -> io::Result<()> {
+        // Нулевой ключ дает тот же поток, что и блочная функция
+        let mut rng = ChaCha20Rng::new(Cursor::new(vec![0u8; 32]))?;
+        let mut buf = [0u8; 128];
+        rng.read_exact(&mut buf)?;
+
+        assert_eq!(buf[..64], block(&[0; 8], 0, &[0; 3]));
+        assert_eq!(buf[64..], block(&[0; 8], 1, &[0; 3]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rng_reseeds_periodically() -> io::Result<()> {
+        let seed: Vec<u8> = (0..=255).cycle().take(32 * 4).collect();
+        let source = CountingSource {
+            inner: Cursor::new(seed),
+            consumed: 0,
+        };
+        let mut rng = ChaCha20Rng::with_reseed_interval(source, 256)?;
+        assert_eq!(rng.source.consumed, 32);
+
+        // 256 байтов на ключ: на 1024 байта нужно четыре ключа
+        let mut buf = vec![0u8; 1024];
+        rng.read_exact(&mut buf)?;
+        assert_eq!(rng.source.consumed, 32 * 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rng_keystream_never_repeats() -> io::Result<()> {
+        let seed: Vec<u8> = (0..32 * 8).map(|i| (i * 31 + 7) as u8).collect();
+        let mut rng = ChaCha20Rng::with_reseed_interval(Cursor::new(seed), 512)?;
+
+        let mut buf = vec![0u8; 64 * 60];
+        rng.read_exact(&mut buf)?;
+
+        // Ни один блок гаммы не выдается дважды, в том числе после пересева
+        let blocks: HashSet<&[u8]> = buf.chunks_exact(64).collect();
+        assert_eq!(blocks.len(), 60);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rng_wipes_consumed_bytes() -> io::Result<()> {
+        let mut rng = ChaCha20Rng::new(Cursor::new(vec![7u8; 32]))?;
+        let mut buf = [0u8; 10];
+        rng.read_exact(&mut buf)?;
+
+        assert!(rng.block[..10].iter().all(|&b| b == 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_seeded_is_deterministic() -> io::Result<()> {
+        let mut a = ChaCha20Rng::seeded(42);
+        let mut b = ChaCha20Rng::seeded(42);
+        let mut c = ChaCha20Rng::seeded(43);
+
+        let mut buf_a = vec![0u8; 4096];
+        let mut buf_b = vec![0u8; 4096];
+        let mut buf_c = vec![0u8; 4096];
+        a.read_exact(&mut buf_a)?;
+        b.read_exact(&mut buf_b)?;
+        c.read_exact(&mut buf_c)?;
+
+        assert_eq!(buf_a, buf_b);
+        assert_ne!(buf_a, buf_c);
+        Ok(())
+    }
+
+    #[test]
+    fn test_seeded_zero_matches_zero_key() -> io::Result<()> {
+        let mut rng = ChaCha20Rng::seeded(0);
+        let mut buf = [0u8; 64];
+        rng.read_exact(&mut buf)?;
+        assert_eq!(buf, block(&[0; 8], 0, &[0; 3]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_seeded_stream() -> io::Result<()> {
+        let read = |mut rng: ChaCha20Rng<io::Empty>| -> io::Result<Vec<u8>> {
+            let mut buf = vec![0u8; 256];
+            rng.read_exact(&mut buf)?;
+            Ok(buf)
+        };
+        let stream = read(ChaCha20Rng::seeded_stream(42, 1))?;
+        assert_eq!(stream, read(ChaCha20Rng::seeded_stream(42, 1))?);
+        assert_ne!(stream, read(ChaCha20Rng::seeded(42))?);
+        assert_ne!(stream, read(ChaCha20Rng::seeded_stream(42, 2))?);
+        assert_eq!(read(ChaCha20Rng::seeded_stream(42, 0))?, read(ChaCha20Rng::seeded(42))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rng_short_seed_is_error() {
+        let result = ChaCha20Rng::new(Cursor::new(vec![0u8; 16]));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{dictionary, keyboard, pin, secret, sequence};
+use crate::{COUNTED_CLASSES, Config, build_charset, can_end, can_lead, in_class, required_classes, shortest_length};
+
+// `pwgen check`: пароли из stdin, по одному в строке, проверяются по тем
+// же правилам, по которым генерируются: длина не меньше pw_length (или в
+// пределах --length-range), только
+// символы из набора (-A, -0, -B, -v, -r), обязательные классы (-c, -n, -y)
+// и число символов класса (--min-*, --max-*), цепочки (--no-sequences) и
+// прогулки по клавиатуре (--no-keyboard-walks), слова (--no-dictionary-words),
+// грубые слова (--no-profanity) и слабые PIN (--pin).
+// Сами пароли не печатаются, только номера строк.
+pub fn run<R: BufRead, W: Write>(config: &Config, mut input: R, out: &mut W) -> io::Result<bool> {
+    let mut line = String::new();
+    let mut number = 0;
+    let mut all_passed = true;
+    loop {
+        secret::wipe_str(&mut line);
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+        number += 1;
+
+        let password = line.trim_end_matches(['\n', '\r']);
+        let problems = problems(password, config);
+        if problems.is_empty() {
+            writeln!(out, "line {}: ok", number)?;
+        } else {
+            all_passed = false;
+            writeln!(out, "line {}: {}", number, problems.join("; "))?;
+        }
+    }
+    Ok(all_passed)
+}
+
+// Чем пароль не подходит под правила config; пустой список - подходит
+pub fn problems(password: &str, config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let length = password.chars().count();
+    let shortest = shortest_length(config);
+    if length < shortest {
+        problems.push(format!(
+            "too short ({} < {} characters)",
+            length, shortest
+        ));
+    }
+    if let Some((_, max)) = config.length_range
+        && length > max
+    {
+        problems.push(format!("too long ({} > {} characters)", length, max));
+    }
+
+    if let Some(first) = password.chars().next()
+        && !can_lead(first, config)
+    {
+        let what = if first.is_ascii_digit() { "digit" } else { "symbol" };
+        problems.push(format!("starts with a {}", what));
+    }
+    if let Some(last) = password.chars().last()
+        && !can_end(last, config)
+    {
+        problems.push("ends with a symbol".to_string());
+    }
+
+    let charset = build_charset(config);
+    // Символы из --require-chars допустимы, даже если их нет в наборе
+    let chars = config.require_chars.as_deref().unwrap_or_default();
+    let foreign: Vec<String> = password
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| !charset.contains(c) && !chars.contains(c))
+        .map(|(pos, _)| (pos + 1).to_string())
+        .collect();
+    if !foreign.is_empty() {
+        problems.push(format!("disallowed character at position {}", foreign.join(", ")));
+    }
+
+    for (name, _, allowed) in required_classes(config, &charset) {
+        if !password.chars().any(|c| allowed.contains(&c)) {
+            problems.push(format!("no {}", name));
+        }
+    }
+    for c in chars {
+        if !password.contains(*c) {
+            problems.push(format!("no {:?}", c));
+        }
+    }
+
+    let counts = &config.class_counts;
+    for (i, (_, class, name, plural)) in COUNTED_CLASSES.iter().enumerate() {
+        let count = password.chars().filter(|&c| in_class(class, c)).count();
+        // Про класс, которого нет совсем, уже сказано "no digit"
+        if count < counts.min[i] && !(count == 0 && problems.contains(&format!("no {}", name))) {
+            problems.push(format!("too few {} ({} < {})", plural, count, counts.min[i]));
+        }
+        if let Some(max) = counts.max[i]
+            && count > max
+        {
+            problems.push(format!("too many {} ({} > {})", plural, count, max));
+        }
+    }
+
+    if config.pin
+        && let Some(why) = pin::weakness(password)
+    {
+        problems.push(format!("weak PIN ({})", why));
+    }
+    if let Some(run) = config.no_sequences
+        && sequence::has_sequence(password, run)
+    {
+        problems.push(format!("contains a run of {} characters", run));
+    }
+    if config.no_keyboard_walks && keyboard::has_walk(password, keyboard::WALK_LENGTH) {
+        problems.push("contains a keyboard walk".to_string());
+    }
+    if let Some(length) = config.no_dictionary_words
+        && dictionary::has_word(password, length)
+    {
+        problems.push("contains a dictionary word".to_string());
+    }
+    if config.profanity.as_ref().is_some_and(|list| list.matches(password)) {
+        problems.push("contains an offensive word".to_string());
+    }
+    problems
+}
+
+pub fn print_help() -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "Usage: pwgen check [ OPTIONS ] [ pw_length ]")?;
+    writeln!(out)?;
+    writeln!(out, "Read passwords from stdin, one per line, and check them against the")?;
+    writeln!(out, "policy given by the same options as password generation: a length of")?;
+    writeln!(out, "at least pw_length (or within --length-range), only characters from the")?;
+    writeln!(out, "generated character set, and every required character class. Each line")?;
+    writeln!(out, "is reported by number; the passwords themselves are never printed. The")?;
+    writeln!(out, "exit status is 1 if any password fails.")?;
+    writeln!(out)?;
+    writeln!(out, "Options that set the policy: -c, -A, -n, -0, -y, -r, -B, --ambiguous-chars,")?;
+    writeln!(out, "-v, -L, --length-range, --pin, --no-sequences, --no-keyboard-walks,")?;
+    writeln!(out, "--no-dictionary-words, --no-profanity and --profanity-file")?;
+    writeln!(out, "(see 'pwgen --help').")?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Config {
+        Config {
+            symbols: true,
+            ambiguous: true,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_problems() {
+        let config = policy();
+        assert!(problems("Xyz7#abc", &config).is_empty());
+        assert_eq!(problems("Xyz7#ab", &config), ["too short (7 < 8 characters)"]);
+        let range = Config {
+            length_range: Some((6, 7)),
+            ..policy()
+        };
+        assert!(problems("Xyz7#a", &range).is_empty());
+        assert_eq!(problems("Xyz7#abc", &range), ["too long (8 > 7 characters)"]);
+        assert_eq!(problems("xyz7#abc", &config), ["no uppercase"]);
+        assert_eq!(problems("Xyzw#abc", &config), ["no digit"]);
+        assert_eq!(
+            problems("xyzwvabc", &config),
+            ["no uppercase", "no digit", "no symbol"]
+        );
+        // 0 и O исключены через -B; не-ASCII вне набора
+        assert_eq!(
+            problems("X0z7#abé", &config),
+            ["disallowed character at position 2, 8"]
+        );
+    }
+
+    #[test]
+    fn test_class_counts() {
+        let mut config = policy();
+        config.class_counts.min = [0, 0, 2, 0];
+        config.class_counts.max = [None, None, None, Some(1)];
+        assert!(problems("Xyz7#ab9", &config).is_empty());
+        assert_eq!(problems("Xyz7#abc", &config), ["too few digits (1 < 2)"]);
+        assert_eq!(problems("Xyz7#a#9", &config), ["too many symbols (2 > 1)"]);
+        assert_eq!(problems("Xyzw#abc", &config), ["no digit"]);
+    }
+
+    #[test]
+    fn test_leading_character() {
+        let config = Config {
+            no_leading_digit: true,
+            no_leading_symbol: true,
+            ..policy()
+        };
+        assert!(problems("Xyz7#abc", &config).is_empty());
+        assert_eq!(problems("7yzX#abc", &config), ["starts with a digit"]);
+        assert_eq!(problems("#yz7Xabc", &config), ["starts with a symbol"]);
+        assert!(problems("#yz7Xabc", &policy()).is_empty());
+
+        let config = Config {
+            no_edge_symbols: true,
+            ..policy()
+        };
+        assert!(problems("Xyz7#abc", &config).is_empty());
+        assert_eq!(problems("Xyz7abc#", &config), ["ends with a symbol"]);
+        assert_eq!(problems("#yz7Xabc", &config), ["starts with a symbol"]);
+    }
+
+    #[test]
+    fn test_filters() {
+        let config = Config {
+            no_sequences: Some(3),
+            ..policy()
+        };
+        assert_eq!(problems("Xyz7#abc", &config), ["contains a run of 3 characters"]);
+        assert!(problems("Xzy7#acb", &config).is_empty());
+        assert!(problems("Xyz7#abc", &policy()).is_empty());
+
+        let config = Config {
+            no_keyboard_walks: true,
+            ..policy()
+        };
+        assert_eq!(problems("Xyz7#asd", &config), ["contains a keyboard walk"]);
+        assert!(problems("Xyz7#abc", &config).is_empty());
+
+        // Без встроенного словаря слов не находится
+        if cfg!(feature = "dictionary") {
+            let config = Config {
+                no_dictionary_words: Some(4),
+                ..policy()
+            };
+            assert_eq!(problems("Xyz7#pass", &config), ["contains a dictionary word"]);
+            assert!(problems("Xyz7#pas", &config).is_empty());
+        }
+
+        let config = Config {
+            profanity: Some(crate::profanity::Blocklist::builtin()),
+            ..policy()
+        };
+        assert_eq!(problems("Xyz7#$hit", &config), ["contains an offensive word"]);
+        assert!(problems("Xyz7#abc", &config).is_empty());
+
+        // PIN проверяется как код из цифр, без заглавных
+        let config = Config {
+            pin: true,
+            secure: true,
+            pw_length: 4,
+            ..Config::default()
+        };
+        assert_eq!(problems("2580", &config), ["weak PIN (a common PIN)"]);
+        assert_eq!(problems("9876", &config), ["weak PIN (consecutive digits)"]);
+        assert!(problems("9172", &config).is_empty());
+        assert_eq!(problems("91a2", &config), ["disallowed character at position 3"]);
+    }
+
+    #[test]
+    fn test_run_reports_by_line() -> io::Result<()> {
+        let input = "Xyz7#abc\nhunter\r\nXyz7#abcdef\n";
+        let mut out = Vec::new();
+        assert!(!run(&policy(), input.as_bytes(), &mut out)?);
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            "line 1: ok\n\
+             line 2: too short (6 < 8 characters); no uppercase; no digit; no symbol\n\
+             line 3: ok\n"
+        );
+        // Пароли в отчет не попадают
+        assert!(!out.contains("hunter"));
+
+        let mut out = Vec::new();
+        assert!(run(&policy(), "Xyz7#abc\n".as_bytes(), &mut out)?);
+        Ok(())
+    }
+}
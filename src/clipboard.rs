@@ -0,0 +1,334 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use crate::secret;
+use crate::sha1::Sha1;
+
+// Через сколько секунд буфер обмена очищается по умолчанию (--clear-after)
+pub const DEFAULT_CLEAR_AFTER: u64 = 45;
+
+// Скрытый аргумент, с которым запускается фоновый процесс очистки
+pub const WORKER_ARG: &str = "--clipboard-worker";
+
+// Когда backend применим
+enum Requirement {
+    Env(&'static str),
+    Os(&'static str),
+}
+
+// Внешние программы для работы с буфером обмена. Пустой clear означает,
+// что буфер очищается копированием пустой строки.
+struct Backend {
+    name: &'static str,
+    requires: Requirement,
+    copy: &'static [&'static str],
+    paste: &'static [&'static str],
+    clear: &'static [&'static str],
+}
+
+// Порядок важен: под XWayland доступны и Wayland, и X11
+const BACKENDS: &[Backend] = &[
+    Backend {
+        name: "wayland",
+        requires: Requirement::Env("WAYLAND_DISPLAY"),
+        copy: &["wl-copy"],
+        paste: &["wl-paste", "--no-newline"],
+        clear: &["wl-copy", "--clear"],
+    },
+    Backend {
+        name: "xclip",
+        requires: Requirement::Env("DISPLAY"),
+        copy: &["xclip", "-selection", "clipboard"],
+        paste: &["xclip", "-selection", "clipboard", "-o"],
+        clear: &[],
+    },
+    Backend {
+        name: "xsel",
+        requires: Requirement::Env("DISPLAY"),
+        copy: &["xsel", "--clipboard", "--input"],
+        paste: &["xsel", "--clipboard", "--output"],
+        clear: &["xsel", "--clipboard", "--delete"],
+    },
+    Backend {
+        name: "macos",
+        requires: Requirement::Os("macos"),
+        copy: &["pbcopy"],
+        paste: &["pbpaste"],
+        clear: &[],
+    },
+    Backend {
+        name: "windows",
+        requires: Requirement::Os("windows"),
+        copy: &["clip.exe"],
+        paste: &["powershell.exe", "-NoProfile", "-Command", "Get-Clipboard -Raw"],
+        clear: &[],
+    },
+];
+
+pub struct Clipboard {
+    backend: &'static Backend,
+    search_path: OsString,
+}
+
+impl Clipboard {
+    // Первый backend, который подходит к окружению и чьи программы есть в PATH
+    pub fn detect() -> io::Result<Self> {
+        Self::detect_in(
+            env::var_os("PATH").unwrap_or_default(),
+            |name| env::var_os(name).is_some_and(|value| !value.is_empty()),
+        )
+    }
+
+    fn detect_in(search_path: OsString, has_env: impl Fn(&str) -> bool) -> io::Result<Self> {
+        BACKENDS
+            .iter()
+            .filter(|backend| match backend.requires {
+                Requirement::Env(name) => has_env(name),
+                Requirement::Os(os) => env::consts::OS == os,
+            })
+            .find(|backend| {
+                find_program(&search_path, backend.copy[0]).is_some()
+                    && find_program(&search_path, backend.paste[0]).is_some()
+            })
+            .map(|backend| Self {
+                backend,
+                search_path: search_path.clone(),
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "no clipboard backend found (install wl-clipboard, xclip or xsel)",
+                )
+            })
+    }
+
+    // Backend, выбранный родительским процессом (для фоновой очистки)
+    fn by_name(name: &str) -> io::Result<Self> {
+        BACKENDS
+            .iter()
+            .find(|backend| backend.name == name)
+            .map(|backend| Self {
+                backend,
+                search_path: env::var_os("PATH").unwrap_or_default(),
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown clipboard backend {}", name),
+                )
+            })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.backend.name
+    }
+
+    fn command(&self, argv: &[&str]) -> io::Result<Command> {
+        let program = find_program(&self.search_path, argv[0]).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{} not found in PATH", argv[0]))
+        })?;
+        let mut command = Command::new(program);
+        command.args(&argv[1..]);
+        Ok(command)
+    }
+
+    // Данные передаются через stdin, а не аргументами: аргументы видны в ps
+    fn run_with_input(&self, argv: &[&str], data: &[u8]) -> io::Result<()> {
+        // xclip и wl-copy остаются в фоне обслуживать буфер, поэтому
+        // их вывод не должен держать наши каналы открытыми
+        let mut child = self
+            .command(argv)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let written = child.stdin.take().unwrap().write_all(data);
+        let status = child.wait()?;
+        written?;
+        if !status.success() {
+            return Err(io::Error::other(format!("{} failed ({})", argv[0], status)));
+        }
+        Ok(())
+    }
+
+    pub fn copy(&self, data: &[u8]) -> io::Result<()> {
+        self.run_with_input(self.backend.copy, data)
+    }
+
+    fn paste(&self) -> io::Result<Vec<u8>> {
+        let output = self
+            .command(self.backend.paste)?
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        if !output.status.success() {
+            let mut stdout = output.stdout;
+            secret::wipe(&mut stdout);
+            return Err(io::Error::other(format!(
+                "{} failed ({})",
+                self.backend.paste[0], output.status
+            )));
+        }
+        Ok(output.stdout)
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        if self.backend.clear.is_empty() {
+            return self.run_with_input(self.backend.copy, b"");
+        }
+        let status = self
+            .command(self.backend.clear)?
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} failed ({})",
+                self.backend.clear[0], status
+            )));
+        }
+        Ok(())
+    }
+
+    // Очищает буфер, только если в нем все еще наш пароль: то, что
+    // пользователь скопировал позже, не трогаем
+    fn clear_if_unchanged(&self, expected: &str) -> io::Result<bool> {
+        let mut current = self.paste()?;
+        // Get-Clipboard и некоторые версии xsel добавляют перевод строки
+        let len = current
+            .iter()
+            .rposition(|&c| c != b'\n' && c != b'\r')
+            .map_or(0, |i| i + 1);
+        let unchanged = fingerprint(&current[..len]) == expected;
+        secret::wipe(&mut current);
+        if unchanged {
+            self.clear()?;
+        }
+        Ok(unchanged)
+    }
+
+    // Запускает отдельный процесс, который переживет завершение pwgen и
+    // очистит буфер через seconds секунд. Сам пароль ему не передается,
+    // только отпечаток SHA-1 для сравнения.
+    pub fn spawn_clearer(&self, data: &[u8], seconds: u64) -> io::Result<()> {
+        let mut command = Command::new(env::current_exe()?);
+        command
+            .args([WORKER_ARG, self.backend.name, &seconds.to_string()])
+            .env("PATH", &self.search_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Своя группа процессов: Ctrl-C в терминале не прервет очистку
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        let mut child = command.spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        writeln!(stdin, "{}", fingerprint(data))
+    }
+}
+
+fn fingerprint(data: &[u8]) -> String {
+    let mut ctx = Sha1::new();
+    ctx.update(data);
+    ctx.finish().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn find_program(search_path: &OsStr, name: &str) -> Option<PathBuf> {
+    env::split_paths(search_path)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+// Точка входа фонового процесса: `pwgen WORKER_ARG BACKEND SECONDS`,
+// отпечаток пароля приходит одной строкой в stdin. Возвращает код выхода;
+// сообщать об ошибках некому, stderr уже закрыт.
+pub fn run_worker(args: &[String]) -> i32 {
+    let (Some(name), Some(seconds)) = (args.first(), args.get(1)) else {
+        return 2;
+    };
+    let Ok(seconds) = seconds.parse() else {
+        return 2;
+    };
+    let mut fingerprint = String::new();
+    if io::stdin().read_to_string(&mut fingerprint).is_err() {
+        return 1;
+    }
+
+    thread::sleep(Duration::from_secs(seconds));
+    match Clipboard::by_name(name).and_then(|clipboard| clipboard.clear_if_unchanged(fingerprint.trim())) {
+        Ok(_) => 0,
+        Err(_) => 1,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    // Поддельный xclip, хранящий "буфер обмена" в файле state
+    fn fake_xclip(name: &str) -> (PathBuf, PathBuf) {
+        let dir = env::temp_dir().join(format!("pwgen-rs-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let state = dir.join("state");
+        let script = dir.join("xclip");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\ncase \"$*\" in\n  *-o) cat '{0}' ;;\n  *) cat > '{0}' ;;\nesac\n",
+                state.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        (dir, state)
+    }
+
+    fn x11_clipboard(dir: &Path) -> io::Result<Clipboard> {
+        Clipboard::detect_in(dir.as_os_str().to_owned(), |name| name == "DISPLAY")
+    }
+
+    #[test]
+    fn test_detect_without_backend_is_error() {
+        let err = Clipboard::detect_in(OsString::new(), |_| true).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("no clipboard backend"));
+    }
+
+    #[test]
+    fn test_detect_requires_display() {
+        let (dir, _) = fake_xclip("clipboard-detect");
+        assert!(Clipboard::detect_in(dir.as_os_str().to_owned(), |_| false).is_err());
+        assert_eq!(x11_clipboard(&dir).unwrap().name(), "xclip");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_and_clear_if_unchanged() -> io::Result<()> {
+        let (dir, state) = fake_xclip("clipboard-clear");
+        let clipboard = x11_clipboard(&dir)?;
+
+        clipboard.copy(b"Secret42")?;
+        assert_eq!(fs::read(&state)?, b"Secret42");
+
+        // Пользователь скопировал что-то другое - не трогаем
+        clipboard.copy(b"other text\n")?;
+        assert!(!clipboard.clear_if_unchanged(&fingerprint(b"Secret42"))?);
+        assert_eq!(fs::read(&state)?, b"other text\n");
+
+        clipboard.copy(b"Secret42")?;
+        assert!(clipboard.clear_if_unchanged(&fingerprint(b"Secret42"))?);
+        assert_eq!(fs::read(&state)?, b"");
+
+        fs::remove_dir_all(dir)
+    }
+}
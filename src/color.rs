@@ -0,0 +1,120 @@
+use crate::{ColorChoice, LOWERCASE, NUMERALS, UPPERCASE, is_symbol};
+
+// `--color`: в терминале строчные, заглавные, цифры и символы печатаются
+// разными цветами, чтобы O не путалась с 0, а l с 1. Цвет ставится на
+// каждую цепочку символов одного класса и сбрасывается после нее, так что
+// пробелы между столбцами остаются без цвета. Ширина столбцов считается по
+// самому паролю, без escape-последовательностей.
+
+// Сколько байтов может добавить раскраска на один символ: "\x1b[33m" и "\x1b[0m"
+pub const MAX_OVERHEAD: usize = 9;
+
+const RESET: &[u8] = b"\x1b[0m";
+
+// Цвет класса: строчные - зеленые, заглавные - желтые, цифры - синие,
+// символы - красные; остальное, например буквы из --charset-file, без цвета
+fn code(c: char) -> Option<&'static [u8]> {
+    let in_class = |class: &[u8]| u8::try_from(c).is_ok_and(|b| class.contains(&b));
+    if in_class(LOWERCASE) {
+        Some(b"\x1b[32m")
+    } else if in_class(UPPERCASE) {
+        Some(b"\x1b[33m")
+    } else if in_class(NUMERALS) {
+        Some(b"\x1b[34m")
+    } else if is_symbol(c) {
+        Some(b"\x1b[31m")
+    } else {
+        None
+    }
+}
+
+// Раскрашивать ли вывод в stdout. auto - только в терминале; NO_COLOR
+// (https://no-color.org) выключает цвет и при --color always.
+pub fn enabled(choice: ColorChoice, stdout_is_tty: bool, no_color: bool) -> bool {
+    if no_color {
+        return false;
+    }
+    match choice {
+        ColorChoice::Auto => stdout_is_tty,
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+}
+
+// Пароль с цветами классов
+pub fn push_colored(line: &mut Vec<u8>, password: &str) {
+    let mut current = None;
+    for c in password.chars() {
+        let next = code(c);
+        if next != current {
+            if current.is_some() {
+                line.extend_from_slice(RESET);
+            }
+            if let Some(code) = next {
+                line.extend_from_slice(code);
+            }
+            current = next;
+        }
+        let mut buf = [0; 4];
+        line.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    if current.is_some() {
+        line.extend_from_slice(RESET);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Текст без escape-последовательностей "\x1b[...m"
+    fn strip(text: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                for c in chars.by_ref() {
+                    if c == 'm' {
+                        break;
+                    }
+                }
+            } else {
+                plain.push(c);
+            }
+        }
+        plain
+    }
+
+    fn colored(password: &str) -> String {
+        let mut line = Vec::new();
+        push_colored(&mut line, password);
+        String::from_utf8(line).unwrap()
+    }
+
+    #[test]
+    fn test_push_colored() {
+        assert_eq!(colored("ab"), "\x1b[32mab\x1b[0m");
+        assert_eq!(colored("aB1!"), "\x1b[32ma\x1b[0m\x1b[33mB\x1b[0m\x1b[34m1\x1b[0m\x1b[31m!\x1b[0m");
+        // O и 0 - разных цветов
+        assert_ne!(colored("O"), colored("0").replace('0', "O"));
+        // Символы вне классов без цвета
+        assert_eq!(colored("жa"), "ж\x1b[32ma\x1b[0m");
+        assert_eq!(colored(""), "");
+        for password in ["aB1!xyz", "0O1lI|", "ж-ж"] {
+            let out = colored(password);
+            assert_eq!(strip(&out), password);
+            assert!(out.len() <= password.len() + MAX_OVERHEAD * password.chars().count());
+        }
+    }
+
+    #[test]
+    fn test_enabled() {
+        assert!(enabled(ColorChoice::Auto, true, false));
+        assert!(!enabled(ColorChoice::Auto, false, false));
+        assert!(enabled(ColorChoice::Always, false, false));
+        assert!(!enabled(ColorChoice::Never, true, false));
+        // NO_COLOR сильнее всего
+        assert!(!enabled(ColorChoice::Auto, true, true));
+        assert!(!enabled(ColorChoice::Always, true, true));
+    }
+}
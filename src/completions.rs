@@ -0,0 +1,394 @@
+use std::fmt::Write;
+use std::io;
+
+use crate::options::{OPTIONS, OptionSpec, ValueHint};
+use crate::{COMMANDS, CliError, passphrase};
+
+// `pwgen completions SHELL`: скрипт дополнения строится по тем же таблицам
+// опций, что и --help, поэтому новая опция попадает в него без правок здесь
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+const SHELLS: &[(&str, Shell)] = &[
+    ("bash", Shell::Bash),
+    ("zsh", Shell::Zsh),
+    ("fish", Shell::Fish),
+    ("powershell", Shell::Powershell),
+];
+
+// Скрипты подключаются к обоим именам: pwgen и pwgen-rs
+const NAMES: &[&str] = &["pwgen", "pwgen-rs"];
+
+// Аргументы после имени команды; None - запрошена справка
+pub fn parse_args(args: &[String]) -> Result<Option<Shell>, CliError> {
+    match args {
+        [] => Err(CliError::MissingValue("completions")),
+        [arg] if arg == "-h" || arg == "--help" => Ok(None),
+        [arg] if arg.starts_with('-') => Err(CliError::unknown_option(&[], arg)),
+        [arg] => match SHELLS.iter().find(|(name, _)| name == arg) {
+            Some(&(_, shell)) => Ok(Some(shell)),
+            None => Err(CliError::InvalidValue {
+                option: "completions",
+                value: arg.clone(),
+                expected: "bash, zsh, fish or powershell".to_string(),
+            }),
+        },
+        _ => Err(CliError::TooManyArguments),
+    }
+}
+
+pub fn script(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+        Shell::Powershell => powershell(),
+    }
+}
+
+fn shell_names() -> Vec<&'static str> {
+    SHELLS.iter().map(|&(name, _)| name).collect()
+}
+
+fn names(options: &[OptionSpec]) -> Vec<&'static str> {
+    options.iter().flat_map(|option| option.names.iter().copied()).collect()
+}
+
+// Опции, после которых идет значение, сгруппированные по подсказке
+fn with_hint(options: &[OptionSpec], matches: impl Fn(ValueHint) -> bool) -> Vec<&'static str> {
+    options
+        .iter()
+        .filter(|option| option.hint().is_some_and(&matches))
+        .flat_map(|option| option.names.iter().copied())
+        .collect()
+}
+
+fn bash_values(out: &mut String, options: &[OptionSpec], indent: &str) {
+    let _ = writeln!(out, "{}case \"$prev\" in", indent);
+    let files = with_hint(options, |hint| hint == ValueHint::File);
+    if !files.is_empty() {
+        let _ = writeln!(out, "{}    {})", indent, files.join("|"));
+        let _ = writeln!(out, "{}        COMPREPLY=($(compgen -f -- \"$cur\"))", indent);
+        let _ = writeln!(out, "{}        return ;;", indent);
+    }
+    for option in options {
+        if let Some(ValueHint::Choice(choices)) = option.hint() {
+            let _ = writeln!(out, "{}    {})", indent, option.names.join("|"));
+            let _ = writeln!(out, "{}        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", indent, choices.join(" "));
+            let _ = writeln!(out, "{}        return ;;", indent);
+        }
+    }
+    // Свободный текст и числа не дополняются
+    let free = with_hint(options, |hint| matches!(hint, ValueHint::Text | ValueHint::Number));
+    if !free.is_empty() {
+        let _ = writeln!(out, "{}    {})", indent, free.join("|"));
+        let _ = writeln!(out, "{}        return ;;", indent);
+    }
+    let _ = writeln!(out, "{}esac", indent);
+}
+
+fn bash() -> String {
+    let commands: Vec<&str> = COMMANDS.iter().map(|&(name, _)| name).collect();
+    let mut out = String::new();
+    out.push_str("# bash completion for pwgen, generated by 'pwgen completions bash'\n");
+    out.push_str("_pwgen() {\n");
+    out.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    out.push_str("    case \"${COMP_WORDS[1]}\" in\n");
+    out.push_str("        completions)\n");
+    out.push_str("            if [[ $COMP_CWORD -eq 2 ]]; then\n");
+    let _ = writeln!(out, "                COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", shell_names().join(" "));
+    out.push_str("            fi\n");
+    out.push_str("            return ;;\n");
+    out.push_str("        passphrase)\n");
+    bash_values(&mut out, passphrase::OPTIONS, "            ");
+    let _ = writeln!(
+        out,
+        "            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        names(passphrase::OPTIONS).join(" ")
+    );
+    out.push_str("            return ;;\n");
+    out.push_str("    esac\n");
+    bash_values(&mut out, OPTIONS, "    ");
+    let _ = writeln!(out, "    local words=\"{}\"", names(OPTIONS).join(" "));
+    out.push_str("    if [[ $COMP_CWORD -eq 1 ]]; then\n");
+    let _ = writeln!(out, "        words=\"{} $words\"", commands.join(" "));
+    out.push_str("    fi\n");
+    out.push_str("    COMPREPLY=($(compgen -W \"$words\" -- \"$cur\"))\n");
+    out.push_str("}\n");
+    let _ = writeln!(out, "complete -F _pwgen {}", NAMES.join(" "));
+    out
+}
+
+// Текст внутри '...' в zsh: кавычки закрываются, [ ] и : экранируются
+fn zsh_quote(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh_spec(option: &OptionSpec) -> String {
+    let mut spec = String::new();
+    if option.names.len() > 1 {
+        let _ = write!(spec, "'({})'", option.names.join(" "));
+    }
+    // -r+ и --remove-chars=: значение слитно или следующим словом
+    let forms: Vec<String> = option
+        .names
+        .iter()
+        .map(|name| match option.hint() {
+            Some(_) if name.starts_with("--") => format!("{}=", name),
+            Some(_) => format!("{}+", name),
+            None => name.to_string(),
+        })
+        .collect();
+    if forms.len() > 1 {
+        let _ = write!(spec, "{{{}}}", forms.join(","));
+    } else {
+        spec.push_str(&forms[0]);
+    }
+    let help = option.help.first().copied().unwrap_or_default();
+    let _ = write!(spec, "'[{}]", zsh_quote(help));
+    if let (Some(value), Some(hint)) = (option.value, option.hint()) {
+        let message = zsh_quote(value.trim_start_matches('<').trim_end_matches('>'));
+        let action = match hint {
+            ValueHint::File => "_files".to_string(),
+            ValueHint::Choice(choices) => format!("({})", zsh_quote(&choices.join(" "))),
+            ValueHint::Text | ValueHint::Number => " ".to_string(),
+        };
+        let _ = write!(spec, ":{}:{}", message, action);
+    }
+    spec.push('\'');
+    spec
+}
+
+fn zsh_array(out: &mut String, name: &str, options: &[OptionSpec]) {
+    let _ = writeln!(out, "    local -a {}", name);
+    let _ = writeln!(out, "    {}=(", name);
+    for option in options {
+        let _ = writeln!(out, "        {}", zsh_spec(option));
+    }
+    out.push_str("    )\n");
+}
+
+fn zsh() -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "#compdef {}", NAMES.join(" "));
+    out.push_str("# zsh completion for pwgen, generated by 'pwgen completions zsh'\n");
+    out.push('\n');
+    out.push_str("_pwgen() {\n");
+    out.push_str("    local -a commands\n");
+    out.push_str("    commands=(\n");
+    for (name, description) in COMMANDS {
+        let _ = writeln!(out, "        '{}:{}'", name, zsh_quote(description));
+    }
+    out.push_str("    )\n");
+    zsh_array(&mut out, "generate_options", OPTIONS);
+    zsh_array(&mut out, "passphrase_options", passphrase::OPTIONS);
+    out.push_str("    case $words[2] in\n");
+    out.push_str("        completions)\n");
+    let _ = writeln!(out, "            (( CURRENT == 3 )) && _values shell {}", shell_names().join(" "));
+    out.push_str("            ;;\n");
+    out.push_str("        passphrase)\n");
+    out.push_str("            shift words; (( CURRENT-- ))\n");
+    out.push_str("            _arguments -s $passphrase_options\n");
+    out.push_str("            ;;\n");
+    out.push_str("        generate|check)\n");
+    out.push_str("            shift words; (( CURRENT-- ))\n");
+    out.push_str("            _arguments -s $generate_options\n");
+    out.push_str("            ;;\n");
+    out.push_str("        *)\n");
+    out.push_str("            if (( CURRENT == 2 )) && [[ $PREFIX != -* ]]; then\n");
+    out.push_str("                _describe -t commands command commands\n");
+    out.push_str("            else\n");
+    out.push_str("                _arguments -s $generate_options\n");
+    out.push_str("            fi\n");
+    out.push_str("            ;;\n");
+    out.push_str("    esac\n");
+    out.push_str("}\n");
+    out.push('\n');
+    out.push_str("_pwgen \"$@\"\n");
+    out
+}
+
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish_options(out: &mut String, condition: &str, options: &[OptionSpec]) {
+    for option in options {
+        let _ = write!(out, "complete -c pwgen -n {}", fish_quote(condition));
+        for name in option.names {
+            match name.strip_prefix("--") {
+                Some(long) => {
+                    let _ = write!(out, " -l {}", long);
+                }
+                None => {
+                    let _ = write!(out, " -s {}", &name[1..]);
+                }
+            }
+        }
+        match option.hint() {
+            Some(ValueHint::File) => out.push_str(" -r -F"),
+            Some(ValueHint::Choice(choices)) => {
+                let _ = write!(out, " -x -a {}", fish_quote(&choices.join(" ")));
+            }
+            Some(ValueHint::Text | ValueHint::Number) => out.push_str(" -x"),
+            None => {}
+        }
+        let help = option.help.first().copied().unwrap_or_default();
+        let _ = writeln!(out, " -d {}", fish_quote(help));
+    }
+}
+
+fn fish() -> String {
+    let mut out = String::new();
+    out.push_str("# fish completion for pwgen, generated by 'pwgen completions fish'\n");
+    out.push_str("complete -c pwgen -f\n");
+    for (name, description) in COMMANDS {
+        let _ = writeln!(
+            out,
+            "complete -c pwgen -n __fish_use_subcommand -a {} -d {}",
+            name,
+            fish_quote(description)
+        );
+    }
+    let _ = writeln!(
+        out,
+        "complete -c pwgen -n '__fish_seen_subcommand_from completions' -a {}",
+        fish_quote(&shell_names().join(" "))
+    );
+    fish_options(
+        &mut out,
+        "not __fish_seen_subcommand_from passphrase completions",
+        OPTIONS,
+    );
+    fish_options(&mut out, "__fish_seen_subcommand_from passphrase", passphrase::OPTIONS);
+    for name in &NAMES[1..] {
+        let _ = writeln!(out, "complete -c {} -w pwgen", name);
+    }
+    out
+}
+
+fn powershell_list(words: &[&str]) -> String {
+    let quoted: Vec<String> = words.iter().map(|word| format!("'{}'", word.replace('\'', "''"))).collect();
+    format!("@({})", quoted.join(", "))
+}
+
+fn powershell_values(out: &mut String, options: &[OptionSpec]) {
+    for option in options {
+        if let Some(ValueHint::Choice(choices)) = option.hint() {
+            let names: Vec<String> = option.names.iter().map(|name| format!("'{}'", name)).collect();
+            let _ = writeln!(
+                out,
+                "    if ($previous -in {}) {{ $candidates = {} }}",
+                names.join(", "),
+                powershell_list(choices)
+            );
+        }
+    }
+}
+
+fn powershell() -> String {
+    let commands: Vec<&str> = COMMANDS.iter().map(|&(name, _)| name).collect();
+    let targets: Vec<String> = NAMES.iter().map(|name| format!("'{}'", name)).collect();
+    let mut out = String::new();
+    out.push_str("# PowerShell completion for pwgen, generated by 'pwgen completions powershell'\n");
+    let _ = writeln!(out, "Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{", targets.join(", "));
+    out.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+    out.push_str("    $words = @($commandAst.CommandElements | ForEach-Object { $_.ToString() })\n");
+    out.push_str("    if ($wordToComplete) { $words = $words[0..($words.Count - 2)] }\n");
+    out.push_str("    $previous = $words[-1]\n");
+    out.push_str("    $command = if ($words.Count -gt 1) { $words[1] } else { '' }\n");
+    out.push_str("    switch ($command) {\n");
+    let _ = writeln!(out, "        'completions' {{ $candidates = {} }}", powershell_list(&shell_names()));
+    let _ = writeln!(
+        out,
+        "        'passphrase' {{ $candidates = {} }}",
+        powershell_list(&names(passphrase::OPTIONS))
+    );
+    out.push_str("        default {\n");
+    let _ = writeln!(out, "            $candidates = {}", powershell_list(&names(OPTIONS)));
+    let _ = writeln!(
+        out,
+        "            if ($words.Count -eq 1) {{ $candidates = {} + $candidates }}",
+        powershell_list(&commands)
+    );
+    out.push_str("        }\n");
+    out.push_str("    }\n");
+    powershell_values(&mut out, OPTIONS);
+    out.push_str("    $candidates | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n");
+    out.push_str("        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n");
+    out.push_str("    }\n");
+    out.push_str("}\n");
+    out
+}
+
+pub fn print_help() -> io::Result<()> {
+    use std::io::Write;
+    let mut out = io::stdout().lock();
+    writeln!(out, "Usage: pwgen completions {{bash|zsh|fish|powershell}}")?;
+    writeln!(out)?;
+    writeln!(out, "Print a completion script for the given shell to stdout. For example:")?;
+    writeln!(out)?;
+    writeln!(out, "  pwgen completions bash > ~/.local/share/bash-completion/completions/pwgen")?;
+    writeln!(out, "  pwgen completions zsh > ~/.zfunc/_pwgen")?;
+    writeln!(out, "  pwgen completions fish > ~/.config/fish/completions/pwgen.fish")?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&args(&["zsh"])), Ok(Some(Shell::Zsh)));
+        assert_eq!(parse_args(&args(&["--help"])), Ok(None));
+        assert_eq!(parse_args(&[]), Err(CliError::MissingValue("completions")));
+        assert!(matches!(
+            parse_args(&args(&["tcsh"])),
+            Err(CliError::InvalidValue { option: "completions", .. })
+        ));
+        assert_eq!(parse_args(&args(&["bash", "zsh"])), Err(CliError::TooManyArguments));
+    }
+
+    #[test]
+    fn test_every_option_is_completed() {
+        for shell in SHELLS.iter().map(|&(_, shell)| shell) {
+            let script = script(shell);
+            for option in OPTIONS.iter().chain(passphrase::OPTIONS) {
+                for name in option.names {
+                    let name = if shell == Shell::Fish { name.trim_start_matches('-') } else { name };
+                    assert!(script.contains(name), "{:?}: {}", shell, name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_zsh_spec() {
+        let find = |name: &str| OPTIONS.iter().find(|option| option.names.contains(&name)).unwrap();
+        assert_eq!(
+            zsh_spec(find("-c")),
+            "'(-c --capitalize)'{-c,--capitalize}'[Include at least one capital letter in the password]'"
+        );
+        assert_eq!(
+            zsh_spec(find("-o")),
+            "'(-o --output)'{-o+,--output=}'[Write the passwords to <file> (created with mode 0600) instead of stdout,]:file:_files'"
+        );
+        assert_eq!(
+            zsh_spec(find("--rng")),
+            "--rng='[Select the random source; auto tries getrandom, then /dev/urandom]:auto|getrandom|urandom|file\\:PATH:(auto getrandom urandom file\\:)'"
+        );
+    }
+}
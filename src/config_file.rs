@@ -0,0 +1,330 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Config, MAX_COUNT, MAX_LENGTH};
+
+// Личные настройки по умолчанию: $XDG_CONFIG_HOME/pwgen/config.toml
+// (или ~/.config/pwgen/config.toml), на Windows %APPDATA%\pwgen\config.toml.
+// Флаги командной строки применяются поверх них, --no-config их отключает.
+//
+// Формат - подмножество TOML без таблиц и массивов:
+//
+//     length = 20
+//     secure = true
+//     symbols = true
+//     remove_chars = "'\""
+pub fn default_path() -> Option<PathBuf> {
+    path_in(
+        env::var_os("XDG_CONFIG_HOME"),
+        env::var_os("HOME"),
+        env::var_os("APPDATA"),
+    )
+}
+
+fn path_in(xdg_config_home: Option<OsString>, home: Option<OsString>, appdata: Option<OsString>) -> Option<PathBuf> {
+    // Пустое значение переменной считается неустановленным, как в спецификации XDG
+    let non_empty = |value: Option<OsString>| value.filter(|v| !v.is_empty()).map(PathBuf::from);
+    let dir = if cfg!(windows) {
+        non_empty(appdata)?
+    } else {
+        non_empty(xdg_config_home).or_else(|| non_empty(home).map(|home| home.join(".config")))?
+    };
+    Some(dir.join("pwgen").join("config.toml"))
+}
+
+// Настройки из файла поверх значений по умолчанию. Если файла нет,
+// возвращаются значения по умолчанию.
+pub fn load(path: &Path) -> io::Result<Config> {
+    let mut config = Config::default();
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(config),
+        Err(e) => return Err(io::Error::new(e.kind(), format!("cannot read {}: {}", path.display(), e))),
+    };
+    apply(&text, &mut config).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}", path.display(), e)))?;
+    Ok(config)
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Bool(bool),
+    Integer(u64),
+    String(String),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Bool(_) => "a boolean",
+            Self::Integer(_) => "a number",
+            Self::String(_) => "a string",
+        }
+    }
+}
+
+// Применяет строки файла к config. Ошибка начинается с номера строки и
+// имени ключа: "3: length: expected a number from 1 to 1024"
+fn apply(text: &str, config: &mut Config) -> Result<(), String> {
+    let mut keys: Vec<&str> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(format!("{}: tables are not supported", number));
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("{}: expected key = value", number));
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("{}: missing key", number));
+        }
+        if keys.contains(&key) {
+            return Err(format!("{}: {}: duplicate key", number, key));
+        }
+        keys.push(key);
+
+        let value = parse_value(value.trim()).map_err(|e| format!("{}: {}: {}", number, key, e))?;
+        set(config, key, value).map_err(|e| format!("{}: {}: {}", number, key, e))?;
+    }
+    Ok(())
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(rest) = text.strip_prefix('"') {
+        return parse_basic_string(rest);
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        // Литеральная строка: без экранирования, до следующей кавычки
+        let Some((value, tail)) = rest.split_once('\'') else {
+            return Err("unterminated string".to_string());
+        };
+        check_tail(tail)?;
+        return Ok(Value::String(value.to_string()));
+    }
+
+    // Вне строк # начинает комментарий
+    let text = match text.split_once('#') {
+        Some((value, _)) => value.trim_end(),
+        None => text,
+    };
+    match text {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+    // TOML разрешает подчеркивания между цифрами: 10_000
+    let digits = text.replace('_', "");
+    if !digits.is_empty() && digits.bytes().all(|c| c.is_ascii_digit()) {
+        return digits
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("number {} is too large", text));
+    }
+    Err(format!("invalid value {}", text))
+}
+
+fn parse_basic_string(text: &str) -> Result<Value, String> {
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '"' => {
+                check_tail(&text[pos + 1..])?;
+                return Ok(Value::String(value));
+            }
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some(other) => return Err(format!("unsupported escape \\{}", other)),
+                    None => return Err("unterminated string".to_string()),
+                };
+                value.push(escaped);
+            }
+            c => value.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+// После значения допускается только комментарий
+fn check_tail(tail: &str) -> Result<(), String> {
+    let tail = tail.trim_start();
+    if tail.is_empty() || tail.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected {} after the value", tail))
+    }
+}
+
+fn set(config: &mut Config, key: &str, value: Value) -> Result<(), String> {
+    match (key, value) {
+        ("length", Value::Integer(n)) => config.pw_length = count(n, MAX_LENGTH)?,
+        ("count", Value::Integer(n)) => config.num_pw = count(n, MAX_COUNT)?,
+        ("capitalize", Value::Bool(on)) => {
+            config.capitalize = on;
+            config.no_capitalize = !on;
+        }
+        ("numerals", Value::Bool(on)) => {
+            config.numerals = on;
+            config.no_numerals = !on;
+        }
+        ("symbols", Value::Bool(on)) => config.symbols = on,
+        ("secure", Value::Bool(on)) => config.secure = on,
+        ("ambiguous", Value::Bool(on)) => config.ambiguous = on,
+        ("no_vowels", Value::Bool(on)) => config.no_vowels = on,
+        ("lock_memory", Value::Bool(on)) => config.lock_memory = on,
+        ("columns", Value::Bool(on)) => config.columns = on,
+        ("columns", Value::Integer(n)) => {
+            config.columns = true;
+            config.fixed_columns = Some(count(n, MAX_COUNT)?);
+        }
+        ("clear_after", Value::Integer(seconds)) => config.clear_after = seconds,
+        ("remove_chars", Value::String(chars)) => {
+            if let Some(c) = chars.chars().find(|c| c.is_control()) {
+                return Err(format!("control character {:?} is not allowed", c));
+            }
+            let mut remove_chars: Vec<char> = Vec::new();
+            for c in chars.chars() {
+                if !remove_chars.contains(&c) {
+                    remove_chars.push(c);
+                }
+            }
+            config.remove_chars = Some(remove_chars);
+        }
+        // Как --ambiguous-chars: "+chars" дополняет набор, включает -B
+        ("ambiguous_chars", Value::String(chars)) => {
+            match chars.strip_prefix('+') {
+                Some(extra) => config.ambiguous_chars.extend(extra.chars()),
+                None => config.ambiguous_chars = chars.chars().collect(),
+            }
+            config.ambiguous = true;
+        }
+        (key, value) => {
+            let expected = match key {
+                "length" | "count" | "clear_after" => "a number",
+                "columns" => "a boolean or a number",
+                "remove_chars" | "ambiguous_chars" => "a string",
+                "capitalize" | "numerals" | "symbols" | "secure" | "ambiguous" | "no_vowels" | "lock_memory" => {
+                    "a boolean"
+                }
+                _ => return Err("unknown key".to_string()),
+            };
+            return Err(format!("expected {}, found {}", expected, value.kind()));
+        }
+    }
+    Ok(())
+}
+
+fn count(n: u64, max: usize) -> Result<usize, String> {
+    match usize::try_from(n) {
+        Ok(n) if (1..=max).contains(&n) => Ok(n),
+        _ => Err(format!("expected a number from 1 to {}", max)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        apply(text, &mut config)?;
+        Ok(config)
+    }
+
+    #[test]
+    fn test_path() {
+        if cfg!(windows) {
+            assert_eq!(
+                path_in(None, None, Some("C:\\Users\\me\\AppData\\Roaming".into())),
+                Some(PathBuf::from("C:\\Users\\me\\AppData\\Roaming\\pwgen\\config.toml"))
+            );
+            return;
+        }
+        assert_eq!(
+            path_in(Some("/xdg".into()), Some("/home/me".into()), None),
+            Some(PathBuf::from("/xdg/pwgen/config.toml"))
+        );
+        assert_eq!(
+            path_in(Some("".into()), Some("/home/me".into()), None),
+            Some(PathBuf::from("/home/me/.config/pwgen/config.toml"))
+        );
+        assert_eq!(path_in(None, None, None), None);
+    }
+
+    #[test]
+    fn test_apply_settings() -> Result<(), String> {
+        let config = parse(
+            "# личные настройки\n\
+             length = 20\n\
+             count = 1_000\n\
+             secure = true   # всегда -s\n\
+             symbols = true\n\
+             capitalize = false\n\
+             remove_chars = \"'\\\"\\\\\"\n\
+             ambiguous_chars = '+@'\n\
+             columns = 3\n",
+        )?;
+        assert_eq!(config.pw_length, 20);
+        assert_eq!(config.num_pw, 1000);
+        assert!(config.secure && config.symbols && config.no_capitalize);
+        assert_eq!(config.remove_chars, Some(vec!['\'', '"', '\\']));
+        assert!(config.ambiguous);
+        assert_eq!(config.ambiguous_chars.last(), Some(&'@'));
+        assert_eq!(config.fixed_columns, Some(3));
+        // Количество из файла - значение по умолчанию, а не явный аргумент
+        assert!(!config.num_pw_given);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_name_line_and_key() {
+        let cases = [
+            ("length = 0", "1: length: expected a number from 1 to 1024"),
+            ("secure = true\nsymbols = yes", "2: symbols: invalid value yes"),
+            ("\n\nlength = \"20\"", "3: length: expected a number, found a string"),
+            ("columns = 'wide'", "1: columns: expected a boolean or a number, found a string"),
+            ("colour = true", "1: colour: unknown key"),
+            ("length = 8\nlength = 9", "2: length: duplicate key"),
+            ("remove_chars = \"abc", "1: remove_chars: unterminated string"),
+            ("remove_chars = \"a\\qb\"", "1: remove_chars: unsupported escape \\q"),
+            ("remove_chars = \"a\\tb\"", "1: remove_chars: control character '\\t' is not allowed"),
+            ("secure = true false", "1: secure: invalid value true false"),
+            ("[pwgen]", "1: tables are not supported"),
+            ("secure", "1: expected key = value"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse(text).err().as_deref(), Some(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_load_missing_file_gives_defaults() -> io::Result<()> {
+        let config = load(Path::new("/nonexistent/pwgen/config.toml"))?;
+        assert_eq!(config.pw_length, crate::DEFAULT_LENGTH);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_error_names_file() -> io::Result<()> {
+        let dir = env::temp_dir().join(format!("pwgen-rs-{}-config", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("config.toml");
+        fs::write(&path, "length = 16\ncount = lots\n")?;
+
+        let err = load(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), format!("{}:2: count: invalid value lots", path.display()));
+        fs::remove_dir_all(dir)
+    }
+}
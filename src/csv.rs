@@ -0,0 +1,117 @@
+use std::io::{self, Write};
+
+use crate::secret::Passwords;
+use crate::{Config, dry_run, write_line};
+
+// Заголовок таблицы --format csv
+const HEADER: &str = "index,password,length,entropy_bits,mode";
+
+// Первые символы, с которых табличный редактор начинает формулу
+const FORMULA_STARTS: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+// `--format csv`: заголовок и по строке на пароль с номером от 1, длиной,
+// энтропией и режимом. Поля с запятой, кавычкой или переводом строки
+// берутся в кавычки, а кавычки внутри удваиваются, как в RFC 4180. Строки
+// кончаются на LF, а не на CRLF из RFC: так вывод удобнее в конвейере, а
+// табличные редакторы читают оба варианта.
+//
+// С --csv-safe к паролю, который начинается с "=", "+", "-" или "@",
+// спереди добавляется апостроф, чтобы редактор не принял его за формулу.
+// Длина и энтропия считаются без апострофа, а при вставке пароля его
+// нужно убрать.
+pub fn write<W: Write>(out: &mut W, passwords: &Passwords, config: &Config) -> io::Result<()> {
+    let mode = dry_run::mode_name(config);
+    let mut entropies = dry_run::Entropies::new(config);
+    let longest = passwords.iter().map(str::len).max().unwrap_or(0);
+    let mut line = Vec::with_capacity(HEADER.len().max(2 * longest + 64));
+    line.extend_from_slice(HEADER.as_bytes());
+    write_line(out, &mut line)?;
+    for (i, password) in passwords.iter().enumerate() {
+        write!(line, "{},", i + 1)?;
+        push_field(&mut line, password, config.csv_safe);
+        write!(line, ",{},", password.chars().count())?;
+        if let Some(bits) = entropies.of(password).filter(|bits| bits.is_finite()) {
+            write!(line, "{:.2}", bits)?;
+        }
+        line.push(b',');
+        push_field(&mut line, mode, false);
+        write_line(out, &mut line)?;
+    }
+    out.flush()
+}
+
+// Поле CSV: как есть, если в нем нет запятой, кавычки и перевода строки,
+// иначе в кавычках с удвоенными кавычками внутри
+fn push_field(line: &mut Vec<u8>, field: &str, safe: bool) {
+    let guard = safe && field.starts_with(FORMULA_STARTS);
+    let quote = field.contains([',', '"', '\n', '\r']);
+    if quote {
+        line.push(b'"');
+    }
+    if guard {
+        line.push(b'\'');
+    }
+    for c in field.chars() {
+        if c == '"' {
+            line.push(b'"');
+        }
+        let mut buf = [0; 4];
+        line.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    if quote {
+        line.push(b'"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(s: &str, safe: bool) -> String {
+        let mut line = Vec::new();
+        push_field(&mut line, s, safe);
+        String::from_utf8(line).unwrap()
+    }
+
+    #[test]
+    fn test_push_field() {
+        assert_eq!(field("abc", false), "abc");
+        assert_eq!(field("a,b", false), "\"a,b\"");
+        assert_eq!(field("a\"b", false), "\"a\"\"b\"");
+        assert_eq!(field("=1+2", false), "=1+2");
+        // Апостроф только с --csv-safe и только перед началом формулы
+        assert_eq!(field("=1+2", true), "'=1+2");
+        assert_eq!(field("-x", true), "'-x");
+        assert_eq!(field("a=b", true), "a=b");
+        assert_eq!(field("=\"a\",b", true), "\"'=\"\"a\"\",b\"");
+    }
+
+    #[test]
+    fn test_write_table() {
+        let config = Config {
+            secure: true,
+            symbols: true,
+            pw_length: 4,
+            num_pw: 3,
+            ..Config::default()
+        };
+        let passwords = Passwords::from(vec!["a,b1".to_string(), "x\"y2".to_string(), "=A1!".to_string()]);
+        let write_with = |config: &Config| {
+            let mut out = Vec::new();
+            write(&mut out, &passwords, config).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            write_with(&config),
+            "index,password,length,entropy_bits,mode\n\
+             1,\"a,b1\",4,26.22,secure\n\
+             2,\"x\"\"y2\",4,26.22,secure\n\
+             3,=A1!,4,26.22,secure\n"
+        );
+        let safe = Config {
+            csv_safe: true,
+            ..config
+        };
+        assert!(write_with(&safe).ends_with("\n3,'=A1!,4,26.22,secure\n"));
+    }
+}
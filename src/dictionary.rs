@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+// `--no-dictionary-words[=N]`: пароль, в котором без учета регистра
+// встречается английское слово из N и больше букв ("pass", "LoVe"),
+// генерируется заново. Слова встраиваются с cargo feature "dictionary";
+// проверка идет по готовому паролю, уже после замен apply_requirements и
+// --case, так что слово не может появиться после нее.
+
+pub const DEFAULT_MIN_LENGTH: usize = 4;
+// Слова короче не встраиваются
+pub const SHORTEST: usize = 3;
+
+#[cfg(feature = "dictionary")]
+pub const TEXT: Option<&str> = Some(include_str!("dictionary.txt"));
+#[cfg(not(feature = "dictionary"))]
+pub const TEXT: Option<&str> = None;
+
+// Слова, разложенные по длине: buckets[n] - слова из n букв
+struct Dictionary {
+    buckets: Vec<HashSet<&'static [u8]>>,
+}
+
+fn dictionary() -> &'static Dictionary {
+    static DICTIONARY: OnceLock<Dictionary> = OnceLock::new();
+    DICTIONARY.get_or_init(|| {
+        let mut buckets: Vec<HashSet<&'static [u8]>> = Vec::new();
+        for word in TEXT.unwrap_or_default().lines().map(str::as_bytes) {
+            if buckets.len() <= word.len() {
+                buckets.resize_with(word.len() + 1, HashSet::new);
+            }
+            buckets[word.len()].insert(word);
+        }
+        Dictionary { buckets }
+    })
+}
+
+pub fn missing() -> String {
+    "the dictionary for --no-dictionary-words is not built into this binary (cargo feature \"dictionary\")"
+        .to_string()
+}
+
+// Есть ли в пароле слово из min_length и больше букв. Слово ищется только
+// среди соседних букв ASCII, так что "pa5s" и "pаss" с кириллической "а"
+// словами не считаются.
+pub fn has_word(pw: &str, min_length: usize) -> bool {
+    let buckets = &dictionary().buckets;
+    let mut letters: Vec<u8> =
+        pw.chars().map(|c| if c.is_ascii_alphabetic() { c.to_ascii_lowercase() as u8 } else { 0 }).collect();
+    let found = (0..letters.len()).any(|start| {
+        (min_length.max(SHORTEST)..buckets.len())
+            .take_while(|&n| start + n <= letters.len())
+            .any(|n| buckets[n].contains(&letters[start..start + n]))
+    });
+    letters.fill(0);
+    found
+}
+
+#[cfg(all(test, feature = "dictionary"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_list() {
+        let words: Vec<&str> = TEXT.unwrap().lines().collect();
+        assert!(words.len() > 1000);
+        assert!(words.windows(2).all(|pair| pair[0] < pair[1]), "the list is sorted and unique");
+        assert!(words.iter().all(|word| word.len() >= SHORTEST && word.bytes().all(|b| b.is_ascii_lowercase())));
+    }
+
+    #[test]
+    fn test_has_word() {
+        for pw in ["pass", "xPASSx", "7LoVe!", "ab4word", "kqSecretz", "dragon99"] {
+            assert!(has_word(pw, DEFAULT_MIN_LENGTH), "{}", pw);
+        }
+        for pw in ["", "pas", "xqzv", "pa5s", "l0ve", "p-a-s-s", "p\u{430}ss", "Ohngip8o"] {
+            assert!(!has_word(pw, DEFAULT_MIN_LENGTH), "{}", pw);
+        }
+        // Короткие слова ищутся только с меньшим N
+        assert!(!has_word("xcatx", 4) && has_word("xcatx", 3));
+        assert!(has_word("xpasswordx", 8) && !has_word("xpasswordx", 9));
+        // N меньше самых коротких слов - то же, что они
+        assert_eq!(has_word("xcatx", 1), has_word("xcatx", SHORTEST));
+    }
+
+    #[test]
+    fn test_large_batch() -> std::io::Result<()> {
+        // Сто тысяч паролей проверяются быстро даже в отладочной сборке
+        let mut rng = crate::chacha20::ChaCha20Rng::seeded(583);
+        let letters: Vec<char> = crate::class_chars(crate::LOWERCASE).collect();
+        let mut passwords = Vec::with_capacity(100_000);
+        for _ in 0..100_000 {
+            let mut password = String::with_capacity(12);
+            for _ in 0..12 {
+                password.push(letters[crate::rng::random_index(&mut rng, letters.len())?]);
+            }
+            passwords.push(password);
+        }
+        let started = std::time::Instant::now();
+        let hits = passwords.iter().filter(|password| has_word(password, DEFAULT_MIN_LENGTH)).count();
+        assert!(started.elapsed() < std::time::Duration::from_secs(10), "{:?}", started.elapsed());
+        // Случайные буквы иногда складываются в слова, но редко
+        assert!(hits > 0 && hits < passwords.len() / 10, "{}", hits);
+        Ok(())
+    }
+}
@@ -0,0 +1,928 @@
+use std::io::Write;
+
+use crate::{
+    CONSONANTS, CONSONANTS_LOWER, COUNTED_CLASSES, Config, LOWERCASE, MemorableModel, NUMERALS, RunError, SYMBOLS,
+    UPPERCASE, affix_length, blocks, build_charset, like, can_end, can_lead, class_pools, check_settings, is_allowed, markov, mobile,
+    none_left_error, phoneme, pin, required_classes, required_names, shortest_length, syllables, template,
+    weighted_pools, wordlist,
+};
+
+// Классы в том порядке, в каком они печатаются
+const CLASSES: &[(&str, &[u8])] = &[
+    ("Lowercase", LOWERCASE),
+    ("Uppercase", UPPERCASE),
+    ("Digits", NUMERALS),
+    ("Symbols", SYMBOLS),
+];
+
+// `pwgen --dry-run`: итоговые настройки, набор символов по классам и
+// энтропия одного пароля. Источник случайности не открывается. Если из
+// набора ничего не осталось, отчет все равно печатается, а затем
+// возвращается та же ошибка, что и при генерации.
+pub fn run<W: Write>(config: &Config, out: &mut W) -> Result<(), RunError> {
+    if let Some(count) = config.words {
+        return run_words(config, count, out);
+    }
+    if let Some(template) = &config.template {
+        return run_template(config, template, out);
+    }
+    if let Some(spec) = &config.blocks {
+        return run_blocks(config, spec, out);
+    }
+    if let Some(shape) = &config.like {
+        return run_like(config, shape, out);
+    }
+    if config.pin {
+        return run_pin(config, out);
+    }
+    let charset = build_charset(config);
+    let required = required_classes(config, &charset);
+    // С --length-range энтропия считается для самых коротких, то есть слабых, паролей
+    let length = shortest_length(config);
+    let (mode, used, entropy, missing) = describe(config, length);
+
+    writeln!(out, "Mode:       {}", mode)?;
+    match (config.syllable_count, config.length_range) {
+        (Some(count), _) => {
+            let (shortest, longest) = syllables::lengths(&syllables::inventory(config));
+            writeln!(out, "Length:     {}-{} ({} syllables)", count * shortest, count * longest, count)?
+        }
+        (None, Some((min, max))) => writeln!(out, "Length:     {}-{} (entropy below is for {})", min, max, min)?,
+        (None, None) => writeln!(out, "Length:     {}", length)?,
+    }
+    writeln!(out, "Count:      {}", config.num_pw)?;
+    let mut size = 0;
+    for (name, class) in CLASSES {
+        let chars: String = class.iter().map(|&c| char::from(c)).filter(|c| used.contains(c)).collect();
+        size += chars.len();
+        if chars.is_empty() {
+            writeln!(out, "{:<11} none", format!("{}:", name))?;
+        } else {
+            writeln!(out, "{:<11} {} ({})", format!("{}:", name), chars, chars.len())?;
+        }
+    }
+    // Символы вне классов приходят только из --include-only и --charset-file
+    let mut other: Vec<char> = used
+        .iter()
+        .cloned()
+        .filter(|&c| !CLASSES.iter().any(|(_, class)| u8::try_from(c).is_ok_and(|b| class.contains(&b))))
+        .collect();
+    other.sort_unstable();
+    other.dedup();
+    if !other.is_empty() {
+        size += other.len();
+        writeln!(out, "{:<11} {} ({})", "Other:", other.iter().collect::<String>(), other.len())?;
+    }
+    writeln!(out, "Charset:    {} characters", size)?;
+    let names = required_names(&required, config.require_chars.as_deref().unwrap_or_default());
+    writeln!(out, "Required:   {}", if names.is_empty() { "none".to_string() } else { names.join(", ") })?;
+    // Строка только для --min-* и --max-*, чтобы не загромождать отчет
+    let counts: Vec<String> = COUNTED_CLASSES
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (_, _, name, plural))| {
+            let noun = |n: usize| if n == 1 { name } else { plural };
+            match (config.class_counts.min[i], config.class_counts.max[i]) {
+                (0, None) => None,
+                (0, Some(0)) => Some(format!("no {}", plural)),
+                (min, Some(max)) if min == max => Some(format!("exactly {} {}", min, noun(min))),
+                (min, None) => Some(format!("at least {} {}", min, noun(min))),
+                (0, Some(max)) => Some(format!("at most {} {}", max, noun(max))),
+                (min, Some(max)) => Some(format!("{}-{} {}", min, max, plural)),
+            }
+        })
+        .collect();
+    if !counts.is_empty() {
+        writeln!(out, "Counts:     {}", counts.join(", "))?;
+    }
+    writeln!(out, "Entropy:    {}", entropy.as_ref().map_or("none", |(_, line)| line.as_str()))?;
+    out.flush()?;
+
+    if let Some(name) = missing {
+        return Err(none_left_error(name));
+    }
+    check_settings(config)
+}
+
+// Энтропия пароля длины length в режиме из настроек; по ней же --bits
+// подбирает длину. У фразы length - число слов, и энтропия та же, что у
+// фразы из стольких слов в --dry-run. У --template длину задает он сам.
+pub fn entropy(config: &Config, length: usize) -> Option<f64> {
+    if config.pin {
+        return Some(pin::entropy(length));
+    }
+    if config.words.is_some() {
+        let style = wordlist::Style::new(config).ok()?;
+        let slots = wordlist::Slots::new(length, wordlist::words(config).ok()?, None).ok()?;
+        return Some(wordlist::entropy(&slots, &style));
+    }
+    describe(config, length).2.map(|(bits, _)| bits)
+}
+
+// Короткое имя режима для --format json; варианты -s вроде --url-safe и
+// --preset в нем не различаются, их видно по настройкам
+pub fn mode_name(config: &Config) -> &'static str {
+    if config.words.is_some() {
+        "passphrase"
+    } else if config.template.is_some() {
+        "template"
+    } else if config.blocks.is_some() {
+        "blocks"
+    } else if config.like.is_some() {
+        "like"
+    } else if config.pin {
+        "pin"
+    } else if config.mobile_friendly {
+        "mobile-friendly"
+    } else if config.weights.is_some() {
+        "weighted"
+    } else if config.classes.is_some() {
+        "classes"
+    } else if config.balanced {
+        "balanced"
+    } else if config.secure {
+        "secure"
+    } else if config.syllable_count.is_some() {
+        "syllables"
+    } else if config.no_vowels {
+        "no-vowels"
+    } else if config.syllables.is_none() && config.memorable_model == MemorableModel::Markov {
+        "markov"
+    } else {
+        "pronounceable"
+    }
+}
+
+// Сколько разных символов может попасть в пароль, для --format json. У
+// --words и --blocks пароль собирается из слов, а не из символов.
+pub fn charset_size(config: &Config) -> Option<usize> {
+    let mut used: Vec<char> = if config.words.is_some() || config.blocks.is_some() {
+        return None;
+    } else if let Some(template) = &config.template {
+        template.pools(config).ok()?.concat()
+    } else if let Some(shape) = &config.like {
+        shape.pools(config).ok()?.concat()
+    } else if config.pin {
+        return Some(NUMERALS.len());
+    } else {
+        describe(config, shortest_length(config)).1
+    };
+    used.sort_unstable();
+    used.dedup();
+    Some(used.len())
+}
+
+// Энтропия пароля длины length в любом режиме, для --format json. В
+// отличие от entropy, здесь есть и режимы, которые задают длину сами.
+pub fn password_entropy(config: &Config, length: usize) -> Option<f64> {
+    if let Some(count) = config.words {
+        let style = wordlist::Style::new(config).ok()?;
+        let slots = wordlist::Slots::new(count, wordlist::words(config).ok()?, config.acrostic.as_deref()).ok()?;
+        return Some(wordlist::entropy(&slots, &style));
+    }
+    if let Some(template) = &config.template {
+        return template.pools(config).ok().map(|pools| template::entropy(&pools));
+    }
+    if let Some(spec) = &config.blocks {
+        let (words, digits) = spec.entropy(&spec.parts(config).ok()?);
+        return Some(words + digits);
+    }
+    if let Some(shape) = &config.like {
+        return shape.pools(config).ok().map(|pools| template::entropy(&pools));
+    }
+    entropy(config, length)
+}
+
+// Энтропия каждого пароля для --format json и csv. Она зависит только от
+// длины случайной части, а с --length-range длин немного, так что
+// считается по разу на длину.
+pub struct Entropies<'a> {
+    config: &'a Config,
+    known: Vec<(usize, Option<f64>)>,
+}
+
+impl<'a> Entropies<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self { config, known: Vec::new() }
+    }
+
+    // Энтропия готового пароля, уже с --prefix, --suffix и разделителями --group
+    pub fn of(&mut self, password: &str) -> Option<f64> {
+        let shown = password.chars().count().saturating_sub(affix_length(self.config));
+        let random = match &self.config.group {
+            Some(grouping) => grouping.chars_for(shown).unwrap_or(shown),
+            None => shown,
+        };
+        if let Some(&(_, bits)) = self.known.iter().find(|(length, _)| *length == random) {
+            return bits;
+        }
+        let bits = password_entropy(self.config, random);
+        self.known.push((random, bits));
+        bits
+    }
+}
+
+// Режим генератора для отчета: его название, символы, которые могут
+// попасть в пароль, энтропия пароля длины length со строкой отчета о ней
+// и класс, которого не осталось
+type Description = (&'static str, Vec<char>, Option<(f64, String)>, Option<&'static str>);
+
+fn describe(config: &Config, length: usize) -> Description {
+    let charset = build_charset(config);
+    let required = required_classes(config, &charset);
+
+    // В запоминаемом режиме буквы берутся из согласных и гласных, а
+    // цифры и символы появляются только как обязательные классы
+    if config.mobile_friendly {
+        // Блоки экономят переключения раскладки ценой части энтропии:
+        // рядом печатается энтропия случайного пароля из тех же символов
+        let blocks = mobile::blocks(config, length);
+        let used: Vec<char> = blocks.concat();
+        let entropy = (!blocks.is_empty()).then(|| {
+            let bits = mobile::entropy(length, &blocks);
+            let line = format!(
+                "{:.1} bits per password in {} blocks ({:.1} bits if not grouped)",
+                bits,
+                blocks.len(),
+                length as f64 * (used.len() as f64).log2()
+            );
+            (bits, line)
+        });
+        ("mobile-friendly (--mobile-friendly)", used, entropy, None)
+    } else if config.weights.is_some() {
+        // Класс k выбирается с вероятностью p_k, символ в нем - из n_k:
+        // на символ приходится sum p_k x (log2 1/p_k + log2 n_k) бит
+        let pools = weighted_pools(config, &charset);
+        let total: usize = pools.iter().map(|(weight, _)| weight).sum();
+        let per_char: f64 = pools
+            .iter()
+            .map(|(weight, pool)| {
+                let p = *weight as f64 / total as f64;
+                p * (-p.log2() + (pool.len() as f64).log2())
+            })
+            .sum();
+        let entropy = (!pools.is_empty()).then(|| {
+            let bits = length as f64 * per_char;
+            (bits, format!("{:.1} bits per password ({} x {:.2} bits per weighted character)", bits, length, per_char))
+        });
+        ("secure (-s), weighted", charset.clone(), entropy, None)
+    } else if let Some(counts) = config.classes {
+        // Порядок классов - одна из n! / (c_1! ... c_k!) расстановок, плюс
+        // c_k x log2 n_k бит на символы каждого класса
+        let pools = class_pools(&charset);
+        let order = log2_factorial(counts.iter().sum()) - counts.iter().map(|&c| log2_factorial(c)).sum::<f64>();
+        let chars: f64 = counts.iter().zip(&pools).map(|(&count, pool)| count as f64 * (pool.len() as f64).log2()).sum();
+        let entropy = counts.iter().zip(&pools).all(|(&count, pool)| count == 0 || !pool.is_empty()).then(|| {
+            let bits = order + chars;
+            (bits, format!("{:.1} bits per password ({:.1} bits for the order of classes)", bits, order))
+        });
+        ("secure (-s), exact class counts (--classes)", charset.clone(), entropy, None)
+    } else if config.balanced {
+        // Какие r = L mod k классов получат лишний символ, порядок классов и
+        // сами символы; лишний символ достается классу с вероятностью r/k
+        let pools: Vec<Vec<char>> = class_pools(&charset).into_iter().filter(|pool| !pool.is_empty()).collect();
+        let entropy = (!pools.is_empty()).then(|| {
+            let (k, q, r) = (pools.len(), length / pools.len(), length % pools.len());
+            let extra = log2_factorial(k) - log2_factorial(r) - log2_factorial(k - r);
+            let order =
+                log2_factorial(length) - (k - r) as f64 * log2_factorial(q) - r as f64 * log2_factorial(q + 1);
+            let per_class: f64 = pools.iter().map(|pool| (pool.len() as f64).log2()).sum();
+            let chars = (q as f64 + r as f64 / k as f64) * per_class;
+            let bits = extra + order + chars;
+            (bits, format!("{:.1} bits per password ({} classes, {:.1} bits for their order)", bits, k, extra + order))
+        });
+        ("secure (-s), balanced (--balanced)", charset.clone(), entropy, None)
+    } else if config.secure {
+        // С --no-leading-* и --no-edge-symbols первый и последний символы
+        // выбираются из меньшего набора
+        let leading = charset.iter().filter(|&&c| can_lead(c, config)).count();
+        let ending = charset.iter().filter(|&&c| can_end(c, config)).count();
+        let entropy = (!charset.is_empty() && leading > 0).then(|| {
+            // С --no-repeat символ после первого не равен предыдущему, то есть
+            // выбирается из набора на один символ меньше. Это приближение:
+            // предыдущего символа может и не быть в наборе края.
+            let less = usize::from(config.no_repeat);
+            let sizes = (0..length).map(|i| match i {
+                0 => leading,
+                i if i + 1 == length => ending.saturating_sub(less).max(1),
+                _ => (charset.len() - less).max(1),
+            });
+            // Подряд идущие позиции с одним размером набора - одно слагаемое
+            let mut groups: Vec<(usize, usize)> = Vec::new();
+            for size in sizes {
+                match groups.last_mut() {
+                    Some((count, last)) if *last == size => *count += 1,
+                    _ => groups.push((1, size)),
+                }
+            }
+            let bits: f64 = groups.iter().map(|&(count, size)| count as f64 * (size as f64).log2()).sum();
+            let terms: Vec<String> = groups
+                .iter()
+                .map(|&(count, size)| match count {
+                    1 => format!("log2 {}", size),
+                    _ => format!("{} x log2 {}", count, size),
+                })
+                .collect();
+            (bits, format!("{:.1} bits per password ({})", bits, terms.join(" + ")))
+        });
+        let mode = if config.url_safe {
+            "secure (-s), URL-safe (--url-safe)"
+        } else if let Some(preset) = config.preset {
+            preset.mode()
+        } else if config.digits_only {
+            "secure (-s), digits only (--digits-only)"
+        } else if config.alpha_only {
+            "secure (-s), letters only (--alpha-only)"
+        } else {
+            "secure (-s)"
+        };
+        (mode, charset.clone(), entropy, None)
+    } else {
+        let (mode, mut used, entropy, missing) = if let Some(count) = config.syllable_count {
+            // Слоги равновероятны; выбор слога с заглавной не учитывается
+            let list = syllables::inventory(config);
+            let entropy = (!list.is_empty()).then(|| {
+                let bits = count as f64 * (list.len() as f64).log2();
+                let per_syllable = (list.len() as f64).log2();
+                (bits, format!("{:.1} bits per password ({} x {:.2} bits per syllable)", bits, count, per_syllable))
+            });
+            let missing = list.is_empty().then_some("syllables");
+            ("pronounceable, whole syllables (--syllables)", list.concat(), entropy, missing)
+        } else if config.no_vowels {
+            let consonants = if config.no_capitalize { CONSONANTS_LOWER } else { CONSONANTS };
+            let consonants: Vec<char> =
+                consonants.iter().map(|&c| char::from(c)).filter(|&c| is_allowed(c, config)).collect();
+            // Диграфы дают меньше вариантов, чем две любые согласные, так что это оценка сверху
+            let entropy = (!consonants.is_empty()).then(|| {
+                let bits = length as f64 * (consonants.len() as f64).log2();
+                (bits, format!("at most {:.1} bits per password ({} x log2 {})", bits, length, consonants.len()))
+            });
+            let missing = consonants.is_empty().then_some("consonants");
+            ("pronounceable, no vowels (-v)", consonants, entropy, missing)
+        } else if let Some(list) = &config.syllables {
+            let bits = syllables::entropy(length, list);
+            let line = format!("at most {:.1} bits per password (choices among {} syllables)", bits, list.len());
+            let entropy = (bits, line);
+            ("pronounceable, syllables (--syllable-file)", list.concat(), Some(entropy), None)
+        } else if config.memorable_model == MemorableModel::Markov {
+            // Ошибку пустой строки модели вернет check_settings
+            let entropy = markov::entropy(length, config).ok().map(|bits| {
+                let line = format!(
+                    "at most {:.1} bits per password ({:.2} bits per letter from trigram weights)",
+                    bits,
+                    bits / length as f64
+                );
+                (bits, line)
+            });
+            ("pronounceable, Markov model (--memorable-model markov)", markov::letters(config), entropy, None)
+        } else {
+            // Разные пути генератора дают и одинаковые пароли, так что это оценка сверху
+            let missing = phoneme::missing(config);
+            let entropy = missing.is_none().then(|| {
+                let bits = phoneme::entropy(length, config);
+                (bits, format!("at most {:.1} bits per password (phoneme choices)", bits))
+            });
+            ("pronounceable", phoneme::letters(config), entropy, missing)
+        };
+        for (_, _, chars) in &required {
+            used.extend_from_slice(chars);
+        }
+        (mode, used, entropy, missing)
+    }
+}
+
+// --words: у фразы нет классов символов, только слова списка
+fn run_words<W: Write>(config: &Config, count: usize, out: &mut W) -> Result<(), RunError> {
+    let phrase = wordlist::Style::new(config).and_then(|style| {
+        let slots = wordlist::Slots::new(count, wordlist::words(config)?, config.acrostic.as_deref())?;
+        Ok((style, slots))
+    });
+    let flag = if config.acrostic.is_some() { "--words-acrostic" } else { "--words" };
+    writeln!(out, "Mode:       passphrase ({})", flag)?;
+    writeln!(out, "Words:      {}", count)?;
+    writeln!(out, "Count:      {}", config.num_pw)?;
+    writeln!(out, "Separator:  {:?}", config.separator.as_deref().unwrap_or(wordlist::DEFAULT_SEPARATOR))?;
+    if config.capitalize_words {
+        writeln!(out, "Capitals:   first letter of every word (0 bits)")?;
+    }
+    match &phrase {
+        Ok((style, slots)) => {
+            let list = slots.words();
+            let bits = wordlist::bits_per_word(list);
+            let source = match &config.wordlist_file {
+                Some(path) => path.display().to_string(),
+                None => match wordlist::language(config) {
+                    Some(lang) => format!("embedded, {}", lang.name()),
+                    None => "embedded".to_string(),
+                },
+            };
+            writeln!(out, "Wordlist:   {} words ({})", list.len(), source)?;
+            for (name, pool) in [("Digit:     ", &style.digits), ("Symbol:    ", &style.symbols)] {
+                if !pool.is_empty() {
+                    let chars: String = pool.iter().collect();
+                    writeln!(
+                        out,
+                        "{} {} ({}) at one of {} boundaries, {:.2} bits",
+                        name,
+                        chars,
+                        pool.len(),
+                        count + 1,
+                        wordlist::injection_bits(count, pool)
+                    )?;
+                }
+            }
+            let total = wordlist::entropy(slots, style);
+            let from_words: f64 = (0..count).map(|position| wordlist::bits_per_word(slots.get(position))).sum();
+            let injected = total - from_words;
+            let extra = if injected > 0.0 { format!(" + {:.2} bits inserted", injected) } else { String::new() };
+            if let Some(letters) = &config.acrostic {
+                // Слов на букву меньше, чем во всем списке: энтропия ниже
+                let sizes: Vec<String> =
+                    letters.iter().enumerate().map(|(i, c)| format!("{} {}", c, slots.get(i).len())).collect();
+                writeln!(out, "Acrostic:   {} (words per initial)", sizes.join(", "))?;
+                writeln!(
+                    out,
+                    "Entropy:    {:.1} bits per passphrase ({:.2} bits from the initials' words{})",
+                    total, from_words, extra
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "Entropy:    {:.1} bits per passphrase ({} x log2 {} = {} x {:.2} bits per word{})",
+                    total,
+                    count,
+                    list.len(),
+                    count,
+                    bits,
+                    extra
+                )?;
+            }
+        }
+        Err(_) => {
+            writeln!(out, "Wordlist:   none")?;
+            writeln!(out, "Entropy:    none")?;
+        }
+    }
+    out.flush()?;
+    phrase.map(|_| ())
+}
+
+// --template: классы задает шаблон, так что печатается только он и энтропия
+fn run_template<W: Write>(config: &Config, template: &template::Template, out: &mut W) -> Result<(), RunError> {
+    let pools = template.pools(config);
+    writeln!(out, "Mode:       template (--template)")?;
+    writeln!(out, "Template:   {}", template.pattern())?;
+    writeln!(out, "Length:     {}", template.len())?;
+    writeln!(out, "Count:      {}", config.num_pw)?;
+    match &pools {
+        Ok(pools) => writeln!(out, "Entropy:    {:.1} bits per password", template::entropy(pools))?,
+        Err(_) => writeln!(out, "Entropy:    none")?,
+    }
+    out.flush()?;
+    pools.map(|_| ())
+}
+
+// --like: сколько позиций каждого класса, без самого пароля
+fn run_like<W: Write>(config: &Config, shape: &like::Shape, out: &mut W) -> Result<(), RunError> {
+    let pools = shape.pools(config);
+    writeln!(out, "Mode:       same shape as a given password (--like)")?;
+    writeln!(out, "Shape:      {}", shape.summary())?;
+    writeln!(out, "Length:     {}", shape.len())?;
+    writeln!(out, "Count:      {}", config.num_pw)?;
+    match &pools {
+        Ok(pools) => writeln!(out, "Entropy:    {:.1} bits per password", template::entropy(pools))?,
+        Err(_) => writeln!(out, "Entropy:    none")?,
+    }
+    out.flush()?;
+    pools.map(|_| ())
+}
+
+// --blocks: энтропия отдельно для слов и для цифр
+fn run_blocks<W: Write>(config: &Config, spec: &blocks::Spec, out: &mut W) -> Result<(), RunError> {
+    let parts = spec.parts(config);
+    writeln!(out, "Mode:       blocks (--blocks)")?;
+    writeln!(out, "Blocks:     {}", spec.spec())?;
+    writeln!(out, "Length:     {}", spec.len())?;
+    writeln!(out, "Count:      {}", config.num_pw)?;
+    match &parts {
+        Ok(parts) => {
+            let ((words, letters), (groups, digits)) = spec.counts();
+            let (word_bits, digit_bits) = spec.entropy(parts);
+            let noun = |n: usize, one: &'static str, many: &'static str| if n == 1 { one } else { many };
+            if words > 0 {
+                let word = noun(words, "word", "words");
+                writeln!(out, "Words:      {} {}, {} letters, at most {:.1} bits", words, word, letters, word_bits)?;
+            }
+            if groups > 0 {
+                writeln!(
+                    out,
+                    "Digits:     {} {}, {} digits, {:.1} bits ({} x log2 {})",
+                    groups,
+                    noun(groups, "group", "groups"),
+                    digits,
+                    digit_bits,
+                    digits,
+                    parts.digits().len()
+                )?;
+            }
+            let bound = if words > 0 { "at most " } else { "" };
+            writeln!(out, "Entropy:    {}{:.1} bits per password", bound, word_bits + digit_bits)?;
+        }
+        Err(_) => writeln!(out, "Entropy:    none")?,
+    }
+    out.flush()?;
+    parts.map(|_| ())
+}
+
+// --pin: цифры равновероятны, слабые коды из выбора исключены
+fn run_pin<W: Write>(config: &Config, out: &mut W) -> Result<(), RunError> {
+    let length = shortest_length(config);
+    let result = check_settings(config);
+    writeln!(out, "Mode:       PIN (--pin)")?;
+    match config.length_range {
+        Some((min, max)) => writeln!(out, "Length:     {}-{} (entropy below is for {})", min, max, min)?,
+        None => writeln!(out, "Length:     {}", length)?,
+    }
+    writeln!(out, "Count:      {}", config.num_pw)?;
+    match result {
+        Ok(()) => writeln!(
+            out,
+            "Entropy:    {:.1} bits per password ({} weak PINs excluded)",
+            pin::entropy(length),
+            pin::weak_count(length)
+        )?,
+        Err(_) => writeln!(out, "Entropy:    none")?,
+    }
+    out.flush()?;
+    result
+}
+
+// log2 n! для числа расстановок классов
+fn log2_factorial(n: usize) -> f64 {
+    (2..=n).map(|k| (k as f64).log2()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassCounts;
+
+    fn report(config: &Config) -> (String, Result<(), RunError>) {
+        let mut out = Vec::new();
+        let result = run(config, &mut out);
+        (String::from_utf8(out).unwrap(), result)
+    }
+
+    #[test]
+    fn test_secure_report() {
+        let config = Config {
+            secure: true,
+            ambiguous: true,
+            pw_length: 12,
+            num_pw: 5,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert_eq!(
+            out,
+            "Mode:       secure (-s)\n\
+             Length:     12\n\
+             Count:      5\n\
+             Lowercase:  abcdefghijkmnopqrstuvwxyz (25)\n\
+             Uppercase:  ACEFHJKLMNPRTUVWXY (18)\n\
+             Digits:     3479 (4)\n\
+             Symbols:    none\n\
+             Charset:    47 characters\n\
+             Required:   uppercase, digit\n\
+             Entropy:    66.7 bits per password (12 x log2 47)\n"
+        );
+    }
+
+    #[test]
+    fn test_length_range_report() {
+        let config = Config {
+            secure: true,
+            pw_length: 16,
+            length_range: Some((12, 16)),
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert!(out.contains("Length:     12-16 (entropy below is for 12)\n"), "{}", out);
+        assert!(out.contains("(12 x log2 62)\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_pin_report() {
+        let config = Config {
+            pin: true,
+            secure: true,
+            pw_length: 4,
+            num_pw: 3,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert_eq!(
+            out,
+            "Mode:       PIN (--pin)\n\
+             Length:     4\n\
+             Count:      3\n\
+             Entropy:    13.3 bits per password (119 weak PINs excluded)\n"
+        );
+
+        let (out, result) = report(&Config { pw_length: 3, ..config });
+        assert!(matches!(result, Err(RunError::Settings(_))));
+        assert!(out.ends_with("Entropy:    none\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_like_report() {
+        let config = Config {
+            like: Some(like::Shape::new("Abc7").unwrap()),
+            pw_length: 4,
+            num_pw: 1,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        // Без старых символов: 25 x 25 x 25 x 9
+        assert_eq!(
+            out,
+            "Mode:       same shape as a given password (--like)\n\
+             Shape:      2 lowercase letters, 1 uppercase letter, 1 digit\n\
+             Length:     4\n\
+             Count:      1\n\
+             Entropy:    17.1 bits per password\n"
+        );
+        assert!(!out.contains("Abc7"));
+    }
+
+    #[test]
+    fn test_syllables_report() {
+        let config = Config { syllable_count: Some(4), ..Config::default() };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert!(out.contains("Length:     8-16 (4 syllables)\n"), "{}", out);
+        // 25 согласных на 13 гласных - log2 325 бит на слог
+        assert!(out.contains("Entropy:    33.4 bits per password (4 x 8.34 bits per syllable)\n"), "{}", out);
+
+        let config = Config { syllables: Some(vec![vec!['k', 'a'], vec!['s', 't', 'o']]), ..config };
+        let (out, _) = report(&config);
+        assert!(out.contains("Length:     8-12 (4 syllables)\n"), "{}", out);
+        assert!(out.contains("Entropy:    4.0 bits per password (4 x 1.00 bits per syllable)\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_blocks_report() {
+        let spec = |text: &str| Some(blocks::Spec::parse(text).unwrap());
+        let config = Config {
+            blocks: spec("d4-d4"),
+            no_capitalize: true,
+            pw_length: 9,
+            num_pw: 2,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert_eq!(
+            out,
+            "Mode:       blocks (--blocks)\n\
+             Blocks:     d4-d4\n\
+             Length:     9\n\
+             Count:      2\n\
+             Digits:     2 groups, 8 digits, 26.6 bits (8 x log2 10)\n\
+             Entropy:    26.6 bits per password\n"
+        );
+
+        // В словах энтропия - верхняя оценка
+        let (out, result) = report(&Config { blocks: spec("w6-d4"), ..config.clone() });
+        assert!(result.is_ok());
+        assert!(out.contains("Words:      1 word, 6 letters, at most "), "{}", out);
+        assert!(out.contains("Entropy:    at most "), "{}", out);
+
+        let (out, result) = report(&Config { blocks: spec("w6"), symbols: true, ..config });
+        assert!(matches!(result, Err(RunError::Settings(_))));
+        assert!(out.ends_with("Entropy:    none\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_url_safe_report() {
+        let config = Config {
+            secure: true,
+            url_safe: true,
+            pw_length: 20,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert!(out.starts_with("Mode:       secure (-s), URL-safe (--url-safe)\n"), "{}", out);
+        assert!(out.contains("Symbols:    -._~ (4)\n"), "{}", out);
+        assert!(out.contains("Charset:    66 characters\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_mobile_friendly_report() {
+        let config = Config {
+            mobile_friendly: true,
+            no_capitalize: true,
+            pw_length: 12,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert!(out.starts_with("Mode:       mobile-friendly (--mobile-friendly)\n"), "{}", out);
+        assert!(out.contains("Uppercase:  none\n"), "{}", out);
+        assert!(out.contains("Charset:    36 characters\n"), "{}", out);
+        // log2 11 + 6 x log2 26 + 6 x log2 10 против 12 x log2 36
+        assert!(
+            out.contains("Entropy:    51.6 bits per password in 2 blocks (62.0 bits if not grouped)\n"),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_layout_safe_report() {
+        let config = Config {
+            secure: true,
+            layout_safe: true,
+            ambiguous: true,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert!(out.contains("Lowercase:  bcdefghijknoprstuvx (19)\n"), "{}", out);
+        assert!(out.contains("Uppercase:  CEFHJKLNPRTUVX (14)\n"), "{}", out);
+        assert!(out.contains("Digits:     3479 (4)\n"), "{}", out);
+        assert!(out.contains("Charset:    37 characters\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_classes_report() {
+        let config = Config {
+            secure: true,
+            classes: Some([4, 2, 2, 1]),
+            class_counts: ClassCounts {
+                min: [4, 2, 2, 1],
+                max: [Some(4), Some(2), Some(2), Some(1)],
+            },
+            symbols: true,
+            pw_length: 9,
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        run(&config, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Mode:       secure (-s), exact class counts (--classes)\n"), "{}", out);
+        assert!(out.contains("Counts:     exactly 4 lowercase, exactly 2 uppercase, exactly 2 digits, exactly 1 symbol\n"));
+        // 9! / (4! 2! 2! 1!) = 3780 порядков
+        let bits = 3780f64.log2() + 6.0 * 26f64.log2() + 2.0 * 10f64.log2() + 32f64.log2();
+        assert!(out.contains(&format!("Entropy:    {:.1} bits per password (11.9 bits for the order of classes)", bits)), "{}", out);
+    }
+
+    #[test]
+    fn test_balanced_report() {
+        let config = Config {
+            secure: true,
+            balanced: true,
+            pw_length: 8,
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        run(&config, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Mode:       secure (-s), balanced (--balanced)\n"), "{}", out);
+        // Три класса: 3, 3, 2 символа; лишний символ у одного из трех
+        // классов, 8! / (3! 3! 2!) = 560 порядков
+        let order = 3f64.log2() + 560f64.log2();
+        let bits = order + (2.0 + 2.0 / 3.0) * (26f64.log2() * 2.0 + 10f64.log2());
+        let expected = format!("Entropy:    {:.1} bits per password (3 classes, {:.1} bits for their order)", bits, order);
+        assert!(out.contains(&expected), "{}", out);
+    }
+
+    #[test]
+    fn test_weighted_report() {
+        let config = Config {
+            secure: true,
+            weights: Some([8, 0, 3, 1]),
+            no_capitalize: true,
+            pw_length: 10,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert!(out.starts_with("Mode:       secure (-s), weighted\n"), "{}", out);
+        assert!(out.contains("Uppercase:  none\n"), "{}", out);
+        // 2/3 x log2(1.5 x 26) + 1/4 x log2(4 x 10) + 1/12 x log2(12 x 32) = 5.57
+        assert!(
+            out.contains("Entropy:    55.7 bits per password (10 x 5.57 bits per weighted character)\n"),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_memorable_report() {
+        let config = Config {
+            pw_length: 8,
+            num_pw: 1,
+            no_capitalize: true,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        assert!(out.starts_with("Mode:       pronounceable\n"), "{}", out);
+        assert!(out.contains("Uppercase:  none\n"), "{}", out);
+        // Цифры попадают в пароль только как обязательный класс
+        assert!(out.contains("Digits:     0123456789 (10)\n"), "{}", out);
+        // В таблице элементов есть все 26 букв
+        assert!(out.contains("Lowercase:  abcdefghijklmnopqrstuvwxyz (26)\n"), "{}", out);
+        assert!(out.contains("Entropy:    at most 28.7 bits per password (phoneme choices)\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_no_repeat_report() {
+        let config = Config {
+            secure: true,
+            ambiguous: true,
+            no_repeat: true,
+            pw_length: 12,
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(result.is_ok());
+        // Первый символ - из 47, каждый следующий - из 46 оставшихся
+        assert!(out.contains("Entropy:    66.3 bits per password (log2 47 + 11 x log2 46)\n"), "{}", out);
+
+        // Символы не на краях: первый из 47 букв и цифр, последний из них же
+        // без предыдущего
+        let config = Config {
+            no_edge_symbols: true,
+            symbols: true,
+            ..config
+        };
+        let (out, _) = report(&config);
+        assert!(out.contains("(log2 47 + 10 x log2 69 + log2 46)\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_entropy_by_mode() {
+        let bits = |config: &Config, length: usize| format!("{:.2}", password_entropy(config, length).unwrap());
+        // -s: length x log2 62
+        let secure = Config {
+            secure: true,
+            ..Config::default()
+        };
+        assert_eq!(bits(&secure, 16), "95.27");
+        assert_eq!(bits(&secure, 8), "47.63");
+        // --no-repeat: log2 62 + 15 x log2 61
+        let no_repeat = Config {
+            no_repeat: true,
+            ..secure.clone()
+        };
+        assert_eq!(bits(&no_repeat, 16), "94.92");
+        // Запоминаемые пароли слабее случайных той же длины
+        let memorable = Config::default();
+        assert_eq!(bits(&memorable, 8), "31.12");
+    }
+
+    #[cfg(feature = "wordlist-en")]
+    #[test]
+    fn test_passphrase_entropy() {
+        let bits = |config: &Config| format!("{:.2}", password_entropy(config, 0).unwrap());
+        // Фраза: сумма по словам
+        let words = Config {
+            words: Some(4),
+            ..Config::default()
+        };
+        let per_word = bits(&Config { words: Some(1), ..words.clone() });
+        assert_eq!(bits(&words), "42.75");
+        assert_eq!(per_word, "10.69");
+    }
+
+    #[test]
+    fn test_empty_charset_still_reports() {
+        let config = Config {
+            secure: true,
+            no_capitalize: true,
+            no_numerals: true,
+            remove_chars: Some(LOWERCASE.iter().map(|&c| c as char).collect()),
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(out.contains("Charset:    0 characters\n"), "{}", out);
+        assert!(out.contains("Entropy:    none\n"), "{}", out);
+        assert!(result.unwrap_err().to_string().contains("character set is empty"));
+
+        // Без гласных запоминаемый пароль не составить
+        let config = Config {
+            remove_chars: Some("aeiouyAEIOUY".chars().collect()),
+            ..Config::default()
+        };
+        let (out, result) = report(&config);
+        assert!(out.contains("Entropy:    none\n"), "{}", out);
+        assert!(result.unwrap_err().to_string().contains("no vowels left"));
+    }
+}
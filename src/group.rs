@@ -0,0 +1,131 @@
+use crate::{MAX_LENGTH, secret};
+
+// `--group N[,SEP]`: разделитель после каждых N символов случайной части,
+// как в "hX3k-9dfQ-2mWp", чтобы длинный пароль было проще переписать.
+// Группы расставляются у готового пароля, после apply_requirements и
+// отбраковки, так что разделитель не считается ни символом набора, ни
+// символом класса. --prefix и --suffix не делятся.
+
+pub const DEFAULT_SEPARATOR: &str = "-";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grouping {
+    size: usize,
+    separator: String,
+}
+
+impl Grouping {
+    // Ошибка - что ожидалось
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (size, separator) = value.split_once(',').unwrap_or((value, DEFAULT_SEPARATOR));
+        let size = match size.parse::<usize>() {
+            Ok(n) if (1..=MAX_LENGTH).contains(&n) => n,
+            _ => return Err(format!("a group size from 1 to {}, then optionally ,SEP", MAX_LENGTH)),
+        };
+        // Пустой разделитель ничего не делит, а управляющие символы сломали бы строки вывода
+        if separator.is_empty() || separator.chars().any(char::is_control) {
+            return Err("a separator of printable characters after the comma".to_string());
+        }
+        Ok(Self {
+            size,
+            separator: separator.to_string(),
+        })
+    }
+
+    // Сколько разделителей встает в пароль из length символов
+    fn separators(&self, length: usize) -> usize {
+        length.saturating_sub(1) / self.size
+    }
+
+    // Длина пароля из length символов вместе с разделителями
+    pub fn display_length(&self, length: usize) -> usize {
+        length + self.separators(length) * self.separator.chars().count()
+    }
+
+    // Сколько байтов добавляют разделители к паролю из length символов
+    pub fn extra_bytes(&self, length: usize) -> usize {
+        self.separators(length) * self.separator.len()
+    }
+
+    // Сколько символов дают пароль длины total с разделителями
+    // (--group-counts-length). None, если такой длины не бывает: пароль
+    // кончался бы разделителем.
+    pub fn chars_for(&self, total: usize) -> Option<usize> {
+        // k полных групп с разделителем и последняя группа из rest символов
+        let step = self.size + self.separator.chars().count();
+        let full = total.checked_sub(1)? / step;
+        let rest = total - full * step;
+        (rest <= self.size).then_some(full * self.size + rest)
+    }
+
+    // Пароль с разделителями; копия без них затирается
+    pub fn apply(&self, mut password: String) -> String {
+        let length = password.chars().count();
+        if length <= self.size {
+            return password;
+        }
+        let mut grouped = String::with_capacity(password.len() + self.extra_bytes(length));
+        for (i, c) in password.chars().enumerate() {
+            if i > 0 && i % self.size == 0 {
+                grouped.push_str(&self.separator);
+            }
+            grouped.push(c);
+        }
+        secret::wipe_str(&mut password);
+        grouped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let group = |size: usize, separator: &str| Grouping {
+            size,
+            separator: separator.to_string(),
+        };
+        assert_eq!(Grouping::parse("4"), Ok(group(4, "-")));
+        assert_eq!(Grouping::parse("3, "), Ok(group(3, " ")));
+        assert_eq!(Grouping::parse("5,,"), Ok(group(5, ",")));
+        assert_eq!(Grouping::parse("2,::"), Ok(group(2, "::")));
+        for bad in ["", "0", "x", "-1", "4,", "4,\t", "99999"] {
+            assert!(Grouping::parse(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let group = Grouping::parse("4").unwrap();
+        assert_eq!(group.apply("hX3k9dfQ2mWp".to_string()), "hX3k-9dfQ-2mWp");
+        // Длина не делится на 4: последняя группа короче
+        assert_eq!(group.apply("hX3k9dfQ2m".to_string()), "hX3k-9dfQ-2m");
+        assert_eq!(group.apply("hX3k".to_string()), "hX3k");
+        assert_eq!(group.apply("hX".to_string()), "hX");
+        let group = Grouping::parse("3,::").unwrap();
+        assert_eq!(group.apply("абвгдеж".to_string()), "абв::где::ж");
+    }
+
+    #[test]
+    fn test_lengths() {
+        let group = Grouping::parse("4").unwrap();
+        assert_eq!(group.display_length(12), 14);
+        assert_eq!(group.display_length(10), 12);
+        assert_eq!(group.display_length(4), 4);
+        assert_eq!(group.display_length(0), 0);
+        // Обратно: 14 = 12 символов и 2 разделителя, а 5 и 10 кончались бы разделителем
+        for length in 1..40 {
+            assert_eq!(group.chars_for(group.display_length(length)), Some(length));
+        }
+        assert_eq!(group.chars_for(5), None);
+        assert_eq!(group.chars_for(10), None);
+        assert_eq!(group.chars_for(0), None);
+
+        let group = Grouping::parse("3,::").unwrap();
+        assert_eq!(group.display_length(7), 11);
+        assert_eq!(group.chars_for(11), Some(7));
+        assert_eq!(group.chars_for(4), None);
+        assert_eq!(group.chars_for(5), None);
+    }
+}
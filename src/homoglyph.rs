@@ -0,0 +1,186 @@
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::RunError;
+use crate::rng::{EntropySource, random_index};
+
+// `--homoglyph-strict`: группы написаний, которые легко спутать на
+// распечатке. Одиночный символ, похожий на другой одиночный символ,
+// убирается из набора; последовательность (rn, похожее на m) в пароле не
+// появляется: ее последний символ перевыбирается.
+//
+// Формат таблицы и файла --homoglyph-file: группа на строку, написания
+// через пробел; пустые строки и строки, начинающиеся с #, пропускаются.
+const BUILTIN: &str = "\
+0 O D Q
+1 l I |
+2 Z
+5 S
+6 G
+8 B
+9 g q
+rn m
+vv w
+VV W
+cl d
+";
+
+// Сколько раз на символ пароля можно перевыбрать позицию, прежде чем
+// признать, что из набора не составить пароль без похожих сочетаний
+const MAX_REROLLS_PER_CHAR: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    // Одиночные символы, у которых есть похожий одиночный символ
+    singles: Vec<char>,
+    // Последовательности из двух и более символов
+    sequences: Vec<Vec<char>>,
+}
+
+impl Table {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN).expect("builtin homoglyph table is valid")
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut table = Self {
+            singles: Vec::new(),
+            sequences: Vec::new(),
+        };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let group: Vec<Vec<char>> = line.split_whitespace().map(|entry| entry.chars().collect()).collect();
+            if group.len() < 2 {
+                return Err(format!("line {}: a group needs at least two spellings", n + 1));
+            }
+            let singles: Vec<char> = group.iter().filter(|entry| entry.len() == 1).map(|entry| entry[0]).collect();
+            if singles.len() > 1 {
+                table.singles.extend(singles);
+            }
+            table.sequences.extend(group.into_iter().filter(|entry| entry.len() > 1));
+        }
+        table.singles.sort_unstable();
+        table.singles.dedup();
+        table.sequences.sort_unstable();
+        table.sequences.dedup();
+        Ok(table)
+    }
+
+    // Дополняет таблицу группами из файла --homoglyph-file
+    pub fn extend(&mut self, other: Table) {
+        self.singles.extend(other.singles);
+        self.singles.sort_unstable();
+        self.singles.dedup();
+        self.sequences.extend(other.sequences);
+        self.sequences.sort_unstable();
+        self.sequences.dedup();
+    }
+
+    pub fn is_confusable(&self, c: char) -> bool {
+        self.singles.contains(&c)
+    }
+
+    pub fn has_sequence(&self, password: &[char]) -> bool {
+        self.find_sequence(password).is_some()
+    }
+
+    // Позиции первой похожей последовательности
+    fn find_sequence(&self, password: &[char]) -> Option<RangeInclusive<usize>> {
+        (0..password.len()).find_map(|end| {
+            self.sequences
+                .iter()
+                .find(|seq| end + 1 >= seq.len() && password[end + 1 - seq.len()..=end] == seq[..])
+                .map(|seq| end + 1 - seq.len()..=end)
+        })
+    }
+}
+
+pub fn load(path: &Path) -> Result<Table, RunError> {
+    let text = fs::read_to_string(path).map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => RunError::Settings(format!("{}: not valid UTF-8", path.display())),
+        _ => io::Error::new(e.kind(), format!("cannot read {}: {}", path.display(), e)).into(),
+    })?;
+    Table::parse(&text).map_err(|e| RunError::Settings(format!("{}: {}", path.display(), e)))
+}
+
+// Перевыбирает в похожей последовательности последний символ, не
+// закрепленный за обязательным классом или символом (pinned), из того же
+// пула (класса), так что обязательные классы не теряются
+pub fn reroll<R: EntropySource + ?Sized>(
+    password: &mut [char],
+    pinned: &[bool],
+    pools: &[Vec<char>],
+    table: &Table,
+    rng: &mut R,
+) -> Result<(), RunError> {
+    let mut rerolls = 0;
+    while let Some(range) = table.find_sequence(password) {
+        rerolls += 1;
+        let pos = range.rev().find(|&i| !pinned[i]);
+        let pool = pos.and_then(|pos| pools.iter().find(|pool| pool.contains(&password[pos])));
+        let Some(pool) = pool.filter(|_| rerolls <= password.len() * MAX_REROLLS_PER_CHAR) else {
+            return Err(RunError::Generation(
+                "cannot avoid confusable sequences (--homoglyph-strict) with this character set".to_string(),
+            ));
+        };
+        let pos = pos.expect("pool is found only for a free position");
+        password[pos] = pool[random_index(rng, pool.len())?];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_builtin_table() {
+        let table = Table::builtin();
+        for c in "0OQD1lI|2Z5S6G8B9gq".chars() {
+            assert!(table.is_confusable(c), "{}", c);
+        }
+        // m, w и d похожи только на последовательности и остаются
+        for c in "mwdaz7".chars() {
+            assert!(!table.is_confusable(c), "{}", c);
+        }
+        let password: Vec<char> = "xarnvv".chars().collect();
+        assert_eq!(table.find_sequence(&password), Some(2..=3));
+        assert_eq!(table.find_sequence(&password[4..]), Some(0..=1));
+        assert_eq!(table.find_sequence(&password[..3]), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Table::parse("# comment\n\nii u\n").is_ok());
+        assert_eq!(Table::parse("a e\nx\n"), Err("line 2: a group needs at least two spellings".to_string()));
+    }
+
+    #[test]
+    fn test_reroll_keeps_pool() -> Result<(), RunError> {
+        let table = Table::builtin();
+        let pools = vec!["rnx".chars().collect(), "0123456789".chars().collect()];
+        // rn -> n перевыбирается: байт 0 дает 'r', получается rr
+        let mut password: Vec<char> = "arn5".chars().collect();
+        reroll(&mut password, &[false; 4], &pools, &table, &mut Cursor::new([0u8; 8]))?;
+        assert_eq!(password.iter().collect::<String>(), "arr5");
+
+        // Закрепленную n не трогаем, вместо нее перевыбирается r
+        let mut password: Vec<char> = "arn5".chars().collect();
+        let pinned = [false, false, true, false];
+        reroll(&mut password, &pinned, &pools, &table, &mut Cursor::new([2u8; 8]))?;
+        assert_eq!(password.iter().collect::<String>(), "axn5");
+
+        // Из одного v не уйти от vv
+        let pools = vec![vec!['v']];
+        let mut password = vec!['v'; 4];
+        let err = reroll(&mut password, &[false; 4], &pools, &table, &mut io::repeat(0)).unwrap_err();
+        assert!(matches!(err, RunError::Generation(_)));
+        Ok(())
+    }
+}
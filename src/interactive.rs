@@ -0,0 +1,176 @@
+use std::io;
+
+use crate::{Config, RunError};
+use crate::secret::Passwords;
+
+// Клавиши в интерактивном режиме
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Regenerate,
+    Accept,
+    Abort,
+}
+
+const PROMPT: &str = "  [r] another, [y/Enter] accept, [q/Esc] quit";
+
+// Что значат байты одного чтения из терминала. Стрелки и другие
+// escape-последовательности приходят одним чтением и пропускаются, а
+// одиночный Esc - отказ. Ctrl-C и Ctrl-D в raw-режиме - тоже отказ.
+fn key(bytes: &[u8]) -> Option<Key> {
+    match bytes {
+        [b'r' | b'R' | b' '] => Some(Key::Regenerate),
+        [b'y' | b'Y' | b'\r' | b'\n'] => Some(Key::Accept),
+        [b'q' | b'Q' | 0x1b | 0x03 | 0x04] => Some(Key::Abort),
+        _ => None,
+    }
+}
+
+// `pwgen -i`: показывает по одному паролю на терминале, пока пользователь
+// не примет один. Кандидаты пишутся в /dev/tty, так что stdout можно
+// перенаправить и получить в нем только принятый пароль. None - отказ.
+#[cfg(unix)]
+pub fn run(config: &Config) -> Result<Option<Passwords>, RunError> {
+    use std::fs::OpenOptions;
+    use std::io::IsTerminal;
+
+    use crate::{generate_passwords, secret, signals};
+
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "--interactive needs a terminal on stdin").into());
+    }
+    let tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot open /dev/tty: {}", e)))?;
+    let mut terminal = raw::Terminal::new(tty)?;
+
+    // Каждый кандидат - обычная генерация одного пароля
+    let mut single = config.clone();
+    single.num_pw = 1;
+    loop {
+        let passwords = generate_passwords(&single)?;
+        let mut line = Vec::with_capacity(passwords.get(0).len() + PROMPT.len() + 8);
+        // В начало строки, стереть ее и вывести нового кандидата
+        line.extend_from_slice(b"\r\x1b[K");
+        line.extend_from_slice(passwords.get(0).as_bytes());
+        line.extend_from_slice(PROMPT.as_bytes());
+        let shown = terminal.write(&line);
+        secret::wipe(&mut line);
+        shown?;
+
+        let action = loop {
+            let mut buf = [0u8; 8];
+            let n = terminal.read(&mut buf)?;
+            let action = key(&buf[..n]);
+            secret::wipe(&mut buf);
+            signals::check()?;
+            if n == 0 {
+                break Key::Abort;
+            }
+            if let Some(action) = action {
+                break action;
+            }
+        };
+        match action {
+            Key::Regenerate => continue,
+            Key::Accept => {
+                terminal.write(b"\r\x1b[K")?;
+                return Ok(Some(passwords));
+            }
+            Key::Abort => {
+                terminal.write(b"\r\x1b[K")?;
+                return Ok(None);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn run(_config: &Config) -> Result<Option<Passwords>, RunError> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "--interactive is not supported on this platform").into())
+}
+
+#[cfg(unix)]
+mod raw {
+    use std::ffi::c_int;
+    use std::fs::File;
+    use std::io::{self, Read, Write};
+    use std::os::fd::AsRawFd;
+
+    // struct termios различается между системами, но ее поля не нужны:
+    // она только сохраняется и передается cfmakeraw и tcsetattr, так что
+    // хватает буфера заведомо большего размера
+    #[repr(C, align(8))]
+    #[derive(Clone, Copy)]
+    struct Termios([u8; 256]);
+
+    const TCSANOW: c_int = 0;
+
+    unsafe extern "C" {
+        fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        fn tcsetattr(fd: c_int, action: c_int, termios: *const Termios) -> c_int;
+        fn cfmakeraw(termios: *mut Termios);
+    }
+
+    // Терминал в raw-режиме: клавиши читаются сразу, без Enter и эха.
+    // Прежний режим возвращается в Drop, в том числе при панике.
+    pub struct Terminal {
+        tty: File,
+        saved: Termios,
+    }
+
+    impl Terminal {
+        pub fn new(tty: File) -> io::Result<Self> {
+            let fd = tty.as_raw_fd();
+            let mut saved = Termios([0; 256]);
+            // SAFETY: tcgetattr записывает одну struct termios, буфер больше нее
+            if unsafe { tcgetattr(fd, &mut saved) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = saved;
+            // SAFETY: raw - копия настроек, полученных от tcgetattr
+            unsafe { cfmakeraw(&mut raw) };
+            // SAFETY: raw получен из настроек этого же терминала
+            if unsafe { tcsetattr(fd, TCSANOW, &raw) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { tty, saved })
+        }
+
+        pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.tty.read(buf)
+        }
+
+        pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.tty.write_all(bytes)?;
+            self.tty.flush()
+        }
+    }
+
+    impl Drop for Terminal {
+        fn drop(&mut self) {
+            // SAFETY: saved - исходные настройки этого терминала
+            unsafe { tcsetattr(self.tty.as_raw_fd(), TCSANOW, &self.saved) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert_eq!(key(b"r"), Some(Key::Regenerate));
+        assert_eq!(key(b"y"), Some(Key::Accept));
+        assert_eq!(key(b"\r"), Some(Key::Accept));
+        assert_eq!(key(b"q"), Some(Key::Abort));
+        assert_eq!(key(b"\x1b"), Some(Key::Abort));
+        assert_eq!(key(b"\x03"), Some(Key::Abort));
+        // Стрелка вверх - не Esc
+        assert_eq!(key(b"\x1b[A"), None);
+        assert_eq!(key(b"x"), None);
+        assert_eq!(key(b""), None);
+    }
+}
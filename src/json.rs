@@ -0,0 +1,375 @@
+use std::io::{self, Read, Write};
+
+use crate::secret::Passwords;
+use crate::{Config, annotate, dry_run, mnemonic, mnemonic_rng, phonetic, secret, write_line};
+
+// `--format json`: один документ с итоговыми настройками и массивом
+// паролей, у каждого длина, энтропия и режим. Каждый пароль пишется
+// отдельной строкой из собственного буфера, который затирается, как в
+// write_passwords. Столбцы, выравнивание и номера сюда не доходят.
+//
+// {
+//   "settings": {"mode": "secure", "length": 16, ...},
+//   "passwords": [
+//     {"password": "...", "length": 16, "entropy_bits": 104.87, "mode": "secure"}
+//   ]
+// }
+//
+// С --phonetic у каждого пароля есть еще поле "phonetic" со словами, с
+// --mnemonic - поле "mnemonic" с фразой, а с --annotate - массив
+// "annotations" с похожими символами:
+// [{"position": 3, "character": "O", "description": "capital O", "confused_with": "zero"}]
+pub fn write<W: Write>(out: &mut W, passwords: &Passwords, config: &Config) -> io::Result<()> {
+    let mut line = Vec::new();
+    line.extend_from_slice(b"{");
+    write_line(out, &mut line)?;
+    write_settings(&mut line, config)?;
+    write_line(out, &mut line)?;
+    line.extend_from_slice(b"  \"passwords\": [");
+    write_line(out, &mut line)?;
+
+    let mut entries = Entries::new(config)?;
+    let longest = passwords.iter().map(str::len).max().unwrap_or(0);
+    line.reserve(entries.capacity(longest));
+    for (i, password) in passwords.iter().enumerate() {
+        line.extend_from_slice(b"    ");
+        entries.push(&mut line, password)?;
+        if i + 1 < passwords.len() {
+            line.push(b',');
+        }
+        write_line(out, &mut line)?;
+    }
+
+    line.extend_from_slice(b"  ]\n}");
+    write_line(out, &mut line)?;
+    out.flush()
+}
+
+// `--format jsonl`: тот же объект, что в массиве "passwords", по строке на
+// пароль и без настроек. main вызывает ее для каждого пароля сразу после
+// генерации, так что строку можно читать, не дожидаясь конца пакета.
+pub fn write_entry<W: Write>(out: &mut W, entries: &mut Entries, password: &str) -> io::Result<()> {
+    let mut line = Vec::with_capacity(entries.capacity(password.len()));
+    entries.push(&mut line, password)?;
+    write_line(out, &mut line)
+}
+
+// Объекты паролей: {"password": ..., "length": ..., "entropy_bits": ..., "mode": ...}
+pub struct Entries<'a> {
+    mode: &'static str,
+    entropies: dry_run::Entropies<'a>,
+    phonetic: bool,
+    annotate: bool,
+    // Источник слов --mnemonic, как у текстового вывода
+    mnemonic: Option<Box<dyn Read>>,
+}
+
+impl<'a> Entries<'a> {
+    pub fn new(config: &'a Config) -> io::Result<Self> {
+        Ok(Self {
+            mode: dry_run::mode_name(config),
+            entropies: dry_run::Entropies::new(config),
+            phonetic: config.phonetic,
+            annotate: config.annotate,
+            mnemonic: if config.mnemonic { Some(mnemonic_rng(config)?) } else { None },
+        })
+    }
+
+    // Сколько байтов нужно строке с паролем из bytes байтов, чтобы буфер
+    // не перевыделялся и не оставлял копий пароля
+    fn capacity(&self, bytes: usize) -> usize {
+        let spelled = if self.phonetic { bytes * phonetic::MAX_WORD + 16 } else { 0 };
+        let sentence = if self.mnemonic.is_some() { bytes * mnemonic::MAX_WORD + 16 } else { 0 };
+        // Объект пометки в "annotations" короче 128 байтов
+        let notes = if self.annotate { bytes * 128 + 20 } else { 0 };
+        2 * bytes + 96 + spelled + sentence + notes
+    }
+
+    fn push(&mut self, line: &mut Vec<u8>, password: &str) -> io::Result<()> {
+        line.extend_from_slice(b"{\"password\": ");
+        push_string(line, password);
+        write!(line, ", \"length\": {}, \"entropy_bits\": ", password.chars().count())?;
+        push_number(line, self.entropies.of(password))?;
+        line.extend_from_slice(b", \"mode\": ");
+        push_string(line, self.mode);
+        if self.phonetic {
+            let mut spelled = Vec::with_capacity(password.len() * phonetic::MAX_WORD);
+            phonetic::push_spelled(&mut spelled, password);
+            line.extend_from_slice(b", \"phonetic\": ");
+            let mut spelled = String::from_utf8(spelled).unwrap_or_default();
+            push_string(line, &spelled);
+            secret::wipe_str(&mut spelled);
+        }
+        if let Some(rng) = &mut self.mnemonic {
+            let mut sentence = mnemonic::sentence(password, rng.as_mut())?;
+            line.extend_from_slice(b", \"mnemonic\": ");
+            push_string(line, &sentence);
+            secret::wipe_str(&mut sentence);
+        }
+        if self.annotate {
+            line.extend_from_slice(b", \"annotations\": [");
+            for (i, note) in annotate::annotations(password).enumerate() {
+                if i > 0 {
+                    line.extend_from_slice(b", ");
+                }
+                write!(line, "{{\"position\": {}, \"character\": ", note.position)?;
+                let mut buf = [0; 4];
+                push_string(line, note.character.encode_utf8(&mut buf));
+                line.extend_from_slice(b", \"description\": ");
+                push_string(line, note.description);
+                line.extend_from_slice(b", \"confused_with\": ");
+                push_string(line, note.confused_with);
+                line.push(b'}');
+            }
+            line.push(b']');
+        }
+        line.push(b'}');
+        Ok(())
+    }
+}
+
+// Объект "settings": то, что влияет на пароли, без путей к файлам и
+// настроек вывода. У фразы длины в символах нет, вместо нее "words";
+// "charset" и "include_only" - набор из --charset и --include-only (или
+// --charset-file), null без них.
+fn write_settings(line: &mut Vec<u8>, config: &Config) -> io::Result<()> {
+    line.extend_from_slice(b"  \"settings\": {\"mode\": ");
+    push_string(line, dry_run::mode_name(config));
+    match (config.words, config.length_range) {
+        (Some(_), _) => line.extend_from_slice(b", \"length\": null"),
+        (None, Some((min, max))) => write!(line, ", \"length\": null, \"length_range\": [{}, {}]", min, max)?,
+        (None, None) => write!(line, ", \"length\": {}", config.pw_length)?,
+    }
+    write!(line, ", \"count\": {}, \"charset_size\": ", config.num_pw)?;
+    match dry_run::charset_size(config) {
+        Some(size) => write!(line, "{}", size)?,
+        None => line.extend_from_slice(b"null"),
+    }
+    line.extend_from_slice(b", \"words\": ");
+    match config.words {
+        Some(words) => write!(line, "{}", words)?,
+        None => line.extend_from_slice(b"null"),
+    }
+    line.extend_from_slice(b", \"charset\": ");
+    match config.charset_preset {
+        Some((name, _)) => push_string(line, name),
+        None => line.extend_from_slice(b"null"),
+    }
+    line.extend_from_slice(b", \"include_only\": ");
+    match &config.include_only {
+        Some(chars) => push_string(line, &chars.iter().collect::<String>()),
+        None => line.extend_from_slice(b"null"),
+    }
+    line.extend_from_slice(b", \"flags\": {");
+    let flags = [
+        ("capitalize", config.capitalize),
+        ("no_capitalize", config.no_capitalize),
+        ("numerals", config.numerals),
+        ("no_numerals", config.no_numerals),
+        ("symbols", config.symbols),
+        ("secure", config.secure),
+        ("ambiguous", config.ambiguous),
+        ("no_vowels", config.no_vowels),
+        ("alpha_only", config.alpha_only),
+        ("digits_only", config.digits_only),
+        ("pin", config.pin),
+        ("url_safe", config.url_safe),
+        ("shell_safe", config.shell_safe),
+        ("no_shift", config.no_shift),
+        ("mobile_friendly", config.mobile_friendly),
+        ("seeded", config.seed.is_some()),
+    ];
+    for (i, (name, value)) in flags.iter().enumerate() {
+        if i > 0 {
+            line.extend_from_slice(b", ");
+        }
+        write!(line, "\"{}\": {}", name, value)?;
+    }
+    line.extend_from_slice(b"}},");
+    Ok(())
+}
+
+// Строка JSON в кавычках. Кавычки, обратная косая черта и управляющие
+// символы экранируются, остальное, включая не-ASCII, идет как есть в UTF-8.
+pub fn push_string(line: &mut Vec<u8>, s: &str) {
+    line.push(b'"');
+    for c in s.chars() {
+        match c {
+            '"' => line.extend_from_slice(b"\\\""),
+            '\\' => line.extend_from_slice(b"\\\\"),
+            '\n' => line.extend_from_slice(b"\\n"),
+            '\r' => line.extend_from_slice(b"\\r"),
+            '\t' => line.extend_from_slice(b"\\t"),
+            c if u32::from(c) < 0x20 || c == '\u{7f}' => {
+                let _ = write!(line, "\\u{:04x}", u32::from(c));
+            }
+            c => {
+                let mut buf = [0; 4];
+                line.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    line.push(b'"');
+}
+
+// Энтропия с двумя знаками; null, если ее не посчитать
+pub fn push_number(line: &mut Vec<u8>, bits: Option<f64>) -> io::Result<()> {
+    match bits {
+        Some(bits) if bits.is_finite() => write!(line, "{:.2}", bits),
+        _ => {
+            line.extend_from_slice(b"null");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> String {
+        let mut line = Vec::new();
+        push_string(&mut line, s);
+        String::from_utf8(line).unwrap()
+    }
+
+    #[test]
+    fn test_push_string_escapes() {
+        assert_eq!(string("abc"), "\"abc\"");
+        assert_eq!(string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(string("\n\t\u{1}\u{7f}"), "\"\\n\\t\\u0001\\u007f\"");
+        // Остальные символы, включая / и не-ASCII, не экранируются
+        assert_eq!(string("/'`ж"), "\"/'`ж\"");
+    }
+
+    #[test]
+    fn test_push_number() {
+        let number = |bits| {
+            let mut line = Vec::new();
+            push_number(&mut line, bits).unwrap();
+            String::from_utf8(line).unwrap()
+        };
+        assert_eq!(number(Some(104.868)), "104.87");
+        assert_eq!(number(None), "null");
+        assert_eq!(number(Some(f64::NAN)), "null");
+    }
+
+    #[test]
+    fn test_write_document() {
+        let config = Config {
+            secure: true,
+            symbols: true,
+            pw_length: 4,
+            num_pw: 2,
+            ..Config::default()
+        };
+        let passwords = Passwords::from(vec!["a\"b\\".to_string(), "x1y2".to_string()]);
+        let mut out = Vec::new();
+        write(&mut out, &passwords, &config).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 7);
+        let settings = "  \"settings\": {\"mode\": \"secure\", \"length\": 4, \"count\": 2, \"charset_size\": 94,";
+        assert!(lines[1].starts_with(settings), "{}", lines[1]);
+        assert!(lines[1].contains("\"symbols\": true"));
+        assert!(lines[1].contains("\"words\": null, \"charset\": null, \"include_only\": null"), "{}", lines[1]);
+        assert!(lines[1].contains("\"url_safe\": false"));
+        assert_eq!(
+            lines[3],
+            "    {\"password\": \"a\\\"b\\\\\", \"length\": 4, \"entropy_bits\": 26.22, \"mode\": \"secure\"},"
+        );
+        assert_eq!(lines[4], "    {\"password\": \"x1y2\", \"length\": 4, \"entropy_bits\": 26.22, \"mode\": \"secure\"}");
+        assert_eq!(&lines[5..], ["  ]", "}"]);
+    }
+
+    #[test]
+    fn test_write_mode_settings() {
+        let settings = |config: &Config| {
+            let mut line = Vec::new();
+            write_settings(&mut line, config).unwrap();
+            String::from_utf8(line).unwrap()
+        };
+        let url_safe = settings(&Config {
+            url_safe: true,
+            secure: true,
+            ..Config::default()
+        });
+        assert!(url_safe.contains("\"url_safe\": true"), "{}", url_safe);
+        assert!(url_safe.contains("\"shell_safe\": false"), "{}", url_safe);
+        let included = settings(&Config {
+            include_only: Some(vec!['a', '"', '1']),
+            no_shift: true,
+            ..Config::default()
+        });
+        assert!(included.contains("\"include_only\": \"a\\\"1\""), "{}", included);
+        assert!(included.contains("\"no_shift\": true"), "{}", included);
+        // У фразы длина в словах, а не в символах
+        let words = settings(&Config {
+            words: Some(4),
+            ..Config::default()
+        });
+        assert!(words.contains("\"length\": null, \"count\""), "{}", words);
+        assert!(words.contains("\"words\": 4"), "{}", words);
+    }
+
+    #[test]
+    fn test_write_phonetic() {
+        let config = Config {
+            pw_length: 4,
+            num_pw: 1,
+            phonetic: true,
+            ..Config::default()
+        };
+        let mut entries = Entries::new(&config).unwrap();
+        let mut out = Vec::new();
+        write_entry(&mut out, &mut entries, "hX3\\").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.ends_with(", \"phonetic\": \"hotel X-RAY three backslash\"}\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_write_mnemonic() {
+        let config = Config {
+            pw_length: 3,
+            num_pw: 1,
+            mnemonic: true,
+            seed: Some(577),
+            ..Config::default()
+        };
+        let mut entries = Entries::new(&config).unwrap();
+        let mut out = Vec::new();
+        write_entry(&mut out, &mut entries, "a3#").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let start = out.find(", \"mnemonic\": \"").expect(&out) + 15;
+        let sentence: Vec<&str> = out[start..].trim_end_matches("\"}\n").split(' ').collect();
+        assert_eq!(sentence.len(), 3, "{}", out);
+        assert!(sentence[0].starts_with('a') && ["hash", "pound"].contains(&sentence[2]), "{}", out);
+    }
+
+    #[test]
+    fn test_write_annotations() {
+        let config = Config {
+            pw_length: 4,
+            num_pw: 1,
+            annotate: true,
+            ..Config::default()
+        };
+        let write_with = |password: &str| {
+            let mut entries = Entries::new(&config).unwrap();
+            let mut out = Vec::new();
+            write_entry(&mut out, &mut entries, password).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert!(
+            write_with("aO\"b").ends_with(
+                ", \"annotations\": [{\"position\": 2, \"character\": \"O\", \"description\": \"capital O\", \
+                 \"confused_with\": \"zero\"}, {\"position\": 3, \"character\": \"\\\"\", \
+                 \"description\": \"double quote\", \"confused_with\": \"two apostrophes\"}]}\n"
+            ),
+            "{}",
+            write_with("aO\"b")
+        );
+        // Массив есть и у пароля без похожих символов
+        assert!(write_with("hxkw").ends_with(", \"annotations\": []}\n"));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::layout;
+
+// `--no-keyboard-walks`: пароль с "прогулкой" по клавиатуре QWERTY
+// ("qwer", "1qaz", "!@#") генерируется заново. Прогулка - WALK_LENGTH и
+// больше символов на соседних клавишах, где каждый шаг идет в одну и ту
+// же сторону: по ряду, по столбцу или по диагонали. Shift не важен: "1@3"
+// - тоже прогулка, клавиши те же.
+
+pub const WALK_LENGTH: usize = 3;
+
+// Сдвиг рядов относительно верхнего в половинах клавиши: q стоит между 1
+// и 2, a - под q и w, z - под a и s
+const ROW_OFFSETS: [i32; 4] = [0, 3, 4, 5];
+
+// Соседние клавиши: вправо и влево на клавишу, вниз и вверх на ряд со
+// сдвигом на полклавиши в любую сторону
+const STEPS: [(i32, i32); 6] = [(0, 2), (0, -2), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+// Клавиша символа: ряд и столбец в половинах клавиши
+fn key(c: char) -> Option<(i32, i32)> {
+    let (row, col) = layout::qwerty_key(c)?;
+    Some((row as i32, col as i32 * 2 + ROW_OFFSETS[row]))
+}
+
+// Есть ли в пароле n символов подряд на соседних клавишах, идущих в одну
+// сторону
+pub fn has_walk(pw: &str, n: usize) -> bool {
+    let mut prev: Option<(i32, i32)> = None;
+    let mut step: Option<(i32, i32)> = None;
+    let mut length = 0;
+    for c in pw.chars() {
+        let current = key(c);
+        let next = prev.zip(current).map(|(a, b)| (b.0 - a.0, b.1 - a.1)).filter(|d| STEPS.contains(d));
+        length = match (next, current) {
+            (Some(d), _) if step == Some(d) => length + 1,
+            (Some(_), _) => 2,
+            (None, Some(_)) => 1,
+            (None, None) => 0,
+        };
+        if current.is_some() && length >= n {
+            return true;
+        }
+        step = next;
+        prev = current;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_walks(walks: &[&str]) {
+        for pw in walks {
+            assert!(has_walk(pw, WALK_LENGTH), "{}", pw);
+        }
+    }
+
+    #[test]
+    fn test_rows() {
+        assert_walks(&["qwer", "asdf", "zxcv", "poiu", "123", "890-=", "jkl;'", "m,./", "x-wert-y", "QwE"]);
+    }
+
+    #[test]
+    fn test_columns_and_diagonals() {
+        // Столбцы клавиатуры идут вниз-вправо
+        assert_walks(&["1qaz", "zaq1", "2wsx", "edc", "9ol.", "0p;/", "rfv", "yhn"]);
+        // Диагонали в другую сторону
+        assert_walks(&["rdx", "xdr", "4esz", "tfc"]);
+    }
+
+    #[test]
+    fn test_shifted_symbols() {
+        assert_walks(&["!@#", "#@!", "$%^", "()_+", "{}|", "1@3", "QAZ", "!QA", "<LP_", "AsD"]);
+    }
+
+    #[test]
+    fn test_near_misses() {
+        for pw in [
+            "", "qw", "qwr", "qaw", "qwa", "abc", "q1q", "aqa", "qqw", "aAs", "plm", "p[\\", "`1q", "-=\\", "q w", "qé we",
+            "zas",
+        ] {
+            assert!(!has_walk(pw, WALK_LENGTH), "{}", pw);
+        }
+        // Длина прогулки
+        assert!(has_walk("qwer", 4) && !has_walk("qwe", 4));
+        assert!(has_walk("qw", 2) && !has_walk("qe", 2));
+    }
+}
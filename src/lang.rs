@@ -0,0 +1,110 @@
+// `--lang`: язык встроенного списка слов для --words. Каждый список
+// встраивается со своей cargo feature "wordlist-<код>", чтобы сборщики
+// пакетов могли оставить только нужные; feature "wordlist" включает все.
+// Размеры списков и бит на слово - в wordlist.rs у SMALL_LIST.
+// Новый язык - это новый вариант Lang, файл в wordlists/ и feature в
+// Cargo.toml.
+
+#[cfg(feature = "wordlist-en")]
+const EN: Option<&str> = Some(include_str!("wordlists/en.txt"));
+#[cfg(not(feature = "wordlist-en"))]
+const EN: Option<&str> = None;
+
+#[cfg(feature = "wordlist-de")]
+const DE: Option<&str> = Some(include_str!("wordlists/de.txt"));
+#[cfg(not(feature = "wordlist-de"))]
+const DE: Option<&str> = None;
+
+#[cfg(feature = "wordlist-es")]
+const ES: Option<&str> = Some(include_str!("wordlists/es.txt"));
+#[cfg(not(feature = "wordlist-es"))]
+const ES: Option<&str> = None;
+
+#[cfg(feature = "wordlist-fr")]
+const FR: Option<&str> = Some(include_str!("wordlists/fr.txt"));
+#[cfg(not(feature = "wordlist-fr"))]
+const FR: Option<&str> = None;
+
+#[cfg(feature = "wordlist-ru")]
+const RU: Option<&str> = Some(include_str!("wordlists/ru.txt"));
+#[cfg(not(feature = "wordlist-ru"))]
+const RU: Option<&str> = None;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    En,
+    De,
+    Es,
+    Fr,
+    Ru,
+}
+
+impl Lang {
+    pub const ALL: &[Lang] = &[Lang::En, Lang::De, Lang::Es, Lang::Fr, Lang::Ru];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|lang| lang.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+            Lang::Es => "es",
+            Lang::Fr => "fr",
+            Lang::Ru => "ru",
+        }
+    }
+
+    // Текст списка, если он встроен в эту сборку
+    pub fn text(self) -> Option<&'static str> {
+        match self {
+            Lang::En => EN,
+            Lang::De => DE,
+            Lang::Es => ES,
+            Lang::Fr => FR,
+            Lang::Ru => RU,
+        }
+    }
+}
+
+// Языки, списки которых есть в этой сборке
+pub fn built() -> Vec<Lang> {
+    Lang::ALL.iter().copied().filter(|lang| lang.text().is_some()).collect()
+}
+
+// Ошибка для языка, собранного без своего списка
+pub fn missing(lang: Lang, built: &[Lang]) -> String {
+    let names: Vec<&str> = built.iter().map(|lang| lang.name()).collect();
+    let available = if names.is_empty() { "none; use --wordlist FILE".to_string() } else { names.join(", ") };
+    format!(
+        "the {} wordlist is not built into this binary (cargo feature \"wordlist-{}\"); available: {}",
+        lang.name(),
+        lang.name(),
+        available
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        for &lang in Lang::ALL {
+            assert_eq!(Lang::parse(lang.name()), Some(lang));
+        }
+        assert_eq!(Lang::parse("EN"), None);
+        assert_eq!(Lang::parse("pt"), None);
+        assert!(built().iter().all(|lang| lang.text().is_some()));
+    }
+
+    #[test]
+    fn test_missing() {
+        assert_eq!(
+            missing(Lang::Ru, &[Lang::En, Lang::De]),
+            "the ru wordlist is not built into this binary (cargo feature \"wordlist-ru\"); available: en, de"
+        );
+        assert!(missing(Lang::En, &[]).ends_with("available: none; use --wordlist FILE"));
+    }
+}
@@ -0,0 +1,93 @@
+// `--layout-safe`: символы, которые на раскладках QWERTY (США), QWERTZ
+// (Германия) и AZERTY (Франция) стоят на одной и той же физической
+// клавише. Пароль, придуманный на одной из них, набирается на другой по
+// памяти пальцев без ошибок. Регистр не учитывается: цифры на AZERTY
+// набираются с Shift, но на тех же клавишах.
+//
+// Остаются буквы bcdefghijklnoprstuvx (и заглавные) и цифры; y, z, q, a,
+// w и m переезжают, а символов, стоящих везде на одном месте, нет.
+
+// Ряды основной части клавиатуры: нижний и верхний регистр. Клавиша ISO
+// слева от нижнего ряда (< > на QWERTZ и AZERTY) опущена: на QWERTY ее нет.
+type Layout = (&'static str, [(&'static str, &'static str); 4]);
+
+const LAYOUTS: &[Layout] = &[
+    (
+        "QWERTY",
+        [
+            ("`1234567890-=", "~!@#$%^&*()_+"),
+            ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+            ("asdfghjkl;'", "ASDFGHJKL:\""),
+            ("zxcvbnm,./", "ZXCVBNM<>?"),
+        ],
+    ),
+    (
+        "QWERTZ",
+        [
+            ("^1234567890ß´", "°!\"§$%&/()=?`"),
+            ("qwertzuiopü+", "QWERTZUIOPÜ*"),
+            ("asdfghjklöä#", "ASDFGHJKLÖÄ'"),
+            ("yxcvbnm,.-", "YXCVBNM;:_"),
+        ],
+    ),
+    (
+        "AZERTY",
+        [
+            ("²&é\"'(-è_çà)=", "²1234567890°+"),
+            ("azertyuiop^$", "AZERTYUIOP¨£"),
+            ("qsdfghjklmù*", "QSDFGHJKLM%µ"),
+            ("wxcvbn,;:!", "WXCVBN?./§"),
+        ],
+    ),
+];
+
+// Ряд и номер клавиши, на которой символ набирается в раскладке
+fn position(layout: &Layout, c: char) -> Option<(usize, usize)> {
+    let (_, rows) = layout;
+    rows.iter().enumerate().find_map(|(row, (lower, upper))| {
+        let col = lower.chars().position(|x| x == c).or_else(|| upper.chars().position(|x| x == c))?;
+        Some((row, col))
+    })
+}
+
+// Ряд и номер клавиши символа на QWERTY, первой раскладке LAYOUTS
+pub fn qwerty_key(c: char) -> Option<(usize, usize)> {
+    position(&LAYOUTS[0], c)
+}
+
+pub fn is_safe(c: char) -> bool {
+    let Some(expected) = position(&LAYOUTS[0], c) else {
+        return false;
+    };
+    LAYOUTS[1..].iter().all(|layout| position(layout, c) == Some(expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn safe(set: &[u8]) -> String {
+        set.iter().map(|&c| char::from(c)).filter(|&c| is_safe(c)).collect()
+    }
+
+    #[test]
+    fn test_rows_have_matching_lengths() {
+        // Оба регистра ряда описывают одни и те же клавиши
+        for (name, rows) in LAYOUTS {
+            for (lower, upper) in rows {
+                assert_eq!(lower.chars().count(), upper.chars().count(), "{} {}", name, lower);
+            }
+        }
+    }
+
+    #[test]
+    fn test_safe_alphabet() {
+        assert_eq!(safe(crate::LOWERCASE), "bcdefghijklnoprstuvx");
+        assert_eq!(safe(crate::UPPERCASE), "BCDEFGHIJKLNOPRSTUVX");
+        assert_eq!(safe(crate::NUMERALS), "0123456789");
+        assert_eq!(safe(crate::SYMBOLS), "");
+        for c in "yzqawmYZQAWM".chars() {
+            assert!(!is_safe(c), "{}", c);
+        }
+    }
+}
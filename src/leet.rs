@@ -0,0 +1,114 @@
+use std::io;
+
+use crate::rng::{EntropySource, random_index};
+use crate::{Config, build_charset, fits_at, is_allowed};
+
+// `--leet[=N]`: до N строчных букв запоминаемого пароля заменяются
+// похожими цифрами и символами ("e" -> "3", "a" -> "@"), чтобы пароль
+// проходил проверки на цифры и символы и оставался читаемым. Замена
+// берется только из набора символов пароля: без -y символов не будет,
+// а исключенное через -r и -B не появится. Заглавные не трогаем, их
+// вставил генератор ради -c. Дальше пароль идет в apply_requirements,
+// которая засчитает замененные цифры и символы и не будет вставлять их
+// повторно.
+
+pub const DEFAULT_COUNT: usize = 2;
+
+const TABLE: &[(char, &[char])] = &[
+    ('a', &['@', '4']),
+    ('b', &['8']),
+    ('e', &['3']),
+    ('g', &['9']),
+    ('i', &['1', '!']),
+    ('l', &['1']),
+    ('o', &['0']),
+    ('s', &['$', '5']),
+    ('t', &['7', '+']),
+    ('z', &['2']),
+];
+
+// Похож ли символ на букву так, как в таблице замен: "5" на "s", "@" на "a"
+pub fn looks_like(c: char, letter: char) -> bool {
+    TABLE.iter().any(|(l, targets)| *l == letter && targets.contains(&c))
+}
+
+// Замены символа на позиции pos, которые разрешены набором и краями
+fn targets(c: char, pos: usize, length: usize, charset: &[char], config: &Config) -> Vec<char> {
+    let Some((_, targets)) = TABLE.iter().find(|(letter, _)| *letter == c) else {
+        return Vec::new();
+    };
+    targets
+        .iter()
+        .copied()
+        .filter(|&t| charset.contains(&t) && is_allowed(t, config) && fits_at(t, pos, length, config))
+        .collect()
+}
+
+// Возвращает число замен: меньше count, если подходящих букв меньше
+pub fn substitute<R: EntropySource + ?Sized>(
+    password: &mut [char],
+    count: usize,
+    config: &Config,
+    rng: &mut R,
+) -> io::Result<usize> {
+    let charset = build_charset(config);
+    let length = password.len();
+    let mut eligible: Vec<usize> =
+        (0..length).filter(|&i| !targets(password[i], i, length, &charset, config).is_empty()).collect();
+    let mut done = 0;
+    while done < count && !eligible.is_empty() {
+        let pos = eligible.swap_remove(random_index(rng, eligible.len())?);
+        let targets = targets(password[pos], pos, length, &charset, config);
+        password[pos] = targets[random_index(rng, targets.len())?];
+        done += 1;
+    }
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+
+    #[test]
+    fn test_substitute() -> io::Result<()> {
+        let config = Config { symbols: true, ..Config::default() };
+        for seed in 0..50 {
+            let mut password: Vec<char> = "eastsideLOT".chars().collect();
+            let done = substitute(&mut password, 3, &config, &mut ChaCha20Rng::seeded(seed))?;
+            assert_eq!(done, 3);
+            let changed = password.iter().zip("eastsideLOT".chars()).filter(|(a, b)| **a != *b).count();
+            assert_eq!(changed, 3, "{:?}", password);
+            // Заглавные остаются как есть
+            assert!(password.ends_with(&['L', 'O', 'T']));
+        }
+
+        // Подходящих букв меньше, чем замен
+        let mut password: Vec<char> = "hxyeh".chars().collect();
+        assert_eq!(substitute(&mut password, 5, &config, &mut ChaCha20Rng::seeded(1))?, 1);
+        assert_eq!(password, ['h', 'x', 'y', '3', 'h']);
+        Ok(())
+    }
+
+    #[test]
+    fn test_substitute_respects_exclusions() -> io::Result<()> {
+        // Без -y только цифры; -r убирает 3, -B - 0, 1, 2, 5 и 8
+        let mut config = Config {
+            remove_chars: Some(vec!['3']),
+            ambiguous: true,
+            ..Config::default()
+        };
+        for seed in 0..50 {
+            let mut password: Vec<char> = "seaotilbgz".chars().collect();
+            substitute(&mut password, 10, &config, &mut ChaCha20Rng::seeded(seed))?;
+            assert_eq!(password.iter().filter(|c| !c.is_ascii_lowercase()).count(), 3, "{:?}", password);
+            assert!(password.iter().all(|&c| c.is_ascii_lowercase() || "479".contains(c)), "{:?}", password);
+        }
+
+        // Цифра не встает в начало с --no-leading-digit
+        config.no_leading_digit = true;
+        let mut password: Vec<char> = "ehh".chars().collect();
+        assert_eq!(substitute(&mut password, 2, &config, &mut ChaCha20Rng::seeded(2))?, 0);
+        Ok(())
+    }
+}
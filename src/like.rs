@@ -0,0 +1,226 @@
+use std::io::BufRead;
+
+use crate::{Config, LOWERCASE, MAX_LENGTH, NUMERALS, RunError, UPPERCASE};
+use crate::{class_chars, is_allowed, secret, symbol_class};
+
+// `--like PASSWORD`: новый пароль той же формы, что и старый - той же
+// длины и с тем же классом на каждой позиции: строчная, заглавная, цифра
+// или символ (все, что не буква и не цифра ASCII). Каждая позиция получает
+// символ своего класса, отличный от старого, если в классе после -B и -r
+// есть другой; таких позиций должно быть не меньше половины. С "--like -"
+// старый пароль читается из первой строки stdin и не попадает в историю
+// shell. Дальше пароль собирается так же, как по --template.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Class {
+    Lower,
+    Upper,
+    Digit,
+    Symbol,
+}
+
+// Названия для сообщений и --dry-run, в единственном и множественном числе
+const NAMES: &[(Class, &str, &str)] = &[
+    (Class::Lower, "lowercase letter", "lowercase letters"),
+    (Class::Upper, "uppercase letter", "uppercase letters"),
+    (Class::Digit, "digit", "digits"),
+    (Class::Symbol, "symbol", "symbols"),
+];
+
+// Строка stdin длиннее этого - не пароль: MAX_LENGTH символов до 4 байтов и \r\n
+const LINE_LIMIT: usize = MAX_LENGTH * 4 + 2;
+
+// Форма пароля. Старые символы нужны, чтобы не повторить их; они
+// затираются вместе с формой.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    classes: Vec<Class>,
+    old: Vec<char>,
+}
+
+impl Drop for Shape {
+    fn drop(&mut self) {
+        secret::wipe_chars(&mut self.old);
+    }
+}
+
+impl Shape {
+    // Ошибка - что ожидалось; сам пароль в сообщение не попадает
+    pub fn new(password: &str) -> Result<Self, String> {
+        let old: Vec<char> = password.chars().collect();
+        if old.is_empty() {
+            return Err("a password to imitate".to_string());
+        }
+        if old.len() > MAX_LENGTH {
+            let mut old = old;
+            secret::wipe_chars(&mut old);
+            return Err(format!("a password of at most {} characters", MAX_LENGTH));
+        }
+        let classes = old
+            .iter()
+            .map(|&c| match c {
+                'a'..='z' => Class::Lower,
+                'A'..='Z' => Class::Upper,
+                '0'..='9' => Class::Digit,
+                _ => Class::Symbol,
+            })
+            .collect();
+        Ok(Self { classes, old })
+    }
+
+    // Длина пароля по форме
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    // Сколько позиций каждого класса, для --dry-run: "4 lowercase letters, 1 digit"
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = NAMES
+            .iter()
+            .filter_map(|&(class, one, many)| {
+                let count = self.classes.iter().filter(|&&other| other == class).count();
+                (count > 0).then(|| format!("{} {}", count, if count == 1 { one } else { many }))
+            })
+            .collect();
+        parts.join(", ")
+    }
+
+    // Из чего выбирается каждая позиция: класс без старого символа. Пустой
+    // класс или меньше половины позиций, которые могут измениться, - ошибка.
+    pub fn pools(&self, config: &Config) -> Result<Vec<Vec<char>>, RunError> {
+        let mut pools = Vec::with_capacity(self.classes.len());
+        let mut changed = 0;
+        for (position, (&class, &old)) in self.classes.iter().zip(&self.old).enumerate() {
+            let chars: Vec<char> = match class {
+                Class::Lower => class_chars(LOWERCASE).collect(),
+                Class::Upper => class_chars(UPPERCASE).collect(),
+                Class::Digit => class_chars(NUMERALS).collect(),
+                Class::Symbol => class_chars(symbol_class(config)).collect(),
+            };
+            let pool: Vec<char> = chars.into_iter().filter(|&c| is_allowed(c, config)).collect();
+            if pool.is_empty() {
+                let (_, name, _) = NAMES.iter().find(|(other, _, _)| *other == class).unwrap();
+                return Err(RunError::Settings(format!(
+                    "--like: no {} is left for position {} after applying --ambiguous/--remove-chars/--include-only",
+                    name,
+                    position + 1
+                )));
+            }
+            let other: Vec<char> = pool.iter().copied().filter(|&c| c != old).collect();
+            if other.is_empty() {
+                pools.push(pool);
+            } else {
+                changed += 1;
+                pools.push(other);
+            }
+        }
+        if changed * 2 < self.classes.len() {
+            return Err(RunError::Settings(format!(
+                "--like: only {} of {} positions can get a new character after applying \
+                 --ambiguous/--remove-chars/--include-only; at least half must change",
+                changed,
+                self.classes.len()
+            )));
+        }
+        Ok(pools)
+    }
+}
+
+// --like -: пароль из первой строки. Буфер выделен сразу на всю строку,
+// чтобы при росте не оставалось копий, и затирается после разбора.
+pub fn read<R: BufRead>(input: R) -> Result<Shape, RunError> {
+    let mut line: Vec<u8> = Vec::with_capacity(LINE_LIMIT + 1);
+    let result = input.take(LINE_LIMIT as u64 + 1).read_until(b'\n', &mut line);
+    let shape = result.map_err(RunError::from).and_then(|_| {
+        let mut end = line.len();
+        if line.len() > LINE_LIMIT {
+            return Err(RunError::Settings(format!(
+                "--like -: the first line of stdin is longer than {} characters",
+                MAX_LENGTH
+            )));
+        }
+        if line[..end].ends_with(b"\n") {
+            end -= 1;
+        }
+        if line[..end].ends_with(b"\r") {
+            end -= 1;
+        }
+        let password = std::str::from_utf8(&line[..end])
+            .map_err(|_| RunError::Settings("--like -: stdin is not valid UTF-8".to_string()))?;
+        Shape::new(password).map_err(|expected| RunError::Settings(format!("--like -: expected {} on stdin", expected)))
+    });
+    secret::wipe(&mut line);
+    shape
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+    use crate::template;
+
+    #[test]
+    fn test_shape() {
+        let shape = Shape::new("aB3$-x9 é").unwrap();
+        use Class::*;
+        assert_eq!(shape.classes, [Lower, Upper, Digit, Symbol, Symbol, Lower, Digit, Symbol, Symbol]);
+        assert_eq!(shape.len(), 9);
+        assert_eq!(shape.summary(), "2 lowercase letters, 1 uppercase letter, 2 digits, 4 symbols");
+        assert_eq!(Shape::new(""), Err("a password to imitate".to_string()));
+        assert!(Shape::new(&"x".repeat(MAX_LENGTH + 1)).is_err());
+        assert!(Shape::new(&"x".repeat(MAX_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn test_generate_same_shape() -> Result<(), RunError> {
+        let old = "Tr0ub4dor&3xY";
+        let shape = Shape::new(old).unwrap();
+        let pools = shape.pools(&Config::default())?;
+        let mut rng = ChaCha20Rng::seeded(590);
+        for _ in 0..200 {
+            let password = template::generate(&pools, &mut rng)?;
+            assert_eq!(Shape::new(&password).unwrap().classes, shape.classes, "{}", password);
+            // Все классы шире одного символа: меняется каждая позиция
+            assert!(password.chars().zip(old.chars()).all(|(new, old)| new != old), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_minimum_difference() -> Result<(), RunError> {
+        // Из цифр осталась только 7: три позиции из шести не изменятся
+        let config = Config {
+            remove_chars: Some("012345689".chars().collect()),
+            ..Config::default()
+        };
+        let shape = Shape::new("ab7c77").unwrap();
+        let pools = shape.pools(&config)?;
+        let mut rng = ChaCha20Rng::seeded(5900);
+        for _ in 0..50 {
+            let password = template::generate(&pools, &mut rng)?;
+            let changed = password.chars().zip("ab7c77".chars()).filter(|(new, old)| new != old).count();
+            assert_eq!(changed, 3, "{}", password);
+        }
+        // Четыре из семи - уже меньше половины
+        let err = Shape::new("a7b7777").unwrap().pools(&config).unwrap_err();
+        assert!(err.to_string().contains("only 2 of 7 positions"), "{}", err);
+        // Класс, от которого ничего не осталось
+        let config = Config { remove_chars: Some(('0'..='9').collect()), ..Config::default() };
+        let err = Shape::new("ab7").unwrap().pools(&config).unwrap_err();
+        assert!(err.to_string().contains("no digit is left for position 3"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read() -> Result<(), RunError> {
+        let shape = read("Passw0rd!\r\nsecond line\n".as_bytes())?;
+        assert_eq!(shape.summary(), "6 lowercase letters, 1 uppercase letter, 1 digit, 1 symbol");
+        assert_eq!(read("abc".as_bytes())?.len(), 3);
+        for bad in [&b"\n"[..], b"", b"\xff\xfe\n"] {
+            assert!(matches!(read(bad), Err(RunError::Settings(_))), "{:?}", bad);
+        }
+        let long = "x".repeat(LINE_LIMIT + 5);
+        assert!(matches!(read(long.as_bytes()), Err(RunError::Settings(_))));
+        Ok(())
+    }
+}
@@ -1,740 +1,9690 @@
+use std::collections::HashSet;
 use std::env;
-use std::fs::File;
-use std::io::{self, Read};
+use std::fs::{self, File, OpenOptions};
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+mod annotate;
+mod bits;
+mod blocks;
+mod chacha20;
+mod check;
+mod clipboard;
+mod color;
+mod completions;
+mod dictionary;
+mod config_file;
+mod csv;
+mod dry_run;
+mod encrypt;
+mod group;
+mod homoglyph;
+mod interactive;
+mod json;
+mod keyboard;
+mod lang;
+mod layout;
+mod leet;
+mod like;
+mod man;
+mod markov;
+mod mnemonic;
+mod mobile;
+mod options;
+mod passphrase;
+mod phoneme;
+mod phonetic;
+mod pin;
+mod png;
+mod preset;
+mod profanity;
+mod qr;
+mod reject;
+mod rng;
+mod secret;
+mod selftest;
+mod sequence;
+mod sha1;
+mod signals;
+mod syllables;
+mod template;
+mod term;
+mod wordlist;
+
+use chacha20::ChaCha20Rng;
+use clipboard::Clipboard;
+use encrypt::Recipient;
+use lang::Lang;
+use options::{Opt, OptionSpec};
+use preset::Preset;
+use qr::{QrCode, QrFormat};
+use rng::{EntropySource, RngChoice, random_index};
+use secret::Passwords;
+use sha1::Sha1Stream;
+use term::Terminal;
 
 const DEFAULT_LENGTH: usize = 8;
-const DEFAULT_COUNT: usize = 160;
-const COLUMNS: usize = 5;
+
+// Коды выхода: 1 - отрицательный ответ (check нашел неподходящий пароль,
+// self-test не прошел, -i отменен), остальные - ошибки, см. RunError
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_GENERATION: i32 = 3;
+const EXIT_IO: i32 = 4;
+
+// Верхние границы для позиционных аргументов
+const MAX_LENGTH: usize = 1024;
+const MAX_COUNT: usize = 10_000_000;
 
 // Наборы символов
 const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
 const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 const NUMERALS: &[u8] = b"0123456789";
 const SYMBOLS: &[u8] = b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+// Символы, которые можно вставить в shell и YAML без кавычек и экранирования
+const SHELL_SAFE_SYMBOLS: &[u8] = b"@#%^*-_=+.:/";
+// Символы, которые на американской раскладке набираются без Shift
+const NO_SHIFT_SYMBOLS: &[u8] = b"`-=[];',./";
+// Символы с первой страницы символов на клавиатурах телефонов
+const MOBILE_SYMBOLS: &[u8] = b".-_@!";
 const VOWELS: &[u8] = b"aeiouyAEIOUY";
 const AMBIGUOUS: &[u8] = b"B8G6I1l0OQDS5Z2";
+// Готовые алфавиты для --charset: base32 по RFC 4648, base58 как в Bitcoin
+// (без 0, O, I и l), printable - все видимые символы ASCII
+const HEX: &[u8] = b"0123456789abcdef";
+const BASE32: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BASE58: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const ALNUM: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const PRINTABLE: &[u8] =
+    b"!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+// Незарезервированные символы URI по RFC 3986: их не нужно экранировать
+const URL_SAFE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+// Классы для --min-* и --max-* в порядке --weights: окончание имени
+// опции, символы класса, название (как в required_classes) и название во
+// множественном числе
+const COUNTED_CLASSES: [(&str, &[u8], &str, &str); 4] = [
+    ("lower", LOWERCASE, "lowercase", "lowercase"),
+    ("upper", UPPERCASE, "uppercase", "uppercase"),
+    ("digits", NUMERALS, "digit", "digits"),
+    ("symbols", SYMBOLS, "symbol", "symbols"),
+];
+// Буквы классов COUNTED_CLASSES в --classes
+const CLASS_LETTERS: [char; 4] = ['l', 'u', 'd', 's'];
+const CHARSET_PRESETS: &[(&str, &[u8])] = &[
+    ("hex", HEX),
+    ("base32", BASE32),
+    ("base58", BASE58),
+    ("alnum", ALNUM),
+    ("printable", PRINTABLE),
+];
+// Символы, которые легко спутать на слух и на письме: ' и `, | и l, , и .
+const AMBIGUOUS_SYMBOLS: &[u8] = b"'`\"|!,.;:";
 
 // Согласные для запоминаемых паролей
 const CONSONANTS: &[u8] = b"bcdfghjklmnpqrstvwxzBCDFGHJKLMNPQRSTVWXZ";
 const CONSONANTS_LOWER: &[u8] = b"bcdfghjklmnpqrstvwxz";
-const VOWELS_LOWER: &[u8] = b"aeiouy";
+
+// Сочетания согласных для паролей без гласных (-v): согласная с плавной
+// (br, kl), s со смычной (st, sp) и диграфы с h (ch, sh, th). Их легко
+// произнести и переписать, в отличие от случайного набора согласных.
+const CONSONANT_DIGRAPHS: &[&[u8; 2]] = &[
+    b"bl", b"br", b"ch", b"cl", b"cr", b"dr", b"fl", b"fr", b"gl", b"gr", b"kl", b"kr", b"ph",
+    b"pl", b"pr", b"sc", b"sh", b"sk", b"sl", b"sm", b"sn", b"sp", b"st", b"th", b"tr", b"tw",
+    b"wh", b"wr",
+];
 
 #[derive(Debug, Clone)]
 struct Config {
     pw_length: usize,
+    // --length-range: длина каждого пароля случайна в [min, max], а
+    // pw_length равна max, чтобы по ней считалась ширина столбцов
+    length_range: Option<(usize, usize)>,
+    // --bits: pw_length подбирается в main по энтропии, см. bits.rs
+    bits: Option<usize>,
+    // --prefix и --suffix: текст вокруг случайной части пароля
+    prefix: Option<String>,
+    suffix: Option<String>,
+    // --length-includes-affixes: pw_length задан для пароля целиком; после
+    // разбора из него вычитается длина prefix и suffix
+    length_includes_affixes: bool,
+    // --group: разделитель через каждые N символов случайной части, см. group.rs
+    group: Option<group::Grouping>,
+    // --group-counts-length: pw_length задан вместе с разделителями; после
+    // разбора он заменяется числом символов
+    group_counts_length: bool,
+    // Без явного значения main заполняет им экран, см. apply_terminal_defaults
     num_pw: usize,
+    // num_pw задан в командной строке, а не взят по умолчанию
+    num_pw_given: bool,
     capitalize: bool,
     no_capitalize: bool,
     numerals: bool,
     no_numerals: bool,
     symbols: bool,
-    remove_chars: Option<Vec<u8>>,
+    // --shell-safe: из символов только SHELL_SAFE_SYMBOLS
+    shell_safe: bool,
+    // --url-safe: только URL_SAFE, всегда в режиме -s
+    url_safe: bool,
+    // --alpha-only: только буквы; --digits-only: только цифры, всегда в режиме -s
+    alpha_only: bool,
+    digits_only: bool,
+    // --pin: числовые PIN-коды без слабых, см. pin.rs
+    pin: bool,
+    // --mobile-friendly: классы идут блоками, см. mobile.rs
+    mobile_friendly: bool,
+    // --no-shift: только то, что набирается без Shift
+    no_shift: bool,
+    // --layout-safe: только символы, одинаковые на QWERTY, QWERTZ и AZERTY
+    layout_safe: bool,
+    // --no-leading-digit и --no-leading-symbol: чем пароль не может начинаться
+    no_leading_digit: bool,
+    no_leading_symbol: bool,
+    // --no-edge-symbols: символы только внутри пароля, не первым и не последним
+    no_edge_symbols: bool,
+    // --no-repeat: два одинаковых символа подряд не допускаются
+    no_repeat: bool,
+    // --no-sequences: длина цепочек вроде "abc" и "321", с которой пароль
+    // генерируется заново
+    no_sequences: Option<usize>,
+    // --no-keyboard-walks: пароль с "qwer" или "1qaz" генерируется заново
+    no_keyboard_walks: bool,
+    // --no-dictionary-words: пароль со словом из стольких букв генерируется заново
+    no_dictionary_words: Option<usize>,
+    // --no-profanity: список корней, с которыми пароль генерируется заново
+    profanity: Option<profanity::Blocklist>,
+    // --profanity-file: корни, которые main добавляет к списку
+    profanity_file: Option<PathBuf>,
+    // --case: регистр букв меняется после всех требований, см. apply_case
+    case: Option<Case>,
+    // --homoglyph-strict: таблица похожих написаний, см. homoglyph.rs
+    homoglyphs: Option<homoglyph::Table>,
+    // --homoglyph-file: группы, которые main добавляет к таблице
+    homoglyph_file: Option<PathBuf>,
+    remove_chars: Option<Vec<char>>,
+    // --weights L:U:D:S: вес строчных, заглавных, цифр и символов в режиме -s
+    weights: Option<[usize; 4]>,
+    // --min-* и --max-*: сколько символов каждого класса допускается
+    class_counts: ClassCounts,
+    // --classes: точное число символов каждого класса, в сумме pw_length;
+    // class_counts тогда задает те же числа как минимум и максимум
+    classes: Option<[usize; 4]>,
+    // --template: шаблон вместо длины и классов
+    template: Option<template::Template>,
+    // --blocks: слова и группы цифр вместо длины и классов
+    blocks: Option<blocks::Spec>,
+    // --like: форма старого пароля; с "--like -" ее читает main из stdin
+    like: Option<like::Shape>,
+    like_stdin: bool,
+    // --balanced: классы поровну, см. generate_secure_password
+    balanced: bool,
+    // --require-chars: символы, которые обязательно есть в каждом пароле
+    require_chars: Option<Vec<char>>,
+    // --include-only или --charset-file: точный набор символов вместо классов
+    include_only: Option<Vec<char>>,
+    // --charset-file: файл, из которого main загружает include_only
+    charset_file: Option<PathBuf>,
+    // --charset: готовый алфавит из CHARSET_PRESETS
+    charset_preset: Option<(&'static str, &'static [u8])>,
+    // --preset: готовые настройки, см. preset.rs
+    preset: Option<Preset>,
     secure: bool,
     ambiguous: bool,
+    ambiguous_chars: Vec<char>,
     columns: bool,
+    // Явно указан -C или -1
+    columns_given: bool,
+    // --columns=N вместо подбора по ширине терминала
+    fixed_columns: Option<usize>,
+    // --per-line=N: ровно N паролей в строке, без учета ширины терминала
+    per_line: Option<usize>,
+    // Номер перед каждым паролем (--numbered)
+    numbered: bool,
+    // Ровно num_pw строк по паролю для скриптов (--porcelain)
+    porcelain: bool,
+    // Все пароли пакета разные (--unique)
+    unique: bool,
+    // Пакет по порядку (--sort)
+    sort: bool,
+    // Энтропия паролей в выводе (--show-entropy)
+    show_entropy: bool,
+    // -z: пароли разделяются байтом NUL вместо перевода строки
+    null: bool,
+    // --format: текст, один документ JSON или строка JSON на пароль (см.
+    // json.rs) или таблица CSV (см. csv.rs)
+    format: Format,
+    // --color: цвета классов в выводе в терминал, см. color.rs
+    color: ColorChoice,
+    // --csv-safe: апостроф перед паролем, который табличный редактор принял бы за формулу
+    csv_safe: bool,
+    // Фраза для запоминания рядом с каждым паролем (--mnemonic)
+    mnemonic: bool,
+    // Строка с паролем по буквам NATO после каждого пароля (--phonetic)
+    phonetic: bool,
+    // Строка с символами, которые легко спутать, после пароля (--annotate)
+    annotate: bool,
+    // QR-код пароля после него (--qr) или вместо него (--qr-only), см. qr.rs
+    qr: bool,
+    qr_only: bool,
+    // --qr-format; без него ansi
+    qr_format: Option<QrFormat>,
     no_vowels: bool,
+    // --memorable-model: чем строятся запоминаемые пароли без -v
+    memorable_model: MemorableModel,
+    // --syllable-file: файл, из которого main загружает syllables
+    syllable_file: Option<PathBuf>,
+    // --leet: сколько букв запоминаемого пароля заменить цифрами и символами
+    leet: Option<usize>,
+    // Слоги для запоминаемых паролей, уже без исключенных символов
+    syllables: Option<Vec<Vec<char>>>,
+    // --syllables: запоминаемый пароль из стольких слогов, pw_length не используется
+    syllable_count: Option<usize>,
+    // --words: парольные фразы из стольких слов вместо паролей, см. wordlist.rs
+    words: Option<usize>,
+    // --words-acrostic: первые буквы слов фразы, в нижнем регистре
+    acrostic: Option<Vec<char>>,
+    // --separator: что ставится между словами фразы
+    separator: Option<String>,
+    // --capitalize-words, --inject-digit, --inject-symbol: обработка фразы,
+    // см. wordlist::Style
+    capitalize_words: bool,
+    inject_digit: bool,
+    inject_symbol: bool,
+    // --wordlist: файл или "-" для stdin, из которого main загружает wordlist
+    wordlist_file: Option<PathBuf>,
+    // Слова для фраз вместо встроенного списка, уже без исключенных символов
+    wordlist: Option<Vec<String>>,
+    // --lang: язык встроенного списка слов
+    lang: Option<Lang>,
+    rng: RngChoice,
+    sha1: Option<Sha1Spec>,
+    fast_rng: bool,
+    seed: Option<u64>,
+    verbose: bool,
+    lock_memory: bool,
+    output: Option<PathBuf>,
+    force: bool,
+    encrypt_to: Option<Recipient>,
+    copy: bool,
+    pick: Option<usize>,
+    // Выбор одного пароля на терминале (-i)
+    interactive: bool,
+    clear_after: u64,
+    self_test: bool,
+    // Показать набор символов и энтропию без генерации (--dry-run)
+    dry_run: bool,
     help: bool,
 }
 
+// Границы числа символов по классам в порядке COUNTED_CLASSES
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ClassCounts {
+    min: [usize; 4],
+    max: [Option<usize>; 4],
+}
+
+// Параметры -H / --sha1=path[#seed]
+#[derive(Debug, Clone, PartialEq)]
+struct Sha1Spec {
+    path: PathBuf,
+    seed: String,
+}
+
+impl Sha1Spec {
+    fn parse(spec: &str) -> Self {
+        match spec.split_once('#') {
+            Some((path, seed)) => Self {
+                path: PathBuf::from(path),
+                seed: seed.to_string(),
+            },
+            None => Self {
+                path: PathBuf::from(spec),
+                seed: sha1::DEFAULT_SEED.to_string(),
+            },
+        }
+    }
+}
+
+// Значение --case
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Case {
+    Lower,
+    Upper,
+    Title,
+    Random,
+}
+
+impl Case {
+    const ALL: [Case; 4] = [Case::Lower, Case::Upper, Case::Title, Case::Random];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|case| case.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Case::Lower => "lower",
+            Case::Upper => "upper",
+            Case::Title => "title",
+            Case::Random => "random",
+        }
+    }
+}
+
+// Значение --memorable-model
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MemorableModel {
+    // Таблица элементов pwgen, см. phoneme.rs
+    Phoneme,
+    // Триграммы английских слов, см. markov.rs
+    Markov,
+}
+
+impl MemorableModel {
+    const ALL: [MemorableModel; 2] = [MemorableModel::Phoneme, MemorableModel::Markov];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|model| model.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            MemorableModel::Phoneme => "phoneme",
+            MemorableModel::Markov => "markov",
+        }
+    }
+}
+
+// Значение --format
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Jsonl,
+    Csv,
+}
+
+impl Format {
+    const ALL: [Format; 4] = [Format::Text, Format::Json, Format::Jsonl, Format::Csv];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Jsonl => "jsonl",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+// Значение --color
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    const ALL: [ColorChoice; 3] = [ColorChoice::Auto, ColorChoice::Always, ColorChoice::Never];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|choice| choice.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        }
+    }
+}
+
+// Набор для -B по умолчанию: буквы и цифры, а при -y еще и символы
+fn default_ambiguous_chars() -> Vec<char> {
+    AMBIGUOUS.iter().chain(AMBIGUOUS_SYMBOLS).map(|&c| c as char).collect()
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             pw_length: DEFAULT_LENGTH,
-            num_pw: DEFAULT_COUNT,
+            length_range: None,
+            bits: None,
+            prefix: None,
+            suffix: None,
+            length_includes_affixes: false,
+            group: None,
+            group_counts_length: false,
+            num_pw: 1,
+            num_pw_given: false,
             capitalize: true,
             no_capitalize: false,
             numerals: true,
             no_numerals: false,
             symbols: false,
+            shell_safe: false,
+            url_safe: false,
+            alpha_only: false,
+            digits_only: false,
+            pin: false,
+            mobile_friendly: false,
+            no_shift: false,
+            layout_safe: false,
+            no_leading_digit: false,
+            no_leading_symbol: false,
+            no_edge_symbols: false,
+            no_repeat: false,
+            no_sequences: None,
+            no_keyboard_walks: false,
+            no_dictionary_words: None,
+            profanity: None,
+            profanity_file: None,
+            case: None,
+            homoglyphs: None,
+            homoglyph_file: None,
             remove_chars: None,
+            weights: None,
+            class_counts: ClassCounts::default(),
+            classes: None,
+            template: None,
+            like: None,
+            like_stdin: false,
+            blocks: None,
+            balanced: false,
+            require_chars: None,
+            include_only: None,
+            charset_file: None,
+            charset_preset: None,
+            preset: None,
             secure: false,
             ambiguous: false,
+            ambiguous_chars: default_ambiguous_chars(),
             columns: true,
+            columns_given: false,
+            fixed_columns: None,
+            per_line: None,
+            numbered: false,
+            porcelain: false,
+            unique: false,
+            sort: false,
+            show_entropy: false,
+            null: false,
+            format: Format::Text,
+            color: ColorChoice::Auto,
+            csv_safe: false,
+            mnemonic: false,
+            phonetic: false,
+            annotate: false,
+            qr: false,
+            qr_only: false,
+            qr_format: None,
             no_vowels: false,
+            memorable_model: MemorableModel::Phoneme,
+            syllable_file: None,
+            leet: None,
+            syllables: None,
+            syllable_count: None,
+            words: None,
+            acrostic: None,
+            separator: None,
+            capitalize_words: false,
+            inject_digit: false,
+            inject_symbol: false,
+            wordlist_file: None,
+            wordlist: None,
+            lang: None,
+            rng: RngChoice::Auto,
+            sha1: None,
+            fast_rng: false,
+            seed: None,
+            verbose: false,
+            lock_memory: false,
+            output: None,
+            force: false,
+            encrypt_to: None,
+            copy: false,
+            pick: None,
+            interactive: false,
+            clear_after: clipboard::DEFAULT_CLEAR_AFTER,
+            self_test: false,
+            dry_run: false,
             help: false,
         }
     }
 }
 
-fn main() -> io::Result<()> {
-    let config = parse_args();
+fn main() {
+    // env::args() паникует на аргументах, которые не являются Unicode
+    // (например, непарные суррогаты в Windows), поэтому проверяем сами
+    let mut args: Vec<String> = match env::args_os().map(|arg| arg.into_string()).collect() {
+        Ok(args) => args,
+        Err(arg) => {
+            eprintln!("Error: {}", CliError::NotUnicode(arg.to_string_lossy().into_owned()));
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+    // Фоновая очистка буфера обмена, запущенная предыдущим вызовом --copy
+    if args.get(1).map(String::as_str) == Some(clipboard::WORKER_ARG) {
+        std::process::exit(clipboard::run_worker(&args[2..]));
+    }
 
-    if config.help {
-        print_help();
-        return Ok(());
+    let command = take_command(&mut args);
+    if command == Command::Passphrase {
+        run_passphrase(&args[1..]);
+        return;
+    }
+    if command == Command::Completions {
+        run_completions(&args[1..]);
+        return;
+    }
+    if command == Command::Mangen {
+        run_mangen(&args[1..]);
+        return;
+    }
+
+    let env_opts = match env::var_os("PWGEN_OPTS").map(|opts| opts.into_string()) {
+        Some(Ok(opts)) => Some(opts),
+        Some(Err(opts)) => {
+            let e = CliError::NotUnicode(opts.to_string_lossy().into_owned());
+            eprintln!("Error: {}", CliError::InEnv(Box::new(e)));
+            std::process::exit(EXIT_USAGE);
+        }
+        None => None,
+    };
+
+    // Файл настроек читается до разбора: флаги применяются поверх него
+    let no_config = args
+        .iter()
+        .map(String::as_str)
+        .chain(env_opts.iter().flat_map(|opts| opts.split_whitespace()))
+        .any(|arg| arg == "--no-config");
+    let base = if no_config {
+        Config::default()
+    } else {
+        match config_file::default_path().map(|path| config_file::load(&path)) {
+            Some(Ok(config)) => config,
+            // Ошибка в самом файле - такая же ошибка настроек, как в флагах
+            Some(Err(e)) if e.kind() == io::ErrorKind::InvalidData => exit_with_error(RunError::Settings(e.to_string())),
+            Some(Err(e)) => exit_with_error(e),
+            None => Config::default(),
+        }
+    };
+
+    let mut config = match parse_args_with_env(base, args, env_opts.as_deref()) {
+        Ok(config) => config,
+        Err(e) => usage_error(e, command),
+    };
+
+    // Набор из --charset-file нужен и для генерации, и для check
+    if let Some(path) = &config.charset_file
+        && !config.help
+    {
+        match load_charset_file(path) {
+            Ok(chars) => config.include_only = Some(chars),
+            Err(e) => exit_with_error(e),
+        }
+        if let Err(e) = check_required_chars(&config) {
+            usage_error(e, command);
+        }
     }
 
-    let passwords = generate_passwords(&config)?;
-    print_passwords(&passwords, config.columns);
+    if let Some(path) = &config.homoglyph_file
+        && let Some(table) = &mut config.homoglyphs
+        && !config.help
+    {
+        match homoglyph::load(path) {
+            Ok(extra) => table.extend(extra),
+            Err(e) => exit_with_error(e),
+        }
+    }
 
-    Ok(())
-}
+    if let Some(path) = &config.profanity_file
+        && let Some(list) = &mut config.profanity
+        && !config.help
+    {
+        match profanity::load(path) {
+            Ok(extra) => list.extend(extra),
+            Err(e) => exit_with_error(e),
+        }
+    }
 
-fn parse_args() -> Config {
-    let args: Vec<String> = env::args().collect();
-    parse_args_from_vec(args)
-}
+    // Слоги фильтруются по -r и -B при загрузке, так что нужны готовые флаги
+    if let Some(path) = &config.syllable_file
+        && !config.help
+    {
+        match syllables::load(path, &config) {
+            Ok(list) => config.syllables = Some(list),
+            Err(e) => exit_with_error(e),
+        }
+    }
 
-fn parse_args_from_vec(args: Vec<String>) -> Config {
-    let mut config = Config::default();
-    let mut positional_args = Vec::new();
-    let mut i = 1;
-
-    while i < args.len() {
-        match args[i].as_str() {
-            "-c" | "--capitalize" => config.capitalize = true,
-            "-A" | "--no-capitalize" => config.no_capitalize = true,
-            "-n" | "--numerals" => config.numerals = true,
-            "-0" | "--no-numerals" => config.no_numerals = true,
-            "-y" | "--symbols" => config.symbols = true,
-            "-s" | "--secure" => config.secure = true,
-            "-B" | "--ambiguous" => config.ambiguous = true,
-            "-C" => config.columns = true,
-            "-1" => config.columns = false,
-            "-v" | "--no-vowels" => config.no_vowels = true,
-            "-h" | "--help" => config.help = true,
-            arg if arg.starts_with("-r") || arg.starts_with("--remove-chars") => {
-                let chars = if arg.starts_with("-r") && arg.len() > 2 {
-                    arg[2..].as_bytes().to_vec()
-                } else if let Some(equal_pos) = arg.find('=') {
-                    arg[equal_pos + 1..].as_bytes().to_vec()
-                } else if i + 1 < args.len() {
-                    i += 1;
-                    args[i].as_bytes().to_vec()
-                } else {
-                    eprintln!("Error: Missing characters to remove");
-                    std::process::exit(1);
-                };
-                config.remove_chars = Some(chars);
-            }
-            arg if !arg.starts_with('-') => {
-                positional_args.push(arg);
-            }
-            _ => {
-                eprintln!("Unknown option: {}", args[i]);
-                std::process::exit(1);
+    // Список слов тоже фильтруется по -r и -B. check читает stdin сам и
+    // фраз не строит.
+    if let Some(path) = &config.wordlist_file
+        && !config.help
+        && command != Command::Check
+    {
+        match wordlist::load(path, &config) {
+            Ok(list) => {
+                if list.len() < wordlist::SMALL_LIST {
+                    eprintln!(
+                        "WARNING: the wordlist {} has only {} words, {:.1} bits per word; a list of 7776 words gives 12.9",
+                        path.display(),
+                        list.len(),
+                        wordlist::bits_per_word(&list)
+                    );
+                }
+                config.wordlist = Some(list);
             }
+            Err(e) => exit_with_error(e),
         }
-        i += 1;
     }
 
-    // Обработка позиционных аргументов
-    match positional_args.len() {
-        0 => {},
-        1 => {
-            if let Ok(n) = positional_args[0].parse() {
-                config.pw_length = n;
+    // Старый пароль для --like -; check читает stdin сам
+    if config.like_stdin && !config.help && command != Command::Check {
+        match like::read(io::stdin().lock()) {
+            Ok(shape) => {
+                config.pw_length = shape.len();
+                config.like = Some(shape);
             }
+            Err(e) => exit_with_error(e),
         }
-        2 => {
-            if let Ok(n) = positional_args[0].parse() {
-                config.pw_length = n;
+    }
+
+    // Длина по --bits считается по готовому набору: после -B, -r и файлов
+    if let Some(bits) = config.bits
+        && !config.help
+    {
+        match bits::length_for(&config, bits) {
+            Ok((count, got)) if config.words.is_some() => {
+                config.words = Some(count);
+                eprintln!("Words: {} words, {:.1} bits per passphrase (--bits {})", count, got, bits);
             }
-            if let Ok(n) = positional_args[1].parse() {
-                config.num_pw = n;
+            Ok((length, got)) => {
+                config.pw_length = length;
+                eprintln!("Length: {} characters, {:.1} bits per password (--bits {})", length, got, bits);
             }
-        }
-        _ => {
-            eprintln!("Too many arguments");
-            std::process::exit(1);
+            Err(e) => exit_with_error(e),
         }
     }
 
-    config
-}
-
-fn generate_passwords(config: &Config) -> io::Result<Vec<String>> {
-    let mut passwords = Vec::with_capacity(config.num_pw);
-    let mut rng = File::open("/dev/urandom")?;
-
-    for _ in 0..config.num_pw {
-        let password = if config.secure {
-            generate_secure_password(config.pw_length, config, &mut rng)?
-        } else {
-            generate_memorable_password(config.pw_length, config, &mut rng)?
-        };
-        passwords.push(password);
+    if command == Command::Check {
+        run_check(&config);
+        return;
+    }
+    if config.help {
+        if let Err(e) = print_help() {
+            exit_with_error(e);
+        }
+        return;
     }
 
-    Ok(passwords)
-}
+    apply_terminal_defaults(&mut config, &term::Stdout);
+    signals::install();
 
-fn generate_secure_password<R: Read>(length: usize, config: &Config, rng: &mut R) -> io::Result<String> {
-    let charset = build_charset(config);
-    if charset.is_empty() {
-        return Ok("a".repeat(length)); // fallback
+    if config.dry_run {
+        if let Err(e) = dry_run::run(&config, &mut io::stdout().lock()) {
+            exit_with_error(e);
+        }
+        return;
     }
 
-    let mut password = String::with_capacity(length);
-
-    for _ in 0..length {
-        let mut buf = [0u8; 1];
-        rng.read_exact(&mut buf)?;
-        let idx = buf[0] as usize % charset.len();
-        password.push(charset[idx] as char);
+    if config.self_test {
+        match selftest::run(&config) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(EXIT_FAILURE),
+            Err(e) => exit_with_error(e),
+        }
     }
 
-    Ok(password)
-}
+    if config.seed.is_some() {
+        eprintln!("WARNING: --seed makes the output reproducible by anyone who knows the seed.");
+        eprintln!("WARNING: These passwords are NOT secure; use them only for tests and examples.");
+    }
+    warn_skipped_classes(&config);
 
-fn generate_memorable_password<R: Read>(length: usize, config: &Config, rng: &mut R) -> io::Result<String> {
-    // Если установлен флаг no_vowels, используем безопасную генерацию без шаблона
-    if config.no_vowels {
-        return generate_secure_password(length, config, rng);
+    if config.interactive {
+        match interactive::run(&config) {
+            Ok(Some(password)) => {
+                if let Err(e) = print_passwords(&password, Layout::columns(1)) {
+                    exit_with_error(e);
+                }
+                return;
+            }
+            Ok(None) => std::process::exit(EXIT_FAILURE),
+            Err(e) => exit_with_error(e),
+        }
     }
 
-    let mut password = String::with_capacity(length);
+    if let Some(recipient) = &config.encrypt_to
+        && let Err(e) = recipient.check()
+    {
+        exit_with_error(e);
+    }
 
-    // Выбираем наборы символов в зависимости от опции --no-capitalize
-    let (consonants, vowels) = if config.no_capitalize {
-        (CONSONANTS_LOWER, VOWELS_LOWER)
-    } else {
-        (CONSONANTS, VOWELS)
+    // Файл открывается до генерации, чтобы не тратить время на пароли,
+    // которые некуда записать, но после проверки настроек: из-за них
+    // остался бы пустой файл, и следующий запуск без --force упал бы
+    if (config.output.is_some() || config.qr_format.is_some())
+        && let Err(e) = check_settings(&config)
+    {
+        exit_with_error(e);
+    }
+    // Новые файлы, которые надо удалить, если пароли так и не появятся
+    let mut created: Vec<&Path> = Vec::new();
+    let output = match &config.output {
+        Some(path) => {
+            if !path.exists() {
+                created.push(path);
+            }
+            match open_output(path, config.force) {
+                Ok(file) => Some((file, path)),
+                Err(e) => exit_with_error(e),
+            }
+        }
+        None => None,
     };
-
-    // Для запоминаемых паролей используем шаблон согласная-гласная
-    for i in 0..length {
-        let char_set = if i % 2 == 0 {
-            // Четные позиции - согласные
-            consonants
-        } else {
-            // Нечетные позиции - гласные
-            vowels
-        };
-
-        let mut buf = [0u8; 1];
-        let mut attempts = 0;
-        loop {
-            rng.read_exact(&mut buf)?;
-            let idx = buf[0] as usize % char_set.len();
-            let candidate = char_set[idx];
-
-            // Проверка на удаляемые символы
-            if let Some(remove_chars) = &config.remove_chars {
-                if remove_chars.contains(&candidate) {
-                    attempts += 1;
-                    if attempts > 100 {
-                        // Fallback: используем любой символ после множества попыток
-                        password.push(candidate as char);
-                        break;
-                    }
-                    continue;
+    // PNG для --qr-format png:PATH - тоже заранее и тоже с правами 0600
+    let qr_png = match &config.qr_format {
+        Some(QrFormat::Png(path)) => {
+            if !path.exists() {
+                created.push(path);
+            }
+            match open_output(path, config.force) {
+                Ok(file) => Some((file, path)),
+                Err(e) => {
+                    remove_created(&created);
+                    exit_with_error(e)
                 }
             }
-
-            // Проверка на неоднозначные символы
-            if config.ambiguous && AMBIGUOUS.contains(&candidate) {
-                attempts += 1;
-                if attempts > 100 {
-                    password.push(candidate as char);
-                    break;
+        }
+        _ => None,
+    };
+    let clipboard = if config.copy {
+        match Clipboard::detect() {
+            Ok(clipboard) => {
+                if config.verbose {
+                    eprintln!("Clipboard: {}", clipboard.name());
                 }
-                continue;
+                Some(clipboard)
             }
+            Err(e) => {
+                remove_created(&created);
+                exit_with_error(e)
+            }
+        }
+    } else {
+        None
+    };
 
-            password.push(candidate as char);
-            break;
+    // Строки JSONL печатаются по мере генерации, без пакета в памяти
+    if config.format == Format::Jsonl {
+        if let Err(e) = print_jsonl(&config) {
+            exit_with_error(e);
         }
+        return;
     }
 
-    // Применяем требования к цифрам и символам (но не к заглавным буквам, если --no-capitalize)
-    let password_bytes = password.into_bytes();
-    let password = apply_requirements(password_bytes, config, rng)?;
-    Ok(password)
+    let passwords = match generate_passwords(&config) {
+        Ok(passwords) => passwords,
+        Err(e) => {
+            remove_created(&created);
+            exit_with_error(e)
+        }
+    };
+    let result = match (output, clipboard) {
+        (None, None) if config.format == Format::Json => print_json(&passwords, &config),
+        (None, None) if config.format == Format::Csv => print_csv(&passwords, &config),
+        (None, None) if config.mnemonic => print_mnemonics(&passwords, &config),
+        (Some((file, path)), _) => {
+            let layout = Layout::new(&config, None);
+            match &config.encrypt_to {
+                Some(recipient) => encrypt_passwords(recipient, file, path, &passwords, layout),
+                None => save_passwords(file, path, &passwords, layout),
+            }
+        }
+        (None, Some(clipboard)) => copy_password(&clipboard, &passwords, &config),
+        (None, None) => {
+            let width = term::Stdout.size().map(|size| size.width);
+            let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+            let layout = Layout {
+                color: color::enabled(config.color, io::stdout().is_terminal(), no_color),
+                ..Layout::new(&config, width)
+            };
+            let columns = layout.columns;
+            if let Some(requested) = config.fixed_columns
+                && config.columns
+                && config.pick.is_none()
+                && !config.porcelain
+                && requested > columns
+            {
+                eprintln!(
+                    "Note: {} columns of {} characters do not fit in a {}-column terminal; using {}",
+                    requested,
+                    password_width(&config),
+                    width.unwrap_or(term::DEFAULT_WIDTH),
+                    columns
+                );
+            }
+            let printed = if config.qr_only {
+                Ok(())
+            } else if config.show_entropy {
+                print_with_entropy(&passwords, &config, layout)
+            } else {
+                print_passwords(&passwords, layout)
+            };
+            match printed {
+                Ok(()) if config.qr => print_qr(&passwords, &config, qr_png),
+                printed => printed,
+            }
+        }
+    };
+    // process::exit не вызывает деструкторы, поэтому пароли затираем заранее
+    drop(passwords);
+    if let Err(e) = result {
+        exit_with_error(e);
+    }
 }
 
-fn apply_requirements<R: Read>(password: Vec<u8>, config: &Config, rng: &mut R) -> io::Result<String> {
-    let mut result = password;
-    let mut buf = [0u8; 1];
-
-    // Проверка и добавление заглавной буквы если требуется и разрешено
-    if config.capitalize && !config.no_capitalize && !result.iter().any(|&c| c.is_ascii_uppercase()) {
-        let uppercase_filtered: Vec<u8> = UPPERCASE.iter()
-            .filter(|&&c| {
-                if config.ambiguous && AMBIGUOUS.contains(&c) {
-                    return false;
-                }
-                if let Some(remove_chars) = &config.remove_chars {
-                    if remove_chars.contains(&c) {
-                        return false;
-                    }
-                }
-                true
-            })
-            .cloned()
-            .collect();
+// Подкоманды. Без имени команды работает generate, так что прежние
+// вызовы `pwgen [ OPTIONS ] [ pw_length ] [ num_pw ]` не меняются.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Generate,
+    Passphrase,
+    Check,
+    Completions,
+    Mangen,
+}
 
-        if !uppercase_filtered.is_empty() {
-            rng.read_exact(&mut buf)?;
-            let upper_idx = buf[0] as usize % uppercase_filtered.len();
-            let upper_char = uppercase_filtered[upper_idx];
+// Имена и описания команд для справки и дополнения в shell
+const COMMANDS: &[(&str, &str)] = &[
+    ("generate", "Generate passwords (the default)"),
+    ("passphrase", "Generate passphrases of words from a wordlist"),
+    ("check", "Check passwords from stdin against the options' policy"),
+    ("completions", "Print a shell completion script"),
+    ("mangen", "Print the manual page in roff format"),
+];
 
-            rng.read_exact(&mut buf)?;
-            let pos = buf[0] as usize % result.len();
-            result[pos] = upper_char;
+impl Command {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Generate => "generate",
+            Self::Passphrase => "passphrase",
+            Self::Check => "check",
+            Self::Completions => "completions",
+            Self::Mangen => "mangen",
         }
     }
+}
 
-    // Проверка и добавление цифры если требуется
-    if config.numerals && !config.no_numerals {
-        let has_numeral = result.iter().any(|&c| c.is_ascii_digit());
-        if !has_numeral {
-            let numerals_filtered: Vec<u8> = NUMERALS.iter()
-                .filter(|&&c| {
-                    if config.ambiguous && AMBIGUOUS.contains(&c) {
-                        return false;
-                    }
-                    if let Some(remove_chars) = &config.remove_chars {
-                        if remove_chars.contains(&c) {
-                            return false;
-                        }
-                    }
-                    true
-                })
-                .cloned()
-                .collect();
+// Убирает имя команды из аргументов
+fn take_command(args: &mut Vec<String>) -> Command {
+    let command = match args.get(1).map(String::as_str) {
+        Some("generate") => Command::Generate,
+        Some("passphrase") => Command::Passphrase,
+        Some("check") => Command::Check,
+        Some("completions") => Command::Completions,
+        Some("mangen") => Command::Mangen,
+        _ => return Command::Generate,
+    };
+    args.remove(1);
+    command
+}
 
-            if !numerals_filtered.is_empty() {
-                rng.read_exact(&mut buf)?;
-                let numeral_idx = buf[0] as usize % numerals_filtered.len();
-                let numeral = numerals_filtered[numeral_idx];
+fn usage_error(e: CliError, command: Command) -> ! {
+    eprintln!("Error: {}", e);
+    match command {
+        Command::Generate => eprintln!("Try 'pwgen --help' for more information."),
+        command => eprintln!("Try 'pwgen {} --help' for more information.", command.name()),
+    }
+    std::process::exit(EXIT_USAGE);
+}
 
-                rng.read_exact(&mut buf)?;
-                let pos = buf[0] as usize % result.len();
-                result[pos] = numeral;
-            }
+fn run_passphrase(args: &[String]) {
+    let options = match passphrase::parse_args(args) {
+        Ok(options) => options,
+        Err(e) => usage_error(e, Command::Passphrase),
+    };
+    if options.help {
+        if let Err(e) = passphrase::print_help() {
+            exit_with_error(e);
         }
+        return;
     }
+    signals::install();
+    if options.seed.is_some() {
+        eprintln!("WARNING: --seed makes the output reproducible by anyone who knows the seed.");
+    }
+    let config = match passphrase::config(&options) {
+        Ok(config) => config,
+        Err(e) => exit_with_error(e),
+    };
+    // Вывод тот же, что у `pwgen --words`, по фразе на строку
+    if config.format == Format::Jsonl {
+        if let Err(e) = print_jsonl(&config) {
+            exit_with_error(e);
+        }
+        return;
+    }
+    let phrases = match generate_passwords(&config) {
+        Ok(phrases) => phrases,
+        Err(e) => exit_with_error(e),
+    };
+    let result = match config.format {
+        Format::Json => print_json(&phrases, &config),
+        Format::Csv => print_csv(&phrases, &config),
+        _ if config.show_entropy => print_with_entropy(&phrases, &config, Layout::columns(1)),
+        _ => print_passwords(&phrases, Layout::columns(1)),
+    };
+    drop(phrases);
+    if let Err(e) = result {
+        exit_with_error(e);
+    }
+}
 
-    // Проверка и добавление символа если требуется
-    if config.symbols {
-        let has_symbol = result.iter().any(|&c| SYMBOLS.contains(&c));
-        if !has_symbol {
-            let symbols_filtered: Vec<u8> = SYMBOLS.iter()
-                .filter(|&&c| {
-                    if let Some(remove_chars) = &config.remove_chars {
-                        if remove_chars.contains(&c) {
-                            return false;
-                        }
-                    }
-                    true
-                })
-                .cloned()
-                .collect();
-
-            if !symbols_filtered.is_empty() {
-                rng.read_exact(&mut buf)?;
-                let symbol_idx = buf[0] as usize % symbols_filtered.len();
-                let symbol = symbols_filtered[symbol_idx];
-
-                rng.read_exact(&mut buf)?;
-                let pos = buf[0] as usize % result.len();
-                result[pos] = symbol;
+fn run_completions(args: &[String]) {
+    match completions::parse_args(args) {
+        Ok(Some(shell)) => {
+            let mut stdout = io::stdout().lock();
+            if let Err(e) = stdout.write_all(completions::script(shell).as_bytes()) {
+                exit_with_error(e);
+            }
+        }
+        Ok(None) => {
+            if let Err(e) = completions::print_help() {
+                exit_with_error(e);
             }
         }
+        Err(e) => usage_error(e, Command::Completions),
     }
-
-    Ok(String::from_utf8(result).unwrap())
 }
 
-fn build_charset(config: &Config) -> Vec<u8> {
-    let mut charset = Vec::new();
-
-    // Строчные буквы всегда включены
-    charset.extend_from_slice(LOWERCASE);
-
-    // Заглавные буквы
-    if config.capitalize && !config.no_capitalize {
-        charset.extend_from_slice(UPPERCASE);
+fn run_mangen(args: &[String]) {
+    match man::parse_args(args) {
+        Ok(true) => {
+            if let Err(e) = man::print_help() {
+                exit_with_error(e);
+            }
+        }
+        Ok(false) => {
+            let mut stdout = io::stdout().lock();
+            if let Err(e) = stdout.write_all(man::render().as_bytes()) {
+                exit_with_error(e);
+            }
+        }
+        Err(e) => usage_error(e, Command::Mangen),
     }
+}
 
-    // Цифры
-    if config.numerals && !config.no_numerals {
-        charset.extend_from_slice(NUMERALS);
+fn run_check(config: &Config) {
+    if config.help {
+        if let Err(e) = check::print_help() {
+            exit_with_error(e);
+        }
+        return;
     }
-
-    // Символы
-    if config.symbols {
-        charset.extend_from_slice(SYMBOLS);
+    if config.num_pw_given {
+        let e = CliError::ConflictingFlags("check takes no num_pw argument".to_string());
+        usage_error(e, Command::Check);
     }
-
-    // Удаляем неоднозначные символы если требуется
-    if config.ambiguous {
-        charset.retain(|&c| !AMBIGUOUS.contains(&c));
+    if config.wordlist_file.as_deref() == Some(Path::new("-")) {
+        let e = CliError::ConflictingFlags(
+            "--wordlist - cannot be used with check, which reads passwords from stdin".to_string(),
+        );
+        usage_error(e, Command::Check);
     }
+    if config.like_stdin {
+        let e = CliError::ConflictingFlags(
+            "--like - cannot be used with check, which reads passwords from stdin".to_string(),
+        );
+        usage_error(e, Command::Check);
+    }
+    if config.no_dictionary_words.is_some() && dictionary::TEXT.is_none() {
+        exit_with_error(RunError::Settings(dictionary::missing()));
+    }
+    signals::install();
+    match check::run(config, io::stdin().lock(), &mut io::stdout().lock()) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(EXIT_FAILURE),
+        Err(e) => exit_with_error(e),
+    }
+}
 
-    // Удаляем гласные если требуется
-    if config.no_vowels {
-        charset.retain(|&c| !VOWELS.contains(&c));
+// Прерывание сигналом завершает работу молча, с кодом 128 + номер сигнала
+fn exit_with_error<E: Into<RunError>>(e: E) -> ! {
+    if let Some(code) = signals::exit_code() {
+        std::process::exit(code);
     }
+    let e = e.into();
+    // `pwgen | head -1`: читатель получил, что хотел, и закрыл канал
+    if let RunError::Io(e) = &e
+        && e.kind() == io::ErrorKind::BrokenPipe
+    {
+        std::process::exit(0);
+    }
+    eprintln!("Error: {}", e);
+    std::process::exit(e.exit_code());
+}
 
-    // Удаляем пользовательские символы
-    if let Some(remove_chars) = &config.remove_chars {
-        charset.retain(|&c| !remove_chars.contains(&c));
+// Ошибки во время работы. По коду выхода скрипт отличает невыполнимую
+// просьбу от сбоя окружения.
+#[derive(Debug)]
+enum RunError {
+    // Из таких настроек пароль не составить; это видно до генерации,
+    // поэтому код тот же, что у ошибок командной строки (2)
+    Settings(String),
+    // Генератор не смог выполнить требования к паролю (3)
+    Generation(String),
+    // Источник случайности, файлы, буфер обмена, терминал (4)
+    Io(io::Error),
+}
+
+impl RunError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Self::Settings(_) => EXIT_USAGE,
+            Self::Generation(_) => EXIT_GENERATION,
+            Self::Io(_) => EXIT_IO,
+        }
     }
+}
 
-    charset
+impl From<io::Error> for RunError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
 }
 
-fn print_passwords(passwords: &[String], columns: bool) {
-    if !columns || passwords.len() <= COLUMNS {
-        for password in passwords {
-            println!("{}", password);
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Settings(message) | Self::Generation(message) => write!(f, "{}", message),
+            Self::Io(e) => write!(f, "{}", e),
         }
-        return;
     }
+}
 
-    let rows = (passwords.len() + COLUMNS - 1) / COLUMNS;
-    let mut row_buffers = vec![Vec::new(); rows];
+// Ошибки разбора командной строки; main печатает их с подсказкой и
+// завершается с кодом 2
+#[derive(Debug, Clone, PartialEq)]
+enum CliError {
+    UnknownOption {
+        option: String,
+        // Похожая опция из таблицы, если аргумент - вероятно, опечатка
+        suggestion: Option<&'static str>,
+    },
+    // Опция pwgen, которой нет у подкоманды
+    UnsupportedOption {
+        option: String,
+        command: &'static str,
+    },
+    MissingValue(&'static str),
+    InvalidNumber {
+        what: &'static str,
+        value: String,
+        max: Option<usize>,
+    },
+    InvalidValue {
+        option: &'static str,
+        value: String,
+        expected: String,
+    },
+    TooManyArguments,
+    ConflictingFlags(String),
+    NotUnicode(String),
+    // Ошибка в словах из PWGEN_OPTS
+    InEnv(Box<CliError>),
+}
 
-    for (i, password) in passwords.iter().enumerate() {
-        row_buffers[i % rows].push(password.as_str());
+impl CliError {
+    // Подсказка ищется только в таблице той команды, которую разбирают
+    fn unknown_option(table: &'static [OptionSpec], option: &str) -> Self {
+        Self::UnknownOption {
+            option: option.to_string(),
+            suggestion: options::suggest(table, option),
+        }
     }
 
-    // Находим максимальную ширину для каждого столбца
-    let mut max_widths = vec![0; COLUMNS];
-    for row in &row_buffers {
-        for (col, &item) in row.iter().enumerate() {
-            if item.len() > max_widths[col] {
-                max_widths[col] = item.len();
+    // Ошибка разбора подкоманды: опция, которая есть только у самой pwgen,
+    // - не опечатка, и подсказка по таблице подкоманды ей не нужна
+    fn in_command(self, command: &'static str) -> Self {
+        match self {
+            Self::UnknownOption { option, .. } if options::is_known(&option) => {
+                let option = option.split_once('=').map_or(option.as_str(), |(name, _)| name).to_string();
+                Self::UnsupportedOption { option, command }
             }
+            e => e,
         }
     }
 
-    for row in row_buffers {
-        for (col, item) in row.iter().enumerate() {
-            if col > 0 {
-                print!(" ");
+    fn invalid_number(what: &'static str, value: &str) -> Self {
+        Self::InvalidNumber {
+            what,
+            value: value.to_string(),
+            max: None,
+        }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnknownOption { option, suggestion: None } => write!(f, "unknown option {}", option),
+            Self::UnknownOption { option, suggestion: Some(suggestion) } => {
+                write!(f, "unknown option {} (did you mean {}?)", option, suggestion)
             }
-            print!("{:<width$}", item, width = max_widths[col]);
+            Self::UnsupportedOption { option, command } => {
+                write!(f, "{} is not supported by pwgen {}", option, command)
+            }
+            Self::MissingValue(option) => write!(f, "missing value for {}", option),
+            Self::InvalidNumber { what, value, max: Some(max) } => {
+                write!(f, "invalid {} '{}' (expected a number from 1 to {})", what, value, max)
+            }
+            Self::InvalidNumber { what, value, max: None } => {
+                write!(f, "invalid {} '{}'", what, value)
+            }
+            Self::InvalidValue { option, value, expected } => {
+                write!(f, "invalid {} value '{}' ({})", option, value, expected)
+            }
+            Self::TooManyArguments => write!(f, "too many arguments"),
+            Self::ConflictingFlags(message) => write!(f, "{}", message),
+            Self::NotUnicode(arg) => write!(f, "argument '{}' is not valid Unicode", arg),
+            Self::InEnv(e) => write!(f, "in PWGEN_OPTS: {}", e),
         }
-        println!();
     }
 }
 
-fn print_help() {
-    println!("Usage: pwgen [ OPTIONS ] [ pw_length ] [ num_pw ]");
-    println!();
-    println!("Options supported by pwgen:");
-    println!("  -c or --capitalize");
-    println!("    Include at least one capital letter in the password");
-    println!("  -A or --no-capitalize");
-    println!("    Don't include capital letters in the password");
-    println!("  -n or --numerals");
-    println!("    Include at least one number in the password");
-    println!("  -0 or --no-numerals");
-    println!("    Don't include numbers in the password");
-    println!("  -y or --symbols");
-    println!("    Include at least one special symbol in the password");
-    println!("  -r <chars> or --remove-chars=<chars>");
-    println!("    Remove characters from the set of characters to generate passwords");
-    println!("  -s or --secure");
-    println!("    Generate completely random passwords");
-    println!("  -B or --ambiguous");
-    println!("    Don't include ambiguous characters in the password");
-    println!("  -h or --help");
-    println!("    Print a help message");
-    println!("  -C");
-    println!("    Print the generated passwords in columns");
-    println!("  -1");
-    println!("    Don't print the generated passwords in columns");
-    println!("  -v or --no-vowels");
-    println!("    Do not use any vowels so as to avoid accidental nasty words");
+// Разбирает аргументы поверх base - значений по умолчанию или файла настроек.
+// Как GZIP или LESS: PWGEN_OPTS="-s -B 24" задает флаги по умолчанию.
+// Они разбираются отдельным слоем перед командной строкой, поэтому ее
+// флаги и позиционные аргументы важнее, а не конфликтуют с ними.
+fn parse_args_with_env(base: Config, args: Vec<String>, env: Option<&str>) -> Result<Config, CliError> {
+    let mut config = base;
+    if let Some(opts) = env {
+        let words = split_words(opts).map_err(|expected| CliError::InvalidValue {
+            option: "PWGEN_OPTS",
+            value: opts.to_string(),
+            expected,
+        })?;
+        let mut env_args = vec![args.first().cloned().unwrap_or_default()];
+        env_args.extend(words);
+        parse_layer(&mut config, env_args).map_err(|e| CliError::InEnv(Box::new(e)))?;
+    }
+    parse_layer(&mut config, args)?;
+    check_combinations(&mut config)?;
+    Ok(config)
 }
 
-// Тесты
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Cursor;
+// Разбиение строки на слова по правилам sh: пробелы разделяют слова,
+// в '...' все буквально, в "..." и вне кавычек \ экранирует символ
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unbalanced ' quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unbalanced \" quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unbalanced \" quote".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_string()),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
 
-    // Вспомогательная функция для создания конфигурации для тестов
-    fn test_config() -> Config {
-        Config {
+// Флаги одного слоя (PWGEN_OPTS или командной строки) поверх config
+fn parse_layer(config: &mut Config, args: Vec<String>) -> Result<(), CliError> {
+    let mut positional_args = Vec::new();
+    // Флаги, заданные явно: значения по умолчанию не отличить от них по Config
+    let mut seen: Vec<&str> = Vec::new();
+    // Длина и количество из именованных опций вместе с тем, как они заданы
+    let mut length_option: Option<(&str, usize)> = None;
+    let mut count_option: Option<(&str, usize)> = None;
+    let mut length_range: Option<(usize, usize)> = None;
+    // --bits этого слоя: длину подберет main
+    let mut target_bits: Option<usize> = None;
+    let mut syllable_count: Option<usize> = None;
+    // Повторные --include-only в одном слое объединяются, следующий слой их заменяет
+    let mut include_only: Option<Vec<char>> = None;
+    // --classes этого слоя: длину задает сумма его чисел
+    let mut classes: Option<[usize; 4]> = None;
+    // --preset этого слоя: без длины в том же слое действует его длина
+    let mut preset: Option<Preset> = None;
+    // --pin этого слоя: без длины в том же слое коды из pin::DEFAULT_LENGTH цифр
+    let mut pin_mode = false;
+    // --template этого слоя: длину задает шаблон
+    let mut template: Option<template::Template> = None;
+    // --blocks этого слоя: длину задают блоки
+    let mut block_spec: Option<blocks::Spec> = None;
+    let mut like_shape: Option<like::Shape> = None;
+    let mut like_stdin = false;
+
+    for arg in options::parse(options::OPTIONS, args.get(1..).unwrap_or_default())? {
+        let (opt, name, value) = match arg {
+            options::Arg::Option { opt, name, value } => (opt, name, value),
+            options::Arg::Positional(arg) => {
+                positional_args.push(arg);
+                continue;
+            }
+        };
+        match opt {
+            Opt::Capitalize => {
+                // Отменяет capitalize = false из файла настроек
+                config.capitalize = true;
+                config.no_capitalize = false;
+                seen.push("-c");
+            }
+            Opt::NoCapitalize => {
+                config.no_capitalize = true;
+                seen.push("-A");
+            }
+            Opt::Numerals => {
+                config.numerals = true;
+                config.no_numerals = false;
+                seen.push("-n");
+            }
+            Opt::NoNumerals => {
+                config.no_numerals = true;
+                seen.push("-0");
+            }
+            Opt::Symbols => config.symbols = true,
+            Opt::ShellSafe => config.shell_safe = true,
+            Opt::MobileFriendly => {
+                // Заглавные на телефоне - лишнее переключение регистра
+                config.mobile_friendly = true;
+                config.no_capitalize = true;
+            }
+            Opt::NoShift => {
+                config.no_shift = true;
+                config.no_capitalize = true;
+            }
+            Opt::LayoutSafe => config.layout_safe = true,
+            Opt::NoLeadingDigit => config.no_leading_digit = true,
+            Opt::NoLeadingSymbol => config.no_leading_symbol = true,
+            Opt::NoEdgeSymbols => config.no_edge_symbols = true,
+            Opt::NoRepeat => config.no_repeat = true,
+            Opt::NoKeyboardWalks => config.no_keyboard_walks = true,
+            Opt::NoProfanity => {
+                config.profanity.get_or_insert_with(profanity::Blocklist::builtin);
+            }
+            Opt::ProfanityFile => {
+                config.profanity.get_or_insert_with(profanity::Blocklist::builtin);
+                config.profanity_file = Some(PathBuf::from(value));
+            }
+            Opt::NoDictionaryWords => {
+                config.no_dictionary_words = Some(match value.as_str() {
+                    "" => dictionary::DEFAULT_MIN_LENGTH,
+                    value => match value.parse::<usize>() {
+                        Ok(length) if (dictionary::SHORTEST..=MAX_LENGTH).contains(&length) => length,
+                        _ => {
+                            return Err(CliError::InvalidValue {
+                                option: "--no-dictionary-words",
+                                value: value.to_string(),
+                                expected: format!("a word length from {} to {}", dictionary::SHORTEST, MAX_LENGTH),
+                            });
+                        }
+                    },
+                });
+            }
+            Opt::NoSequences => {
+                config.no_sequences = Some(match value.as_str() {
+                    "" => sequence::DEFAULT_RUN,
+                    value => match value.parse::<usize>() {
+                        Ok(run) if (2..=MAX_LENGTH).contains(&run) => run,
+                        _ => {
+                            return Err(CliError::InvalidValue {
+                                option: "--no-sequences",
+                                value: value.to_string(),
+                                expected: format!("a run length from 2 to {}", MAX_LENGTH),
+                            });
+                        }
+                    },
+                });
+            }
+            Opt::Case => {
+                let Some(case) = Case::parse(&value) else {
+                    let names: Vec<&str> = Case::ALL.iter().map(|case| case.name()).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--case",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                config.case = Some(case);
+            }
+            Opt::HomoglyphStrict => {
+                config.homoglyphs.get_or_insert_with(homoglyph::Table::builtin);
+            }
+            Opt::HomoglyphFile => {
+                config.homoglyphs.get_or_insert_with(homoglyph::Table::builtin);
+                config.homoglyph_file = Some(PathBuf::from(value));
+            }
+            Opt::UrlSafe => {
+                config.url_safe = true;
+                config.secure = true;
+            }
+            Opt::AlphaOnly => config.alpha_only = true,
+            Opt::DigitsOnly => {
+                // Запоминаемый пароль без букв не составить
+                config.digits_only = true;
+                config.secure = true;
+            }
+            Opt::Pin => {
+                pin_mode = true;
+                config.pin = true;
+                config.secure = true;
+            }
+            Opt::Secure => config.secure = true,
+            Opt::MemorableModel => {
+                let Some(model) = MemorableModel::parse(&value) else {
+                    let names: Vec<&str> = MemorableModel::ALL.iter().map(|model| model.name()).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--memorable-model",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                config.memorable_model = model;
+            }
+            Opt::SyllableFile => config.syllable_file = Some(PathBuf::from(value)),
+            Opt::Syllables => {
+                syllable_count = Some(parse_count(&value, "number of syllables", syllables::MAX_COUNT)?);
+            }
+            Opt::Leet => {
+                config.leet = Some(match value.as_str() {
+                    "" => leet::DEFAULT_COUNT,
+                    value => parse_count(value, "number of --leet substitutions", MAX_LENGTH)?,
+                });
+            }
+            Opt::Words => {
+                config.words = Some(parse_count(&value, "number of words", passphrase::MAX_WORDS)?);
+                // Акростих из предыдущего слоя задавал свое число слов
+                config.acrostic = None;
+                seen.push("--words");
+                // Фразы длинные, и в столбцы их не выстраивают
+                if !config.columns_given {
+                    config.columns = false;
+                }
+            }
+            Opt::WordsAcrostic => {
+                let letters: Vec<char> = value.chars().flat_map(char::to_lowercase).collect();
+                if letters.len() < 2 || letters.len() > passphrase::MAX_WORDS || !letters.iter().all(|c| c.is_alphabetic()) {
+                    return Err(CliError::InvalidValue {
+                        option: "--words-acrostic",
+                        value,
+                        expected: format!("2 to {} letters", passphrase::MAX_WORDS),
+                    });
+                }
+                config.words = Some(letters.len());
+                config.acrostic = Some(letters);
+                seen.push("--words-acrostic");
+                if !config.columns_given {
+                    config.columns = false;
+                }
+            }
+            Opt::Separator => config.separator = Some(value),
+            Opt::CapitalizeWords => config.capitalize_words = true,
+            Opt::InjectDigit => config.inject_digit = true,
+            Opt::InjectSymbol => config.inject_symbol = true,
+            Opt::Wordlist => {
+                config.wordlist_file = Some(PathBuf::from(value));
+                // Список слов сам по себе означает фразы
+                if config.words.is_none() {
+                    config.words = Some(passphrase::DEFAULT_WORDS);
+                }
+                if !config.columns_given {
+                    config.columns = false;
+                }
+            }
+            Opt::Lang => {
+                let Some(lang) = Lang::parse(&value) else {
+                    let names: Vec<&str> = Lang::ALL.iter().map(|lang| lang.name()).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--lang",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                config.lang = Some(lang);
+                // Как и --wordlist, язык сам по себе означает фразы
+                if config.words.is_none() {
+                    config.words = Some(passphrase::DEFAULT_WORDS);
+                }
+                if !config.columns_given {
+                    config.columns = false;
+                }
+            }
+            Opt::Ambiguous => config.ambiguous = true,
+            Opt::Columns => {
+                config.columns = true;
+                config.columns_given = true;
+                seen.push("-C");
+            }
+            Opt::FixedColumns => {
+                config.fixed_columns = Some(parse_count(&value, "number of columns", MAX_COUNT)?);
+                // -1 отключает столбцы независимо от --columns
+                if !seen.contains(&"-1") {
+                    config.columns = true;
+                }
+                config.columns_given = true;
+                seen.push("--columns");
+            }
+            Opt::PerLine => {
+                config.per_line = Some(parse_count(&value, "passwords per line", MAX_COUNT)?);
+                config.columns = true;
+                config.columns_given = true;
+                seen.push("--per-line");
+            }
+            Opt::Numbered => config.numbered = true,
+            Opt::Porcelain => config.porcelain = true,
+            Opt::Unique => config.unique = true,
+            Opt::Sort => config.sort = true,
+            Opt::ShowEntropy => config.show_entropy = true,
+            Opt::Null => config.null = true,
+            Opt::CsvSafe => config.csv_safe = true,
+            Opt::Color => {
+                let Some(choice) = ColorChoice::parse(&value) else {
+                    let names: Vec<&str> = ColorChoice::ALL.iter().map(|choice| choice.name()).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--color",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                config.color = choice;
+            }
+            Opt::Format => {
+                let Some(format) = Format::parse(&value) else {
+                    let names: Vec<&str> = Format::ALL.iter().map(|format| format.name()).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--format",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                config.format = format;
+            }
+            Opt::SingleColumn => {
+                config.columns = false;
+                config.columns_given = true;
+                seen.push("-1");
+            }
+            Opt::NoVowels => config.no_vowels = true,
+            Opt::FastRng => config.fast_rng = true,
+            Opt::Verbose => config.verbose = true,
+            Opt::LockMemory => config.lock_memory = true,
+            Opt::Force => config.force = true,
+            Opt::Copy => config.copy = true,
+            Opt::Interactive => config.interactive = true,
+            Opt::Mnemonic => {
+                config.mnemonic = true;
+                // Фраза идет в той же строке, что и пароль
+                if !config.columns_given {
+                    config.columns = false;
+                }
+            }
+            Opt::Phonetic => {
+                config.phonetic = true;
+                // Слова идут строкой под каждым паролем
+                if !config.columns_given {
+                    config.columns = false;
+                }
+            }
+            Opt::Annotate => {
+                config.annotate = true;
+                if !config.columns_given {
+                    config.columns = false;
+                }
+            }
+            Opt::Qr => config.qr = true,
+            Opt::QrOnly => {
+                config.qr = true;
+                config.qr_only = true;
+            }
+            Opt::QrFormat => {
+                let Some(format) = QrFormat::parse(&value) else {
+                    return Err(CliError::InvalidValue {
+                        option: "--qr-format",
+                        value,
+                        expected: "ansi, utf8 or png:PATH".to_string(),
+                    });
+                };
+                config.qr_format = Some(format);
+            }
+            Opt::SelfTest => config.self_test = true,
+            Opt::DryRun => config.dry_run = true,
+            // Обрабатывается в main до разбора аргументов
+            Opt::NoConfig => {}
+            Opt::Help => config.help = true,
+            Opt::RemoveChars => {
+                // Сравниваем символы, а не байты: "é" - один символ, а не два
+                if let Some(c) = value.chars().find(|c| c.is_control()) {
+                    return Err(CliError::InvalidValue {
+                        option: "--remove-chars",
+                        value: value.escape_default().to_string(),
+                        expected: format!("control character {:?} is not allowed", c),
+                    });
+                }
+                let chars = expand_ranges(&value).map_err(|expected| CliError::InvalidValue {
+                    option: "--remove-chars",
+                    value: value.clone(),
+                    expected,
+                })?;
+                // Повторные -r объединяются, а не заменяют друг друга
+                let remove_chars = config.remove_chars.get_or_insert_with(Vec::new);
+                for c in chars {
+                    if !remove_chars.contains(&c) {
+                        remove_chars.push(c);
+                    }
+                }
+            }
+            Opt::IncludeOnly => {
+                let include_only = include_only.get_or_insert_with(Vec::new);
+                include_only.extend(password_chars("--include-only", &value)?);
+                include_only.sort_unstable();
+                include_only.dedup();
+            }
+            Opt::RequireChars => {
+                // Как и -r, повторные --require-chars объединяются
+                let require_chars = config.require_chars.get_or_insert_with(Vec::new);
+                require_chars.extend(password_chars("--require-chars", &value)?);
+                require_chars.sort_unstable();
+                require_chars.dedup();
+            }
+            Opt::Charset => {
+                let Some(&preset) = CHARSET_PRESETS.iter().find(|(name, _)| *name == value) else {
+                    let names: Vec<&str> = CHARSET_PRESETS.iter().map(|(name, _)| *name).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--charset",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                // Алфавит задает символы целиком, запоминаемый режим к нему неприменим
+                config.charset_preset = Some(preset);
+                config.secure = true;
+            }
+            Opt::Preset => {
+                let Some(named) = Preset::parse(&value) else {
+                    let names: Vec<&str> = Preset::ALL.iter().map(|preset| preset.name()).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--preset",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                preset = Some(named);
+                config.preset = Some(named);
+                config.secure = true;
+            }
+            Opt::AmbiguousChars => {
+                // "+chars" дополняет набор по умолчанию, "chars" заменяет его
+                match value.strip_prefix('+') {
+                    Some(extra) => config.ambiguous_chars.extend(extra.chars()),
+                    None => config.ambiguous_chars = value.chars().collect(),
+                }
+                config.ambiguous = true;
+            }
+            Opt::RandomSource => {
+                set_random_source(config, None, RngChoice::File(PathBuf::from(value)), None);
+                seen.push("--random-source");
+            }
+            Opt::Output => config.output = Some(PathBuf::from(value)),
+            Opt::EncryptTo => {
+                config.encrypt_to = match Recipient::parse(&value) {
+                    Ok(recipient) => Some(recipient),
+                    Err(e) => {
+                        return Err(CliError::InvalidValue {
+                            option: "--encrypt-to",
+                            value,
+                            expected: e,
+                        });
+                    }
+                };
+            }
+            Opt::Pick => match value.parse() {
+                Ok(n) if n > 0 => config.pick = Some(n),
+                _ => return Err(CliError::invalid_number("--pick value", &value)),
+            },
+            Opt::ClearAfter => match value.parse() {
+                Ok(seconds) => {
+                    config.clear_after = seconds;
+                    seen.push("--clear-after");
+                }
+                Err(_) => return Err(CliError::invalid_number("--clear-after value", &value)),
+            },
+            Opt::Seed => match value.parse() {
+                Ok(seed) => {
+                    set_random_source(config, Some(seed), RngChoice::Auto, None);
+                    seen.push("--seed");
+                }
+                Err(_) => return Err(CliError::invalid_number("seed", &value)),
+            },
+            Opt::Rng => {
+                let choice = match RngChoice::parse(&value) {
+                    Some(choice) => choice,
+                    None => {
+                        return Err(CliError::InvalidValue {
+                            option: "--rng",
+                            value,
+                            expected: "auto, getrandom, urandom or file:PATH".to_string(),
+                        });
+                    }
+                };
+                set_random_source(config, None, choice, None);
+                seen.push("--rng");
+            }
+            Opt::Length => {
+                length_option = Some(("-L/--length", parse_count(&value, "password length", MAX_LENGTH)?));
+            }
+            Opt::CharsetFile => config.charset_file = Some(PathBuf::from(value)),
+            Opt::LengthRange => length_range = Some(parse_length_range(&value)?),
+            Opt::Bits => target_bits = Some(parse_count(&value, "number of bits", bits::MAX_BITS)?),
+            Opt::Prefix => config.prefix = Some(parse_affix("--prefix", value)?),
+            Opt::Suffix => config.suffix = Some(parse_affix("--suffix", value)?),
+            Opt::LengthIncludesAffixes => config.length_includes_affixes = true,
+            Opt::Group => match group::Grouping::parse(&value) {
+                Ok(grouping) => config.group = Some(grouping),
+                Err(expected) => {
+                    return Err(CliError::InvalidValue {
+                        option: "--group",
+                        value,
+                        expected,
+                    });
+                }
+            },
+            Opt::GroupCountsLength => config.group_counts_length = true,
+            Opt::Weights => {
+                // Нулевой вес выключает класс так же, как -A и -0
+                let weights = parse_weights(&value)?;
+                config.weights = Some(weights);
+                config.secure = true;
+                config.no_capitalize = weights[1] == 0;
+                config.no_numerals = weights[2] == 0;
+            }
+            Opt::MinCount(class) => {
+                let min = parse_class_count(name, &value)?;
+                config.class_counts.min[class] = min;
+                // Как -c, -n и -y: класс включается в набор
+                if min > 0 {
+                    match class {
+                        1 => {
+                            config.capitalize = true;
+                            config.no_capitalize = false;
+                        }
+                        2 => {
+                            config.numerals = true;
+                            config.no_numerals = false;
+                        }
+                        3 => config.symbols = true,
+                        _ => {}
+                    }
+                }
+            }
+            Opt::MaxCount(class) => {
+                let max = parse_class_count(name, &value)?;
+                config.class_counts.max[class] = Some(max);
+                // Ноль выключает класс так же, как -A и -0
+                match class {
+                    1 if max == 0 => config.no_capitalize = true,
+                    2 if max == 0 => config.no_numerals = true,
+                    _ => {}
+                }
+            }
+            Opt::Classes => {
+                let counts = parse_classes(&value)?;
+                classes = Some(counts);
+                config.classes = Some(counts);
+                config.class_counts = ClassCounts {
+                    min: counts,
+                    max: counts.map(Some),
+                };
+                config.secure = true;
+                // Флаги классов следуют за числами, как у --weights
+                config.capitalize = counts[1] > 0;
+                config.no_capitalize = counts[1] == 0;
+                config.numerals = counts[2] > 0;
+                config.no_numerals = counts[2] == 0;
+                config.symbols = counts[3] > 0;
+            }
+            Opt::Balanced => {
+                config.balanced = true;
+                config.secure = true;
+            }
+            Opt::Template => match template::Template::parse(&value) {
+                Ok(parsed) => template = Some(parsed),
+                Err(expected) => {
+                    return Err(CliError::InvalidValue {
+                        option: "--template",
+                        value,
+                        expected,
+                    });
+                }
+            },
+            Opt::Like if value == "-" => {
+                like_shape = None;
+                like_stdin = true;
+            }
+            Opt::Like => {
+                // Старый пароль не нужен после разбора формы и не попадает в сообщения
+                let mut value = value;
+                let shape = like::Shape::new(&value);
+                secret::wipe_str(&mut value);
+                like_shape = Some(shape.map_err(|expected| CliError::InvalidValue {
+                    option: "--like",
+                    value: "(hidden)".to_string(),
+                    expected,
+                })?);
+                like_stdin = false;
+            }
+            Opt::Blocks => match blocks::Spec::parse(&value) {
+                Ok(parsed) => block_spec = Some(parsed),
+                Err(expected) => {
+                    return Err(CliError::InvalidValue {
+                        option: "--blocks",
+                        value,
+                        expected,
+                    });
+                }
+            },
+            Opt::Count => {
+                let flag = if name == "--count" { "--count" } else { "-N/--num-passwords" };
+                count_option = Some((flag, parse_count(&value, "number of passwords", MAX_COUNT)?));
+            }
+            Opt::Sha1 => {
+                set_random_source(config, None, RngChoice::Auto, Some(Sha1Spec::parse(&value)));
+                seen.push("-H/--sha1");
+            }
+        }
+    }
+
+    if include_only.is_some() {
+        config.include_only = include_only;
+    }
+
+    // Обработка позиционных аргументов
+    if positional_args.len() > 2 {
+        return Err(CliError::TooManyArguments);
+    }
+    let positional_length = positional_args
+        .first()
+        .map(|arg| parse_count(arg, "password length", MAX_LENGTH))
+        .transpose()?;
+    let mut fixed_length = merge_named_count(length_option, positional_length, "pw_length")?;
+    if target_bits.is_some() && (fixed_length.is_some() || length_range.is_some()) {
+        return Err(CliError::ConflictingFlags(
+            "--bits sets the password length and cannot be used with a fixed pw_length or --length-range; \
+             use -N for the number of passwords"
+                .to_string(),
+        ));
+    }
+    if syllable_count.is_some() && (fixed_length.is_some() || length_range.is_some()) {
+        return Err(CliError::ConflictingFlags(
+            "--syllables sets the password length and cannot be used with a fixed pw_length or --length-range; \
+             use -N for the number of passwords"
+                .to_string(),
+        ));
+    }
+    if let Some(counts) = classes {
+        let total = counts.iter().sum();
+        if length_range.is_some() {
+            return Err(CliError::ConflictingFlags("--length-range cannot be used with --classes".to_string()));
+        }
+        if let Some(length) = fixed_length
+            && length != total
+        {
+            return Err(CliError::ConflictingFlags(format!(
+                "--classes {} makes passwords {} characters long, which conflicts with pw_length {}",
+                classes_spec(&counts),
+                total,
+                length
+            )));
+        }
+        fixed_length = Some(total);
+    } else if config.classes.is_some() && (fixed_length.is_some() || length_range.is_some()) {
+        // Новая длина отменяет --classes из предыдущего слоя
+        config.classes = None;
+        config.class_counts = ClassCounts::default();
+    }
+    if let Some(template) = template {
+        if length_range.is_some() {
+            return Err(CliError::ConflictingFlags("--length-range cannot be used with --template".to_string()));
+        }
+        if let Some(length) = fixed_length
+            && length != template.len()
+        {
+            return Err(CliError::ConflictingFlags(format!(
+                "--template {} makes passwords {} characters long, which conflicts with pw_length {}",
+                template.pattern(),
+                template.len(),
+                length
+            )));
+        }
+        fixed_length = Some(template.len());
+        config.template = Some(template);
+    } else if config.template.is_some() && (fixed_length.is_some() || length_range.is_some()) {
+        // Новая длина отменяет --template из предыдущего слоя
+        config.template = None;
+    }
+    if let Some(spec) = block_spec {
+        if length_range.is_some() {
+            return Err(CliError::ConflictingFlags("--length-range cannot be used with --blocks".to_string()));
+        }
+        if let Some(length) = fixed_length
+            && length != spec.len()
+        {
+            return Err(CliError::ConflictingFlags(format!(
+                "--blocks {} makes passwords {} characters long, which conflicts with pw_length {}",
+                spec.spec(),
+                spec.len(),
+                length
+            )));
+        }
+        fixed_length = Some(spec.len());
+        config.blocks = Some(spec);
+    } else if config.blocks.is_some() && (fixed_length.is_some() || length_range.is_some()) {
+        // Новая длина отменяет --blocks из предыдущего слоя
+        config.blocks = None;
+    }
+    if like_shape.is_some() || like_stdin {
+        if fixed_length.is_some() || length_range.is_some() {
+            return Err(CliError::ConflictingFlags(
+                "--like sets the password length and cannot be used with a fixed pw_length or --length-range; \
+                 use -N for the number of passwords"
+                    .to_string(),
+            ));
+        }
+        // Длину пароля из stdin main узнает после чтения
+        fixed_length = like_shape.as_ref().map(like::Shape::len);
+        config.like = like_shape;
+        config.like_stdin = like_stdin;
+    } else if (config.like.is_some() || config.like_stdin) && (fixed_length.is_some() || length_range.is_some()) {
+        // Новая длина отменяет --like из предыдущего слоя
+        config.like = None;
+        config.like_stdin = false;
+    }
+    if config.words.is_some() && (fixed_length.is_some() || length_range.is_some()) {
+        return Err(CliError::ConflictingFlags(
+            "a password length cannot be used with --words; use -N for the number of passphrases".to_string(),
+        ));
+    }
+    match (fixed_length, length_range) {
+        (Some(_), Some(_)) => {
+            return Err(CliError::ConflictingFlags(
+                "--length-range cannot be used with a fixed pw_length; use -N for the number of passwords"
+                    .to_string(),
+            ));
+        }
+        // Фиксированная длина отменяет диапазон из предыдущего слоя
+        (Some(length), None) => {
+            config.pw_length = length;
+            config.length_range = None;
+        }
+        (None, Some((min, max))) => {
+            config.pw_length = max;
+            config.length_range = Some((min, max));
+        }
+        (None, None) => {
+            if let Some(preset) = preset {
+                config.pw_length = preset.default_length();
+                config.length_range = None;
+            } else if pin_mode {
+                config.pw_length = pin::DEFAULT_LENGTH;
+                config.length_range = None;
+            }
+        }
+    }
+    // Длина или число слов из этого слоя отменяет --bits из предыдущего, и
+    // наоборот
+    if target_bits.is_some() {
+        config.bits = target_bits;
+        config.length_range = None;
+    } else if fixed_length.is_some() || length_range.is_some() || seen.contains(&"--words") {
+        config.bits = None;
+    }
+    // И --syllables тоже
+    if syllable_count.is_some() {
+        config.syllable_count = syllable_count;
+        config.length_range = None;
+    } else if fixed_length.is_some() || length_range.is_some() {
+        config.syllable_count = None;
+    }
+    let positional_count = positional_args
+        .get(1)
+        .map(|arg| parse_count(arg, "number of passwords", MAX_COUNT))
+        .transpose()?;
+    if let Some(count) = merge_named_count(count_option, positional_count, "num_pw")? {
+        config.num_pw = count;
+        config.num_pw_given = true;
+    }
+
+    check_conflicts(config, &seen)
+}
+
+// --seed, --rng, --random-source и -H задают один и тот же источник
+// случайности: open_rng взял бы первый из них и молча отбросил остальные.
+// Источник из командной строки заменяет источник из PWGEN_OPTS, а два в
+// одном слое отвергает check_conflicts.
+fn set_random_source(config: &mut Config, seed: Option<u64>, rng: RngChoice, sha1: Option<Sha1Spec>) {
+    config.seed = seed;
+    config.rng = rng;
+    config.sha1 = sha1;
+}
+
+// Значение --prefix и --suffix: управляющие символы сломали бы строки вывода
+fn parse_affix(option: &'static str, value: String) -> Result<String, CliError> {
+    if value.chars().any(char::is_control) {
+        return Err(CliError::InvalidValue {
+            option,
+            value,
+            expected: "text without control characters".to_string(),
+        });
+    }
+    Ok(value)
+}
+
+// Значение --length-range: "12-16", обе границы от 1 до MAX_LENGTH
+fn parse_length_range(value: &str) -> Result<(usize, usize), CliError> {
+    let invalid = |expected: &str| CliError::InvalidValue {
+        option: "--length-range",
+        value: value.to_string(),
+        expected: expected.to_string(),
+    };
+    let Some((min, max)) = value.split_once('-') else {
+        return Err(invalid("MIN-MAX, as in 12-16"));
+    };
+    let min = parse_count(min, "minimum password length", MAX_LENGTH)?;
+    let max = parse_count(max, "maximum password length", MAX_LENGTH)?;
+    if min > max {
+        return Err(invalid("MIN must not be greater than MAX"));
+    }
+    Ok((min, max))
+}
+
+// Самый большой вес класса в --weights
+const MAX_WEIGHT: usize = 1000;
+
+// "8:2:3:1" - веса строчных, заглавных, цифр и символов
+fn parse_weights(value: &str) -> Result<[usize; 4], CliError> {
+    let invalid = |expected: &str| CliError::InvalidValue {
+        option: "--weights",
+        value: value.to_string(),
+        expected: expected.to_string(),
+    };
+    let parts: Vec<&str> = value.split(':').collect();
+    let [lower, upper, digits, symbols] = parts[..] else {
+        return Err(invalid("four weights L:U:D:S, as in 8:2:3:1"));
+    };
+    let mut weights = [0; 4];
+    for (weight, part) in weights.iter_mut().zip([lower, upper, digits, symbols]) {
+        *weight = match part.parse::<usize>() {
+            Ok(n) if n <= MAX_WEIGHT => n,
+            _ => return Err(invalid(&format!("weights from 0 to {}", MAX_WEIGHT))),
+        };
+    }
+    if weights.iter().all(|&w| w == 0) {
+        return Err(invalid("at least one positive weight"));
+    }
+    Ok(weights)
+}
+
+// Значение --min-* и --max-*: число от 0 до MAX_LENGTH
+fn parse_class_count(option: &'static str, value: &str) -> Result<usize, CliError> {
+    match value.parse::<usize>() {
+        Ok(n) if n <= MAX_LENGTH => Ok(n),
+        _ => Err(CliError::InvalidValue {
+            option,
+            value: value.to_string(),
+            expected: format!("a count from 0 to {}", MAX_LENGTH),
+        }),
+    }
+}
+
+// "l4u2d2s1": буква класса (строчные, заглавные, цифры, символы) и
+// число его символов. Буквы идут в любом порядке, каждая не больше
+// одного раза; ошибка указывает на неверный фрагмент.
+fn parse_classes(value: &str) -> Result<[usize; 4], CliError> {
+    let invalid = |expected: String| CliError::InvalidValue {
+        option: "--classes",
+        value: value.to_string(),
+        expected,
+    };
+    if value.is_empty() {
+        return Err(invalid("class letters l, u, d, s with counts, as in l4u2d2s1".to_string()));
+    }
+    let mut counts = [None; 4];
+    let mut rest = value;
+    while let Some(letter) = rest.chars().next() {
+        let start = value.len() - rest.len();
+        let end = rest[letter.len_utf8()..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest.len(), |i| i + letter.len_utf8());
+        let (token, tail) = rest.split_at(end);
+        rest = tail;
+        let bad = |expected: &str| invalid(format!("{} at '{}', character {}", expected, token, start + 1));
+
+        let Some(class) = CLASS_LETTERS.iter().position(|&c| c == letter) else {
+            return Err(bad("a class letter l, u, d or s"));
+        };
+        let count = match token[1..].parse::<usize>() {
+            Ok(n) if n <= MAX_LENGTH => n,
+            _ => return Err(bad(&format!("a count from 0 to {} after the letter", MAX_LENGTH))),
+        };
+        if counts[class].replace(count).is_some() {
+            return Err(bad("each class letter only once"));
+        }
+    }
+    let counts = counts.map(|count| count.unwrap_or(0));
+    let total: usize = counts.iter().sum();
+    if !(1..=MAX_LENGTH).contains(&total) {
+        return Err(invalid(format!("counts adding up to a length from 1 to {}", MAX_LENGTH)));
+    }
+    Ok(counts)
+}
+
+// Обратное к parse_classes для сообщений: классы без символов опускаются
+fn classes_spec(counts: &[usize; 4]) -> String {
+    CLASS_LETTERS
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| **count > 0)
+        .map(|(letter, count)| format!("{}{}", letter, count))
+        .collect()
+}
+
+// Самый длинный диапазон в -r: защита от "-r ' -\u{10FFFF}'"
+const MAX_RANGE: u32 = 1024;
+
+// Значение -r с диапазонами: "a-f0-3" - это abcdef0123. Дефис в начале,
+// после диапазона в конце или "\-" - сам символ "-", "\\" - обратная
+// косая черта. Ошибка называет неверный диапазон: "z-a", "a-".
+fn expand_ranges(value: &str) -> Result<Vec<char>, String> {
+    // Символы с признаком экранирования
+    let mut items: Vec<(char, bool)> = Vec::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next @ ('-' | '\\'))) => {
+                chars.next();
+                items.push((next, true));
+            }
+            _ => items.push((c, false)),
+        }
+    }
+
+    let mut expanded = Vec::new();
+    let mut i = 0;
+    while i < items.len() {
+        let (start, _) = items[i];
+        if items.get(i + 1) != Some(&('-', false)) {
+            expanded.push(start);
+            i += 1;
+            continue;
+        }
+        let Some(&(end, _)) = items.get(i + 2) else {
+            return Err(format!("dangling range {}-; use \\- for a literal hyphen", start));
+        };
+        if start > end {
+            return Err(format!("invalid range {}-{}", start, end));
+        }
+        if end as u32 - start as u32 >= MAX_RANGE {
+            return Err(format!("range {}-{} is too large", start, end));
+        }
+        expanded.extend(start..=end);
+        i += 3;
+        // Дефис после диапазона в конце - сам символ
+        if i + 1 == items.len() && items[i] == ('-', false) {
+            expanded.push('-');
+            i += 1;
+        }
+    }
+    Ok(expanded)
+}
+
+// Символы для --include-only и --require-chars: диапазоны как в -r, но
+// пробелы и управляющие символы в паролях не используются
+fn password_chars(option: &'static str, value: &str) -> Result<Vec<char>, CliError> {
+    let chars = expand_ranges(value).map_err(|expected| CliError::InvalidValue {
+        option,
+        value: value.to_string(),
+        expected,
+    })?;
+    if let Some(c) = chars.iter().find(|c| c.is_whitespace() || c.is_control()) {
+        return Err(CliError::InvalidValue {
+            option,
+            value: value.escape_default().to_string(),
+            expected: format!("{:?} cannot be part of a password", c),
+        });
+    }
+    Ok(chars)
+}
+
+// Сочетания опций, которые проверяются после всех слоев
+fn check_combinations(config: &mut Config) -> Result<(), CliError> {
+    // --copy кладет в буфер один пароль: без явного количества генерируется
+    // ровно один, а из пакета его выбирают через --pick (без --copy --pick
+    // просто печатает этот пароль)
+    if config.copy && config.pick.is_none() {
+        if !config.num_pw_given {
+            config.num_pw = 1;
+        } else if config.num_pw != 1 {
+            return Err(CliError::ConflictingFlags(
+                "--copy needs a single password; use --pick N to choose from a batch".to_string(),
+            ));
+        }
+    }
+    // Так же и --qr: код рисуется для одного пароля
+    if config.qr && config.pick.is_none() {
+        if !config.num_pw_given {
+            config.num_pw = 1;
+        } else if config.num_pw != 1 {
+            return Err(CliError::ConflictingFlags(
+                "--qr needs a single password; use --pick N to choose from a batch".to_string(),
+            ));
+        }
+    }
+    // Пакет по умолчанию не меньше номера из --pick, см. apply_terminal_defaults
+    if let Some(pick) = config.pick
+        && config.num_pw_given
+        && pick > config.num_pw
+    {
+        return Err(CliError::InvalidNumber {
+            what: "--pick value",
+            value: pick.to_string(),
+            max: Some(config.num_pw),
+        });
+    }
+    // С --length-includes-affixes генераторы получают длину случайной
+    // части: pw_length и --length-range без --prefix и --suffix
+    if config.length_includes_affixes {
+        let affixes = affix_length(config);
+        let shortest = config.length_range.map_or(config.pw_length, |(min, _)| min);
+        if shortest <= affixes {
+            return Err(CliError::ConflictingFlags(format!(
+                "pw_length {} leaves no room for a random part after {} characters of --prefix and --suffix",
+                shortest, affixes
+            )));
+        }
+        config.pw_length -= affixes;
+        config.length_range = config.length_range.map(|(min, max)| (min - affixes, max - affixes));
+    }
+    // С --group-counts-length разделители входят в длину: из нее остается
+    // столько символов, сколько с разделителями дают ровно эту длину
+    if config.group_counts_length
+        && let Some(grouping) = &config.group
+    {
+        let chars = |total: usize| {
+            grouping.chars_for(total).ok_or_else(|| {
+                CliError::ConflictingFlags(format!(
+                    "with --group-counts-length a password of {} characters would end in a separator; use {} or {}",
+                    total,
+                    (1..total).rev().find(|&t| grouping.chars_for(t).is_some()).unwrap_or(1),
+                    (total..).find(|&t| grouping.chars_for(t).is_some()).unwrap_or(total)
+                ))
+            })
+        };
+        config.pw_length = chars(config.pw_length)?;
+        config.length_range = match config.length_range {
+            Some((min, max)) => Some((chars(min)?, chars(max)?)),
+            None => None,
+        };
+    }
+    // Зашифрованный вывод в терминал бесполезен, а в конвейере неудобен
+    if config.encrypt_to.is_some() && config.output.is_none() {
+        return Err(CliError::ConflictingFlags("--encrypt-to requires --output".to_string()));
+    }
+    // В --porcelain вывод не зависит ни от чего, кроме num_pw
+    if config.porcelain {
+        let decoration = [
+            (config.numbered, "--numbered"),
+            (config.pick.is_some(), "--pick"),
+            (config.copy, "--copy"),
+            (config.interactive, "--interactive"),
+            (config.self_test, "--self-test"),
+            (config.per_line.is_some(), "--per-line"),
+            (config.columns_given && config.columns, "-C/--columns"),
+        ];
+        if let Some((_, flag)) = decoration.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "--porcelain prints one password per line and cannot be used with {}",
+                flag
+            )));
+        }
+    }
+    // С -z каждый пароль - отдельная запись, как у find -print0; столбцы
+    // по умолчанию выключаются, а явно заданные - ошибка
+    if config.null {
+        let other = [
+            (config.per_line.is_some(), "--per-line"),
+            (config.columns_given && config.columns, "-C/--columns"),
+            (config.format != Format::Text, "--format"),
+            (config.mnemonic, "--mnemonic"),
+            (config.interactive, "--interactive"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "-z/--null ends each password with a NUL byte and cannot be used with {}",
+                flag
+            )));
+        }
+        config.columns = false;
+    }
+    if config.csv_safe && config.format != Format::Csv {
+        return Err(CliError::ConflictingFlags("--csv-safe requires --format csv".to_string()));
+    }
+    // JSON и CSV печатаются только в stdout; столбцы в них не нужны и
+    // молча пропускаются. Фраза --mnemonic в JSON - поле пароля, а в CSV
+    // для нее столбца нет
+    if config.format != Format::Text {
+        let other = [
+            (config.porcelain, "--porcelain"),
+            (config.numbered, "--numbered"),
+            (config.pick.is_some(), "--pick"),
+            (config.mnemonic && config.format == Format::Csv, "--mnemonic"),
+            (config.copy, "--copy"),
+            (config.output.is_some(), "--output"),
+            (config.interactive, "--interactive"),
+            (config.self_test, "--self-test"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "--format {} prints to stdout and cannot be used with {}",
+                config.format.name(),
+                flag
+            )));
+        }
+    }
+    // -i выдает один принятый пароль в stdout
+    if config.interactive {
+        let other = if config.output.is_some() {
+            Some("--output")
+        } else if config.copy {
+            Some("--copy")
+        } else if config.pick.is_some() {
+            Some("--pick")
+        } else if config.num_pw_given {
+            Some("num_pw")
+        } else {
+            None
+        };
+        if let Some(other) = other {
+            return Err(CliError::ConflictingFlags(format!(
+                "--interactive shows one password at a time and cannot be used with {}",
+                other
+            )));
+        }
+    }
+    if config.copy && config.output.is_some() {
+        return Err(CliError::ConflictingFlags(
+            "--copy and --output cannot be used together".to_string(),
+        ));
+    }
+    // Фраза выдает пароль целиком, поэтому печатается только рядом с ним
+    if config.mnemonic {
+        let other = [
+            (config.copy, "--copy"),
+            (config.output.is_some(), "--output"),
+            (config.porcelain, "--porcelain"),
+            (config.interactive, "--interactive"),
+            (config.words.is_some(), "--words"),
+            (config.per_line.is_some(), "--per-line"),
+            (config.columns_given && config.columns, "-C/--columns"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "--mnemonic prints each password with its sentence and cannot be used with {}",
+                flag
+            )));
+        }
+    }
+    // Слова печатаются строкой под паролем, поэтому нужен вывод по паролю
+    // в строке и для человека; в JSON они идут полем "phonetic"
+    if config.phonetic {
+        let other = [
+            (config.copy, "--copy"),
+            (config.porcelain, "--porcelain"),
+            (config.null, "-z/--null"),
+            (config.mnemonic, "--mnemonic"),
+            (config.interactive, "--interactive"),
+            (config.format == Format::Csv, "--format csv"),
+            (config.per_line.is_some(), "--per-line"),
+            (config.columns_given && config.columns, "-C/--columns"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "--phonetic prints each password with its spelling and cannot be used with {}",
+                flag
+            )));
+        }
+    }
+    // Пометки - для человека: в --porcelain, -z и CSV их просто нет, в
+    // JSON они идут массивом "annotations"
+    if config.annotate {
+        let other = [
+            (config.copy, "--copy"),
+            (config.mnemonic, "--mnemonic"),
+            (config.interactive, "--interactive"),
+            (config.per_line.is_some(), "--per-line"),
+            (config.columns_given && config.columns, "-C/--columns"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "--annotate prints a note under each password and cannot be used with {}",
+                flag
+            )));
+        }
+    }
+    // Повторы и порядок - свойства пакета: -i показывает пароли по одному,
+    // а jsonl печатает каждый сразу, не дожидаясь остальных
+    if config.unique || config.sort {
+        let flag = if config.sort { "--sort" } else { "--unique" };
+        if config.interactive {
+            return Err(CliError::ConflictingFlags(format!(
+                "{} works on a batch and cannot be used with --interactive",
+                flag
+            )));
+        }
+        if config.sort && config.format == Format::Jsonl {
+            return Err(CliError::ConflictingFlags(
+                "--sort needs the whole batch, but --format jsonl prints each password as soon as it is generated"
+                    .to_string(),
+            ));
+        }
+    }
+    // Энтропия печатается рядом с паролями или строкой под ними, то есть
+    // только в stdout и для человека; в JSON и CSV она есть всегда
+    if config.show_entropy {
+        let other = [
+            (config.copy, "--copy"),
+            (config.output.is_some(), "--output"),
+            (config.porcelain, "--porcelain"),
+            (config.null, "-z/--null"),
+            (config.mnemonic, "--mnemonic"),
+            (config.interactive, "--interactive"),
+            (config.qr_only, "--qr-only"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "--show-entropy prints the entropy next to the passwords and cannot be used with {}",
+                flag
+            )));
+        }
+    }
+    if config.qr_format.is_some() && !config.qr {
+        return Err(CliError::ConflictingFlags("--qr-format requires --qr or --qr-only".to_string()));
+    }
+    // Код печатается в терминал после пароля (или пишется в PNG)
+    if config.qr {
+        let other = [
+            (config.copy, "--copy"),
+            (config.output.is_some(), "--output"),
+            (config.porcelain, "--porcelain"),
+            (config.null, "-z/--null"),
+            (config.mnemonic, "--mnemonic"),
+            (config.interactive, "--interactive"),
+            (config.format != Format::Text, "--format"),
+            (config.qr_only && config.phonetic, "--phonetic"),
+            (config.qr_only && config.annotate, "--annotate"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "{} prints a QR code of one password and cannot be used with {}",
+                if config.qr_only { "--qr-only" } else { "--qr" },
+                flag
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Именованная опция (-L, -N, --count) и позиционный аргумент вместе
+// допустимы, только если задают одно и то же значение
+fn merge_named_count(
+    named: Option<(&str, usize)>,
+    positional: Option<usize>,
+    argument: &str,
+) -> Result<Option<usize>, CliError> {
+    match (named, positional) {
+        (Some((flag, value)), Some(other)) if value != other => Err(CliError::ConflictingFlags(format!(
+            "{} {} conflicts with the {} argument {}",
+            flag, value, argument, other
+        ))),
+        (Some((_, value)), _) | (None, Some(value)) => Ok(Some(value)),
+        (None, None) => Ok(None),
+    }
+}
+
+// Как в pwgen: полный экран паролей в столбцах нужен только человеку у
+// терминала. Без явного количества паролей столько, сколько столбцов
+// помещается в ширину терминала, на высоту терминала без одной строки
+// (для приглашения shell); с -1 - один пароль. В конвейер (`pwgen | head -1`)
+// по умолчанию уходит один пароль в строке, чтобы скрипт случайно не
+// записал в лог сотню секретов. Файл --output заменяет перенаправление
+// `pwgen > file` и получает то же самое. -C без терминала заполняет экран
+// 80x24. Явно заданные количество, -C и -1 не меняются.
+fn apply_terminal_defaults(config: &mut Config, terminal: &dyn term::Terminal) {
+    let tty = terminal.is_terminal() && config.output.is_none();
+    if !tty && !config.copy && !config.columns_given {
+        config.columns = false;
+    }
+    // Количество уже известно: задано явно или это один пароль --copy и --qr
+    let single = (config.copy || config.qr) && config.pick.is_none();
+    if config.num_pw_given || single {
+        return;
+    }
+    config.num_pw = if config.columns {
+        // Без терминала столбцы считаются по 80 символам
+        let size = if tty { terminal.size() } else { None };
+        screen_count(config, size.unwrap_or(term::DEFAULT_SIZE))
+    } else {
+        1
+    };
+    // С --pick пакет нужен целиком, печатается из него один пароль
+    if let Some(pick) = config.pick {
+        config.num_pw = config.num_pw.max(pick);
+    }
+}
+
+// Сколько паролей заполняют экран: столбцы по column_count на все строки,
+// кроме последней
+fn screen_count(config: &mut Config, size: term::Size) -> usize {
+    let rows = size.height.saturating_sub(1).max(1);
+    // Ширина номеров с --numbered зависит от числа паролей: сначала она
+    // берется для столбцов без номеров, то есть с запасом
+    config.num_pw = (size.width / (password_width(config) + 1)).max(1) * rows;
+    column_count(config, Some(size.width)) * rows
+}
+
+// Взаимоисключающие флаги и флаги, которые ничего не могут дать
+fn check_conflicts(config: &Config, seen: &[&str]) -> Result<(), CliError> {
+    let given = |flag: &str| seen.contains(&flag);
+
+    for (on, off, what) in [("-c", "-A", "capital letters"), ("-n", "-0", "numbers")] {
+        if given(on) && given(off) {
+            return Err(CliError::ConflictingFlags(format!(
+                "{} and {} conflict: both include and exclude {}",
+                on, off, what
+            )));
+        }
+    }
+
+    // Один источник случайности на слой, см. set_random_source
+    let mut sources = ["--seed", "--rng", "--random-source", "-H/--sha1"].into_iter().filter(|flag| given(flag));
+    if let (Some(first), Some(second)) = (sources.next(), sources.next()) {
+        return Err(CliError::ConflictingFlags(format!(
+            "{} cannot be used with {}: both select the random source",
+            second, first
+        )));
+    }
+
+    // Требование класса, все символы которого удалены через -r
+    if let Some(remove_chars) = &config.remove_chars {
+        let classes = [
+            (config.symbols, "-y/--symbols", symbol_class(config), "symbol"),
+            (given("-n"), "-n/--numerals", NUMERALS, "digit"),
+            (given("-c"), "-c/--capitalize", UPPERCASE, "capital letter"),
+        ];
+        for (requested, flag, class, what) in classes {
+            if requested && class.iter().all(|&c| remove_chars.contains(&(c as char))) {
+                return Err(CliError::ConflictingFlags(format!(
+                    "{} conflicts with --remove-chars, which removes every {}",
+                    flag, what
+                )));
+            }
+        }
+    }
+
+    // --url-safe сам задает набор, а символы общего вида исключает по определению
+    if config.url_safe {
+        let other = [
+            (config.symbols, "-y/--symbols"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.include_only.is_some(), "--include-only"),
+            (config.charset_file.is_some(), "--charset-file"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --url-safe", flag)));
+        }
+    }
+
+    // --alpha-only и --digits-only сами задают набор, и классы вне него
+    // потребовать нельзя
+    if config.alpha_only || config.digits_only {
+        let mode = if config.digits_only { "--digits-only" } else { "--alpha-only" };
+        let other = [
+            (config.alpha_only && config.digits_only, "--alpha-only"),
+            (config.alpha_only && given("-n"), "-n/--numerals"),
+            (config.digits_only && given("-c"), "-c/--capitalize"),
+            (config.digits_only && given("-0"), "-0/--no-numerals"),
+            (config.symbols, "-y/--symbols"),
+            (config.url_safe, "--url-safe"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.include_only.is_some(), "--include-only"),
+            (config.charset_file.is_some(), "--charset-file"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.mobile_friendly, "--mobile-friendly"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with {}", flag, mode)));
+        }
+    }
+
+    // --case не должен спорить с -A и -c и с числами букв каждого регистра
+    if let Some(case) = config.case {
+        let counts = &config.class_counts;
+        let letters = (0..2).any(|i| counts.min[i] > 0 || counts.max[i].is_some());
+        let other = [
+            (case == Case::Lower && given("-c"), "-c/--capitalize"),
+            (case != Case::Lower && given("-A"), "-A/--no-capitalize"),
+            (case != Case::Lower && config.no_shift, "--no-shift"),
+            (letters, "--min-lower/--max-lower/--min-upper/--max-upper"),
+            (config.classes.is_some(), "--classes"),
+            (config.balanced, "--balanced"),
+            (config.weights.is_some(), "--weights"),
+            (config.mobile_friendly, "--mobile-friendly"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --case {}", flag, case.name())));
+        }
+    }
+
+    // --preset задает набор символов и допустимые длины
+    if let Some(preset) = config.preset {
+        let option = format!("--preset {}", preset.name());
+        let other = [
+            (given("-A"), "-A/--no-capitalize"),
+            (given("-0"), "-0/--no-numerals"),
+            (config.no_vowels, "-v/--no-vowels"),
+            (config.no_shift, "--no-shift"),
+            (config.url_safe, "--url-safe"),
+            (config.alpha_only, "--alpha-only"),
+            (config.digits_only, "--digits-only"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.include_only.is_some(), "--include-only"),
+            (config.charset_file.is_some(), "--charset-file"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.mobile_friendly, "--mobile-friendly"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with {}", flag, option)));
+        }
+        let (shortest, longest) = config.length_range.unwrap_or((config.pw_length, config.pw_length));
+        let lengths = preset.lengths();
+        if !lengths.contains(&shortest) || !lengths.contains(&longest) {
+            let length = match config.length_range {
+                Some((min, max)) => format!("{}-{}", min, max),
+                None => config.pw_length.to_string(),
+            };
+            return Err(CliError::ConflictingFlags(format!(
+                "{} allows lengths from {} to {} characters, not {}",
+                option,
+                lengths.start(),
+                lengths.end(),
+                length
+            )));
+        }
+    }
+
+    // Модель Маркова строит только запоминаемые пароли, и без -v
+    if config.memorable_model == MemorableModel::Markov {
+        let other = [
+            (config.no_vowels, "-v/--no-vowels"),
+            (config.url_safe, "--url-safe"),
+            (config.digits_only, "--digits-only"),
+            (config.preset.is_some(), "--preset"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.balanced, "--balanced"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.secure, "-s/--secure"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --memorable-model markov", flag)));
+        }
+    }
+
+    // --leet меняет буквы только в запоминаемых паролях
+    if config.leet.is_some() {
+        let other = [
+            (config.url_safe, "--url-safe"),
+            (config.digits_only, "--digits-only"),
+            (config.preset.is_some(), "--preset"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.words.is_some(), "--words"),
+            (config.secure, "-s/--secure"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!(
+                "--leet replaces letters in memorable passwords and cannot be used with {}",
+                flag
+            )));
+        }
+    }
+
+    // Слоги из файла заменяют генератор запоминаемых паролей
+    if config.syllable_file.is_some() {
+        let other = [
+            (config.memorable_model == MemorableModel::Markov, "--memorable-model markov"),
+            (config.no_vowels, "-v/--no-vowels"),
+            (config.url_safe, "--url-safe"),
+            (config.digits_only, "--digits-only"),
+            (config.preset.is_some(), "--preset"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.balanced, "--balanced"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.secure, "-s/--secure"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --syllable-file", flag)));
+        }
+    }
+
+    // Слоги собирает генератор запоминаемых паролей, а длина - их сумма
+    if config.syllable_count.is_some() {
+        let other = [
+            (config.words.is_some(), "--words"),
+            (config.template.is_some(), "--template"),
+            (config.blocks.is_some(), "--blocks"),
+            (config.pin, "--pin"),
+            (config.bits.is_some(), "--bits"),
+            (config.length_includes_affixes, "--length-includes-affixes"),
+            (config.memorable_model == MemorableModel::Markov, "--memorable-model markov"),
+            (config.no_vowels, "-v/--no-vowels"),
+            (config.url_safe, "--url-safe"),
+            (config.digits_only, "--digits-only"),
+            (config.preset.is_some(), "--preset"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.balanced, "--balanced"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.secure, "-s/--secure"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --syllables", flag)));
+        }
+    }
+
+    // Шаблон сам задает класс каждой позиции
+    if config.template.is_some() {
+        let other = [
+            (config.words.is_some(), "--words"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.balanced, "--balanced"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.class_counts != ClassCounts::default(), "--min-*/--max-*"),
+            (config.require_chars.is_some(), "--require-chars"),
+            (config.case.is_some(), "--case"),
+            (config.leet.is_some(), "--leet"),
+            (config.memorable_model == MemorableModel::Markov, "--memorable-model markov"),
+            (config.syllable_file.is_some(), "--syllable-file"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --template", flag)));
+        }
+    }
+
+    // Блоки сами задают длину и классы, а слова берут у генератора
+    // запоминаемых паролей
+    if config.blocks.is_some() {
+        let other = [
+            (config.words.is_some(), "--words"),
+            (config.template.is_some(), "--template"),
+            (config.pin, "--pin"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.balanced, "--balanced"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.url_safe, "--url-safe"),
+            (config.alpha_only, "--alpha-only"),
+            (config.digits_only, "--digits-only"),
+            (config.preset.is_some(), "--preset"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.include_only.is_some(), "--include-only"),
+            (config.charset_file.is_some(), "--charset-file"),
+            (config.class_counts != ClassCounts::default(), "--min-*/--max-*"),
+            (config.require_chars.is_some(), "--require-chars"),
+            (config.no_leading_digit, "--no-leading-digit"),
+            (config.no_leading_symbol, "--no-leading-symbol"),
+            (config.no_edge_symbols, "--no-edge-symbols"),
+            (config.no_repeat, "--no-repeat"),
+            (config.case.is_some(), "--case"),
+            (config.leet.is_some(), "--leet"),
+            (config.bits.is_some(), "--bits"),
+            (config.secure, "-s/--secure"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --blocks", flag)));
+        }
+    }
+
+    // Форма старого пароля задает длину и класс каждой позиции
+    if config.like.is_some() || config.like_stdin {
+        let other = [
+            (config.words.is_some(), "--words"),
+            (config.template.is_some(), "--template"),
+            (config.blocks.is_some(), "--blocks"),
+            (config.pin, "--pin"),
+            (config.syllable_count.is_some(), "--syllables"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.balanced, "--balanced"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.class_counts != ClassCounts::default(), "--min-*/--max-*"),
+            (config.require_chars.is_some(), "--require-chars"),
+            (config.no_leading_digit, "--no-leading-digit"),
+            (config.no_leading_symbol, "--no-leading-symbol"),
+            (config.no_edge_symbols, "--no-edge-symbols"),
+            (config.no_repeat, "--no-repeat"),
+            (config.case.is_some(), "--case"),
+            (config.leet.is_some(), "--leet"),
+            (config.bits.is_some(), "--bits"),
+            (config.length_includes_affixes, "--length-includes-affixes"),
+            (config.memorable_model == MemorableModel::Markov, "--memorable-model markov"),
+            (config.syllable_file.is_some(), "--syllable-file"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --like", flag)));
+        }
+    }
+
+    // Длину по --bits подбирает main, а эти режимы задают ее сами. Число
+    // слов фразы из --lang или --wordlist подбирается так же, а явное
+    // --words N задает его само
+    if config.bits.is_some() {
+        let other = [
+            (given("--words"), "--words"),
+            (config.acrostic.is_some(), "--words-acrostic"),
+            (config.template.is_some(), "--template"),
+            (config.classes.is_some(), "--classes"),
+            (config.preset.is_some(), "--preset"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --bits", flag)));
+        }
+    }
+
+    if config.group_counts_length && config.group.is_none() {
+        return Err(CliError::ConflictingFlags("--group-counts-length requires --group".to_string()));
+    }
+    if given("--clear-after") && !config.copy {
+        return Err(CliError::ConflictingFlags("--clear-after requires --copy".to_string()));
+    }
+    // Разделители вычитаются из pw_length, а эти режимы задают длину сами
+    if config.group_counts_length {
+        let other = [
+            (config.words.is_some(), "--words"),
+            (config.acrostic.is_some(), "--words-acrostic"),
+            (config.template.is_some(), "--template"),
+            (config.blocks.is_some(), "--blocks"),
+            (config.like.is_some() || config.like_stdin, "--like"),
+            (config.classes.is_some(), "--classes"),
+            (config.syllable_count.is_some(), "--syllables"),
+            (config.bits.is_some(), "--bits"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --group-counts-length", flag)));
+        }
+    }
+
+    // В этих режимах длину задает не pw_length, и вычитать аффиксы не из чего
+    if config.length_includes_affixes {
+        let other = [
+            (config.words.is_some(), "--words"),
+            (config.acrostic.is_some(), "--words-acrostic"),
+            (config.template.is_some(), "--template"),
+            (config.blocks.is_some(), "--blocks"),
+            (config.classes.is_some(), "--classes"),
+            (config.bits.is_some(), "--bits"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --length-includes-affixes", flag)));
+        }
+    }
+
+    // PIN - только цифры, все десять, и собирается без apply_requirements
+    if config.pin {
+        let other = [
+            (config.words.is_some(), "--words"),
+            (config.template.is_some(), "--template"),
+            (config.classes.is_some(), "--classes"),
+            (config.weights.is_some(), "--weights"),
+            (config.balanced, "--balanced"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.url_safe, "--url-safe"),
+            (config.alpha_only, "--alpha-only"),
+            (config.digits_only, "--digits-only"),
+            (config.preset.is_some(), "--preset"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.include_only.is_some(), "--include-only"),
+            (config.charset_file.is_some(), "--charset-file"),
+            (given("-c"), "-c/--capitalize"),
+            (given("-0"), "-0/--no-numerals"),
+            (config.symbols, "-y/--symbols"),
+            (config.no_vowels, "-v/--no-vowels"),
+            (config.ambiguous, "-B/--ambiguous"),
+            (config.remove_chars.is_some(), "-r/--remove-chars"),
+            (config.class_counts != ClassCounts::default(), "--min-*/--max-*"),
+            (config.require_chars.is_some(), "--require-chars"),
+            (config.no_leading_digit, "--no-leading-digit"),
+            (config.no_repeat, "--no-repeat"),
+            (config.case.is_some(), "--case"),
+            (config.leet.is_some(), "--leet"),
+            (config.memorable_model == MemorableModel::Markov, "--memorable-model markov"),
+            (config.syllable_file.is_some(), "--syllable-file"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --pin", flag)));
+        }
+    }
+
+    // Соседей проверяет apply_requirements, а эти режимы собирают пароль без
+    // нее или меняют его после: --case сделал бы из "aA" "aa"
+    if config.no_repeat {
+        let other = [
+            (config.template.is_some(), "--template"),
+            (config.words.is_some(), "--words"),
+            (config.classes.is_some(), "--classes"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.case.is_some(), "--case"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --no-repeat", flag)));
+        }
+    }
+
+    // Во фразе нет классов символов: флаги классов и наборов к ней неприменимы
+    if config.words.is_some() {
+        let other = [
+            (given("-c"), "-c/--capitalize"),
+            (given("-n"), "-n/--numerals"),
+            (config.symbols, "-y/--symbols"),
+            (config.no_vowels, "-v/--no-vowels"),
+            (config.url_safe, "--url-safe"),
+            (config.alpha_only, "--alpha-only"),
+            (config.digits_only, "--digits-only"),
+            (config.preset.is_some(), "--preset"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.charset_file.is_some(), "--charset-file"),
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.balanced, "--balanced"),
+            (config.mobile_friendly, "--mobile-friendly"),
+            (config.class_counts != ClassCounts::default(), "--min-*/--max-*"),
+            (config.require_chars.is_some(), "--require-chars"),
+            (config.case.is_some(), "--case"),
+            (config.memorable_model == MemorableModel::Markov, "--memorable-model markov"),
+            (config.syllable_file.is_some(), "--syllable-file"),
+            (config.secure, "-s/--secure"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --words", flag)));
+        }
+    }
+
+    if given("--words") && given("--words-acrostic") {
+        return Err(CliError::ConflictingFlags(
+            "--words cannot be used with --words-acrostic, whose letters set the number of words".to_string(),
+        ));
+    }
+
+    if config.lang.is_some() && config.wordlist_file.is_some() {
+        return Err(CliError::ConflictingFlags(
+            "--lang selects an embedded wordlist and cannot be used with --wordlist".to_string(),
+        ));
+    }
+
+    // Ни один символ не стоит на одном месте на всех трех раскладках
+    if config.layout_safe && config.symbols {
+        return Err(CliError::ConflictingFlags(
+            "-y/--symbols conflicts with --layout-safe, which keeps no symbols".to_string(),
+        ));
+    }
+
+    if config.no_shift && given("-c") {
+        return Err(CliError::ConflictingFlags(
+            "-c/--capitalize cannot be used with --no-shift".to_string(),
+        ));
+    }
+
+    // У --mobile-friendly свой набор символов и свой порядок классов:
+    // символы всегда идут последними
+    if config.mobile_friendly {
+        let other = [
+            (given("-c"), "-c/--capitalize"),
+            (config.require_chars.is_some(), "--require-chars"),
+            (config.class_counts != ClassCounts::default(), "--min-*/--max-*"),
+            (config.url_safe, "--url-safe"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.include_only.is_some(), "--include-only"),
+            (config.charset_file.is_some(), "--charset-file"),
+            (config.no_edge_symbols, "--no-edge-symbols"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --mobile-friendly", flag)));
+        }
+    }
+
+    if config.charset_file.is_some() && (config.include_only.is_some() || config.charset_preset.is_some()) {
+        return Err(CliError::ConflictingFlags(
+            "--charset-file cannot be combined with --include-only or --charset".to_string(),
+        ));
+    }
+
+    // Веса задают классы сами: флаг класса не должен им противоречить
+    if let Some([lower, upper, digits, symbols]) = config.weights {
+        let classes = [
+            (given("-c") && upper == 0, "-c/--capitalize"),
+            (given("-A") && upper > 0, "-A/--no-capitalize"),
+            (given("-n") && digits == 0, "-n/--numerals"),
+            (given("-0") && digits > 0, "-0/--no-numerals"),
+            (config.symbols && symbols == 0, "-y/--symbols"),
+        ];
+        if let Some((_, flag)) = classes.iter().find(|(conflict, _)| *conflict) {
+            return Err(CliError::ConflictingFlags(format!(
+                "{} conflicts with --weights {}:{}:{}:{}",
+                flag, lower, upper, digits, symbols
+            )));
+        }
+        let other = [
+            (config.charset_preset.is_some(), "--charset"),
+            (config.include_only.is_some(), "--include-only"),
+            (config.charset_file.is_some(), "--charset-file"),
+            (config.url_safe, "--url-safe"),
+            (config.mobile_friendly, "--mobile-friendly"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --weights", flag)));
+        }
+    }
+
+    // Потребовать символ, который сами же убрали, нельзя
+    if let (Some(require_chars), Some(remove_chars)) = (&config.require_chars, &config.remove_chars)
+        && let Some(c) = require_chars.iter().find(|c| remove_chars.contains(c))
+    {
+        return Err(CliError::ConflictingFlags(format!(
+            "--require-chars {:?} conflicts with --remove-chars, which removes it",
+            c
+        )));
+    }
+
+    // --balanced сам распределяет символы по классам
+    if config.balanced {
+        let other = [
+            (config.weights.is_some(), "--weights"),
+            (config.classes.is_some(), "--classes"),
+            (config.class_counts != ClassCounts::default(), "--min-*/--max-*"),
+            (config.mobile_friendly, "--mobile-friendly"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --balanced", flag)));
+        }
+    }
+
+    // --classes задает и набор, и число символов каждого класса
+    if let Some(counts) = config.classes {
+        let spec = classes_spec(&counts);
+        let classes = [
+            (given("-c") && counts[1] == 0, "-c/--capitalize"),
+            (given("-A") && counts[1] > 0, "-A/--no-capitalize"),
+            (given("-n") && counts[2] == 0, "-n/--numerals"),
+            (given("-0") && counts[2] > 0, "-0/--no-numerals"),
+            (config.symbols && counts[3] == 0, "-y/--symbols"),
+        ];
+        if let Some((_, flag)) = classes.iter().find(|(conflict, _)| *conflict) {
+            return Err(CliError::ConflictingFlags(format!("{} conflicts with --classes {}", flag, spec)));
+        }
+        let exact = ClassCounts {
+            min: counts,
+            max: counts.map(Some),
+        };
+        let other = [
+            (config.class_counts != exact, "--min-*/--max-*"),
+            (config.weights.is_some(), "--weights"),
+            (config.require_chars.is_some(), "--require-chars"),
+            (config.charset_preset.is_some(), "--charset"),
+            (config.include_only.is_some(), "--include-only"),
+            (config.charset_file.is_some(), "--charset-file"),
+            (config.url_safe, "--url-safe"),
+            (config.mobile_friendly, "--mobile-friendly"),
+        ];
+        if let Some((_, flag)) = other.iter().find(|(given, _)| *given) {
+            return Err(CliError::ConflictingFlags(format!("{} cannot be used with --classes", flag)));
+        }
+    }
+
+    // --min-* и --max-* не должны противоречить друг другу, флагам классов
+    // и --require-chars
+    let counts = &config.class_counts;
+    for (i, (suffix, class, _, plural)) in COUNTED_CLASSES.iter().enumerate() {
+        let min = counts.min[i];
+        if let Some(max) = counts.max[i]
+            && min > max
+        {
+            return Err(CliError::ConflictingFlags(format!(
+                "--min-{} {} conflicts with --max-{} {}",
+                suffix, min, suffix, max
+            )));
+        }
+        // Флаг, который включает класс, и флаг, который его выключает
+        let (include, exclude) = match i {
+            1 => ((given("-c"), "-c/--capitalize"), (given("-A"), "-A/--no-capitalize")),
+            2 => ((given("-n"), "-n/--numerals"), (given("-0"), "-0/--no-numerals")),
+            3 => ((config.symbols, "-y/--symbols"), (false, "")),
+            _ => ((false, ""), (false, "")),
+        };
+        if include.0 && counts.max[i] == Some(0) {
+            return Err(CliError::ConflictingFlags(format!("{} conflicts with --max-{} 0", include.1, suffix)));
+        }
+        if exclude.0 && min > 0 {
+            return Err(CliError::ConflictingFlags(format!("{} conflicts with --min-{} {}", exclude.1, suffix, min)));
+        }
+        let required = config.require_chars.iter().flatten().filter(|&&c| in_class(class, c)).count();
+        if let Some(max) = counts.max[i]
+            && required > max
+        {
+            return Err(CliError::ConflictingFlags(format!(
+                "--require-chars puts {} {} in every password, more than --max-{} {}",
+                required, plural, suffix, max
+            )));
+        }
+    }
+
+    // Класс, которого нет в алфавите --charset, потребовать нельзя
+    if let Some((name, alphabet)) = config.charset_preset {
+        if config.include_only.is_some() {
+            return Err(CliError::ConflictingFlags(
+                "--charset and --include-only cannot be used together".to_string(),
+            ));
+        }
+        let classes = [
+            (config.symbols, "-y/--symbols", symbol_class(config), "symbols"),
+            (given("-n"), "-n/--numerals", NUMERALS, "digits"),
+            (given("-c"), "-c/--capitalize", UPPERCASE, "capital letters"),
+        ];
+        for (requested, flag, class, what) in classes {
+            if requested && !class.iter().any(|c| alphabet.contains(c)) {
+                return Err(CliError::ConflictingFlags(format!(
+                    "{} conflicts with --charset {}, which has no {}",
+                    flag, name, what
+                )));
+            }
+        }
+    }
+
+    // Обе опции задают число паролей в строке, но по-разному, и -1 с
+    // --per-line друг другу противоречат
+    if given("--per-line") {
+        if given("-1") {
+            return Err(CliError::ConflictingFlags(
+                "--per-line and -1 conflict: -1 prints one password per line".to_string(),
+            ));
+        }
+        if given("--columns") {
+            return Err(CliError::ConflictingFlags(
+                "--per-line and --columns conflict: --columns N is a number of columns that is reduced to fit \
+                 the terminal, --per-line N puts exactly N passwords on every line whatever the width"
+                    .to_string(),
+            ));
+        }
+    }
+
+    check_required_chars(config)?;
+
+    // -C и -1 вместе - не ошибка: действует последний
+    if config.verbose && given("-C") && given("-1") {
+        let last = seen.iter().rev().find(|&&flag| flag == "-C" || flag == "-1").unwrap();
+        eprintln!("Note: both -C and -1 given; the last one ({}) wins", last);
+    }
+    Ok(())
+}
+
+// Символ из --require-chars может быть не из набора (например, _ без -y),
+// но не из тех, что набор режима исключает: -A, -B, -v, --url-safe,
+// --charset, --include-only, --no-shift и другие. Набор из --charset-file
+// читается позже, и main проверяет его отдельно.
+fn check_required_chars(config: &Config) -> Result<(), CliError> {
+    let Some(require_chars) = &config.require_chars else {
+        return Ok(());
+    };
+    let allowed = build_charset(&Config { symbols: true, ..config.clone() });
+    match require_chars.iter().find(|c| !allowed.contains(c)) {
+        Some(c) => Err(CliError::ConflictingFlags(format!(
+            "--require-chars {:?} conflicts with the other options, which exclude it from the character set",
+            c
+        ))),
+        None => Ok(()),
+    }
+}
+
+// Положительное число не больше max; опечатка вроде "1o" - ошибка, а не
+// молчаливое значение по умолчанию
+fn parse_count(arg: &str, what: &'static str, max: usize) -> Result<usize, CliError> {
+    match arg.parse::<usize>() {
+        Ok(n) if (1..=max).contains(&n) => Ok(n),
+        _ => Err(CliError::InvalidNumber {
+            what,
+            value: arg.to_string(),
+            max: Some(max),
+        }),
+    }
+}
+
+// Опции, которые запрещают символы на краях пароля, для сообщений
+fn edge_options(config: &Config) -> String {
+    let flags = [
+        (config.no_leading_digit, "--no-leading-digit"),
+        (config.no_leading_symbol, "--no-leading-symbol"),
+        (config.no_edge_symbols, "--no-edge-symbols"),
+    ];
+    let given: Vec<&str> = flags.iter().filter(|(given, _)| *given).map(|(_, flag)| *flag).collect();
+    given.join("/")
+}
+
+// edge - "start" или "end"
+fn edge_error(config: &Config, edge: &str) -> RunError {
+    RunError::Settings(format!("no character is left to {} a password with ({})", edge, edge_options(config)))
+}
+
+fn empty_charset_error() -> RunError {
+    RunError::Settings("character set is empty after applying --no-capitalize/--no-vowels/--remove-chars".to_string())
+}
+
+// --charset-file: каждый отличный от пробельных символ файла входит в
+// набор один раз. Файл должен быть в UTF-8, иначе непонятно, какие
+// символы имелись в виду.
+fn load_charset_file(path: &Path) -> Result<Vec<char>, RunError> {
+    let bytes = fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("cannot read {}: {}", path.display(), e)))?;
+    let text = str::from_utf8(&bytes).map_err(|e| {
+        RunError::Settings(format!("{}: invalid UTF-8 at byte {}", path.display(), e.valid_up_to()))
+    })?;
+    if let Some((pos, c)) = text.char_indices().find(|(_, c)| c.is_control() && !c.is_whitespace()) {
+        return Err(RunError::Settings(format!(
+            "{}: control character {:?} at byte {} cannot be part of a password",
+            path.display(),
+            c,
+            pos
+        )));
+    }
+    let mut chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.sort_unstable();
+    chars.dedup();
+    if chars.is_empty() {
+        return Err(RunError::Settings(format!("{} contains no characters", path.display())));
+    }
+    Ok(chars)
+}
+
+// Самая короткая длина пароля: нижняя граница --length-range или pw_length
+fn shortest_length(config: &Config) -> usize {
+    config.length_range.map_or(config.pw_length, |(min, _)| min)
+}
+
+// Самый длинный пароль в символах: с --syllables - из самых длинных слогов
+fn longest_length(config: &Config) -> usize {
+    match config.syllable_count {
+        Some(count) => count * syllables::lengths(&syllables::inventory(config)).1,
+        None => config.pw_length,
+    }
+}
+
+// Набор символов и длина, с которыми пароль вообще можно составить
+fn check_settings(config: &Config) -> Result<(), RunError> {
+    if config.no_dictionary_words.is_some() && dictionary::TEXT.is_none() {
+        return Err(RunError::Settings(dictionary::missing()));
+    }
+    // Для фразы достаточно двух слов в списке
+    if let Some(count) = config.words {
+        wordlist::Style::new(config)?;
+        return wordlist::Slots::new(count, wordlist::words(config)?, config.acrostic.as_deref()).map(|_| ());
+    }
+    // У шаблона каждый класс проверяется отдельно
+    if let Some(template) = &config.template {
+        return template.pools(config).map(|_| ());
+    }
+    if let Some(shape) = &config.like {
+        return shape.pools(config).map(|_| ());
+    }
+    // Блоки проверяют и свои классы, и обязательные
+    if let Some(spec) = &config.blocks {
+        return spec.parts(config).map(|_| ());
+    }
+    let charset = build_charset(config);
+    if charset.is_empty() {
+        return Err(empty_charset_error());
+    }
+    // Длина пароля из слогов - от count самых коротких до count самых длинных
+    if let Some(count) = config.syllable_count {
+        let list = syllables::inventory(config);
+        if list.is_empty() {
+            return Err(none_left_error("syllables"));
+        }
+        let (shortest, longest) = syllables::lengths(&list);
+        return check_limits(config, &charset, count * shortest, count * longest);
+    }
+    let shortest = shortest_length(config);
+    if shortest == 0 {
+        return Err(RunError::Settings("password length must be at least 1".to_string()));
+    }
+    if config.pin && shortest < pin::MIN_LENGTH {
+        return Err(RunError::Settings(format!(
+            "--pin needs at least {} digits, not {}; shorter PINs are almost all weak",
+            pin::MIN_LENGTH,
+            shortest
+        )));
+    }
+    // Из одного символа пароль длиннее одного символа без повторов не составить
+    if config.no_repeat && charset.len() < 2 && config.pw_length > 1 {
+        return Err(RunError::Settings(format!(
+            "--no-repeat needs at least 2 characters in the character set, but only {:?} is left",
+            charset.iter().collect::<String>()
+        )));
+    }
+    // Каждая достижимая строка модели должна пережить -B и -r
+    if !config.secure && !config.no_vowels && config.memorable_model == MemorableModel::Markov {
+        markov::Model::new(config)?;
+    }
+    // Минимумы должны выполняться у самых коротких паролей диапазона, а
+    // максимумы - у самых длинных
+    check_limits(config, &charset, shortest, config.pw_length)
+}
+
+// Буквы, которых не осталось для запоминаемого пароля
+fn none_left_error(name: &str) -> RunError {
+    RunError::Settings(format!(
+        "no {} left after applying --ambiguous/--remove-chars/--include-only",
+        name
+    ))
+}
+
+fn generate_passwords(config: &Config) -> Result<Passwords, RunError> {
+    let generator = Generator::new(config)?;
+    let mut distinct = Distinct::new(config)?;
+    let mut passwords = Passwords::with_capacity(config.num_pw, generator.capacity());
+    if config.lock_memory
+        && let Err(e) = passwords.lock()
+    {
+        eprintln!("WARNING: cannot lock password memory: {}", e);
+        eprintln!("WARNING: continuing without --lock-memory; passwords may be swapped to disk");
+    }
+    if config.verbose && passwords.is_locked() {
+        eprintln!("Password memory: locked");
+    }
+    let mut rng = open_rng(config)?;
+
+    for _ in 0..config.num_pw {
+        let password = match &mut distinct {
+            Some(distinct) => distinct.generate(&generator, rng.as_mut())?,
+            None => generator.generate(rng.as_mut())?,
+        };
+        passwords.push(password);
+    }
+    if config.sort {
+        passwords.sort();
+    }
+
+    Ok(passwords)
+}
+
+// --format jsonl: каждый пароль уходит в emit сразу после генерации, так
+// что пакет целиком в памяти не собирается, а читатель получает строки, не
+// дожидаясь конца. emit забирает пароль и сам его затирает.
+fn stream_passwords<F>(config: &Config, mut emit: F) -> Result<(), RunError>
+where
+    F: FnMut(String) -> Result<(), RunError>,
+{
+    let generator = Generator::new(config)?;
+    let mut distinct = Distinct::new(config)?;
+    let mut rng = open_rng(config)?;
+    for _ in 0..config.num_pw {
+        emit(match &mut distinct {
+            Some(distinct) => distinct.generate(&generator, rng.as_mut())?,
+            None => generator.generate(rng.as_mut())?,
+        })?;
+    }
+    Ok(())
+}
+
+// --unique: повтор уже выданного пароля генерируется заново. В множестве
+// не сами пароли, а их хеши со случайным ключом, так что копий паролей в
+// куче не остается; совпадение хешей разных паролей стоит лишней попытки.
+struct Distinct {
+    seen: HashSet<u64>,
+    keys: RandomState,
+    // Сколько повторов подряд допустимо, прежде чем сдаться
+    patience: usize,
+}
+
+impl Distinct {
+    // None без --unique; ошибка, если таких паролей заведомо меньше, чем
+    // num_pw. Заведомо - только у -s и --pin, где possible_passwords
+    // считает их точно; в остальных режимах это оценка, и сдаться решает
+    // patience.
+    fn new(config: &Config) -> Result<Option<Self>, RunError> {
+        if !config.unique {
+            return Ok(None);
+        }
+        let possible = possible_passwords(config);
+        if let Some(possible) = possible
+            && matches!(dry_run::mode_name(config), "secure" | "pin")
+            && config.num_pw as f64 > possible + 0.5
+        {
+            return Err(RunError::Settings(format!(
+                "--unique: these settings give only {:.0} different passwords, fewer than {}",
+                possible, config.num_pw
+            )));
+        }
+        // Когда из possible паролей выдано почти num_pw, новый выпадает в
+        // среднем раз в possible / (possible - num_pw + 1) попыток; в 20 раз
+        // больше не хватает с вероятностью около e^-20
+        let patience = possible.map_or(reject::MAX_ATTEMPTS, |possible| {
+            let left = (possible - config.num_pw as f64 + 1.0).max(1.0);
+            reject::MAX_ATTEMPTS.max((20.0 * possible / left) as usize)
+        });
+        Ok(Some(Self {
+            seen: HashSet::with_capacity(config.num_pw),
+            keys: RandomState::new(),
+            patience,
+        }))
+    }
+
+    fn generate(&mut self, generator: &Generator, rng: &mut dyn Read) -> Result<String, RunError> {
+        for _ in 0..self.patience {
+            let mut password = generator.generate(rng)?;
+            if self.seen.insert(self.keys.hash_one(password.as_str())) {
+                return Ok(password);
+            }
+            secret::wipe_str(&mut password);
+        }
+        Err(RunError::Generation(format!(
+            "--unique: no new password in {} attempts after {} different ones; generate fewer or longer passwords",
+            self.patience,
+            self.seen.len()
+        )))
+    }
+}
+
+// Сколько разных паролей дают настройки: 2 в степени энтропии на каждую
+// длину из --length-range. Для равновероятных режимов (-s, --pin, шаблоны,
+// слова) это точное число, для запоминаемых - оценка снизу. None, если
+// энтропию не посчитать.
+fn possible_passwords(config: &Config) -> Option<f64> {
+    (shortest_length(config)..=config.pw_length)
+        .map(|length| dry_run::password_entropy(config, length).map(f64::exp2))
+        .sum()
+}
+
+// То, что готовится один раз на весь пакет: слова для --words, позиции
+// шаблона и --like, части --blocks
+struct Generator<'a> {
+    config: &'a Config,
+    slots: Option<wordlist::Slots>,
+    style: wordlist::Style,
+    pools: Option<Vec<Vec<char>>>,
+    blocks: Option<(&'a blocks::Spec, blocks::Parts)>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(config: &'a Config) -> Result<Self, RunError> {
+        // Проверяем набор символов и длину до открытия источника и генерации
+        check_settings(config)?;
+
+        let slots = match config.words {
+            Some(count) => Some(wordlist::Slots::new(count, wordlist::words(config)?, config.acrostic.as_deref())?),
+            None => None,
+        };
+        let style = wordlist::Style::new(config)?;
+        // --like собирается по позициям так же, как шаблон
+        let pools = match (&config.template, &config.like) {
+            (Some(template), _) => Some(template.pools(config)?),
+            (None, Some(shape)) => Some(shape.pools(config)?),
+            (None, None) => None,
+        };
+        let blocks = match &config.blocks {
+            Some(spec) => Some((spec, spec.parts(config)?)),
+            None => None,
+        };
+        Ok(Self {
+            config,
+            slots,
+            style,
+            pools,
+            blocks,
+        })
+    }
+
+    // Сколько байтов может занять один пароль вместе с --prefix и --suffix
+    fn capacity(&self) -> usize {
+        let config = self.config;
+        let capacity = match (&self.slots, &self.pools, &self.blocks) {
+            // Заглавная буква и вставки добавляют не больше 8 байтов
+            (Some(slots), _, _) => {
+                let longest = slots.words().iter().map(String::len).max().unwrap_or(0);
+                slots.count() * (longest + self.style.separator.len()) + 8
+            }
+            // Буквальные символы шаблона могут быть не из набора
+            (None, Some(pools), _) => {
+                pools.iter().map(|pool| pool.iter().map(|c| c.len_utf8()).max().unwrap_or(1)).sum()
+            }
+            (None, None, Some((spec, _))) => spec.byte_len(),
+            // Символы из --charset-file могут занимать до 4 байтов
+            _ => longest_length(config) * build_charset(config).iter().map(|c| c.len_utf8()).max().unwrap_or(1),
+        };
+        let affixes: usize = [&config.prefix, &config.suffix].iter().filter_map(|a| a.as_ref()).map(String::len).sum();
+        // Символов в пароле не больше, чем байтов, так что разделителей
+        // не больше, чем у пароля из capacity символов
+        let separators = config.group.as_ref().map_or(0, |grouping| grouping.extra_bytes(capacity));
+        capacity + separators + affixes
+    }
+
+    fn generate(&self, rng: &mut dyn Read) -> Result<String, RunError> {
+        let config = self.config;
+        signals::check()?;
+        // Длина из диапазона берется из того же источника, что и символы
+        let length = match config.length_range {
+            Some((min, max)) => min + random_index(rng, max - min + 1)?,
+            None => config.pw_length,
+        };
+        let generate = || {
+            // Начала слогов для --case title; в остальных режимах слог один
+            let mut starts = vec![0];
+            let password = if let Some(slots) = &self.slots {
+                wordlist::generate(slots, &self.style, rng)?
+            } else if let Some(pools) = &self.pools {
+                template::generate(pools, rng)?
+            } else if let Some((spec, parts)) = &self.blocks {
+                spec.generate(parts, rng)?
+            } else if config.pin {
+                pin::generate(length, rng)?
+            } else if config.mobile_friendly {
+                mobile::generate(length, config, rng)?
+            } else if config.classes.is_some() {
+                generate_class_spec_password(config, rng)?
+            } else if config.secure {
+                generate_secure_password(length, config, rng)?
+            } else {
+                let (password, syllables) = generate_syllables(length, config, rng)?;
+                starts = syllables;
+                password
+            };
+            match config.case {
+                Some(case) => apply_case(password, case, &starts, config, rng),
+                None => Ok(password),
+            }
+        };
+        // Цепочки вроде "abc" и "qwer" и слова проверяются у готового
+        // пароля, после apply_requirements и --case
+        let password = reject::retry(config, generate)?;
+        let password = match &config.group {
+            Some(grouping) => grouping.apply(password),
+            None => password,
+        };
+        Ok(with_affixes(password, config))
+    }
+}
+
+// --prefix и --suffix добавляются к готовому паролю: apply_requirements и
+// отбраковка видят только случайную часть, так что цифра в "svc1-" не
+// заменяет цифру, которую требует -n. Копия без них затирается.
+fn with_affixes(mut password: String, config: &Config) -> String {
+    if config.prefix.is_none() && config.suffix.is_none() {
+        return password;
+    }
+    let prefix = config.prefix.as_deref().unwrap_or_default();
+    let suffix = config.suffix.as_deref().unwrap_or_default();
+    let mut whole = String::with_capacity(prefix.len() + password.len() + suffix.len());
+    whole.push_str(prefix);
+    whole.push_str(&password);
+    whole.push_str(suffix);
+    secret::wipe_str(&mut password);
+    whole
+}
+
+// Сколько символов добавляют --prefix и --suffix
+fn affix_length(config: &Config) -> usize {
+    [&config.prefix, &config.suffix]
+        .iter()
+        .filter_map(|affix| affix.as_deref())
+        .map(|affix| affix.chars().count())
+        .sum()
+}
+
+// Источник случайности по --seed, -H, --rng и --fast-rng
+fn open_rng(config: &Config) -> io::Result<Box<dyn Read>> {
+    let (mut rng, mut source_name): (Box<dyn Read>, String) = match (config.seed, &config.sha1) {
+        (Some(seed), _) => (
+            Box::new(ChaCha20Rng::seeded(seed)),
+            format!("ChaCha20 with fixed seed {}", seed),
+        ),
+        (None, Some(spec)) => (
+            Box::new(Sha1Stream::from_file(&spec.path, &spec.seed)?),
+            format!("sha1 of {}", spec.path.display()),
+        ),
+        (None, None) => rng::open(&config.rng)?,
+    };
+    // Поток от --seed и так генерируется в userspace
+    if config.fast_rng && config.seed.is_none() {
+        rng = Box::new(ChaCha20Rng::new(rng)?);
+        source_name = format!("ChaCha20 seeded from {}", source_name);
+    }
+    if config.verbose {
+        eprintln!("Random source: {}", source_name);
+    }
+    Ok(rng)
+}
+
+fn generate_secure_password<R: EntropySource + ?Sized>(length: usize, config: &Config, rng: &mut R) -> Result<String, RunError> {
+    let charset = build_charset(config);
+    if charset.is_empty() {
+        return Err(empty_charset_error());
+    }
+
+    let mut password: Vec<char> = Vec::with_capacity(length);
+
+    if config.weights.is_some() {
+        // Сначала класс по весам, затем символ внутри класса, оба выбора равномерные
+        let pools = weighted_pools(config, &charset);
+        let total: usize = pools.iter().map(|(weight, _)| weight).sum();
+        while password.len() < length {
+            let mut pick = random_index(rng, total)?;
+            let (_, pool) = pools
+                .iter()
+                .find(|(weight, _)| {
+                    let found = pick < *weight;
+                    pick = pick.saturating_sub(*weight);
+                    found
+                })
+                .expect("pick is below the total weight");
+            let c = pool[random_index(rng, pool.len())?];
+            // С --no-repeat повтор предыдущего символа выбирается заново,
+            // вместе с классом, если у классов есть другие символы
+            let last = password.last().copied();
+            if config.no_repeat && Some(c) == last && pools.iter().any(|(_, pool)| pool.iter().any(|&o| o != c)) {
+                continue;
+            }
+            password.push(c);
+        }
+        return apply_requirements(password, config, rng);
+    }
+
+    if config.balanced {
+        // Без отбраковки: число символов каждого класса задано заранее.
+        // Если классов больше длины, обязательные доставит apply_requirements.
+        let pools: Vec<Vec<char>> = class_pools(&charset).into_iter().filter(|pool| !pool.is_empty()).collect();
+        let counts = balanced_counts(length, pools.len(), rng)?;
+        let password = shuffled_classes(&counts, &pools, rng)?;
+        return apply_requirements(password, config, rng);
+    }
+
+    // Первый и последний символы - из тех, которые могут стоять на краю
+    let leading: Vec<char> = charset.iter().cloned().filter(|&c| can_lead(c, config)).collect();
+    let ending: Vec<char> = charset.iter().cloned().filter(|&c| can_end(c, config)).collect();
+    for i in 0..length {
+        let (pool, edge) = if i == 0 {
+            (&leading, "start")
+        } else if i + 1 == length {
+            (&ending, "end")
+        } else {
+            (&charset, "")
+        };
+        if pool.is_empty() {
+            return Err(edge_error(config, edge));
+        }
+        password.push(draw(pool, password.last().copied(), config, rng)?);
+    }
+
+    // Как и в pwgen, -c, -n и -y гарантируют класс и в режиме -s
+    apply_requirements(password, config, rng)
+}
+
+// Случайный символ из pool. С --no-repeat символ, равный предыдущему,
+// выбирается заново, пока в pool есть другие.
+fn draw<R: EntropySource + ?Sized>(pool: &[char], last: Option<char>, config: &Config, rng: &mut R) -> io::Result<char> {
+    loop {
+        let c = pool[random_index(rng, pool.len())?];
+        if !config.no_repeat || Some(c) != last || pool.iter().all(|&o| o == c) {
+            return Ok(c);
+        }
+    }
+}
+
+// --classes: ровно столько символов каждого класса, сколько задано.
+// Символы выбираются класс за классом, затем вся последовательность
+// перемешивается (Фишер - Йейтс), так что все расстановки равновероятны.
+fn generate_class_spec_password<R: EntropySource + ?Sized>(config: &Config, rng: &mut R) -> Result<String, RunError> {
+    let counts = config.classes.unwrap_or_default();
+    let pools = class_pools(&build_charset(config));
+    let mut password = shuffled_classes(&counts, &pools, rng)?;
+    let mut pinned = vec![false; password.len()];
+    if let Some(edge) = swap_edges(&mut password, &mut pinned, config, rng)? {
+        return Err(edge_error(config, if edge == 0 { "start" } else { "end" }));
+    }
+
+    // Перевыбор внутри класса не меняет число символов класса
+    if let Some(table) = &config.homoglyphs {
+        homoglyph::reroll(&mut password, &pinned, &pools, table, rng)?;
+    }
+
+    let mut result = String::with_capacity(password.iter().map(|c| c.len_utf8()).sum());
+    result.extend(password.iter());
+    secret::wipe_chars(&mut password);
+    Ok(result)
+}
+
+// counts[i] символов из пула i в случайном порядке: символы выбираются
+// класс за классом, затем вся последовательность перемешивается
+// (Фишер - Йейтс), так что все расстановки классов равновероятны
+fn shuffled_classes<R: EntropySource + ?Sized>(
+    counts: &[usize],
+    pools: &[Vec<char>],
+    rng: &mut R,
+) -> Result<Vec<char>, RunError> {
+    let mut password: Vec<char> = Vec::with_capacity(counts.iter().sum());
+    for (&count, pool) in counts.iter().zip(pools) {
+        if count > 0 && pool.is_empty() {
+            return Err(empty_charset_error());
+        }
+        for _ in 0..count {
+            password.push(pool[random_index(rng, pool.len())?]);
+        }
+    }
+    rng::shuffle(rng, &mut password)?;
+    Ok(password)
+}
+
+// --balanced: длина делится между непустыми классами поровну, а остаток
+// достается случайным классам, по символу на класс
+fn balanced_counts<R: EntropySource + ?Sized>(length: usize, classes: usize, rng: &mut R) -> Result<Vec<usize>, RunError> {
+    let mut counts = vec![length / classes; classes];
+    let mut order: Vec<usize> = (0..classes).collect();
+    rng::shuffle(rng, &mut order)?;
+    for &class in &order[..length % classes] {
+        counts[class] += 1;
+    }
+    Ok(counts)
+}
+
+// Запоминаемый пароль и позиции, с которых начинаются его слоги
+type Syllabled = (Vec<char>, Vec<usize>);
+
+fn generate_memorable_password<R: EntropySource + ?Sized>(length: usize, config: &Config, rng: &mut R) -> Result<String, RunError> {
+    generate_syllables(length, config, rng).map(|(password, _)| password)
+}
+
+// Запоминаемый пароль и начала его слогов для --case title. Замены из
+// --leet и apply_requirements меняют символы на месте, так что начала
+// остаются верными. У пароля без гласных слог один, весь пароль.
+fn generate_syllables<R: EntropySource + ?Sized>(
+    length: usize,
+    config: &Config,
+    rng: &mut R,
+) -> Result<(String, Vec<usize>), RunError> {
+    if config.no_vowels {
+        return Ok((generate_consonant_password(length, config, rng)?, vec![0]));
+    }
+
+    let (password, starts) = if let Some(count) = config.syllable_count {
+        syllables::compose(count, &syllables::inventory(config), config, rng)?
+    } else if let Some(list) = &config.syllables {
+        syllables::generate(length, list, rng)?
+    } else {
+        match config.memorable_model {
+            // Заглавные, цифры и символы движок вставляет сам; apply_requirements
+            // доставит то, что не поместилось, и проверит --min-*, -R и края
+            MemorableModel::Phoneme => {
+                if let Some(name) = phoneme::missing(config) {
+                    return Err(none_left_error(name));
+                }
+                phoneme::generate(length, config, rng)?
+            }
+            // Модель дает только буквы: остальное добавит apply_requirements
+            MemorableModel::Markov => markov::generate(length, config, rng)?,
+        }
+    };
+    Ok((finish_memorable(password, config, rng)?, starts))
+}
+
+// --leet до apply_requirements: замененные цифры и символы она засчитает
+// в требования и вставлять свои уже не будет
+fn finish_memorable<R: EntropySource + ?Sized>(
+    mut password: Vec<char>,
+    config: &Config,
+    rng: &mut R,
+) -> Result<String, RunError> {
+    if let Some(count) = config.leet {
+        leet::substitute(&mut password, count, config, rng)?;
+    }
+    apply_requirements(password, config, rng)
+}
+
+// Символ не исключен через -B, -r, --layout-safe или --case и есть в --include-only
+fn is_allowed(c: char, config: &Config) -> bool {
+    let ambiguous = config.ambiguous && config.ambiguous_chars.contains(&c);
+    let removed = config.remove_chars.as_ref().is_some_and(|r| r.contains(&c));
+    let listed = config.include_only.as_ref().is_none_or(|list| list.contains(&c));
+    let layout = !config.layout_safe || layout::is_safe(c);
+    let confusable = config.homoglyphs.as_ref().is_some_and(|table| table.is_confusable(c));
+    let case = match config.case {
+        Some(Case::Lower) => !c.is_ascii_uppercase(),
+        Some(Case::Upper) => !c.is_ascii_lowercase(),
+        _ => true,
+    };
+    !ambiguous && !removed && listed && layout && !confusable && case
+}
+
+// Символом для --no-leading-symbol и --no-edge-symbols считается все,
+// что не буква и не цифра, в том числе не-ASCII
+fn is_symbol(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+// Может ли символ стоять первым
+fn can_lead(c: char, config: &Config) -> bool {
+    let digit = in_class(NUMERALS, c);
+    let symbol = is_symbol(c);
+    !(config.no_leading_digit && digit || (config.no_leading_symbol || config.no_edge_symbols) && symbol)
+}
+
+// Может ли символ стоять последним
+fn can_end(c: char, config: &Config) -> bool {
+    !(config.no_edge_symbols && is_symbol(c))
+}
+
+// Может ли символ стоять в позиции pos пароля длиной length
+fn fits_at(c: char, pos: usize, length: usize, config: &Config) -> bool {
+    (pos > 0 || can_lead(c, config)) && (pos + 1 < length || can_end(c, config))
+}
+
+// Меняет символ на краю пароля, которому там стоять нельзя, местами со
+// случайным символом, который может занять и край, и освободившуюся
+// позицию. Числа символов классов от этого не меняются, а если запрещен
+// только первый символ, у перемешанного пароля все разрешенные
+// расстановки остаются равновероятными. Some(край) - подходящего
+// символа в пароле нет.
+fn swap_edges<R: EntropySource + ?Sized>(
+    password: &mut [char],
+    pinned: &mut [bool],
+    config: &Config,
+    rng: &mut R,
+) -> Result<Option<usize>, RunError> {
+    let length = password.len();
+    for edge in [0, length.saturating_sub(1)] {
+        if password.get(edge).is_none_or(|&c| fits_at(c, edge, length, config)) {
+            continue;
+        }
+        let allowed: Vec<usize> = (0..length)
+            .filter(|&i| i != edge && fits_at(password[i], edge, length, config) && fits_at(password[edge], i, length, config))
+            .collect();
+        if allowed.is_empty() {
+            return Ok(Some(edge));
+        }
+        let pos = allowed[random_index(rng, allowed.len())?];
+        password.swap(edge, pos);
+        pinned.swap(edge, pos);
+    }
+    Ok(None)
+}
+
+// --case: последний шаг после apply_requirements. Буква меняет регистр,
+// только если новая буква есть в наборе, а символы из --require-chars
+// не меняются, так что пароль не выходит из набора и не теряет
+// обязательных символов.
+fn apply_case<R: EntropySource + ?Sized>(
+    mut password: String,
+    case: Case,
+    starts: &[usize],
+    config: &Config,
+    rng: &mut R,
+) -> Result<String, RunError> {
+    let charset = build_charset(config);
+    let limits = class_limits(config, &charset);
+    let required = config.require_chars.as_deref().unwrap_or_default();
+    let convert = |c: char, upper: bool| {
+        let to = if upper { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() };
+        if required.contains(&c) || !charset.contains(&to) { c } else { to }
+    };
+    // Смена регистра не должна оставить пароль без обязательных строчных
+    // или заглавных и не должна собрать похожую последовательность (VV)
+    let keeps = |chars: &[char]| {
+        let enough = (0..2).all(|i| chars.iter().filter(|&&c| class_index(c) == Some(i)).count() >= limits[i].0);
+        enough && config.homoglyphs.as_ref().is_none_or(|table| !table.has_sequence(chars))
+    };
+
+    let mut chars: Vec<char> = password.chars().collect();
+    match case {
+        Case::Lower | Case::Upper => {
+            for c in chars.iter_mut() {
+                *c = convert(*c, case == Case::Upper);
+            }
+        }
+        Case::Title => {
+            // Заглавная - первая буква каждого слога от его начала до
+            // начала следующего
+            let mut ends = starts.get(1..).unwrap_or_default().to_vec();
+            ends.push(chars.len());
+            for (&start, end) in starts.iter().zip(ends) {
+                let syllable = &mut chars[start..end];
+                let first = syllable.iter().position(|&c| c.is_ascii_alphabetic() && convert(c, true).is_ascii_uppercase());
+                for (i, c) in syllable.iter_mut().enumerate() {
+                    *c = convert(*c, Some(i) == first);
+                }
+            }
+        }
+        Case::Random => {
+            // Монета на каждую букву, которой можно сменить регистр. Если
+            // после бросков требования не выполнены, монеты бросаются заново.
+            let free: Vec<usize> =
+                (0..chars.len()).filter(|&i| convert(chars[i], !chars[i].is_ascii_uppercase()) != chars[i]).collect();
+            loop {
+                let mut flipped = chars.clone();
+                for &i in &free {
+                    if random_index(rng, 2)? == 1 {
+                        flipped[i] = convert(flipped[i], !flipped[i].is_ascii_uppercase());
+                    }
+                }
+                if keeps(&flipped) {
+                    secret::wipe_chars(&mut chars);
+                    chars = flipped;
+                    break;
+                }
+                secret::wipe_chars(&mut flipped);
+            }
+        }
+    }
+    if !keeps(&chars) {
+        secret::wipe_chars(&mut chars);
+        return Err(RunError::Generation(format!(
+            "--case {} cannot keep the required letters or avoid confusable sequences with this character set",
+            case.name()
+        )));
+    }
+
+    let mut result = String::with_capacity(password.len());
+    result.extend(chars.iter());
+    secret::wipe_chars(&mut chars);
+    secret::wipe_str(&mut password);
+    Ok(result)
+}
+
+// Запоминаемый пароль без гласных: диграфы вперемежку с одиночными
+// согласными (kr-t-sp-d...). Соседние группы не начинаются с той буквы,
+// которой закончилась предыдущая, так что трех одинаковых согласных
+// подряд не бывает.
+fn generate_consonant_password<R: EntropySource + ?Sized>(length: usize, config: &Config, rng: &mut R) -> Result<String, RunError> {
+    let singles: Vec<u8> = if config.no_capitalize { CONSONANTS_LOWER } else { CONSONANTS }
+        .iter()
+        .filter(|&&c| is_allowed(char::from(c), config))
+        .cloned()
+        .collect();
+    if singles.is_empty() {
+        return Err(none_left_error("consonants"));
+    }
+
+    let mut digraphs: Vec<[u8; 2]> = Vec::new();
+    for &&[first, second] in CONSONANT_DIGRAPHS {
+        digraphs.push([first, second]);
+        if !config.no_capitalize {
+            digraphs.push([first.to_ascii_uppercase(), second]);
+        }
+    }
+    digraphs.retain(|pair| pair.iter().all(|&c| is_allowed(char::from(c), config)));
+
+    let mut password = Vec::with_capacity(length);
+    let mut group = 0;
+    while password.len() < length {
+        let last = password.last().map(u8::to_ascii_lowercase);
+        let fresh = |c: u8| Some(c.to_ascii_lowercase()) != last;
+
+        if group % 2 == 0 && length - password.len() >= 2 && !digraphs.is_empty() {
+            let mut candidates: Vec<&[u8; 2]> = digraphs.iter().filter(|pair| fresh(pair[0])).collect();
+            if candidates.is_empty() {
+                candidates = digraphs.iter().collect();
+            }
+            password.extend_from_slice(candidates[random_index(rng, candidates.len())?]);
+        } else {
+            let mut candidates: Vec<u8> = singles.iter().cloned().filter(|&c| fresh(c)).collect();
+            if candidates.is_empty() {
+                candidates = singles.clone();
+            }
+            password.push(candidates[random_index(rng, candidates.len())?]);
+        }
+        group += 1;
+    }
+
+    let chars = password.iter().map(|&c| char::from(c)).collect();
+    secret::wipe(&mut password);
+    finish_memorable(chars, config, rng)
+}
+
+// Байтовые классы (LOWERCASE, SYMBOLS...) как символы
+fn class_chars(class: &[u8]) -> impl Iterator<Item = char> + '_ {
+    class.iter().map(|&c| char::from(c))
+}
+
+fn in_class(class: &[u8], c: char) -> bool {
+    u8::try_from(c).is_ok_and(|b| class.contains(&b))
+}
+
+// Символы, из которых -y берет обязательный символ
+fn symbol_class(config: &Config) -> &'static [u8] {
+    if config.mobile_friendly {
+        MOBILE_SYMBOLS
+    } else if config.shell_safe {
+        SHELL_SAFE_SYMBOLS
+    } else {
+        SYMBOLS
+    }
+}
+
+// Символы класса, которые допускает набор символов с учетом -B, -v и -r
+fn allowed_chars(class: &[u8], charset: &[char]) -> Vec<char> {
+    class_chars(class).filter(|c| charset.contains(c)).collect()
+}
+
+// Классы --weights с их весами. Класс, из которого -B или -r убрали все
+// символы, выпадает вместе с весом.
+fn weighted_pools(config: &Config, charset: &[char]) -> Vec<(usize, Vec<char>)> {
+    let classes = [LOWERCASE, UPPERCASE, NUMERALS, symbol_class(config)];
+    let weights = config.weights.unwrap_or_default();
+    classes
+        .iter()
+        .zip(weights)
+        .map(|(class, weight)| (weight, allowed_chars(class, charset)))
+        .filter(|(weight, pool)| *weight > 0 && !pool.is_empty())
+        .collect()
+}
+
+// Набор символов, разбитый по классам COUNTED_CLASSES; символы вне
+// классов - отдельный последний пул
+fn class_pools(charset: &[char]) -> Vec<Vec<char>> {
+    let mut pools: Vec<Vec<char>> = COUNTED_CLASSES.iter().map(|(_, class, _, _)| allowed_chars(class, charset)).collect();
+    pools.push(charset.iter().cloned().filter(|&c| class_index(c).is_none()).collect());
+    pools
+}
+
+// Номер класса символа в COUNTED_CLASSES
+fn class_index(c: char) -> Option<usize> {
+    COUNTED_CLASSES.iter().position(|(_, class, _, _)| in_class(class, c))
+}
+
+// Сколько символов каждого класса из COUNTED_CLASSES должно быть в
+// пароле: не меньше --min-*, чем требуют -c, -n, -y и чем символов класса
+// в --require-chars, и не больше --max-*
+fn class_limits(config: &Config, charset: &[char]) -> [(usize, usize); 4] {
+    let required = required_classes(config, charset);
+    let chars = config.require_chars.as_deref().unwrap_or_default();
+    let counts = &config.class_counts;
+    std::array::from_fn(|i| {
+        let (_, _, name, _) = COUNTED_CLASSES[i];
+        let flag = usize::from(required.iter().any(|(required, _, _)| *required == name));
+        let pinned = chars.iter().filter(|&&c| class_index(c) == Some(i)).count();
+        (counts.min[i].max(flag).max(pinned), counts.max[i].unwrap_or(usize::MAX))
+    })
+}
+
+// Выполнимы ли требования к классам для паролей длиной от shortest до longest
+fn check_limits(config: &Config, charset: &[char], shortest: usize, longest: usize) -> Result<(), RunError> {
+    let limits = class_limits(config, charset);
+    let pools = class_pools(charset);
+    let chars = config.require_chars.as_deref().unwrap_or_default();
+    let counts = &config.class_counts;
+    let pinned = |i| chars.iter().filter(|&&c| class_index(c) == Some(i)).count();
+    // Символы из --require-chars вне классов занимают свои позиции
+    let other = chars.iter().filter(|&&c| class_index(c).is_none()).count();
+
+    for (i, (suffix, _, _, plural)) in COUNTED_CLASSES.iter().enumerate() {
+        if pools[i].is_empty() && counts.min[i] > pinned(i) {
+            let option = match &config.classes {
+                Some(classes) => format!("--classes {}", classes_spec(classes)),
+                None => format!("--min-{} {}", suffix, counts.min[i]),
+            };
+            return Err(RunError::Settings(format!(
+                "{} cannot be met: no {} are left in the character set",
+                option, plural
+            )));
+        }
+    }
+
+    let needed = limits.iter().map(|(min, _)| min).sum::<usize>() + other;
+    if needed > shortest {
+        if *counts == ClassCounts::default() {
+            return Err(too_short_error(shortest, &required_classes(config, charset), chars));
+        }
+        let mut names: Vec<String> = COUNTED_CLASSES
+            .iter()
+            .zip(limits)
+            .filter(|(_, (min, _))| *min > 0)
+            .map(|((_, _, name, plural), (min, _))| format!("{} {}", min, if min == 1 { name } else { plural }))
+            .collect();
+        names.extend(chars.iter().filter(|&&c| class_index(c).is_none()).map(|c| format!("{:?}", c)));
+        return Err(RunError::Settings(format!(
+            "length {} cannot contain {} required characters ({})",
+            shortest,
+            needed,
+            names.join(", ")
+        )));
+    }
+
+    // Хотя бы одна позиция должна остаться символу, с которого можно
+    // начать пароль
+    if config.no_leading_digit || config.no_leading_symbol || config.no_edge_symbols {
+        let leads = |&c: &char| can_lead(c, config);
+        let trailing: usize = COUNTED_CLASSES
+            .iter()
+            .zip(limits)
+            .filter(|((_, class, _, _), _)| !class_chars(class).any(|c| leads(&c)))
+            .map(|(_, (min, _))| min)
+            .sum::<usize>()
+            + chars.iter().filter(|&&c| class_index(c).is_none() && !leads(&c)).count();
+        if !charset.iter().chain(chars).any(leads) || trailing >= shortest {
+            return Err(edge_error(config, "start"));
+        }
+    }
+
+    // С --no-edge-symbols обязательным символам нужны позиции внутри
+    // пароля, а у паролей из одного и двух символов их нет
+    if config.no_edge_symbols {
+        let inside = COUNTED_CLASSES
+            .iter()
+            .zip(limits)
+            .filter(|((_, class, _, _), _)| class_chars(class).all(is_symbol))
+            .map(|(_, (min, _))| min)
+            .sum::<usize>()
+            + chars.iter().filter(|&&c| class_index(c).is_none() && is_symbol(c)).count();
+        if inside > shortest.saturating_sub(2) {
+            return Err(RunError::Settings(format!(
+                "length {} leaves no room for {} {} inside the password (--no-edge-symbols)",
+                shortest,
+                inside,
+                if inside == 1 { "symbol" } else { "symbols" }
+            )));
+        }
+    }
+
+    // Если символов вне классов нет, длину ограничивает сумма --max-*
+    if pools[COUNTED_CLASSES.len()].is_empty() {
+        let room = (0..COUNTED_CLASSES.len())
+            .map(|i| if pools[i].is_empty() { pinned(i) } else { limits[i].1 })
+            .fold(other, usize::saturating_add);
+        if room < longest {
+            return Err(RunError::Settings(format!(
+                "length {} is more than --max-* allow ({} characters)",
+                longest, room
+            )));
+        }
+    }
+    Ok(())
+}
+
+type CharClass = (&'static str, &'static [u8], Vec<char>);
+
+// Обязательные классы (-c, -n, -y) с допустимыми символами. Класс, из
+// которого -B, -v или -r убрали все символы или которого нет в
+// --include-only, потребовать нельзя, его пропускаем.
+fn required_classes(config: &Config, charset: &[char]) -> Vec<CharClass> {
+    let mut required = requested_classes(config, charset);
+    required.retain(|(_, _, allowed)| !allowed.is_empty());
+    required
+}
+
+// Классы, которые просили (-c, -n, -y или по умолчанию), даже пустые
+fn requested_classes(config: &Config, charset: &[char]) -> Vec<CharClass> {
+    // Заглавные и цифры по умолчанию не требуются там, где их нет по
+    // определению: с --digits-only, --pin и --alpha-only
+    let mut required = Vec::new();
+    let no_letters = config.digits_only || config.pin;
+    if config.capitalize && !config.no_capitalize && !no_letters && config.case != Some(Case::Lower) {
+        required.push(("uppercase", UPPERCASE, allowed_chars(UPPERCASE, charset)));
+    }
+    if config.numerals && !config.no_numerals && !config.alpha_only {
+        required.push(("digit", NUMERALS, allowed_chars(NUMERALS, charset)));
+    }
+    if config.symbols {
+        let symbols = symbol_class(config);
+        required.push(("symbol", symbols, allowed_chars(symbols, charset)));
+    }
+    required
+}
+
+// Обязательные классы, которых нет в --include-only: их пропускают, но
+// предупреждают, чтобы пароль без цифры не стал неожиданностью
+fn warn_skipped_classes(config: &Config) {
+    if config.include_only.is_none() {
+        return;
+    }
+    let charset = build_charset(config);
+    for (name, _, allowed) in requested_classes(config, &charset) {
+        if allowed.is_empty() {
+            let source = if config.charset_file.is_some() { "--charset-file" } else { "--include-only" };
+            eprintln!("WARNING: no {} is left in {}; passwords will not require one", name, source);
+        }
+    }
+}
+
+// Обязательные классы и символы из --require-chars для сообщений
+fn required_names(required: &[CharClass], chars: &[char]) -> Vec<String> {
+    let mut names: Vec<String> = required.iter().map(|(name, _, _)| name.to_string()).collect();
+    names.extend(chars.iter().map(|c| format!("{:?}", c)));
+    names
+}
+
+fn too_short_error(length: usize, required: &[CharClass], chars: &[char]) -> RunError {
+    let what = if chars.is_empty() {
+        "required character classes"
+    } else {
+        "required character classes and characters"
+    };
+    let names = required_names(required, chars);
+    RunError::Settings(format!(
+        "length {} cannot contain {} {} ({})",
+        length,
+        names.len(),
+        what,
+        names.join(", ")
+    ))
+}
+
+fn apply_requirements<R: EntropySource + ?Sized>(password: Vec<char>, config: &Config, rng: &mut R) -> Result<String, RunError> {
+    let mut result = password;
+    let charset = build_charset(config);
+    check_limits(config, &charset, result.len(), result.len())?;
+
+    let limits = class_limits(config, &charset);
+    let pools = class_pools(&charset);
+    let chars = config.require_chars.as_deref().unwrap_or_default();
+    let count = |result: &[char], i| result.iter().filter(|&&c| class_index(c) == Some(i)).count();
+
+    // Позиции, которые уже обеспечивают какое-то требование. Сначала
+    // закрепляем имеющиеся символы, иначе замена для одного класса могла
+    // бы затереть единственного представителя другого. Символы из
+    // --require-chars засчитываются и в минимум своего класса.
+    let mut used = vec![false; result.len()];
+    let mut missing: Vec<&[char]> = Vec::new();
+    for c in chars {
+        match (0..result.len()).find(|&i| !used[i] && result[i] == *c) {
+            Some(pos) => used[pos] = true,
+            None => missing.push(std::slice::from_ref(c)),
+        }
+    }
+    for (class, (min, _)) in limits.iter().enumerate() {
+        let pinned = chars.iter().filter(|&&c| class_index(c) == Some(class)).count();
+        for _ in pinned..*min {
+            match (0..result.len()).find(|&i| !used[i] && class_index(result[i]) == Some(class)) {
+                Some(pos) => used[pos] = true,
+                None => missing.push(&pools[class]),
+            }
+        }
+    }
+
+    // Недостающее ставим в разные свободные позиции, по возможности в
+    // те, где символу можно стоять: символ с --no-edge-symbols - внутрь
+    let length = result.len();
+    for allowed in missing {
+        let c = allowed[random_index(rng, allowed.len())?];
+        let free: Vec<usize> = (0..length).filter(|&i| !used[i]).collect();
+        let fitting: Vec<usize> = free.iter().cloned().filter(|&i| fits_at(c, i, length, config)).collect();
+        let free = if fitting.is_empty() { free } else { fitting };
+        let apart: Vec<usize> = free.iter().cloned().filter(|&i| differs_around(&result, i, c, config)).collect();
+        let free = if apart.is_empty() { free } else { apart };
+        let pos = free[random_index(rng, free.len())?];
+        result[pos] = c;
+        used[pos] = true;
+    }
+
+    // Символы сверх --max-* перевыбираются из классов, где еще есть
+    // место. Закрепленных символов класса не больше минимума, поэтому
+    // свободный лишний символ всегда найдется.
+    for (class, (_, max)) in limits.iter().enumerate() {
+        while count(&result, class) > *max {
+            let excess: Vec<usize> =
+                (0..result.len()).filter(|&i| !used[i] && class_index(result[i]) == Some(class)).collect();
+            let room: Vec<char> = pools
+                .iter()
+                .enumerate()
+                .filter(|&(other, _)| other != class && limits.get(other).is_none_or(|(_, max)| count(&result, other) < *max))
+                .flat_map(|(_, pool)| pool.iter().cloned())
+                .collect();
+            if excess.is_empty() || room.is_empty() {
+                return Err(RunError::Generation("failed to satisfy --max-* with this character set".to_string()));
+            }
+            let pos = excess[random_index(rng, excess.len())?];
+            let apart: Vec<char> = room.iter().cloned().filter(|&c| differs_around(&result, pos, c, config)).collect();
+            let room = if apart.is_empty() { room } else { apart };
+            result[pos] = room[random_index(rng, room.len())?];
+        }
+    }
+
+    // Если замены или другой способ генерации поставили на край цифру или
+    // символ, которым там стоять нельзя, он меняется местами с разрешенным.
+    // Если разрешенных нет, символ на краю заменяется, когда его класс
+    // может уступить символ.
+    while let Some(edge) = swap_edges(&mut result, &mut used, config, rng)? {
+        let spare = !used[edge] && class_index(result[edge]).is_none_or(|i| count(&result, i) > limits[i].0);
+        let room: Vec<char> = charset
+            .iter()
+            .cloned()
+            .filter(|&c| {
+                fits_at(c, edge, length, config) && class_index(c).is_none_or(|i| count(&result, i) < limits[i].1)
+            })
+            .collect();
+        if !spare || room.is_empty() {
+            return Err(RunError::Generation(format!(
+                "failed to find a character to {} the password with ({})",
+                if edge == 0 { "start" } else { "end" },
+                edge_options(config)
+            )));
+        }
+        let apart: Vec<char> = room.iter().cloned().filter(|&c| differs_around(&result, edge, c, config)).collect();
+        let room = if apart.is_empty() { room } else { apart };
+        result[edge] = room[random_index(rng, room.len())?];
+    }
+    if length > 0 {
+        used[0] |= config.no_leading_digit || config.no_leading_symbol || config.no_edge_symbols;
+        used[length - 1] |= config.no_edge_symbols;
+    }
+
+    // Похожие последовательности (rn вместо m) перевыбираются внутри класса
+    if let Some(table) = &config.homoglyphs {
+        homoglyph::reroll(&mut result, &used, &pools, table, rng)?;
+    }
+
+    // Повторы, которые оставили генератор и замены выше
+    if config.no_repeat {
+        split_repeats(&mut result, &used, &limits, &pools, config, rng)?;
+    }
+
+    // Контрольная проверка: ни одна замена не должна была нарушить требования
+    for (class, (suffix, _, name, plural)) in COUNTED_CLASSES.iter().enumerate() {
+        let (min, max) = limits[class];
+        if count(&result, class) < min {
+            return Err(RunError::Generation(format!("failed to include a required {}", name)));
+        }
+        if count(&result, class) > max {
+            return Err(RunError::Generation(format!("failed to keep {} within --max-{}", plural, suffix)));
+        }
+    }
+    if let Some(c) = chars.iter().find(|c| !result.contains(c)) {
+        return Err(RunError::Generation(format!("failed to include the required character {:?}", c)));
+    }
+    if config.no_repeat && result.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(RunError::Generation("failed to avoid repeated characters (--no-repeat)".to_string()));
+    }
+
+    // Строка сразу нужной емкости, чтобы при росте не оставалось копий
+    // пароля в освобожденной памяти
+    let mut password = String::with_capacity(result.iter().map(|c| c.len_utf8()).sum());
+    password.extend(result.iter());
+    secret::wipe_chars(&mut result);
+    Ok(password)
+}
+
+// С --no-repeat символ c в позиции pos не совпадает ни с одним из соседей
+fn differs_around(password: &[char], pos: usize, c: char, config: &Config) -> bool {
+    !config.no_repeat || (pos == 0 || password[pos - 1] != c) && password.get(pos + 1) != Some(&c)
+}
+
+// --no-repeat: в каждой паре одинаковых соседей перевыбирается второй
+// символ, а если он закреплен или замены нет - первый. Замена отличается
+// от обоих соседей; если такой нет (из "ab" не исправить "abba" одной
+// заменой), второй символ отличается хотя бы от левого, а повтор справа
+// исправляется на следующем шаге. Так один проход слева направо убирает
+// все повторы. Сначала замена ищется в классе символа (в запоминаемом
+// пароле - гласная вместо гласной), затем в классах, которые --min-* и
+// --max-* позволяют поменять; с --balanced классы не меняются.
+fn split_repeats<R: EntropySource + ?Sized>(
+    password: &mut [char],
+    pinned: &[bool],
+    limits: &[(usize, usize); 4],
+    pools: &[Vec<char>],
+    config: &Config,
+    rng: &mut R,
+) -> Result<(), RunError> {
+    let length = password.len();
+    let count = |password: &[char], i| password.iter().filter(|&&c| class_index(c) == Some(i)).count();
+    for i in 1..length {
+        if password[i] != password[i - 1] {
+            continue;
+        }
+        let mut split = false;
+        for (pos, both) in [(i, true), (i - 1, true), (i, false)] {
+            if pinned[pos] {
+                continue;
+            }
+            let old = password[pos];
+            let fits = |c: char| {
+                let sequence = config.homoglyphs.as_ref().is_some_and(|table| {
+                    let mut trial = password.to_vec();
+                    trial[pos] = c;
+                    let found = table.has_sequence(&trial);
+                    secret::wipe_chars(&mut trial);
+                    found
+                });
+                let apart = if both { differs_around(password, pos, c, config) } else { password[pos - 1] != c };
+                c != old && apart && fits_at(c, pos, length, config) && !sequence
+            };
+            let class = class_index(old).unwrap_or(COUNTED_CLASSES.len());
+            let mut room: Vec<char> = pools[class].iter().cloned().filter(|&c| fits(c)).collect();
+            let same_sound: Vec<char> =
+                room.iter().cloned().filter(|&c| in_class(VOWELS, c) == in_class(VOWELS, old)).collect();
+            if !config.secure && !same_sound.is_empty() {
+                room = same_sound;
+            }
+            let spare = limits.get(class).is_none_or(|(min, _)| count(password, class) > *min);
+            if room.is_empty() && spare && !config.balanced {
+                room = pools
+                    .iter()
+                    .enumerate()
+                    .filter(|&(other, _)| other != class && limits.get(other).is_none_or(|(_, max)| count(password, other) < *max))
+                    .flat_map(|(_, pool)| pool.iter().cloned())
+                    .filter(|&c| fits(c))
+                    .collect();
+            }
+            if !room.is_empty() {
+                password[pos] = room[random_index(rng, room.len())?];
+                split = true;
+                break;
+            }
+        }
+        if !split {
+            return Err(RunError::Generation(
+                "failed to avoid repeated characters (--no-repeat) with this character set".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn build_charset(config: &Config) -> Vec<char> {
+    let mut charset = Vec::new();
+
+    if config.url_safe {
+        charset.extend(class_chars(URL_SAFE));
+    } else if let Some(preset) = config.preset {
+        charset.extend(preset.charset());
+    } else if let Some((_, alphabet)) = config.charset_preset {
+        charset.extend(class_chars(alphabet));
+    } else if config.digits_only || config.pin {
+        charset.extend(class_chars(NUMERALS));
+    } else if config.alpha_only {
+        // -A убирает заглавные, -v - гласные, как и в обычном наборе
+        charset.extend(class_chars(LOWERCASE));
+        if config.capitalize && !config.no_capitalize {
+            charset.extend(class_chars(UPPERCASE));
+        }
+    } else if let Some(include_only) = &config.include_only {
+        // Набор задан целиком, классы его не дополняют
+        charset.extend_from_slice(include_only);
+    } else if let Some(weights) = config.weights {
+        // Классы с ненулевым весом; -0 и -A уже согласованы с весами
+        let classes = [LOWERCASE, UPPERCASE, NUMERALS, symbol_class(config)];
+        for (class, weight) in classes.into_iter().zip(weights) {
+            if weight > 0 {
+                charset.extend(class_chars(class));
+            }
+        }
+    } else {
+        // Строчные буквы всегда включены
+        charset.extend(class_chars(LOWERCASE));
+
+        // Заглавные буквы
+        if config.capitalize && !config.no_capitalize {
+            charset.extend(class_chars(UPPERCASE));
+        }
+
+        // Цифры
+        if config.numerals && !config.no_numerals {
+            charset.extend(class_chars(NUMERALS));
+        }
+
+        // Символы
+        if config.symbols {
+            charset.extend(class_chars(symbol_class(config)));
+        }
+    }
+
+    // Удаляем неоднозначные символы если требуется
+    if config.ambiguous {
+        charset.retain(|c| !config.ambiguous_chars.contains(c));
+    }
+
+    // С --shell-safe опасные для shell символы не попадают и из --include-only или --charset
+    if config.shell_safe {
+        charset.retain(|&c| !in_class(SYMBOLS, c) || in_class(SHELL_SAFE_SYMBOLS, c));
+    }
+
+    // С --no-shift заглавные и верхние символы не попадают и из --include-only или --charset
+    if config.no_shift {
+        charset.retain(|&c| !c.is_ascii_uppercase() && (!in_class(SYMBOLS, c) || in_class(NO_SHIFT_SYMBOLS, c)));
+    }
+
+    // Символы, которые на QWERTZ или AZERTY стоят на другой клавише
+    if config.layout_safe {
+        charset.retain(|&c| layout::is_safe(c));
+    }
+
+    // Символы, похожие на другие символы
+    if let Some(table) = &config.homoglyphs {
+        charset.retain(|&c| !table.is_confusable(c));
+    }
+
+    // Удаляем гласные если требуется
+    if config.no_vowels {
+        charset.retain(|&c| !in_class(VOWELS, c));
+    }
+
+    // Удаляем пользовательские символы
+    if let Some(remove_chars) = &config.remove_chars {
+        charset.retain(|c| !remove_chars.contains(c));
+    }
+
+    // С --case lower и upper остаются буквы одного регистра
+    match config.case {
+        Some(Case::Lower) => charset.retain(|c| !c.is_ascii_uppercase()),
+        Some(Case::Upper) => charset.retain(|c| !c.is_ascii_lowercase()),
+        _ => {}
+    }
+
+    // --max-* 0 убирает класс, в том числе строчные буквы
+    for ((_, class, _, _), max) in COUNTED_CLASSES.iter().zip(config.class_counts.max) {
+        if max == Some(0) {
+            charset.retain(|&c| !in_class(class, c));
+        }
+    }
+
+    charset
+}
+
+// Каждая строка собирается в собственном буфере заранее известного размера
+// и затирается после вывода. Целые строки LineWriter передает в stdout
+// напрямую, так что копий паролей во внутренних буферах не остается.
+fn write_line<W: Write>(out: &mut W, line: &mut Vec<u8>) -> io::Result<()> {
+    write_record(out, line, b'\n')
+}
+
+// Строка, которая кончается на end: перевод строки или NUL с -z
+fn write_record<W: Write>(out: &mut W, line: &mut Vec<u8>, end: u8) -> io::Result<()> {
+    line.push(end);
+    let result = signals::check().and_then(|_| out.write_all(line));
+    secret::wipe(line);
+    line.clear();
+    result
+}
+
+// Сколько паролей помещается в строку: каждый занимает password_width
+// символов и пробел, с --numbered еще и номер. Ширина известна только для терминала, иначе берется 80.
+// --columns=N соблюдается точно, но в терминале урезается до его ширины,
+// чтобы строки не переносились. --per-line=N не урезается никогда: такие
+// строки вставляют в таблицу, а не читают с экрана. С -1 - всегда по одному.
+fn column_count(config: &Config, terminal_width: Option<usize>) -> usize {
+    if !config.columns {
+        return 1;
+    }
+    if let Some(per_line) = config.per_line {
+        return per_line;
+    }
+    let fit = |width: usize| (width / (password_width(config) + 1 + number_width(config))).max(1);
+    match (config.fixed_columns, terminal_width) {
+        (Some(columns), Some(width)) => columns.min(fit(width)),
+        (Some(columns), None) => columns,
+        (None, width) => fit(width.unwrap_or(term::DEFAULT_WIDTH)),
+    }
+}
+
+// Ширина пароля в выводе вместе с --prefix, --suffix и разделителями --group
+fn password_width(config: &Config) -> usize {
+    let longest = longest_length(config);
+    config.group.as_ref().map_or(longest, |grouping| grouping.display_length(longest)) + affix_length(config)
+}
+
+// Ширина номера "12. " перед паролем; 0 без --numbered
+fn number_width(config: &Config) -> usize {
+    if config.numbered { config.num_pw.to_string().len() + 2 } else { 0 }
+}
+
+// Как печатать пароли
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Layout {
+    columns: usize,
+    // Номера с 1 перед паролями
+    numbered: bool,
+    // Только пароль с этим номером (--pick)
+    pick: Option<usize>,
+    // По паролю в строке, без выравнивания и номеров
+    porcelain: bool,
+    // Чем кончается строка: \n или NUL с -z
+    delimiter: u8,
+    // Цвета классов (--color); только для строк с \n, не для --porcelain
+    color: bool,
+    // Строка с паролем по буквам после каждого пароля (--phonetic)
+    phonetic: bool,
+    // Пометки о похожих символах под паролем (--annotate); не для -z
+    annotate: bool,
+}
+
+impl Layout {
+    fn columns(columns: usize) -> Self {
+        Self {
+            columns,
+            numbered: false,
+            pick: None,
+            porcelain: false,
+            delimiter: b'\n',
+            color: false,
+            phonetic: false,
+            annotate: false,
+        }
+    }
+
+    fn new(config: &Config, terminal_width: Option<usize>) -> Self {
+        Self {
+            columns: column_count(config, terminal_width),
+            numbered: config.numbered,
+            pick: config.pick,
+            porcelain: config.porcelain,
+            delimiter: if config.null { b'\0' } else { b'\n' },
+            // Цвет нужен только в терминале, main включает его сам
+            color: false,
+            phonetic: config.phonetic,
+            annotate: config.annotate,
+        }
+    }
+}
+
+// Как в pwgen: пароли идут в порядке генерации слева направо, строка за
+// строкой, и дополняются пробелами до общей ширины, чтобы столбцы
+// выровнялись. Последняя строка может быть неполной. Номера выровнены
+// по правому краю и в ширину пароля не входят.
+fn write_passwords<W: Write>(out: &mut W, passwords: &Passwords, layout: Layout) -> io::Result<()> {
+    write_passwords_with_entropy(out, passwords, layout, &[])
+}
+
+// То же с " (≈52.4 bits)" после каждого пароля, у которого есть bits[i];
+// без bits - без пометок. Пометка входит в ширину столбца.
+fn write_passwords_with_entropy<W: Write>(
+    out: &mut W,
+    passwords: &Passwords,
+    layout: Layout,
+    bits: &[Option<f64>],
+) -> io::Result<()> {
+    if layout.porcelain {
+        return write_porcelain(out, passwords, layout.delimiter);
+    }
+    let selected = match layout.pick {
+        Some(pick) => pick - 1..pick,
+        None => 0..passwords.len(),
+    };
+    let columns = layout.columns.max(1);
+    let label = |i: usize| bits.get(i).copied().flatten().map(|bits| format!(" (\u{2248}{:.1} bits)", bits));
+    let labels: Vec<Option<String>> = (0..passwords.len()).map(label).collect();
+    let cell = |i: usize| passwords.get(i).chars().count() + labels[i].as_ref().map_or(0, |l| l.chars().count());
+    // Столбцы выравниваются по символам, а буфер считается в байтах
+    let width = selected.clone().map(cell).max().unwrap_or(0);
+    let bytes = selected.clone().map(|i| passwords.get(i).len()).max().unwrap_or(0);
+    let digits = selected.end.to_string().len();
+    // Цвета не попадают в файлы и в записи с NUL
+    let color = layout.color && layout.delimiter == b'\n';
+    let paint = if color { width * color::MAX_OVERHEAD } else { 0 };
+    let spelled = if layout.phonetic { width * phonetic::MAX_WORD } else { 0 };
+    // Пометки, как и цвета, только для строк с \n
+    let annotate = layout.annotate && layout.delimiter == b'\n';
+    let notes = if annotate { width * annotate::MAX_NOTE + 6 } else { 0 };
+    let marks = labels.iter().flatten().map(String::len).max().unwrap_or(0);
+    let mut line = Vec::with_capacity(columns * (bytes + paint + width + digits + 3 + marks) + spelled + notes);
+    for (n, i) in selected.clone().enumerate() {
+        let col = n % columns;
+        if col > 0 {
+            line.resize(line.len() + width - cell(i - 1), b' ');
+            line.push(b' ');
+        }
+        if layout.numbered {
+            write!(line, "{:>digits$}. ", i + 1)?;
+        }
+        if color {
+            color::push_colored(&mut line, passwords.get(i));
+        } else {
+            line.extend_from_slice(passwords.get(i).as_bytes());
+        }
+        if let Some(label) = &labels[i] {
+            line.extend_from_slice(label.as_bytes());
+        }
+        if col + 1 == columns || n + 1 == selected.len() {
+            write_record(out, &mut line, layout.delimiter)?;
+        }
+        // --phonetic: строкой ниже, на два пробела правее начала пароля
+        if layout.phonetic {
+            let indent = if layout.numbered { digits + 4 } else { 2 };
+            line.resize(indent, b' ');
+            phonetic::push_spelled(&mut line, passwords.get(i));
+            write_line(out, &mut line)?;
+        }
+        if annotate {
+            let indent = if layout.numbered { digits + 4 } else { 2 };
+            line.resize(indent, b' ');
+            if annotate::push_note(&mut line, passwords.get(i)) {
+                write_line(out, &mut line)?;
+            } else {
+                line.clear();
+            }
+        }
+    }
+    // Записи с NUL LineWriter stdout сам не сбрасывает, и ошибка записи
+    // потерялась бы при выходе
+    out.flush()
+}
+
+// --porcelain: ровно по паролю в строке, каждая с \n (с -z - с NUL), и
+// больше ничего; столбцы, выравнивание и номера сюда не доходят
+fn write_porcelain<W: Write>(out: &mut W, passwords: &Passwords, delimiter: u8) -> io::Result<()> {
+    let mut line = Vec::with_capacity(passwords.iter().map(|p| p.len()).max().unwrap_or(0) + 1);
+    for password in passwords.iter() {
+        line.extend_from_slice(password.as_bytes());
+        write_record(out, &mut line, delimiter)?;
+    }
+    out.flush()
+}
+
+// --mnemonic: пароль, дополненный до общей ширины, и через два пробела его
+// фраза. Номера и --pick работают как обычно, столбцов нет.
+fn write_mnemonics<W: Write>(
+    out: &mut W,
+    passwords: &Passwords,
+    mnemonics: &Passwords,
+    layout: Layout,
+) -> io::Result<()> {
+    let selected = match layout.pick {
+        Some(pick) => pick - 1..pick,
+        None => 0..passwords.len(),
+    };
+    let width = selected.clone().map(|i| passwords.get(i).chars().count()).max().unwrap_or(0);
+    let bytes = selected.clone().map(|i| passwords.get(i).len() + mnemonics.get(i).len()).max().unwrap_or(0);
+    let digits = selected.end.to_string().len();
+    let mut line = Vec::with_capacity(bytes + width + digits + 5);
+    for i in selected {
+        if layout.numbered {
+            write!(line, "{:>digits$}. ", i + 1)?;
+        }
+        line.extend_from_slice(passwords.get(i).as_bytes());
+        line.resize(line.len() + width - passwords.get(i).chars().count() + 2, b' ');
+        line.extend_from_slice(mnemonics.get(i).as_bytes());
+        write_line(out, &mut line)?;
+    }
+    Ok(())
+}
+
+// Слова фразы берутся не из потока паролей: поток -H конечен, а тот же
+// поток --seed заново выбрал бы слова по тем же байтам, что и символы
+// паролей. С --seed у фраз свой поток того же seed, и они тоже
+// воспроизводимы.
+const MNEMONIC_STREAM: u32 = 577;
+
+fn mnemonic_rng(config: &Config) -> io::Result<Box<dyn Read>> {
+    match config.seed {
+        Some(seed) => Ok(Box::new(ChaCha20Rng::seeded_stream(seed, MNEMONIC_STREAM))),
+        None => Ok(rng::open(&config.rng)?.0),
+    }
+}
+
+fn print_mnemonics(passwords: &Passwords, config: &Config) -> io::Result<()> {
+    let mut rng = mnemonic_rng(config)?;
+    let mnemonics = mnemonic::sentences(passwords, rng.as_mut())?;
+    let stdout = io::stdout();
+    write_mnemonics(&mut stdout.lock(), passwords, &mnemonics, Layout::new(config, None))
+}
+
+fn print_json(passwords: &Passwords, config: &Config) -> io::Result<()> {
+    let stdout = io::stdout();
+    json::write(&mut stdout.lock(), passwords, config)
+}
+
+fn print_csv(passwords: &Passwords, config: &Config) -> io::Result<()> {
+    let stdout = io::stdout();
+    csv::write(&mut stdout.lock(), passwords, config)
+}
+
+// Строка каждого пароля пишется сразу: stdout построчно буферизован, так
+// что `pwgen --format jsonl 16 1000000 | jq` начинает читать без задержки.
+// Закрытый читателем канал обрабатывает exit_with_error.
+fn print_jsonl(config: &Config) -> Result<(), RunError> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut entries = json::Entries::new(config)?;
+    stream_passwords(config, |mut password| {
+        let result = json::write_entry(&mut out, &mut entries, &password);
+        secret::wipe_str(&mut password);
+        Ok(result?)
+    })?;
+    Ok(out.flush()?)
+}
+
+fn print_passwords(passwords: &Passwords, layout: Layout) -> io::Result<()> {
+    let stdout = io::stdout();
+    write_passwords(&mut stdout.lock(), passwords, layout)
+}
+
+// --show-entropy: энтропия считается так же, как для JSON, по режиму и
+// длине случайной части каждого пароля
+fn print_with_entropy(passwords: &Passwords, config: &Config, layout: Layout) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut entropies = dry_run::Entropies::new(config);
+    let bits: Vec<Option<f64>> = passwords.iter().map(|password| entropies.of(password)).collect();
+    // Разная энтропия бывает только у паролей разной длины
+    let each = config.length_range.is_some();
+    write_passwords_with_entropy(&mut out, passwords, layout, if each { &bits } else { &[] })?;
+    let printed = match layout.pick {
+        Some(pick) => &bits[pick - 1..pick],
+        None => &bits[..],
+    };
+    write_entropy_summary(&mut out, printed, each)
+}
+
+// --show-entropy: итог под паролями. Пароли независимы, так что энтропия
+// пакета - сумма энтропий паролей (с --unique чуть меньше).
+fn write_entropy_summary<W: Write>(out: &mut W, bits: &[Option<f64>], each: bool) -> io::Result<()> {
+    let Some(bits) = bits.iter().copied().collect::<Option<Vec<f64>>>() else {
+        return writeln!(out, "Entropy: unknown");
+    };
+    let total: f64 = bits.iter().sum();
+    match (bits.len(), each) {
+        (0, _) | (1, true) => Ok(()),
+        (1, false) => writeln!(out, "Entropy: \u{2248}{:.1} bits", total),
+        (count, true) => writeln!(out, "Entropy: \u{2248}{:.1} bits for all {}", total, count),
+        (count, false) => writeln!(
+            out,
+            "Entropy: \u{2248}{:.1} bits per password, \u{2248}{:.1} bits for all {}",
+            bits[0], total, count
+        ),
+    }
+}
+
+// QR-код пароля (или выбранного --pick) в stdout или в открытый заранее PNG
+fn print_qr(passwords: &Passwords, config: &Config, png: Option<(File, &PathBuf)>) -> io::Result<()> {
+    let code = QrCode::encode(passwords.get(config.pick.map_or(0, |pick| pick - 1)).as_bytes())?;
+    match png {
+        Some((mut file, path)) => qr::write_png(&mut file, &code)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| io::Error::new(e.kind(), format!("cannot write {}: {}", path.display(), e))),
+        None => {
+            let ansi = config.qr_format != Some(QrFormat::Utf8);
+            qr::write_terminal(&mut io::stdout().lock(), &code, ansi)
+        }
+    }
+}
+
+// Текст собирается в памяти в буфере заранее известного размера, чтобы
+// при росте не оставалось копий, и затирается после шифрования
+fn encrypt_passwords(
+    recipient: &Recipient,
+    file: File,
+    path: &Path,
+    passwords: &Passwords,
+    layout: Layout,
+) -> io::Result<()> {
+    // Выравнивание добавляет до width пробелов к паролю из bytes байтов
+    let width = passwords.iter().map(|p| p.chars().count()).max().unwrap_or(0);
+    let bytes = passwords.iter().map(|p| p.len()).max().unwrap_or(0);
+    // С --numbered к каждому паролю добавляется "12. "
+    let numbers = if layout.numbered { passwords.len().to_string().len() + 2 } else { 0 };
+    let mut plaintext = Vec::with_capacity(passwords.len() * (bytes + width + numbers + 1));
+    let result = write_passwords(&mut plaintext, passwords, layout)
+        .and_then(|_| encrypt::encrypt_to_file(recipient.command(), &plaintext, file));
+    secret::wipe(&mut plaintext);
+    result.map_err(|e| io::Error::new(e.kind(), format!("cannot write {}: {}", path.display(), e)))
+}
+
+// Вместо пароля печатается только подтверждение
+fn copy_password(clipboard: &Clipboard, passwords: &Passwords, config: &Config) -> io::Result<()> {
+    let password = passwords.get(config.pick.unwrap_or(1) - 1);
+    clipboard.copy(password.as_bytes())?;
+    if config.clear_after == 0 {
+        return writeln!(io::stdout(), "Password copied to the clipboard");
+    }
+    clipboard.spawn_clearer(password.as_bytes(), config.clear_after)?;
+    writeln!(
+        io::stdout(),
+        "Password copied to the clipboard; it will be cleared in {} seconds",
+        config.clear_after
+    )
+}
+
+// Файл для --output создается с правами 0600, чтобы пароли не были видны
+// другим пользователям независимо от umask. На Windows новый файл наследует
+// ACL каталога (обычно профиля пользователя), отдельной настройки нет.
+fn open_output(path: &Path, force: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let file = options.open(path).map_err(|e| {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            io::Error::new(
+                e.kind(),
+                format!("{} already exists (use --force to overwrite)", path.display()),
+            )
+        } else {
+            io::Error::new(e.kind(), format!("cannot create {}: {}", path.display(), e))
+        }
+    })?;
+
+    // mode действует только при создании: существующий файл, перезаписанный
+    // через --force, сохранил бы прежние права
+    #[cfg(unix)]
+    if force {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| io::Error::new(e.kind(), format!("cannot chmod {}: {}", path.display(), e)))?;
+    }
+    Ok(file)
+}
+
+// Удаляет файлы, созданные этим запуском, если пароли в них так и не
+// записаны. Файлы, которые были до запуска (--force), не трогаются.
+fn remove_created(paths: &[&Path]) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// Тот же вывод, что и в stdout. sync_all нужен, чтобы нехватка места на
+// диске обнаружилась здесь, а не потерялась при закрытии файла.
+fn save_passwords(mut file: File, path: &Path, passwords: &Passwords, layout: Layout) -> io::Result<()> {
+    write_passwords(&mut file, passwords, layout)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot write {}: {}", path.display(), e)))
+}
+
+// "-L, -N and -r"
+fn join_names(names: &[&str]) -> String {
+    match names.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+fn print_help() -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "Usage: pwgen [ generate ] [ OPTIONS ] [ pw_length ] [ num_pw ]")?;
+    writeln!(out, "       pwgen passphrase [ OPTIONS ] [ num_words ] [ num_phrases ]")?;
+    writeln!(out, "       pwgen check [ OPTIONS ] [ pw_length ] < passwords")?;
+    writeln!(out, "       pwgen completions {{bash|zsh|fish|powershell}}")?;
+    writeln!(out, "       pwgen mangen > pwgen.1")?;
+    writeln!(out)?;
+    writeln!(out, "Commands (see 'pwgen COMMAND --help'):")?;
+    for (name, description) in COMMANDS {
+        writeln!(out, "  {:<12} {}", name, description)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "pw_length is at most {} and num_pw at most {}.", MAX_LENGTH, MAX_COUNT)?;
+    writeln!(out, "Short options can be combined, as in -sy1B; {} take", join_names(&options::short_value_names(options::OPTIONS)))?;
+    writeln!(out, "the rest of the group as their value, as in -sBr0O1l.")?;
+    writeln!(out, "Flags in the PWGEN_OPTS environment variable are applied before")?;
+    writeln!(out, "the command line, which overrides them.")?;
+    writeln!(out)?;
+    writeln!(out, "For scripts, use --porcelain: stdout then holds exactly num_pw lines,")?;
+    writeln!(out, "one password each, with no padding, numbering or trailing spaces, and")?;
+    writeln!(out, "every line ends in a newline. Warnings and errors go to stderr only.")?;
+    writeln!(out, "This format will not change between releases. If the reader closes the")?;
+    writeln!(out, "pipe early, as in 'pwgen --porcelain 16 5 | head -1', pwgen exits with")?;
+    writeln!(out, "status 0 without a message.")?;
+    writeln!(out)?;
+    writeln!(out, "Exit status: 0 on success; 1 when the answer is no (check found a weak")?;
+    writeln!(out, "password, --self-test failed, -i was quit); 2 on a usage error or settings")?;
+    writeln!(out, "no password can satisfy, such as an empty character set; 3 when")?;
+    writeln!(out, "generation fails to meet the requirements; 4 on an I/O error (random")?;
+    writeln!(out, "source, output file, clipboard, terminal); 128 + n on signal n.")?;
+    writeln!(out)?;
+    writeln!(out, "Options supported by pwgen:")?;
+    for option in options::OPTIONS {
+        writeln!(out, "  {}", options::synopsis(option))?;
+        for line in option.help {
+            writeln!(out, "    {}", line)?;
+        }
+    }
+    out.flush()
+}
+
+// Тесты
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn parse_args_from_vec(args: Vec<String>) -> Result<Config, CliError> {
+        parse_args_with_env(Config::default(), args, None)
+    }
+
+    // Вспомогательная функция для создания конфигурации для тестов
+    fn test_config() -> Config {
+        Config {
+            pw_length: 8,
+            length_range: None,
+            bits: None,
+            prefix: None,
+            suffix: None,
+            length_includes_affixes: false,
+            group: None,
+            group_counts_length: false,
+            num_pw: 1,
+            num_pw_given: false,
+            capitalize: true,
+            no_capitalize: false,
+            numerals: true,
+            no_numerals: false,
+            symbols: false,
+            shell_safe: false,
+            url_safe: false,
+            alpha_only: false,
+            digits_only: false,
+            pin: false,
+            mobile_friendly: false,
+            no_shift: false,
+            layout_safe: false,
+            no_leading_digit: false,
+            no_leading_symbol: false,
+            no_edge_symbols: false,
+            no_repeat: false,
+            no_sequences: None,
+            no_keyboard_walks: false,
+            no_dictionary_words: None,
+            profanity: None,
+            profanity_file: None,
+            case: None,
+            homoglyphs: None,
+            homoglyph_file: None,
+            remove_chars: None,
+            weights: None,
+            class_counts: ClassCounts::default(),
+            classes: None,
+            template: None,
+            like: None,
+            like_stdin: false,
+            blocks: None,
+            balanced: false,
+            require_chars: None,
+            include_only: None,
+            charset_file: None,
+            charset_preset: None,
+            preset: None,
+            secure: false,
+            ambiguous: false,
+            ambiguous_chars: default_ambiguous_chars(),
+            columns: false,
+            columns_given: false,
+            fixed_columns: None,
+            per_line: None,
+            numbered: false,
+            porcelain: false,
+            unique: false,
+            sort: false,
+            show_entropy: false,
+            null: false,
+            format: Format::Text,
+            color: ColorChoice::Auto,
+            csv_safe: false,
+            mnemonic: false,
+            phonetic: false,
+            annotate: false,
+            qr: false,
+            qr_only: false,
+            qr_format: None,
+            no_vowels: false,
+            memorable_model: MemorableModel::Phoneme,
+            syllable_file: None,
+            leet: None,
+            syllables: None,
+            syllable_count: None,
+            words: None,
+            acrostic: None,
+            separator: None,
+            capitalize_words: false,
+            inject_digit: false,
+            inject_symbol: false,
+            wordlist_file: None,
+            wordlist: None,
+            lang: None,
+            rng: RngChoice::Auto,
+            sha1: None,
+            fast_rng: false,
+            seed: None,
+            verbose: false,
+            lock_memory: false,
+            output: None,
+            force: false,
+            encrypt_to: None,
+            copy: false,
+            pick: None,
+            interactive: false,
+            clear_after: clipboard::DEFAULT_CLEAR_AFTER,
+            self_test: false,
+            dry_run: false,
+            help: false,
+        }
+    }
+
+    // Детерминированный источник: ChaCha20 с фиксированным ключом.
+    // В отличие от Cursor с заранее подобранными байтами не ломается,
+    // когда меняется количество чтений в генераторах.
+    fn seeded_rng(seed: u8) -> ChaCha20Rng<Cursor<Vec<u8>>> {
+        ChaCha20Rng::new(Cursor::new(vec![seed; 32])).unwrap()
+    }
+
+    // Эталонный вывод для --seed 42; меняется только вместе с алгоритмом генерации
+    const GOLDEN_MEMORABLE: [&str; 3] = ["aeM7yo9A", "foo1Ohvu", "faeb2Hi0"];
+    const GOLDEN_SECURE: [&str; 3] = ["F5M>kme}T}`H", "weS9^R\\K-)-i", "Mc$OtpeM0A|i"];
+
+    // Временный файл с заданным содержимым, уникальный для каждого теста
+    fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("pwgen-rs-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_build_charset_default() {
+        let config = Config::default();
+        let charset = build_charset(&config);
+
+        // Должен содержать строчные, заглавные и цифры по умолчанию
+        assert!(charset.contains(&'a'));
+        assert!(charset.contains(&'A'));
+        assert!(charset.contains(&'1'));
+        assert!(!charset.contains(&'!')); // Символы по умолчанию отключены
+    }
+
+    #[test]
+    fn test_build_charset_no_capitalize() {
+        let mut config = test_config();
+        config.no_capitalize = true;
+        let charset = build_charset(&config);
+
+        // Не должен содержать заглавные буквы
+        assert!(charset.contains(&'a'));
+        assert!(!charset.contains(&'A'));
+    }
+
+    #[test]
+    fn test_build_charset_no_numerals() {
+        let mut config = test_config();
+        config.no_numerals = true;
+        let charset = build_charset(&config);
+
+        // Не должен содержать цифры
+        assert!(!charset.iter().any(|&c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_build_charset_symbols() {
+        let mut config = test_config();
+        config.symbols = true;
+        let charset = build_charset(&config);
+
+        // Должен содержать символы
+        assert!(charset.contains(&'!'));
+        assert!(charset.contains(&'@'));
+    }
+
+    #[test]
+    fn test_build_charset_ambiguous() {
+        let mut config = test_config();
+        config.ambiguous = true;
+        let charset = build_charset(&config);
+
+        // Не должен содержать неоднозначные символы
+        assert!(!charset.contains(&'0'));
+        assert!(!charset.contains(&'O'));
+        assert!(!charset.contains(&'1'));
+        assert!(!charset.contains(&'l'));
+    }
+
+    #[test]
+    fn test_build_charset_ambiguous_symbols() {
+        let mut config = test_config();
+        config.ambiguous = true;
+        config.symbols = true;
+        let charset = build_charset(&config);
+
+        assert!(!charset.contains(&'`'));
+        assert!(!charset.contains(&'\''));
+        assert!(!charset.contains(&'|'));
+        assert!(charset.contains(&'#'));
+    }
+
+    #[test]
+    fn test_custom_ambiguous_chars() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args).unwrap()
+        };
+
+        // Замена набора: 0 и O снова разрешены, а x и k - нет
+        let config = parse(&["--ambiguous-chars=xkXK", "1", "1"]);
+        assert!(config.ambiguous);
+        let charset = build_charset(&config);
+        assert!(charset.contains(&'0') && charset.contains(&'O'));
+        assert!(!charset.contains(&'x') && !charset.contains(&'K'));
+
+        // Дополнение набора по умолчанию
+        let extended = parse(&["--ambiguous-chars", "+#", "-y"]);
+        let charset = build_charset(&extended);
+        assert!(!charset.contains(&'#') && !charset.contains(&'0') && !charset.contains(&'|'));
+
+        for seed in 0..20 {
+            let secure = Config { secure: true, ..config.clone() };
+            let password = generate_secure_password(24, &secure, &mut seeded_rng(seed))?;
+            assert!(!password.contains(['x', 'k', 'X', 'K']), "{}", password);
+
+            let password = generate_memorable_password(24, &config, &mut seeded_rng(seed))?;
+            assert!(!password.contains(['x', 'k', 'X', 'K']), "{}", password);
+
+            // Обязательный символ тоже выбирается с учетом набора
+            let password = generate_secure_password(4, &extended, &mut seeded_rng(seed))?;
+            assert!(!password.contains(['#', '|', '`', '\'']), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_charset_no_vowels() {
+        let mut config = test_config();
+        config.no_vowels = true;
+        let charset = build_charset(&config);
+
+        // Не должен содержать гласные
+        assert!(!charset.contains(&'a'));
+        assert!(!charset.contains(&'e'));
+        assert!(!charset.contains(&'i'));
+        assert!(!charset.contains(&'o'));
+        assert!(!charset.contains(&'u'));
+        assert!(!charset.contains(&'A'));
+        assert!(!charset.contains(&'E'));
+        assert!(!charset.contains(&'I'));
+        assert!(!charset.contains(&'O'));
+        assert!(!charset.contains(&'U'));
+    }
+
+    #[test]
+    fn test_build_charset_remove_chars() {
+        let mut config = test_config();
+        config.remove_chars = Some("aeiouAEIOU".chars().collect());
+        let charset = build_charset(&config);
+
+        // Не должен содержать удаленные символы
+        assert!(!charset.contains(&'a'));
+        assert!(!charset.contains(&'A'));
+    }
+
+    #[test]
+    fn test_parse_include_only() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert!(parse(&["--include-only=c-a"]).is_err());
+        let config = parse(&["--include-only", "d-f", "--include-only=a-e9"]).unwrap();
+        assert_eq!(config.include_only, Some("9abcdef".chars().collect()));
+        for bad in ["ab c", "a\tb", "a\u{7}"] {
+            assert!(parse(&["--include-only", bad]).is_err(), "{:?}", bad);
+        }
+        // Символы не обязаны быть ASCII
+        let config = parse(&["--include-only", "abé"]).unwrap();
+        assert_eq!(config.include_only, Some("abé".chars().collect()));
+
+        // Командная строка заменяет список из PWGEN_OPTS, а не дополняет его
+        let args = vec!["pwgen".to_string(), "--include-only=xyz".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("--include-only a-f")).unwrap();
+        assert_eq!(config.include_only, Some("xyz".chars().collect()));
+    }
+
+    // Двузначные пароли из цифр: всего 100 разных, повторы неизбежны
+    fn digit_pairs(num_pw: usize, seed: u64) -> Config {
+        let mut config = test_config();
+        config.secure = true;
+        config.no_capitalize = true;
+        config.include_only = Some("0123456789".chars().collect());
+        config.pw_length = 2;
+        config.num_pw = num_pw;
+        config.seed = Some(seed);
+        config
+    }
+
+    #[test]
+    fn test_unique_impossible_count() -> Result<(), RunError> {
+        let mut config = digit_pairs(10000, 1);
+        config.unique = true;
+        match generate_passwords(&config) {
+            Err(RunError::Settings(message)) => assert_eq!(
+                message,
+                "--unique: these settings give only 100 different passwords, fewer than 10000"
+            ),
+            other => panic!("{:?}", other.map(|p| p.len())),
+        }
+        config.num_pw = 101;
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+
+        // Ровно столько, сколько возможно: все до единого
+        config.num_pw = 100;
+        let mut passwords: Vec<String> = generate_passwords(&config)?.iter().map(String::from).collect();
+        passwords.sort_unstable();
+        let all: Vec<String> = (0..100).map(|n| format!("{:02}", n)).collect();
+        assert_eq!(passwords, all);
+
+        // С --length-range считаются все длины: 10 + 100 + 1000
+        config.length_range = Some((1, 3));
+        config.pw_length = 3;
+        config.num_pw = 1110;
+        assert_eq!(generate_passwords(&config)?.len(), 1110);
+        config.num_pw = 1111;
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+
+        // Слабые PIN не генерируются и в счет не идут
+        let mut config = test_config();
+        config.pin = true;
+        config.pw_length = 4;
+        config.num_pw = 10000;
+        config.unique = true;
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_beyond_estimate() -> Result<(), RunError> {
+        // Для запоминаемых паролей из двух строчных букв оценка - 2^7, а
+        // разных паролей генератор дает больше: это не повод отказать
+        let mut config = test_config();
+        config.no_capitalize = true;
+        config.no_numerals = true;
+        config.pw_length = 2;
+        config.seed = Some(1);
+        config.unique = true;
+        let possible = possible_passwords(&config).unwrap();
+        config.num_pw = possible as usize + 1;
+        let passwords: HashSet<String> = generate_passwords(&config)?.iter().map(String::from).collect();
+        assert_eq!(passwords.len(), config.num_pw);
+
+        // Когда разные пароли кончаются, отказ приходит от patience
+        config.num_pw = 1000;
+        assert!(matches!(generate_passwords(&config), Err(RunError::Generation(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_regenerates_duplicates() -> Result<(), RunError> {
+        // Без --unique тот же поток дает повторы уже в первых 30 паролях
+        let plain: Vec<String> = generate_passwords(&digit_pairs(300, 7))?.iter().map(String::from).collect();
+        let mut distinct: Vec<String> = Vec::new();
+        for password in &plain {
+            if !distinct.contains(password) {
+                distinct.push(password.clone());
+            }
+        }
+        assert!(plain[..30].iter().collect::<HashSet<_>>().len() < 30);
+        assert!(distinct.len() >= 30);
+
+        // Повтор заменяется следующим паролем из того же потока, так что
+        // пакет --unique - это поток без повторов
+        let mut config = digit_pairs(30, 7);
+        config.unique = true;
+        let unique: Vec<String> = generate_passwords(&config)?.iter().map(String::from).collect();
+        assert_eq!(unique, distinct[..30]);
+
+        // Так же и в потоке --format jsonl
+        let mut streamed = Vec::new();
+        stream_passwords(&config, |password| {
+            streamed.push(password);
+            Ok(())
+        })?;
+        assert_eq!(streamed, unique);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.num_pw = 50;
+        config.seed = Some(11);
+        let mut expected: Vec<String> = generate_passwords(&config)?.iter().map(String::from).collect();
+        expected.sort_unstable();
+        config.sort = true;
+        let sorted: Vec<String> = generate_passwords(&config)?.iter().map(String::from).collect();
+        assert_eq!(sorted, expected);
+
+        // Повторы при сортировке стоят рядом, с --unique их нет
+        let mut config = digit_pairs(60, 3);
+        config.sort = true;
+        let sorted: Vec<String> = generate_passwords(&config)?.iter().map(String::from).collect();
+        assert!(sorted.is_sorted());
+        assert!(sorted.windows(2).any(|pair| pair[0] == pair[1]));
+        config.unique = true;
+        let sorted: Vec<String> = generate_passwords(&config)?.iter().map(String::from).collect();
+        assert!(sorted.windows(2).all(|pair| pair[0] < pair[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_sort_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let conflict = |list: &[&str]| match parse(list) {
+            Err(CliError::ConflictingFlags(message)) => message,
+            other => panic!("{:?}: {:?}", list, other.map(|_| ())),
+        };
+
+        assert_eq!(
+            conflict(&["--unique", "-i"]),
+            "--unique works on a batch and cannot be used with --interactive"
+        );
+        assert_eq!(
+            conflict(&["--sort", "--unique", "-i"]),
+            "--sort works on a batch and cannot be used with --interactive"
+        );
+        assert!(conflict(&["--sort", "--format", "jsonl"]).starts_with("--sort needs the whole batch"));
+        // Повторы отсеиваются и в потоке
+        assert!(parse(&["--unique", "--format", "jsonl"]).unwrap().unique);
+        let config = parse(&["--unique", "--sort", "--format", "json"]).unwrap();
+        assert!(config.unique && config.sort);
+    }
+
+    #[test]
+    fn test_charset_file_ascii() -> Result<(), RunError> {
+        let path = temp_file("charset-ascii", b"abc\ncab\n  123\n");
+        let chars = load_charset_file(&path)?;
+        std::fs::remove_file(&path).unwrap();
+        // Повторы и пробельные символы отброшены
+        assert_eq!(String::from_iter(&chars), "123abc");
+
+        let mut config = test_config();
+        config.secure = true;
+        config.no_capitalize = true;
+        config.include_only = Some(chars);
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            assert!(password.chars().all(|c| "123abc".contains(c)), "{}", password);
+            assert!(password.chars().any(|c| c.is_ascii_digit()), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_charset_file_unicode() -> Result<(), RunError> {
+        let path = temp_file("charset-unicode", "жёлтый ж\n🦀🔑🦀\n".as_bytes());
+        let chars = load_charset_file(&path)?;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(chars.len(), 8);
+
+        let mut config = test_config();
+        config.secure = true;
+        config.pw_length = 12;
+        config.num_pw = 20;
+        config.include_only = Some(chars.clone());
+        // Заглавных и цифр в наборе нет, их требование пропускается
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            assert_eq!(password.chars().count(), 12, "{}", password);
+            assert!(password.chars().all(|c| chars.contains(&c)), "{}", password);
+        }
+
+        // Столбцы выравниваются по символам, а не по байтам
+        let passwords = Passwords::from(vec!["жж".to_string(), "ab".to_string(), "🦀".to_string()]);
+        let mut out = Vec::new();
+        write_passwords(&mut out, &passwords, Layout::columns(3)).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "жж ab 🦀\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_multibyte_charset() -> Result<(), RunError> {
+        // é - два байта, 🦀 - четыре
+        let mut config = test_config();
+        config.secure = true;
+        config.no_capitalize = true;
+        config.pw_length = 9;
+        config.num_pw = 50;
+        config.include_only = Some("aé🦀7".chars().collect());
+        config.remove_chars = Some(vec!['a']);
+        assert_eq!(build_charset(&config), vec!['é', '🦀', '7']);
+
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            // Длина считается в символах, а не в байтах
+            assert_eq!(password.chars().count(), 9, "{}", password);
+            assert!(password.chars().all(|c| "7é🦀".contains(c)), "{}", password);
+            assert!(password.contains('7'), "{}", password);
+            assert!(check::problems(password, &config).is_empty(), "{}", password);
+        }
+
+        // Замена для обязательного класса не разрезает многобайтовый символ
+        let result = apply_requirements(vec!['🦀'; 4], &config, &mut seeded_rng(3))?;
+        assert_eq!(result.chars().count(), 4);
+        assert_eq!(result.chars().filter(|&c| c == '7').count(), 1, "{}", result);
+        Ok(())
+    }
+
+    #[test]
+    fn test_charset_file_errors() {
+        let path = temp_file("charset-blank", b" \n\t\r\n");
+        let err = load_charset_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, RunError::Settings(_)));
+        assert!(err.to_string().contains("contains no characters"), "{}", err);
+
+        let path = temp_file("charset-latin1", b"ab\xe9cd");
+        let err = load_charset_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.to_string().ends_with("invalid UTF-8 at byte 2"), "{}", err);
+
+        let err = load_charset_file(Path::new("/nonexistent/pwgen-rs-charset")).unwrap_err();
+        assert!(matches!(err, RunError::Io(_)));
+
+        let args = ["pwgen", "--charset-file=chars.txt", "--include-only=abc"];
+        assert!(matches!(
+            parse_args_from_vec(args.iter().map(|s| s.to_string()).collect()),
+            Err(CliError::ConflictingFlags(_))
+        ));
+    }
+
+    #[test]
+    fn test_shell_safe_symbols() -> Result<(), RunError> {
+        for secure in [true, false] {
+            let config = Config {
+                secure,
+                symbols: true,
+                shell_safe: true,
+                pw_length: 12,
+                num_pw: 2000,
+                seed: Some(553),
+                ..test_config()
+            };
+            let passwords = generate_passwords(&config)?;
+            for password in passwords.iter() {
+                assert!(!password.contains(['\'', '"', '`', '$', '\\', '!']), "{}", password);
+                // Обязательный символ по-прежнему есть
+                assert!(password.bytes().any(|c| SHELL_SAFE_SYMBOLS.contains(&c)), "{}", password);
+            }
+        }
+
+        // -B убирает '.' и ':', -r - то, что перечислено
+        let config = Config {
+            secure: true,
+            symbols: true,
+            shell_safe: true,
+            ambiguous: true,
+            remove_chars: Some(vec!['@', '#']),
+            ..test_config()
+        };
+        let symbols: String = build_charset(&config).into_iter().filter(|&c| in_class(SYMBOLS, c)).collect();
+        assert_eq!(symbols, "%^*-_=+/");
+
+        // Из явного набора опасные символы тоже убираются
+        let config = Config {
+            secure: true,
+            shell_safe: true,
+            include_only: Some("ab$!@".chars().collect()),
+            ..test_config()
+        };
+        assert_eq!(String::from_iter(build_charset(&config)), "ab@");
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_shift() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let shifted = |c: char| c.is_ascii_uppercase() || "~!@#$%^&*()_+{}|:\"<>?".contains(c);
+        for secure in [&["--no-shift", "-y", "-s"][..], &["--no-shift", "-y"]] {
+            let mut config = parse(secure).unwrap();
+            assert!(config.no_capitalize);
+            config.num_pw = 2000;
+            config.pw_length = 12;
+            config.seed = Some(556);
+            let passwords = generate_passwords(&config)?;
+            for password in passwords.iter() {
+                assert!(!password.chars().any(shifted), "{}", password);
+                assert!(password.bytes().any(|c| NO_SHIFT_SYMBOLS.contains(&c)), "{}", password);
+                assert!(password.bytes().any(|c| c.is_ascii_digit()), "{}", password);
+            }
+        }
+
+        // -B и -r по-прежнему убирают символы
+        let config = parse(&["--no-shift", "-y", "-s", "-B", "-r", "/"]).unwrap();
+        let symbols: String = build_charset(&config).into_iter().filter(|&c| in_class(SYMBOLS, c)).collect();
+        assert_eq!(symbols, "-=[]");
+        let config = parse(&["--no-shift", "--charset", "printable"]).unwrap();
+        assert!(!build_charset(&config).into_iter().any(shifted));
+
+        assert!(matches!(parse(&["--no-shift", "-c"]), Err(CliError::ConflictingFlags(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_layout_safe() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--layout-safe", "-s"]).unwrap();
+        assert_eq!(
+            String::from_iter(build_charset(&config)),
+            "bcdefghijklnoprstuvxBCDEFGHIJKLNOPRSTUVX0123456789"
+        );
+        // -B и -v убирают символы из того, что осталось
+        let config = parse(&["--layout-safe", "-s", "-B", "-v"]).unwrap();
+        assert_eq!(String::from_iter(build_charset(&config)), "bcdfghjknprstvxCFHJKLNPRTVX3479");
+
+        // Запоминаемые пароли тоже берут только безопасные буквы
+        for args in [&["--layout-safe"][..], &["--layout-safe", "-v"]] {
+            let mut config = parse(args).unwrap();
+            config.num_pw = 500;
+            config.seed = Some(557);
+            for password in generate_passwords(&config)?.iter() {
+                assert!(password.chars().all(layout::is_safe), "{}", password);
+            }
+        }
+
+        assert!(matches!(parse(&["--layout-safe", "-y"]), Err(CliError::ConflictingFlags(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_homoglyph_strict() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        // Одиночные похожие символы убираются из набора
+        let config = parse(&["--homoglyph-strict", "-s"]).unwrap();
+        let charset = build_charset(&config);
+        assert!(!charset.iter().any(|c| "0OQD1lI2Z5S6G8B9gq".contains(*c)));
+        assert!(charset.contains(&'m') && charset.contains(&'w') && charset.contains(&'d'));
+        assert_eq!(charset.len(), 62 - 18);
+
+        // Без фильтра rn и vv в таком наборе встречаются постоянно
+        let mut config = parse(&["--include-only=rnvwm7", "-s", "24"]).unwrap();
+        config.num_pw = 500;
+        config.seed = Some(558);
+        let sequences = |passwords: &Passwords| passwords.iter().filter(|p| p.contains("rn") || p.contains("vv")).count();
+        assert!(sequences(&generate_passwords(&config)?) > 0);
+        config.homoglyphs = Some(homoglyph::Table::builtin());
+        let passwords = generate_passwords(&config)?;
+        assert_eq!(sequences(&passwords), 0);
+        // Перевыбор не трогает обязательную цифру
+        assert!(passwords.iter().all(|p| p.contains('7')));
+
+        // Группы из файла дополняют встроенные
+        let path = temp_file("homoglyphs", b"# extra\na e\nii u\n");
+        let mut config = parse(&["--homoglyph-file", path.to_str().unwrap(), "-s"]).unwrap();
+        let table = config.homoglyphs.as_mut().unwrap();
+        table.extend(homoglyph::load(&path)?);
+        std::fs::remove_file(&path).unwrap();
+        let charset = build_charset(&config);
+        assert!(!charset.contains(&'a') && !charset.contains(&'e') && !charset.contains(&'0'));
+        Ok(())
+    }
+
+    // Сколько символов каждого класса из COUNTED_CLASSES в пароле
+    fn counted(password: &str) -> [usize; 4] {
+        std::array::from_fn(|i| password.chars().filter(|&c| in_class(COUNTED_CLASSES[i].1, c)).count())
+    }
+
+    #[test]
+    fn test_class_counts_min_only() -> Result<(), RunError> {
+        for (secure, min) in [(true, [0, 2, 3, 2]), (false, [1, 3, 2, 1]), (true, [5, 0, 1, 1]), (false, [0, 1, 7, 0])] {
+            let config = Config {
+                secure,
+                symbols: min[3] > 0,
+                class_counts: ClassCounts { min, max: [None; 4] },
+                pw_length: 8,
+                num_pw: 300,
+                seed: Some(61),
+                ..test_config()
+            };
+            for password in generate_passwords(&config)?.iter() {
+                let counts = counted(password);
+                assert!(counts.iter().zip(min).all(|(&count, min)| count >= min), "{} {:?}", password, min);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_class_counts_max_only() -> Result<(), RunError> {
+        let max = [Some(2), Some(1), None, Some(1)];
+        for secure in [true, false] {
+            let config = Config {
+                secure,
+                symbols: true,
+                class_counts: ClassCounts { min: [0; 4], max },
+                pw_length: 10,
+                num_pw: 300,
+                seed: Some(61),
+                ..test_config()
+            };
+            for password in generate_passwords(&config)?.iter() {
+                let counts = counted(password);
+                assert!(counts.iter().zip(max).all(|(&count, max)| max.is_none_or(|max| count <= max)), "{}", password);
+                // Обязательные классы не пропадают из-за максимумов
+                assert!(counts[1] == 1 && counts[3] == 1 && counts[2] >= 1, "{}", password);
+            }
+        }
+
+        // --max-lower 0 убирает строчные из набора, а не перевыбирает их
+        let config = Config {
+            secure: true,
+            class_counts: ClassCounts { min: [0; 4], max: [Some(0), None, None, None] },
+            ..test_config()
+        };
+        assert_eq!(String::from_iter(build_charset(&config)), "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789");
+        Ok(())
+    }
+
+    #[test]
+    fn test_class_counts_near_length_boundary() {
+        let limits = [(0, None), (0, Some(0)), (1, None), (1, Some(1)), (2, Some(2)), (2, None), (1, Some(2))];
+        for length in 1..=5 {
+            for max_lower in [None, Some(0), Some(1)] {
+                for (min_digits, max_digits) in limits {
+                    for (min_symbols, max_symbols) in limits {
+                        let mut args: Vec<String> = ["pwgen", "-s", "--seed=61", "-N", "20", "-L"]
+                            .iter()
+                            .map(|s| s.to_string())
+                            .collect();
+                        args.push(length.to_string());
+                        args.push(format!("--min-digits={}", min_digits));
+                        args.push(format!("--min-symbols={}", min_symbols));
+                        let mut add_max = |name: &str, max: Option<usize>| {
+                            if let Some(max) = max {
+                                args.push(format!("--max-{}={}", name, max));
+                            }
+                        };
+                        add_max("lower", max_lower);
+                        add_max("digits", max_digits);
+                        add_max("symbols", max_symbols);
+                        let config = parse_args_from_vec(args).unwrap();
+
+                        // Заглавная и цифра требуются по умолчанию, символ - с --min-symbols
+                        let digits = if max_digits == Some(0) { 0 } else { min_digits.max(1) };
+                        let needed = 1 + digits + min_symbols;
+                        // Места хватает всегда: число заглавных не ограничено
+                        let expected_ok = needed <= length;
+                        let case = (length, max_lower, min_digits, max_digits, min_symbols, max_symbols);
+                        match generate_passwords(&config) {
+                            Ok(passwords) => {
+                                assert!(expected_ok, "{:?}", case);
+                                for password in passwords.iter() {
+                                    let counts = counted(password);
+                                    assert_eq!(password.chars().count(), length);
+                                    assert!(max_lower.is_none_or(|max| counts[0] <= max), "{} {:?}", password, case);
+                                    assert!(counts[1] >= 1, "{} {:?}", password, case);
+                                    assert!(counts[2] >= digits, "{} {:?}", password, case);
+                                    assert!(max_digits.is_none_or(|max| counts[2] <= max), "{} {:?}", password, case);
+                                    assert!(counts[3] >= min_symbols, "{} {:?}", password, case);
+                                    assert!(max_symbols.is_none_or(|max| counts[3] <= max), "{} {:?}", password, case);
+                                }
+                            }
+                            Err(err) => {
+                                assert!(!expected_ok, "{:?}: {}", case, err);
+                                assert!(matches!(err, RunError::Settings(_)), "{:?}: {}", case, err);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_class_counts_without_room() {
+        // Ни одного класса без максимума: длина ограничена их суммой
+        let config = Config {
+            secure: true,
+            symbols: true,
+            class_counts: ClassCounts { min: [0; 4], max: [Some(1), Some(2), Some(2), Some(1)] },
+            pw_length: 7,
+            ..test_config()
+        };
+        let err = generate_passwords(&config).unwrap_err();
+        assert_eq!(err.to_string(), "length 7 is more than --max-* allow (6 characters)");
+        assert!(generate_passwords(&Config { pw_length: 6, ..config.clone() }).is_ok());
+
+        let config = Config {
+            secure: true,
+            class_counts: ClassCounts { min: [0, 0, 3, 0], max: [None; 4] },
+            pw_length: 3,
+            ..test_config()
+        };
+        let err = generate_passwords(&config).unwrap_err();
+        assert_eq!(err.to_string(), "length 3 cannot contain 4 required characters (1 uppercase, 3 digits)");
+
+        // Цифр в наборе не осталось
+        let config = Config {
+            remove_chars: Some(NUMERALS.iter().map(|&c| char::from(c)).collect()),
+            no_numerals: true,
+            ..config
+        };
+        let err = generate_passwords(&config).unwrap_err();
+        assert_eq!(err.to_string(), "--min-digits 3 cannot be met: no digits are left in the character set");
+    }
+
+    #[test]
+    fn test_parse_class_counts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--min-symbols=2", "--max-upper", "0", "--max-lower=3"]).unwrap();
+        assert_eq!(config.class_counts.min, [0, 0, 0, 2]);
+        assert_eq!(config.class_counts.max, [Some(3), Some(0), None, None]);
+        assert!(config.symbols && config.no_capitalize);
+
+        assert!(matches!(parse(&["--min-digits=x"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--max-digits=1025"]), Err(CliError::InvalidValue { .. })));
+        for conflict in [
+            &["--min-digits=3", "--max-digits=2"][..],
+            &["--min-upper=1", "-A"],
+            &["-c", "--max-upper=0"],
+            &["-y", "--max-symbols=0"],
+            &["--min-digits=2", "-0"],
+            &["--require-chars=#!", "--max-symbols=1"],
+            &["--min-digits=2", "--mobile-friendly"],
+        ] {
+            assert!(matches!(parse(conflict), Err(CliError::ConflictingFlags(_))), "{:?}", conflict);
+        }
+        // Ноль снимает требование цифры по умолчанию
+        assert!(parse(&["--max-digits=0"]).unwrap().no_numerals);
+        assert!(parse(&["-A", "--max-upper=3"]).unwrap().no_capitalize);
+    }
+
+    #[test]
+    fn test_parse_classes() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--classes=s1l4d2u2"]).unwrap();
+        assert_eq!(config.classes, Some([4, 2, 2, 1]));
+        assert_eq!(config.pw_length, 9);
+        assert!(config.secure && config.symbols && !config.no_capitalize);
+        let config = parse(&["--classes", "l6d0"]).unwrap();
+        assert_eq!((config.classes, config.pw_length), (Some([6, 0, 0, 0]), 6));
+        assert!(config.no_capitalize && config.no_numerals && !config.symbols);
+        // Совпадающая длина не мешает
+        assert!(parse(&["--classes=l4d2", "6"]).is_ok());
+
+        let error = |list: &[&str]| parse(list).unwrap_err().to_string();
+        assert_eq!(
+            error(&["--classes=l4x2"]),
+            "invalid --classes value 'l4x2' (a class letter l, u, d or s at 'x2', character 3)"
+        );
+        assert_eq!(
+            error(&["--classes=l4u"]),
+            "invalid --classes value 'l4u' (a count from 0 to 1024 after the letter at 'u', character 3)"
+        );
+        assert!(error(&["--classes=l1u1l2"]).contains("each class letter only once at 'l2', character 5"));
+        assert!(error(&["--classes=l0d0"]).contains("counts adding up to a length from 1 to 1024"));
+        assert!(error(&["--classes=l2é"]).contains("a class letter l, u, d or s at 'é', character 3"));
+        assert!(error(&["--classes="]).contains("as in l4u2d2s1"));
+        assert_eq!(
+            error(&["--classes=l4u2", "8"]),
+            "--classes l4u2 makes passwords 6 characters long, which conflicts with pw_length 8"
+        );
+        for conflict in [
+            &["--classes=l4u2", "-L", "5"][..],
+            &["--classes=l4u2", "--length-range=4-6"],
+            &["--classes=l4d2", "-c"],
+            &["--classes=l4u2", "-y"],
+            &["--classes=l4u2", "--min-digits=1"],
+            &["--classes=l4u2", "--weights=1:1:1:1"],
+            &["--classes=l4u2", "--require-chars=x"],
+        ] {
+            assert!(matches!(parse(conflict), Err(CliError::ConflictingFlags(_))), "{:?}", conflict);
+        }
+    }
+
+    #[test]
+    fn test_classes_exact_counts() -> Result<(), RunError> {
+        for (counts, ambiguous) in [([4, 2, 2, 1], false), ([0, 3, 5, 0], true), ([1, 0, 0, 7], false)] {
+            let config = Config {
+                secure: true,
+                classes: Some(counts),
+                class_counts: ClassCounts {
+                    min: counts,
+                    max: counts.map(Some),
+                },
+                capitalize: counts[1] > 0,
+                no_capitalize: counts[1] == 0,
+                no_numerals: counts[2] == 0,
+                symbols: counts[3] > 0,
+                ambiguous,
+                pw_length: counts.iter().sum(),
+                num_pw: 500,
+                seed: Some(62),
+                ..test_config()
+            };
+            let mut first_class = [0usize; 4];
+            for password in generate_passwords(&config)?.iter() {
+                assert_eq!(counted(password), counts, "{}", password);
+                assert!(!ambiguous || !password.bytes().any(|c| AMBIGUOUS.contains(&c)), "{}", password);
+                first_class[class_index(password.chars().next().unwrap()).unwrap()] += 1;
+            }
+            // Перемешивание ставит в начало любой класс пропорционально его числу
+            let length: usize = counts.iter().sum();
+            for (seen, count) in first_class.iter().zip(counts) {
+                let expected = 500.0 * count as f64 / length as f64;
+                assert!((*seen as f64 - expected).abs() < 60.0, "{:?} {:?}", first_class, counts);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_template_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--template", "Cvcvnn-Cvcvnn", "-N", "50", "--seed", "579"]).unwrap();
+        assert_eq!(config.pw_length, 13);
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            let chars: Vec<char> = password.chars().collect();
+            assert_eq!(chars.len(), 13, "{}", password);
+            assert_eq!(chars[6], '-');
+            assert!(chars[0].is_ascii_uppercase() && chars[7].is_ascii_uppercase(), "{}", password);
+            assert!(chars[4..6].iter().chain(&chars[11..]).all(char::is_ascii_digit), "{}", password);
+        }
+        // С тем же seed - те же пароли
+        assert_eq!(generate_passwords(&config)?, passwords);
+
+        // Та же длина не спорит с шаблоном, другая - ошибка
+        assert!(parse(&["--template", "nnnn", "4"]).is_ok());
+        for args in [
+            &["--template", "nnnn", "5"][..],
+            &["--template", "nnnn", "--length-range", "4-6"],
+            &["--template", "nnnn", "--words", "4"],
+            &["--template", "nnnn", "--classes", "d4"],
+            &["--template", "nnnn", "--leet"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert!(matches!(
+            parse(&["--template", "Cv\\"]),
+            Err(CliError::InvalidValue { option: "--template", .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_like_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--like", "Ab1-xY_9", "-N", "30", "--seed", "590"]).unwrap();
+        assert_eq!(config.pw_length, 8);
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            let chars: Vec<char> = password.chars().collect();
+            assert!(chars[0].is_ascii_uppercase() && chars[1].is_ascii_lowercase(), "{}", password);
+            assert!(chars[2].is_ascii_digit() && chars[7].is_ascii_digit(), "{}", password);
+            assert!(!chars[3].is_alphanumeric() && !chars[6].is_alphanumeric(), "{}", password);
+            assert!(chars[4].is_ascii_lowercase() && chars[5].is_ascii_uppercase(), "{}", password);
+        }
+        assert_eq!(generate_passwords(&config)?, passwords);
+
+        // Пароль из stdin main прочитает позже
+        let config = parse(&["--like", "-"]).unwrap();
+        assert!(config.like_stdin && config.like.is_none());
+        // Пароль не попадает в сообщение об ошибке
+        let err = parse(&["--like", &"secret".repeat(200)]).unwrap_err();
+        assert!(!err.to_string().contains("secret"), "{}", err);
+        for args in [
+            &["--like", "abc1", "4"][..],
+            &["--like", "-", "--length-range", "4-6"],
+            &["--like", "abc1", "--template", "nnnn"],
+            &["--like", "abc1", "--words", "3"],
+            &["--like", "abc1", "--no-leading-digit"],
+            &["--like", "abc1", "--min-digits", "2"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_syllable_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--syllables", "4", "-N", "50", "--seed", "589"]).unwrap();
+        assert_eq!(config.syllable_count, Some(4));
+        let passwords = generate_passwords(&config)?;
+        let mut lengths: Vec<usize> = passwords.iter().map(str::len).collect();
+        lengths.sort_unstable();
+        lengths.dedup();
+        // Длина зависит от слогов: от 8 до 16 символов
+        assert!(lengths.len() > 1 && lengths.iter().all(|n| (8..=16).contains(n)), "{:?}", lengths);
+        for password in passwords.iter() {
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()), "{}", password);
+            assert!(password.chars().any(|c| c.is_ascii_digit()), "{}", password);
+        }
+        assert_eq!(generate_passwords(&config)?, passwords);
+
+        for args in [
+            &["--syllables", "4", "12"][..],
+            &["--syllables", "4", "-L", "12"],
+            &["--syllables", "4", "--length-range", "8-12"],
+            &["--syllables", "4", "-s"],
+            &["--syllables", "4", "--words", "3"],
+            &["--syllables", "4", "-v"],
+            &["--syllables", "4", "--memorable-model", "markov"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        for bad in ["0", "x", "257"] {
+            assert!(parse(&["--syllables", bad]).is_err(), "{}", bad);
+        }
+        // Длина из позднего слоя отменяет --syllables, и наоборот
+        let args = vec!["pwgen".to_string(), "12".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("--syllables 3")).unwrap();
+        assert_eq!((config.syllable_count, config.pw_length), (None, 12));
+
+        // Одного слога мало для трех обязательных классов
+        let config = parse(&["--syllables", "1", "-c", "-n", "-y"]).unwrap();
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_affix_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        // По умолчанию pw_length - длина случайной части
+        let config = parse(&["--prefix", "svc-", "--suffix", "!", "-N", "20", "--seed", "588", "12"]).unwrap();
+        assert_eq!(config.pw_length, 12);
+        for password in generate_passwords(&config)?.iter() {
+            assert_eq!(password.chars().count(), 17, "{}", password);
+            assert!(password.starts_with("svc-") && password.ends_with('!'), "{}", password);
+        }
+
+        // С --length-includes-affixes - длина пароля целиком
+        let config = parse(&["--prefix", "svc-", "--suffix", "!", "--length-includes-affixes", "-s", "12"]).unwrap();
+        assert_eq!(config.pw_length, 7);
+        assert!(generate_passwords(&config)?.iter().all(|password| password.len() == 12));
+        let config = parse(&["--prefix=ab", "--length-includes-affixes", "--length-range=8-10", "-N", "50"]).unwrap();
+        assert_eq!(config.length_range, Some((6, 8)));
+        assert!(generate_passwords(&config)?.iter().all(|password| (8..=10).contains(&password.len())));
+        let short = [
+            &["--prefix", "svc-", "--length-includes-affixes", "4"][..],
+            &["--suffix", "12345678", "--length-includes-affixes"],
+        ];
+        for args in short {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        for args in [&["--length-includes-affixes", "--words", "4"][..], &["--length-includes-affixes", "--bits=60"]] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert!(matches!(parse(&["--prefix", "a\nb"]), Err(CliError::InvalidValue { option: "--prefix", .. })));
+
+        // Цифры и символы в аффиксах не засчитываются: -n и -y гарантируют
+        // их в случайной части
+        let config = parse(&["--prefix", "7#", "--suffix", "9", "-n", "-y", "-N", "200", "--seed", "1", "8"]).unwrap();
+        for password in generate_passwords(&config)?.iter() {
+            let core = &password[2..password.len() - 1];
+            assert!(core.chars().any(|c| c.is_ascii_digit()), "{}", password);
+            assert!(core.chars().any(|c| c.is_ascii_punctuation()), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let groups = |password: &str, sep: &str| password.split(sep).map(str::len).collect::<Vec<_>>();
+
+        // По умолчанию разделители в pw_length не входят; 14 не делится на 4
+        let config = parse(&["-s", "-y", "--group", "4,.", "-N", "50", "--seed", "595", "14"]).unwrap();
+        assert_eq!(config.pw_length, 14);
+        for password in generate_passwords(&config)?.iter() {
+            assert_eq!(password.chars().count(), 17, "{}", password);
+            assert_eq!(&password[4..5], ".", "{}", password);
+            assert_eq!(&password[9..10], ".", "{}", password);
+            assert_eq!(&password[14..15], ".", "{}", password);
+        }
+        let config = parse(&["--group", "4", "--seed", "595", "-N", "20", "12"]).unwrap();
+        for password in generate_passwords(&config)?.iter() {
+            assert_eq!(groups(password, "-"), [4, 4, 4], "{}", password);
+        }
+
+        // С --group-counts-length - вместе с ними
+        let config = parse(&["--group", "4", "--group-counts-length", "--seed", "595", "-N", "20", "12"]).unwrap();
+        assert_eq!(config.pw_length, 10);
+        for password in generate_passwords(&config)?.iter() {
+            assert_eq!(groups(password, "-"), [4, 4, 2], "{}", password);
+        }
+        let config = parse(&["--group", "3,::", "--group-counts-length", "--length-range", "6-11", "-N", "50"]).unwrap();
+        assert_eq!(config.length_range, Some((4, 7)));
+        for password in generate_passwords(&config)?.iter() {
+            assert!((6..=11).contains(&password.len()), "{}", password);
+            assert!(!password.ends_with(':'), "{}", password);
+        }
+        // Длина, которая кончалась бы разделителем
+        assert!(matches!(parse(&["--group", "4", "--group-counts-length", "10"]), Err(CliError::ConflictingFlags(_))));
+        for args in [
+            &["--group-counts-length", "12"][..],
+            &["--group", "4", "--group-counts-length", "--words", "4"],
+            &["--group", "4", "--group-counts-length", "--bits", "60"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert!(matches!(parse(&["--group", "0"]), Err(CliError::InvalidValue { option: "--group", .. })));
+
+        // Разделители и аффиксы: делится только случайная часть
+        let config = parse(&["--group", "4", "--prefix", "svc-", "--seed", "595", "8"]).unwrap();
+        let passwords = generate_passwords(&config)?;
+        assert_eq!(groups(passwords.get(0), "-"), [3, 4, 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocks_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--blocks", "w6-d4-w6", "-y", "-N", "50", "--seed", "587"]).unwrap();
+        assert_eq!(config.pw_length, 18);
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            let chars: Vec<char> = password.chars().collect();
+            assert_eq!(chars.len(), 18, "{}", password);
+            assert!(chars[6] == '-' && chars[11] == '-', "{}", password);
+            assert!(chars[7..11].iter().all(char::is_ascii_digit), "{}", password);
+            for word in [&chars[..6], &chars[12..]] {
+                assert!(word.iter().all(char::is_ascii_alphabetic), "{}", password);
+                assert_eq!(word.iter().filter(|c| c.is_ascii_uppercase()).count(), 1, "{}", password);
+            }
+        }
+        // С тем же seed - те же пароли
+        assert_eq!(generate_passwords(&config)?, passwords);
+
+        // Обязательный класс, которого блоки не дают, - ошибка настроек
+        let config = parse(&["--blocks", "w6-w6", "-y"]).unwrap();
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+        let config = parse(&["--blocks", "w6w6", "-0"]).unwrap();
+        assert!(generate_passwords(&config).is_ok());
+
+        assert!(parse(&["--blocks", "d4", "4", "-A"]).is_ok());
+        for args in [
+            &["--blocks", "d4", "5"][..],
+            &["--blocks", "d4", "--length-range", "4-6"],
+            &["--blocks", "d4", "--template", "nnnn"],
+            &["--blocks", "d4", "--pin"],
+            &["--blocks", "d4", "-s"],
+            &["--blocks", "d4", "--bits", "40"],
+            &["--blocks", "d4", "--no-leading-digit"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert!(matches!(parse(&["--blocks", "w-d4"]), Err(CliError::InvalidValue { option: "--blocks", .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_balanced() -> Result<(), RunError> {
+        let base = Config {
+            secure: true,
+            balanced: true,
+            num_pw: 50,
+            seed: Some(63),
+            ..test_config()
+        };
+        let combinations = [
+            base.clone(),
+            Config { symbols: true, ..base.clone() },
+            Config { no_capitalize: true, no_numerals: true, ..base.clone() },
+            Config { no_capitalize: true, symbols: true, ..base.clone() },
+            Config { include_only: Some("abcXYZ12éü".chars().collect()), ..base.clone() },
+        ];
+        for config in combinations {
+            let charset = build_charset(&config);
+            let pools: Vec<Vec<char>> = class_pools(&charset).into_iter().filter(|pool| !pool.is_empty()).collect();
+            for length in (3..=13).chain([100, 1000]) {
+                let config = Config { pw_length: length, ..config.clone() };
+                for password in generate_passwords(&config)?.iter() {
+                    let counts: Vec<usize> =
+                        pools.iter().map(|pool| password.chars().filter(|c| pool.contains(c)).count()).collect();
+                    assert_eq!(counts.iter().sum::<usize>(), length, "{}", password);
+                    let (min, max) = (counts.iter().min().unwrap(), counts.iter().max().unwrap());
+                    assert!(max - min <= 1, "{:?} {}", counts, password);
+                }
+            }
+        }
+
+        let args = ["pwgen", "--balanced", "--classes=l4d4"].iter().map(|s| s.to_string()).collect();
+        assert!(matches!(parse_args_from_vec(args), Err(CliError::ConflictingFlags(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_leading_digit_first_character() -> Result<(), RunError> {
+        let naive = Config {
+            secure: true,
+            ..test_config()
+        };
+        let config = Config {
+            no_leading_digit: true,
+            ..naive.clone()
+        };
+        // Байт 52 - это '0' в наборе из 62 символов, но 'a' среди 52 букв
+        let bytes = [52, 1, 2, 3, 4, 5, 6, 30, 3, 2];
+        let password = generate_secure_password(8, &naive, &mut Cursor::new(bytes))?;
+        assert_eq!(password, "0bcdefgE");
+        // Без цифры в начале ее ставит apply_requirements: '3' в третью из
+        // свободных позиций 1..=6
+        let password = generate_secure_password(8, &config, &mut Cursor::new(bytes))?;
+        assert_eq!(password, "abc3efgE");
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_leading_digit_after_substitution() -> Result<(), RunError> {
+        let naive = test_config();
+        let config = Config {
+            no_leading_digit: true,
+            ..test_config()
+        };
+        // Цифра '3', позиция 0 из семи свободных (H закреплена)
+        let password: Vec<char> = "abcdefgH".chars().collect();
+        let result = apply_requirements(password.clone(), &naive, &mut Cursor::new([3, 0]))?;
+        assert_eq!(result, "3bcdefgH");
+        // С --no-leading-digit позиция 0 цифре не достается: 0 - первая из
+        // позиций 1..=6
+        let result = apply_requirements(password, &config, &mut Cursor::new([3, 0]))?;
+        assert_eq!(result, "a3cdefgH");
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_leading_digit_or_symbol() -> Result<(), RunError> {
+        let base = Config {
+            no_leading_digit: true,
+            no_leading_symbol: true,
+            symbols: true,
+            pw_length: 6,
+            num_pw: 300,
+            seed: Some(64),
+            ..test_config()
+        };
+        let modes = [
+            base.clone(),
+            Config { secure: true, ..base.clone() },
+            Config { secure: true, weights: Some([1, 1, 6, 6]), ..base.clone() },
+            Config { secure: true, balanced: true, ..base.clone() },
+            Config {
+                secure: true,
+                classes: Some([0, 1, 3, 2]),
+                class_counts: ClassCounts { min: [0, 1, 3, 2], max: [Some(0), Some(1), Some(3), Some(2)] },
+                ..base.clone()
+            },
+            Config { no_vowels: true, ..base.clone() },
+        ];
+        for config in modes {
+            for password in generate_passwords(&config)?.iter() {
+                assert_eq!(password.len(), 6);
+                assert!(password.starts_with(|c: char| c.is_ascii_alphabetic()), "{}", password);
+                assert_eq!(check::problems(password, &config), Vec::<String>::new(), "{}", password);
+            }
+        }
+
+        // Первым может стоять только цифра или символ
+        let config = Config {
+            secure: true,
+            classes: Some([0, 0, 3, 3]),
+            class_counts: ClassCounts { min: [0, 0, 3, 3], max: [Some(0), Some(0), Some(3), Some(3)] },
+            ..base.clone()
+        };
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+        let config = Config { no_capitalize: true, mobile_friendly: true, pw_length: 2, ..base };
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_edge_symbols() -> Result<(), RunError> {
+        let base = Config {
+            no_edge_symbols: true,
+            symbols: true,
+            pw_length: 6,
+            num_pw: 2000,
+            seed: Some(565),
+            ..test_config()
+        };
+        let modes = [
+            base.clone(),
+            Config { secure: true, ..base.clone() },
+            Config { secure: true, weights: Some([1, 1, 1, 12]), ..base.clone() },
+            Config { secure: true, balanced: true, ..base.clone() },
+            Config {
+                secure: true,
+                classes: Some([1, 1, 1, 3]),
+                class_counts: ClassCounts { min: [1, 1, 1, 3], max: [Some(1), Some(1), Some(1), Some(3)] },
+                ..base.clone()
+            },
+            Config { no_vowels: true, ..base.clone() },
+            Config { require_chars: Some(vec!['€', '#']), ..base.clone() },
+            Config { secure: true, pw_length: 3, ..base.clone() },
+        ];
+        for config in modes {
+            for password in generate_passwords(&config)?.iter() {
+                assert!(password.starts_with(char::is_alphanumeric), "{}", password);
+                assert!(password.ends_with(char::is_alphanumeric), "{}", password);
+                assert!(password.contains(is_symbol), "{}", password);
+                assert_eq!(check::problems(password, &config), Vec::<String>::new(), "{}", password);
+            }
+        }
+
+        // В паролях из одного и двух символов символу негде стоять
+        for pw_length in [1, 2] {
+            for secure in [false, true] {
+                let config = Config { pw_length, secure, capitalize: false, numerals: false, ..base.clone() };
+                assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_transform() -> Result<(), RunError> {
+        let apply = |password: &str, case: Case, starts: &[usize], config: &Config| {
+            let config = Config { case: Some(case), ..config.clone() };
+            apply_case(password.to_string(), case, starts, &config, &mut Cursor::new([]))
+        };
+        let config = test_config();
+        assert_eq!(apply("Xyz7aBc", Case::Lower, &[0], &config)?, "xyz7abc");
+        assert_eq!(apply("Xyz7aBc", Case::Upper, &[0], &config)?, "XYZ7ABC");
+        // Запоминаемый пароль: первая буква каждого слога, как их собрал
+        // генератор (de-7-sae-8-quiey), а не каждая вторая буква
+        assert_eq!(apply("dE7SAe8QUiEy", Case::Title, &[0, 3, 7], &config)?, "De7Sae8Quiey");
+        assert_eq!(apply("kexo3ida", Case::Title, &[0, 2, 5, 6], &config)?, "KeXo3IDa");
+        // Пароль -s - один слог: только первая буква
+        let secure = Config { secure: true, symbols: true, ..test_config() };
+        assert_eq!(apply("3xKeq#Ab", Case::Title, &[0], &secure)?, "3Xkeq#ab");
+        // Символы из --require-chars не меняются
+        let required = Config { require_chars: Some(vec!['a']), ..test_config() };
+        assert_eq!(apply("ab7C", Case::Upper, &[0], &required)?, "aB7C");
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_random_coin() -> Result<(), RunError> {
+        let config = Config { case: Some(Case::Random), ..test_config() };
+        // Одна монета (байт) на букву: 1 меняет регистр, 0 оставляет
+        let mut rng = Cursor::new([1, 0, 0, 1, 1, 0]);
+        assert_eq!(apply_case("abcdEF12".to_string(), Case::Random, &[0], &config, &mut rng)?, "AbcDeF12");
+        assert_eq!(rng.position(), 6);
+        // Без заглавной (-c по умолчанию) монеты бросаются заново
+        let mut rng = Cursor::new([0, 1, 1, 0]);
+        assert_eq!(apply_case("aB12".to_string(), Case::Random, &[0], &config, &mut rng)?, "AB12");
+        assert_eq!(rng.position(), 4);
+
+        let config = Config { num_pw: 300, seed: Some(568), ..config };
+        let passwords = generate_passwords(&config)?;
+        let upper = passwords.iter().flat_map(str::chars).filter(char::is_ascii_uppercase).count();
+        let letters = passwords.iter().flat_map(str::chars).filter(char::is_ascii_alphabetic).count();
+        // Примерно половина букв заглавные
+        assert!((upper as f64 / letters as f64 - 0.5).abs() < 0.05, "{} of {}", upper, letters);
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_passwords() -> Result<(), RunError> {
+        for case in Case::ALL {
+            for secure in [false, true] {
+                let config = Config {
+                    case: Some(case),
+                    secure,
+                    ambiguous: true,
+                    num_pw: 300,
+                    seed: Some(568),
+                    ..test_config()
+                };
+                for password in generate_passwords(&config)?.iter() {
+                    assert_eq!(check::problems(password, &config), Vec::<String>::new(), "{}", password);
+                    match case {
+                        Case::Lower => assert!(!password.contains(|c: char| c.is_ascii_uppercase()), "{}", password),
+                        Case::Upper => assert!(!password.contains(|c: char| c.is_ascii_lowercase()), "{}", password),
+                        // Остальные заглавные остаются, только если строчной нет в наборе (-B)
+                        Case::Title if secure => {
+                            let mut upper = password.chars().filter(char::is_ascii_uppercase).skip(1);
+                            assert!(upper.all(|c| !is_allowed(c.to_ascii_lowercase(), &config)), "{}", password);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert_eq!(parse(&["--case", "random"]).unwrap().case, Some(Case::Random));
+        for args in [
+            &["--case", "upper", "-A"][..],
+            &["--case", "title", "-A"],
+            &["--case", "random", "--no-capitalize"],
+            &["--case", "lower", "-c"],
+            &["--case", "upper", "--min-lower", "2"],
+            &["--case", "lower", "--classes", "l4d2"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert_eq!(
+            parse(&["--case", "upper", "-A"]).unwrap_err().to_string(),
+            "-A/--no-capitalize cannot be used with --case upper"
+        );
+        assert!(parse(&["--case", "lower", "-A"]).is_ok());
+        assert!(matches!(parse(&["--case", "shout"]), Err(CliError::InvalidValue { option: "--case", .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_markov_passwords() -> Result<(), RunError> {
+        let config = Config {
+            memorable_model: MemorableModel::Markov,
+            symbols: true,
+            ambiguous: true,
+            num_pw: 200,
+            seed: Some(570),
+            ..test_config()
+        };
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            assert_eq!(check::problems(password, &config), Vec::<String>::new(), "{}", password);
+        }
+        // С тем же seed - те же пароли
+        assert_eq!(generate_passwords(&config)?, passwords);
+
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert_eq!(parse(&["--memorable-model", "markov"]).unwrap().memorable_model, MemorableModel::Markov);
+        for args in [
+            &["--memorable-model", "markov", "-s"][..],
+            &["--memorable-model", "markov", "-v"],
+            &["--memorable-model", "markov", "--url-safe"],
+            &["--memorable-model", "markov", "--balanced"],
+            &["--memorable-model", "markov", "--syllable-file", "syllables.txt"],
+            &["-s", "--syllable-file", "syllables.txt"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        // Модель по умолчанию с -s не спорит
+        assert!(parse(&["--memorable-model", "phoneme", "-s"]).is_ok());
+        assert!(matches!(
+            parse(&["--memorable-model", "ngram"]),
+            Err(CliError::InvalidValue { option: "--memorable-model", .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_leet_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert_eq!(parse(&["--leet"]).unwrap().leet, Some(leet::DEFAULT_COUNT));
+        assert_eq!(parse(&["--leet=4", "12"]).unwrap().leet, Some(4));
+        // Без "=" следующее слово - длина пароля, а не число замен
+        assert_eq!(parse(&["--leet", "12"]).unwrap().pw_length, 12);
+        assert!(matches!(parse(&["--leet=0"]), Err(CliError::InvalidNumber { .. })));
+        for args in [&["--leet", "-s"][..], &["--leet", "--words", "4"], &["--leet", "--url-safe"]] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+
+        // Модель Маркова дает только буквы, так что цифры в пароле - это
+        // замены, и обязательную цифру apply_requirements уже не вставляет
+        let config = Config {
+            memorable_model: MemorableModel::Markov,
+            leet: Some(2),
+            pw_length: 12,
+            num_pw: 200,
+            seed: Some(578),
+            ..test_config()
+        };
+        for password in generate_passwords(&config)?.iter() {
+            let digits = password.chars().filter(char::is_ascii_digit).count();
+            assert!((1..=2).contains(&digits), "{}", password);
+        }
+
+        // Требования выполнены, исключенные символы не появляются
+        let config = Config {
+            leet: Some(3),
+            symbols: true,
+            remove_chars: Some(vec!['3', '$', '@']),
+            ambiguous: true,
+            num_pw: 200,
+            seed: Some(578),
+            ..test_config()
+        };
+        for password in generate_passwords(&config)?.iter() {
+            assert_eq!(check::problems(password, &config), Vec::<String>::new(), "{}", password);
+            assert!(!password.contains(['3', '$', '@', '0', '1', '5']), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_repeat_batches() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        for args in [
+            &["--no-repeat", "--case", "lower"][..],
+            &["--no-repeat", "--words", "4"],
+            &["--no-repeat", "--mobile-friendly"],
+            &["--no-repeat", "--template", "Cvcvnn"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+
+        // Все генераторы, которые доходят до apply_requirements
+        let batches = [
+            &["-s", "--seed", "580", "8", "3000"][..],
+            &["-sy", "--seed", "580", "20", "1000"],
+            &["-sB", "--weights", "1:0:8:0", "--seed", "580", "12", "1000"],
+            &["-sy", "--balanced", "--seed", "580", "12", "1000"],
+            &["--seed", "580", "12", "2000"],
+            &["-y", "--leet", "--seed", "580", "12", "1000"],
+            &["--memorable-model", "markov", "--seed", "580", "12", "1000"],
+            &["-v", "--seed", "580", "12", "1000"],
+            &["-s", "--include-only", "ab1", "--min-digits", "3", "--seed", "580", "10", "1000"],
+        ];
+        for args in batches {
+            let mut list = vec!["--no-repeat"];
+            list.extend_from_slice(args);
+            let config = parse(&list).unwrap();
+            for password in generate_passwords(&config)?.iter() {
+                let chars: Vec<char> = password.chars().collect();
+                assert!(chars.windows(2).all(|pair| pair[0] != pair[1]), "{:?}: {}", args, password);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_sequences_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert_eq!(parse(&["--no-sequences"]).unwrap().no_sequences, Some(sequence::DEFAULT_RUN));
+        assert_eq!(parse(&["--no-sequences=4", "12"]).unwrap().no_sequences, Some(4));
+        for value in ["--no-sequences=1", "--no-sequences=0", "--no-sequences=x", "--no-sequences=2000"] {
+            assert!(matches!(parse(&[value]), Err(CliError::InvalidValue { .. })), "{}", value);
+        }
+
+        for args in [
+            &["-s", "--seed", "581", "16", "500"][..],
+            &["--seed", "581", "16", "500"],
+            &["-s", "--digits-only", "--seed", "581", "16", "500"],
+        ] {
+            let mut list = vec!["--no-sequences"];
+            list.extend_from_slice(args);
+            for password in generate_passwords(&parse(&list).unwrap())?.iter() {
+                assert!(!sequence::has_sequence(password, 3), "{:?}: {}", args, password);
+            }
+        }
+
+        // Из одних цифр длинный пароль без пар вроде "12" и "21" почти не
+        // выпадает: попытки кончаются ошибкой, а не вечным циклом
+        let config = parse(&["--no-sequences=2", "-s", "--digits-only", "--seed", "581", "200", "1"]).unwrap();
+        assert!(matches!(generate_passwords(&config), Err(RunError::Generation(_))));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "dictionary")]
+    fn test_no_dictionary_words_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert_eq!(parse(&["--no-dictionary-words"]).unwrap().no_dictionary_words, Some(4));
+        assert_eq!(parse(&["--no-dictionary-words=6", "12"]).unwrap().no_dictionary_words, Some(6));
+        for value in ["--no-dictionary-words=2", "--no-dictionary-words=x"] {
+            assert!(matches!(parse(&[value]), Err(CliError::InvalidValue { .. })), "{}", value);
+        }
+
+        // С тем же зерном без фильтра слова попадаются, с фильтром - нет
+        for args in [&["--seed", "583", "12", "300"][..], &["-s", "-A", "-0", "--seed", "583", "16", "300"]] {
+            let unfiltered = generate_passwords(&parse(args).unwrap())?;
+            assert!(unfiltered.iter().any(|password| dictionary::has_word(password, 4)), "{:?}", args);
+            let mut list = vec!["--no-dictionary-words"];
+            list.extend_from_slice(args);
+            for password in generate_passwords(&parse(&list).unwrap())?.iter() {
+                assert!(!dictionary::has_word(password, 4), "{:?}: {}", args, password);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(feature = "dictionary"))]
+    fn test_no_dictionary_words_without_feature() {
+        let config = Config {
+            no_dictionary_words: Some(4),
+            ..test_config()
+        };
+        assert_eq!(check_settings(&config).unwrap_err().to_string(), dictionary::missing());
+    }
+
+    #[test]
+    fn test_no_profanity_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert_eq!(parse(&["--no-profanity"]).unwrap().profanity, Some(profanity::Blocklist::builtin()));
+        let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/profanity.txt");
+        let config = parse(&["--profanity-file", fixture]).unwrap();
+        assert_eq!(config.profanity, Some(profanity::Blocklist::builtin()));
+        assert_eq!(config.profanity_file.as_deref(), Some(Path::new(fixture)));
+
+        // "ch" из тестового списка часто попадается в запоминаемых паролях
+        let mut list = profanity::Blocklist::builtin();
+        list.extend(profanity::load(Path::new(fixture))?);
+        let config = Config {
+            num_pw: 300,
+            seed: Some(584),
+            ..test_config()
+        };
+        let unfiltered = generate_passwords(&config)?;
+        assert!(unfiltered.iter().any(|password| list.matches(password)));
+        let config = Config { profanity: Some(list.clone()), ..config };
+        for password in generate_passwords(&config)?.iter() {
+            assert!(!list.matches(password), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pin_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--pin"]).unwrap();
+        assert!(config.pin && config.secure);
+        assert_eq!(config.pw_length, pin::DEFAULT_LENGTH);
+        let config = parse(&["--pin", "6", "10"]).unwrap();
+        assert_eq!((config.pw_length, config.num_pw), (6, 10));
+        assert_eq!(parse(&["-L", "8", "--pin"]).unwrap().pw_length, 8);
+        assert_eq!(parse(&["--pin", "--length-range", "4-6"]).unwrap().length_range, Some((4, 6)));
+        for (args, flag) in [
+            (&["--pin", "-y"][..], "-y/--symbols"),
+            (&["--pin", "--words", "3"], "--words"),
+            (&["--pin", "--digits-only"], "--digits-only"),
+            (&["--pin", "-r", "0"], "-r/--remove-chars"),
+            (&["--pin", "-B"], "-B/--ambiguous"),
+            (&["--pin", "--min-digits", "2"], "--min-*/--max-*"),
+            (&["--pin", "--no-repeat"], "--no-repeat"),
+        ] {
+            match parse(args) {
+                Err(CliError::ConflictingFlags(msg)) => assert_eq!(msg, format!("{} cannot be used with --pin", flag)),
+                other => panic!("{:?}: {:?}", args, other.map(|config| config.pw_length)),
+            }
+        }
+        let config = parse(&["--pin", "3"]).unwrap();
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+
+        // Из тех же цифр без --pin слабые коды попадаются, с --pin - нет
+        let unfiltered = parse(&["-s", "--digits-only", "--seed", "585", "4", "2000"]).unwrap();
+        assert!(generate_passwords(&unfiltered)?.iter().any(|pin| pin::weakness(pin).is_some()));
+        for args in [&["--seed", "585", "4", "2000"][..], &["--seed", "585", "--length-range", "4-8", "-N", "500"]] {
+            let mut list = vec!["--pin"];
+            list.extend_from_slice(args);
+            let config = parse(&list).unwrap();
+            let pins = generate_passwords(&config)?;
+            assert_eq!(pins.len(), config.num_pw);
+            for pin in pins.iter() {
+                let (min, max) = config.length_range.unwrap_or((4, 4));
+                assert!((min..=max).contains(&pin.len()), "{}", pin);
+                assert!(pin.bytes().all(|d| d.is_ascii_digit()), "{}", pin);
+                assert_eq!(pin::weakness(pin), None, "{}", pin);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_keyboard_walks_passwords() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        for args in [
+            &["-sy", "--seed", "582", "16", "500"][..],
+            &["--seed", "582", "16", "500"],
+            &["--no-sequences", "-s", "--digits-only", "--seed", "582", "12", "500"],
+        ] {
+            let mut list = vec!["--no-keyboard-walks"];
+            list.extend_from_slice(args);
+            let config = parse(&list).unwrap();
+            for password in generate_passwords(&config)?.iter() {
+                assert!(!keyboard::has_walk(password, keyboard::WALK_LENGTH), "{:?}: {}", args, password);
+                assert!(config.no_sequences.is_none_or(|run| !sequence::has_sequence(password, run)), "{}", password);
+            }
+        }
+
+        // Из одного ряда клавиатуры длинный пароль без прогулок почти не
+        // выпадает: попытки кончаются ошибкой, а не вечным циклом
+        let config =
+            parse(&["--no-keyboard-walks", "-s", "--include-only", "qwertyuiop", "--seed", "582", "1000", "1"]).unwrap();
+        let err = generate_passwords(&config).unwrap_err();
+        assert!(err.to_string().contains("--no-keyboard-walks"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_repeat_small_charsets() -> Result<(), RunError> {
+        // Из одного символа пароль без повторов не собрать
+        let config = Config {
+            no_repeat: true,
+            secure: true,
+            include_only: Some(vec!['a']),
+            ..test_config()
+        };
+        assert!(matches!(check_settings(&config), Err(RunError::Settings(_))));
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+        // Но пароль из одного символа - можно
+        let config = Config { pw_length: 1, num_pw: 3, ..config };
+        assert_eq!(generate_passwords(&config)?.iter().collect::<Vec<_>>(), ["a", "a", "a"]);
+
+        // Из двух символов - только чередование, и генерация завершается
+        for secure in [true, false] {
+            let config = Config {
+                no_repeat: true,
+                secure,
+                include_only: Some(vec!['a', 'b']),
+                pw_length: 40,
+                num_pw: 100,
+                seed: Some(580),
+                ..test_config()
+            };
+            for password in generate_passwords(&config)?.iter() {
+                assert!(password == "ab".repeat(20) || password == "ba".repeat(20), "{}", password);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "wordlist-en")]
+    #[test]
+    fn test_words_passphrases() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--words", "4", "--separator", ".", "-N", "20", "--seed", "572"]).unwrap();
+        assert_eq!(config.words, Some(4));
+        // Столбцы выключаются сами, но -C их возвращает
+        assert!(!config.columns);
+        assert!(parse(&["--words", "4", "-C"]).unwrap().columns);
+
+        let passwords = generate_passwords(&config)?;
+        assert_eq!(passwords.len(), 20);
+        let words = wordlist::words(&config)?;
+        for phrase in passwords.iter() {
+            let parts: Vec<&str> = phrase.split('.').collect();
+            assert_eq!(parts.len(), 4, "{}", phrase);
+            assert!(parts.iter().all(|part| words.iter().any(|word| word == part)), "{}", phrase);
+        }
+
+        for args in [
+            &["--words", "4", "12"][..],
+            &["--words", "4", "--length-range", "8-12"],
+            &["--words", "4", "-s"],
+            &["--words", "4", "-y"],
+            &["--words", "4", "--case", "upper"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert!(matches!(parse(&["--words", "0"]), Err(CliError::InvalidNumber { .. })));
+
+        // Вставки и заглавные складываются: ровно одна цифра и один символ
+        let args = ["--words", "3", "--separator", " ", "--capitalize-words", "--inject-digit", "--inject-symbol", "-N", "50"];
+        for phrase in generate_passwords(&parse(&args).unwrap())?.iter() {
+            assert_eq!(phrase.chars().filter(char::is_ascii_digit).count(), 1, "{}", phrase);
+            assert_eq!(phrase.chars().filter(|&c| in_class(SYMBOLS, c)).count(), 1, "{}", phrase);
+            let words: Vec<&str> = phrase.split(' ').collect();
+            assert_eq!(words.len(), 3, "{}", phrase);
+            assert!(words.iter().all(|word| word.trim_start_matches(|c: char| !c.is_alphabetic()).starts_with(char::is_uppercase)));
+        }
+
+        // Акростих задает и буквы, и число слов
+        let config = parse(&["--words-acrostic", "Banana", "-N", "20"]).unwrap();
+        assert_eq!(config.words, Some(6));
+        for phrase in generate_passwords(&config)?.iter() {
+            let initials: String = phrase.split('-').filter_map(|word| word.chars().next()).collect();
+            assert_eq!(initials, "banana", "{}", phrase);
+        }
+        for value in ["b", "ba na", "b4", ""] {
+            assert!(
+                matches!(parse(&["--words-acrostic", value]), Err(CliError::InvalidValue { option: "--words-acrostic", .. })),
+                "{:?}",
+                value
+            );
+        }
+        assert!(matches!(parse(&["--words", "4", "--words-acrostic", "ab"]), Err(CliError::ConflictingFlags(_))));
+        let config = parse(&["--words-acrostic", "xq"]).unwrap();
+        assert!(matches!(generate_passwords(&config), Err(RunError::Settings(_))));
+
+        // --wordlist сам включает фразы, но не меняет заданное --words
+        let config = parse(&["--wordlist", "words.txt"]).unwrap();
+        assert_eq!((config.words, config.columns), (Some(passphrase::DEFAULT_WORDS), false));
+        assert_eq!(parse(&["--words", "3", "--wordlist", "words.txt"]).unwrap().words, Some(3));
+        assert!(matches!(parse(&["--wordlist", "words.txt", "-y"]), Err(CliError::ConflictingFlags(_))));
+
+        // --lang тоже включает фразы и не сочетается с --wordlist
+        let config = parse(&["--lang", "es"]).unwrap();
+        assert_eq!((config.lang, config.words), (Some(Lang::Es), Some(passphrase::DEFAULT_WORDS)));
+        assert!(matches!(parse(&["--lang", "es", "--wordlist", "words.txt"]), Err(CliError::ConflictingFlags(_))));
+        assert!(matches!(parse(&["--lang", "pt"]), Err(CliError::InvalidValue { option: "--lang", .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_require_chars() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--require-chars=_", "--require-chars", "#_"]).unwrap();
+        assert_eq!(config.require_chars, Some(vec!['#', '_']));
+
+        // Символы не из набора и классы -c/-n ставятся в разные позиции,
+        // даже когда длины хватает впритык
+        for args in [
+            &["-s", "--require-chars=_#", "12"][..],
+            &["--require-chars=_#", "4"],
+            &["-s", "--require-chars=_#", "4"],
+        ] {
+            let mut config = parse(args).unwrap();
+            config.num_pw = 500;
+            config.seed = Some(559);
+            for password in generate_passwords(&config)?.iter() {
+                assert!(password.contains('_') && password.contains('#'), "{}", password);
+                assert!(password.bytes().any(|c| c.is_ascii_uppercase()), "{}", password);
+                assert!(password.bytes().any(|c| c.is_ascii_digit()), "{}", password);
+                assert!(check::problems(password, &config).is_empty(), "{}", password);
+            }
+        }
+
+        let config = parse(&["--require-chars=_#", "3"]).unwrap();
+        let err = generate_passwords(&config).unwrap_err();
+        assert!(matches!(err, RunError::Settings(_)));
+        assert_eq!(
+            err.to_string(),
+            "length 3 cannot contain 4 required character classes and characters (uppercase, digit, '#', '_')"
+        );
+        let config = parse(&["-s", "--require-chars=_"]).unwrap();
+        assert_eq!(check::problems("Abcdefg1", &config), vec!["no '_'"]);
+
+        assert!(matches!(parse(&["--require-chars=_", "-r", "x_"]), Err(CliError::ConflictingFlags(_))));
+        assert!(parse(&["--require-chars= "]).is_err());
+
+        // Символ, который режим исключает из набора, потребовать нельзя
+        for conflict in [
+            &["--url-safe", "--require-chars=!"][..],
+            &["--digits-only", "--require-chars=a"],
+            &["-A", "--require-chars=A"],
+            &["-B", "--require-chars=0"],
+            &["-v", "--require-chars=e"],
+            &["--no-shift", "--require-chars=_"],
+            &["--charset=hex", "--require-chars=Z"],
+            &["--include-only=abc", "--require-chars=z"],
+        ] {
+            assert!(matches!(parse(conflict), Err(CliError::ConflictingFlags(_))), "{:?}", conflict);
+        }
+        // Символ не из набора, но и не исключенный им, по-прежнему можно
+        for allowed in [&["--url-safe", "--require-chars=_"][..], &["--include-only=abc", "--require-chars=c"]] {
+            assert!(parse(allowed).is_ok(), "{:?}", allowed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_weights() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--weights=8:0:3:1"]).unwrap();
+        assert_eq!(config.weights, Some([8, 0, 3, 1]));
+        assert!(config.secure && config.no_capitalize && !config.no_numerals);
+
+        for bad in ["8:3:1", "8:3:1:1:1", "8:x:1:1", "0:0:0:0", "8:1001:1:1"] {
+            assert!(
+                matches!(parse(&["--weights", bad]), Err(CliError::InvalidValue { .. })),
+                "{}",
+                bad
+            );
+        }
+        // Флаг класса не может противоречить весу
+        for conflict in [
+            &["--weights=8:0:3:1", "-c"][..],
+            &["--weights=8:1:3:1", "-A"],
+            &["--weights=8:1:0:1", "-n"],
+            &["--weights=8:1:3:1", "-0"],
+            &["--weights=8:1:3:0", "-y"],
+            &["--weights=8:1:3:1", "--charset=hex"],
+        ] {
+            assert!(matches!(parse(conflict), Err(CliError::ConflictingFlags(_))), "{:?}", conflict);
+        }
+        assert!(parse(&["--weights=8:1:3:1", "-y"]).is_ok());
+    }
+
+    #[test]
+    fn test_weighted_class_frequencies() -> Result<(), RunError> {
+        // Без обязательных классов частоты задаются только весами
+        let config = Config {
+            secure: true,
+            weights: Some([8, 3, 3, 1]),
+            capitalize: false,
+            numerals: false,
+            pw_length: 20,
+            num_pw: 3000,
+            seed: Some(560),
+            ..test_config()
+        };
+        let mut counts = [0usize; 4];
+        for password in generate_passwords(&config)?.iter() {
+            for c in password.bytes() {
+                let class = [LOWERCASE, UPPERCASE, NUMERALS, SYMBOLS].iter().position(|class| class.contains(&c));
+                counts[class.unwrap()] += 1;
+            }
+        }
+        // 60000 символов: стандартное отклонение доли не больше 0.2%
+        let total: usize = counts.iter().sum();
+        for (count, weight) in counts.iter().zip([8, 3, 3, 1]) {
+            let observed = *count as f64 / total as f64;
+            let expected = weight as f64 / 15.0;
+            assert!((observed - expected).abs() < 0.01, "{:?}", counts);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_url_safe() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let mut config = parse(&["--url-safe", "24", "2000"]).unwrap();
+        assert!(config.secure);
+        config.seed = Some(554);
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            assert!(password.bytes().all(|c| URL_SAFE.contains(&c)), "{}", password);
+        }
+        // Разделители URL не нужны и в наборе, из которого выбирают
+        assert!(!build_charset(&config).iter().any(|c| "&?/%#=+@:".contains(*c)));
+
+        for conflict in [
+            &["--url-safe", "-y"][..],
+            &["--url-safe", "--charset=hex"],
+            &["--include-only=abc", "--url-safe"],
+            &["--url-safe", "--charset-file", "chars.txt"],
+        ] {
+            assert!(
+                matches!(parse(conflict), Err(CliError::ConflictingFlags(_))),
+                "{:?}",
+                conflict
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_charset_presets() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let expected: [(&str, &[u8]); 5] = [
+            ("hex", b"0123456789abcdef"),
+            ("base32", b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567"),
+            ("base58", b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"),
+            ("alnum", b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789"),
+            ("printable", &(b'!'..=b'~').collect::<Vec<u8>>()),
+        ];
+        for (name, alphabet) in expected {
+            let config = parse(&["--charset", name]).unwrap();
+            // Алфавит подразумевает -s и возвращается как есть
+            assert!(config.secure, "{}", name);
+            assert!(build_charset(&config).into_iter().eq(alphabet.iter().map(|&c| char::from(c))), "{}", name);
+        }
+
+        // -B и -r убирают символы и из алфавита
+        let config = parse(&["--charset=hex", "-B", "-r", "a-c"]).unwrap();
+        assert_eq!(String::from_iter(build_charset(&config)), "3479def");
+
+        assert!(matches!(
+            parse(&["--charset", "base64"]),
+            Err(CliError::InvalidValue { option: "--charset", .. })
+        ));
+    }
+
+    #[test]
+    fn test_charset_preset_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        for args in [
+            &["--charset", "hex", "-y"][..],
+            &["--charset", "hex", "-c"],
+            &["--charset", "base32", "-y"],
+            &["--charset", "base58", "--symbols"],
+            &["--charset", "alnum", "-y"],
+            &["--charset", "hex", "--include-only", "abc"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert_eq!(
+            parse(&["--charset=hex", "-y"]).unwrap_err().to_string(),
+            "-y/--symbols conflicts with --charset hex, which has no symbols"
+        );
+        for args in [
+            &["--charset", "hex", "-n"][..],
+            &["--charset", "base32", "-c", "-n"],
+            &["--charset", "printable", "-c", "-n", "-y"],
+        ] {
+            assert!(parse(args).is_ok(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_charset_preset_passwords() -> Result<(), RunError> {
+        let mut args = vec!["pwgen".to_string()];
+        args.extend(["--charset", "base32", "--seed", "4", "16", "100"].iter().map(|s| s.to_string()));
+        let config = parse_args_from_vec(args).unwrap();
+        for password in generate_passwords(&config)?.iter() {
+            assert_eq!(password.len(), 16);
+            assert!(password.bytes().all(|c| BASE32.contains(&c)), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_alpha_and_digits_only() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let expected = [
+            (&["--alpha-only"][..], "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+            (&["--alpha-only", "-A"], "abcdefghijklmnopqrstuvwxyz"),
+            (&["--alpha-only", "-v", "-s"], "bcdfghjklmnpqrstvwxzBCDFGHJKLMNPQRSTVWXZ"),
+            (&["--digits-only"], "0123456789"),
+            (&["--digits-only", "-A", "-n"], "0123456789"),
+        ];
+        for (args, charset) in expected {
+            let mut config = parse(args).unwrap();
+            assert_eq!(String::from_iter(build_charset(&config)), charset, "{:?}", args);
+
+            // Требования к классам вне набора пропускаются, а не валят генерацию
+            config.num_pw = 300;
+            config.seed = Some(566);
+            for password in generate_passwords(&config)?.iter() {
+                assert!(password.chars().all(|c| charset.contains(c)), "{}", password);
+                assert_eq!(check::problems(password, &config), Vec::<String>::new(), "{}", password);
+            }
+        }
+        assert!(parse(&["--digits-only"]).unwrap().secure);
+        assert!(!parse(&["--alpha-only"]).unwrap().secure);
+
+        for args in [
+            &["--digits-only", "-y"][..],
+            &["--digits-only", "--symbols"],
+            &["--digits-only", "-c"],
+            &["--digits-only", "-0"],
+            &["--digits-only", "--alpha-only"],
+            &["--alpha-only", "-n"],
+            &["--alpha-only", "-y"],
+            &["--alpha-only", "--charset", "hex"],
+            &["--alpha-only", "--include-only", "abc"],
+            &["--digits-only", "--url-safe"],
+            &["--digits-only", "--weights", "1:1:1:0"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert_eq!(
+            parse(&["--digits-only", "-y"]).unwrap_err().to_string(),
+            "-y/--symbols cannot be used with --digits-only"
+        );
+        assert_eq!(
+            parse(&["--alpha-only", "-n"]).unwrap_err().to_string(),
+            "-n/--numerals cannot be used with --alpha-only"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_wpa_preset() -> Result<(), RunError> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        // Без длины в том же слое - 20 символов, и всегда -s
+        let config = parse(&["--preset", "wpa"]).unwrap();
+        assert!(config.secure);
+        assert_eq!(config.pw_length, 20);
+        assert_eq!(parse(&["--preset=wpa", "8"]).unwrap().pw_length, 8);
+        assert_eq!(parse(&["--preset=wpa", "-L", "63"]).unwrap().pw_length, 63);
+        assert_eq!(parse(&["--preset=wpa", "--length-range", "12-16"]).unwrap().length_range, Some((12, 16)));
+
+        for (args, length) in [
+            (&["--preset=wpa", "7"][..], "7"),
+            (&["--preset=wpa", "64"], "64"),
+            (&["--preset=wpa", "-L", "1"], "1"),
+            (&["--preset=wpa", "--length-range", "4-20"], "4-20"),
+            (&["--preset=wpa", "--length-range", "20-64"], "20-64"),
+        ] {
+            assert_eq!(
+                parse(args).unwrap_err().to_string(),
+                format!("--preset wpa allows lengths from 8 to 63 characters, not {}", length)
+            );
+        }
+
+        for args in [
+            &["--preset=wpa", "-A"][..],
+            &["--preset=wpa", "-0"],
+            &["--preset=wpa", "-v"],
+            &["--preset=wpa", "--charset", "hex"],
+            &["--preset=wpa", "--include-only", "abc"],
+            &["--preset=wpa", "--alpha-only"],
+            &["--preset=wpa", "--url-safe"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        assert_eq!(
+            parse(&["--preset=wpa", "-A"]).unwrap_err().to_string(),
+            "-A/--no-capitalize cannot be used with --preset wpa"
+        );
+        assert!(matches!(parse(&["--preset", "wep"]), Err(CliError::InvalidValue { option: "--preset", .. })));
+
+        let mut config = parse(&["--preset=wpa", "-y"]).unwrap();
+        config.num_pw = 300;
+        config.seed = Some(567);
+        for password in generate_passwords(&config)?.iter() {
+            assert_eq!(password.len(), 20);
+            assert!(password.bytes().all(|c| c.is_ascii_graphic() && c != b'"'), "{}", password);
+            assert_eq!(check::problems(password, &config), Vec::<String>::new(), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_only_charset() {
+        let mut config = test_config();
+        config.symbols = true;
+        config.include_only = Some("abcdefghijkmnopqrstuvwxyz23456789".chars().collect());
+        // Классы (-c, -y) набор не дополняют
+        assert_eq!(String::from_iter(build_charset(&config)), "abcdefghijkmnopqrstuvwxyz23456789");
+
+        // -B, -v и -r по-прежнему убирают символы
+        config.ambiguous = true;
+        config.no_vowels = true;
+        config.remove_chars = Some(vec!['x', '9']);
+        assert_eq!(String::from_iter(build_charset(&config)), "bcdfghjkmnpqrstvwz347");
+    }
+
+    #[test]
+    fn test_include_only_requirements() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.secure = true;
+        config.symbols = true;
+        config.num_pw = 200;
+        config.seed = Some(3);
+        config.include_only = Some("abcdefgh23#".chars().collect());
+
+        // Заглавных в списке нет - требование пропускается
+        let charset = build_charset(&config);
+        let required: Vec<&str> = required_classes(&config, &charset).iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(required, ["digit", "symbol"]);
+
+        // Обязательные цифры и символы берутся только из списка
+        for password in generate_passwords(&config)?.iter() {
+            assert!(password.bytes().all(|c| b"abcdefgh23#".contains(&c)), "{}", password);
+            assert!(password.bytes().any(|c| c == b'2' || c == b'3'), "{}", password);
+            assert!(password.contains('#'), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_only_memorable() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.num_pw = 50;
+        config.seed = Some(5);
+
+        config.include_only = Some("0123456789".chars().collect());
+        let err = generate_passwords(&config).unwrap_err();
+        assert!(err.to_string().contains("no consonants left"), "{}", err);
+
+        // Согласные есть, гласных нет - шаблон согласная-гласная не составить
+        config.include_only = Some("bcdfg".chars().collect());
+        let err = generate_passwords(&config).unwrap_err();
+        assert!(err.to_string().contains("no vowels left"), "{}", err);
+
+        config.include_only = Some("bdkmaeoBK7".chars().collect());
+        for password in generate_passwords(&config)?.iter() {
+            assert!(password.bytes().all(|c| b"bdkmaeoBK7".contains(&c)), "{}", password);
+            assert!(password.contains('7'), "{}", password);
+            assert!(password.bytes().any(|c| c == b'B' || c == b'K'), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_secure_password() -> Result<(), RunError> {
+        let config = test_config();
+        let mut rng = seeded_rng(1);
+
+        let password = generate_secure_password(8, &config, &mut rng)?;
+
+        assert_eq!(password.len(), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_memorable_password_pattern() -> Result<(), RunError> {
+        let config = test_config();
+        let mut rng = seeded_rng(2);
+
+        let password = generate_memorable_password(8, &config, &mut rng)?;
+
+        assert_eq!(password.len(), 8);
+        // Нечетные позиции заняты гласными (если их не заменили требования)
+        let vowel_count = password
+            .bytes()
+            .skip(1)
+            .step_by(2)
+            .filter(|c| VOWELS.contains(c))
+            .count();
+        assert!(vowel_count >= 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generators_accept_cursor_source() -> Result<(), RunError> {
+        // Любой Read по-прежнему годится как источник энтропии.
+        // Без обязательных классов байты переходят в символы напрямую.
+        let mut config = test_config();
+        config.no_capitalize = true;
+        config.no_numerals = true;
+        let mut mock_rng = Cursor::new(vec![0, 1, 2, 3, 4, 5, 6, 7]);
+
+        let password = generate_secure_password(8, &config, &mut mock_rng)?;
+
+        assert_eq!(password, "abcdefgh");
+        Ok(())
+    }
+
+    #[test]
+    fn test_secure_password_enforces_classes() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.symbols = true;
+
+        // Первые восемь байтов дают "abcdefgh" - раньше так и выводилось
+        let mut bytes: Vec<u8> = (0..8).collect();
+        bytes.extend_from_slice(&[0, 3, 5, 6, 0, 1]);
+        bytes.resize(64, 0);
+        let password = generate_secure_password(8, &config, &mut Cursor::new(bytes))?;
+
+        assert!(password.bytes().any(|c| c.is_ascii_uppercase()), "{}", password);
+        assert!(password.bytes().any(|c| c.is_ascii_digit()), "{}", password);
+        assert!(password.bytes().any(|c| SYMBOLS.contains(&c)), "{}", password);
+        Ok(())
+    }
+
+    #[test]
+    fn test_secure_requirements_respect_no_vowels() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.no_vowels = true;
+        config.ambiguous = true;
+
+        // Вставленная заглавная буква и цифра тоже проходят через фильтры
+        for seed in 0..20 {
+            let password = generate_secure_password(6, &config, &mut seeded_rng(seed))?;
+            assert!(!password.bytes().any(|c| VOWELS.contains(&c) || AMBIGUOUS.contains(&c)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_memorable_remove_all_vowels_but_one() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.no_capitalize = true;
+        config.no_numerals = true;
+        config.remove_chars = Some("aeiuy".chars().collect());
+
+        for seed in 0..20 {
+            let password = generate_memorable_password(8, &config, &mut seeded_rng(seed))?;
+            // Из гласных остались только элементы o, oo и oh
+            assert!(password.bytes().all(|c| c == b'o' || CONSONANTS_LOWER.contains(&c)), "{}", password);
+            assert!(password.contains('o'), "{}", password);
+            assert!(!password.bytes().any(|c| b"aeiuy".contains(&c)), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_memorable_all_vowels_removed_is_error() {
+        let mut config = test_config();
+        config.no_capitalize = true;
+        config.remove_chars = Some("aeiouy".chars().collect());
+
+        let err = generate_memorable_password(8, &config, &mut seeded_rng(1)).unwrap_err();
+        assert!(matches!(err, RunError::Settings(_)));
+        assert!(err.to_string().contains("no vowels left"));
+    }
+
+    #[test]
+    fn test_empty_charset_is_error() {
+        let combinations: [&[&str]; 4] = [
+            &["-A", "-0", "-v", "-r", "bcdfghjklmnpqrstvwxz"],
+            &["-s", "-A", "-0", "-v", "-r", "bcdfghjklmnpqrstvwxz"],
+            &["-s", "-A", "-0", "-rabcdefghijklmnopqrstuvwxyz"],
+            &["-s", "-0", "-B", "-r", "abcdefghijklmnopqrstuvwxyzACEFHJKLMNPRTUVWXY"],
+        ];
+        for flags in combinations {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(flags.iter().map(|s| s.to_string()));
+            let err = generate_passwords(&parse_args_from_vec(args).unwrap()).unwrap_err();
+            assert!(matches!(err, RunError::Settings(_)), "{:?}", flags);
+            assert!(err.to_string().contains("character set is empty"), "{:?}", flags);
+        }
+
+        // Генератор сам по себе тоже не выдает "aaaaaaaa"
+        let mut config = test_config();
+        config.remove_chars = Some(build_charset(&config));
+        assert!(generate_secure_password(8, &config, &mut seeded_rng(1)).is_err());
+    }
+
+    #[test]
+    fn test_generate_memorable_password_no_capitalize() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.no_capitalize = true;
+        let mut rng = seeded_rng(3);
+
+        let password = generate_memorable_password(8, &config, &mut rng)?;
+
+        // Не должно быть заглавных букв
+        assert!(!password.chars().any(|c| c.is_uppercase()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_password_no_vowels() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.no_vowels = true;
+        let mut rng = seeded_rng(4);
+
+        let password = generate_memorable_password(10, &config, &mut rng)?;
+
+        // Пароль должен быть сгенерирован
+        assert_eq!(password.len(), 10);
+        // Не должен содержать гласные
+        let vowels = "aeiouyAEIOUY";
+        assert!(!password.chars().any(|c| vowels.contains(c)));
+        Ok(())
+    }
+
+    // Пароль разбивается на диграфы из CONSONANT_DIGRAPHS и одиночные согласные
+    fn has_digraph(password: &str) -> bool {
+        password
+            .to_ascii_lowercase()
+            .as_bytes()
+            .windows(2)
+            .any(|pair| CONSONANT_DIGRAPHS.iter().any(|digraph| digraph[..] == *pair))
+    }
+
+    #[test]
+    fn test_no_vowels_memorable_structure() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.no_vowels = true;
+        config.no_numerals = true;
+
+        for seed in 0..50 {
+            let password = generate_memorable_password(12, &config, &mut seeded_rng(seed))?;
+            assert_eq!(password.len(), 12);
+            assert!(!password.bytes().any(|c| VOWELS.contains(&c)), "{}", password);
+            assert!(password.bytes().all(|c| c.is_ascii_alphabetic()), "{}", password);
+            assert!(password.bytes().any(|c| c.is_ascii_uppercase()), "{}", password);
+            assert!(has_digraph(&password), "{}", password);
+
+            let lower = password.to_ascii_lowercase();
+            assert!(
+                !lower.as_bytes().windows(3).any(|w| w[0] == w[1] && w[1] == w[2]),
+                "{}",
+                password
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_vowels_memorable_respects_filters() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.no_vowels = true;
+        config.no_capitalize = true;
+        config.ambiguous = true;
+        config.remove_chars = Some("rst".chars().collect());
+
+        for seed in 0..20 {
+            let password = generate_memorable_password(10, &config, &mut seeded_rng(seed))?;
+            assert!(!password.bytes().any(|c| c.is_ascii_uppercase()), "{}", password);
+            assert!(!password.bytes().any(|c| b"rst".contains(&c)), "{}", password);
+            assert!(!password.bytes().any(|c| AMBIGUOUS.contains(&c)), "{}", password);
+            assert!(password.bytes().any(|c| c.is_ascii_digit()), "{}", password);
+        }
+
+        // Из согласных ничего не осталось
+        config.remove_chars = Some(CONSONANTS_LOWER.iter().map(|&c| c as char).collect());
+        let err = generate_memorable_password(8, &config, &mut seeded_rng(0)).unwrap_err();
+        assert!(err.to_string().contains("no consonants left"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_requirements_adds_capital() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.no_numerals = true; // Отключаем цифры, чтобы они не мешали тесту
+        let mut rng = seeded_rng(5);
+
+        // Пароль без заглавных букв
+        let password: Vec<char> = "abcdefgh".chars().collect();
+        let result = apply_requirements(password, &config, &mut rng)?;
+
+        // Должна быть хотя бы одна заглавная буква
+        assert!(result.chars().any(|c| c.is_uppercase()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_requirements_adds_numeral() -> Result<(), RunError> {
+        let config = test_config();
+        let mut rng = seeded_rng(6);
+
+        // Пароль без цифр
+        let password: Vec<char> = "abcdefgh".chars().collect();
+        let result = apply_requirements(password, &config, &mut rng)?;
+
+        // Должна быть хотя бы одна цифра
+        assert!(result.chars().any(|c| c.is_ascii_digit()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_requirements_adds_symbol() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.symbols = true;
+        let mut rng = seeded_rng(7);
+
+        // Пароль без символов
+        let password: Vec<char> = "abcdefgh".chars().collect();
+        let result = apply_requirements(password, &config, &mut rng)?;
+
+        // Должен быть хотя бы один символ
+        assert!(result.chars().any(|c| SYMBOLS.contains(&(c as u8))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_requirements_distinct_positions() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.symbols = true;
+
+        // Раньше: 'A' в позицию 1, затем '5' в позицию 1 поверх нее,
+        // затем '!' опять в позицию 1 - оставался только символ.
+        // Теперь позиции выбираются среди свободных.
+        for length in 3..=4 {
+            let bytes = [0, 1, 5, 0, 0, 0, 0, 0];
+            let password: Vec<char> = "abcd".chars().take(length).collect();
+            let result = apply_requirements(password, &config, &mut Cursor::new(bytes))?;
+            assert!(result.bytes().any(|c| c.is_ascii_uppercase()), "{}", result);
+            assert!(result.bytes().any(|c| c.is_ascii_digit()), "{}", result);
+            assert!(result.bytes().any(|c| SYMBOLS.contains(&c)), "{}", result);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_requirements_keeps_existing_class() -> Result<(), RunError> {
+        let config = test_config();
+
+        // Единственная заглавная буква не должна уйти под цифру
+        for seed in 0..50 {
+            let result = apply_requirements("Ab".chars().collect(), &config, &mut seeded_rng(seed))?;
+            assert!(result.starts_with('A'), "{}", result);
+            assert!(result.as_bytes()[1].is_ascii_digit(), "{}", result);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_requirements_too_short() {
+        let mut config = test_config();
+        config.symbols = true;
+
+        let err = apply_requirements("ab".chars().collect(), &config, &mut seeded_rng(1)).unwrap_err();
+        assert!(matches!(err, RunError::Settings(_)));
+        assert!(err.to_string().contains("uppercase, digit, symbol"));
+    }
+
+    #[test]
+    fn test_parse_length_range() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--length-range", "12-16", "-N", "3"]).unwrap();
+        assert_eq!((config.length_range, config.pw_length, config.num_pw), (Some((12, 16)), 16, 3));
+        assert_eq!(parse(&["--length-range=9-9"]).unwrap().length_range, Some((9, 9)));
+
+        for bad in ["16-12", "12", "0-4", "4-", "a-b", "1-2000"] {
+            assert!(parse(&["--length-range", bad]).is_err(), "{}", bad);
+        }
+        // С фиксированной длиной диапазон не сочетается
+        for args in [&["--length-range", "12-16", "14"][..], &["--length-range", "12-16", "-L", "14"]] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+
+        // Длина в командной строке отменяет диапазон из PWGEN_OPTS
+        let args = vec!["pwgen".to_string(), "20".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("--length-range 12-16")).unwrap();
+        assert_eq!((config.length_range, config.pw_length), (None, 20));
+    }
+
+    #[test]
+    fn test_parse_bits() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["-s", "--bits", "96", "-N", "3"]).unwrap();
+        assert_eq!((config.bits, config.num_pw), (Some(96), 3));
+        for bad in ["0", "-1", "x", "4097"] {
+            assert!(parse(&["--bits", bad]).is_err(), "{}", bad);
+        }
+        // Длину задает --bits, а не позиционный аргумент
+        let fixed = [&["--bits", "96", "14"][..], &["--bits", "96", "-L", "14"], &["--bits=96", "--length-range", "8-9"]];
+        for args in fixed {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        for (args, flag) in [
+            (&["--bits", "96", "--words", "4"][..], "--words"),
+            (&["--bits", "96", "--classes", "l4d2"], "--classes"),
+            (&["--bits", "96", "--template", "nnnn"], "--template"),
+            (&["--bits", "96", "--preset", "wpa"], "--preset"),
+        ] {
+            match parse(args) {
+                Err(CliError::ConflictingFlags(msg)) => assert_eq!(msg, format!("{} cannot be used with --bits", flag)),
+                other => panic!("{:?}: {:?}", args, other.map(|config| config.bits)),
+            }
+        }
+
+        // Число слов фразы из --lang и --wordlist подбирает --bits; ошибка
+        // называет флаг, который задан на самом деле
+        let config = parse(&["--bits", "60", "--lang", "de"]).unwrap();
+        assert_eq!((config.bits, config.words), (Some(60), Some(passphrase::DEFAULT_WORDS)));
+        assert!(parse(&["--bits", "60", "--wordlist", "words.txt"]).is_ok());
+        match parse(&["--bits", "60", "--lang", "de", "--words-acrostic", "abc"]) {
+            Err(CliError::ConflictingFlags(msg)) => assert_eq!(msg, "--words-acrostic cannot be used with --bits"),
+            other => panic!("{:?}", other.map(|config| config.bits)),
+        }
+
+        // Слой командной строки отменяет длину или --bits из PWGEN_OPTS
+        let args = vec!["pwgen".to_string(), "20".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("--bits 96")).unwrap();
+        assert_eq!((config.bits, config.pw_length), (None, 20));
+        let args = vec!["pwgen".to_string(), "--bits".to_string(), "64".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("--length-range 12-16")).unwrap();
+        assert_eq!((config.bits, config.length_range), (Some(64), None));
+        let args = vec!["pwgen".to_string(), "--words".to_string(), "6".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("--bits 96")).unwrap();
+        assert_eq!((config.bits, config.words), (None, Some(6)));
+    }
+
+    #[test]
+    fn test_length_range_spans_range() -> Result<(), RunError> {
+        for secure in [false, true] {
+            let config = Config {
+                secure,
+                symbols: true,
+                length_range: Some((3, 6)),
+                pw_length: 6,
+                num_pw: 200,
+                seed: Some(7),
+                ..test_config()
+            };
+            let passwords = generate_passwords(&config)?;
+            let mut lengths: Vec<usize> = passwords.iter().map(str::len).collect();
+            lengths.sort();
+            lengths.dedup();
+            assert_eq!(lengths, [3, 4, 5, 6]);
+            // Требования выполняются и у паролей длины MIN
+            for password in passwords.iter() {
+                assert!(selftest::missing_classes(password, &config).is_empty(), "{}", password);
+            }
+        }
+
+        // MIN короче числа обязательных классов - ошибка, даже если MAX хватает
+        let config = Config {
+            symbols: true,
+            length_range: Some((2, 8)),
             pw_length: 8,
-            num_pw: 1,
-            capitalize: true,
-            no_capitalize: false,
-            numerals: true,
-            no_numerals: false,
-            symbols: false,
-            remove_chars: None,
-            secure: false,
-            ambiguous: false,
-            columns: false,
-            no_vowels: false,
-            help: false,
+            ..test_config()
+        };
+        let err = generate_passwords(&config).unwrap_err();
+        assert!(err.to_string().starts_with("length 2 cannot contain 3"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_length_must_fit_required_classes() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.symbols = true;
+        config.num_pw = 5;
+
+        config.pw_length = 2;
+        let err = generate_passwords(&config).unwrap_err();
+        assert!(matches!(err, RunError::Settings(_)));
+        assert!(err
+            .to_string()
+            .starts_with("length 2 cannot contain 3 required character classes"));
+
+        config.pw_length = 0;
+        assert!(generate_passwords(&config).is_err());
+
+        // Граница: длина равна числу классов - по одному символу каждого
+        config.pw_length = 3;
+        for secure in [false, true] {
+            config.secure = secure;
+            for password in generate_passwords(&config)?.iter() {
+                assert!(selftest::missing_classes(password, &config).is_empty(), "{}", password);
+            }
+        }
+
+        // Без обязательных классов хватает и одного символа
+        let mut config = test_config();
+        config.no_capitalize = true;
+        config.no_numerals = true;
+        config.pw_length = 1;
+        assert_eq!(generate_passwords(&config)?.get(0).len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_args_default() {
+        let args = vec!["pwgen".to_string()];
+        let config = parse_args_from_vec(args).unwrap();
+
+        assert_eq!(config.pw_length, DEFAULT_LENGTH);
+        assert!(!config.num_pw_given);
+        assert!(config.capitalize);
+        assert!(config.numerals);
+    }
+
+    #[test]
+    fn test_parse_args_with_length() {
+        let args = vec!["pwgen".to_string(), "12".to_string()];
+        let config = parse_args_from_vec(args).unwrap();
+
+        assert_eq!(config.pw_length, 12);
+        assert!(!config.num_pw_given);
+    }
+
+    #[test]
+    fn test_parse_args_with_length_and_count() {
+        let args = vec!["pwgen".to_string(), "12".to_string(), "5".to_string()];
+        let config = parse_args_from_vec(args).unwrap();
+
+        assert_eq!(config.pw_length, 12);
+        assert_eq!(config.num_pw, 5);
+    }
+
+    #[test]
+    fn test_parse_args_rejects_bad_numbers() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let length_error = |value: &str| CliError::InvalidNumber {
+            what: "password length",
+            value: value.to_string(),
+            max: Some(MAX_LENGTH),
+        };
+
+        // Опечатка: буква o вместо нуля
+        assert_eq!(parse(&["1o", "5"]).unwrap_err(), length_error("1o"));
+        assert_eq!(parse(&["0"]).unwrap_err(), length_error("0"));
+        assert_eq!(parse(&["999999999999"]).unwrap_err(), length_error("999999999999"));
+
+        let err = parse(&["8", "0"]).unwrap_err();
+        assert!(err.to_string().contains("number of passwords '0'"), "{}", err);
+        let err = parse(&["8", "999999999999"]).unwrap_err();
+        assert!(err.to_string().contains("number of passwords"), "{}", err);
+
+        assert!(parse(&["1024", "10000000"]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args).unwrap_err()
+        };
+
+        assert_eq!(parse(&["--bogus"]), CliError::unknown_option(options::OPTIONS, "--bogus"));
+        assert_eq!(parse(&["-5"]), CliError::unknown_option(options::OPTIONS, "-5"));
+        assert_eq!(
+            parse(&["--sybols"]).to_string(),
+            "unknown option --sybols (did you mean --symbols?)"
+        );
+        assert_eq!(parse(&["-r"]), CliError::MissingValue("--remove-chars"));
+        assert_eq!(parse(&["--seed"]), CliError::MissingValue("--seed"));
+        assert_eq!(parse(&["8", "1", "2"]), CliError::TooManyArguments);
+        assert_eq!(parse(&["--seed=x"]), CliError::invalid_number("seed", "x"));
+        assert!(matches!(
+            parse(&["--rng=magic"]),
+            CliError::InvalidValue { option: "--rng", .. }
+        ));
+        assert!(matches!(
+            parse(&["--pick=6", "8", "5"]),
+            CliError::InvalidNumber { what: "--pick value", .. }
+        ));
+        assert_eq!(
+            parse(&["--bogus"]).to_string(),
+            "unknown option --bogus"
+        );
+    }
+
+    #[test]
+    fn test_conflicting_flags() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let message = |list: &[&str]| match parse(list) {
+            Err(CliError::ConflictingFlags(message)) => message,
+            other => panic!("{:?}: {:?}", list, other),
+        };
+
+        assert!(message(&["-c", "-A"]).starts_with("-c and -A conflict"));
+        assert!(message(&["--no-capitalize", "--capitalize"]).starts_with("-c and -A conflict"));
+        assert!(message(&["-n", "-0"]).starts_with("-n and -0 conflict"));
+        assert!(message(&["-0", "--numerals"]).starts_with("-n and -0 conflict"));
+
+        let symbols = String::from_utf8(SYMBOLS.to_vec()).unwrap();
+        assert!(message(&["-y", "-r", &symbols]).contains("removes every symbol"));
+        assert!(message(&["-n", "-r", "0123456789"]).contains("removes every digit"));
+        assert!(message(&["-c", "-r", "ABCDEFGHIJKLMNOPQRSTUVWXYZ"]).contains("every capital letter"));
+
+        assert_eq!(message(&["--clear-after", "10"]), "--clear-after requires --copy");
+        assert_eq!(message(&["--clear-after=0", "16", "3"]), "--clear-after requires --copy");
+        assert!(parse(&["--copy", "--clear-after", "10"]).is_ok());
+
+        // Частичное удаление и значения по умолчанию - не конфликт
+        assert!(parse(&["-y", "-r", "!@#"]).is_ok());
+        assert!(parse(&["-r", "0123456789"]).is_ok());
+        assert!(parse(&["-A"]).is_ok());
+    }
+
+    #[test]
+    fn test_columns_last_flag_wins() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args).unwrap()
+        };
+        assert!(!parse(&["-C", "-1"]).columns);
+        assert!(parse(&["-1", "-C"]).columns);
+        assert!(!parse(&["-C", "-1", "--verbose"]).columns);
+    }
+
+    #[test]
+    fn test_parse_args_options() {
+        let args = vec![
+            "pwgen".to_string(),
+            "-A".to_string(), // no-capitalize
+            "-0".to_string(), // no-numerals
+            "-y".to_string(), // symbols
+            "-s".to_string(), // secure
+            "-B".to_string(), // ambiguous
+            "-v".to_string(), // no-vowels
+            "-1".to_string(), // no columns
+        ];
+        let config = parse_args_from_vec(args).unwrap();
+
+        assert!(config.no_capitalize);
+        assert!(config.no_numerals);
+        assert!(config.symbols);
+        assert!(config.secure);
+        assert!(config.ambiguous);
+        assert!(config.no_vowels);
+        assert!(!config.columns);
+    }
+
+    #[test]
+    fn test_parse_args_remove_chars() {
+        let args = vec![
+            "pwgen".to_string(),
+            "-r".to_string(),
+            "abc".to_string(),
+        ];
+        let config = parse_args_from_vec(args).unwrap();
+
+        assert_eq!(config.remove_chars, Some(vec!['a', 'b', 'c']));
+    }
+
+    #[test]
+    fn test_remove_chars_accumulate() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args).unwrap().remove_chars.unwrap()
+        };
+
+        assert_eq!(parse(&["-r", "0O", "-rl1"]), vec!['0', 'O', 'l', '1']);
+        assert_eq!(
+            parse(&["--remove-chars=ab", "-r", "bc"]),
+            vec!['a', 'b', 'c']
+        );
+        assert_eq!(
+            parse(&["-rxy", "--remove-chars=z", "-r", "x0"]),
+            vec!['x', 'y', 'z', '0']
+        );
+    }
+
+    #[test]
+    fn test_remove_chars_multibyte() -> Result<(), RunError> {
+        let remove = |value: &str| {
+            let args = vec!["pwgen".to_string(), format!("-r{}", value)];
+            parse_args_from_vec(args).unwrap().remove_chars.unwrap()
+        };
+
+        // ASCII-символ удаляется как раньше
+        let mut config = test_config();
+        config.remove_chars = Some(remove("x"));
+        assert!(!build_charset(&config).contains(&'x'));
+
+        // "é" - один символ; ASCII-набор он не задевает и не портит
+        assert_eq!(remove("é"), vec!['é']);
+        config.remove_chars = Some(remove("é"));
+        assert_eq!(build_charset(&config), build_charset(&test_config()));
+
+        // Смешанный ввод: удаляются только ASCII-символы из него
+        assert_eq!(remove("aé1ж"), vec!['a', 'é', '1', 'ж']);
+        config.remove_chars = Some(remove("aé1ж"));
+        config.secure = true;
+        let charset = build_charset(&config);
+        assert_eq!(charset.len(), build_charset(&test_config()).len() - 2);
+        let password = generate_secure_password(32, &config, &mut seeded_rng(1))?;
+        assert!(password.is_ascii());
+        assert!(!password.contains('a') && !password.contains('1'));
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_chars_ranges() {
+        let remove = |value: &str| {
+            let args = vec!["pwgen".to_string(), "-r".to_string(), value.to_string()];
+            parse_args_from_vec(args).map(|config| config.remove_chars.unwrap())
+        };
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+
+        assert_eq!(remove("a-f0-3"), Ok(chars("abcdef0123")));
+        assert_eq!(remove("xa-cy"), Ok(chars("xabcy")));
+        assert_eq!(remove("A-Z").unwrap().len(), 26);
+        // Повторы из диапазонов не дублируются
+        assert_eq!(remove("a-cb-d"), Ok(chars("abcd")));
+
+        // Дефис как символ: в начале, после диапазона в конце, через \-
+        assert_eq!(remove("-ab"), Ok(chars("-ab")));
+        assert_eq!(remove("a-c-"), Ok(chars("abc-")));
+        assert_eq!(remove("a\\-c"), Ok(chars("a-c")));
+        assert_eq!(remove("-"), Ok(chars("-")));
+        assert_eq!(remove("\\\\"), Ok(chars("\\")));
+
+        for (value, message) in [
+            ("z-a", "invalid range z-a"),
+            ("09-0", "invalid range 9-0"),
+            ("a-", "dangling range a-"),
+            ("ab-", "dangling range b-"),
+            (" -\u{10ffff}", "is too large"),
+        ] {
+            let err = remove(value).unwrap_err();
+            assert!(matches!(err, CliError::InvalidValue { option: "--remove-chars", .. }));
+            assert!(err.to_string().contains(message), "{}: {}", value, err);
+        }
+    }
+
+    #[test]
+    fn test_build_charset_sees_expanded_ranges() {
+        let args = vec!["pwgen".to_string(), "-s".to_string(), "-ra-z0-9".to_string()];
+        let config = parse_args_from_vec(args).unwrap();
+        let charset = build_charset(&config);
+        assert!(charset.into_iter().eq(class_chars(UPPERCASE)));
+    }
+
+    #[test]
+    fn test_remove_chars_rejects_control_characters() {
+        let args = vec!["pwgen".to_string(), "-r".to_string(), "ab\u{7}".to_string()];
+        let err = parse_args_from_vec(args).unwrap_err();
+        assert!(matches!(err, CliError::InvalidValue { option: "--remove-chars", .. }));
+        assert!(err.to_string().contains("control character"));
+    }
+
+    #[test]
+    fn test_parse_args_random_source() {
+        let args = vec![
+            "pwgen".to_string(),
+            "--random-source=/dev/hwrng".to_string(),
+        ];
+        let config = parse_args_from_vec(args).unwrap();
+        assert_eq!(config.rng, RngChoice::File(PathBuf::from("/dev/hwrng")));
+
+        let args = vec![
+            "pwgen".to_string(),
+            "--random-source".to_string(),
+            "/tmp/entropy.bin".to_string(),
+        ];
+        let config = parse_args_from_vec(args).unwrap();
+        assert_eq!(config.rng, RngChoice::File(PathBuf::from("/tmp/entropy.bin")));
+    }
+
+    #[test]
+    fn test_one_random_source() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        for other in [&["--rng", "urandom"][..], &["--random-source", "/dev/hwrng"], &["-H", "/etc/hosts"]] {
+            for args in [[&["--seed", "1"][..], other].concat(), [other, &["--seed", "1"][..]].concat()] {
+                match parse(&args) {
+                    Err(CliError::ConflictingFlags(message)) => assert!(message.contains("--seed"), "{}", message),
+                    other => panic!("{:?}: {:?}", args, other),
+                }
+            }
+        }
+
+        // --rng и --random-source тоже не складываются, как и с -H
+        for args in [
+            &["--rng", "urandom", "--random-source", "/dev/hwrng"][..],
+            &["--random-source", "/dev/hwrng", "--rng", "getrandom"],
+            &["-H", "/etc/hosts", "--rng", "urandom"],
+            &["--random-source", "/dev/hwrng", "-H", "/etc/hosts"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+
+        // Источник из командной строки заменяет источник из PWGEN_OPTS
+        let args = vec!["pwgen".to_string(), "--seed=3".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("-H /etc/hosts")).unwrap();
+        assert_eq!((config.seed, &config.rng, &config.sha1), (Some(3), &RngChoice::Auto, &None));
+        let args = vec!["pwgen".to_string(), "--rng=urandom".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("--seed 3")).unwrap();
+        assert_eq!((config.seed, config.rng), (None, RngChoice::Urandom));
+        let args = vec!["pwgen".to_string(), "--random-source=/dev/hwrng".to_string()];
+        let config = parse_args_with_env(Config::default(), args, Some("--rng urandom")).unwrap();
+        assert_eq!(config.rng, RngChoice::File(PathBuf::from("/dev/hwrng")));
+    }
+
+    #[test]
+    fn test_random_source_reproducible() -> Result<(), RunError> {
+        let contents: Vec<u8> = (0..4096).map(|i| (i * 7 + 3) as u8).collect();
+        let path = temp_file("reproducible", &contents);
+
+        let mut config = test_config();
+        config.num_pw = 10;
+        config.rng = RngChoice::File(path.clone());
+
+        // Одинаковый файл - одинаковые пароли
+        let first = generate_passwords(&config)?;
+        let second = generate_passwords(&config)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_source_too_short() {
+        let path = temp_file("short", &[1, 2, 3]);
+
+        let mut config = test_config();
+        config.num_pw = 10;
+        config.rng = RngChoice::File(path.clone());
+
+        let err = generate_passwords(&config).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(&err, RunError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof));
+        assert!(err.to_string().contains(&path.display().to_string()));
+    }
+
+    #[test]
+    fn test_random_source_missing_file() {
+        let mut config = test_config();
+        config.rng = RngChoice::File(PathBuf::from("/nonexistent/pwgen-rs-entropy"));
+
+        let err = generate_passwords(&config).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/pwgen-rs-entropy"));
+    }
+
+    #[test]
+    fn test_parse_args_sha1() {
+        let args = vec!["pwgen".to_string(), "--sha1=/etc/hosts#abc".to_string()];
+        let config = parse_args_from_vec(args).unwrap();
+        assert_eq!(
+            config.sha1,
+            Some(Sha1Spec {
+                path: PathBuf::from("/etc/hosts"),
+                seed: "abc".to_string(),
+            })
+        );
+
+        // Без seed используется значение по умолчанию, как в pwgen
+        let args = vec!["pwgen".to_string(), "-H".to_string(), "/etc/hosts".to_string()];
+        let config = parse_args_from_vec(args).unwrap();
+        assert_eq!(
+            config.sha1,
+            Some(Sha1Spec {
+                path: PathBuf::from("/etc/hosts"),
+                seed: sha1::DEFAULT_SEED.to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_sha1_same_seed_same_passwords() -> Result<(), RunError> {
+        let path = temp_file("sha1-same", b"the quick brown fox");
+        let spec = format!("{}#seed", path.display());
+
+        let mut config = test_config();
+        config.num_pw = 20;
+        config.sha1 = Some(Sha1Spec::parse(&spec));
+
+        let first = generate_passwords(&config)?;
+        let second = generate_passwords(&config)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha1_different_seeds_diverge() -> Result<(), RunError> {
+        let path = temp_file("sha1-diverge", b"the quick brown fox");
+
+        let mut config = test_config();
+        config.num_pw = 20;
+        config.sha1 = Some(Sha1Spec::parse(&format!("{}#one", path.display())));
+        let first = generate_passwords(&config)?;
+
+        config.sha1 = Some(Sha1Spec::parse(&format!("{}#two", path.display())));
+        let second = generate_passwords(&config)?;
+        std::fs::remove_file(&path)?;
+
+        assert_ne!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_args_rng() {
+        let args = vec!["pwgen".to_string(), "--rng=getrandom".to_string()];
+        assert_eq!(parse_args_from_vec(args).unwrap().rng, RngChoice::GetRandom);
+
+        let args = vec![
+            "pwgen".to_string(),
+            "--rng".to_string(),
+            "file:/tmp/fixture.bin".to_string(),
+            "--verbose".to_string(),
+        ];
+        let config = parse_args_from_vec(args).unwrap();
+        assert_eq!(config.rng, RngChoice::File(PathBuf::from("/tmp/fixture.bin")));
+        assert!(config.verbose);
+    }
+
+    #[test]
+    fn test_pinned_file_rng_from_fixture() -> Result<(), RunError> {
+        let contents: Vec<u8> = (0..2048).map(|i| (i * 11 + 1) as u8).collect();
+        let path = temp_file("rng-pinned", &contents);
+
+        let mut config = test_config();
+        config.num_pw = 5;
+        config.rng = RngChoice::parse(&format!("file:{}", path.display())).unwrap();
+
+        let first = generate_passwords(&config)?;
+        let second = generate_passwords(&config)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_args_seed() {
+        let args = vec!["pwgen".to_string(), "--seed".to_string(), "42".to_string()];
+        assert_eq!(parse_args_from_vec(args).unwrap().seed, Some(42));
+
+        let args = vec!["pwgen".to_string(), "--seed=18446744073709551615".to_string()];
+        assert_eq!(parse_args_from_vec(args).unwrap().seed, Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_seed_golden_memorable() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.num_pw = 3;
+        config.seed = Some(42);
+
+        let passwords = generate_passwords(&config)?;
+        assert_eq!(passwords.iter().collect::<Vec<_>>(), GOLDEN_MEMORABLE);
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed_golden_secure_symbols() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.num_pw = 3;
+        config.pw_length = 12;
+        config.secure = true;
+        config.symbols = true;
+        config.seed = Some(42);
+
+        let passwords = generate_passwords(&config)?;
+        assert_eq!(passwords.iter().collect::<Vec<_>>(), GOLDEN_SECURE);
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed_overrides_other_sources() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.num_pw = 3;
+        config.seed = Some(42);
+        let plain = generate_passwords(&config)?;
+
+        // Ни --rng, ни --fast-rng не меняют вывод при заданном seed
+        config.rng = RngChoice::File(PathBuf::from("/nonexistent/pwgen-rs-entropy"));
+        config.fast_rng = true;
+        assert_eq!(generate_passwords(&config)?, plain);
+
+        config.seed = Some(7);
+        config.rng = RngChoice::Auto;
+        assert_ne!(generate_passwords(&config)?, plain);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_args_fast_rng() {
+        let args = vec!["pwgen".to_string(), "--fast-rng".to_string()];
+        let config = parse_args_from_vec(args).unwrap();
+        assert!(config.fast_rng);
+    }
+
+    #[test]
+    fn test_fast_rng_same_shape_as_kernel_path() -> Result<(), RunError> {
+        let contents: Vec<u8> = (0..8192).map(|i| (i * 13 + 5) as u8).collect();
+        let path = temp_file("fast-rng", &contents);
+
+        let mut config = test_config();
+        config.secure = true;
+        config.symbols = true;
+        config.num_pw = 50;
+        config.pw_length = 16;
+        config.rng = RngChoice::File(path.clone());
+        let direct = generate_passwords(&config)?;
+
+        config.fast_rng = true;
+        let expanded = generate_passwords(&config)?;
+        std::fs::remove_file(&path)?;
+
+        // Длина и набор символов не зависят от источника случайности
+        let charset = build_charset(&config);
+        for password in direct.iter().chain(expanded.iter()) {
+            assert_eq!(password.len(), 16);
+            assert!(password.chars().all(|c| charset.contains(&c)));
+        }
+        assert_ne!(direct, expanded);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_args_lock_memory() {
+        assert!(!parse_args_from_vec(vec!["pwgen".to_string()]).unwrap().lock_memory);
+        let args = vec!["pwgen".to_string(), "--lock-memory".to_string()];
+        assert!(parse_args_from_vec(args).unwrap().lock_memory);
+    }
+
+    #[test]
+    fn test_lock_memory_same_passwords() -> Result<(), RunError> {
+        // Закрепление памяти не влияет на результат, даже если mlock недоступен
+        let mut config = test_config();
+        config.num_pw = 3;
+        config.seed = Some(42);
+        config.lock_memory = true;
+        let passwords = generate_passwords(&config)?;
+        assert_eq!(passwords.iter().collect::<Vec<_>>(), GOLDEN_MEMORABLE);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_args_output() {
+        let config = parse_args_from_vec(vec!["pwgen".to_string()]).unwrap();
+        assert_eq!(config.output, None);
+        assert!(!config.force);
+
+        for args in [
+            vec!["-o", "out.txt"],
+            vec!["-oout.txt"],
+            vec!["--output", "out.txt"],
+            vec!["--output=out.txt"],
+        ] {
+            let mut args: Vec<String> = args.into_iter().map(String::from).collect();
+            args.insert(0, "pwgen".to_string());
+            args.push("--force".to_string());
+            let config = parse_args_from_vec(args).unwrap();
+            assert_eq!(config.output, Some(PathBuf::from("out.txt")));
+            assert!(config.force);
+        }
+    }
+
+    #[test]
+    fn test_save_passwords_to_file() -> io::Result<()> {
+        let path = std::env::temp_dir().join(format!("pwgen-rs-{}-output", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let passwords = Passwords::from(vec!["abc".to_string(), "defg".to_string()]);
+
+        save_passwords(open_output(&path, false)?, &path, &passwords, Layout::columns(1))?;
+        assert_eq!(std::fs::read_to_string(&path)?, "abc\ndefg\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        }
+
+        // Без --force существующий файл не трогаем
+        let err = open_output(&path, false).unwrap_err();
+        assert!(err.to_string().contains(&path.display().to_string()));
+        assert!(err.to_string().contains("--force"));
+
+        let single = Passwords::from(vec!["xyz".to_string()]);
+        save_passwords(open_output(&path, true)?, &path, &single, Layout::columns(1))?;
+        assert_eq!(std::fs::read_to_string(&path)?, "xyz\n");
+
+        std::fs::remove_file(&path)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_force_restricts_existing_file() -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        let path = temp_file("output-force", b"old");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))?;
+
+        open_output(&path, true)?;
+        assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read(&path)?, b"");
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn test_write_passwords_columns() -> io::Result<()> {
+        let passwords = Passwords::from(
+            ["ab", "cd", "ef", "gh", "ij", "kl"].iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+        );
+        let mut out = Vec::new();
+        write_passwords(&mut out, &passwords, Layout::columns(3))?;
+        assert_eq!(out, b"ab cd ef\ngh ij kl\n");
+        Ok(())
+    }
+
+    fn write_to_vec(passwords: &[&str], columns: usize) -> io::Result<String> {
+        let passwords = Passwords::from(passwords.iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        let mut out = Vec::new();
+        write_passwords(&mut out, &passwords, Layout::columns(columns))?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_write_passwords_row_major_order() -> io::Result<()> {
+        // Количество не кратно числу столбцов: последняя строка неполная
+        let passwords = ["p1", "p2", "p3", "p4", "p5", "p6", "p7"];
+        assert_eq!(write_to_vec(&passwords, 3)?, "p1 p2 p3\np4 p5 p6\np7\n");
+        assert_eq!(write_to_vec(&passwords, 4)?, "p1 p2 p3 p4\np5 p6 p7\n");
+        assert_eq!(write_to_vec(&passwords, 7)?, "p1 p2 p3 p4 p5 p6 p7\n");
+        assert_eq!(write_to_vec(&passwords, 10)?, "p1 p2 p3 p4 p5 p6 p7\n");
+        assert_eq!(write_to_vec(&passwords[..1], 3)?, "p1\n");
+        assert_eq!(write_to_vec(&[], 3)?, "");
+
+        // -1: по одному в строке
+        assert_eq!(write_to_vec(&passwords[..3], 1)?, "p1\np2\np3\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_passwords_numbered() -> io::Result<()> {
+        let passwords = Passwords::from(
+            ["a", "bcd", "ef", "ghij", "k", "lm", "n", "op", "q", "rs", "tu"]
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>(),
+        );
+        let numbered = |columns| Layout {
+            numbered: true,
+            ..Layout::columns(columns)
+        };
+
+        // Номера выровнены по правому краю, пароли - по своей ширине
+        let mut out = Vec::new();
+        write_passwords(&mut out, &passwords, numbered(4))?;
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            " 1. a     2. bcd   3. ef    4. ghij\n\
+             \x205. k     6. lm    7. n     8. op\n\
+             \x209. q    10. rs   11. tu\n"
+        );
+
+        let mut out = Vec::new();
+        write_passwords(&mut out, &passwords, numbered(1))?;
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(" 1. a\n 2. bcd\n"), "{}", out);
+        assert!(out.ends_with("10. rs\n11. tu\n"), "{}", out);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_passwords_pick() -> io::Result<()> {
+        let passwords = Passwords::from(["ab", "cd", "ef"].iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        let pick = |pick, numbered| -> io::Result<String> {
+            let mut out = Vec::new();
+            let layout = Layout {
+                pick: Some(pick),
+                numbered,
+                ..Layout::columns(1)
+            };
+            write_passwords(&mut out, &passwords, layout)?;
+            Ok(String::from_utf8(out).unwrap())
+        };
+        assert_eq!(pick(2, false)?, "cd\n");
+        assert_eq!(pick(3, false)?, "ef\n");
+        // Номер остается номером в пакете
+        assert_eq!(pick(2, true)?, "2. cd\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_error_exit_codes() {
+        assert_eq!(empty_charset_error().exit_code(), EXIT_USAGE);
+        assert_eq!(RunError::Generation("failed".to_string()).exit_code(), EXIT_GENERATION);
+        assert_eq!(RunError::from(io::Error::other("disk full")).exit_code(), EXIT_IO);
+        assert_eq!(RunError::from(io::Error::other("disk full")).to_string(), "disk full");
+    }
+
+    #[test]
+    fn test_write_passwords_porcelain() -> io::Result<()> {
+        let passwords = Passwords::from(["ab", "cdef", "g"].iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        let mut out = Vec::new();
+        // Ни столбцов, ни выравнивания, даже если они заданы в Layout
+        let layout = Layout {
+            porcelain: true,
+            ..Layout::columns(3)
+        };
+        write_passwords(&mut out, &passwords, layout)?;
+        assert_eq!(String::from_utf8(out).unwrap(), "ab\ncdef\ng\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_passwords_color() -> io::Result<()> {
+        let passwords = Passwords::from(["aB1!", "O0", "xyzXYZ12", "l"].iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        let strip = |text: &str| {
+            let mut plain = String::new();
+            let mut escape = false;
+            for c in text.chars() {
+                match c {
+                    '\x1b' => escape = true,
+                    'm' if escape => escape = false,
+                    _ if escape => {}
+                    c => plain.push(c),
+                }
+            }
+            plain
+        };
+        let write = |layout| -> io::Result<String> {
+            let mut out = Vec::new();
+            write_passwords(&mut out, &passwords, layout)?;
+            Ok(String::from_utf8(out).unwrap())
+        };
+        let color = Layout {
+            color: true,
+            ..Layout::columns(2)
+        };
+        // Без escape-последовательностей столбцы выровнены так же, как без цвета
+        let colored = write(color)?;
+        assert!(colored.contains("\x1b[34m0\x1b[0m"), "{:?}", colored);
+        assert_eq!(strip(&colored), write(Layout::columns(2))?);
+        assert_eq!(strip(&colored), "aB1!     O0\nxyzXYZ12 l\n");
+        // С --porcelain и -z цвета нет
+        for layout in [
+            Layout { porcelain: true, ..color },
+            Layout { delimiter: b'\0', ..color },
+        ] {
+            assert!(!write(layout)?.contains('\x1b'));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_passwords_null() -> io::Result<()> {
+        let passwords = Passwords::from(["ab", "cdef", "g"].iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        for porcelain in [false, true] {
+            let layout = Layout {
+                porcelain,
+                delimiter: b'\0',
+                ..Layout::columns(1)
+            };
+            let mut out = Vec::new();
+            write_passwords(&mut out, &passwords, layout)?;
+            assert_eq!(out, b"ab\0cdef\0g\0");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        // Столбцы выключаются и у терминала
+        let config = parse(&["-z", "16", "5"]).unwrap();
+        assert!(config.null);
+        assert!(!config.columns);
+        assert_eq!(Layout::new(&config, Some(80)).delimiter, b'\0');
+        assert!(parse(&["-sz", "--porcelain", "-1"]).is_ok());
+        for args in [
+            &["-z", "-C"][..],
+            &["--null", "--columns", "3"],
+            &["-z", "--format", "json"],
+            &["-z", "--mnemonic"],
+            &["-z", "-i"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_write_passwords_phonetic() -> io::Result<()> {
+        let passwords = Passwords::from(["hX3k", "a!"].iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        let layout = Layout {
+            phonetic: true,
+            ..Layout::columns(1)
+        };
+        let mut out = Vec::new();
+        write_passwords(&mut out, &passwords, layout)?;
+        assert_eq!(String::from_utf8(out).unwrap(), "hX3k\n  hotel X-RAY three kilo\na!\n  alfa exclamation\n");
+        let layout = Layout {
+            numbered: true,
+            pick: Some(2),
+            ..layout
+        };
+        let mut out = Vec::new();
+        write_passwords(&mut out, &passwords, layout)?;
+        assert_eq!(String::from_utf8(out).unwrap(), "2. a!\n     alfa exclamation\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_qr_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        // Без количества - один пароль, как с --copy
+        let config = parse(&["--qr", "16"]).unwrap();
+        assert_eq!(config.num_pw, 1);
+        let config = parse(&["--qr-only", "--qr-format", "png:code.png", "16"]).unwrap();
+        assert!(config.qr && config.qr_only);
+        assert_eq!(config.qr_format, Some(QrFormat::Png(PathBuf::from("code.png"))));
+        assert!(parse(&["--qr", "--pick", "2", "16", "5"]).is_ok());
+        assert!(parse(&["--qr", "--phonetic", "--annotate", "16", "1"]).is_ok());
+        assert!(matches!(
+            parse(&["--qr", "--qr-format", "svg"]),
+            Err(CliError::InvalidValue { option: "--qr-format", .. })
+        ));
+        for args in [
+            &["--qr", "16", "5"][..],
+            &["--qr-format", "utf8"],
+            &["--qr", "--copy"],
+            &["--qr", "-o", "out.txt"],
+            &["--qr", "--porcelain"],
+            &["--qr", "-z"],
+            &["--qr", "--mnemonic"],
+            &["--qr", "-i"],
+            &["--qr", "--format", "json"],
+            &["--qr-only", "--phonetic"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_print_qr_png() -> io::Result<()> {
+        let path = std::env::temp_dir().join(format!("pwgen-rs-{}-qr.png", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let passwords = Passwords::from(vec!["abc".to_string(), "hX3k-9dfQ".to_string(), "x".to_string()]);
+        let config = Config {
+            qr: true,
+            qr_format: Some(QrFormat::Png(path.clone())),
+            pick: Some(2),
+            ..test_config()
+        };
+        print_qr(&passwords, &config, Some((open_output(&path, false)?, &path)))?;
+        let png = std::fs::read(&path)?;
+        assert_eq!(qr::tests::decode(&qr::tests::read_png(&png)).unwrap(), b"hX3k-9dfQ");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn test_write_passwords_entropy() -> io::Result<()> {
+        let passwords = Passwords::from(["abc", "defgh", "ij"].iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        let bits = [Some(19.6), Some(32.75), Some(13.1)];
+        let write = |layout: Layout, bits: &[Option<f64>]| -> io::Result<String> {
+            let mut out = Vec::new();
+            write_passwords_with_entropy(&mut out, &passwords, layout, bits)?;
+            Ok(String::from_utf8(out).unwrap())
+        };
+
+        assert_eq!(
+            write(Layout::columns(1), &bits)?,
+            "abc (\u{2248}19.6 bits)\ndefgh (\u{2248}32.8 bits)\nij (\u{2248}13.1 bits)\n"
+        );
+        // Пометка входит в ширину столбца
+        assert_eq!(
+            write(Layout::columns(2), &bits)?,
+            "abc (\u{2248}19.6 bits)   defgh (\u{2248}32.8 bits)\nij (\u{2248}13.1 bits)\n"
+        );
+        // Без энтропии - как write_passwords
+        assert_eq!(write(Layout::columns(2), &[])?, "abc   defgh\nij\n");
+        let numbered = Layout {
+            numbered: true,
+            pick: Some(2),
+            ..Layout::columns(1)
+        };
+        assert_eq!(write(numbered, &bits)?, "2. defgh (\u{2248}32.8 bits)\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_entropy_summary() -> io::Result<()> {
+        let summary = |bits: &[Option<f64>], each: bool| -> io::Result<String> {
+            let mut out = Vec::new();
+            write_entropy_summary(&mut out, bits, each)?;
+            Ok(String::from_utf8(out).unwrap())
+        };
+        // Одинаковые настройки: энтропия пароля и сумма по пакету
+        let same = [Some(95.27), Some(95.27), Some(95.27)];
+        assert_eq!(
+            summary(&same, false)?,
+            "Entropy: \u{2248}95.3 bits per password, \u{2248}285.8 bits for all 3\n"
+        );
+        assert_eq!(summary(&same[..1], false)?, "Entropy: \u{2248}95.3 bits\n");
+        // Разные длины: у каждого пароля своя пометка, под ними только сумма
+        assert_eq!(summary(&[Some(47.6), Some(59.5)], true)?, "Entropy: \u{2248}107.1 bits for all 2\n");
+        assert_eq!(summary(&[Some(47.6)], true)?, "");
+        assert_eq!(summary(&[Some(47.6), None], false)?, "Entropy: unknown\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_entropy_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        for (flags, flag) in [
+            (&["--copy"][..], "--copy"),
+            (&["-o", "passwords.txt"], "--output"),
+            (&["--porcelain"], "--porcelain"),
+            (&["-z"], "-z/--null"),
+            (&["--mnemonic"], "--mnemonic"),
+            (&["-i"], "--interactive"),
+            (&["--qr-only"], "--qr-only"),
+        ] {
+            let mut list = vec!["--show-entropy"];
+            list.extend_from_slice(flags);
+            assert_eq!(
+                parse(&list).map(|_| ()),
+                Err(CliError::ConflictingFlags(format!(
+                    "--show-entropy prints the entropy next to the passwords and cannot be used with {}",
+                    flag
+                )))
+            );
+        }
+        // В JSON и CSV энтропия есть и так
+        for format in ["json", "jsonl", "csv"] {
+            assert!(parse(&["--show-entropy", "--format", format]).unwrap().show_entropy);
+        }
+        assert!(parse(&["--show-entropy", "--qr"]).is_ok());
+    }
+
+    #[test]
+    fn test_write_passwords_annotate() -> io::Result<()> {
+        let passwords = Passwords::from(["abO1", "hxkw", "l"].iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        let layout = Layout {
+            annotate: true,
+            numbered: true,
+            ..Layout::columns(1)
+        };
+        let mut out = Vec::new();
+        write_passwords(&mut out, &passwords, layout)?;
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1. abO1\n     note: position 3 is capital O (not zero), position 4 is one (not lowercase l)\n\
+             2. hxkw\n\
+             3. l\n     note: position 1 is lowercase l (not one)\n"
+        );
+        // В --porcelain и с -z пометок нет
+        for layout in [
+            Layout {
+                porcelain: true,
+                ..layout
+            },
+            Layout {
+                delimiter: b'\0',
+                numbered: false,
+                ..layout
+            },
+        ] {
+            let mut out = Vec::new();
+            write_passwords(&mut out, &passwords, layout)?;
+            assert!(!String::from_utf8(out).unwrap().contains("note"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--annotate", "10", "3"]).unwrap();
+        assert!(config.annotate);
+        assert_eq!(Layout::new(&config, Some(80)).columns, 1);
+        for args in [
+            &["--annotate", "--porcelain"][..],
+            &["--annotate", "-z"],
+            &["--annotate", "--format", "csv"],
+            &["--annotate", "--format", "json"],
+            &["--annotate", "--phonetic", "-n", "-o", "out.txt"],
+        ] {
+            assert!(parse(args).is_ok(), "{:?}", args);
+        }
+        for args in [
+            &["--annotate", "--copy"][..],
+            &["--annotate", "--mnemonic"],
+            &["--annotate", "-i"],
+            &["--annotate", "-C"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
         }
     }
 
     #[test]
-    fn test_build_charset_default() {
-        let config = Config::default();
-        let charset = build_charset(&config);
-
-        // Должен содержать строчные, заглавные и цифры по умолчанию
-        assert!(charset.contains(&b'a'));
-        assert!(charset.contains(&b'A'));
-        assert!(charset.contains(&b'1'));
-        assert!(!charset.contains(&b'!')); // Символы по умолчанию отключены
+    fn test_phonetic_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--phonetic", "10", "3"]).unwrap();
+        assert!(config.phonetic);
+        assert_eq!(Layout::new(&config, Some(80)).columns, 1);
+        assert!(parse(&["--phonetic", "--numbered", "-o", "out.txt", "10", "3"]).is_ok());
+        assert!(parse(&["--phonetic", "--format", "json"]).is_ok());
+        for args in [
+            &["--phonetic", "--copy"][..],
+            &["--phonetic", "--porcelain"],
+            &["--phonetic", "-z"],
+            &["--phonetic", "--mnemonic"],
+            &["--phonetic", "-i"],
+            &["--phonetic", "--format", "csv"],
+            &["--phonetic", "-C"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
     }
 
     #[test]
-    fn test_build_charset_no_capitalize() {
-        let mut config = test_config();
-        config.no_capitalize = true;
-        let charset = build_charset(&config);
+    fn test_porcelain_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["--porcelain", "16", "5"]).unwrap();
+        assert!(config.porcelain);
+        assert!(Layout::new(&config, Some(80)).porcelain);
+        for args in [
+            &["--porcelain", "--numbered"][..],
+            &["--porcelain", "--pick", "1"],
+            &["--porcelain", "--copy"],
+            &["--porcelain", "-i"],
+            &["--porcelain", "--self-test"],
+            &["--porcelain", "-C"],
+            &["--porcelain", "--columns", "3"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+        // -1 только подтверждает формат
+        assert!(parse(&["--porcelain", "-1"]).is_ok());
+    }
 
-        // Не должен содержать заглавные буквы
-        assert!(charset.contains(&b'a'));
-        assert!(!charset.contains(&b'A'));
+    #[test]
+    fn test_format_json_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert_eq!(parse(&[]).unwrap().format, Format::Text);
+        // Столбцы JSON не мешают: они просто не используются
+        let config = parse(&["--format", "json", "-C", "16", "5"]).unwrap();
+        assert_eq!(config.format, Format::Json);
+        assert!(matches!(parse(&["--format", "xml"]), Err(CliError::InvalidValue { .. })));
+        assert_eq!(parse(&["--format", "jsonl"]).unwrap().format, Format::Jsonl);
+        assert!(parse(&["--format", "csv", "--csv-safe"]).unwrap().csv_safe);
+        // Фраза --mnemonic в JSON становится полем пароля
+        assert!(parse(&["--format", "json", "--mnemonic"]).unwrap().mnemonic);
+        assert!(parse(&["--format", "jsonl", "--mnemonic"]).unwrap().mnemonic);
+        for args in [
+            &["--format", "json", "--porcelain"][..],
+            &["--format", "json", "--numbered"],
+            &["--format", "json", "--pick", "1"],
+            &["--format", "csv", "--mnemonic"],
+            &["--format", "json", "--copy"],
+            &["--format", "json", "-o", "out.json"],
+            &["--format", "json", "-i"],
+            &["--format", "jsonl", "--porcelain"],
+            &["--format", "jsonl", "-o", "out.json"],
+            &["--format", "csv", "--numbered"],
+            &["--csv-safe"],
+            &["--format", "json", "--csv-safe"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
     }
 
     #[test]
-    fn test_build_charset_no_numerals() {
-        let mut config = test_config();
-        config.no_numerals = true;
-        let charset = build_charset(&config);
+    fn test_mnemonic_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let config = parse(&["-s", "--mnemonic", "10", "3"]).unwrap();
+        assert!(config.mnemonic);
+        assert!(!config.columns);
+        assert!(parse(&["--mnemonic", "--numbered", "--pick", "2", "10", "3"]).is_ok());
+        for args in [
+            &["--mnemonic", "--copy"][..],
+            &["--mnemonic", "-o", "out.txt"],
+            &["--mnemonic", "--porcelain"],
+            &["--mnemonic", "-i"],
+            &["--mnemonic", "--words", "4"],
+            &["--mnemonic", "-C"],
+        ] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+    }
 
-        // Не должен содержать цифры
-        assert!(!charset.iter().any(|&c| c.is_ascii_digit()));
+    #[test]
+    fn test_write_mnemonics() -> io::Result<()> {
+        let passwords = Passwords::from(["a1", "B#c"].iter().map(|p| p.to_string()).collect::<Vec<_>>());
+        let mnemonics = ["apple one", "Bravo hash cactus"].iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let mnemonics = Passwords::from(mnemonics);
+        let write = |layout| -> io::Result<String> {
+            let mut out = Vec::new();
+            write_mnemonics(&mut out, &passwords, &mnemonics, layout)?;
+            Ok(String::from_utf8(out).unwrap())
+        };
+        // Фразы начинаются в одном столбце
+        assert_eq!(write(Layout::columns(4))?, "a1   apple one\nB#c  Bravo hash cactus\n");
+        let layout = Layout {
+            numbered: true,
+            pick: Some(2),
+            ..Layout::columns(1)
+        };
+        assert_eq!(write(layout)?, "2. B#c  Bravo hash cactus\n");
+        Ok(())
     }
 
     #[test]
-    fn test_build_charset_symbols() {
+    fn test_mnemonic_sentences() -> Result<(), RunError> {
+        // По слову на каждый символ пароля
         let mut config = test_config();
+        config.secure = true;
         config.symbols = true;
-        let charset = build_charset(&config);
+        config.num_pw = 5;
+        let passwords = generate_passwords(&config)?;
+        let mnemonics = mnemonic::sentences(&passwords, &mut ChaCha20Rng::seeded(577))?;
+        for (password, sentence) in passwords.iter().zip(mnemonics.iter()) {
+            assert_eq!(sentence.split(' ').count(), password.chars().count(), "{} {}", password, sentence);
+        }
 
-        // Должен содержать символы
-        assert!(charset.contains(&b'!'));
-        assert!(charset.contains(&b'@'));
+        // С --seed слова берутся из своего потока, а не из потока паролей
+        config.seed = Some(577);
+        let mut words = [0u8; 64];
+        mnemonic_rng(&config)?.read_exact(&mut words)?;
+        let mut passwords = [0u8; 64];
+        ChaCha20Rng::seeded(577).read_exact(&mut passwords)?;
+        assert_ne!(words, passwords);
+        let mut again = [0u8; 64];
+        mnemonic_rng(&config)?.read_exact(&mut again)?;
+        assert_eq!(words, again);
+        Ok(())
     }
 
     #[test]
-    fn test_build_charset_ambiguous() {
-        let mut config = test_config();
-        config.ambiguous = true;
-        let charset = build_charset(&config);
+    fn test_interactive_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        assert!(parse(&["-i", "16"]).unwrap().interactive);
+        assert!(parse(&["-si", "16"]).unwrap().interactive);
+        for args in [&["-i", "16", "5"][..], &["-i", "--copy"], &["-i", "-o", "out.txt"]] {
+            assert!(matches!(parse(args), Err(CliError::ConflictingFlags(_))), "{:?}", args);
+        }
+    }
 
-        // Не должен содержать неоднозначные символы
-        assert!(!charset.contains(&b'0'));
-        assert!(!charset.contains(&b'O'));
-        assert!(!charset.contains(&b'1'));
-        assert!(!charset.contains(&b'l'));
+    #[test]
+    fn test_pick_without_copy() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+
+        let mut config = parse(&["--pick", "3", "-1"]).unwrap();
+        apply_terminal_defaults(&mut config, &FakeTerminal::PIPE);
+        // Пакет не меньше номера, который из него берется
+        assert_eq!(config.num_pw, 3);
+        assert_eq!(Layout::new(&config, None).pick, Some(3));
+        assert_eq!(Layout::new(&config, None).columns, 1);
+
+        // Номер вне пакета - ошибка
+        assert!(matches!(
+            parse(&["--pick=4", "8", "3"]),
+            Err(CliError::InvalidNumber { what: "--pick value", max: Some(3), .. })
+        ));
+        assert!(parse(&["--pick=3", "8", "3"]).is_ok());
     }
 
     #[test]
-    fn test_build_charset_no_vowels() {
+    fn test_column_count_numbered() {
         let mut config = test_config();
-        config.no_vowels = true;
-        let charset = build_charset(&config);
-
-        // Не должен содержать гласные
-        assert!(!charset.contains(&b'a'));
-        assert!(!charset.contains(&b'e'));
-        assert!(!charset.contains(&b'i'));
-        assert!(!charset.contains(&b'o'));
-        assert!(!charset.contains(&b'u'));
-        assert!(!charset.contains(&b'A'));
-        assert!(!charset.contains(&b'E'));
-        assert!(!charset.contains(&b'I'));
-        assert!(!charset.contains(&b'O'));
-        assert!(!charset.contains(&b'U'));
+        config.columns = true;
+        config.pw_length = 8;
+        config.num_pw = 160;
+        config.numbered = true;
+        // "160. " + пароль + пробел = 14
+        assert_eq!(column_count(&config, Some(80)), 5);
     }
 
     #[test]
-    fn test_build_charset_remove_chars() {
+    fn test_column_count_syllables() -> io::Result<()> {
         let mut config = test_config();
-        config.remove_chars = Some(b"aeiouAEIOU".to_vec());
-        let charset = build_charset(&config);
+        config.columns = true;
+        // Четыре слога - до 16 символов: по 4 пароля в строке шириной 80
+        config.syllable_count = Some(4);
+        assert_eq!(password_width(&config), 16);
+        assert_eq!(column_count(&config, Some(80)), 4);
+        // Из слогов файла - по самому длинному
+        config.syllables = Some(vec![vec!['k', 'a'], vec!['s', 't', 'o']]);
+        assert_eq!(column_count(&config, Some(80)), 6);
 
-        // Не должен содержать удаленные символы
-        assert!(!charset.contains(&b'a'));
-        assert!(!charset.contains(&b'A'));
+        // По 12 символов и пробел: в 26 колонок - два пароля, а
+        // выравниваются они по самому длинному из напечатанных
+        let passwords: Vec<&str> = vec!["kasto", "stosto", "kaka", "stoka"];
+        let out = write_to_vec(&passwords, column_count(&config, Some(26)))?;
+        assert_eq!(out, "kasto  stosto\nkaka   stoka\n");
+        Ok(())
     }
 
     #[test]
-    fn test_generate_secure_password() -> io::Result<()> {
-        let config = test_config();
-        // Mock RNG, который возвращает предсказуемую последовательность
-        let mut mock_rng = Cursor::new(vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    fn test_column_count_affixes() {
+        let mut config = test_config();
+        config.columns = true;
+        config.pw_length = 8;
+        // "svc-" + пароль + ".x" + пробел = 15
+        config.prefix = Some("svc-".to_string());
+        config.suffix = Some(".x".to_string());
+        assert_eq!(column_count(&config, Some(80)), 5);
+        // Ширина считается в символах, а не в байтах
+        config.prefix = Some("ключ-".to_string());
+        config.suffix = None;
+        assert_eq!(column_count(&config, Some(80)), 5);
+    }
 
-        let password = generate_secure_password(8, &config, &mut mock_rng)?;
+    #[test]
+    fn test_column_count_group() {
+        let mut config = test_config();
+        config.columns = true;
+        config.pw_length = 16;
+        // 16 символов и 3 разделителя + пробел = 20
+        config.group = group::Grouping::parse("4").ok();
+        assert_eq!(column_count(&config, Some(80)), 4);
+        config.group = group::Grouping::parse("4,::").ok();
+        assert_eq!(column_count(&config, Some(80)), 3);
+    }
 
-        assert_eq!(password.len(), 8);
+    #[test]
+    fn test_write_passwords_pads_to_width() -> io::Result<()> {
+        // Короткие пароли дополняются до самого длинного, в конце строки
+        // пробелов нет
+        assert_eq!(
+            write_to_vec(&["abc", "d", "efgh", "ij", "k"], 2)?,
+            "abc  d\nefgh ij\nk\n"
+        );
+        assert_eq!(
+            write_to_vec(&["a", "bb", "c", "dd"], 2)?,
+            "a  bb\nc  dd\n"
+        );
         Ok(())
     }
 
     #[test]
-    fn test_generate_memorable_password_pattern() -> io::Result<()> {
-        let config = test_config();
-        // Mock RNG, который возвращает индексы для согласных и гласных
-        // Увеличиваем количество данных, чтобы хватило на все чтения
-        let mut mock_rng = Cursor::new(vec![
-            0, 0, 0, 0, 0, 0, 0, 0, // 8 байт для базовой генерации
-            0, 0, 0, 0, // дополнительные байты для apply_requirements
-        ]);
+    fn test_parse_num_passwords() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
 
-        let password = generate_memorable_password(8, &config, &mut mock_rng)?;
+        for list in [
+            &["-N20"][..],
+            &["-N", "20"],
+            &["--num-passwords=20"],
+            &["--num-passwords", "20"],
+            &["-sN20"],
+            &["16", "-N", "20"],
+        ] {
+            let config = parse(list).unwrap();
+            assert_eq!(config.num_pw, 20, "{:?}", list);
+            assert!(config.num_pw_given);
+        }
+        assert_eq!(parse(&["-N", "20", "-N3"]).unwrap().num_pw, 3);
 
-        assert_eq!(password.len(), 8);
-        Ok(())
+        // Вместе с позиционным аргументом - только то же значение
+        assert_eq!(parse(&["-N", "5", "16", "5"]).unwrap().num_pw, 5);
+        assert_eq!(
+            parse(&["-N", "4", "16", "5"]).unwrap_err(),
+            CliError::ConflictingFlags("-N/--num-passwords 4 conflicts with the num_pw argument 5".to_string())
+        );
+        assert!(matches!(
+            parse(&["-N0"]),
+            Err(CliError::InvalidNumber { what: "number of passwords", .. })
+        ));
+        assert_eq!(parse(&["-N"]).unwrap_err(), CliError::MissingValue("--num-passwords"));
+
+        // Явное количество через -N для --copy то же, что позиционное
+        assert!(parse(&["--copy", "-N", "2"]).is_err());
+        assert_eq!(parse(&["--copy", "-N1"]).unwrap().num_pw, 1);
     }
 
     #[test]
-    fn test_generate_memorable_password_no_capitalize() -> io::Result<()> {
-        let mut config = test_config();
-        config.no_capitalize = true;
-        // Mock RNG, который возвращает индексы
-        let mut mock_rng = Cursor::new(vec![0, 0, 1, 1, 2, 2, 3, 3, 0, 0]);
+    fn test_parse_length_and_count() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
 
-        let password = generate_memorable_password(8, &config, &mut mock_rng)?;
+        for list in [
+            &["--length=16", "--count=3"][..],
+            &["--length", "16", "--count", "3"],
+            &["-L", "16", "-N3"],
+            &["-sL16", "--count", "3"],
+            &["16", "3", "--length=16", "--count=3"],
+        ] {
+            let config = parse(list).unwrap();
+            assert_eq!((config.pw_length, config.num_pw), (16, 3), "{:?}", list);
+        }
 
-        // Не должно быть заглавных букв
-        assert!(!password.chars().any(|c| c.is_uppercase()));
-        Ok(())
+        // Именованная опция заменяет только свой аргумент
+        let config = parse(&["--count=3"]).unwrap();
+        assert_eq!((config.pw_length, config.num_pw), (DEFAULT_LENGTH, 3));
+
+        assert_eq!(
+            parse(&["--length=16", "20"]).unwrap_err(),
+            CliError::ConflictingFlags("-L/--length 16 conflicts with the pw_length argument 20".to_string())
+        );
+        assert_eq!(
+            parse(&["--count=3", "16", "4"]).unwrap_err(),
+            CliError::ConflictingFlags("--count 3 conflicts with the num_pw argument 4".to_string())
+        );
+
+        // Та же проверка, что и для позиционных аргументов
+        assert!(matches!(
+            parse(&["--length=0"]),
+            Err(CliError::InvalidNumber { what: "password length", .. })
+        ));
+        assert!(matches!(
+            parse(&["--length=2000"]),
+            Err(CliError::InvalidNumber { max: Some(MAX_LENGTH), .. })
+        ));
+        assert!(matches!(
+            parse(&["--count=many"]),
+            Err(CliError::InvalidNumber { what: "number of passwords", .. })
+        ));
+        assert_eq!(parse(&["--count"]).unwrap_err(), CliError::MissingValue("--count"));
     }
 
     #[test]
-    fn test_generate_password_no_vowels() -> io::Result<()> {
-        let mut config = test_config();
-        config.no_vowels = true;
-        let mut mock_rng = Cursor::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    fn test_flags_override_config_file() {
+        let base = Config {
+            pw_length: 20,
+            no_capitalize: true,
+            capitalize: false,
+            no_numerals: true,
+            numerals: false,
+            symbols: true,
+            remove_chars: Some(vec!['x']),
+            ..Config::default()
+        };
+        let args = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            args
+        };
 
-        let password = generate_memorable_password(10, &config, &mut mock_rng)?;
+        let config = parse_args_with_env(base.clone(), args(&[]), None).unwrap();
+        assert_eq!(config.pw_length, 20);
+        assert!(config.symbols && config.no_capitalize);
 
-        // Пароль должен быть сгенерирован
-        assert_eq!(password.len(), 10);
-        // Не должен содержать гласные
-        let vowels = "aeiouyAEIOUY";
-        assert!(!password.chars().any(|c| vowels.contains(c)));
-        Ok(())
+        let config = parse_args_with_env(base.clone(), args(&["-c", "-n", "-r", "y", "12"]), None).unwrap();
+        assert_eq!(config.pw_length, 12);
+        assert!(config.capitalize && !config.no_capitalize);
+        assert!(config.numerals && !config.no_numerals);
+        assert_eq!(config.remove_chars, Some(vec!['x', 'y']));
+
+        assert!(parse_args_with_env(base, args(&["--no-config"]), None).is_ok());
     }
 
     #[test]
-    fn test_apply_requirements_adds_capital() -> io::Result<()> {
-        let mut config = test_config();
-        config.no_numerals = true; // Отключаем цифры, чтобы они не мешали тесту
-        let mut mock_rng = Cursor::new(vec![0, 0]); // Только 2 байта нужно для заглавной буквы
+    fn test_split_words() {
+        assert_eq!(split_words("  -s -B\t24 ").unwrap(), ["-s", "-B", "24"]);
+        assert_eq!(split_words("--remove-chars=\"a b\" -y").unwrap(), ["--remove-chars=a b", "-y"]);
+        assert_eq!(split_words("-r 'x\"y' -r \"\\\"\\\\\"").unwrap(), ["-r", "x\"y", "-r", "\"\\"]);
+        assert_eq!(split_words("a\\ b ''").unwrap(), ["a b", ""]);
+        assert!(split_words("").unwrap().is_empty());
 
-        // Пароль без заглавных букв
-        let password = b"abcdefgh".to_vec();
-        let result = apply_requirements(password, &config, &mut mock_rng)?;
+        assert_eq!(split_words("-r \"ab").unwrap_err(), "unbalanced \" quote");
+        assert_eq!(split_words("-r 'ab").unwrap_err(), "unbalanced ' quote");
+        assert_eq!(split_words("-s \\").unwrap_err(), "trailing backslash");
+    }
 
-        // Должна быть хотя бы одна заглавная буква
-        assert!(result.chars().any(|c| c.is_uppercase()));
-        Ok(())
+    #[test]
+    fn test_parse_args_with_env() {
+        let parse = |list: &[&str], env: &str| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_with_env(Config::default(), args, Some(env))
+        };
+
+        let config = parse(&[], "-s -B 24").unwrap();
+        assert!(config.secure && config.ambiguous);
+        assert_eq!(config.pw_length, 24);
+
+        let config = parse(&["-y"], "--remove-chars=\"a b\"").unwrap();
+        assert!(config.symbols);
+        assert_eq!(config.remove_chars, Some(vec!['a', ' ', 'b']));
+
+        // Командная строка важнее: позиционные аргументы не сдвигаются,
+        // а противоположные флаги не конфликтуют
+        let config = parse(&["16"], "-s 24 3").unwrap();
+        assert_eq!((config.pw_length, config.num_pw), (16, 3));
+        let config = parse(&["-c"], "-A").unwrap();
+        assert!(config.capitalize && !config.no_capitalize);
+        let config = parse(&["-1"], "-C").unwrap();
+        assert!(!config.columns);
+
+        // Сочетания проверяются по итоговым настройкам
+        assert!(parse(&["-o", "out.gpg"], "--encrypt-to alice@example.com").is_ok());
+
+        assert_eq!(
+            parse(&[], "-r \"ab").unwrap_err().to_string(),
+            "invalid PWGEN_OPTS value '-r \"ab' (unbalanced \" quote)"
+        );
+        assert_eq!(
+            parse(&[], "--bogus").unwrap_err().to_string(),
+            "in PWGEN_OPTS: unknown option --bogus"
+        );
     }
 
     #[test]
-    fn test_apply_requirements_adds_numeral() -> io::Result<()> {
-        let config = test_config();
-        // Увеличиваем количество данных
-        let mut mock_rng = Cursor::new(vec![0, 0, 0, 0, 0, 0]);
+    fn test_value_forms_are_uniform() {
+        // Допустимое значение для каждой опции со значением
+        let valid_value = |opt: Opt| match opt {
+            Opt::EncryptTo => "alice@example.com",
+            Opt::Rng => "urandom",
+            Opt::LengthRange => "3-5",
+            Opt::Weights => "8:2:3:1",
+            Opt::Classes => "l4d2",
+            Opt::Preset => "wpa",
+            Opt::Case => "title",
+            Opt::MemorableModel => "markov",
+            Opt::Format => "json",
+            Opt::Color => "always",
+            Opt::QrFormat => "utf8",
+            Opt::Separator => "_",
+            Opt::WordsAcrostic => "banana",
+            Opt::Lang => "de",
+            Opt::Blocks => "w3-d2",
+            Opt::Charset => "hex",
+            Opt::AmbiguousChars | Opt::RemoveChars => "xyz",
+            Opt::Sha1
+            | Opt::Output
+            | Opt::RandomSource
+            | Opt::CharsetFile
+            | Opt::HomoglyphFile
+            | Opt::SyllableFile
+            | Opt::Wordlist => "some/file",
+            _ => "3",
+        };
+        let parse = |args: &[&str]| -> Result<String, CliError> {
+            let mut config = Config::default();
+            let mut list = vec!["pwgen".to_string()];
+            // --clear-after без --copy - конфликт
+            if args.iter().any(|arg| arg.starts_with("--clear-after")) {
+                list.push("--copy".to_string());
+            }
+            list.extend(args.iter().map(|s| s.to_string()));
+            parse_layer(&mut config, list)?;
+            Ok(format!("{:?}", config))
+        };
 
-        // Пароль без цифр
-        let password = b"abcdefgh".to_vec();
-        let result = apply_requirements(password, &config, &mut mock_rng)?;
+        for option in options::OPTIONS.iter().filter(|option| option.value.is_some() && !option.optional_value()) {
+            let value = valid_value(option.opt);
+            for name in option.names {
+                // Каждая форма записи дает то же, что "name value"
+                let separate = parse(&[name, value]).unwrap_or_else(|e| panic!("{}: {}", name, e));
+                let forms = if name.starts_with("--") {
+                    vec![(vec![format!("{}={}", name, value)], separate.clone())]
+                } else {
+                    vec![
+                        (vec![format!("{}{}", name, value)], separate.clone()),
+                        // В группе после флагов без значения
+                        (
+                            vec![format!("-sB{}{}", &name[1..], value)],
+                            parse(&["-s", "-B", name, value]).unwrap(),
+                        ),
+                    ]
+                };
+                for (form, expected) in forms {
+                    let form: Vec<&str> = form.iter().map(String::as_str).collect();
+                    let actual = parse(&form).unwrap_or_else(|e| panic!("{:?}: {}", form, e));
+                    assert_eq!(actual, expected, "{:?}", form);
+                }
 
-        // Должна быть хотя бы одна цифра
-        assert!(result.chars().any(|c| c.is_ascii_digit()));
-        Ok(())
+                // Без значения - ошибка с длинным именем опции
+                let long_name = if name.starts_with("--") { name } else { option.long_name() };
+                assert_eq!(parse(&[name]), Err(CliError::MissingValue(long_name)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_listed_option_is_parsed() {
+        // Таблица для справки и подсказок не расходится с разбором
+        for option in options::OPTIONS {
+            for name in option.names {
+                let mut args = vec!["pwgen".to_string(), name.to_string()];
+                if option.value.is_some() {
+                    args.push("1".to_string());
+                }
+                if let Err(e @ CliError::UnknownOption { .. }) = parse_args_from_vec(args) {
+                    panic!("{}: {}", name, e);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_take_command() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let mut list = args(&["pwgen", "-s", "12"]);
+        assert_eq!(take_command(&mut list), Command::Generate);
+        assert_eq!(list, args(&["pwgen", "-s", "12"]));
+
+        for (name, command) in [
+            ("generate", Command::Generate),
+            ("passphrase", Command::Passphrase),
+            ("check", Command::Check),
+            ("completions", Command::Completions),
+            ("mangen", Command::Mangen),
+        ] {
+            let mut list = args(&["pwgen", name, "12"]);
+            assert_eq!(take_command(&mut list), command);
+            assert_eq!(list, args(&["pwgen", "12"]));
+        }
+
+        // Имя команды только на первом месте
+        let mut list = args(&["pwgen", "-r", "check"]);
+        assert_eq!(take_command(&mut list), Command::Generate);
+        assert_eq!(list.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_flag_clusters() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+
+        let config = parse(&["-sy1B", "16"]).unwrap();
+        assert!(config.secure && config.symbols && config.ambiguous);
+        assert!(!config.columns);
+        assert_eq!(config.pw_length, 16);
+
+        // Порядок сохраняется: действует последний из -C и -1
+        assert!(parse(&["-1C"]).unwrap().columns);
+        assert!(!parse(&["-C1"]).unwrap().columns);
+
+        // -r забирает остаток аргумента
+        let config = parse(&["-sBr0O1l", "12"]).unwrap();
+        assert!(config.secure && config.ambiguous);
+        assert_eq!(config.remove_chars, Some(vec!['0', 'O', '1', 'l']));
+        assert_eq!(config.pw_length, 12);
+
+        // ...или следующий аргумент, если остатка нет
+        let config = parse(&["-sr", "-sy", "10"]).unwrap();
+        assert!(config.secure && !config.symbols);
+        assert_eq!(config.remove_chars, Some(vec!['-', 's', 'y']));
+
+        let config = parse(&["-so", "out.txt"]).unwrap();
+        assert_eq!(config.output, Some(PathBuf::from("out.txt")));
+
+        assert_eq!(
+            parse(&["-syx1"]).unwrap_err(),
+            CliError::UnknownOption {
+                option: "'x' in -syx1".to_string(),
+                suggestion: None,
+            }
+        );
+        assert_eq!(parse(&["-sr"]).unwrap_err(), CliError::MissingValue("--remove-chars"));
+    }
+
+    // Терминал заданного размера вместо stdout
+    struct FakeTerminal {
+        tty: bool,
+        size: Option<term::Size>,
+    }
+
+    impl FakeTerminal {
+        const PIPE: FakeTerminal = FakeTerminal { tty: false, size: None };
+
+        fn sized(width: usize, height: usize) -> FakeTerminal {
+            FakeTerminal {
+                tty: true,
+                size: Some(term::Size { width, height }),
+            }
+        }
+    }
+
+    impl term::Terminal for FakeTerminal {
+        fn is_terminal(&self) -> bool {
+            self.tty
+        }
+
+        fn size(&self) -> Option<term::Size> {
+            self.size
+        }
+    }
+
+    #[test]
+    fn test_terminal_defaults() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args).unwrap()
+        };
+        let on = |terminal: &FakeTerminal, list: &[&str]| {
+            let mut config = parse(list);
+            apply_terminal_defaults(&mut config, terminal);
+            (config.num_pw, config.columns)
+        };
+        let tty = FakeTerminal::sized(80, 24);
+
+        // В терминале 80x24 - 8 столбцов на 23 строки, как у pwgen
+        assert_eq!(on(&tty, &[]), (184, true));
+        assert_eq!(on(&tty, &["-1"]), (1, false));
+        assert_eq!(on(&tty, &["16", "5"]), (5, true));
+
+        // В конвейере - один пароль в строке
+        let pipe = FakeTerminal::PIPE;
+        assert_eq!(on(&pipe, &[]), (1, false));
+        assert_eq!(on(&pipe, &["16"]), (1, false));
+        assert_eq!(on(&pipe, &["-1"]), (1, false));
+
+        // Явные значения всегда важнее
+        assert_eq!(on(&pipe, &["16", "20"]), (20, false));
+        assert_eq!(on(&pipe, &["-C"]), (184, true));
+        assert_eq!(on(&pipe, &["-C", "16", "3"]), (3, true));
+        assert_eq!(on(&pipe, &["-C", "-1"]), (1, false));
+
+        // Вывод в файл - как в конвейер, от терминала не зависит
+        let wide = FakeTerminal::sized(200, 60);
+        assert_eq!(on(&wide, &["-o", "passwords.txt"]), (1, false));
+        assert_eq!(on(&wide, &["-o", "passwords.txt", "8", "5"]), (5, false));
+        assert_eq!(on(&wide, &["-o", "passwords.txt", "-C"]), (184, true));
+        assert_eq!(on(&pipe, &["-o", "passwords.txt", "--per-line=2", "8", "5"]), (5, true));
+
+        // Один пароль для --copy и --qr, пакет не меньше --pick
+        assert_eq!(on(&tty, &["--copy"]).0, 1);
+        assert_eq!(on(&tty, &["--qr"]).0, 1);
+        assert_eq!(on(&FakeTerminal::sized(40, 10), &["20", "--pick", "30"]).0, 30);
     }
 
     #[test]
-    fn test_apply_requirements_adds_symbol() -> io::Result<()> {
+    fn test_screen_count() {
+        let count = |terminal: &FakeTerminal, list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            let mut config = parse_args_from_vec(args).unwrap();
+            apply_terminal_defaults(&mut config, terminal);
+            let width = terminal.size.map(|size| size.width);
+            (config.num_pw, Layout::new(&config, width).columns)
+        };
+
+        // Столбцы - ширина / (длина + 1), строк на одну меньше высоты
+        for (width, height, length, expected) in [
+            (80, 24, "8", (184, 8)),
+            (120, 40, "12", (351, 9)),
+            (100, 30, "16", (145, 5)),
+            (132, 50, "8", (686, 14)),
+            // Пароль шире терминала - один столбец
+            (40, 10, "50", (9, 1)),
+            // Одна строка на экране все равно печатается
+            (80, 1, "8", (8, 8)),
+        ] {
+            let terminal = FakeTerminal::sized(width, height);
+            assert_eq!(count(&terminal, &[length]), expected, "{}x{} {}", width, height, length);
+        }
+
+        // Номера занимают место: "184. " - 5 символов
+        assert_eq!(count(&FakeTerminal::sized(80, 24), &["--numbered"]), (115, 5));
+
+        // Размер не узнать - 80x24
+        let unknown = FakeTerminal { tty: true, size: None };
+        let mut config = parse_args_from_vec(vec!["pwgen".to_string(), "12".to_string()]).unwrap();
+        apply_terminal_defaults(&mut config, &unknown);
+        assert_eq!(config.num_pw, 6 * 23);
+    }
+
+    #[test]
+    fn test_column_count() {
         let mut config = test_config();
-        config.symbols = true;
-        // Увеличиваем количество данных
-        let mut mock_rng = Cursor::new(vec![0, 0, 0, 0, 0, 0]);
+        config.columns = true;
+        // pwgen в терминале шириной 80: 8 паролей по 8 символов
+        config.pw_length = 8;
+        assert_eq!(column_count(&config, Some(80)), 8);
+        assert_eq!(column_count(&config, None), 8);
+        config.pw_length = 12;
+        assert_eq!(column_count(&config, Some(80)), 6);
+        assert_eq!(column_count(&config, Some(13)), 1);
 
-        // Пароль без символов
-        let password = b"abcdefgh".to_vec();
-        let result = apply_requirements(password, &config, &mut mock_rng)?;
+        // Пароль шире терминала: все равно один столбец
+        config.pw_length = 100;
+        assert_eq!(column_count(&config, Some(80)), 1);
 
-        // Должен быть хотя бы один символ
-        assert!(result.chars().any(|c| SYMBOLS.contains(&(c as u8))));
-        Ok(())
+        config.pw_length = 8;
+        config.columns = false;
+        assert_eq!(column_count(&config, Some(80)), 1);
     }
 
     #[test]
-    fn test_parse_args_default() {
-        let args = vec!["pwgen".to_string()];
-        let config = parse_args_from_vec(args);
+    fn test_fixed_columns() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
 
-        assert_eq!(config.pw_length, DEFAULT_LENGTH);
-        assert_eq!(config.num_pw, DEFAULT_COUNT);
-        assert!(config.capitalize);
-        assert!(config.numerals);
+        let config = parse(&["--columns=3", "6"]).unwrap();
+        assert_eq!(config.fixed_columns, Some(3));
+        assert!(config.columns && config.columns_given);
+        assert_eq!(column_count(&config, Some(80)), 3);
+        assert_eq!(parse(&["--columns", "12"]).unwrap().fixed_columns, Some(12));
+
+        // Больше, чем помещается в терминал: урезается до его ширины,
+        // а в файл или канал выводится ровно столько, сколько просили
+        let config = parse(&["--columns=10", "20"]).unwrap();
+        assert_eq!(column_count(&config, Some(80)), 3);
+        assert_eq!(column_count(&config, None), 10);
+
+        // -1 выключает столбцы в любом порядке
+        assert_eq!(column_count(&parse(&["--columns=4", "-1"]).unwrap(), Some(80)), 1);
+        assert_eq!(column_count(&parse(&["-1", "--columns=4"]).unwrap(), Some(80)), 1);
+
+        assert!(matches!(
+            parse(&["--columns=0"]),
+            Err(CliError::InvalidNumber { what: "number of columns", .. })
+        ));
+        assert_eq!(parse(&["--columns"]).unwrap_err(), CliError::MissingValue("--columns"));
     }
 
     #[test]
-    fn test_parse_args_with_length() {
-        let args = vec!["pwgen".to_string(), "12".to_string()];
-        let config = parse_args_from_vec(args);
+    fn test_per_line() -> io::Result<()> {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let per_line = |passwords: &[&str], list: &[&str]| -> io::Result<String> {
+            let config = parse(list).unwrap();
+            let passwords = Passwords::from(passwords.iter().map(|p| p.to_string()).collect::<Vec<_>>());
+            let mut out = Vec::new();
+            // Терминал в 10 символов на --per-line не влияет
+            write_passwords(&mut out, &passwords, Layout::new(&config, Some(10)))?;
+            Ok(String::from_utf8(out).unwrap())
+        };
 
-        assert_eq!(config.pw_length, 12);
-        assert_eq!(config.num_pw, DEFAULT_COUNT);
+        let config = parse(&["--per-line=4", "12"]).unwrap();
+        assert_eq!(config.per_line, Some(4));
+        assert!(config.columns && config.columns_given);
+        assert_eq!(column_count(&config, Some(20)), 4);
+        assert_eq!(column_count(&config, None), 4);
+
+        // Делится нацело: все строки полные
+        let passwords = ["abc", "d", "efgh", "ij", "k", "lmnop", "q", "rs"];
+        assert_eq!(per_line(&passwords, &["--per-line", "4"])?, "abc   d     efgh  ij\nk     lmnop q     rs\n");
+        assert_eq!(per_line(&passwords, &["--per-line", "2"])?, "abc   d\nefgh  ij\nk     lmnop\nq     rs\n");
+        // Не делится: последняя строка короче, но выровнена так же
+        assert_eq!(per_line(&passwords, &["--per-line", "3"])?, "abc   d     efgh\nij    k     lmnop\nq     rs\n");
+        assert_eq!(per_line(&passwords[..5], &["--per-line", "4"])?, "abc  d    efgh ij\nk\n");
+        // Больше, чем паролей
+        assert_eq!(per_line(&passwords[..3], &["--per-line", "5"])?, "abc  d    efgh\n");
+        assert_eq!(per_line(&passwords[..3], &["--per-line", "1"])?, "abc\nd\nefgh\n");
+        // С номерами
+        assert_eq!(
+            per_line(&passwords[..3], &["--per-line", "2", "--numbered"])?,
+            "1. abc  2. d\n3. efgh\n"
+        );
+
+        // Работает и в конвейере, где столбцы по умолчанию выключены
+        let mut config = parse(&["--per-line", "4", "8"]).unwrap();
+        apply_terminal_defaults(&mut config, &FakeTerminal::PIPE);
+        assert!(config.columns);
+        assert_eq!(config.num_pw, 4 * 23);
+        let mut config = parse(&["--per-line", "4", "8"]).unwrap();
+        apply_terminal_defaults(&mut config, &FakeTerminal::sized(200, 11));
+        assert_eq!(config.num_pw, 40);
+
+        assert!(matches!(
+            parse(&["--per-line=0"]),
+            Err(CliError::InvalidNumber { what: "passwords per line", .. })
+        ));
+        assert_eq!(parse(&["--per-line"]).unwrap_err(), CliError::MissingValue("--per-line"));
+        Ok(())
     }
 
     #[test]
-    fn test_parse_args_with_length_and_count() {
-        let args = vec!["pwgen".to_string(), "12".to_string(), "5".to_string()];
-        let config = parse_args_from_vec(args);
+    fn test_per_line_conflicts() {
+        let parse = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args)
+        };
+        let conflict = |list: &[&str]| match parse(list) {
+            Err(CliError::ConflictingFlags(message)) => message,
+            other => panic!("{:?}: {:?}", list, other),
+        };
 
-        assert_eq!(config.pw_length, 12);
-        assert_eq!(config.num_pw, 5);
+        for list in [&["--per-line", "3", "-1"][..], &["-1", "--per-line", "3"]] {
+            assert_eq!(conflict(list), "--per-line and -1 conflict: -1 prints one password per line");
+        }
+        for list in [&["--per-line", "3", "--columns", "2"][..], &["--columns=2", "--per-line=3"]] {
+            let message = conflict(list);
+            assert!(message.starts_with("--per-line and --columns conflict: --columns N is a number of columns"));
+            assert!(message.contains("exactly N passwords on every line"));
+        }
+        for (flag, what) in [
+            ("--porcelain", "--porcelain prints one password per line"),
+            ("-z", "-z/--null"),
+            ("--phonetic", "--phonetic"),
+            ("--annotate", "--annotate"),
+        ] {
+            let message = conflict(&["--per-line", "3", flag]);
+            assert!(message.starts_with(what), "{}", message);
+            assert!(message.ends_with("--per-line"), "{}", message);
+        }
+        // -C с --per-line ничего не меняет
+        assert_eq!(parse(&["-C", "--per-line", "3"]).unwrap().per_line, Some(3));
     }
 
     #[test]
-    fn test_parse_args_options() {
-        let args = vec![
-            "pwgen".to_string(),
-            "-A".to_string(), // no-capitalize
-            "-0".to_string(), // no-numerals
-            "-y".to_string(), // symbols
-            "-s".to_string(), // secure
-            "-B".to_string(), // ambiguous
-            "-v".to_string(), // no-vowels
-            "-1".to_string(), // no columns
-        ];
-        let config = parse_args_from_vec(args);
+    fn test_columns_fit_default_width() -> Result<(), RunError> {
+        let mut config = test_config();
+        config.columns = true;
+        config.num_pw = 20;
+        for length in [4, 8, 13, 20, 79, 80] {
+            config.pw_length = length;
+            let passwords = generate_passwords(&config)?;
+            let mut out = Vec::new();
+            write_passwords(&mut out, &passwords, Layout::new(&config, None))?;
+            let text = String::from_utf8(out).unwrap();
+            let lines: Vec<&str> = text.lines().collect();
+            assert!(lines.iter().all(|line| line.len() <= term::DEFAULT_WIDTH.max(length)));
+            assert_eq!(text.split_whitespace().count(), 20);
+        }
+        Ok(())
+    }
 
-        assert!(config.no_capitalize);
-        assert!(config.no_numerals);
-        assert!(config.symbols);
-        assert!(config.secure);
-        assert!(config.ambiguous);
-        assert!(config.no_vowels);
-        assert!(!config.columns);
+    #[test]
+    fn test_parse_args_encrypt_to() {
+        let args: Vec<String> = ["pwgen", "--encrypt-to", "alice@example.com", "-o", "alice.gpg"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = parse_args_from_vec(args).unwrap();
+        assert_eq!(
+            config.encrypt_to,
+            Some(Recipient::Gpg("alice@example.com".to_string()))
+        );
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_parse_args_remove_chars() {
-        let args = vec![
-            "pwgen".to_string(),
-            "-r".to_string(),
-            "abc".to_string(),
-        ];
-        let config = parse_args_from_vec(args);
+    fn test_encrypted_output_is_sized_upfront() -> io::Result<()> {
+        // Буфер открытого текста не перевыделяется даже для столбцов
+        let passwords = Passwords::from(
+            ["a", "bcd", "ef", "ghij", "k", "lm", "n"].iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+        );
+        let width = 4;
+        let mut plaintext = Vec::with_capacity(passwords.len() * (width + 1));
+        let capacity = plaintext.capacity();
+        write_passwords(&mut plaintext, &passwords, Layout::columns(3))?;
+        assert_eq!(plaintext.capacity(), capacity);
+        Ok(())
+    }
 
-        assert_eq!(config.remove_chars, Some(b"abc".to_vec()));
+    #[test]
+    fn test_parse_args_copy() {
+        let args = |list: &[&str]| {
+            let mut args = vec!["pwgen".to_string()];
+            args.extend(list.iter().map(|s| s.to_string()));
+            parse_args_from_vec(args).unwrap()
+        };
+
+        // Без явного количества копируется единственный пароль
+        let config = args(&["--copy", "16"]);
+        assert!(config.copy);
+        assert_eq!(config.num_pw, 1);
+        assert_eq!(config.pick, None);
+        assert_eq!(config.clear_after, clipboard::DEFAULT_CLEAR_AFTER);
+
+        let config = args(&["--copy", "--pick=3", "--clear-after", "10", "16", "5"]);
+        assert_eq!(config.num_pw, 5);
+        assert_eq!(config.pick, Some(3));
+        assert_eq!(config.clear_after, 10);
+
+        let config = args(&["--copy", "--pick", "2"]);
+        assert!(!config.num_pw_given);
+        assert_eq!(config.pick, Some(2));
     }
 
     #[test]
     fn test_print_passwords_columns() {
-        let passwords = vec![
+        let passwords = Passwords::from(vec![
             "abc".to_string(),
             "defg".to_string(),
             "hi".to_string(),
             "jklmn".to_string(),
             "op".to_string(),
-        ];
+        ]);
 
         // Этот тест просто проверяет, что функция не падает
-        print_passwords(&passwords, true);
-        print_passwords(&passwords, false);
+        print_passwords(&passwords, Layout::columns(3)).unwrap();
+        print_passwords(&passwords, Layout::columns(1)).unwrap();
     }
 
     #[test]
@@ -748,6 +9698,5 @@ mod tests {
         assert!(!AMBIGUOUS.is_empty());
         assert!(!CONSONANTS.is_empty());
         assert!(!CONSONANTS_LOWER.is_empty());
-        assert!(!VOWELS_LOWER.is_empty());
     }
 }
@@ -0,0 +1,216 @@
+use std::fmt::Write;
+use std::io;
+
+use crate::options::{self, OPTIONS, OptionSpec};
+use crate::{COMMANDS, CliError, DEFAULT_LENGTH, MAX_COUNT, MAX_LENGTH, join_names, passphrase};
+
+// `pwgen mangen`: страница руководства pwgen.1 в формате roff. Опции берутся
+// из той же таблицы, что и --help, так что страница не расходится с флагами.
+pub fn parse_args(args: &[String]) -> Result<bool, CliError> {
+    match args {
+        [] => Ok(false),
+        [arg] if arg == "-h" || arg == "--help" => Ok(true),
+        [arg, ..] if arg.starts_with('-') => Err(CliError::unknown_option(&[], arg)),
+        _ => Err(CliError::TooManyArguments),
+    }
+}
+
+// Текст для roff: обратная косая черта и дефисы экранируются, строка
+// не должна начинаться с точки или апострофа (это управляющие строки)
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+// Заголовок .TP: "\fB\-r\fR \fIchars\fR, \fB\-\-remove\-chars\fR=\fIchars\fR"
+fn option_tag(option: &OptionSpec) -> String {
+    let forms: Vec<String> = option
+        .names
+        .iter()
+        .map(|name| {
+            let separator = if name.starts_with("--") { "=" } else { " " };
+            let name = format!("\\fB{}\\fR", escape(name));
+            match option.value {
+                // --leet[=n]
+                Some(value) if option.optional_value() => {
+                    let value = escape(value.trim_start_matches("[=").trim_end_matches(']'));
+                    format!("{}[=\\fI{}\\fR]", name, value)
+                }
+                Some(value) => {
+                    let value = escape(value.trim_start_matches('<').trim_end_matches('>'));
+                    format!("{}{}\\fI{}\\fR", name, separator, value)
+                }
+                None => name,
+            }
+        })
+        .collect();
+    forms.join(", ")
+}
+
+fn options_section(out: &mut String, options: &[OptionSpec]) {
+    for option in options {
+        out.push_str(".TP\n");
+        let _ = writeln!(out, "{}", option_tag(option));
+        let _ = writeln!(out, "{}", escape(&option.help.join(" ")));
+    }
+}
+
+pub fn render() -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        ".TH PWGEN 1 \"\" \"pwgen-rs {}\" \"User Commands\"",
+        env!("CARGO_PKG_VERSION")
+    );
+    out.push_str(".SH NAME\n");
+    out.push_str("pwgen \\- generate pronounceable passwords\n");
+
+    out.push_str(".SH SYNOPSIS\n");
+    out.push_str(".B pwgen\n");
+    out.push_str("[ \\fBgenerate\\fR ] [ \\fIOPTIONS\\fR ] [ \\fIpw_length\\fR ] [ \\fInum_pw\\fR ]\n");
+    out.push_str(".br\n");
+    out.push_str(".B pwgen passphrase\n");
+    out.push_str("[ \\fIOPTIONS\\fR ] [ \\fInum_words\\fR ] [ \\fInum_phrases\\fR ]\n");
+    out.push_str(".br\n");
+    out.push_str(".B pwgen check\n");
+    out.push_str("[ \\fIOPTIONS\\fR ] [ \\fIpw_length\\fR ] < \\fIpasswords\\fR\n");
+    out.push_str(".br\n");
+    out.push_str(".B pwgen completions\n");
+    out.push_str("\\fIshell\\fR\n");
+    out.push_str(".br\n");
+    out.push_str(".B pwgen mangen\n");
+
+    out.push_str(".SH DESCRIPTION\n");
+    out.push_str("The pwgen program generates passwords which are designed to be easily\n");
+    out.push_str("memorized by humans, while being as secure as possible. With \\fB\\-s\\fR\n");
+    out.push_str("the passwords are completely random instead.\n");
+    out.push_str(".PP\n");
+    let _ = writeln!(
+        out,
+        "\\fIpw_length\\fR is the length of the passwords, {} by default and at most {}.",
+        DEFAULT_LENGTH, MAX_LENGTH
+    );
+    let _ = writeln!(
+        out,
+        "\\fInum_pw\\fR is the number of passwords, at most {}. When standard output",
+        MAX_COUNT
+    );
+    out.push_str("is a terminal, by default it fills the screen: as many columns as fit in its\n");
+    out.push_str("width, on all rows but the last (80x24 if the size is unknown). With \\fB\\-1\\fR,\n");
+    out.push_str("or when standard output is not a terminal, a single password is printed.\n");
+    out.push_str(".PP\n");
+    let short_values: Vec<String> = options::short_value_names(OPTIONS)
+        .iter()
+        .map(|name| format!("\\fB{}\\fR", escape(name)))
+        .collect();
+    let short_values: Vec<&str> = short_values.iter().map(String::as_str).collect();
+    out.push_str("Short options can be combined, as in \\fB\\-sy1B\\fR;\n");
+    let _ = writeln!(out, "{} take the rest of the group as their value.", join_names(&short_values));
+
+    out.push_str(".SH COMMANDS\n");
+    for (name, description) in COMMANDS {
+        out.push_str(".TP\n");
+        let _ = writeln!(out, "\\fB{}\\fR", escape(name));
+        let _ = writeln!(out, "{}", escape(description));
+    }
+
+    out.push_str(".SH OPTIONS\n");
+    options_section(&mut out, OPTIONS);
+    out.push_str(".SS \"Options of pwgen passphrase\"\n");
+    options_section(&mut out, passphrase::OPTIONS);
+
+    out.push_str(".SH ENVIRONMENT\n");
+    out.push_str(".TP\n");
+    out.push_str(".B PWGEN_OPTS\n");
+    out.push_str("Flags applied before the command line, which overrides them, for example\n");
+    out.push_str("\\fBPWGEN_OPTS=\"\\-s \\-B 24\"\\fR.\n");
+
+    out.push_str(".SH FILES\n");
+    out.push_str(".TP\n");
+    out.push_str(".I $XDG_CONFIG_HOME/pwgen/config.toml\n");
+    out.push_str("Default settings, read unless \\fB\\-\\-no\\-config\\fR is given\n");
+    out.push_str("(\\fI~/.config/pwgen/config.toml\\fR if XDG_CONFIG_HOME is not set,\n");
+    out.push_str("\\fI%APPDATA%\\epwgen\\econfig.toml\\fR on Windows).\n");
+
+    out.push_str(".SH EXIT STATUS\n");
+    let statuses = [
+        ("0", "Success."),
+        (
+            "1",
+            "A negative answer: \\fBpwgen check\\fR found a password that fails the policy, \\fB\\-\\-self\\-test\\fR failed, or \\fB\\-i\\fR was quit without a password.",
+        ),
+        (
+            "2",
+            "A usage error, or settings no password can satisfy (an empty character set, a length shorter than the required classes), found before generating.",
+        ),
+        ("3", "Generation failed to meet the requirements."),
+        ("4", "An I/O error: the random source, the output file, the clipboard or the terminal failed."),
+        ("128+\\fIn\\fR", "Interrupted by signal \\fIn\\fR."),
+    ];
+    for (status, meaning) in statuses {
+        out.push_str(".TP\n");
+        let _ = writeln!(out, "{}", status);
+        let _ = writeln!(out, "{}", meaning);
+    }
+    out
+}
+
+pub fn print_help() -> io::Result<()> {
+    use std::io::Write;
+    let mut out = io::stdout().lock();
+    writeln!(out, "Usage: pwgen mangen > pwgen.1")?;
+    writeln!(out)?;
+    writeln!(out, "Print the pwgen(1) manual page in roff format to stdout.")?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("--no-config"), "\\-\\-no\\-config");
+        assert_eq!(escape("a\\b"), "a\\eb");
+        assert_eq!(escape(".hidden"), "\\&.hidden");
+    }
+
+    #[test]
+    fn test_option_tag() {
+        let find = |name: &str| OPTIONS.iter().find(|option| option.names.contains(&name)).unwrap();
+        assert_eq!(option_tag(find("-c")), "\\fB\\-c\\fR, \\fB\\-\\-capitalize\\fR");
+        assert_eq!(
+            option_tag(find("-r")),
+            "\\fB\\-r\\fR \\fIchars\\fR, \\fB\\-\\-remove\\-chars\\fR=\\fIchars\\fR"
+        );
+        assert_eq!(option_tag(find("-C")), "\\fB\\-C\\fR");
+    }
+
+    #[test]
+    fn test_every_option_is_documented() {
+        let page = render();
+        for option in OPTIONS.iter().chain(passphrase::OPTIONS) {
+            for name in option.names {
+                assert!(page.contains(&format!("\\fB{}\\fR", escape(name))), "{}", name);
+            }
+        }
+        for (name, _) in COMMANDS {
+            assert!(page.contains(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_no_line_starts_with_control_by_accident() {
+        // Управляющие строки - только те, что написаны здесь явно
+        for line in render().lines() {
+            if let Some(request) = line.strip_prefix('.') {
+                let name = request.split_whitespace().next().unwrap_or_default();
+                assert!(["TH", "SH", "SS", "B", "I", "br", "PP", "TP"].contains(&name), "{}", line);
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::rng::{EntropySource, random_index};
+use crate::{Case, Config, RunError, Syllabled, is_allowed, secret};
+
+// `--memorable-model markov`: буквы пароля выбираются по частотам триграмм
+// английских слов из markov_words.txt. Следующая буква зависит от двух
+// предыдущих; конец слова - тоже исход, после него слово начинается
+// заново. Цифры, заглавные и символы добавляет apply_requirements.
+
+// Буквы a-z - индексы 1..=26, 0 - граница слова
+const LETTERS: usize = 27;
+const END: usize = 0;
+
+type Counts = [[[u16; LETTERS]; LETTERS]; LETTERS];
+
+// COUNTS[a][b][c] - сколько раз в словах корпуса за a и b шла c. Таблица
+// считается при сборке; буква вне a-z в корпусе - ошибка компиляции.
+static COUNTS: Counts = count(include_bytes!("markov_words.txt"));
+
+const fn count(words: &[u8]) -> Counts {
+    let mut counts = [[[0; LETTERS]; LETTERS]; LETTERS];
+    let (mut a, mut b) = (END, END);
+    let mut i = 0;
+    while i < words.len() {
+        let c = if words[i] == b'\n' { END } else { (words[i] - b'a' + 1) as usize };
+        counts[a][b][c] += 1;
+        (a, b) = if c == END { (END, END) } else { (b, c) };
+        i += 1;
+    }
+    counts
+}
+
+pub struct Model {
+    // Буква для каждого индекса; None - буква исключена
+    letters: [Option<char>; LETTERS],
+    // rows[a * LETTERS + b]: допустимые продолжения и их веса
+    rows: Vec<Vec<(usize, u32)>>,
+}
+
+impl Model {
+    // Веса перенормируются: исключенные буквы просто выпадают из строк.
+    // Если строка опустела, выпадают и переходы в нее, и так до тех пор,
+    // пока пустых строк не останется; ошибка - только если опустела строка
+    // начала слова.
+    pub fn new(config: &Config) -> Result<Self, RunError> {
+        // С --case upper буквы сразу пишутся заглавными
+        let upper = config.case == Some(Case::Upper);
+        let mut letters = [None; LETTERS];
+        for (i, c) in (b'a'..=b'z').map(char::from).enumerate() {
+            let c = if upper { c.to_ascii_uppercase() } else { c };
+            letters[i + 1] = is_allowed(c, config).then_some(c);
+        }
+
+        let mut rows: Vec<Vec<(usize, u32)>> = COUNTS
+            .iter()
+            .flatten()
+            .map(|counts| {
+                (0..LETTERS)
+                    .filter(|&c| c == END || letters[c].is_some())
+                    .map(|c| (c, u32::from(counts[c])))
+                    .filter(|&(_, weight)| weight > 0)
+                    .collect()
+            })
+            .collect();
+        loop {
+            let empty: Vec<bool> = rows.iter().map(Vec::is_empty).collect();
+            let mut changed = false;
+            for (context, row) in rows.iter_mut().enumerate() {
+                let b = context % LETTERS;
+                let before = row.len();
+                row.retain(|&(c, _)| c == END || !empty[b * LETTERS + c]);
+                changed |= row.len() != before;
+            }
+            if !changed {
+                break;
+            }
+        }
+        if rows[0].is_empty() {
+            return Err(RunError::Settings(
+                "no word of the Markov model can start with the letters left after \
+                 --ambiguous/--remove-chars/--include-only"
+                    .to_string(),
+            ));
+        }
+        Ok(Self { letters, rows })
+    }
+
+    fn row(&self, (a, b): (usize, usize)) -> &[(usize, u32)] {
+        &self.rows[a * LETTERS + b]
+    }
+
+    // Буква с вероятностью, пропорциональной весу: равномерное число
+    // меньше суммы весов без смещения, затем поиск по накопленным весам
+    fn sample<R: EntropySource + ?Sized>(&self, context: (usize, usize), rng: &mut R) -> Result<usize, RunError> {
+        let row = self.row(context);
+        let total: u32 = row.iter().map(|&(_, weight)| weight).sum();
+        let mut pick = random_index(rng, total as usize)? as u32;
+        for &(c, weight) in row {
+            if pick < weight {
+                return Ok(c);
+            }
+            pick -= weight;
+        }
+        unreachable!("pick is below the total weight")
+    }
+
+    // Энтропия выбора в строке, в битах
+    fn row_bits(&self, context: (usize, usize)) -> f64 {
+        let row = self.row(context);
+        let total: u32 = row.iter().map(|&(_, weight)| weight).sum();
+        row.iter()
+            .map(|&(_, weight)| {
+                let p = f64::from(weight) / f64::from(total);
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+// Пароль и позиции, с которых начинаются слова модели: для --case title
+// они вместо слогов
+pub fn generate<R: EntropySource + ?Sized>(
+    length: usize,
+    config: &Config,
+    rng: &mut R,
+) -> Result<Syllabled, RunError> {
+    let model = Model::new(config)?;
+    let mut password: Vec<char> = Vec::with_capacity(length);
+    let mut starts = Vec::new();
+    let mut context = (END, END);
+    while password.len() < length {
+        let c = match model.sample(context, rng) {
+            Ok(c) => c,
+            Err(e) => {
+                secret::wipe_chars(&mut password);
+                return Err(e);
+            }
+        };
+        if c == END {
+            context = (END, END);
+            continue;
+        }
+        if context == (END, END) {
+            starts.push(password.len());
+        }
+        // Исключенных букв в строках нет
+        password.push(model.letters[c].expect("excluded letters have no weight"));
+        context = (context.1, c);
+    }
+    Ok((password, starts))
+}
+
+// Буквы, которые могут попасть в пароль, для --dry-run
+pub fn letters(config: &Config) -> Vec<char> {
+    match Model::new(config) {
+        Ok(model) => model.letters.iter().flatten().cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Энтропия пароля из length букв: сколько бит в среднем уходит на выборы
+// по весам триграмм. Конец слова - тоже выбор, а "the" + "re" и "there"
+// дают один пароль, так что это оценка сверху.
+pub fn entropy(length: usize, config: &Config) -> Result<f64, RunError> {
+    let model = Model::new(config)?;
+    // bits[b * LETTERS + c] - энтропия оставшихся букв после контекста (b, c)
+    let mut bits = vec![0.0; LETTERS * LETTERS];
+    for _ in 0..length {
+        let mut next = vec![0.0; LETTERS * LETTERS];
+        // Начало слова: конца слова в этой строке нет
+        let restart = model.row_bits((END, END)) + mean(&model, (END, END), |c| bits[c]);
+        next[0] = restart;
+        for context in 1..LETTERS * LETTERS {
+            let context = (context / LETTERS, context % LETTERS);
+            if model.row(context).is_empty() {
+                continue;
+            }
+            next[context.0 * LETTERS + context.1] = model.row_bits(context)
+                + mean(&model, context, |c| if c == END { restart } else { bits[context.1 * LETTERS + c] });
+        }
+        bits = next;
+    }
+    Ok(bits[0])
+}
+
+// Среднее f(c) по весам продолжений контекста
+fn mean<F: Fn(usize) -> f64>(model: &Model, context: (usize, usize), f: F) -> f64 {
+    let row = model.row(context);
+    let total: u32 = row.iter().map(|&(_, weight)| weight).sum();
+    row.iter().map(|&(c, weight)| f64::from(weight) / f64::from(total) * f(c)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+
+    #[test]
+    fn test_corpus_counts() {
+        // Каждое слово начинается и заканчивается ровно один раз
+        let words = include_str!("markov_words.txt").lines().count() as u32;
+        let starts: u32 = COUNTS[END][END].iter().map(|&n| u32::from(n)).sum();
+        let ends: u32 = COUNTS.iter().flatten().map(|row| u32::from(row[END])).sum();
+        assert_eq!((starts, ends), (words, words));
+        // Пустых слов нет, так что и конца сразу после начала нет
+        assert_eq!(COUNTS[END][END][END], 0);
+        // За q в корпусе всегда идет u
+        let q = usize::from(b'q' - b'a' + 1);
+        let after_q: u16 = COUNTS.iter().map(|rows| rows[q].iter().sum::<u16>()).sum();
+        let qu: u16 = COUNTS.iter().map(|rows| rows[q][usize::from(b'u' - b'a' + 1)]).sum();
+        assert_eq!(after_q, qu);
+    }
+
+    #[test]
+    fn test_seeded_output() -> Result<(), RunError> {
+        let config = Config::default();
+        let mut rng = ChaCha20Rng::seeded(570);
+        let mut again = ChaCha20Rng::seeded(570);
+        for _ in 0..5 {
+            let (password, starts) = generate(12, &config, &mut rng)?;
+            assert_eq!((password.clone(), starts.clone()), generate(12, &config, &mut again)?);
+            assert_eq!(starts.first(), Some(&0));
+            assert_eq!(password.len(), 12);
+            assert!(password.iter().all(char::is_ascii_lowercase));
+        }
+
+        // -B убирает l, -r - перечисленные буквы
+        let config = Config {
+            ambiguous: true,
+            remove_chars: Some(vec!['e', 't']),
+            ..Config::default()
+        };
+        for _ in 0..200 {
+            let (password, _) = generate(10, &config, &mut rng)?;
+            assert!(!password.iter().any(|c| "let".contains(*c)), "{:?}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_emptied_rows() -> Result<(), RunError> {
+        // Без u словам на q нечем продолжиться, и q пропадает совсем
+        let config = Config {
+            remove_chars: Some(vec!['u']),
+            ..Config::default()
+        };
+        let mut rng = ChaCha20Rng::seeded(5700);
+        for _ in 0..200 {
+            let (password, _) = generate(12, &config, &mut rng)?;
+            assert!(!password.iter().any(|c| "qu".contains(*c)), "{:?}", password);
+        }
+        // Из x и z слова не начать: zebra, zero и zone без e и o обрываются
+        let config = Config {
+            remove_chars: Some(('a'..='y').filter(|&c| c != 'x').collect()),
+            ..Config::default()
+        };
+        assert!(matches!(Model::new(&config), Err(RunError::Settings(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_entropy() -> Result<(), RunError> {
+        let config = Config::default();
+        let model = Model::new(&config)?;
+        // Одна буква - один выбор из строки начала слова
+        assert!((entropy(1, &config)? - model.row_bits((END, END))).abs() < 1e-9);
+        // Первые буквы слов распределены неравномерно: меньше log2 26
+        assert!(model.row_bits((END, END)) < 26f64.log2());
+        // На букву в среднем меньше бит, чем у случайной буквы, но не ноль
+        let per_char = entropy(12, &config)? / 12.0;
+        assert!(per_char > 1.0 && per_char < 26f64.log2(), "{}", per_char);
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use std::io;
+
+use crate::Passwords;
+use crate::rng::{EntropySource, random_index};
+
+// `--mnemonic`: фраза для запоминания пароля, по слову на символ. Буква
+// дает слово на эту букву (заглавная - с заглавной), цифра - свое название
+// или созвучное слово, символ - свое название. Кандидатов несколько, и
+// слово выбирается случайно, чтобы фразы не повторялись. Символы вне
+// таблицы (из --charset-file или --syllable-file) остаются как есть.
+
+const TABLE: &[(char, &[&str])] = &[
+    ('a', &["alpha", "apple", "anchor"]),
+    ('b', &["bravo", "banana", "bridge"]),
+    ('c', &["charlie", "cactus", "castle"]),
+    ('d', &["delta", "dolphin", "drum"]),
+    ('e', &["echo", "eagle", "engine"]),
+    ('f', &["foxtrot", "falcon", "forest"]),
+    ('g', &["golf", "garden", "giraffe"]),
+    ('h', &["hotel", "harbor", "hammer"]),
+    ('i', &["india", "igloo", "island"]),
+    ('j', &["juliet", "jaguar", "jigsaw"]),
+    ('k', &["kilo", "kettle", "koala"]),
+    ('l', &["lima", "lemon", "ladder"]),
+    ('m', &["mike", "mango", "mirror"]),
+    ('n', &["november", "needle", "nest"]),
+    ('o', &["oscar", "orange", "owl"]),
+    ('p', &["papa", "piano", "pepper"]),
+    ('q', &["quebec", "queen", "quilt"]),
+    ('r', &["romeo", "rocket", "river"]),
+    ('s', &["sierra", "sugar", "saddle"]),
+    ('t', &["tango", "tiger", "tunnel"]),
+    ('u', &["uniform", "umbrella", "unicorn"]),
+    ('v', &["victor", "violin", "valley"]),
+    ('w', &["whiskey", "walrus", "window"]),
+    ('x', &["xray", "xylophone", "xenon"]),
+    ('y', &["yankee", "yogurt", "yacht"]),
+    ('z', &["zulu", "zebra", "zipper"]),
+    ('0', &["zero", "hero"]),
+    ('1', &["one", "won", "sun"]),
+    ('2', &["two", "shoe", "zoo"]),
+    ('3', &["three", "tree", "free"]),
+    ('4', &["four", "door", "floor"]),
+    ('5', &["five", "hive", "dive"]),
+    ('6', &["six", "sticks", "bricks"]),
+    ('7', &["seven", "heaven"]),
+    ('8', &["eight", "gate", "plate"]),
+    ('9', &["nine", "wine", "line"]),
+    ('!', &["bang", "exclamation"]),
+    ('"', &["quote", "double-quote"]),
+    ('#', &["hash", "pound"]),
+    ('$', &["dollar", "cash"]),
+    ('%', &["percent"]),
+    ('&', &["ampersand", "and"]),
+    ('\'', &["apostrophe", "tick"]),
+    ('(', &["open-paren"]),
+    (')', &["close-paren"]),
+    ('*', &["star", "asterisk"]),
+    ('+', &["plus"]),
+    (',', &["comma"]),
+    ('-', &["dash", "minus", "hyphen"]),
+    ('.', &["dot", "period"]),
+    ('/', &["slash"]),
+    (':', &["colon"]),
+    (';', &["semicolon"]),
+    ('<', &["less-than"]),
+    ('=', &["equals"]),
+    ('>', &["greater-than"]),
+    ('?', &["question"]),
+    ('@', &["at"]),
+    ('[', &["open-bracket"]),
+    ('\\', &["backslash"]),
+    (']', &["close-bracket"]),
+    ('^', &["caret", "hat"]),
+    ('_', &["underscore"]),
+    ('`', &["backtick", "grave"]),
+    ('{', &["open-brace"]),
+    ('|', &["pipe", "bar"]),
+    ('}', &["close-brace"]),
+    ('~', &["tilde", "squiggle"]),
+];
+
+// Самое длинное слово таблицы с пробелом после него
+pub const MAX_WORD: usize = 14;
+
+// Слова для символа; у заглавной буквы - те же, что у строчной
+fn candidates(c: char) -> Option<&'static [&'static str]> {
+    let key = c.to_ascii_lowercase();
+    TABLE.iter().find(|(k, _)| *k == key).map(|(_, words)| *words)
+}
+
+pub fn sentence<R: EntropySource + ?Sized>(password: &str, rng: &mut R) -> io::Result<String> {
+    let mut sentence = String::with_capacity(password.len() * 12);
+    for c in password.chars() {
+        if !sentence.is_empty() {
+            sentence.push(' ');
+        }
+        match candidates(c) {
+            Some(words) => {
+                let word = words[random_index(rng, words.len())?];
+                if c.is_ascii_uppercase() {
+                    sentence.push(c);
+                    sentence.push_str(&word[1..]);
+                } else {
+                    sentence.push_str(word);
+                }
+            }
+            None => sentence.push(c),
+        }
+    }
+    Ok(sentence)
+}
+
+// Фразы для всех паролей; они выдают пароль так же, как он сам, и хранятся
+// так же
+pub fn sentences<R: EntropySource + ?Sized>(passwords: &Passwords, rng: &mut R) -> io::Result<Passwords> {
+    let longest = passwords.iter().map(str::len).max().unwrap_or(0);
+    let mut sentences = Passwords::with_capacity(passwords.len(), longest * 16);
+    for password in passwords.iter() {
+        sentences.push(sentence(password, rng)?);
+    }
+    Ok(sentences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+    use crate::{
+        CHARSET_PRESETS, LOWERCASE, MOBILE_SYMBOLS, NO_SHIFT_SYMBOLS, NUMERALS, SHELL_SAFE_SYMBOLS, SYMBOLS, UPPERCASE,
+        URL_SAFE,
+    };
+
+    #[test]
+    fn test_table_covers_charsets() {
+        // Все наборы, из которых build_charset берет символы
+        let mut classes: Vec<&[u8]> = vec![
+            LOWERCASE,
+            UPPERCASE,
+            NUMERALS,
+            SYMBOLS,
+            SHELL_SAFE_SYMBOLS,
+            NO_SHIFT_SYMBOLS,
+            MOBILE_SYMBOLS,
+            URL_SAFE,
+        ];
+        classes.extend(CHARSET_PRESETS.iter().map(|(_, chars)| *chars));
+        for c in classes.concat().into_iter().map(char::from) {
+            let words = candidates(c).unwrap_or_else(|| panic!("no mnemonic for {:?}", c));
+            assert!(!words.is_empty());
+            // Слово буквы начинается с нее
+            if c.is_ascii_alphabetic() {
+                assert!(words.iter().all(|word| word.starts_with(c.to_ascii_lowercase())), "{:?}", c);
+            }
+            assert!(words.iter().all(|word| !word.contains(' ')), "{:?}", c);
+            assert!(words.iter().all(|word| word.len() < MAX_WORD), "{:?}", c);
+        }
+        assert_eq!(TABLE.len(), 26 + 10 + SYMBOLS.len());
+    }
+
+    #[test]
+    fn test_sentence() -> io::Result<()> {
+        let mut rng = ChaCha20Rng::seeded(577);
+        let sentence = sentence("aK3!ж", &mut rng)?;
+        let words: Vec<&str> = sentence.split(' ').collect();
+        assert_eq!(words.len(), 5, "{}", sentence);
+        assert!(words[0].starts_with('a'));
+        assert!(words[1].starts_with('K') && words[1][1..].chars().all(|c| c.is_ascii_lowercase()));
+        assert!(["three", "tree", "free"].contains(&words[2]));
+        assert!(["bang", "exclamation"].contains(&words[3]));
+        // Символа нет в таблице - он остается сам собой
+        assert_eq!(words[4], "ж");
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use crate::rng::{EntropySource, random_index};
+use crate::{
+    Config, LOWERCASE, RunError, allowed_chars, build_charset, can_lead, edge_error, empty_charset_error, homoglyph,
+    required_classes, secret,
+};
+
+// `pwgen --mobile-friendly`: строчные буквы, цифры и (с -y) символы с
+// первой страницы символов телефонной клавиатуры. Символы одного класса
+// идут подряд: сначала буквы, затем цифры, затем символы, так что
+// раскладку приходится переключать не больше двух раз.
+
+// Блоки в порядке следования. Буквы не обязательны: если длины не хватает
+// на все блоки, они уступают место обязательным классам.
+pub fn blocks(config: &Config, length: usize) -> Vec<Vec<char>> {
+    let charset = build_charset(config);
+    let required = required_classes(config, &charset);
+    let letters = allowed_chars(LOWERCASE, &charset);
+
+    let mut blocks = Vec::new();
+    if !letters.is_empty() && length > required.len() {
+        blocks.push(letters);
+    }
+    blocks.extend(required.into_iter().map(|(_, _, allowed)| allowed));
+    blocks
+}
+
+// Энтропия пароля: выбор границ блоков плюс символы внутри них. Все
+// разбиения на k непустых блоков равновероятны, поэтому в среднем на
+// блок приходится length/k символов.
+pub fn entropy(length: usize, blocks: &[Vec<char>]) -> f64 {
+    let k = blocks.len();
+    if k == 0 || length < k {
+        return 0.0;
+    }
+    let splits: f64 = (0..k - 1).map(|i| ((length - 1 - i) as f64 / (i + 1) as f64).log2()).sum();
+    let per_char: f64 = blocks.iter().map(|block| (block.len() as f64).log2()).sum();
+    splits + length as f64 / k as f64 * per_char
+}
+
+pub fn generate<R: EntropySource + ?Sized>(length: usize, config: &Config, rng: &mut R) -> Result<String, RunError> {
+    let blocks = blocks(config, length);
+    if blocks.is_empty() {
+        return Err(empty_charset_error());
+    }
+    // Буквы идут первыми, но в коротком пароле их блок уступает место
+    // обязательным классам, а цифрой начинать нельзя
+    if !blocks[0].iter().any(|&c| can_lead(c, config)) {
+        return Err(edge_error(config, "start"));
+    }
+
+    // Границы блоков - k-1 разных позиций из 1..length
+    let mut points: Vec<usize> = (1..length).collect();
+    for i in 0..blocks.len() - 1 {
+        let j = i + random_index(rng, points.len() - i)?;
+        points.swap(i, j);
+    }
+    let mut ends = points[..blocks.len() - 1].to_vec();
+    ends.sort_unstable();
+    ends.push(length);
+
+    let mut password: Vec<char> = Vec::with_capacity(length);
+    for (block, end) in blocks.iter().zip(ends) {
+        while password.len() < end {
+            password.push(block[random_index(rng, block.len())?]);
+        }
+    }
+
+    // Перевыбор внутри блока не нарушает группировку
+    if let Some(table) = &config.homoglyphs {
+        homoglyph::reroll(&mut password, &vec![false; length], &blocks, table, rng)?;
+    }
+
+    let mut result = String::with_capacity(password.iter().map(|c| c.len_utf8()).sum());
+    result.extend(password.iter());
+    secret::wipe_chars(&mut password);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MOBILE_SYMBOLS, NUMERALS, generate_passwords};
+
+    fn config() -> Config {
+        Config {
+            mobile_friendly: true,
+            no_capitalize: true,
+            pw_length: 12,
+            num_pw: 2000,
+            seed: Some(555),
+            ..Config::default()
+        }
+    }
+
+    // Номер блока, к которому относится символ
+    fn class(c: char) -> usize {
+        let b = c as u8;
+        if LOWERCASE.contains(&b) {
+            0
+        } else if NUMERALS.contains(&b) {
+            1
+        } else {
+            2
+        }
+    }
+
+    #[test]
+    fn test_grouped_classes() -> Result<(), RunError> {
+        let config = Config { symbols: true, ..config() };
+        let passwords = generate_passwords(&config)?;
+        for password in passwords.iter() {
+            assert_eq!(password.len(), 12);
+            let allowed = |c: u8| LOWERCASE.contains(&c) || NUMERALS.contains(&c) || MOBILE_SYMBOLS.contains(&c);
+            assert!(password.bytes().all(allowed), "{}", password);
+            // Буквы, затем цифры, затем символы, и каждый блок не пуст
+            let classes: Vec<usize> = password.chars().map(class).collect();
+            assert!(classes.is_sorted(), "{}", password);
+            assert_eq!(classes.first(), Some(&0), "{}", password);
+            assert!(classes.contains(&1), "{}", password);
+            assert_eq!(classes.last(), Some(&2), "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_password_keeps_required_classes() -> Result<(), RunError> {
+        let config = Config {
+            symbols: true,
+            pw_length: 2,
+            num_pw: 100,
+            ..config()
+        };
+        for password in generate_passwords(&config)?.iter() {
+            let classes: Vec<usize> = password.chars().map(class).collect();
+            assert_eq!(classes, [1, 2], "{}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_entropy() {
+        let config = config();
+        let blocks = blocks(&config, 12);
+        assert_eq!(blocks.len(), 2);
+        // 11 вариантов границы и в среднем по 6 букв и цифр
+        let expected = 11f64.log2() + 6.0 * 26f64.log2() + 6.0 * 10f64.log2();
+        assert!((entropy(12, &blocks) - expected).abs() < 1e-9);
+        // Один блок - обычный случайный пароль
+        assert!((entropy(8, &blocks[..1]) - 8.0 * 26f64.log2()).abs() < 1e-9);
+    }
+}
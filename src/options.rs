@@ -0,0 +1,1323 @@
+use crate::CliError;
+
+// Все опции, которые принимает разбор аргументов. По этой таблице разбираются
+// аргументы, печатается --help и подбирается подсказка для опечатки.
+pub struct OptionSpec {
+    pub opt: Opt,
+    // Короткое имя (если есть) и длинные, в порядке показа в справке
+    pub names: &'static [&'static str],
+    pub value: Option<&'static str>,
+    pub help: &'static [&'static str],
+}
+
+// Что делает опция: по нему разбор применяет ее к настройкам
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opt {
+    Capitalize,
+    Length,
+    LengthRange,
+    Bits,
+    Prefix,
+    Suffix,
+    LengthIncludesAffixes,
+    Group,
+    GroupCountsLength,
+    Weights,
+    // Индекс класса в COUNTED_CLASSES
+    MinCount(usize),
+    MaxCount(usize),
+    Classes,
+    Template,
+    Blocks,
+    Like,
+    Balanced,
+    Count,
+    NoCapitalize,
+    Numerals,
+    NoNumerals,
+    Symbols,
+    ShellSafe,
+    UrlSafe,
+    AlphaOnly,
+    DigitsOnly,
+    Pin,
+    MobileFriendly,
+    NoShift,
+    LayoutSafe,
+    NoLeadingDigit,
+    NoLeadingSymbol,
+    NoEdgeSymbols,
+    NoRepeat,
+    NoSequences,
+    NoKeyboardWalks,
+    NoDictionaryWords,
+    NoProfanity,
+    ProfanityFile,
+    Case,
+    HomoglyphStrict,
+    HomoglyphFile,
+    RemoveChars,
+    IncludeOnly,
+    RequireChars,
+    Charset,
+    Preset,
+    CharsetFile,
+    Secure,
+    MemorableModel,
+    SyllableFile,
+    Syllables,
+    Leet,
+    Words,
+    WordsAcrostic,
+    Separator,
+    CapitalizeWords,
+    InjectDigit,
+    InjectSymbol,
+    Wordlist,
+    Lang,
+    Ambiguous,
+    AmbiguousChars,
+    Sha1,
+    RandomSource,
+    Rng,
+    Seed,
+    FastRng,
+    Verbose,
+    Output,
+    Force,
+    EncryptTo,
+    Copy,
+    Interactive,
+    Mnemonic,
+    Phonetic,
+    Annotate,
+    Qr,
+    QrFormat,
+    QrOnly,
+    Pick,
+    ClearAfter,
+    LockMemory,
+    NoConfig,
+    SelfTest,
+    DryRun,
+    Help,
+    Columns,
+    FixedColumns,
+    PerLine,
+    Numbered,
+    Porcelain,
+    Unique,
+    Sort,
+    ShowEntropy,
+    Null,
+    Format,
+    CsvSafe,
+    Color,
+    SingleColumn,
+    NoVowels,
+}
+
+// Что принимает опция со значением: по этому дополняются ее аргументы
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueHint {
+    Text,
+    Number,
+    File,
+    Choice(&'static [&'static str]),
+}
+
+impl OptionSpec {
+    // Имя для сообщений об ошибках: первое длинное, если оно есть
+    pub fn long_name(&self) -> &'static str {
+        self.names.iter().copied().find(|name| name.starts_with("--")).unwrap_or(self.names[0])
+    }
+
+    // Значение "[=n]" необязательно и пишется только через "=": --leet, --leet=3
+    pub fn optional_value(&self) -> bool {
+        self.value.is_some_and(|value| value.starts_with("[="))
+    }
+
+    // Что дополнять после опции; у необязательного значения следующее
+    // слово к опции не относится
+    pub fn hint(&self) -> Option<ValueHint> {
+        if self.optional_value() {
+            return None;
+        }
+        let hint = match self.value? {
+            "num" | "<number>" | "<n>" | "<seconds>" => ValueHint::Number,
+            "<file>" | "path/to/file[#seed]" => ValueHint::File,
+            "<auto|getrandom|urandom|file:PATH>" => ValueHint::Choice(&["auto", "getrandom", "urandom", "file:"]),
+            "<hex|base32|base58|alnum|printable>" => {
+                ValueHint::Choice(&["hex", "base32", "base58", "alnum", "printable"])
+            }
+            "<text|json|jsonl|csv>" => ValueHint::Choice(&["text", "json", "jsonl", "csv"]),
+            "<auto|always|never>" => ValueHint::Choice(&["auto", "always", "never"]),
+            "<ansi|utf8|png:PATH>" => ValueHint::Choice(&["ansi", "utf8", "png:"]),
+            _ => ValueHint::Text,
+        };
+        Some(hint)
+    }
+}
+
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        opt: Opt::Capitalize,
+        names: &["-c", "--capitalize"],
+        value: None,
+        help: &["Include at least one capital letter in the password"],
+    },
+    OptionSpec {
+        opt: Opt::Length,
+        names: &["-L", "--length"],
+        value: Some("num"),
+        help: &["Generate passwords of num characters; the same as pw_length"],
+    },
+    OptionSpec {
+        opt: Opt::LengthRange,
+        names: &["--length-range"],
+        value: Some("<min-max>"),
+        help: &[
+            "Give each password a random length from min to max, as in 12-16;",
+            "cannot be combined with a fixed pw_length (use -N for the count)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Bits,
+        names: &["--bits"],
+        value: Some("<n>"),
+        help: &[
+            "Make passwords just long enough for n bits of entropy, counted from the",
+            "character set left after -B, -r and -v (or the memorable generator's",
+            "choices); the length is printed to stderr and replaces pw_length. With",
+            "--lang or --wordlist it picks the number of words instead",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Prefix,
+        names: &["--prefix"],
+        value: Some("<text>"),
+        help: &[
+            "Put text before every password, as in svc- for svc-Aeb3ohco; its characters",
+            "do not count toward -c, -n, -y or the other class rules",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Suffix,
+        names: &["--suffix"],
+        value: Some("<text>"),
+        help: &["Put text after every password, counted like --prefix"],
+    },
+    OptionSpec {
+        opt: Opt::LengthIncludesAffixes,
+        names: &["--length-includes-affixes"],
+        value: None,
+        help: &[
+            "Count --prefix and --suffix in pw_length and --length-range, so that",
+            "whole passwords have that length; by default it is the random part's",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Group,
+        names: &["--group"],
+        value: Some("<N[,SEP]>"),
+        help: &[
+            "Put SEP (- by default) after every N characters of each password, as in",
+            "hX3k-9dfQ-2mWp, to make it easier to read out; the separators are not",
+            "counted in pw_length unless --group-counts-length is given",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::GroupCountsLength,
+        names: &["--group-counts-length"],
+        value: None,
+        help: &["Count the --group separators in pw_length and --length-range"],
+    },
+    OptionSpec {
+        opt: Opt::Weights,
+        names: &["--weights"],
+        value: Some("<L:U:D:S>"),
+        help: &[
+            "Generate random (-s) passwords where each character is first given a",
+            "class by these weights of lowercase, uppercase, digits and symbols;",
+            "a weight of 0 leaves the class out",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::MinCount(0),
+        names: &["--min-lower"],
+        value: Some("<n>"),
+        help: &["Put at least n lowercase letters in every password"],
+    },
+    OptionSpec {
+        opt: Opt::MinCount(1),
+        names: &["--min-upper"],
+        value: Some("<n>"),
+        help: &["Put at least n capital letters in every password"],
+    },
+    OptionSpec {
+        opt: Opt::MinCount(2),
+        names: &["--min-digits"],
+        value: Some("<n>"),
+        help: &["Put at least n digits in every password"],
+    },
+    OptionSpec {
+        opt: Opt::MinCount(3),
+        names: &["--min-symbols"],
+        value: Some("<n>"),
+        help: &["Put at least n symbols in every password"],
+    },
+    OptionSpec {
+        opt: Opt::MaxCount(0),
+        names: &["--max-lower"],
+        value: Some("<n>"),
+        help: &["Put at most n lowercase letters in every password; 0 leaves them out"],
+    },
+    OptionSpec {
+        opt: Opt::MaxCount(1),
+        names: &["--max-upper"],
+        value: Some("<n>"),
+        help: &["Put at most n capital letters in every password; 0 leaves them out"],
+    },
+    OptionSpec {
+        opt: Opt::MaxCount(2),
+        names: &["--max-digits"],
+        value: Some("<n>"),
+        help: &["Put at most n digits in every password; 0 leaves them out"],
+    },
+    OptionSpec {
+        opt: Opt::MaxCount(3),
+        names: &["--max-symbols"],
+        value: Some("<n>"),
+        help: &["Put at most n symbols in every password; 0 leaves them out"],
+    },
+    OptionSpec {
+        opt: Opt::Classes,
+        names: &["--classes"],
+        value: Some("<spec>"),
+        help: &[
+            "Generate random (-s) passwords with exactly these class counts in a",
+            "random order, as in l4u2d2s1 (l lowercase, u uppercase, d digits,",
+            "s symbols); the counts add up to the password length",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Template,
+        names: &["--template"],
+        value: Some("<pattern>"),
+        help: &[
+            "Generate passwords from a pattern, one character per letter: C/c upper or",
+            "lowercase consonant, V/v vowel, n digit, s symbol, a letter, x any",
+            "character of the set; anything else, or after \\, is copied as is, as in",
+            "Cvcvnn-Cvcvnn; the pattern sets the password length",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Blocks,
+        names: &["--blocks"],
+        value: Some("<spec>"),
+        help: &[
+            "Generate passwords from blocks, as in w6-d4-w6 for Befoka-2846-Rilomu:",
+            "wN is a pronounceable word of N letters with one capital, dN is N digits,",
+            "anything else is copied as is; the blocks must give every required class",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Like,
+        names: &["--like"],
+        value: Some("<password>"),
+        help: &[
+            "Generate passwords shaped like the given one: the same length and the",
+            "same class (lowercase, uppercase, digit or symbol) at each position, with",
+            "a new character wherever the class allows; - reads it from stdin, which",
+            "keeps it out of the shell history",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Balanced,
+        names: &["--balanced"],
+        value: None,
+        help: &[
+            "Generate random (-s) passwords with the classes in equal shares: with",
+            "k classes each one gets L/k characters (rounded down or up) in a random order",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Count,
+        names: &["-N", "--num-passwords", "--count"],
+        value: Some("num"),
+        help: &["Generate num passwords; the same as the num_pw argument"],
+    },
+    OptionSpec {
+        opt: Opt::NoCapitalize,
+        names: &["-A", "--no-capitalize"],
+        value: None,
+        help: &["Don't include capital letters in the password"],
+    },
+    OptionSpec {
+        opt: Opt::Numerals,
+        names: &["-n", "--numerals"],
+        value: None,
+        help: &["Include at least one number in the password"],
+    },
+    OptionSpec {
+        opt: Opt::NoNumerals,
+        names: &["-0", "--no-numerals"],
+        value: None,
+        help: &["Don't include numbers in the password"],
+    },
+    OptionSpec {
+        opt: Opt::Symbols,
+        names: &["-y", "--symbols"],
+        value: None,
+        help: &["Include at least one special symbol in the password"],
+    },
+    OptionSpec {
+        opt: Opt::ShellSafe,
+        names: &["--shell-safe"],
+        value: None,
+        help: &[
+            "Use only symbols that need no quoting in a shell or YAML (@#%^*-_=+.:/);",
+            "-B and -r still remove from them",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::UrlSafe,
+        names: &["--url-safe"],
+        value: None,
+        help: &[
+            "Generate random (-s) passwords of URL-safe characters only (A-Z a-z 0-9",
+            "- . _ ~, unreserved in RFC 3986); -y cannot be used with it",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::AlphaOnly,
+        names: &["--alpha-only"],
+        value: None,
+        help: &["Use letters only, no digits or symbols; -A and -v still apply"],
+    },
+    OptionSpec {
+        opt: Opt::DigitsOnly,
+        names: &["--digits-only"],
+        value: None,
+        help: &["Generate random (-s) passwords of digits only, like PIN codes"],
+    },
+    OptionSpec {
+        opt: Opt::Pin,
+        names: &["--pin"],
+        value: None,
+        help: &[
+            "Generate numeric PINs, 4 digits long by default, and generate again any",
+            "PIN of one repeated digit, consecutive digits (1234, 4321), a repeated",
+            "pair (121212) or a common PIN such as 2580",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::MobileFriendly,
+        names: &["--mobile-friendly"],
+        value: None,
+        help: &[
+            "Generate passwords that are quick to type on a phone: lowercase letters,",
+            "then digits, then (with -y) symbols from . - _ @ !, each class in one block",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::NoShift,
+        names: &["--no-shift"],
+        value: None,
+        help: &[
+            "Use only characters typed without Shift on a US keyboard: no capital",
+            "letters, and with -y only the symbols ` - = [ ] ; ' , . /",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::LayoutSafe,
+        names: &["--layout-safe"],
+        value: None,
+        help: &[
+            "Use only characters on the same key on QWERTY, QWERTZ and AZERTY",
+            "keyboards: letters bcdefghijklnoprstuvx in both cases and digits",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::NoLeadingDigit,
+        names: &["--no-leading-digit"],
+        value: None,
+        help: &["Don't start a password with a digit"],
+    },
+    OptionSpec {
+        opt: Opt::NoLeadingSymbol,
+        names: &["--no-leading-symbol"],
+        value: None,
+        help: &["Don't start a password with a symbol or other non-alphanumeric character"],
+    },
+    OptionSpec {
+        opt: Opt::NoEdgeSymbols,
+        names: &["--no-edge-symbols"],
+        value: None,
+        help: &[
+            "Don't start or end a password with a symbol: symbols only appear",
+            "inside it, so trailing punctuation isn't lost when pasting",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::NoRepeat,
+        names: &["--no-repeat"],
+        value: None,
+        help: &["Never put the same character twice in a row, as in \"aa\" or \"77\""],
+    },
+    OptionSpec {
+        opt: Opt::NoSequences,
+        names: &["--no-sequences"],
+        value: Some("[=n]"),
+        help: &[
+            "Generate a password again if it contains n (default 3) letters or digits",
+            "in alphabetical order, either way, such as \"abc\", \"XyZ\" or \"321\"",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::NoKeyboardWalks,
+        names: &["--no-keyboard-walks"],
+        value: None,
+        help: &[
+            "Generate a password again if it contains 3 or more characters on",
+            "neighbouring QWERTY keys in one direction, such as \"qwe\", \"1qaz\" or \"!@#\"",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::NoDictionaryWords,
+        names: &["--no-dictionary-words"],
+        value: Some("[=n]"),
+        help: &[
+            "Generate a password again if it contains an English word of n (default 4)",
+            "or more letters in any case, such as \"pass\" or \"LoVe\"",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::NoProfanity,
+        names: &["--no-profanity"],
+        value: None,
+        help: &[
+            "Generate a password again if it contains an offensive word from a",
+            "built-in English, German, Spanish, French, Italian and Russian list,",
+            "also spelled with look-alike digits or symbols (5 for s); unlike -v,",
+            "memorable passwords keep their vowels",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::ProfanityFile,
+        names: &["--profanity-file"],
+        value: Some("<file>"),
+        help: &[
+            "Add words to --no-profanity (implied), one per line; lines starting",
+            "with # are comments",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Case,
+        names: &["--case"],
+        value: Some("<lower|upper|title|random>"),
+        help: &[
+            "Change the case of letters as the last step: lower, upper, title (the",
+            "first letter of each syllable of a pronounceable password, of each",
+            "word with --memorable-model markov, otherwise of the whole password)",
+            "or random (a coin flip per letter); -A and -c must agree with it",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::HomoglyphStrict,
+        names: &["--homoglyph-strict"],
+        value: None,
+        help: &[
+            "Avoid look-alikes on printouts: drop characters like 0/O/D/Q and 9/g/q",
+            "and never generate sequences like rn (m), vv (w) or cl (d)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::HomoglyphFile,
+        names: &["--homoglyph-file"],
+        value: Some("<file>"),
+        help: &[
+            "Add look-alike groups to --homoglyph-strict (implied), one group of",
+            "space-separated spellings per line",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::RemoveChars,
+        names: &["-r", "--remove-chars"],
+        value: Some("<chars>"),
+        help: &[
+            "Remove characters from the set of characters to generate passwords",
+            "(may be given several times; the sets are combined); ranges such as",
+            "a-f0-3 are expanded, and a leading '-' or \\- is a literal hyphen",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::IncludeOnly,
+        names: &["--include-only"],
+        value: Some("<chars>"),
+        help: &[
+            "Use exactly these characters (ranges as with -r) instead of the usual",
+            "classes; -B, -v and -r still remove from them, and a required class",
+            "missing from the list is skipped with a warning",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::RequireChars,
+        names: &["--require-chars"],
+        value: Some("<chars>"),
+        help: &[
+            "Put each of these characters (ranges as with -r) in every password at",
+            "least once, even symbols without -y; characters that -B, -r, --url-safe,",
+            "--include-only and similar options exclude are an error",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Charset,
+        names: &["--charset"],
+        value: Some("<hex|base32|base58|alnum|printable>"),
+        help: &[
+            "Generate random (-s) tokens from a fixed alphabet; -B, -v and -r",
+            "still remove from it, and -c, -n or -y are rejected if it lacks the class",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Preset,
+        names: &["--preset"],
+        value: Some("<wpa>"),
+        help: &[
+            "Use ready-made settings: wpa makes random (-s) WPA2-PSK keys of printable",
+            "ASCII without space and \", 8 to 63 characters long (20 by default)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::CharsetFile,
+        names: &["--charset-file"],
+        value: Some("<file>"),
+        help: &[
+            "Like --include-only, but read the characters from a UTF-8 file; every",
+            "distinct non-whitespace character in it is used once",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Secure,
+        names: &["-s", "--secure"],
+        value: None,
+        help: &["Generate completely random passwords"],
+    },
+    OptionSpec {
+        opt: Opt::MemorableModel,
+        names: &["--memorable-model"],
+        value: Some("<phoneme|markov>"),
+        help: &[
+            "How memorable passwords are built: from pwgen's phoneme table",
+            "(the default) or letter by letter from the trigram frequencies of",
+            "English words (markov)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::SyllableFile,
+        names: &["--syllable-file"],
+        value: Some("<file>"),
+        help: &[
+            "Build memorable passwords from the syllables in a UTF-8 file, one per",
+            "line; syllables with removed characters are skipped, and one that",
+            "doesn't fit is redrawn from those that do, or cut short if none fits",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Syllables,
+        names: &["--syllables"],
+        value: Some("<n>"),
+        help: &[
+            "Build memorable passwords from exactly n syllables instead of pw_length",
+            "characters: a consonant and a vowel from pwgen's phoneme table, or lines",
+            "of --syllable-file; the length varies with the syllables drawn",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Leet,
+        names: &["--leet"],
+        value: Some("[=n]"),
+        help: &[
+            "Replace up to n (default 2) random letters of a memorable password with",
+            "look-alike digits or symbols (e 3, a @, s $, o 0); only characters the",
+            "other options allow are used, and they count toward -n and -y",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Words,
+        names: &["--words"],
+        value: Some("<n>"),
+        help: &[
+            "Generate passphrases of n words from the embedded wordlist instead of",
+            "passwords; -N or num_pw sets how many, and they are printed one per line.",
+            "The English list has 1650 words, about 10.7 bits per word",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::WordsAcrostic,
+        names: &["--words-acrostic"],
+        value: Some("<letters>"),
+        help: &[
+            "Generate passphrases whose words start with these letters in order,",
+            "each word drawn among the wordlist entries with that initial",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Separator,
+        names: &["--separator"],
+        value: Some("<text>"),
+        help: &["Put text between the words of a passphrase (default \"-\")"],
+    },
+    OptionSpec {
+        opt: Opt::CapitalizeWords,
+        names: &["--capitalize-words"],
+        value: None,
+        help: &["Capitalize the first letter of every passphrase word"],
+    },
+    OptionSpec {
+        opt: Opt::InjectDigit,
+        names: &["--inject-digit"],
+        value: None,
+        help: &[
+            "Insert a random digit into the passphrase at a random word boundary,",
+            "including its start and end",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::InjectSymbol,
+        names: &["--inject-symbol"],
+        value: None,
+        help: &["Insert a random symbol the same way (--shell-safe narrows the symbols)"],
+    },
+    OptionSpec {
+        opt: Opt::Wordlist,
+        names: &["--wordlist"],
+        value: Some("<file>"),
+        help: &[
+            "Take passphrase words from a UTF-8 file, one per line, or from stdin",
+            "with -; blank lines and duplicates are dropped. Implies --words 5",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Lang,
+        names: &["--lang"],
+        value: Some("<en|de|es|fr|ru>"),
+        help: &[
+            "Take passphrase words from the embedded wordlist in this language",
+            "(default en). The lists have 1131-1650 words, 10.1-10.7 bits per word.",
+            "Implies --words 5",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Ambiguous,
+        names: &["-B", "--ambiguous"],
+        value: None,
+        help: &[
+            "Don't include ambiguous characters in the password",
+            "(with -y this also drops easily confused symbols such as ' ` | , .)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::AmbiguousChars,
+        names: &["--ambiguous-chars"],
+        value: Some("<chars>"),
+        help: &[
+            "Use <chars> as the ambiguous set for -B (implies -B);",
+            "prefix with + to add to the default set instead of replacing it",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Sha1,
+        names: &["-H", "--sha1"],
+        value: Some("path/to/file[#seed]"),
+        help: &["Use sha1 hash of given file as a (not so) random generator"],
+    },
+    OptionSpec {
+        opt: Opt::RandomSource,
+        names: &["--random-source"],
+        value: Some("<file>"),
+        help: &["Read random data from <file> (same as --rng=file:<file>)"],
+    },
+    OptionSpec {
+        opt: Opt::Rng,
+        names: &["--rng"],
+        value: Some("<auto|getrandom|urandom|file:PATH>"),
+        help: &["Select the random source; auto tries getrandom, then /dev/urandom"],
+    },
+    OptionSpec {
+        opt: Opt::Seed,
+        names: &["--seed"],
+        value: Some("<number>"),
+        help: &["Generate reproducible (and therefore insecure) output from a fixed seed"],
+    },
+    OptionSpec {
+        opt: Opt::FastRng,
+        names: &["--fast-rng"],
+        value: None,
+        help: &["Expand a seed from the random source with ChaCha20 (for large batches)"],
+    },
+    OptionSpec {
+        opt: Opt::Verbose,
+        names: &["--verbose"],
+        value: None,
+        help: &["Report diagnostic details such as the random source on stderr"],
+    },
+    OptionSpec {
+        opt: Opt::Output,
+        names: &["-o", "--output"],
+        value: Some("<file>"),
+        help: &[
+            "Write the passwords to <file> (created with mode 0600) instead of stdout,",
+            "one per line as in a pipe unless -C or --per-line is given",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Force,
+        names: &["--force"],
+        value: None,
+        help: &["Allow --output to overwrite an existing file"],
+    },
+    OptionSpec {
+        opt: Opt::EncryptTo,
+        names: &["--encrypt-to"],
+        value: Some("<recipient>"),
+        help: &["Encrypt the --output file to an age recipient (age1...) or a gpg key"],
+    },
+    OptionSpec {
+        opt: Opt::Copy,
+        names: &["--copy"],
+        value: None,
+        help: &["Copy the password to the clipboard instead of printing it"],
+    },
+    OptionSpec {
+        opt: Opt::Interactive,
+        names: &["-i", "--interactive"],
+        value: None,
+        help: &[
+            "Show one password at a time on the terminal: r for another, y or Enter",
+            "to print it to stdout, q or Esc to quit with status 1",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Mnemonic,
+        names: &["--mnemonic"],
+        value: None,
+        help: &[
+            "Print a sentence next to each password to help remember it, one word per",
+            "character: 'a' alpha or apple, '3' three or tree, '#' hash; one per line.",
+            "With --format json or jsonl it is the \"mnemonic\" field of each password",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Phonetic,
+        names: &["--phonetic"],
+        value: None,
+        help: &[
+            "After each password print it spelled out for dictation: NATO words for",
+            "letters (capitals in CAPS), digits as words, symbols by name; one per line",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Annotate,
+        names: &["--annotate"],
+        value: None,
+        help: &[
+            "Under each password with look-alike characters note where they are, as in",
+            "'note: position 3 is capital O (not zero)'; one per line, not in --porcelain",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Qr,
+        names: &["--qr"],
+        value: None,
+        help: &[
+            "Print the password and then a QR code of it, for moving it to a phone;",
+            "needs a single password or --pick",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::QrFormat,
+        names: &["--qr-format"],
+        value: Some("<ansi|utf8|png:PATH>"),
+        help: &[
+            "How to draw the QR code: ansi (the default) half blocks in black and",
+            "white, utf8 half blocks in the terminal's colors (light text on dark),",
+            "or png:PATH to write a PNG image readable only by you",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::QrOnly,
+        names: &["--qr-only"],
+        value: None,
+        help: &["Like --qr, but don't print the password itself"],
+    },
+    OptionSpec {
+        opt: Opt::Pick,
+        names: &["--pick"],
+        value: Some("<n>"),
+        help: &["Print only the n-th password of the batch (with --copy, copy it)"],
+    },
+    OptionSpec {
+        opt: Opt::ClearAfter,
+        names: &["--clear-after"],
+        value: Some("<seconds>"),
+        help: &[
+            "Clear the clipboard after <seconds> if it still holds the password",
+            "(default 45, 0 to keep it)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::LockMemory,
+        names: &["--lock-memory"],
+        value: None,
+        help: &["Lock the generated passwords in RAM (mlock) so they are never swapped"],
+    },
+    OptionSpec {
+        opt: Opt::NoConfig,
+        names: &["--no-config"],
+        value: None,
+        help: &[
+            "Ignore the settings in $XDG_CONFIG_HOME/pwgen/config.toml",
+            "(%APPDATA%\\pwgen\\config.toml on Windows)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::SelfTest,
+        names: &["--self-test"],
+        value: None,
+        help: &[
+            "Check the charset, class requirements, randomness and uniqueness",
+            "of a large internal batch; exit non-zero on any failure",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::DryRun,
+        names: &["--dry-run"],
+        value: None,
+        help: &[
+            "Print the resolved settings, the character set by class and the",
+            "entropy per password, then exit without generating anything",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Help,
+        names: &["-h", "--help"],
+        value: None,
+        help: &["Print a help message"],
+    },
+    OptionSpec {
+        opt: Opt::Columns,
+        names: &["-C"],
+        value: None,
+        help: &[
+            "Print the generated passwords in columns",
+            "(the default when stdout is a terminal; otherwise a single",
+            "password is printed unless num_pw is given)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::FixedColumns,
+        names: &["--columns"],
+        value: Some("num"),
+        help: &[
+            "Print the passwords in num columns; in a terminal the count is",
+            "reduced so that lines still fit its width",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::PerLine,
+        names: &["--per-line"],
+        value: Some("num"),
+        help: &[
+            "Print exactly num passwords on each line, in the order they are",
+            "generated, whatever the terminal width",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Numbered,
+        names: &["--numbered"],
+        value: None,
+        help: &["Prefix each password with its number, counting from 1"],
+    },
+    OptionSpec {
+        opt: Opt::Porcelain,
+        names: &["--porcelain"],
+        value: None,
+        help: &[
+            "Stable output for scripts: exactly num_pw lines, one password per line,",
+            "no padding or trailing spaces, each line ending in a newline; nothing",
+            "else is ever written to stdout, diagnostics go to stderr",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Unique,
+        names: &["--unique"],
+        value: None,
+        help: &[
+            "Make every password in the batch different, generating replacements",
+            "for duplicates; an error if fewer than num_pw passwords are possible",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Sort,
+        names: &["--sort"],
+        value: None,
+        help: &["Print the batch in lexicographic order"],
+    },
+    OptionSpec {
+        opt: Opt::ShowEntropy,
+        names: &["--show-entropy"],
+        value: None,
+        help: &[
+            "Print the entropy of the passwords: a line after the batch, or next to",
+            "each password with --length-range (JSON and CSV always include it)",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Null,
+        names: &["-z", "--null"],
+        value: None,
+        help: &[
+            "End each password with a NUL byte instead of a newline, as find -print0",
+            "does, for xargs -0 and read -d ''; implies -1",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Format,
+        names: &["--format"],
+        value: Some("<text|json|jsonl|csv>"),
+        help: &[
+            "Output format: text (the default); json, a single document with",
+            "the effective settings and each password with its length, entropy",
+            "and mode; jsonl, one such password object per line, printed as",
+            "soon as it is generated; or csv, a table with the header",
+            "index,password,length,entropy_bits,mode, quoted as in RFC 4180",
+            "but with LF line endings; column options are ignored",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::CsvSafe,
+        names: &["--csv-safe"],
+        value: None,
+        help: &[
+            "With --format csv, put ' before passwords that start with =, +, - or @",
+            "so spreadsheets don't run them as formulas; remove it before use",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::Color,
+        names: &["--color"],
+        value: Some("<auto|always|never>"),
+        help: &[
+            "Color lowercase, uppercase, digits and symbols differently; auto (the",
+            "default) colors only a terminal; a non-empty NO_COLOR environment",
+            "variable turns color off even with always; --porcelain, -z, --format",
+            "and files are never colored",
+        ],
+    },
+    OptionSpec {
+        opt: Opt::SingleColumn,
+        names: &["-1"],
+        value: None,
+        help: &["Don't print the generated passwords in columns"],
+    },
+    OptionSpec {
+        opt: Opt::NoVowels,
+        names: &["-v", "--no-vowels"],
+        value: None,
+        help: &[
+            "Do not use any vowels so as to avoid accidental nasty words;",
+            "without -s, passwords are built from consonant pairs such as",
+            "\"br\", \"st\" and \"th\" to stay easy to transcribe",
+        ],
+    },
+];
+
+// Аргумент после разбора: опция с именем, как ее написали, и значением
+// (пустым у флагов) или позиционный аргумент
+#[derive(Debug, PartialEq)]
+pub enum Arg {
+    Option {
+        opt: Opt,
+        name: &'static str,
+        value: String,
+    },
+    Positional(String),
+}
+
+// Разбор по таблице, одинаковый для всех опций: --opt=value, --opt value,
+// -ovalue, -o value и группы коротких флагов, как в getopt: "-sy1B" - это
+// -s -y -1 -B, а флаг со значением забирает остаток, так что "-sBr0O1l" -
+// это -s -B -r0O1l
+pub fn parse(table: &'static [OptionSpec], args: &[String]) -> Result<Vec<Arg>, CliError> {
+    let mut parsed = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--") {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            let Some((spec, name)) = find(table, |candidate| candidate == name) else {
+                return Err(CliError::unknown_option(table, arg));
+            };
+            let value = match (spec.value, inline) {
+                (None, None) => String::new(),
+                (None, Some(_)) => return Err(CliError::unknown_option(table, arg)),
+                (Some(_), Some(value)) => value.to_string(),
+                (Some(_), None) if spec.optional_value() => String::new(),
+                (Some(_), None) => args.next().cloned().ok_or(CliError::MissingValue(name))?,
+            };
+            parsed.push(Arg::Option { opt: spec.opt, name, value });
+        } else if let Some(flags) = arg.strip_prefix('-') {
+            if flags.is_empty() {
+                return Err(CliError::unknown_option(table, arg));
+            }
+            for (pos, c) in flags.char_indices() {
+                let short = |candidate: &str| candidate.len() == 2 && candidate[1..].starts_with(c);
+                let Some((spec, name)) = find(table, short) else {
+                    if flags.len() == 1 {
+                        return Err(CliError::unknown_option(table, arg));
+                    }
+                    return Err(CliError::UnknownOption {
+                        option: format!("'{}' in {}", c, arg),
+                        suggestion: None,
+                    });
+                };
+                if spec.value.is_none() {
+                    parsed.push(Arg::Option { opt: spec.opt, name, value: String::new() });
+                    continue;
+                }
+                let rest = &flags[pos + c.len_utf8()..];
+                let value = if rest.is_empty() {
+                    args.next().cloned().ok_or(CliError::MissingValue(spec.long_name()))?
+                } else {
+                    rest.to_string()
+                };
+                parsed.push(Arg::Option { opt: spec.opt, name, value });
+                break;
+            }
+        } else {
+            parsed.push(Arg::Positional(arg.clone()));
+        }
+    }
+    Ok(parsed)
+}
+
+fn find(
+    table: &'static [OptionSpec],
+    matches: impl Fn(&str) -> bool,
+) -> Option<(&'static OptionSpec, &'static str)> {
+    table
+        .iter()
+        .find_map(|spec| spec.names.iter().copied().find(|name| matches(name)).map(|name| (spec, name)))
+}
+
+// Короткие опции со значением, которые забирают остаток группы: "-r, -o"
+pub fn short_value_names(table: &[OptionSpec]) -> Vec<&'static str> {
+    table
+        .iter()
+        .filter(|spec| spec.value.is_some())
+        .flat_map(|spec| spec.names.iter().copied())
+        .filter(|name| !name.starts_with("--"))
+        .collect()
+}
+
+// Первая строка справки по опции: "-r <chars> or --remove-chars=<chars>"
+pub fn synopsis(option: &OptionSpec) -> String {
+    let forms: Vec<String> = option
+        .names
+        .iter()
+        .map(|name| match option.value {
+            Some(value) if option.optional_value() => format!("{}{}", name, value),
+            Some(value) if name.starts_with("--") => format!("{}={}", name, value),
+            Some(value) => format!("{} {}", name, value),
+            None => name.to_string(),
+        })
+        .collect();
+    match forms.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+// Опция из таблицы самой pwgen, с именем до '='; у подкоманды ее может
+// не быть
+pub fn is_known(option: &str) -> bool {
+    let name = option.split_once('=').map_or(option, |(name, _)| name);
+    OPTIONS.iter().any(|spec| spec.names.contains(&name))
+}
+
+// Допустимое число опечаток в подсказке
+const MAX_DISTANCE: usize = 2;
+
+// Длинная опция, которую, вероятно, имели в виду: единственная, которая
+// начинается с введенного (--no-num), или ближайшая по расстоянию
+// Левенштейна не дальше MAX_DISTANCE (--sybols)
+pub fn suggest(table: &'static [OptionSpec], option: &str) -> Option<&'static str> {
+    if !option.starts_with("--") {
+        return None;
+    }
+    let name = option.split_once('=').map_or(option, |(name, _)| name);
+    let long_names = || {
+        table
+            .iter()
+            .flat_map(|option| option.names.iter().copied())
+            .filter(|candidate| candidate.starts_with("--"))
+    };
+
+    let mut prefixed = long_names().filter(|candidate| candidate.starts_with(name));
+    if let (Some(candidate), None) = (prefixed.next(), prefixed.next())
+        && name.len() > 3
+    {
+        return Some(candidate);
+    }
+
+    long_names()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        // Короткое имя вроде --qr иначе было бы "похоже" на что угодно, даже на --
+        .filter(|&(distance, candidate)| distance <= MAX_DISTANCE && distance < candidate.len() - 2)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("--sybols", "--symbols"), 1);
+    }
+
+    #[test]
+    fn test_suggest_near_miss() {
+        assert_eq!(suggest(OPTIONS, "--sybols"), Some("--symbols"));
+        assert_eq!(suggest(OPTIONS, "--no-numeral"), Some("--no-numerals"));
+        assert_eq!(suggest(OPTIONS, "--secrue"), Some("--secure"));
+        assert_eq!(suggest(OPTIONS, "--lenght=20"), Some("--length"));
+        assert_eq!(suggest(OPTIONS, "--colums"), Some("--columns"));
+    }
+
+    #[test]
+    fn test_suggest_prefix() {
+        assert_eq!(suggest(OPTIONS, "--no-num"), Some("--no-numerals"));
+        assert_eq!(suggest(OPTIONS, "--encr"), Some("--encrypt-to"));
+        // Начало нескольких опций - не подсказка
+        assert_eq!(suggest(OPTIONS, "--no-"), None);
+    }
+
+    #[test]
+    fn test_no_suggestion_for_garbage() {
+        assert_eq!(suggest(OPTIONS, "--bogus"), None);
+        assert_eq!(suggest(OPTIONS, "--frobnicate"), None);
+        assert_eq!(suggest(OPTIONS, "-x"), None);
+        assert_eq!(suggest(OPTIONS, "--"), None);
+    }
+
+    #[test]
+    fn test_synopsis() {
+        let find = |name: &str| OPTIONS.iter().find(|option| option.names.contains(&name)).unwrap();
+        assert_eq!(synopsis(find("-c")), "-c or --capitalize");
+        assert_eq!(synopsis(find("-r")), "-r <chars> or --remove-chars=<chars>");
+        assert_eq!(synopsis(find("-N")), "-N num, --num-passwords=num or --count=num");
+        assert_eq!(synopsis(find("--copy")), "--copy");
+        assert_eq!(synopsis(find("--seed")), "--seed=<number>");
+        assert_eq!(synopsis(find("--leet")), "--leet[=n]");
+    }
+
+    #[test]
+    fn test_hint() {
+        let find = |name: &str| OPTIONS.iter().find(|option| option.names.contains(&name)).unwrap();
+        assert_eq!(find("-c").hint(), None);
+        assert_eq!(find("-r").hint(), Some(ValueHint::Text));
+        assert_eq!(find("--columns").hint(), Some(ValueHint::Number));
+        assert_eq!(find("--clear-after").hint(), Some(ValueHint::Number));
+        assert_eq!(find("-o").hint(), Some(ValueHint::File));
+        assert_eq!(find("-H").hint(), Some(ValueHint::File));
+        // Необязательное значение не дополняется
+        assert_eq!(find("--leet").hint(), None);
+        assert!(matches!(find("--rng").hint(), Some(ValueHint::Choice(_))));
+        // Варианты --charset совпадают с алфавитами
+        let Some(ValueHint::Choice(choices)) = find("--charset").hint() else { panic!() };
+        assert!(choices.iter().eq(crate::CHARSET_PRESETS.iter().map(|(name, _)| name)));
+    }
+
+    #[test]
+    fn test_parse() -> Result<(), CliError> {
+        let args: Vec<String> = ["-sy1", "--rng=urandom", "--seed", "5", "-rabc", "12"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let option = |opt, name, value: &str| Arg::Option { opt, name, value: value.to_string() };
+        assert_eq!(
+            parse(OPTIONS, &args)?,
+            [
+                option(Opt::Secure, "-s", ""),
+                option(Opt::Symbols, "-y", ""),
+                option(Opt::SingleColumn, "-1", ""),
+                option(Opt::Rng, "--rng", "urandom"),
+                option(Opt::Seed, "--seed", "5"),
+                option(Opt::RemoveChars, "-r", "abc"),
+                Arg::Positional("12".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse_one = |arg: &str| parse(OPTIONS, &[arg.to_string()]);
+        assert_eq!(parse_one("-x"), Err(CliError::unknown_option(OPTIONS, "-x")));
+        assert_eq!(parse_one("-"), Err(CliError::unknown_option(OPTIONS, "-")));
+        // У флага не бывает значения
+        assert_eq!(parse_one("--symbols=1"), Err(CliError::unknown_option(OPTIONS, "--symbols=1")));
+        assert_eq!(
+            parse_one("-sx"),
+            Err(CliError::UnknownOption { option: "'x' in -sx".to_string(), suggestion: None })
+        );
+        assert_eq!(parse_one("-sN"), Err(CliError::MissingValue("--num-passwords")));
+        assert_eq!(parse_one("--count"), Err(CliError::MissingValue("--count")));
+    }
+
+    #[test]
+    fn test_parse_optional_value() -> Result<(), CliError> {
+        let args: Vec<String> = ["--leet", "12", "--leet=3"].iter().map(|s| s.to_string()).collect();
+        let option = |value: &str| Arg::Option { opt: Opt::Leet, name: "--leet", value: value.to_string() };
+        // Следующее слово остается позиционным аргументом
+        assert_eq!(
+            parse(OPTIONS, &args)?,
+            [option(""), Arg::Positional("12".to_string()), option("3")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let mut names: Vec<&str> = OPTIONS.iter().flat_map(|option| option.names.iter().copied()).collect();
+        let total = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), total);
+    }
+}
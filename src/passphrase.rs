@@ -0,0 +1,394 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::lang::Lang;
+use crate::options::{self, Arg, Opt, OptionSpec};
+use crate::rng::RngChoice;
+use crate::{CliError, Config, Format, RunError, join_names, parse_count, wordlist};
+
+pub const DEFAULT_WORDS: usize = 5;
+pub const MAX_WORDS: usize = 64;
+const MAX_PHRASES: usize = 10_000;
+
+// Слова берутся из того же списка, что и у `pwgen --words`: встроенного
+// на языке --lang или из файла --wordlist. Фразы собираются и печатаются
+// тем же путем, что и у `pwgen --words`, через Config, так что опции
+// ниже значат здесь то же, что там.
+
+// Опции `pwgen passphrase`: для справки и дополнения в shell
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        opt: Opt::Lang,
+        names: &["--lang"],
+        value: Some("<en|de|es|fr|ru>"),
+        help: &["Take words from the embedded wordlist in this language (default en)"],
+    },
+    OptionSpec {
+        opt: Opt::Wordlist,
+        names: &["--wordlist"],
+        value: Some("<file>"),
+        help: &["Take words from a UTF-8 file, one per line, or from stdin with -"],
+    },
+    OptionSpec {
+        opt: Opt::Separator,
+        names: &["--separator"],
+        value: Some("<text>"),
+        help: &["Put text between the words (default \"-\")"],
+    },
+    OptionSpec {
+        opt: Opt::CapitalizeWords,
+        names: &["--capitalize-words"],
+        value: None,
+        help: &["Capitalize the first letter of every word"],
+    },
+    OptionSpec {
+        opt: Opt::InjectDigit,
+        names: &["--inject-digit"],
+        value: None,
+        help: &["Insert a random digit at a random word boundary, including the start and end"],
+    },
+    OptionSpec {
+        opt: Opt::InjectSymbol,
+        names: &["--inject-symbol"],
+        value: None,
+        help: &["Insert a random symbol the same way"],
+    },
+    OptionSpec {
+        opt: Opt::ShowEntropy,
+        names: &["--show-entropy"],
+        value: None,
+        help: &["Print the entropy of the passphrases after them"],
+    },
+    OptionSpec {
+        opt: Opt::Format,
+        names: &["--format"],
+        value: Some("<text|json|jsonl|csv>"),
+        help: &["Output format, as for pwgen --format"],
+    },
+    OptionSpec {
+        opt: Opt::Rng,
+        names: &["--rng"],
+        value: Some("<auto|getrandom|urandom|file:PATH>"),
+        help: &["Select the random source"],
+    },
+    OptionSpec {
+        opt: Opt::Seed,
+        names: &["--seed"],
+        value: Some("<number>"),
+        help: &["Generate reproducible (and therefore insecure) output from a fixed seed"],
+    },
+    OptionSpec {
+        opt: Opt::Help,
+        names: &["-h", "--help"],
+        value: None,
+        help: &["Print this help message"],
+    },
+];
+
+// Настройки `pwgen passphrase`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub words: usize,
+    pub count: usize,
+    pub seed: Option<u64>,
+    pub rng: RngChoice,
+    pub lang: Option<Lang>,
+    pub wordlist: Option<PathBuf>,
+    pub separator: Option<String>,
+    pub capitalize_words: bool,
+    pub inject_digit: bool,
+    pub inject_symbol: bool,
+    pub show_entropy: bool,
+    pub format: Format,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            words: DEFAULT_WORDS,
+            count: 1,
+            seed: None,
+            rng: RngChoice::Auto,
+            lang: None,
+            wordlist: None,
+            separator: None,
+            capitalize_words: false,
+            inject_digit: false,
+            inject_symbol: false,
+            show_entropy: false,
+            format: Format::Text,
+            help: false,
+        }
+    }
+}
+
+// Аргументы после имени команды: [ num_words ] [ num_phrases ]
+pub fn parse_args(args: &[String]) -> Result<Options, CliError> {
+    let mut options = Options::default();
+    let mut positional_args = Vec::new();
+
+    for arg in options::parse(OPTIONS, args).map_err(|e| e.in_command("passphrase"))? {
+        let (opt, value) = match arg {
+            Arg::Option { opt, value, .. } => (opt, value),
+            Arg::Positional(arg) => {
+                positional_args.push(arg);
+                continue;
+            }
+        };
+        match opt {
+            Opt::Help => options.help = true,
+            Opt::Seed => match value.parse() {
+                Ok(seed) => options.seed = Some(seed),
+                Err(_) => return Err(CliError::invalid_number("seed", &value)),
+            },
+            Opt::Rng => {
+                options.rng = match RngChoice::parse(&value) {
+                    Some(choice) => choice,
+                    None => {
+                        return Err(CliError::InvalidValue {
+                            option: "--rng",
+                            value,
+                            expected: "auto, getrandom, urandom or file:PATH".to_string(),
+                        });
+                    }
+                };
+            }
+            Opt::Lang => {
+                let Some(lang) = Lang::parse(&value) else {
+                    let names: Vec<&str> = Lang::ALL.iter().map(|lang| lang.name()).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--lang",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                options.lang = Some(lang);
+            }
+            Opt::Wordlist => options.wordlist = Some(PathBuf::from(value)),
+            Opt::Separator => options.separator = Some(value),
+            Opt::CapitalizeWords => options.capitalize_words = true,
+            Opt::InjectDigit => options.inject_digit = true,
+            Opt::InjectSymbol => options.inject_symbol = true,
+            Opt::ShowEntropy => options.show_entropy = true,
+            Opt::Format => {
+                let Some(format) = Format::parse(&value) else {
+                    let names: Vec<&str> = Format::ALL.iter().map(|format| format.name()).collect();
+                    return Err(CliError::InvalidValue {
+                        option: "--format",
+                        value,
+                        expected: join_names(&names),
+                    });
+                };
+                options.format = format;
+            }
+            // Других опций в OPTIONS нет
+            _ => unreachable!("{:?} is not a passphrase option", opt),
+        }
+    }
+
+    if options.seed.is_some() && options.rng != RngChoice::Auto {
+        return Err(CliError::ConflictingFlags(
+            "--rng cannot be used with --seed: both select the random source".to_string(),
+        ));
+    }
+    if options.lang.is_some() && options.wordlist.is_some() {
+        return Err(CliError::ConflictingFlags(
+            "--lang selects an embedded wordlist and cannot be used with --wordlist".to_string(),
+        ));
+    }
+    if positional_args.len() > 2 {
+        return Err(CliError::TooManyArguments);
+    }
+    if let Some(arg) = positional_args.first() {
+        options.words = parse_count(arg, "number of words", MAX_WORDS)?;
+    }
+    if let Some(arg) = positional_args.get(1) {
+        options.count = parse_count(arg, "number of passphrases", MAX_PHRASES)?;
+    }
+    Ok(options)
+}
+
+// Настройки `pwgen --words` с теми же словами, разделителем и выводом
+pub fn config(options: &Options) -> Result<Config, RunError> {
+    let mut config = Config {
+        words: Some(options.words),
+        num_pw: options.count,
+        num_pw_given: true,
+        lang: options.lang,
+        seed: options.seed,
+        rng: options.rng.clone(),
+        separator: options.separator.clone(),
+        capitalize_words: options.capitalize_words,
+        inject_digit: options.inject_digit,
+        inject_symbol: options.inject_symbol,
+        show_entropy: options.show_entropy,
+        format: options.format,
+        columns: false,
+        ..Config::default()
+    };
+    if let Some(path) = &options.wordlist {
+        config.wordlist = Some(wordlist::load(path, &config)?);
+    }
+    Ok(config)
+}
+
+pub fn print_help() -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "Usage: pwgen passphrase [ OPTIONS ] [ num_words ] [ num_phrases ]")?;
+    writeln!(out)?;
+    writeln!(out, "Generate passphrases of num_words words from a wordlist joined by '-'.")?;
+    writeln!(
+        out,
+        "num_words defaults to {} (at most {}), num_phrases to 1 (at most {}).",
+        DEFAULT_WORDS, MAX_WORDS, MAX_PHRASES
+    )?;
+    writeln!(out)?;
+    writeln!(out, "Options:")?;
+    for option in OPTIONS {
+        writeln!(out, "  {}", options::synopsis(option))?;
+        for line in option.help {
+            writeln!(out, "    {}", line)?;
+        }
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_passwords;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&[]).unwrap(), Options::default());
+
+        let options = parse_args(&args(&["4", "3", "--seed=7"])).unwrap();
+        assert_eq!((options.words, options.count, options.seed), (4, 3, Some(7)));
+        assert!(parse_args(&args(&["--help"])).unwrap().help);
+
+        assert!(matches!(
+            parse_args(&args(&["0"])),
+            Err(CliError::InvalidNumber { what: "number of words", .. })
+        ));
+        assert_eq!(parse_args(&args(&["1", "2", "3"])), Err(CliError::TooManyArguments));
+        assert!(matches!(
+            parse_args(&args(&["--seed=1", "--rng=urandom"])),
+            Err(CliError::ConflictingFlags(_))
+        ));
+        // Опции самой pwgen не опечатки: подсказки по ним нет
+        let unsupported = |option: &str| CliError::UnsupportedOption {
+            option: option.to_string(),
+            command: "passphrase",
+        };
+        assert_eq!(parse_args(&args(&["-y"])), Err(unsupported("-y")));
+        assert_eq!(parse_args(&args(&["--columns"])), Err(unsupported("--columns")));
+        assert_eq!(parse_args(&args(&["--bits=60"])), Err(unsupported("--bits")));
+        assert_eq!(
+            parse_args(&args(&["--columns"])).unwrap_err().to_string(),
+            "--columns is not supported by pwgen passphrase"
+        );
+        // Опечатку подсказывают только по опциям подкоманды
+        assert_eq!(
+            parse_args(&args(&["--lnag"])),
+            Err(CliError::UnknownOption {
+                option: "--lnag".to_string(),
+                suggestion: Some("--lang"),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["--sybols"])),
+            Err(CliError::UnknownOption {
+                option: "--sybols".to_string(),
+                suggestion: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_style() -> Result<(), RunError> {
+        let options = parse_args(&args(&[
+            "--separator=_",
+            "--capitalize-words",
+            "--inject-digit",
+            "--inject-symbol",
+            "--show-entropy",
+            "--format",
+            "json",
+        ]))
+        .unwrap();
+        let config = config(&options)?;
+        assert_eq!(config.separator.as_deref(), Some("_"));
+        assert!(config.capitalize_words && config.inject_digit && config.inject_symbol && config.show_entropy);
+        assert_eq!(config.format, Format::Json);
+        assert!(matches!(
+            parse_args(&args(&["--format", "xml"])),
+            Err(CliError::InvalidValue { option: "--format", .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_wordlist() {
+        let options = parse_args(&args(&["--lang", "de", "3"])).unwrap();
+        assert_eq!((options.lang, options.words), (Some(Lang::De), 3));
+        let options = parse_args(&args(&["--wordlist=words.txt"])).unwrap();
+        assert_eq!(options.wordlist, Some(PathBuf::from("words.txt")));
+
+        assert!(matches!(
+            parse_args(&args(&["--lang", "xx"])),
+            Err(CliError::InvalidValue { option: "--lang", .. })
+        ));
+        assert!(matches!(
+            parse_args(&args(&["--lang", "en", "--wordlist", "words.txt"])),
+            Err(CliError::ConflictingFlags(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate() -> Result<(), RunError> {
+        let path = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/wordlist.txt"));
+        let list = wordlist::load(&path, &Config::default())?;
+        let options = Options {
+            words: 4,
+            count: 3,
+            seed: Some(1),
+            wordlist: Some(path),
+            ..Options::default()
+        };
+        let phrases = generate_passwords(&config(&options)?)?;
+        assert_eq!(phrases.len(), 3);
+        for phrase in phrases.iter() {
+            let words: Vec<&str> = phrase.split('-').collect();
+            assert_eq!(words.len(), 4, "{}", phrase);
+            for word in words {
+                assert!(list.iter().any(|w| w == word), "{}", phrase);
+            }
+        }
+
+        // С тем же seed - тот же результат
+        assert_eq!(generate_passwords(&config(&options)?)?, phrases);
+        Ok(())
+    }
+
+    #[cfg(feature = "wordlist-en")]
+    #[test]
+    fn test_generate_embedded() -> Result<(), RunError> {
+        // Без --lang и --wordlist - тот же встроенный список, что у --words
+        let list = wordlist::words(&Config::default())?;
+        let options = Options {
+            words: 6,
+            seed: Some(2),
+            ..Options::default()
+        };
+        let phrases = generate_passwords(&config(&options)?)?;
+        let phrase = phrases.iter().next().unwrap();
+        assert_eq!(phrase.split('-').count(), 6, "{}", phrase);
+        assert!(phrase.split('-').all(|word| list.iter().any(|w| w == word)), "{}", phrase);
+        Ok(())
+    }
+}
@@ -0,0 +1,543 @@
+use crate::rng::{EntropySource, random_index};
+use crate::{Case, Config, NUMERALS, RunError, Syllabled, UPPERCASE, build_charset, is_allowed, required_classes, secret};
+
+// Запоминаемые пароли как в pwgen (pw_phonemes.c): пароль собирается из
+// элементов - букв и сочетаний вроде ch, th, qu, ee - по очереди
+// согласный и гласный элемент. Заглавные, цифры и символы вставляются по
+// ходу сборки, а пароль без обязательного класса собирается заново.
+
+const CONSONANT: u8 = 1;
+const VOWEL: u8 = 2;
+// Сочетание двух букв (в pwgen - DIPTHONG)
+const DIPHTHONG: u8 = 4;
+// Элемент не может начинать пароль или слог после цифры
+const NOT_FIRST: u8 = 8;
+
+const ELEMENTS: &[(&str, u8)] = &[
+    ("a", VOWEL),
+    ("ae", VOWEL | DIPHTHONG),
+    ("ah", VOWEL | DIPHTHONG),
+    ("ai", VOWEL | DIPHTHONG),
+    ("b", CONSONANT),
+    ("c", CONSONANT),
+    ("ch", CONSONANT | DIPHTHONG),
+    ("d", CONSONANT),
+    ("e", VOWEL),
+    ("ee", VOWEL | DIPHTHONG),
+    ("ei", VOWEL | DIPHTHONG),
+    ("f", CONSONANT),
+    ("g", CONSONANT),
+    ("gh", CONSONANT | DIPHTHONG | NOT_FIRST),
+    ("h", CONSONANT),
+    ("i", VOWEL),
+    ("ie", VOWEL | DIPHTHONG),
+    ("j", CONSONANT),
+    ("k", CONSONANT),
+    ("l", CONSONANT),
+    ("m", CONSONANT),
+    ("n", CONSONANT),
+    ("ng", CONSONANT | DIPHTHONG | NOT_FIRST),
+    ("o", VOWEL),
+    ("oh", VOWEL | DIPHTHONG),
+    ("oo", VOWEL | DIPHTHONG),
+    ("p", CONSONANT),
+    ("ph", CONSONANT | DIPHTHONG),
+    ("qu", CONSONANT | DIPHTHONG),
+    ("r", CONSONANT),
+    ("s", CONSONANT),
+    ("sh", CONSONANT | DIPHTHONG),
+    ("t", CONSONANT),
+    ("th", CONSONANT | DIPHTHONG),
+    ("u", VOWEL),
+    ("v", CONSONANT),
+    ("w", CONSONANT),
+    ("x", CONSONANT),
+    ("y", CONSONANT),
+    ("z", CONSONANT),
+];
+
+// Вероятности из pwgen, в десятых: заглавная у согласного или первого
+// элемента, цифра и символ после элемента, гласный после одиночного гласного
+const UPPER_CHANCE: usize = 2;
+const DIGIT_CHANCE: usize = 3;
+const SYMBOL_CHANCE: usize = 2;
+const VOWEL_AGAIN_CHANCE: usize = 4;
+
+// Сколько раз собирать пароль заново, пока в нем не окажутся все
+// обязательные классы. Короткому паролю их может не хватить никогда, и
+// тогда недостающее доставит apply_requirements.
+const MAX_ATTEMPTS: usize = 100;
+
+struct Table {
+    // Элементы, все буквы которых допустимы
+    elements: Vec<(Vec<char>, u8)>,
+    // Допустимые символы обязательных классов; пустой - класс не нужен
+    uppercase: Vec<char>,
+    digits: Vec<char>,
+    symbols: Vec<char>,
+}
+
+impl Table {
+    fn new(config: &Config) -> Self {
+        // С --case upper элементы сразу пишутся заглавными
+        let upper = config.case == Some(Case::Upper);
+        let elements = ELEMENTS
+            .iter()
+            .map(|&(element, flags)| {
+                let spelling = element.chars().map(|c| if upper { c.to_ascii_uppercase() } else { c });
+                (spelling.collect::<Vec<char>>(), flags)
+            })
+            .filter(|(spelling, _)| spelling.iter().all(|&c| is_allowed(c, config)))
+            .collect();
+
+        let charset = build_charset(config);
+        let mut table = Self {
+            elements,
+            uppercase: Vec::new(),
+            digits: Vec::new(),
+            symbols: Vec::new(),
+        };
+        for (_, class, allowed) in required_classes(config, &charset) {
+            if class == UPPERCASE {
+                table.uppercase = allowed;
+            } else if class == NUMERALS {
+                table.digits = allowed;
+            } else {
+                table.symbols = allowed;
+            }
+        }
+        table
+    }
+
+    // Элементы, которые могут идти следующими
+    fn candidates(&self, state: State, remaining: usize) -> Vec<&(Vec<char>, u8)> {
+        self.elements
+            .iter()
+            .filter(|(spelling, flags)| {
+                flags & state.should_be != 0
+                    && !(state.first && flags & NOT_FIRST != 0)
+                    && !(state.after_vowel && flags & VOWEL != 0 && flags & DIPHTHONG != 0)
+                    && spelling.len() <= remaining
+            })
+            .collect()
+    }
+
+    fn has(&self, kind: u8) -> bool {
+        self.elements.iter().any(|(spelling, flags)| flags & kind != 0 && spelling.len() == 1)
+    }
+
+    // Есть ли в пароле все обязательные классы
+    fn complete(&self, password: &[char]) -> bool {
+        [&self.uppercase, &self.digits, &self.symbols]
+            .iter()
+            .all(|class| class.is_empty() || password.iter().any(|c| class.contains(c)))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct State {
+    should_be: u8,
+    after_vowel: bool,
+    first: bool,
+}
+
+impl State {
+    const ALL: [State; 8] = {
+        let mut all = [State { should_be: CONSONANT, after_vowel: false, first: false }; 8];
+        let mut i = 0;
+        while i < 8 {
+            all[i] = State {
+                should_be: if i & 4 != 0 { VOWEL } else { CONSONANT },
+                after_vowel: i & 2 != 0,
+                first: i & 1 != 0,
+            };
+            i += 1;
+        }
+        all
+    };
+
+    fn index(self) -> usize {
+        usize::from(self.should_be == VOWEL) * 4 + usize::from(self.after_vowel) * 2 + usize::from(self.first)
+    }
+
+    fn start(vowel: bool) -> Self {
+        Self {
+            should_be: if vowel { VOWEL } else { CONSONANT },
+            after_vowel: false,
+            first: true,
+        }
+    }
+}
+
+// Нет ни одной одиночной согласной или гласной - пароля не собрать
+pub fn missing(config: &Config) -> Option<&'static str> {
+    let table = Table::new(config);
+    if !table.has(CONSONANT) {
+        Some("consonants")
+    } else if !table.has(VOWEL) {
+        Some("vowels")
+    } else {
+        None
+    }
+}
+
+// Буквы, которые могут попасть в пароль, для --dry-run
+pub fn letters(config: &Config) -> Vec<char> {
+    let table = Table::new(config);
+    let mut letters: Vec<char> = table.elements.iter().flat_map(|(spelling, _)| spelling.iter().cloned()).collect();
+    let upper: Vec<char> = letters.iter().map(|c| c.to_ascii_uppercase()).filter(|c| table.uppercase.contains(c)).collect();
+    letters.extend(upper);
+    letters.sort_unstable();
+    letters.dedup();
+    letters
+}
+
+// Слоги для --syllables: согласный элемент, который может начинать слог,
+// и гласный за ним - "ba", "thee", "quoh". Гласные не начинаются с h, u и
+// g, так что разные пары не дают одинаковых слогов.
+pub fn syllables(config: &Config) -> Vec<Vec<char>> {
+    let table = Table::new(config);
+    let vowels: Vec<&Vec<char>> =
+        table.elements.iter().filter(|(_, flags)| flags & VOWEL != 0).map(|(spelling, _)| spelling).collect();
+    table
+        .elements
+        .iter()
+        .filter(|(_, flags)| flags & CONSONANT != 0 && flags & NOT_FIRST == 0)
+        .flat_map(|(consonant, _)| vowels.iter().map(move |vowel| [&consonant[..], &vowel[..]].concat()))
+        .collect()
+}
+
+// Пароль и позиции, с которых начинаются его слоги, для --case title
+pub fn generate<R: EntropySource + ?Sized>(
+    length: usize,
+    config: &Config,
+    rng: &mut R,
+) -> Result<Syllabled, RunError> {
+    let table = Table::new(config);
+    let mut last: Option<Syllabled> = None;
+    for _ in 0..MAX_ATTEMPTS {
+        let Some((password, starts)) = attempt(length, &table, rng)? else {
+            continue;
+        };
+        if let Some((mut previous, _)) = last.take() {
+            secret::wipe_chars(&mut previous);
+        }
+        if table.complete(&password) {
+            return Ok((password, starts));
+        }
+        last = Some((password, starts));
+    }
+    last.ok_or_else(|| {
+        RunError::Generation(format!(
+            "cannot build a pronounceable password of {} characters from the remaining letters",
+            length
+        ))
+    })
+}
+
+// Одна попытка: None, если из оставшихся элементов не набрать длину.
+// Слог начинается с первого элемента пароля и после цифры, а дальше - с
+// согласной после гласной, если за ней снова гласная (ka-ro). Согласная
+// перед цифрой или в конце замыкает предыдущий слог (kar7).
+fn attempt<R: EntropySource + ?Sized>(
+    length: usize,
+    table: &Table,
+    rng: &mut R,
+) -> Result<Option<Syllabled>, RunError> {
+    let mut password: Vec<char> = Vec::with_capacity(length);
+    let mut starts = Vec::new();
+    // Согласная, которая начнет слог, если за ней будет гласная
+    let mut onset: Option<usize> = None;
+    let mut state = State::start(random_index(rng, 2)? == 1);
+
+    while password.len() < length {
+        let candidates = table.candidates(state, length - password.len());
+        if candidates.is_empty() {
+            secret::wipe_chars(&mut password);
+            return Ok(None);
+        }
+        let (spelling, flags) = candidates[random_index(rng, candidates.len())?];
+        let start = password.len();
+        if state.first {
+            starts.push(start);
+        } else if flags & VOWEL != 0 {
+            starts.extend(onset.take());
+        } else if state.after_vowel {
+            onset = Some(start);
+        }
+        password.extend(spelling);
+
+        if !table.uppercase.is_empty() && (state.first || flags & CONSONANT != 0) && random_index(rng, 10)? < UPPER_CHANCE {
+            let upper = password[start].to_ascii_uppercase();
+            if table.uppercase.contains(&upper) {
+                password[start] = upper;
+            }
+        }
+        if password.len() >= length {
+            break;
+        }
+
+        // Цифра начинает новый слог, как начало пароля
+        if !table.digits.is_empty() && !state.first && random_index(rng, 10)? < DIGIT_CHANCE {
+            password.push(table.digits[random_index(rng, table.digits.len())?]);
+            onset = None;
+            state = State::start(random_index(rng, 2)? == 1);
+            continue;
+        }
+        if !table.symbols.is_empty() && !state.first && random_index(rng, 10)? < SYMBOL_CHANCE {
+            password.push(table.symbols[random_index(rng, table.symbols.len())?]);
+        }
+
+        // После согласного - гласный. После гласного - согласный, но за
+        // одиночным гласным, перед которым не было гласного, может идти
+        // еще один.
+        let should_be = if state.should_be == CONSONANT {
+            VOWEL
+        } else if state.after_vowel || flags & DIPHTHONG != 0 || random_index(rng, 10)? >= VOWEL_AGAIN_CHANCE {
+            CONSONANT
+        } else {
+            VOWEL
+        };
+        state = State {
+            should_be,
+            after_vowel: flags & VOWEL != 0,
+            first: false,
+        };
+    }
+    Ok(Some((password, starts)))
+}
+
+// Оценка для --dry-run: энтропия пути генератора (выборов элементов,
+// заглавных, цифр и символов) без повторных попыток. Разные пути могут
+// дать один пароль (a + e и ae), так что паролей на деле меньше.
+pub fn entropy(length: usize, config: &Config) -> f64 {
+    let table = Table::new(config);
+    // bits[r][состояние] - энтропия оставшихся r символов
+    let mut bits: Vec<[f64; 8]> = vec![[0.0; 8]; length + 1];
+    for r in 1..=length {
+        for state in State::ALL {
+            let candidates = table.candidates(state, r);
+            if candidates.is_empty() {
+                continue;
+            }
+            let sum: f64 = candidates
+                .iter()
+                .map(|(spelling, flags)| element_bits(&table, &bits, state, spelling, *flags, r))
+                .sum();
+            bits[r][state.index()] = (candidates.len() as f64).log2() + sum / candidates.len() as f64;
+        }
+    }
+    restart_bits(&bits, length)
+}
+
+// Слог с начала: бросок за вид первого элемента и остаток
+fn restart_bits(bits: &[[f64; 8]], left: usize) -> f64 {
+    if left == 0 {
+        return 0.0;
+    }
+    1.0 + (bits[left][State::start(false).index()] + bits[left][State::start(true).index()]) / 2.0
+}
+
+// Все, что следует за выбором элемента: заглавная, цифра или символ и
+// остаток пароля
+fn element_bits(table: &Table, bits: &[[f64; 8]], state: State, spelling: &[char], flags: u8, r: usize) -> f64 {
+    let mut h = 0.0;
+    let upper = spelling[0].to_ascii_uppercase();
+    if (state.first || flags & CONSONANT != 0) && upper != spelling[0] && table.uppercase.contains(&upper) {
+        h += binary(UPPER_CHANCE);
+    }
+    let left = r - spelling.len();
+    if left == 0 {
+        return h;
+    }
+    let next = |left| next_bits(bits, state, flags, left);
+    let plain = if table.symbols.is_empty() || state.first {
+        next(left)
+    } else {
+        mix(SYMBOL_CHANCE, (table.symbols.len() as f64).log2() + next(left - 1), next(left))
+    };
+    h + if table.digits.is_empty() || state.first {
+        plain
+    } else {
+        mix(DIGIT_CHANCE, (table.digits.len() as f64).log2() + restart_bits(bits, left - 1), plain)
+    }
+}
+
+// Остаток из left символов после элемента с флагами flags
+fn next_bits(bits: &[[f64; 8]], state: State, flags: u8, left: usize) -> f64 {
+    if left == 0 {
+        return 0.0;
+    }
+    let after_vowel = flags & VOWEL != 0;
+    let next = |should_be| bits[left][State { should_be, after_vowel, first: false }.index()];
+    if state.should_be == CONSONANT {
+        next(VOWEL)
+    } else if state.after_vowel || flags & DIPHTHONG != 0 {
+        next(CONSONANT)
+    } else {
+        mix(10 - VOWEL_AGAIN_CHANCE, next(CONSONANT), next(VOWEL))
+    }
+}
+
+// Бросок с вероятностью tenths/10 и средняя энтропия двух исходов
+fn mix(tenths: usize, yes: f64, no: f64) -> f64 {
+    let p = tenths as f64 / 10.0;
+    binary(tenths) + p * yes + (1.0 - p) * no
+}
+
+// Энтропия броска с вероятностью tenths/10
+fn binary(tenths: usize) -> f64 {
+    let p = tenths as f64 / 10.0;
+    -p * p.log2() - (1.0 - p) * (1.0 - p).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+
+    fn memorable() -> Config {
+        Config {
+            pw_length: 12,
+            ..Config::default()
+        }
+    }
+
+    // Элементы пароля из одних строчных букв: разбор по таблице с
+    // проверкой правил перехода. Слог после цифры начинается заново.
+    fn check_elements(password: &str) {
+        let letters: String = password.to_ascii_lowercase();
+        for syllable in letters.split(|c: char| c.is_ascii_digit()) {
+            let syllable: String = syllable.chars().filter(char::is_ascii_alphabetic).collect();
+            assert!(parses(&syllable, State::start(false)) || parses(&syllable, State::start(true)), "{}", password);
+        }
+    }
+
+    // Можно ли получить строку из состояния state по правилам генератора
+    fn parses(rest: &str, state: State) -> bool {
+        if rest.is_empty() {
+            return true;
+        }
+        ELEMENTS.iter().any(|&(element, flags)| {
+            let fits = flags & state.should_be != 0
+                && !(state.first && flags & NOT_FIRST != 0)
+                && !(state.after_vowel && flags & VOWEL != 0 && flags & DIPHTHONG != 0);
+            if !fits || !rest.starts_with(element) {
+                return false;
+            }
+            let after_vowel = flags & VOWEL != 0;
+            let next = |should_be| State { should_be, after_vowel, first: false };
+            let rest = &rest[element.len()..];
+            if state.should_be == CONSONANT {
+                parses(rest, next(VOWEL))
+            } else if state.after_vowel || flags & DIPHTHONG != 0 {
+                parses(rest, next(CONSONANT))
+            } else {
+                parses(rest, next(CONSONANT)) || parses(rest, next(VOWEL))
+            }
+        })
+    }
+
+    #[test]
+    fn test_pronounceable() -> Result<(), RunError> {
+        let config = Config { symbols: true, ..memorable() };
+        let mut rng = ChaCha20Rng::seeded(569);
+        let digraphs = ["ch", "gh", "ng", "ph", "qu", "sh", "th"];
+        for _ in 0..2000 {
+            let (chars, starts) = generate(12, &config, &mut rng)?;
+            let password: String = chars.iter().collect();
+            assert_eq!(password.chars().count(), 12);
+            check_elements(&password);
+
+            // Слог начинается с буквы: в начале пароля и после каждой цифры
+            assert_eq!(starts.first(), Some(&0), "{}", password);
+            assert!(starts.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", starts);
+            for (i, pair) in chars.windows(2).enumerate() {
+                if pair[0].is_ascii_digit() {
+                    assert!(starts.contains(&(i + 1)), "{} {:?}", password, starts);
+                }
+            }
+            assert!(starts.iter().all(|&i| chars[i].is_ascii_alphabetic()), "{} {:?}", password, starts);
+
+            // В слоге три согласные подряд - только с сочетанием из
+            // таблицы, а три гласные - только с u из qu
+            let lower = password.to_ascii_lowercase();
+            for syllable in lower.split(|c: char| c.is_ascii_digit()) {
+                let letters: String = syllable.chars().filter(char::is_ascii_alphabetic).collect();
+                let chars: Vec<char> = letters.chars().collect();
+                for window in chars.windows(3) {
+                    let text: String = window.iter().collect();
+                    if window.iter().all(|c| !"aeiou".contains(*c)) {
+                        assert!(digraphs.iter().any(|d| text.contains(d)), "{}", password);
+                    }
+                    if window.iter().all(|c| "aeiou".contains(*c)) {
+                        assert!(letters.contains(&format!("q{}", text)), "{}", password);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_classes() -> Result<(), RunError> {
+        let config = Config { symbols: true, ..memorable() };
+        let mut rng = ChaCha20Rng::seeded(5690);
+        for _ in 0..500 {
+            let (password, _) = generate(12, &config, &mut rng)?;
+            // Обязательные классы стоят на своих местах без замен
+            assert!(password.iter().any(char::is_ascii_uppercase));
+            assert!(password.iter().any(char::is_ascii_digit));
+            assert!(password.iter().any(|&c| crate::in_class(crate::SYMBOLS, c)));
+            // Цифра идет только после элемента
+            assert!(!password.first().is_some_and(|c| c.is_ascii_digit()));
+        }
+
+        // -A, -0 и -B: только строчные буквы элементов без похожих символов
+        let config = Config {
+            no_capitalize: true,
+            no_numerals: true,
+            ambiguous: true,
+            ..memorable()
+        };
+        for _ in 0..500 {
+            let (password, _) = generate(12, &config, &mut rng)?;
+            assert!(password.iter().all(|&c| c.is_ascii_lowercase() && is_allowed(c, &config)));
+            check_elements(&password.iter().collect::<String>());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_removed_letters() -> Result<(), RunError> {
+        // Без h пропадают и ch, sh, th, ah и oh
+        let config = Config {
+            remove_chars: Some(vec!['h', 'e']),
+            ..memorable()
+        };
+        let mut rng = ChaCha20Rng::seeded(5691);
+        for _ in 0..300 {
+            let (password, _) = generate(10, &config, &mut rng)?;
+            assert!(!password.iter().any(|c| "heHE".contains(*c)));
+        }
+        let config = Config {
+            remove_chars: Some("aeiou".chars().collect()),
+            ..memorable()
+        };
+        assert_eq!(missing(&config), Some("vowels"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_entropy() {
+        // Одна буква: бит на вид элемента и выбор из одиночных букв этого вида
+        let config = Config { no_capitalize: true, no_numerals: true, ..memorable() };
+        let single = |kind: u8| ELEMENTS.iter().filter(|(e, f)| e.len() == 1 && f & kind != 0 && f & NOT_FIRST == 0).count();
+        let expected = 1.0 + ((single(CONSONANT) as f64).log2() + (single(VOWEL) as f64).log2()) / 2.0;
+        assert!((entropy(1, &config) - expected).abs() < 1e-9);
+        // Чем длиннее пароль, тем больше энтропия
+        let config = memorable();
+        assert!(entropy(12, &config) > entropy(8, &config));
+        assert!(entropy(8, &config) > 20.0);
+    }
+}
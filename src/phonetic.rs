@@ -0,0 +1,159 @@
+// `--phonetic`: пароль, прочитанный по буквам для диктовки по телефону.
+// Буква - слово фонетического алфавита NATO, заглавная - то же слово
+// прописными ("X-RAY"), цифра - ее название, символ - его название. Слово
+// на символ ровно одно и без пробелов, так что слова в строке идут через
+// пробел. Символы вне таблицы (из --charset-file, --prefix) остаются как есть.
+
+const TABLE: &[(char, &str)] = &[
+    ('a', "alfa"),
+    ('b', "bravo"),
+    ('c', "charlie"),
+    ('d', "delta"),
+    ('e', "echo"),
+    ('f', "foxtrot"),
+    ('g', "golf"),
+    ('h', "hotel"),
+    ('i', "india"),
+    ('j', "juliett"),
+    ('k', "kilo"),
+    ('l', "lima"),
+    ('m', "mike"),
+    ('n', "november"),
+    ('o', "oscar"),
+    ('p', "papa"),
+    ('q', "quebec"),
+    ('r', "romeo"),
+    ('s', "sierra"),
+    ('t', "tango"),
+    ('u', "uniform"),
+    ('v', "victor"),
+    ('w', "whiskey"),
+    ('x', "x-ray"),
+    ('y', "yankee"),
+    ('z', "zulu"),
+    ('0', "zero"),
+    ('1', "one"),
+    ('2', "two"),
+    ('3', "three"),
+    ('4', "four"),
+    ('5', "five"),
+    ('6', "six"),
+    ('7', "seven"),
+    ('8', "eight"),
+    ('9', "nine"),
+    ('!', "exclamation"),
+    ('"', "double-quote"),
+    ('#', "hash"),
+    ('$', "dollar"),
+    ('%', "percent"),
+    ('&', "ampersand"),
+    ('\'', "apostrophe"),
+    ('(', "open-paren"),
+    (')', "close-paren"),
+    ('*', "asterisk"),
+    ('+', "plus"),
+    (',', "comma"),
+    ('-', "dash"),
+    ('.', "period"),
+    ('/', "slash"),
+    (':', "colon"),
+    (';', "semicolon"),
+    ('<', "less-than"),
+    ('=', "equals"),
+    ('>', "greater-than"),
+    ('?', "question-mark"),
+    ('@', "at-sign"),
+    ('[', "open-bracket"),
+    ('\\', "backslash"),
+    (']', "close-bracket"),
+    ('^', "caret"),
+    ('_', "underscore"),
+    ('`', "backtick"),
+    ('{', "open-brace"),
+    ('|', "pipe"),
+    ('}', "close-brace"),
+    ('~', "tilde"),
+    (' ', "space"),
+];
+
+// Самое длинное слово таблицы с пробелом после него
+pub const MAX_WORD: usize = 14;
+
+fn word(c: char) -> Option<&'static str> {
+    let key = c.to_ascii_lowercase();
+    TABLE.iter().find(|(k, _)| *k == key).map(|(_, word)| *word)
+}
+
+// Слова пароля через пробел. Пишутся прямо в строку вывода, чтобы
+// пароль не оставался в отдельной строке, которую пришлось бы затирать.
+pub fn push_spelled(line: &mut Vec<u8>, password: &str) {
+    for (i, c) in password.chars().enumerate() {
+        if i > 0 {
+            line.push(b' ');
+        }
+        match word(c) {
+            Some(word) if c.is_ascii_uppercase() => {
+                line.extend(word.bytes().map(|b| b.to_ascii_uppercase()));
+            }
+            Some(word) => line.extend_from_slice(word.as_bytes()),
+            None => {
+                let mut buf = [0; 4];
+                line.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AMBIGUOUS_SYMBOLS, CHARSET_PRESETS, LOWERCASE, MOBILE_SYMBOLS, NO_SHIFT_SYMBOLS, NUMERALS, SHELL_SAFE_SYMBOLS,
+        SYMBOLS, UPPERCASE, URL_SAFE,
+    };
+
+    fn spelled(password: &str) -> String {
+        let mut line = Vec::new();
+        push_spelled(&mut line, password);
+        String::from_utf8(line).unwrap()
+    }
+
+    #[test]
+    fn test_table_covers_charsets() {
+        // Все наборы, из которых пароль может взять символы
+        let mut classes: Vec<&[u8]> = vec![
+            LOWERCASE,
+            UPPERCASE,
+            NUMERALS,
+            SYMBOLS,
+            SHELL_SAFE_SYMBOLS,
+            NO_SHIFT_SYMBOLS,
+            MOBILE_SYMBOLS,
+            AMBIGUOUS_SYMBOLS,
+            URL_SAFE,
+        ];
+        classes.extend(CHARSET_PRESETS.iter().map(|(_, chars)| *chars));
+        for c in classes.concat().into_iter().map(char::from) {
+            let word = word(c).unwrap_or_else(|| panic!("no word for {:?}", c));
+            assert!(!word.contains(' '), "{:?}", c);
+            if c.is_ascii_alphabetic() {
+                assert!(word.starts_with(c.to_ascii_lowercase()), "{:?}", c);
+            }
+        }
+        assert!(TABLE.iter().all(|(_, word)| word.len() < MAX_WORD));
+        // Слова не повторяются, иначе строку нельзя прочитать однозначно
+        let mut words: Vec<&str> = TABLE.iter().map(|(_, word)| *word).collect();
+        words.sort_unstable();
+        words.dedup();
+        assert_eq!(words.len(), TABLE.len());
+    }
+
+    #[test]
+    fn test_push_spelled() {
+        assert_eq!(spelled("hX3k"), "hotel X-RAY three kilo");
+        assert_eq!(spelled("a!@\"0O"), "alfa exclamation at-sign double-quote zero OSCAR");
+        // Символа нет в таблице - он остается сам собой
+        assert_eq!(spelled("ж1"), "ж one");
+        assert_eq!(spelled(""), "");
+    }
+}
@@ -0,0 +1,148 @@
+use std::io;
+
+use crate::rng::{EntropySource, random_index};
+use crate::sequence;
+
+// `--pin`: числовые PIN-коды. Цифры берутся равновероятно из всех десяти,
+// без apply_requirements и без правил запоминаемых паролей. Слабые коды,
+// которые перебирают первыми, отбрасываются и генерируются заново (см.
+// reject.rs): из одной цифры ("0000"), подряд идущие цифры ("1234",
+// "4321"), повтор пары ("121212") и самые частые PIN из таблицы.
+
+pub const DEFAULT_LENGTH: usize = 4;
+// Короче четырех цифр почти все коды - цепочки или повторы
+pub const MIN_LENGTH: usize = 4;
+
+// Двадцать самых частых четырехзначных PIN из утекших баз
+const COMMON: &[&str] = &[
+    "1234", "1111", "0000", "1212", "7777", "1004", "2000", "4444", "2222", "6969", "9999", "3333", "5555", "6666",
+    "1122", "1313", "8888", "4321", "2001", "2580",
+];
+
+// Чем код слаб, если он слаб
+pub fn weakness(pin: &str) -> Option<&'static str> {
+    let digits = pin.as_bytes();
+    if digits.len() < 2 {
+        return None;
+    }
+    if digits.iter().all(|&d| d == digits[0]) {
+        return Some("a single repeated digit");
+    }
+    if sequence::has_sequence(pin, digits.len()) {
+        return Some("consecutive digits");
+    }
+    if digits.iter().enumerate().all(|(i, &d)| d == digits[i % 2]) {
+        return Some("a repeated pair of digits");
+    }
+    if COMMON.contains(&pin) {
+        return Some("a common PIN");
+    }
+    None
+}
+
+// Сколько кодов длины length отбрасывается: все они - повторы пары,
+// цепочки или коды из таблицы, так что их можно перечислить
+pub fn weak_count(length: usize) -> usize {
+    let digit = |d: usize| char::from_digit(d as u32, 10).unwrap();
+    let mut weak: Vec<String> = Vec::new();
+    for pair in 0..100 {
+        weak.push((0..length).map(|i| digit(if i % 2 == 0 { pair / 10 } else { pair % 10 })).collect());
+    }
+    // Цепочки, которые помещаются в ряд 0-9
+    for first in 0..10 {
+        if first + length <= 10 {
+            weak.push((0..length).map(|i| digit(first + i)).collect());
+        }
+        if first + 1 >= length {
+            weak.push((0..length).map(|i| digit(first - i)).collect());
+        }
+    }
+    weak.extend(COMMON.iter().map(|pin| pin.to_string()));
+    weak.retain(|pin| pin.len() == length && weakness(pin).is_some());
+    weak.sort();
+    weak.dedup();
+    weak.len()
+}
+
+// Энтропия кода: равновероятный выбор из оставшихся 10^length - weak_count
+pub fn entropy(length: usize) -> f64 {
+    let total = 10f64.powi(length as i32);
+    length as f64 * 10f64.log2() + (1.0 - weak_count(length) as f64 / total).log2()
+}
+
+pub fn generate<R: EntropySource + ?Sized>(length: usize, rng: &mut R) -> io::Result<String> {
+    let mut pin = String::with_capacity(length);
+    for _ in 0..length {
+        pin.push(char::from(b'0' + random_index(rng, 10)? as u8));
+    }
+    Ok(pin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::chacha20::ChaCha20Rng;
+
+    #[test]
+    fn test_weakness() {
+        for pin in ["0000", "777777", "1234", "4321", "3456789", "987654", "1212", "909090", "1313", "2580", "6969", "12121"] {
+            assert!(weakness(pin).is_some(), "{}", pin);
+        }
+        for pin in ["1235", "9012", "1221", "123123", "12122", "2581", "0001", "7391"] {
+            assert_eq!(weakness(pin), None, "{}", pin);
+        }
+        assert_eq!(weakness("5555"), Some("a single repeated digit"));
+        assert_eq!(weakness("876543"), Some("consecutive digits"));
+        assert_eq!(weakness("474747"), Some("a repeated pair of digits"));
+        assert_eq!(weakness("2001"), Some("a common PIN"));
+    }
+
+    #[test]
+    fn test_weak_count() {
+        // Перебор всех кодов дает то же число
+        for length in 4..=5 {
+            let brute = (0..10usize.pow(length as u32))
+                .filter(|n| weakness(&format!("{:0width$}", n, width = length)).is_some())
+                .count();
+            assert_eq!(weak_count(length), brute, "{}", length);
+        }
+        // 90 пар из разных цифр, 10 одинаковых, 7 + 7 цепочек и 5 других кодов из таблицы
+        assert_eq!(weak_count(4), 119);
+        assert!((entropy(4) - (10_000f64 - 119.0).log2()).abs() < 1e-9);
+        assert!((entropy(100) - 100.0 * 10f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_generate() -> io::Result<()> {
+        let mut rng = ChaCha20Rng::seeded(585);
+        for length in [4, 6, 12] {
+            let pin = generate(length, &mut rng)?;
+            assert_eq!(pin.len(), length);
+            assert!(pin.bytes().all(|d| d.is_ascii_digit()), "{}", pin);
+        }
+        // Байт 250 и больше отбрасывается, а не смещает распределение к 0..5
+        let pin = generate(4, &mut Cursor::new([255u8, 251, 13, 250, 4, 99, 7]))?;
+        assert_eq!(pin, "3497");
+        Ok(())
+    }
+
+    #[test]
+    fn test_weak_sources() -> io::Result<()> {
+        // Источники, которые дают каждый вид слабого кода
+        let sources = [
+            (vec![7u8, 17, 27, 37], "7777"),
+            (vec![1, 2, 3, 4, 5, 6], "123456"),
+            (vec![9, 8, 7, 6], "9876"),
+            (vec![5, 8, 5, 8, 5, 8], "585858"),
+            (vec![2, 5, 8, 0], "2580"),
+        ];
+        for (bytes, expected) in sources {
+            let pin = generate(expected.len(), &mut Cursor::new(bytes))?;
+            assert_eq!(pin, expected);
+            assert!(weakness(&pin).is_some(), "{}", pin);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use std::io::{self, Write};
+
+use crate::secret;
+
+// PNG для `--qr-format png:PATH`: оттенки серого, 1 бит на пиксель,
+// 1 - белый. Данные идут в zlib без сжатия (блоки stored), так что не
+// нужен ни deflate, ни сторонняя библиотека: код QR и так маленький.
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Больше байтов в блоке stored не бывает
+const MAX_STORED: usize = 65535;
+
+// Картинка width x height; dark(x, y) - черный ли пиксель
+pub fn write<W: Write>(
+    out: &mut W,
+    width: usize,
+    height: usize,
+    dark: impl Fn(usize, usize) -> bool,
+) -> io::Result<()> {
+    out.write_all(SIGNATURE)?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Глубина 1, оттенки серого, deflate, стандартные фильтры, без чересстрочности
+    header.extend_from_slice(&[1, 0, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &header)?;
+
+    // Строки пикселей с байтом фильтра 0 в начале
+    let row_len = 1 + width.div_ceil(8);
+    let mut raw = vec![0; row_len * height];
+    for (y, row) in raw.chunks_exact_mut(row_len).enumerate() {
+        for x in 0..width {
+            if !dark(x, y) {
+                row[1 + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+    }
+    let mut data = Vec::with_capacity(raw.len() + raw.len() / MAX_STORED * 5 + 11);
+    // zlib: окно 32K, без словаря, проверочные биты заголовка
+    data.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = raw.chunks(MAX_STORED).peekable();
+    if blocks.peek().is_none() {
+        data.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        data.push(u8::from(blocks.peek().is_none()));
+        let len = block.len() as u16;
+        data.extend_from_slice(&len.to_le_bytes());
+        data.extend_from_slice(&(!len).to_le_bytes());
+        data.extend_from_slice(block);
+    }
+    data.extend_from_slice(&adler32(&raw).to_be_bytes());
+    secret::wipe(&mut raw);
+    let result = write_chunk(out, b"IDAT", &data);
+    secret::wipe(&mut data);
+    result?;
+    write_chunk(out, b"IEND", &[])?;
+    out.flush()
+}
+
+// Длина, тип, данные и CRC типа с данными
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    out.write_all(&crc32(&[kind.as_slice(), data]).to_be_bytes())
+}
+
+// CRC-32 из ISO 3309, побитно: картинка маленькая, таблица не нужна
+pub fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for &b in parts.iter().copied().flatten() {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // Читает PNG, который пишет write: проверяет сигнатуру, CRC, блоки
+    // stored и adler32; пиксели - true для черного
+    pub fn read(png: &[u8]) -> Result<Vec<Vec<bool>>, String> {
+        let mut rest = png.strip_prefix(SIGNATURE).ok_or("no PNG signature")?;
+        let (mut width, mut height) = (0, 0);
+        let mut zlib = Vec::new();
+        loop {
+            if rest.len() < 12 {
+                return Err("truncated chunk".to_string());
+            }
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+            let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            if crc != crc32(&[kind, data]) {
+                return Err(format!("bad CRC in {}", String::from_utf8_lossy(kind)));
+            }
+            match kind {
+                b"IHDR" => {
+                    width = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+                    height = u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize;
+                    if data[8..] != [1, 0, 0, 0, 0] {
+                        return Err("not a 1-bit grayscale image".to_string());
+                    }
+                }
+                b"IDAT" => zlib.extend_from_slice(data),
+                b"IEND" => break,
+                _ => {}
+            }
+            rest = &rest[12 + len..];
+        }
+
+        let mut raw = Vec::new();
+        let mut pos = 2;
+        loop {
+            let last = zlib[pos] & 1 == 1;
+            if zlib[pos] >> 1 != 0 {
+                return Err("not a stored block".to_string());
+            }
+            let len = u16::from_le_bytes([zlib[pos + 1], zlib[pos + 2]]);
+            if !len != u16::from_le_bytes([zlib[pos + 3], zlib[pos + 4]]) {
+                return Err("bad stored block length".to_string());
+            }
+            raw.extend_from_slice(&zlib[pos + 5..pos + 5 + len as usize]);
+            pos += 5 + len as usize;
+            if last {
+                break;
+            }
+        }
+        if zlib[pos..] != adler32(&raw).to_be_bytes() {
+            return Err("bad adler32".to_string());
+        }
+
+        let row_len = 1 + width.div_ceil(8);
+        if raw.len() != row_len * height {
+            return Err(format!("{} bytes of pixels for {}x{}", raw.len(), width, height));
+        }
+        Ok(raw
+            .chunks_exact(row_len)
+            .map(|row| (0..width).map(|x| row[1 + x / 8] & (0x80 >> (x % 8)) == 0).collect())
+            .collect())
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(&[b"IEND"]), 0xAE42_6082);
+        assert_eq!(crc32(&[b"123456789"]), 0xCBF4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Строки больше одного блока stored и ширина не кратна 8
+        for (width, height) in [(3, 2), (13, 7), (1000, 600), (0, 0)] {
+            let dark = |x: usize, y: usize| (x * 7 + y * 3).is_multiple_of(5);
+            let mut out = Vec::new();
+            write(&mut out, width, height, dark).unwrap();
+            let pixels = read(&out).unwrap();
+            assert_eq!(pixels.len(), height);
+            for (y, row) in pixels.iter().enumerate() {
+                assert_eq!(row.len(), width);
+                for (x, &pixel) in row.iter().enumerate() {
+                    assert_eq!(pixel, dark(x, y), "{} {}", x, y);
+                }
+            }
+        }
+    }
+}
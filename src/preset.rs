@@ -0,0 +1,72 @@
+use std::ops::RangeInclusive;
+
+// `--preset`: готовые настройки под конкретное применение. Пресет задает
+// режим -s, набор символов и допустимые длины; флаги, которые с ним
+// расходятся, отвергаются в check_conflicts. Новый пресет - это новый
+// вариант Preset и ветки в его методах.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    // Ключ WPA2-PSK: от 8 до 63 печатных символов ASCII (0x20-0x7E)
+    Wpa,
+}
+
+// Символы, которые интерфейсы некоторых роутеров портят: пробел и "
+const WPA_EXCLUDED: &[u8] = b" \"";
+
+impl Preset {
+    pub const ALL: &[Preset] = &[Preset::Wpa];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|preset| preset.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Wpa => "wpa",
+        }
+    }
+
+    // Строка Mode: в --dry-run
+    pub fn mode(self) -> &'static str {
+        match self {
+            Preset::Wpa => "secure (-s), WPA-PSK key (--preset wpa)",
+        }
+    }
+
+    pub fn charset(self) -> Vec<char> {
+        match self {
+            Preset::Wpa => (0x20..=0x7e).filter(|c| !WPA_EXCLUDED.contains(c)).map(char::from).collect(),
+        }
+    }
+
+    pub fn lengths(self) -> RangeInclusive<usize> {
+        match self {
+            Preset::Wpa => 8..=63,
+        }
+    }
+
+    // Длина, если ее не задали в том же слое, что и пресет
+    pub fn default_length(self) -> usize {
+        match self {
+            Preset::Wpa => 20,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wpa_charset() {
+        let charset = Preset::Wpa.charset();
+        // Из 95 печатных символов ASCII убраны только пробел и "
+        assert_eq!(charset.len(), 93);
+        let excluded: String = (' '..='~').filter(|c| !charset.contains(c)).collect();
+        assert_eq!(excluded, " \"");
+        assert!(charset.iter().all(|c| c.is_ascii_graphic()));
+        assert_eq!(Preset::parse("wpa"), Some(Preset::Wpa));
+        assert_eq!(Preset::parse("WPA"), None);
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{RunError, leet};
+
+// `--no-profanity`: пароль, в котором без учета регистра встречается корень
+// из списка грубых слов, генерируется заново. Корень находится и внутри
+// другого слова, и в написании с цифрами и символами из таблицы --leet
+// ("5h1t"). Запоминаемые пароли похожи на слова, поэтому это мягче, чем
+// -v, который ради того же убирает гласные совсем.
+//
+// Формат встроенного списка и файла --profanity-file: корень на строку;
+// пустые строки и строки, начинающиеся с #, пропускаются.
+const BUILTIN: &str = include_str!("profanity.txt");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blocklist {
+    // Корни в нижнем регистре
+    words: Vec<Vec<char>>,
+}
+
+impl Blocklist {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN).expect("builtin blocklist is valid")
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut words = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.contains(char::is_whitespace) {
+                return Err(format!("line {}: one word per line", n + 1));
+            }
+            words.push(line.to_lowercase().chars().collect());
+        }
+        words.sort_unstable();
+        words.dedup();
+        Ok(Self { words })
+    }
+
+    // Дополняет список корнями из файла --profanity-file
+    pub fn extend(&mut self, other: Blocklist) {
+        self.words.extend(other.words);
+        self.words.sort_unstable();
+        self.words.dedup();
+    }
+
+    pub fn matches(&self, password: &str) -> bool {
+        let mut chars: Vec<char> = password.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect();
+        let found = (0..chars.len()).any(|start| {
+            self.words.iter().any(|word| {
+                let rest = &chars[start..];
+                rest.len() >= word.len()
+                    && word.iter().zip(rest).all(|(&letter, &c)| c == letter || leet::looks_like(c, letter))
+            })
+        });
+        chars.fill('\0');
+        found
+    }
+}
+
+pub fn load(path: &Path) -> Result<Blocklist, RunError> {
+    let text = fs::read_to_string(path).map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => RunError::Settings(format!("{}: not valid UTF-8", path.display())),
+        _ => io::Error::new(e.kind(), format!("cannot read {}: {}", path.display(), e)).into(),
+    })?;
+    Blocklist::parse(&text).map_err(|e| RunError::Settings(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Blocklist {
+        load(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/profanity.txt"))).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let list = fixture();
+        assert_eq!(list.words, [vec!['b', 'a', 'd'], vec!['c', 'h'], vec!['l', 'o', 's', 't']]);
+        assert_eq!(Blocklist::parse("# comment\n\nBAD\nbad\n").unwrap().words, [vec!['b', 'a', 'd']]);
+        assert_eq!(Blocklist::parse("ok\ntwo words\n"), Err("line 2: one word per line".to_string()));
+        assert!(!Blocklist::builtin().words.is_empty());
+    }
+
+    #[test]
+    fn test_matches() {
+        let list = fixture();
+        for password in ["bad", "xBADx", "ooch", "ChOo", "B4D", "b@d", "8ad", "1ost", "l057"] {
+            assert!(list.matches(password), "{}", password);
+        }
+        // Чистые пароли проходят
+        for password in ["", "ba", "bed", "c-h", "cxh", "l0s", "lo5", "good"] {
+            assert!(!list.matches(password), "{}", password);
+        }
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut list = Blocklist::builtin();
+        assert!(!list.matches("xlostx"));
+        list.extend(fixture());
+        assert!(list.matches("xlostx") && list.matches("xL0STx"));
+        // Встроенные корни остаются
+        assert!(list.matches("5hit") && list.matches("ПИЗДа"));
+    }
+}
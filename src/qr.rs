@@ -0,0 +1,770 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::{png, secret, write_line};
+
+// `--qr`: QR-код пароля для переноса на телефон. Кодировщик свой, как
+// sha1 и chacha20: байтовый режим, уровень коррекции M (15% ошибок),
+// версии 1-40 - самая маленькая, в которую помещается пароль. Маска
+// выбирается по штрафам из ISO/IEC 18004. Матрица и все промежуточные
+// буферы затираются, в них пароль.
+
+// Значение --qr-format
+#[derive(Debug, Clone, PartialEq)]
+pub enum QrFormat {
+    // Полублоки с цветами ANSI: читается при любой теме терминала
+    Ansi,
+    // Полублоки без escape-последовательностей: светлые модули рисуются
+    // блоками, так что код виден на терминале со светлым текстом на темном фоне
+    Utf8,
+    // Файл PNG с правами 0600
+    Png(PathBuf),
+}
+
+impl QrFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ansi" => Some(Self::Ansi),
+            "utf8" => Some(Self::Utf8),
+            _ => value
+                .strip_prefix("png:")
+                .filter(|path| !path.is_empty())
+                .map(|path| Self::Png(PathBuf::from(path))),
+        }
+    }
+}
+
+// Светлая рамка вокруг кода в модулях, как требует стандарт
+pub const QUIET_ZONE: usize = 4;
+
+// Пикселей на модуль в PNG
+const PNG_SCALE: usize = 8;
+
+// Кодовых слов коррекции в блоке и число блоков для уровня M, по версиям 1-40
+const ECC_PER_BLOCK: [usize; 40] = [
+    10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+];
+const BLOCKS: [usize; 40] = [
+    1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35,
+    37, 38, 40, 43, 45, 47, 49,
+];
+
+// Биты уровня M в строке формата
+const LEVEL_M: u32 = 0b00;
+
+pub struct QrCode {
+    size: usize,
+    // 1 - темный модуль, по строкам
+    modules: Vec<u8>,
+    // Служебные модули: искатели, синхронизация, формат; маска их не трогает
+    function: Vec<bool>,
+}
+
+impl Drop for QrCode {
+    fn drop(&mut self) {
+        secret::wipe(&mut self.modules);
+    }
+}
+
+impl QrCode {
+    // Ошибка, если данные не помещаются даже в версию 40
+    pub fn encode(data: &[u8]) -> io::Result<Self> {
+        let version = (1..=40).find(|&v| data_bits(data.len(), v) <= data_codewords(v) * 8).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "a password of {} bytes does not fit in a QR code (at most {})",
+                    data.len(),
+                    max_bytes()
+                ),
+            )
+        })?;
+        let mut codewords = encode_data(data, version);
+        let mut interleaved = add_ecc(&codewords, version);
+        secret::wipe(&mut codewords);
+
+        let size = version * 4 + 17;
+        let mut code = Self {
+            size,
+            modules: vec![0; size * size],
+            function: vec![false; size * size],
+        };
+        code.draw_function_patterns(version);
+        code.draw_codewords(&interleaved);
+        secret::wipe(&mut interleaved);
+
+        // Маска с наименьшим штрафом; маска применяется дважды, чтобы снять ее
+        let mut best = (u32::MAX, 0);
+        for mask in 0..8 {
+            code.apply_mask(mask);
+            code.draw_format(mask);
+            best = best.min((code.penalty(), mask));
+            code.apply_mask(mask);
+        }
+        code.apply_mask(best.1);
+        code.draw_format(best.1);
+        Ok(code)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Темный ли модуль; вне матрицы - светлая рамка
+    pub fn dark(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x] == 1
+    }
+
+    // Сторона вместе с рамкой
+    fn framed_size(&self) -> usize {
+        self.size() + 2 * QUIET_ZONE
+    }
+
+    // То же, что dark, но координаты считаются от края рамки
+    fn framed(&self, x: usize, y: usize) -> bool {
+        x >= QUIET_ZONE && y >= QUIET_ZONE && self.dark(x - QUIET_ZONE, y - QUIET_ZONE)
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = u8::from(dark);
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        for (x, y) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            self.draw_finder(x, y);
+        }
+        let positions = alignment_positions(version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // Углы с искателями пропускаются
+                if ![(0, 0), (0, last), (last, 0)].contains(&(i, j)) {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+        // Места формата занимаются заранее, настоящие биты ставит draw_format
+        self.draw_format(0);
+        if version >= 7 {
+            let bits = version_bits(version);
+            for i in 0..18 {
+                let dark = bits >> i & 1 == 1;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    // Искатель 7x7 с белой каймой вокруг
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4..=4isize {
+            for dx in -4..=4isize {
+                let (xx, yy) = (x as isize + dx, y as isize + dy);
+                if (0..self.size as isize).contains(&xx) && (0..self.size as isize).contains(&yy) {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2..=2isize {
+            for dx in -2..=2isize {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((x as isize + dx) as usize, (y as isize + dy) as usize, dark);
+            }
+        }
+    }
+
+    // Уровень и маска, две копии, плюс всегда темный модуль
+    fn draw_format(&mut self, mask: usize) {
+        let size = self.size;
+        let bits = format_bits(mask);
+        let bit = |i: usize| bits >> i & 1 == 1;
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    // Зигзаг парами столбцов справа налево, минуя служебные модули
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                let y = if upward { size - 1 - vert } else { vert };
+                for x in [right, right - 1] {
+                    if !self.function[y * size + x] && i < data.len() * 8 {
+                        self.modules[y * size + x] = data[i / 8] >> (7 - i % 8) & 1;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: usize) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if !self.function[y * self.size + x] && mask_bit(mask, x, y) {
+                    self.modules[y * self.size + x] ^= 1;
+                }
+            }
+        }
+    }
+
+    // Штрафы N1-N4: длинные полосы, квадраты 2x2, узоры как у искателя и
+    // перекос между темными и светлыми
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let mut total = 0;
+        for horizontal in [true, false] {
+            for a in 0..size {
+                let line: Vec<bool> = (0..size)
+                    .map(|b| if horizontal { self.dark(b, a) } else { self.dark(a, b) })
+                    .collect();
+                let mut run = 1;
+                for b in 1..=size {
+                    if b < size && line[b] == line[b - 1] {
+                        run += 1;
+                    } else {
+                        if run >= 5 {
+                            total += run as u32 - 2;
+                        }
+                        run = 1;
+                    }
+                }
+                for window in line.windows(11) {
+                    let pattern = [true, false, true, true, true, false, true];
+                    if (window[..7] == pattern && window[7..].iter().all(|&d| !d))
+                        || (window[4..] == pattern && window[..4].iter().all(|&d| !d))
+                    {
+                        total += 40;
+                    }
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.dark(x, y);
+                if self.dark(x + 1, y) == color && self.dark(x, y + 1) == color && self.dark(x + 1, y + 1) == color {
+                    total += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&m| m == 1).count();
+        let percent = dark * 100 / (size * size);
+        total + (percent.abs_diff(50) / 5) as u32 * 10
+    }
+}
+
+// Сколько байтов пароля помещается в версию 40
+fn max_bytes() -> usize {
+    (1..).take_while(|&n| data_bits(n, 40) <= data_codewords(40) * 8).last().unwrap_or(0)
+}
+
+// Бит данных на n байтов: режим, длина и сами байты
+fn data_bits(n: usize, version: usize) -> usize {
+    4 + count_bits(version) + n * 8
+}
+
+// Ширина поля длины в байтовом режиме
+fn count_bits(version: usize) -> usize {
+    if version <= 9 { 8 } else { 16 }
+}
+
+// Модулей под данные и коррекцию: вся площадь без служебных узоров
+fn raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let align = version / 7 + 2;
+        result -= (25 * align - 10) * align - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_data_modules(version) / 8 - ECC_PER_BLOCK[version - 1] * BLOCKS[version - 1]
+}
+
+// Центры выравнивающих узоров по каждой оси
+pub fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let size = version * 4 + 17;
+    let step = if version == 32 { 26 } else { (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2 };
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+// 15 бит формата: уровень, маска и код БЧХ, с маской 0x5412
+fn format_bits(mask: usize) -> u32 {
+    let data = LEVEL_M << 3 | mask as u32;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+// 18 бит номера версии (с 7-й) с кодом Голея
+fn version_bits(version: usize) -> u32 {
+    let mut rem = version as u32;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+    }
+    (version as u32) << 12 | rem
+}
+
+fn mask_bit(mask: usize, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+// Кодовые слова данных: режим 0100, длина, байты, терминатор и
+// чередующиеся 0xEC, 0x11 до емкости версии
+fn encode_data(data: &[u8], version: usize) -> Vec<u8> {
+    let capacity = data_codewords(version);
+    let mut bits = BitBuffer {
+        bytes: Vec::with_capacity(capacity),
+        len: 0,
+    };
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, count_bits(version));
+    for &b in data {
+        bits.push(u32::from(b), 8);
+    }
+    let terminator = (capacity * 8 - bits.len).min(4);
+    bits.push(0, terminator);
+    bits.push(0, (8 - bits.len % 8) % 8);
+    for pad in [0xEC, 0x11].into_iter().cycle().take(capacity - bits.bytes.len()) {
+        bits.push(pad, 8);
+    }
+    bits.bytes
+}
+
+struct BitBuffer {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitBuffer {
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = (value >> i & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.len % 8);
+            self.len += 1;
+        }
+    }
+}
+
+// Делит данные на блоки, добавляет к каждому коды Рида-Соломона и
+// перемежает: сначала i-е слово каждого блока, затем слова коррекции
+fn add_ecc(data: &[u8], version: usize) -> Vec<u8> {
+    let blocks = BLOCKS[version - 1];
+    let ecc_len = ECC_PER_BLOCK[version - 1];
+    let raw = raw_data_modules(version) / 8;
+    let short_blocks = blocks - raw % blocks;
+    let short_len = raw / blocks - ecc_len;
+    let divisor = rs_divisor(ecc_len);
+
+    let mut result = Vec::with_capacity(raw);
+    let mut starts = Vec::with_capacity(blocks);
+    let mut start = 0;
+    for i in 0..blocks {
+        let len = short_len + usize::from(i >= short_blocks);
+        starts.push((start, len));
+        start += len;
+    }
+    for i in 0..short_len + 1 {
+        for &(start, len) in &starts {
+            if i < len {
+                result.push(data[start + i]);
+            }
+        }
+    }
+    let mut eccs: Vec<Vec<u8>> = starts
+        .iter()
+        .map(|&(start, len)| rs_remainder(&data[start..start + len], &divisor))
+        .collect();
+    for i in 0..ecc_len {
+        for ecc in &eccs {
+            result.push(ecc[i]);
+        }
+    }
+    for ecc in &mut eccs {
+        secret::wipe(ecc);
+    }
+    result
+}
+
+// Умножение в GF(256) с многочленом x^8 + x^4 + x^3 + x^2 + 1
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= u16::from(y >> i & 1) * u16::from(x);
+    }
+    z as u8
+}
+
+// Порождающий многочлен степени degree без старшего коэффициента
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &b in data {
+        let factor = b ^ result[0];
+        result.rotate_left(1);
+        *result.last_mut().unwrap() = 0;
+        for (r, &coef) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(coef, factor);
+        }
+    }
+    result
+}
+
+// Код в терминал: строка текста - две строки модулей, верхний модуль -
+// "▀", нижний - фон. Для ansi цвета заданы явно (черный 30/40, белый
+// 97/107), для utf8 светлые модули рисуются цветом текста.
+pub fn write_terminal<W: Write>(out: &mut W, code: &QrCode, ansi: bool) -> io::Result<()> {
+    let side = code.framed_size();
+    let dark = |x: usize, y: usize| code.framed(x, y);
+    // "\x1b[97;107m▀" на модуль и сброс в конце строки
+    let mut line = Vec::with_capacity(side * 14 + 8);
+    for y in (0..side).step_by(2) {
+        let mut current = None;
+        for x in 0..side {
+            let cell = (dark(x, y), dark(x, y + 1));
+            if ansi {
+                if current != Some(cell) {
+                    let fg = if cell.0 { 30 } else { 97 };
+                    let bg = if cell.1 { 40 } else { 107 };
+                    write!(line, "\x1b[{};{}m", fg, bg)?;
+                    current = Some(cell);
+                }
+                line.extend_from_slice("▀".as_bytes());
+            } else {
+                let c = match cell {
+                    (false, false) => "█",
+                    (false, true) => "▀",
+                    (true, false) => "▄",
+                    (true, true) => " ",
+                };
+                line.extend_from_slice(c.as_bytes());
+            }
+        }
+        if ansi {
+            line.extend_from_slice(b"\x1b[0m");
+        }
+        write_line(out, &mut line)?;
+    }
+    out.flush()
+}
+
+// Код в PNG, модуль - квадрат PNG_SCALE x PNG_SCALE пикселей
+pub fn write_png<W: Write>(out: &mut W, code: &QrCode) -> io::Result<()> {
+    let side = code.framed_size() * PNG_SCALE;
+    png::write(out, side, side, |x, y| code.framed(x / PNG_SCALE, y / PNG_SCALE))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // Декодер для проверки кодировщика: читает формат, снимает маску,
+    // собирает кодовые слова зигзагом, проверяет синдромы Рида-Соломона
+    // каждого блока и разбирает байтовый сегмент. Служебные области
+    // строятся заново, а не берутся из кодировщика.
+    pub fn decode(matrix: &[Vec<bool>]) -> Result<Vec<u8>, String> {
+        let size = matrix.len();
+        if size < 21 || !(size - 17).is_multiple_of(4) || matrix.iter().any(|row| row.len() != size) {
+            return Err(format!("bad size {}", size));
+        }
+        let version = (size - 17) / 4;
+        let at = |x: usize, y: usize| matrix[y][x];
+
+        // Первая копия формата: (8, 0..=5), (8, 7), (8, 8), (7, 8), (5..=0, 8)
+        let mut cells: Vec<(usize, usize)> = (0..6).map(|i| (8, i)).collect();
+        cells.extend([(8, 7), (8, 8), (7, 8)]);
+        cells.extend((9..15).map(|i| (14 - i, 8)));
+        let format = cells.iter().enumerate().fold(0u32, |acc, (i, &(x, y))| acc | u32::from(at(x, y)) << i);
+        let mask = (0..8).find(|&m| format_bits(m) == format).ok_or(format!("bad format bits {:015b}", format))?;
+        // Вторая копия должна совпасть
+        let mut second: Vec<(usize, usize)> = (0..8).map(|i| (size - 1 - i, 8)).collect();
+        second.extend((8..15).map(|i| (8, size - 15 + i)));
+        let copy = second.iter().enumerate().fold(0u32, |acc, (i, &(x, y))| acc | u32::from(at(x, y)) << i);
+        if copy != format {
+            return Err("format copies differ".to_string());
+        }
+
+        let mut function = vec![vec![false; size]; size];
+        let mut mark = |x0: usize, y0: usize, w: usize, h: usize| {
+            for row in function.iter_mut().skip(y0).take(h) {
+                for cell in row.iter_mut().skip(x0).take(w) {
+                    *cell = true;
+                }
+            }
+        };
+        mark(0, 0, 9, 9);
+        mark(size - 8, 0, 8, 9);
+        mark(0, size - 8, 9, 8);
+        mark(6, 0, 1, size);
+        mark(0, 6, size, 1);
+        let positions = alignment_positions(version);
+        for &x in &positions {
+            for &y in &positions {
+                let corner = [(6, 6), (size - 7, 6), (6, size - 7)].contains(&(x, y));
+                if !corner {
+                    mark(x - 2, y - 2, 5, 5);
+                }
+            }
+        }
+        if version >= 7 {
+            mark(size - 11, 0, 3, 6);
+            mark(0, size - 11, 6, 3);
+        }
+
+        let mut bits = Vec::new();
+        let mut right = size as isize - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                let y = if upward { size - 1 - vert } else { vert };
+                for x in [right as usize, right as usize - 1] {
+                    if !function[y][x] {
+                        bits.push(at(x, y) ^ mask_bit(mask, x, y));
+                    }
+                }
+            }
+            right -= 2;
+        }
+        let codewords: Vec<u8> = bits.chunks_exact(8).map(|c| c.iter().fold(0, |acc, &b| acc << 1 | u8::from(b))).collect();
+
+        // Обратное перемежение
+        let blocks = BLOCKS[version - 1];
+        let ecc_len = ECC_PER_BLOCK[version - 1];
+        let raw = codewords.len();
+        let short_blocks = blocks - raw % blocks;
+        let short_len = raw / blocks - ecc_len;
+        let mut data_blocks: Vec<Vec<u8>> = vec![Vec::new(); blocks];
+        let mut words = codewords.iter();
+        for i in 0..short_len + 1 {
+            for (j, block) in data_blocks.iter_mut().enumerate() {
+                if i < short_len || j >= short_blocks {
+                    block.push(*words.next().unwrap());
+                }
+            }
+        }
+        let mut ecc_blocks: Vec<Vec<u8>> = vec![Vec::new(); blocks];
+        for _ in 0..ecc_len {
+            for block in ecc_blocks.iter_mut() {
+                block.push(*words.next().unwrap());
+            }
+        }
+        // Кодовое слово без ошибок делится на (x - a^0)...(x - a^(n-1)):
+        // значения многочлена в этих точках равны нулю
+        for (data, ecc) in data_blocks.iter().zip(&ecc_blocks) {
+            let mut point = 1u8;
+            for _ in 0..ecc_len {
+                let value = data.iter().chain(ecc).fold(0u8, |acc, &c| gf_mul(acc, point) ^ c);
+                if value != 0 {
+                    return Err("Reed-Solomon check failed".to_string());
+                }
+                point = gf_mul(point, 2);
+            }
+        }
+
+        let data: Vec<u8> = data_blocks.concat();
+        let bit = |i: usize| data[i / 8] >> (7 - i % 8) & 1;
+        let read = |from: usize, count: usize| (from..from + count).fold(0usize, |acc, i| acc << 1 | usize::from(bit(i)));
+        if read(0, 4) != 0b0100 {
+            return Err(format!("mode {:04b} is not byte mode", read(0, 4)));
+        }
+        let count_len = count_bits(version);
+        let len = read(4, count_len);
+        Ok((0..len).map(|i| read(4 + count_len + i * 8, 8) as u8).collect())
+    }
+
+    pub fn matrix(code: &QrCode) -> Vec<Vec<bool>> {
+        (0..code.size()).map(|y| (0..code.size()).map(|x| code.dark(x, y)).collect()).collect()
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(QrFormat::parse("ansi"), Some(QrFormat::Ansi));
+        assert_eq!(QrFormat::parse("utf8"), Some(QrFormat::Utf8));
+        assert_eq!(QrFormat::parse("png:out.png"), Some(QrFormat::Png(PathBuf::from("out.png"))));
+        for bad in ["", "png:", "png", "svg", "ANSI"] {
+            assert_eq!(QrFormat::parse(bad), None, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Емкость уровня M по таблицам стандарта
+        assert_eq!(data_codewords(1), 16);
+        assert_eq!(data_codewords(10), 216);
+        assert_eq!(data_codewords(40), 2334);
+        assert_eq!(max_bytes(), 2331);
+        assert_eq!(alignment_positions(1), Vec::<usize>::new());
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+        assert_eq!(alignment_positions(32), [6, 34, 60, 86, 112, 138]);
+        assert_eq!(alignment_positions(40), [6, 30, 58, 86, 114, 142, 170]);
+        // Известные значения из стандарта
+        assert_eq!(version_bits(7), 0x07C94);
+        assert_eq!(format_bits(0), 0b101010000010010);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let passwords: [&[u8]; 6] = [b"", b"a", b"hX3k-9dfQ-2mWp", b"Zee1Xaecue", "пароль".as_bytes(), b"\"'\\`|!,.;:"];
+        for password in passwords {
+            let code = QrCode::encode(password).unwrap();
+            assert_eq!(code.size(), 21);
+            assert_eq!(decode(&matrix(&code)).unwrap(), password);
+        }
+        // Длины на границах версий, включая многоблочные и с полем версии
+        for len in [17, 100, 200, 400, 1000, 2331] {
+            let password: Vec<u8> = (0..len).map(|i| b"!~aZ09"[i % 6]).collect();
+            let code = QrCode::encode(&password).unwrap();
+            assert_eq!(decode(&matrix(&code)).unwrap(), password, "{}", len);
+        }
+        assert!(QrCode::encode(&[b'a'; 2332]).is_err());
+    }
+
+    // Матрица из PNG по центрам модулей, без рамки
+    pub fn read_png(png: &[u8]) -> Vec<Vec<bool>> {
+        let pixels = png::tests::read(png).unwrap();
+        let size = pixels.len() / PNG_SCALE - 2 * QUIET_ZONE;
+        let at = |m: usize| (m + QUIET_ZONE) * PNG_SCALE + PNG_SCALE / 2;
+        (0..size).map(|y| (0..size).map(|x| pixels[at(y)][at(x)]).collect()).collect()
+    }
+
+    #[test]
+    fn test_write_png() {
+        for password in [&b"hX3k-9dfQ-2mWp"[..], &[b'q'; 300]] {
+            let code = QrCode::encode(password).unwrap();
+            let mut out = Vec::new();
+            write_png(&mut out, &code).unwrap();
+            assert_eq!(decode(&read_png(&out)).unwrap(), password);
+        }
+    }
+
+    #[test]
+    fn test_write_terminal() {
+        let code = QrCode::encode(b"hX3k").unwrap();
+        let side = code.size() + 2 * QUIET_ZONE;
+        for ansi in [false, true] {
+            let mut out = Vec::new();
+            write_terminal(&mut out, &code, ansi).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            let lines: Vec<&str> = out.lines().collect();
+            assert_eq!(lines.len(), side.div_ceil(2));
+            // Матрица восстанавливается из полублоков
+            let mut rows = vec![vec![false; side]; lines.len() * 2];
+            for (y, line) in lines.iter().enumerate() {
+                let mut x = 0;
+                let mut colors = (false, false);
+                let mut chars = line.chars();
+                while let Some(c) = chars.next() {
+                    if c == '\x1b' {
+                        let escape: String = chars.by_ref().take_while(|&c| c != 'm').collect();
+                        if escape != "[0" {
+                            colors = (escape.starts_with("[30"), escape.ends_with(";40"));
+                        }
+                        continue;
+                    }
+                    let cell = if ansi {
+                        assert_eq!(c, '▀');
+                        colors
+                    } else {
+                        match c {
+                            '█' => (false, false),
+                            '▀' => (false, true),
+                            '▄' => (true, false),
+                            _ => (true, true),
+                        }
+                    };
+                    rows[2 * y][x] = cell.0;
+                    rows[2 * y + 1][x] = cell.1;
+                    x += 1;
+                }
+                assert_eq!(x, side);
+            }
+            let inner: Vec<Vec<bool>> = rows[QUIET_ZONE..QUIET_ZONE + code.size()]
+                .iter()
+                .map(|row| row[QUIET_ZONE..QUIET_ZONE + code.size()].to_vec())
+                .collect();
+            assert_eq!(decode(&inner).unwrap(), b"hX3k");
+            // Рамка светлая
+            assert!(rows[..QUIET_ZONE].iter().flatten().all(|&d| !d));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{Config, RunError, dictionary, keyboard, pin, secret, sequence};
+
+// Фильтры, которые отбраковывают готовый пароль: слабые PIN с --pin,
+// --no-sequences, --no-keyboard-walks, --no-dictionary-words и
+// --no-profanity. Отброшенный пароль генерируется заново, но попыток
+// не больше MAX_ATTEMPTS, чтобы слишком строгие настройки давали ошибку,
+// а не вечный цикл.
+
+pub const MAX_ATTEMPTS: usize = 1000;
+
+// Чего в пароле быть не должно, если он это содержит
+fn rejection(password: &str, config: &Config) -> Option<String> {
+    if config.pin && pin::weakness(password).is_some() {
+        return Some("weak PINs (--pin)".to_string());
+    }
+    if let Some(run) = config.no_sequences
+        && sequence::has_sequence(password, run)
+    {
+        return Some(format!("runs of {} characters (--no-sequences)", run));
+    }
+    if config.no_keyboard_walks && keyboard::has_walk(password, keyboard::WALK_LENGTH) {
+        return Some("keyboard walks (--no-keyboard-walks)".to_string());
+    }
+    if let Some(length) = config.no_dictionary_words
+        && dictionary::has_word(password, length)
+    {
+        return Some(format!("words of {} or more letters (--no-dictionary-words)", length));
+    }
+    if config.profanity.as_ref().is_some_and(|list| list.matches(password)) {
+        return Some("offensive words (--no-profanity)".to_string());
+    }
+    None
+}
+
+// Пароль из generate, который прошел все фильтры; отброшенные затираются
+pub fn retry<F>(config: &Config, mut generate: F) -> Result<String, RunError>
+where
+    F: FnMut() -> Result<String, RunError>,
+{
+    let mut reason = String::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let mut password = generate()?;
+        match rejection(&password, config) {
+            None => return Ok(password),
+            Some(why) => reason = why,
+        }
+        secret::wipe_str(&mut password);
+    }
+    Err(RunError::Generation(format!(
+        "failed to avoid {} in {} attempts; shorten the passwords or use more characters",
+        reason, MAX_ATTEMPTS
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry() -> Result<(), RunError> {
+        let config = Config {
+            no_sequences: Some(3),
+            no_keyboard_walks: true,
+            ..Config::default()
+        };
+        // Отброшенные пароли не возвращаются, подходящий - возвращается
+        let mut calls = 0;
+        let password = retry(&config, || {
+            calls += 1;
+            Ok(match calls {
+                1 => "x123".to_string(),
+                2 => "xqwe".to_string(),
+                _ => "x132".to_string(),
+            })
+        })?;
+        assert_eq!((password.as_str(), calls), ("x132", 3));
+
+        // Без фильтров подходит первый же пароль
+        let mut calls = 0;
+        let password = retry(&Config::default(), || {
+            calls += 1;
+            Ok("abc".to_string())
+        })?;
+        assert_eq!((password.as_str(), calls), ("abc", 1));
+
+        // Бюджет попыток ограничен, дальше - ошибка генерации с причиной
+        let mut calls = 0;
+        let err = retry(&config, || {
+            calls += 1;
+            Ok("asdf".to_string())
+        })
+        .unwrap_err();
+        assert_eq!(calls, MAX_ATTEMPTS);
+        assert!(matches!(err, RunError::Generation(_)));
+        assert!(err.to_string().contains("keyboard walks (--no-keyboard-walks)"), "{}", err);
+        let err = retry(&config, || Ok("abc".to_string())).unwrap_err();
+        assert!(err.to_string().contains("runs of 3 characters (--no-sequences)"), "{}", err);
+
+        // Слабые PIN отбрасываются, пока не выпадет сильный
+        let pins = Config { pin: true, ..Config::default() };
+        let mut weak = ["0000", "6789", "3434", "2580"].into_iter();
+        let password = retry(&pins, || Ok(weak.next().unwrap_or("9172").to_string()))?;
+        assert_eq!((password.as_str(), weak.next()), ("9172", None));
+
+        // Ошибка самой генерации не повторяется
+        let mut calls = 0;
+        let err = retry(&config, || {
+            calls += 1;
+            Err(RunError::Settings("broken".to_string()))
+        })
+        .unwrap_err();
+        assert_eq!(calls, 1);
+        assert!(matches!(err, RunError::Settings(_)));
+        Ok(())
+    }
+}
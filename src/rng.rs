@@ -0,0 +1,412 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+const URANDOM: &str = "/dev/urandom";
+
+// Источник случайных байтов для генераторов паролей.
+// Любой Read (файл, /dev/urandom, Cursor в тестах) подходит автоматически;
+// генераторы rand подключаются через обертку RngCoreSource (фича "rand").
+pub trait EntropySource {
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<()>;
+}
+
+impl<R: Read + ?Sized> EntropySource for R {
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.read_exact(buf)
+    }
+}
+
+// Равномерный индекс в диапазоне 0..n. Простое `byte % n` завышает
+// вероятность первых `256 % n` значений (для 62 символов - на 25%),
+// поэтому значения из неполного последнего "круга" отбрасываются.
+pub fn random_index<E: EntropySource + ?Sized>(rng: &mut E, n: usize) -> io::Result<usize> {
+    assert!(n > 0, "random_index from an empty range");
+
+    if n <= 256 {
+        let limit = 256 - 256 % n;
+        loop {
+            let mut buf = [0u8; 1];
+            rng.fill(&mut buf)?;
+            if (buf[0] as usize) < limit {
+                return Ok(buf[0] as usize % n);
+            }
+        }
+    }
+
+    let n = n as u64;
+    let limit = (1u64 << 32) - (1u64 << 32) % n;
+    loop {
+        let mut buf = [0u8; 4];
+        rng.fill(&mut buf)?;
+        let value = u32::from_le_bytes(buf) as u64;
+        if value < limit {
+            return Ok((value % n) as usize);
+        }
+    }
+}
+
+// Перестановка Фишера - Йейтса: элемент i меняется местами с
+// равновероятно выбранным из 0..=i, поэтому все n! порядков равновероятны.
+// Берет индексы для n, n-1, ..., 2 элементов - ровно n-1 выборов.
+pub fn shuffle<T, E: EntropySource + ?Sized>(rng: &mut E, items: &mut [T]) -> io::Result<()> {
+    for i in (1..items.len()).rev() {
+        let j = random_index(rng, i + 1)?;
+        items.swap(i, j);
+    }
+    Ok(())
+}
+
+// Обертка для любого rand_core::RngCore. Отдельный тип нужен потому, что
+// общая реализация и для Read, и для RngCore пересекалась бы.
+#[cfg(feature = "rand")]
+#[allow(dead_code)]
+pub struct RngCoreSource<T>(pub T);
+
+#[cfg(feature = "rand")]
+impl<T: rand_core::RngCore> EntropySource for RngCoreSource<T> {
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.0.fill_bytes(buf);
+        Ok(())
+    }
+}
+
+// Какой источник случайности использовать (--rng)
+#[derive(Debug, Clone, PartialEq)]
+pub enum RngChoice {
+    // getrandom, затем /dev/urandom, затем ошибка
+    Auto,
+    GetRandom,
+    Urandom,
+    File(PathBuf),
+}
+
+impl RngChoice {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::Auto),
+            "getrandom" => Some(Self::GetRandom),
+            "urandom" => Some(Self::Urandom),
+            _ => value
+                .strip_prefix("file:")
+                .filter(|path| !path.is_empty())
+                .map(|path| Self::File(PathBuf::from(path))),
+        }
+    }
+}
+
+// Открывает выбранный источник и возвращает его вместе с описанием для
+// --verbose. Явно заданный источник не подменяется другим: если он
+// недоступен, это ошибка.
+pub fn open(choice: &RngChoice) -> io::Result<(Box<dyn Read>, String)> {
+    match choice {
+        RngChoice::Auto => {
+            let getrandom_err = match GetRandom::new() {
+                Ok(source) => return Ok((Box::new(source), "getrandom".to_string())),
+                Err(e) => e,
+            };
+            match open_urandom() {
+                Ok(file) => Ok((Box::new(file), URANDOM.to_string())),
+                Err(urandom_err) => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "no random source available (getrandom: {}; {})",
+                        getrandom_err, urandom_err
+                    ),
+                )),
+            }
+        }
+        RngChoice::GetRandom => {
+            let source = GetRandom::new()?;
+            Ok((Box::new(source), "getrandom".to_string()))
+        }
+        RngChoice::Urandom => Ok((Box::new(open_urandom()?), URANDOM.to_string())),
+        RngChoice::File(path) => {
+            let source = RandomSource::open(path)?;
+            Ok((Box::new(source), format!("file {}", path.display())))
+        }
+    }
+}
+
+fn open_urandom() -> io::Result<File> {
+    File::open(URANDOM)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot open {}: {}", URANDOM, e)))
+}
+
+// Системный вызов getrandom(2)
+pub struct GetRandom(());
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::{c_uint, c_void};
+
+    unsafe extern "C" {
+        pub fn getrandom(buf: *mut c_void, buflen: usize, flags: c_uint) -> isize;
+    }
+}
+
+impl GetRandom {
+    #[cfg(target_os = "linux")]
+    pub fn new() -> io::Result<Self> {
+        // Пробный вызов нулевой длины: ENOSYS на старых ядрах
+        let mut source = Self(());
+        source
+            .read(&mut [])
+            .map_err(|e| io::Error::new(e.kind(), format!("getrandom is unavailable: {}", e)))?;
+        Ok(source)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn new() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "getrandom is not supported on this platform",
+        ))
+    }
+}
+
+impl Read for GetRandom {
+    #[cfg(target_os = "linux")]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            // SAFETY: буфер валиден на всю переданную длину
+            let n = unsafe { sys::getrandom(buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if n >= 0 {
+                return Ok(n as usize);
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+// Файл, заданный через --random-source или --rng file:PATH.
+// Ошибки чтения и преждевременный конец файла сообщают путь к источнику,
+// чтобы не выдавать обрезанные пароли молча.
+pub struct RandomSource {
+    file: File,
+    path: PathBuf,
+}
+
+impl RandomSource {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot open random source {}: {}", path.display(), e),
+            )
+        })?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn wrap_error(&self, e: io::Error) -> io::Error {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            io::Error::new(
+                e.kind(),
+                format!("random source {} is exhausted", self.path.display()),
+            )
+        } else {
+            io::Error::new(
+                e.kind(),
+                format!("cannot read random source {}: {}", self.path.display(), e),
+            )
+        }
+    }
+}
+
+impl Read for RandomSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf).map_err(|e| self.wrap_error(e))
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.file.read_exact(buf).map_err(|e| self.wrap_error(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_is_entropy_source() -> io::Result<()> {
+        let mut source = Cursor::new(vec![1, 2, 3, 4]);
+        let mut buf = [0u8; 3];
+        source.fill(&mut buf)?;
+        assert_eq!(buf, [1, 2, 3]);
+
+        // Нехватка данных - ошибка, а не частично заполненный буфер
+        assert!(source.fill(&mut buf).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dyn_read_is_entropy_source() -> io::Result<()> {
+        let mut boxed: Box<dyn Read> = Box::new(Cursor::new(vec![9; 8]));
+        let mut buf = [0u8; 8];
+        boxed.as_mut().fill(&mut buf)?;
+        assert_eq!(buf, [9; 8]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_index_rejects_biased_bytes() -> io::Result<()> {
+        // Для n = 62 байты 248..=255 отбрасываются
+        let mut source = Cursor::new(vec![250, 255, 248, 63]);
+        assert_eq!(random_index(&mut source, 62)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shuffle_consumes_one_index_per_step() -> io::Result<()> {
+        // i = 3: 1 из 0..4, i = 2: 255 отброшен, затем 0 из 0..3, i = 1: 1 из 0..2
+        let mut source = Cursor::new(vec![1, 255, 0, 1, 42]);
+        let mut items = ['a', 'b', 'c', 'd'];
+        shuffle(&mut source, &mut items)?;
+        assert_eq!(items, ['c', 'd', 'a', 'b']);
+        // Лишних байтов не прочитано
+        assert_eq!(source.position(), 4);
+
+        // Из одного элемента выбирать нечего
+        let mut source = Cursor::new(Vec::new());
+        shuffle(&mut source, &mut ['x'])?;
+        shuffle(&mut source, &mut [] as &mut [char])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_shuffle_reaches_every_order_once() -> io::Result<()> {
+        // Все 4 x 3 x 2 сочетания индексов дают 24 разные перестановки
+        let mut orders = Vec::new();
+        for a in 0..4 {
+            for b in 0..3 {
+                for c in 0..2 {
+                    let mut items = [0, 1, 2, 3];
+                    shuffle(&mut Cursor::new(vec![a, b, c]), &mut items)?;
+                    orders.push(items);
+                }
+            }
+        }
+        orders.sort_unstable();
+        orders.dedup();
+        assert_eq!(orders.len(), 24);
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_index_is_uniform_over_all_bytes() -> io::Result<()> {
+        // Каждое значение байта ровно один раз: все индексы равновероятны
+        let bytes: Vec<u8> = (0..=255).collect();
+        for n in [1, 2, 3, 10, 26, 62, 94, 100, 256] {
+            let mut source = Cursor::new(bytes.clone());
+            let mut counts = vec![0usize; n];
+            while let Ok(idx) = random_index(&mut source, n) {
+                counts[idx] += 1;
+            }
+            assert!(counts.iter().all(|&c| c == counts[0]), "n = {}", n);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_index_large_range() -> io::Result<()> {
+        let mut source = Cursor::new(1000u32.to_le_bytes().to_vec());
+        assert_eq!(random_index(&mut source, 7776)?, 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rng_choice_parse() {
+        assert_eq!(RngChoice::parse("auto"), Some(RngChoice::Auto));
+        assert_eq!(RngChoice::parse("getrandom"), Some(RngChoice::GetRandom));
+        assert_eq!(RngChoice::parse("urandom"), Some(RngChoice::Urandom));
+        assert_eq!(
+            RngChoice::parse("file:/dev/hwrng"),
+            Some(RngChoice::File(PathBuf::from("/dev/hwrng")))
+        );
+        assert_eq!(RngChoice::parse("file:"), None);
+        assert_eq!(RngChoice::parse("random"), None);
+    }
+
+    #[test]
+    fn test_open_file_source() -> io::Result<()> {
+        let path = std::env::temp_dir().join(format!("pwgen-rs-{}-rng-file", std::process::id()));
+        std::fs::write(&path, [1, 2, 3, 4])?;
+
+        let (mut source, name) = open(&RngChoice::File(path.clone()))?;
+        let mut buf = [0u8; 4];
+        source.fill(&mut buf)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(buf, [1, 2, 3, 4]);
+        assert_eq!(name, format!("file {}", path.display()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_missing_pinned_file_fails() {
+        let choice = RngChoice::File(PathBuf::from("/nonexistent/pwgen-rs-rng"));
+        let err = open(&choice).err().unwrap();
+        assert!(err.to_string().contains("/nonexistent/pwgen-rs-rng"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_getrandom_fills_buffer() -> io::Result<()> {
+        let mut source = GetRandom::new()?;
+        let mut buf = [0u8; 64];
+        source.fill(&mut buf)?;
+        // 64 нулевых байта подряд практически невозможны
+        assert!(buf.iter().any(|&b| b != 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_auto_finds_a_source() -> io::Result<()> {
+        let (mut source, name) = open(&RngChoice::Auto)?;
+        let mut buf = [0u8; 16];
+        source.fill(&mut buf)?;
+        assert!(name == "getrandom" || name == URANDOM);
+        Ok(())
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_rng_core_source() -> io::Result<()> {
+        struct Counter(u8);
+
+        impl rand_core::RngCore for Counter {
+            fn next_u32(&mut self) -> u32 {
+                rand_core::impls::next_u32_via_fill(self)
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                rand_core::impls::next_u64_via_fill(self)
+            }
+
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                for byte in dest {
+                    *byte = self.0;
+                    self.0 = self.0.wrapping_add(1);
+                }
+            }
+        }
+
+        let mut source = RngCoreSource(Counter(5));
+        let mut buf = [0u8; 4];
+        source.fill(&mut buf)?;
+        assert_eq!(buf, [5, 6, 7, 8]);
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use std::io;
+use std::ptr;
+use std::str;
+use std::sync::atomic::{Ordering, compiler_fence};
+
+// Затирает буфер нулями. Запись через write_volatile компилятор не может
+// выбросить как "мертвую", даже если буфер сразу после этого освобождается.
+pub fn wipe(buf: &mut [u8]) {
+    for byte in buf.iter_mut() {
+        // SAFETY: byte - валидная ссылка на элемент буфера
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+// Пароль, собранный посимвольно до перевода в строку
+pub fn wipe_chars(chars: &mut [char]) {
+    for c in chars.iter_mut() {
+        // SAFETY: c - валидная ссылка на элемент буфера, '\0' - допустимый char
+        unsafe { ptr::write_volatile(c, '\0') };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+// Нулевые байты - валидный UTF-8, поэтому строка остается корректной
+pub fn wipe_str(s: &mut str) {
+    // SAFETY: записываем только нули, UTF-8 не нарушается
+    wipe(unsafe { s.as_bytes_mut() });
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{c_int, c_void};
+
+    unsafe extern "C" {
+        pub fn mlock(addr: *const c_void, len: usize) -> c_int;
+        pub fn munlock(addr: *const c_void, len: usize) -> c_int;
+    }
+}
+
+// Выделенная под буфер память целиком, включая еще не занятую часть
+fn allocation(buf: &Vec<u8>) -> (*const u8, usize) {
+    (buf.as_ptr(), buf.capacity())
+}
+
+#[cfg(unix)]
+fn lock_region((addr, len): (*const u8, usize)) -> io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    // SAFETY: область целиком принадлежит выделенному буферу
+    if unsafe { sys::mlock(addr.cast(), len) } == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    Err(io::Error::new(
+        err.kind(),
+        format!(
+            "mlock failed: {} (RLIMIT_MEMLOCK may be too small, see `ulimit -l`)",
+            err
+        ),
+    ))
+}
+
+#[cfg(not(unix))]
+fn lock_region(_region: (*const u8, usize)) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "memory locking is not supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+fn unlock_region((addr, len): (*const u8, usize)) {
+    if len > 0 {
+        // SAFETY: та же область, что была передана в mlock
+        unsafe { sys::munlock(addr.cast(), len) };
+    }
+}
+
+#[cfg(not(unix))]
+fn unlock_region(_region: (*const u8, usize)) {}
+
+// Пакет сгенерированных паролей, который затирается при удалении.
+// Все пароли лежат подряд в одном буфере, поэтому его можно целиком
+// закрепить в памяти (--lock-memory), а копий в куче не остается.
+#[derive(Debug, Default)]
+pub struct Passwords {
+    buf: Vec<u8>,
+    // Конец каждого пароля в buf
+    ends: Vec<usize>,
+    locked: bool,
+}
+
+impl Passwords {
+    // count паролей примерно по length байтов - без перевыделений
+    pub fn with_capacity(count: usize, length: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(count.saturating_mul(length)),
+            ends: Vec::with_capacity(count),
+            locked: false,
+        }
+    }
+
+    // Закрепляет буфер в оперативной памяти, чтобы он не попал в swap
+    pub fn lock(&mut self) -> io::Result<()> {
+        if !self.locked {
+            lock_region(allocation(&self.buf))?;
+            self.locked = true;
+        }
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    // Копирует пароль в общий буфер и затирает исходную строку
+    pub fn push(&mut self, mut password: String) {
+        let needed = self.buf.len() + password.len();
+        if needed > self.buf.capacity() {
+            self.grow(needed);
+        }
+        self.buf.extend_from_slice(password.as_bytes());
+        self.ends.push(self.buf.len());
+        wipe_str(&mut password);
+    }
+
+    // Vec при росте оставил бы старую копию в освобожденной памяти,
+    // поэтому буфер переносится вручную: новый закрепляется, старый
+    // затирается и открепляется
+    fn grow(&mut self, needed: usize) {
+        let mut buf = Vec::with_capacity(needed.max(self.buf.capacity() * 2));
+        let relocked = self.locked
+            && match lock_region(allocation(&buf)) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("WARNING: cannot lock password memory: {}", e);
+                    false
+                }
+            };
+        buf.extend_from_slice(&self.buf);
+        wipe(&mut self.buf);
+        if self.locked {
+            unlock_region(allocation(&self.buf));
+        }
+        self.buf = buf;
+        self.locked = relocked;
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn get(&self, index: usize) -> &str {
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        // SAFETY: буфер собран из целых строк, границы совпадают с их концами
+        unsafe { str::from_utf8_unchecked(&self.buf[start..self.ends[index]]) }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    pub fn wipe(&mut self) {
+        wipe(&mut self.buf);
+    }
+
+    // Упорядочивает пароли по байтам, то есть по кодовым точкам. Как и при
+    // росте, пароли переносятся в новый буфер, а старый затирается.
+    pub fn sort(&mut self) {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_unstable_by(|&a, &b| self.get(a).cmp(self.get(b)));
+        let mut sorted = Self::with_capacity(self.len(), 0);
+        sorted.buf.reserve_exact(self.buf.capacity());
+        if self.locked {
+            match lock_region(allocation(&sorted.buf)) {
+                Ok(()) => sorted.locked = true,
+                Err(e) => eprintln!("WARNING: cannot lock password memory: {}", e),
+            }
+        }
+        for i in order {
+            sorted.buf.extend_from_slice(self.get(i).as_bytes());
+            sorted.ends.push(sorted.buf.len());
+        }
+        // Старый буфер затрет Drop
+        std::mem::swap(self, &mut sorted);
+    }
+}
+
+impl PartialEq for Passwords {
+    fn eq(&self, other: &Self) -> bool {
+        self.buf == other.buf && self.ends == other.ends
+    }
+}
+
+impl From<Vec<String>> for Passwords {
+    fn from(passwords: Vec<String>) -> Self {
+        let length = passwords.iter().map(|p| p.len()).max().unwrap_or(0);
+        let mut result = Self::with_capacity(passwords.len(), length);
+        for password in passwords {
+            result.push(password);
+        }
+        result
+    }
+}
+
+impl Drop for Passwords {
+    fn drop(&mut self) {
+        self.wipe();
+        if self.locked {
+            unlock_region(allocation(&self.buf));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wipe_buffer() {
+        let mut buf = *b"secret";
+        wipe(&mut buf);
+        assert_eq!(buf, [0; 6]);
+    }
+
+    #[test]
+    fn test_wipe_string_keeps_length() {
+        let mut s = String::from("hunter2");
+        wipe_str(&mut s);
+        assert_eq!(s.len(), 7);
+        assert!(s.bytes().all(|b| b == 0));
+    }
+
+    #[test]
+    fn test_passwords_wipe_clears_every_buffer() {
+        let mut passwords = Passwords::from(vec!["abc".to_string(), "Xy9!".to_string()]);
+
+        // Запоминаем адреса паролей: затирание идет на месте, без копий
+        let addresses: Vec<*const u8> = passwords.iter().map(|p| p.as_ptr()).collect();
+        passwords.wipe();
+
+        for (password, address) in passwords.iter().zip(addresses) {
+            assert_eq!(password.as_ptr(), address);
+            assert!(password.bytes().all(|b| b == 0));
+        }
+    }
+
+    #[test]
+    fn test_passwords_single_contiguous_region() {
+        let mut passwords = Passwords::with_capacity(3, 4);
+        for password in ["abcd", "EFGH", "1234"] {
+            passwords.push(password.to_string());
+        }
+
+        // Каждый следующий пароль начинается сразу за предыдущим
+        let base = passwords.get(0).as_ptr();
+        for i in 0..passwords.len() {
+            assert_eq!(passwords.get(i).as_ptr(), base.wrapping_add(i * 4));
+        }
+        assert_eq!(passwords.iter().collect::<Vec<_>>(), ["abcd", "EFGH", "1234"]);
+    }
+
+    #[test]
+    fn test_passwords_grow_keeps_contents() {
+        // Пароли длиннее ожидаемого: буфер переносится, но остается одним
+        let mut passwords = Passwords::with_capacity(2, 2);
+        let _ = passwords.lock();
+        let expected = ["first", "second", "third"];
+        for password in expected {
+            passwords.push(password.to_string());
+        }
+
+        assert_eq!(passwords.iter().collect::<Vec<_>>(), expected);
+        let base = passwords.get(0).as_ptr();
+        assert_eq!(passwords.get(2).as_ptr(), base.wrapping_add(11));
+    }
+
+    #[test]
+    fn test_passwords_sort() {
+        let mut passwords = Passwords::from(
+            ["b2", "a", "B1", "ж", "ab", "a"].iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+        );
+        passwords.sort();
+        // По кодовым точкам: заглавные раньше строчных, повторы остаются
+        assert_eq!(passwords.iter().collect::<Vec<_>>(), ["B1", "a", "a", "ab", "b2", "ж"]);
+        // Буфер по-прежнему один
+        let base = passwords.get(0).as_ptr();
+        assert_eq!(passwords.get(3).as_ptr(), base.wrapping_add(4));
+
+        let mut empty = Passwords::default();
+        empty.sort();
+        assert_eq!(empty.len(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_passwords_lock() {
+        let mut passwords = Passwords::with_capacity(4, 16);
+        // Маленькая область укладывается даже в минимальный RLIMIT_MEMLOCK
+        match passwords.lock() {
+            Ok(()) => assert!(passwords.is_locked()),
+            Err(e) => {
+                assert!(e.to_string().contains("RLIMIT_MEMLOCK"));
+                assert!(!passwords.is_locked());
+            }
+        }
+        passwords.push("secret".to_string());
+        assert_eq!(passwords.get(0), "secret");
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use crate::secret::Passwords;
+use crate::{Config, RunError, build_charset, generate_passwords, required_classes};
+
+// Размер пакета для каждой проверки
+const BATCH: usize = 10_000;
+const SECURE_LENGTH: usize = 20;
+
+// z-оценка для уровня значимости 1e-4: ложный FAIL на исправном
+// генераторе случается примерно раз на десять тысяч запусков
+const CHI_SQUARED_Z: f64 = 3.719;
+
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+// Прогоняет проверки на настоящем конвейере генерации и печатает итог.
+// Источник случайности (--rng, --fast-rng и т.д.) берется из base,
+// так что можно проверить, например, аппаратный генератор.
+pub fn run(base: &Config) -> Result<bool, RunError> {
+    let checks = run_checks(base)?;
+
+    let mut out = io::stdout().lock();
+    writeln!(out, "Self-test:")?;
+    for check in &checks {
+        let status = if check.passed { "PASS" } else { "FAIL" };
+        writeln!(out, "  {}  {}: {}", status, check.name, check.detail)?;
+    }
+
+    let passed = checks.iter().all(|check| check.passed);
+    writeln!(out, "Self-test {}", if passed { "passed" } else { "FAILED" })?;
+    out.flush()?;
+    Ok(passed)
+}
+
+fn run_checks(base: &Config) -> Result<Vec<Check>, RunError> {
+    let secure = Config {
+        secure: true,
+        pw_length: SECURE_LENGTH,
+        num_pw: BATCH,
+        ..entropy_settings(base)
+    };
+    let configs = vec![
+        secure.clone(),
+        Config {
+            symbols: true,
+            ambiguous: true,
+            num_pw: BATCH / 5,
+            ..secure.clone()
+        },
+        Config {
+            num_pw: BATCH,
+            ..entropy_settings(base)
+        },
+        Config {
+            no_capitalize: true,
+            symbols: true,
+            pw_length: 12,
+            num_pw: BATCH / 5,
+            ..entropy_settings(base)
+        },
+    ];
+
+    let mut batches = Vec::with_capacity(configs.len());
+    for config in configs {
+        let passwords = generate_passwords(&config)?;
+        batches.push((config, passwords));
+    }
+
+    let (secure_config, secure_passwords) = &batches[0];
+    Ok(vec![
+        check_charset(&batches),
+        check_requirements(&batches),
+        check_frequency(secure_config, secure_passwords),
+        check_collisions(secure_passwords),
+    ])
+}
+
+// Настройки по умолчанию, но с источником случайности из base
+fn entropy_settings(base: &Config) -> Config {
+    Config {
+        rng: base.rng.clone(),
+        sha1: base.sha1.clone(),
+        fast_rng: base.fast_rng,
+        seed: base.seed,
+        ..Config::default()
+    }
+}
+
+fn check_charset(batches: &[(Config, Passwords)]) -> Check {
+    let mut total = 0;
+    let mut bad = 0;
+    for (config, passwords) in batches {
+        let charset = build_charset(config);
+        let chars = config.require_chars.as_deref().unwrap_or_default();
+        total += passwords.len();
+        bad += passwords
+            .iter()
+            .filter(|password| !password.chars().all(|c| charset.contains(&c) || chars.contains(&c)))
+            .count();
+    }
+
+    Check {
+        name: "charset",
+        passed: bad == 0,
+        detail: if bad == 0 {
+            format!("{} passwords use only characters from their charset", total)
+        } else {
+            format!("{} of {} passwords contain characters outside their charset", bad, total)
+        },
+    }
+}
+
+// Какие из обязательных классов символов отсутствуют в пароле
+pub fn missing_classes(password: &str, config: &Config) -> Vec<&'static str> {
+    // Те же классы, что требует генератор: пропущенные в --include-only не проверяются
+    let charset = build_charset(config);
+    required_classes(config, &charset)
+        .into_iter()
+        .filter(|(_, _, allowed)| !password.chars().any(|c| allowed.contains(&c)))
+        .map(|(name, _, _)| name)
+        .collect()
+}
+
+fn check_requirements(batches: &[(Config, Passwords)]) -> Check {
+    let mut total = 0;
+    let mut bad = 0;
+    for (config, passwords) in batches {
+        total += passwords.len();
+        bad += passwords
+            .iter()
+            .filter(|password| !missing_classes(password, config).is_empty())
+            .count();
+    }
+
+    Check {
+        name: "requirements",
+        passed: bad == 0,
+        detail: if bad == 0 {
+            format!("{} passwords contain every required character class", total)
+        } else {
+            format!("{} of {} passwords miss a required character class", bad, total)
+        },
+    }
+}
+
+// Критическое значение хи-квадрат (аппроксимация Уилсона-Хилферти)
+fn chi_squared_limit(degrees_of_freedom: f64) -> f64 {
+    let a = 2.0 / (9.0 * degrees_of_freedom);
+    degrees_of_freedom * (1.0 - a + CHI_SQUARED_Z * a.sqrt()).powi(3)
+}
+
+fn chi_squared(counts: &[usize]) -> f64 {
+    let total: usize = counts.iter().sum();
+    let expected = total as f64 / counts.len() as f64;
+    counts
+        .iter()
+        .map(|&observed| {
+            let diff = observed as f64 - expected;
+            diff * diff / expected
+        })
+        .sum()
+}
+
+fn check_frequency(config: &Config, passwords: &Passwords) -> Check {
+    let charset = build_charset(config);
+    let mut counts = vec![0usize; charset.len()];
+    for password in passwords.iter() {
+        for c in password.chars() {
+            if let Some(idx) = charset.iter().position(|&x| x == c) {
+                counts[idx] += 1;
+            }
+        }
+    }
+
+    let statistic = chi_squared(&counts);
+    let limit = chi_squared_limit((charset.len() - 1) as f64);
+    Check {
+        name: "frequency",
+        passed: statistic <= limit,
+        detail: format!(
+            "chi-squared {:.1} for {} degrees of freedom (limit {:.1})",
+            statistic,
+            charset.len() - 1,
+            limit
+        ),
+    }
+}
+
+fn check_collisions(passwords: &Passwords) -> Check {
+    let unique: HashSet<&str> = passwords.iter().collect();
+    let duplicates = passwords.len() - unique.len();
+
+    Check {
+        name: "collisions",
+        passed: duplicates == 0,
+        detail: if duplicates == 0 {
+            format!(
+                "{} secure {}-character passwords are all distinct",
+                passwords.len(),
+                SECURE_LENGTH
+            )
+        } else {
+            format!(
+                "{} of {} secure passwords are duplicates",
+                duplicates,
+                passwords.len()
+            )
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            num_pw: 1,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_missing_classes() {
+        let mut config = config();
+        config.symbols = true;
+
+        assert!(missing_classes("Abc1!", &config).is_empty());
+        assert_eq!(missing_classes("abc1!", &config), vec!["uppercase"]);
+        assert_eq!(
+            missing_classes("abcdef", &config),
+            vec!["uppercase", "digit", "symbol"]
+        );
+
+        config.no_capitalize = true;
+        config.no_numerals = true;
+        config.symbols = false;
+        assert!(missing_classes("abcdef", &config).is_empty());
+        // Класс, которого нет в --include-only, не требуется и здесь
+        config.no_numerals = false;
+        config.include_only = Some("абв".chars().collect());
+        assert!(missing_classes("ааб", &config).is_empty());
+    }
+
+    #[test]
+    fn test_check_charset_detects_foreign_character() {
+        let mut config = config();
+        config.no_numerals = true;
+
+        let good = vec![(config.clone(), Passwords::from(vec!["abcDEF".to_string()]))];
+        assert!(check_charset(&good).passed);
+
+        let bad = vec![(
+            config,
+            Passwords::from(vec!["abcDEF".to_string(), "abc123".to_string()]),
+        )];
+        let check = check_charset(&bad);
+        assert!(!check.passed);
+        assert!(check.detail.starts_with("1 of 2"));
+    }
+
+    #[test]
+    fn test_check_requirements_detects_missing_class() {
+        let config = config();
+        let batch = vec![(
+            config,
+            Passwords::from(vec!["Abc1".to_string(), "abcd".to_string()]),
+        )];
+        let check = check_requirements(&batch);
+        assert!(!check.passed);
+        assert!(check.detail.starts_with("1 of 2"));
+    }
+
+    #[test]
+    fn test_chi_squared() {
+        assert_eq!(chi_squared(&[10, 10, 10, 10]), 0.0);
+        assert_eq!(chi_squared(&[20, 0]), 20.0);
+
+        // Табличное значение для 61 степени свободы и p = 1e-4 - около 112
+        let limit = chi_squared_limit(61.0);
+        assert!((limit - 112.0).abs() < 2.0, "limit = {}", limit);
+    }
+
+    #[test]
+    fn test_check_frequency_rejects_modulo_bias() {
+        let config = Config {
+            secure: true,
+            ..config()
+        };
+        let charset = build_charset(&config);
+
+        // Смещение как у `byte % 62`: первые 8 символов в 5/4 раза чаще
+        let mut password = String::new();
+        for byte in 0..=255usize {
+            for _ in 0..200 {
+                password.push(charset[byte % charset.len()]);
+            }
+        }
+        assert!(!check_frequency(&config, &Passwords::from(vec![password])).passed);
+    }
+
+    #[test]
+    fn test_check_frequency_with_seeded_pipeline() -> Result<(), RunError> {
+        let config = Config {
+            secure: true,
+            no_numerals: true,
+            no_capitalize: true,
+            pw_length: SECURE_LENGTH,
+            num_pw: 2_000,
+            seed: Some(1),
+            ..Config::default()
+        };
+        let passwords = generate_passwords(&config)?;
+        assert!(check_frequency(&config, &passwords).passed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_collisions() {
+        let passwords = Passwords::from(vec!["a".to_string(), "b".to_string(), "a".to_string()]);
+        let check = check_collisions(&passwords);
+        assert!(!check.passed);
+        assert!(check.detail.starts_with("1 of 3"));
+
+        let distinct = Passwords::from(vec!["a".to_string(), "b".to_string()]);
+        assert!(check_collisions(&distinct).passed);
+    }
+}
@@ -0,0 +1,70 @@
+// `--no-sequences[=N]`: пароль с возрастающей или убывающей цепочкой из N
+// символов ("abc", "CbA", "321") генерируется заново. Цепочка идет по
+// алфавиту ASCII (регистр букв не важен) или по цифрам, без перехода
+// через край: "yzA" и "901" - не цепочки. Повторы генерации - в reject.rs.
+
+pub const DEFAULT_RUN: usize = 3;
+
+// Место символа в своем ряду: буквы и цифры - разные ряды
+fn key(c: char) -> Option<(bool, u8)> {
+    let b = u8::try_from(c).ok()?;
+    match b {
+        b'a'..=b'z' | b'A'..=b'Z' => Some((true, b.to_ascii_lowercase())),
+        b'0'..=b'9' => Some((false, b)),
+        _ => None,
+    }
+}
+
+// Есть ли в пароле цепочка из n соседних символов одного ряда, каждый на
+// единицу больше (или меньше) предыдущего
+pub fn has_sequence(pw: &str, n: usize) -> bool {
+    let (mut up, mut down) = (0, 0);
+    let mut prev: Option<(bool, u8)> = None;
+    for c in pw.chars() {
+        let current = key(c);
+        match (prev, current) {
+            (Some((row, a)), Some((next_row, b))) if row == next_row => {
+                up = if b == a.wrapping_add(1) { up + 1 } else { 1 };
+                down = if b == a.wrapping_sub(1) { down + 1 } else { 1 };
+            }
+            (_, Some(_)) => (up, down) = (1, 1),
+            (_, None) => (up, down) = (0, 0),
+        }
+        if current.is_some() && (up >= n || down >= n) {
+            return true;
+        }
+        prev = current;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_sequence() {
+        for pw in ["abc", "xyz", "123", "321", "cba", "zyx", "789", "x-ABC-1", "aBc", "CbA", "qAbCq"] {
+            assert!(has_sequence(pw, 3), "{}", pw);
+        }
+        for pw in ["", "ab", "abd", "aBd", "acb", "a1b", "12a3", "ab-c", "1.2.3", "aab", "ééé"] {
+            assert!(!has_sequence(pw, 3), "{}", pw);
+        }
+        // Через край ряда цепочка не продолжается
+        for pw in ["yzA", "YZa", "zab", "901", "098", "za", "a`_"] {
+            assert!(!has_sequence(pw, 3), "{}", pw);
+        }
+        // Буквы и цифры - разные ряды, хотя в ASCII 9 и : соседние
+        assert!(!has_sequence("89:", 3));
+        assert!(!has_sequence("@AB", 3));
+        // Длина цепочки
+        assert!(has_sequence("xabcdx", 4));
+        assert!(!has_sequence("xabcx", 4));
+        assert!(has_sequence("a9b", 1));
+        assert!(has_sequence("ab", 2) && has_sequence("98", 2));
+        assert!(!has_sequence("aa", 2) && !has_sequence("a-b", 2));
+        // Смена направления начинает новую цепочку
+        assert!(!has_sequence("abab", 3));
+        assert!(has_sequence("abcba", 3) && !has_sequence("abcba", 4));
+    }
+}
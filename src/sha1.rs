@@ -0,0 +1,234 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+// Seed по умолчанию, как в оригинальном pwgen (sha1num.c)
+pub const DEFAULT_SEED: &str = "pwgen";
+
+const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 20;
+
+// Минимальная реализация SHA-1 (RFC 3174).
+// Используется только для детерминированной генерации (-H), не для защиты.
+#[derive(Clone)]
+pub struct Sha1 {
+    state: [u32; 5],
+    buffer: [u8; BLOCK_SIZE],
+    buffered: usize,
+    length: u64,
+}
+
+impl Sha1 {
+    pub fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0],
+            buffer: [0; BLOCK_SIZE],
+            buffered: 0,
+            length: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+
+        // Дополняем незавершенный блок
+        if self.buffered > 0 {
+            let take = (BLOCK_SIZE - self.buffered).min(data.len());
+            self.buffer[self.buffered..self.buffered + take].copy_from_slice(&data[..take]);
+            self.buffered += take;
+            data = &data[take..];
+            if self.buffered < BLOCK_SIZE {
+                return;
+            }
+            let block = self.buffer;
+            self.compress(&block);
+            self.buffered = 0;
+        }
+
+        let mut chunks = data.chunks_exact(BLOCK_SIZE);
+        for block in &mut chunks {
+            self.compress(block.try_into().unwrap());
+        }
+
+        let rest = chunks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    pub fn finish(mut self) -> [u8; DIGEST_SIZE] {
+        let bit_length = self.length.wrapping_mul(8);
+
+        // Паддинг: 0x80, нули и длина сообщения в битах (big-endian)
+        self.update(&[0x80]);
+        while self.buffered != BLOCK_SIZE - 8 {
+            self.update(&[0]);
+        }
+        self.update(&bit_length.to_be_bytes());
+
+        let mut digest = [0u8; DIGEST_SIZE];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8; BLOCK_SIZE]) {
+        let mut w = [0u32; 80];
+        for (i, chunk) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d, e]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+}
+
+// Детерминированный поток байтов для -H / --sha1=path[#seed].
+// Повторяет конструкцию sha1num.c из pwgen: контекст SHA-1 от содержимого
+// файла на каждые 20 байт дополняется seed, а выходом служит дайджест
+// копии контекста. Один и тот же файл и seed всегда дают один поток.
+pub struct Sha1Stream {
+    ctx: Sha1,
+    seed: Vec<u8>,
+    block: [u8; DIGEST_SIZE],
+    pos: usize,
+}
+
+impl Sha1Stream {
+    pub fn new<R: Read>(mut input: R, seed: &[u8]) -> io::Result<Self> {
+        let mut ctx = Sha1::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = input.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            ctx.update(&buf[..n]);
+        }
+
+        Ok(Self {
+            ctx,
+            seed: seed.to_vec(),
+            block: [0; DIGEST_SIZE],
+            pos: DIGEST_SIZE,
+        })
+    }
+
+    pub fn from_file(path: &Path, seed: &str) -> io::Result<Self> {
+        let file = File::open(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot open sha1 file {}: {}", path.display(), e),
+            )
+        })?;
+        Self::new(file, seed.as_bytes())
+    }
+
+    fn refill(&mut self) {
+        self.ctx.update(&self.seed);
+        self.block = self.ctx.clone().finish();
+        self.pos = 0;
+    }
+}
+
+impl Read for Sha1Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        for byte in buf.iter_mut() {
+            if self.pos == DIGEST_SIZE {
+                self.refill();
+            }
+            *byte = self.block[self.pos];
+            self.pos += 1;
+        }
+        Ok(buf.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn sha1(data: &[u8]) -> String {
+        let mut ctx = Sha1::new();
+        ctx.update(data);
+        hex(&ctx.finish())
+    }
+
+    #[test]
+    fn test_sha1_known_vectors() {
+        assert_eq!(sha1(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(sha1(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn test_sha1_incremental_update() {
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+        // Разбиение на куски произвольного размера не меняет результат
+        let mut ctx = Sha1::new();
+        for chunk in data.chunks(37) {
+            ctx.update(chunk);
+        }
+        assert_eq!(hex(&ctx.finish()), sha1(&data));
+    }
+
+    #[test]
+    fn test_sha1_stream_deterministic() -> io::Result<()> {
+        let mut first = Sha1Stream::new(&b"some file contents"[..], b"seed")?;
+        let mut second = Sha1Stream::new(&b"some file contents"[..], b"seed")?;
+
+        let mut a = [0u8; 100];
+        let mut b = [0u8; 100];
+        first.read_exact(&mut a)?;
+        second.read_exact(&mut b)?;
+        assert_eq!(a, b);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha1_stream_first_block() -> io::Result<()> {
+        // Первый блок - SHA-1 от содержимого файла, за которым следует seed
+        let mut stream = Sha1Stream::new(&b"file"[..], b"pwgen")?;
+        let mut block = [0u8; DIGEST_SIZE];
+        stream.read_exact(&mut block)?;
+        assert_eq!(hex(&block), sha1(b"filepwgen"));
+
+        // Второй блок - тот же контекст, дополненный seed еще раз
+        stream.read_exact(&mut block)?;
+        assert_eq!(hex(&block), sha1(b"filepwgenpwgen"));
+        Ok(())
+    }
+}
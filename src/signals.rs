@@ -0,0 +1,74 @@
+use std::io;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+pub const SIGINT: i32 = 2;
+pub const SIGTERM: i32 = 15;
+
+// Номер пришедшего сигнала или 0. Обработчик только записывает сюда
+// значение (атомарная запись безопасна в обработчике сигнала), а вся
+// очистка идет в основном потоке: генерация и вывод проверяют флаг
+// между паролями и завершаются ошибкой, буферы затираются в Drop.
+static PENDING: AtomicI32 = AtomicI32::new(0);
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::c_int;
+
+    unsafe extern "C" {
+        pub fn signal(signum: c_int, handler: usize) -> usize;
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn handle(signum: std::ffi::c_int) {
+    PENDING.store(signum, Ordering::SeqCst);
+}
+
+// Перехватывает SIGINT и SIGTERM
+#[cfg(unix)]
+pub fn install() {
+    for signum in [SIGINT, SIGTERM] {
+        // SAFETY: обработчик только выполняет атомарную запись
+        unsafe { sys::signal(signum, handle as extern "C" fn(std::ffi::c_int) as usize) };
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+pub fn pending() -> Option<i32> {
+    match PENDING.load(Ordering::SeqCst) {
+        0 => None,
+        signum => Some(signum),
+    }
+}
+
+// Ошибка, если пришел сигнал: вызывается между паролями
+pub fn check() -> io::Result<()> {
+    match pending() {
+        Some(signum) => Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            format!("interrupted by signal {}", signum),
+        )),
+        None => Ok(()),
+    }
+}
+
+// Код выхода по соглашению оболочки: 128 + номер сигнала (130 для SIGINT,
+// 143 для SIGTERM)
+pub fn exit_code() -> Option<i32> {
+    pending().map(|signum| 128 + signum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_signal_pending() {
+        // Тесты не посылают сигналы процессу, флаг остается сброшенным
+        assert_eq!(pending(), None);
+        assert!(check().is_ok());
+        assert_eq!(exit_code(), None);
+    }
+}
@@ -0,0 +1,280 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::rng::{EntropySource, random_index};
+use crate::{Case, Config, MAX_LENGTH, RunError, Syllabled, UPPERCASE, build_charset, is_allowed, phoneme, required_classes};
+
+// `--syllable-file`: запоминаемые пароли из слогов пользователя, по слогу
+// на строку файла. Слоги выбираются равновероятно. Слог, который не
+// помещается в остаток пароля, перевыбирается из тех, что помещаются, а
+// если не помещается ни один, обрезается последний слог. Цифры, заглавные
+// и символы добавляет apply_requirements.
+//
+// `--syllables N`: пароль ровно из N слогов - из файла или пар "согласный
+// и гласный элемент" таблицы pwgen (phoneme::syllables), - и его длина
+// зависит от выпавших слогов.
+
+// Самый длинный встроенный слог - четыре буквы ("thee")
+pub const MAX_COUNT: usize = MAX_LENGTH / 4;
+
+pub fn load(path: &Path, config: &Config) -> Result<Vec<Vec<char>>, RunError> {
+    let text = fs::read_to_string(path).map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => RunError::Settings(format!("{}: not valid UTF-8", path.display())),
+        _ => io::Error::new(e.kind(), format!("cannot read {}: {}", path.display(), e)).into(),
+    })?;
+    let syllables = parse(&text).map_err(|e| RunError::Settings(format!("{}: {}", path.display(), e)))?;
+    let syllables = filter(syllables, config);
+    if syllables.is_empty() {
+        return Err(RunError::Settings(format!(
+            "{}: no syllables left after applying --ambiguous/--remove-chars/--include-only",
+            path.display()
+        )));
+    }
+    Ok(syllables)
+}
+
+// Пробелы по краям строки отбрасываются, пустые строки и повторы
+// пропускаются: повтор сделал бы слог вероятнее остальных
+fn parse(text: &str) -> Result<Vec<Vec<char>>, String> {
+    let mut syllables: Vec<Vec<char>> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(c) = line.chars().find(|c| c.is_whitespace() || c.is_control()) {
+            return Err(format!("line {}: {:?} cannot be part of a syllable", number + 1, c));
+        }
+        let syllable: Vec<char> = line.chars().collect();
+        if !syllable.is_empty() && !syllables.contains(&syllable) {
+            syllables.push(syllable);
+        }
+    }
+    if syllables.is_empty() {
+        return Err("contains no syllables".to_string());
+    }
+    Ok(syllables)
+}
+
+// Слоги с исключенными через -r, -B и --include-only символами выпадают
+fn filter(syllables: Vec<Vec<char>>, config: &Config) -> Vec<Vec<char>> {
+    // С --case upper слоги сразу пишутся заглавными
+    let upper = config.case == Some(Case::Upper);
+    syllables
+        .into_iter()
+        .map(|syllable| if upper { syllable.iter().map(char::to_ascii_uppercase).collect() } else { syllable })
+        .filter(|syllable| syllable.iter().all(|&c| is_allowed(c, config)))
+        .collect()
+}
+
+// Пароль и позиции, с которых начинаются его слоги, для --case title
+pub fn generate<R: EntropySource + ?Sized>(
+    length: usize,
+    syllables: &[Vec<char>],
+    rng: &mut R,
+) -> Result<Syllabled, RunError> {
+    let mut password: Vec<char> = Vec::with_capacity(length);
+    let mut starts = Vec::new();
+    while password.len() < length {
+        starts.push(password.len());
+        let left = length - password.len();
+        let fitting: Vec<&Vec<char>> = syllables.iter().filter(|syllable| syllable.len() <= left).collect();
+        let syllable = if fitting.is_empty() {
+            &syllables[random_index(rng, syllables.len())?][..left]
+        } else {
+            &fitting[random_index(rng, fitting.len())?][..]
+        };
+        password.extend_from_slice(syllable);
+    }
+    Ok((password, starts))
+}
+
+// Слоги для --syllables: из --syllable-file, если он задан
+pub fn inventory(config: &Config) -> Vec<Vec<char>> {
+    match &config.syllables {
+        Some(list) => list.clone(),
+        None => phoneme::syllables(config),
+    }
+}
+
+// Самый короткий и самый длинный слог
+pub fn lengths(syllables: &[Vec<char>]) -> (usize, usize) {
+    let lengths = syllables.iter().map(Vec::len);
+    (lengths.clone().min().unwrap_or(0), lengths.max().unwrap_or(0))
+}
+
+// Если нужна заглавная, ею становится первая буква случайного слога, у
+// которой заглавная не исключена; иначе ее вставит apply_requirements.
+// Начала слогов возвращаются вместе с паролем, как у generate.
+pub fn compose<R: EntropySource + ?Sized>(
+    count: usize,
+    syllables: &[Vec<char>],
+    config: &Config,
+    rng: &mut R,
+) -> Result<Syllabled, RunError> {
+    let mut password: Vec<char> = Vec::with_capacity(count * lengths(syllables).1);
+    let mut starts = Vec::with_capacity(count);
+    for _ in 0..count {
+        starts.push(password.len());
+        password.extend_from_slice(&syllables[random_index(rng, syllables.len())?]);
+    }
+    let uppercase = required_classes(config, &build_charset(config)).iter().any(|(_, class, _)| *class == UPPERCASE);
+    if uppercase {
+        let lower: Vec<usize> = starts
+            .iter()
+            .cloned()
+            .filter(|&i| password[i].is_ascii_lowercase() && is_allowed(password[i].to_ascii_uppercase(), config))
+            .collect();
+        if !lower.is_empty() {
+            let pos = lower[random_index(rng, lower.len())?];
+            password[pos].make_ascii_uppercase();
+        }
+    }
+    Ok((password, starts))
+}
+
+// Оценка для --dry-run: энтропия выбора слогов. Разные слоги могут
+// сложиться в один пароль (ka + ra и kar + a), так что это оценка сверху.
+pub fn entropy(length: usize, syllables: &[Vec<char>]) -> f64 {
+    // bits[r] - энтропия оставшихся r символов
+    let mut bits = vec![0.0; length + 1];
+    for r in 1..=length {
+        let fitting: Vec<usize> = syllables.iter().map(Vec::len).filter(|&len| len <= r).collect();
+        bits[r] = if fitting.is_empty() {
+            (syllables.len() as f64).log2()
+        } else {
+            let rest: f64 = fitting.iter().map(|&len| bits[r - len]).sum();
+            (fitting.len() as f64).log2() + rest / fitting.len() as f64
+        };
+    }
+    bits[length]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+
+    fn fixture() -> Vec<Vec<char>> {
+        parse(include_str!("../tests/fixtures/syllables.txt")).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let syllables = fixture();
+        // Пустая строка и повтор "ра" пропущены
+        let text: Vec<String> = syllables.iter().map(|syllable| syllable.iter().collect()).collect();
+        assert_eq!(text, ["ка", "ра", "ми", "до", "сто", "лу", "ня", "ёж"]);
+        assert_eq!(parse("ka\n\n  \n"), Ok(vec![vec!['k', 'a']]));
+        assert_eq!(parse("ka ra\n"), Err("line 1: ' ' cannot be part of a syllable".to_string()));
+        assert_eq!(parse("\n \n"), Err("contains no syllables".to_string()));
+    }
+
+    #[test]
+    fn test_composition() -> Result<(), RunError> {
+        let syllables = fixture();
+        let mut rng = ChaCha20Rng::seeded(571);
+        for length in [1, 5, 8, 12] {
+            for _ in 0..200 {
+                let (password, starts) = generate(length, &syllables, &mut rng)?;
+                assert_eq!(password.len(), length);
+                // Каждый слог, кроме, может быть, последнего, - слог файла
+                let mut ends = starts[1..].to_vec();
+                ends.push(length);
+                for (&start, &end) in starts.iter().zip(&ends[..ends.len() - 1]) {
+                    assert!(syllables.contains(&password[start..end].to_vec()), "{:?} {:?}", password, starts);
+                }
+                // Пароль разбивается на слоги файла, кроме, может быть, последнего
+                let mut rest = &password[..];
+                while let Some(syllable) = syllables.iter().find(|syllable| rest.starts_with(syllable)) {
+                    rest = &rest[syllable.len()..];
+                }
+                assert!(rest.is_empty() || rest.len() == 1, "{:?}", password);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncation() -> Result<(), RunError> {
+        // Два символа не вмещают ни одного слога: первый слог обрезается
+        let syllables: Vec<Vec<char>> = ["сто", "ёжик"].iter().map(|s| s.chars().collect()).collect();
+        let mut rng = ChaCha20Rng::seeded(5710);
+        for _ in 0..50 {
+            let password: String = generate(2, &syllables, &mut rng)?.0.into_iter().collect();
+            assert!(password == "ст" || password == "ёж", "{}", password);
+            // На остаток в 1 символ после "сто" снова обрезанный слог
+            let password: String = generate(4, &syllables, &mut rng)?.0.into_iter().collect();
+            assert!(["ёжик", "стос", "стоё"].contains(&password.as_str()), "{}", password);
+        }
+        // Одна буква: выбор из двух слогов
+        assert!((entropy(1, &syllables) - 1.0).abs() < 1e-9);
+        // Три: только "сто", а одна буква после обрезанного не остается
+        assert!((entropy(3, &syllables) - 0.0).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compose() -> Result<(), RunError> {
+        // Пары "согласный и гласный элемент", все разные
+        let config = Config { no_capitalize: true, ..Config::default() };
+        let builtin = inventory(&config);
+        assert_eq!(builtin.len(), 25 * 13);
+        let mut unique = builtin.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), builtin.len());
+        assert_eq!(lengths(&builtin), (2, 4));
+
+        // На сколько слогов раскладывается пароль, всеми способами
+        let splits = |password: &[char], list: &[Vec<char>]| {
+            let mut counts: Vec<Vec<usize>> = vec![Vec::new(); password.len() + 1];
+            counts[0].push(0);
+            for i in 0..password.len() {
+                for syllable in list.iter().filter(|syllable| password[i..].starts_with(syllable)) {
+                    let next: Vec<usize> = counts[i].iter().map(|n| n + 1).collect();
+                    counts[i + syllable.len()].extend(next);
+                }
+            }
+            counts.swap_remove(password.len())
+        };
+        let mut rng = ChaCha20Rng::seeded(589);
+        for count in [1, 3, 6] {
+            for _ in 0..100 {
+                let (password, starts) = compose(count, &builtin, &config, &mut rng)?;
+                assert!(splits(&password, &builtin).contains(&count), "{:?}", password);
+                assert_eq!(starts.len(), count);
+                let (password, _) = compose(count, &fixture(), &config, &mut rng)?;
+                assert!(splits(&password, &fixture()).contains(&count), "{:?}", password);
+            }
+        }
+
+        // С -c заглавной становится начало одного из слогов
+        let config = Config::default();
+        for _ in 0..100 {
+            let (password, starts) = compose(4, &builtin, &config, &mut rng)?;
+            let upper: Vec<usize> = (0..password.len()).filter(|&i| password[i].is_ascii_uppercase()).collect();
+            assert_eq!(upper.len(), 1, "{:?}", password);
+            let lower: Vec<char> = password.iter().map(char::to_ascii_lowercase).collect();
+            assert!(splits(&lower, &builtin).contains(&4), "{:?}", password);
+            assert!(!splits(&lower[..upper[0]], &builtin).is_empty(), "{:?}", password);
+            assert!(starts.contains(&upper[0]), "{:?}", password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter() {
+        let config = Config {
+            remove_chars: Some(vec!['к', 'ё']),
+            ..Config::default()
+        };
+        let text: Vec<String> = filter(fixture(), &config).iter().map(|syllable| syllable.iter().collect()).collect();
+        assert_eq!(text, ["ра", "ми", "до", "сто", "лу", "ня"]);
+        // С -B из латинских слогов выпадают те, где есть l
+        let config = Config {
+            ambiguous: true,
+            ..Config::default()
+        };
+        let syllables = parse("la\nmo\nli\n").unwrap();
+        assert_eq!(filter(syllables, &config), vec![vec!['m', 'o']]);
+    }
+}
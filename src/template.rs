@@ -0,0 +1,221 @@
+use std::io;
+
+use crate::rng::{EntropySource, random_index};
+use crate::{CONSONANTS_LOWER, Config, LOWERCASE, MAX_LENGTH, NUMERALS, RunError, VOWELS};
+use crate::{build_charset, class_chars, is_allowed, symbol_class};
+
+// `--template`: шаблон в духе APG, по символу пароля на позицию шаблона.
+// Буквы классов из CLASSES выбирают символ из класса, остальное и все,
+// что после "\", попадает в пароль как есть. -r, -B и --include-only
+// сужают каждый класс; длину пароля задает шаблон.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Class {
+    Consonant,
+    UpperConsonant,
+    Vowel,
+    UpperVowel,
+    Digit,
+    Symbol,
+    Letter,
+    Any,
+}
+
+// Буква шаблона, класс и его название для сообщений
+const CLASSES: &[(char, Class, &str)] = &[
+    ('c', Class::Consonant, "lowercase consonant"),
+    ('C', Class::UpperConsonant, "uppercase consonant"),
+    ('v', Class::Vowel, "lowercase vowel"),
+    ('V', Class::UpperVowel, "uppercase vowel"),
+    ('n', Class::Digit, "digit"),
+    ('s', Class::Symbol, "symbol"),
+    ('a', Class::Letter, "letter"),
+    ('x', Class::Any, "character of the character set"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slot {
+    Class(Class),
+    Literal(char),
+}
+
+// Позиции шаблона с номерами столбцов (с 1) для сообщений
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pattern: String,
+    slots: Vec<(usize, Slot)>,
+}
+
+impl Template {
+    // Ошибка - что ожидалось, со столбцом, если он есть
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let mut slots = Vec::new();
+        let mut chars = pattern.chars().enumerate();
+        while let Some((column, c)) = chars.next() {
+            let slot = if c == '\\' {
+                match chars.next() {
+                    Some((_, escaped)) => Slot::Literal(escaped),
+                    None => return Err(format!("a character after '\\' at column {}", column + 1)),
+                }
+            } else {
+                match CLASSES.iter().find(|(letter, _, _)| *letter == c) {
+                    Some(&(_, class, _)) => Slot::Class(class),
+                    None => Slot::Literal(c),
+                }
+            };
+            slots.push((column + 1, slot));
+        }
+        if slots.is_empty() {
+            return Err("a pattern such as Cvcvnn-Cvcvnn".to_string());
+        }
+        if slots.len() > MAX_LENGTH {
+            return Err(format!("a pattern of at most {} characters", MAX_LENGTH));
+        }
+        Ok(Self { pattern: pattern.to_string(), slots })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    // Длина пароля по шаблону
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    // Из чего выбирается каждая позиция: у буквального символа - он сам.
+    // Класс, который опции опустошили, - ошибка с его столбцом.
+    pub fn pools(&self, config: &Config) -> Result<Vec<Vec<char>>, RunError> {
+        let charset = build_charset(config);
+        let mut pools = Vec::with_capacity(self.slots.len());
+        for &(column, slot) in &self.slots {
+            let pool = match slot {
+                Slot::Literal(c) => vec![c],
+                Slot::Class(class) => {
+                    let upper = |c: char| c.to_ascii_uppercase();
+                    let pool: Vec<char> = match class {
+                        Class::Consonant => class_chars(CONSONANTS_LOWER).collect(),
+                        Class::UpperConsonant => class_chars(CONSONANTS_LOWER).map(upper).collect(),
+                        Class::Vowel => class_chars(VOWELS).filter(char::is_ascii_lowercase).collect(),
+                        Class::UpperVowel => class_chars(VOWELS).filter(char::is_ascii_uppercase).collect(),
+                        Class::Digit => class_chars(NUMERALS).collect(),
+                        Class::Symbol => class_chars(symbol_class(config)).collect(),
+                        Class::Letter => class_chars(LOWERCASE).chain(class_chars(LOWERCASE).map(upper)).collect(),
+                        Class::Any => charset.clone(),
+                    };
+                    let pool: Vec<char> = pool.into_iter().filter(|&c| is_allowed(c, config)).collect();
+                    if pool.is_empty() {
+                        let (letter, _, name) = CLASSES.iter().find(|(_, other, _)| *other == class).unwrap();
+                        return Err(RunError::Settings(format!(
+                            "--template: no {} is left for '{}' at column {} after applying \
+                             --ambiguous/--remove-chars/--include-only",
+                            name, letter, column
+                        )));
+                    }
+                    pool
+                }
+            };
+            pools.push(pool);
+        }
+        Ok(pools)
+    }
+}
+
+pub fn generate<R: EntropySource + ?Sized>(pools: &[Vec<char>], rng: &mut R) -> io::Result<String> {
+    // Строка сразу нужной емкости, чтобы при росте не оставалось копий
+    let capacity = pools.iter().map(|pool| pool.iter().map(|c| c.len_utf8()).max().unwrap_or(1)).sum();
+    let mut password = String::with_capacity(capacity);
+    for pool in pools {
+        password.push(pool[random_index(rng, pool.len())?]);
+    }
+    Ok(password)
+}
+
+// Энтропия пароля по шаблону: буквальные символы ее не добавляют
+pub fn entropy(pools: &[Vec<char>]) -> f64 {
+    pools.iter().map(|pool| (pool.len() as f64).log2()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+
+    #[test]
+    fn test_parse() {
+        let template = Template::parse("Cv-\\n\\\\x").unwrap();
+        let slots: Vec<Slot> = template.slots.iter().map(|&(_, slot)| slot).collect();
+        assert_eq!(
+            slots,
+            [
+                Slot::Class(Class::UpperConsonant),
+                Slot::Class(Class::Vowel),
+                Slot::Literal('-'),
+                Slot::Literal('n'),
+                Slot::Literal('\\'),
+                Slot::Class(Class::Any),
+            ]
+        );
+        assert_eq!(template.len(), 6);
+        // Столбцы считаются по символам шаблона; у экранированного - столбец "\"
+        let columns: Vec<usize> = template.slots.iter().map(|&(column, _)| column).collect();
+        assert_eq!(columns, [1, 2, 3, 4, 6, 8]);
+
+        assert_eq!(Template::parse("nnnn\\"), Err("a character after '\\' at column 5".to_string()));
+        assert!(Template::parse("").is_err());
+        assert!(Template::parse(&"x".repeat(MAX_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_slot_classes() -> Result<(), RunError> {
+        let config = Config::default();
+        let mut rng = ChaCha20Rng::seeded(579);
+        type Check = fn(char) -> bool;
+        let checks: &[(&str, Check)] = &[
+            ("c", |c| CONSONANTS_LOWER.contains(&(c as u8))),
+            ("C", |c| c.is_ascii_uppercase() && !"AEIOUY".contains(c)),
+            ("v", |c| "aeiouy".contains(c)),
+            ("V", |c| "AEIOUY".contains(c)),
+            ("n", |c| c.is_ascii_digit()),
+            ("s", |c| c.is_ascii_punctuation()),
+            ("a", |c| c.is_ascii_alphabetic()),
+            ("x", |c| c.is_ascii_alphanumeric()),
+            ("é", |c| c == 'é'),
+        ];
+        for (pattern, check) in checks {
+            let pools = Template::parse(&pattern.repeat(40)).unwrap().pools(&config)?;
+            let password = generate(&pools, &mut rng)?;
+            assert_eq!(password.chars().count(), 40);
+            assert!(password.chars().all(check), "{}: {}", pattern, password);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pools_respect_exclusions() -> Result<(), RunError> {
+        let config = Config {
+            ambiguous: true,
+            remove_chars: Some(vec!['b', 'c', '7']),
+            ..Config::default()
+        };
+        let pools = Template::parse("cn").unwrap().pools(&config)?;
+        assert!(!pools[0].contains(&'b') && !pools[0].contains(&'c'));
+        assert_eq!(pools[1], ['3', '4', '9']);
+
+        // Пустой класс - ошибка со столбцом
+        let config = Config {
+            remove_chars: Some(NUMERALS.iter().map(|&c| char::from(c)).collect()),
+            ..Config::default()
+        };
+        let err = Template::parse("Cv-nn").unwrap().pools(&config).unwrap_err();
+        assert!(err.to_string().contains("no digit is left for 'n' at column 4"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_entropy() -> Result<(), RunError> {
+        let pools = Template::parse("nn-\\n").unwrap().pools(&Config::default())?;
+        assert!((entropy(&pools) - 2.0 * 10f64.log2()).abs() < 1e-9);
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use std::io::{self, IsTerminal};
+
+// Размер, под который раскладываются столбцы и считается число паролей,
+// если stdout - не терминал (файл, канал) или размер узнать не удалось.
+// Так же делает pwgen.
+pub const DEFAULT_WIDTH: usize = 80;
+pub const DEFAULT_HEIGHT: usize = 24;
+
+// Размер терминала в символах
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size {
+    pub width: usize,
+    pub height: usize,
+}
+
+pub const DEFAULT_SIZE: Size = Size {
+    width: DEFAULT_WIDTH,
+    height: DEFAULT_HEIGHT,
+};
+
+// Откуда main узнает о терминале; в тестах вместо stdout подставляется
+// терминал нужного размера
+pub trait Terminal {
+    // Подключен ли stdout к терминалу
+    fn is_terminal(&self) -> bool;
+    // Размер терминала; None, если это не терминал или размер не узнать
+    fn size(&self) -> Option<Size>;
+}
+
+// Настоящий stdout
+pub struct Stdout;
+
+impl Terminal for Stdout {
+    fn is_terminal(&self) -> bool {
+        io::stdout().is_terminal()
+    }
+
+    fn size(&self) -> Option<Size> {
+        sys::stdout_size().filter(|size| size.width > 0 && size.height > 0)
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{c_int, c_ulong, c_ushort};
+
+    use super::Size;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Winsize {
+        ws_row: c_ushort,
+        ws_col: c_ushort,
+        ws_xpixel: c_ushort,
+        ws_ypixel: c_ushort,
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const TIOCGWINSZ: c_ulong = 0x5413;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const TIOCGWINSZ: c_ulong = 0x40087468;
+
+    unsafe extern "C" {
+        fn isatty(fd: c_int) -> c_int;
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    pub fn stdout_size() -> Option<Size> {
+        // SAFETY: isatty только проверяет дескриптор
+        if unsafe { isatty(1) } != 1 {
+            return None;
+        }
+        let mut size = Winsize::default();
+        // SAFETY: TIOCGWINSZ записывает ровно одну структуру winsize
+        if unsafe { ioctl(1, TIOCGWINSZ, &mut size as *mut Winsize) } != 0 {
+            return None;
+        }
+        Some(Size {
+            width: size.ws_col as usize,
+            height: size.ws_row as usize,
+        })
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+
+    use super::Size;
+
+    // Поля заполняет Windows, читаются не все
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct ConsoleScreenBufferInfo {
+        size: Coord,
+        cursor_position: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord,
+    }
+
+    // (DWORD)-11
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleScreenBufferInfo(console: *mut c_void, info: *mut ConsoleScreenBufferInfo) -> i32;
+    }
+
+    // Видимое окно консоли, а не весь буфер с прокруткой
+    pub fn stdout_size() -> Option<Size> {
+        let mut info = ConsoleScreenBufferInfo::default();
+        // SAFETY: GetStdHandle не требует ничего; GetConsoleScreenBufferInfo
+        // записывает ровно одну структуру и возвращает 0 не для консоли
+        let ok = unsafe { GetConsoleScreenBufferInfo(GetStdHandle(STD_OUTPUT_HANDLE), &mut info) };
+        if ok == 0 {
+            return None;
+        }
+        let width = i32::from(info.window.right) - i32::from(info.window.left) + 1;
+        let height = i32::from(info.window.bottom) - i32::from(info.window.top) + 1;
+        Some(Size {
+            width: usize::try_from(width).ok()?,
+            height: usize::try_from(height).ok()?,
+        })
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use super::Size;
+
+    pub fn stdout_size() -> Option<Size> {
+        None
+    }
+}
@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::lang::{self, Lang};
+use crate::rng::{EntropySource, random_index};
+use crate::{Config, NUMERALS, RunError, class_chars, is_allowed, symbol_class};
+
+// `--words N`: парольная фраза из N слов списка через разделитель. Список
+// встроен в программу на языке --lang (см. lang.rs) или читается из файла
+// --wordlist. Строка списка может начинаться с номера для игральных
+// костей, как в списках EFF ("11111\tabacus"), так что их можно
+// подставить как есть.
+
+pub const DEFAULT_SEPARATOR: &str = "-";
+
+// Встроенные списки меньше списка EFF для костей (7776 слов, 12.9 бита на
+// слово): в en 1650 слов, 10.7 бита, в de, fr, es и ru от 1131 до 1492
+// слов, 10.1-10.5 бита. Фраза из них на 2-3 бита на слово слабее, так что
+// для той же стойкости нужно больше слов. Список меньше SMALL_LIST слабее
+// настолько, что --wordlist о нем предупреждает.
+pub const SMALL_LIST: usize = 1000;
+
+// Слова для фраз: список из --wordlist, загруженный main, или встроенный
+pub fn words(config: &Config) -> Result<Vec<String>, RunError> {
+    match &config.wordlist {
+        Some(list) => Ok(list.clone()),
+        None => embedded(config),
+    }
+}
+
+// Язык встроенного списка: --lang или первый из собранных, обычно en
+pub fn language(config: &Config) -> Option<Lang> {
+    config.lang.or_else(|| lang::built().first().copied())
+}
+
+fn embedded(config: &Config) -> Result<Vec<String>, RunError> {
+    let Some(lang) = language(config) else {
+        return Err(RunError::Settings(
+            "this build has no embedded wordlist (cargo features \"wordlist-*\"); use --wordlist FILE".to_string(),
+        ));
+    };
+    let Some(text) = lang.text() else {
+        return Err(RunError::Settings(lang::missing(lang, &lang::built())));
+    };
+    usable(parse(text), config, &format!("the {} wordlist", lang.name()))
+}
+
+// --wordlist: файл или "-" для stdin, в UTF-8
+pub fn load(path: &Path, config: &Config) -> Result<Vec<String>, RunError> {
+    let text = if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => RunError::Settings("--wordlist -: stdin is not valid UTF-8".to_string()),
+            _ => io::Error::new(e.kind(), format!("cannot read the wordlist from stdin: {}", e)).into(),
+        })?;
+        text
+    } else {
+        fs::read_to_string(path).map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => RunError::Settings(format!("{}: not valid UTF-8", path.display())),
+            _ => io::Error::new(e.kind(), format!("cannot read {}: {}", path.display(), e)).into(),
+        })?
+    };
+    usable(parse(&text), config, &path.display().to_string())
+}
+
+// Слова без символов, исключенных через -r и -B, в том числе после
+// --capitalize-words; из одного слова фразу не составить
+fn usable(words: Vec<String>, config: &Config, source: &str) -> Result<Vec<String>, RunError> {
+    let words: Vec<String> = words
+        .into_iter()
+        .filter(|word| word.chars().all(|c| is_allowed(c, config)))
+        .filter(|word| !config.capitalize_words || capitalized(word).all(|c| is_allowed(c, config)))
+        .collect();
+    if words.len() < 2 {
+        return Err(RunError::Settings(format!(
+            "{}: fewer than 2 distinct words left after applying --ambiguous/--remove-chars/--include-only",
+            source
+        )));
+    }
+    Ok(words)
+}
+
+// Слово - строка без пробелов по краям и без номера для костей перед ним.
+// Пустые строки и повторы отбрасываются: повтор сделал бы слово вероятнее
+// остальных.
+fn parse(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            match line.split_once(char::is_whitespace) {
+                Some((number, word)) if number.chars().all(|c| c.is_ascii_digit()) => word.trim(),
+                _ => line,
+            }
+        })
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect();
+    words.sort_unstable();
+    words.dedup();
+    words
+}
+
+// Как слова складываются во фразу
+pub struct Style {
+    pub separator: String,
+    // --capitalize-words: первая буква каждого слова заглавная
+    pub capitalize: bool,
+    // --inject-digit и --inject-symbol: из чего выбирается вставка; пустой
+    // набор - вставки нет
+    pub digits: Vec<char>,
+    pub symbols: Vec<char>,
+}
+
+impl Style {
+    // Цифры и символы для вставок проходят те же -B, -r и --shell-safe,
+    // что и в паролях
+    pub fn new(config: &Config) -> Result<Self, RunError> {
+        let pool = |wanted: bool, class: &[u8], flag: &str| {
+            let chars: Vec<char> = if wanted { class_chars(class).filter(|&c| is_allowed(c, config)).collect() } else { Vec::new() };
+            if wanted && chars.is_empty() {
+                return Err(RunError::Settings(format!("{}: no characters left to insert", flag)));
+            }
+            Ok(chars)
+        };
+        Ok(Self {
+            separator: config.separator.clone().unwrap_or_else(|| DEFAULT_SEPARATOR.to_string()),
+            capitalize: config.capitalize_words,
+            digits: pool(config.inject_digit, NUMERALS, "--inject-digit")?,
+            symbols: pool(config.inject_symbol, symbol_class(config), "--inject-symbol")?,
+        })
+    }
+}
+
+// Слово с заглавной первой буквой; у не-ASCII букв заглавная бывает и из
+// двух символов
+fn capitalized(word: &str) -> impl Iterator<Item = char> + '_ {
+    let mut chars = word.chars();
+    let first = chars.next().into_iter().flat_map(char::to_uppercase);
+    first.chain(chars)
+}
+
+// Кандидаты для каждого места фразы: весь список или, с --words-acrostic,
+// слова на букву этого места
+pub struct Slots {
+    words: Vec<String>,
+    initials: Option<Vec<Vec<String>>>,
+    count: usize,
+}
+
+impl Slots {
+    pub fn new(count: usize, words: Vec<String>, acrostic: Option<&[char]>) -> Result<Self, RunError> {
+        let Some(letters) = acrostic else {
+            return Ok(Self {
+                words,
+                initials: None,
+                count,
+            });
+        };
+        // Слова по первой букве, без учета регистра
+        let mut index: HashMap<char, Vec<String>> = HashMap::new();
+        for word in &words {
+            if let Some(initial) = initial(word) {
+                index.entry(initial).or_default().push(word.clone());
+            }
+        }
+        let mut initials = Vec::with_capacity(letters.len());
+        for &letter in letters {
+            match index.get(&letter) {
+                Some(candidates) => initials.push(candidates.clone()),
+                None => {
+                    return Err(RunError::Settings(format!(
+                        "--words-acrostic: no word in the wordlist starts with '{}'",
+                        letter
+                    )));
+                }
+            }
+        }
+        Ok(Self {
+            words,
+            initials: Some(initials),
+            count: letters.len(),
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn get(&self, position: usize) -> &[String] {
+        match &self.initials {
+            Some(initials) => &initials[position],
+            None => &self.words,
+        }
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+}
+
+// Первая буква слова в нижнем регистре, как ее сравнивает --words-acrostic
+pub fn initial(word: &str) -> Option<char> {
+    word.chars().next().and_then(|c| c.to_lowercase().next())
+}
+
+// Границ между словами фразы из count слов, считая начало и конец, -
+// count + 1. Цифра и символ вставляются каждый на свою случайную границу
+// без разделителя; на общей границе цифра идет первой.
+pub fn generate<R: EntropySource + ?Sized>(slots: &Slots, style: &Style, rng: &mut R) -> Result<String, RunError> {
+    let count = slots.count();
+    let mut picked = Vec::with_capacity(count);
+    for position in 0..count {
+        let candidates = slots.get(position);
+        picked.push(&candidates[random_index(rng, candidates.len())?]);
+    }
+    let mut inject = |pool: &[char]| -> Result<Option<(usize, char)>, RunError> {
+        if pool.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((random_index(rng, count + 1)?, pool[random_index(rng, pool.len())?])))
+    };
+    let digit = inject(&style.digits)?;
+    let symbol = inject(&style.symbols)?;
+
+    let mut phrase = String::new();
+    for boundary in 0..=count {
+        if boundary > 0 {
+            let word = picked[boundary - 1];
+            if style.capitalize {
+                phrase.extend(capitalized(word));
+            } else {
+                phrase.push_str(word);
+            }
+        }
+        for (at, c) in [digit, symbol].into_iter().flatten() {
+            if at == boundary {
+                phrase.push(c);
+            }
+        }
+        if boundary > 0 && boundary < count {
+            phrase.push_str(&style.separator);
+        }
+    }
+    Ok(phrase)
+}
+
+// Слова выбираются равновероятно: log2 от размера списка на слово
+pub fn bits_per_word(words: &[String]) -> f64 {
+    (words.len() as f64).log2()
+}
+
+// Вставка добавляет выбор границы и выбор символа
+pub fn injection_bits(count: usize, pool: &[char]) -> f64 {
+    if pool.is_empty() {
+        return 0.0;
+    }
+    ((count + 1) as f64).log2() + (pool.len() as f64).log2()
+}
+
+// Слова на каждом месте выбираются равновероятно из его кандидатов.
+// Заглавные буквы энтропии не добавляют: они есть в каждой фразе.
+pub fn entropy(slots: &Slots, style: &Style) -> f64 {
+    let count = slots.count();
+    let words: f64 = (0..count).map(|position| bits_per_word(slots.get(position))).sum();
+    words + injection_bits(count, &style.digits) + injection_bits(count, &style.symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::ChaCha20Rng;
+
+    fn slots(count: usize, words: &[String]) -> Slots {
+        Slots::new(count, words.to_vec(), None).unwrap()
+    }
+
+    fn plain(separator: &str) -> Style {
+        Style {
+            separator: separator.to_string(),
+            capitalize: false,
+            digits: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        // Номера костей и пробелы по краям отбрасываются
+        assert_eq!(parse("11111\tabacus\n11112 abdomen\n\n  acid \n"), ["abacus", "abdomen", "acid"]);
+        // Цифры в самом слове остаются
+        assert_eq!(parse("4wheel\n"), ["4wheel"]);
+    }
+
+    #[test]
+    fn test_load() -> Result<(), RunError> {
+        let path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/wordlist.txt"));
+        // Повторы и пустые строки отброшены
+        let words = load(path, &Config::default())?;
+        assert_eq!(words, ["anchor", "bright", "castle", "lantern", "meadow", "pillow"]);
+        let config = Config {
+            ambiguous: true,
+            ..Config::default()
+        };
+        assert_eq!(load(path, &config)?, ["anchor", "bright", "meadow"]);
+
+        // Одно слово - не список
+        let config = Config {
+            remove_chars: Some(vec!['a', 'l']),
+            ..Config::default()
+        };
+        let err = load(path, &config).unwrap_err();
+        assert!(err.to_string().contains("fewer than 2 distinct words"), "{}", err);
+        assert!(matches!(load(Path::new("/nonexistent/pwgen-rs-words"), &Config::default()), Err(RunError::Io(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_embedded_lists() -> Result<(), RunError> {
+        // По слову из каждого списка; у всех, кроме en, - с буквами не из
+        // ASCII. Размеры те, что указаны у SMALL_LIST и в README
+        let samples = [
+            (Lang::En, "anchor", 1650),
+            (Lang::De, "straße", 1492),
+            (Lang::Es, "niño", 1165),
+            (Lang::Fr, "château", 1314),
+            (Lang::Ru, "ёж", 1131),
+        ];
+        let built = lang::built();
+        let mut rng = ChaCha20Rng::seeded(574);
+        for (lang, sample, size) in samples {
+            let config = Config {
+                lang: Some(lang),
+                ..Config::default()
+            };
+            if !built.contains(&lang) {
+                let err = embedded(&config).unwrap_err();
+                assert_eq!(err.to_string(), lang::missing(lang, &built));
+                continue;
+            }
+            let words = embedded(&config)?;
+            assert!(words.len() > SMALL_LIST, "{}: {}", lang.name(), words.len());
+            assert_eq!(words.len(), size, "{}", lang.name());
+            assert!(words.iter().any(|word| word == sample), "{}", lang.name());
+            assert!(words.iter().all(|word| word.chars().all(|c| c.is_alphabetic() && c.is_lowercase())));
+            // Слова с диакритикой и кириллицей доходят до фразы целыми
+            let phrase = generate(&slots(6, &words), &plain(" "), &mut rng)?;
+            assert!(phrase.split(' ').all(|part| words.iter().any(|word| word == part)), "{}", phrase);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "wordlist-en")]
+    #[test]
+    fn test_embedded_list() -> Result<(), RunError> {
+        // -B убирает слова с l
+        let config = Config {
+            ambiguous: true,
+            ..Config::default()
+        };
+        assert!(embedded(&config)?.iter().all(|word| !word.contains('l')));
+        Ok(())
+    }
+
+    #[cfg(feature = "wordlist-en")]
+    #[test]
+    fn test_generate() -> Result<(), RunError> {
+        let words = embedded(&Config::default())?;
+        let mut rng = ChaCha20Rng::seeded(572);
+        for separator in ["-", " ", "::"] {
+            for count in [1, 4, 7] {
+                let phrase = generate(&slots(count, &words), &plain(separator), &mut rng)?;
+                let parts: Vec<&str> = phrase.split(separator).collect();
+                assert_eq!(parts.len(), count, "{}", phrase);
+                assert!(parts.iter().all(|part| words.iter().any(|word| word == part)), "{}", phrase);
+            }
+        }
+        // Без разделителя слова склеиваются
+        let two = ["ab".to_string(), "cd".to_string()];
+        let phrase = generate(&slots(3, &two), &plain(""), &mut rng)?;
+        assert_eq!(phrase.len(), 6);
+        assert!(phrase.as_bytes().chunks(2).all(|chunk| chunk == b"ab" || chunk == b"cd"), "{}", phrase);
+        assert_eq!(bits_per_word(&two), 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_style() -> Result<(), RunError> {
+        let words: Vec<String> = ["ёж", "apple", "ship", "moon"].iter().map(|s| s.to_string()).collect();
+        let style = Style {
+            separator: ".".to_string(),
+            capitalize: true,
+            digits: vec!['7', '8'],
+            symbols: vec!['#', '%'],
+        };
+        let mut rng = ChaCha20Rng::seeded(575);
+        let phrases: Vec<String> = (0..4).map(|_| generate(&slots(3, &words), &style, &mut rng)).collect::<Result<_, _>>()?;
+        assert_eq!(phrases, ["7Ёж.Moon#.Apple", "Ship.Ёж.Ship8%", "Moon8.Moon#.Apple", "#Ship.Ёж7.Apple"]);
+
+        // Граница выбирается равномерно из count + 1
+        let style = Style {
+            capitalize: false,
+            symbols: Vec::new(),
+            ..style
+        };
+        let mut at = [0; 4];
+        for _ in 0..4000 {
+            let phrase = generate(&slots(3, &words), &style, &mut rng)?;
+            let dots = phrase.split(|c: char| c.is_ascii_digit()).next().unwrap().matches('.').count();
+            // Цифра перед первой точкой - после слова 1, и так далее; в
+            // самом начале фразы - граница 0
+            let boundary = if phrase.starts_with(['7', '8']) { 0 } else { dots + 1 };
+            at[boundary] += 1;
+        }
+        assert!(at.iter().all(|&n| (850..1150).contains(&n)), "{:?}", at);
+
+        // 3 слова из 4 - 6 бит, цифра - log2 4 + log2 2 = 3 бита, символ еще 3
+        assert!((entropy(&slots(3, &words), &plain("-")) - 6.0).abs() < 1e-9);
+        assert!((entropy(&slots(3, &words), &style) - 9.0).abs() < 1e-9);
+        let both = Style {
+            symbols: vec!['#', '%'],
+            ..style
+        };
+        assert!((entropy(&slots(3, &words), &both) - 12.0).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_style_from_config() -> Result<(), RunError> {
+        // -B убирает из вставок 0, 1, 2, 5, 6, 8
+        let config = Config {
+            ambiguous: true,
+            inject_digit: true,
+            inject_symbol: true,
+            shell_safe: true,
+            ..Config::default()
+        };
+        let style = Style::new(&config)?;
+        assert_eq!(style.digits, ['3', '4', '7', '9']);
+        assert_eq!(style.symbols.iter().collect::<String>(), "@#%^*-_=+/");
+        let config = Config {
+            remove_chars: Some(('0'..='9').collect()),
+            ..config
+        };
+        assert!(matches!(Style::new(&config), Err(RunError::Settings(_))));
+        assert!(Style::new(&Config::default())?.digits.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_acrostic() -> Result<(), RunError> {
+        let words: Vec<String> = ["apple", "anchor", "arrow", "Banana", "nest", "ёж"].iter().map(|s| s.to_string()).collect();
+        let letters: Vec<char> = "banana".chars().collect();
+        let acrostic = Slots::new(0, words.clone(), Some(&letters))?;
+        assert_eq!(acrostic.count(), 6);
+        // Заглавная B в списке подходит к b
+        assert_eq!(acrostic.get(0), ["Banana"]);
+        assert_eq!(acrostic.get(1), ["apple", "anchor", "arrow"]);
+
+        let mut rng = ChaCha20Rng::seeded(576);
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..3000 {
+            let phrase = generate(&acrostic, &plain(" "), &mut rng)?;
+            let initials: String = phrase.split(' ').filter_map(initial).collect();
+            assert_eq!(initials, "banana", "{}", phrase);
+            for word in phrase.split(' ').skip(1).step_by(2) {
+                *counts.entry(word.to_string()).or_default() += 1;
+            }
+        }
+        // 9000 слов на a делятся поровну между тремя
+        for word in ["apple", "anchor", "arrow"] {
+            assert!((2800..3200).contains(&counts[word]), "{:?}", counts);
+        }
+        // Энтропия честно меньше: на a - log2 3, на b и n - по 0 бит
+        assert!((entropy(&acrostic, &plain(" ")) - 3.0 * 3f64.log2()).abs() < 1e-9);
+
+        let letters: Vec<char> = "ёжик".chars().collect();
+        let err = Slots::new(0, words, Some(&letters)).err().unwrap();
+        assert_eq!(err.to_string(), "--words-acrostic: no word in the wordlist starts with 'ж'");
+        Ok(())
+    }
+}
@@ -0,0 +1,479 @@
+// Коды выхода: 2 - ошибка в командной строке или невыполнимые настройки,
+// 4 - ошибка ввода-вывода
+use std::process::Command;
+
+fn run(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(args)
+        .output()
+        .unwrap();
+    (output.status.code(), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+#[test]
+fn test_usage_error_exit_code() {
+    let (code, stderr) = run(&["--bogus"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("unknown option --bogus"));
+    assert!(stderr.contains("--help"));
+
+    assert_eq!(run(&["1o", "5"]).0, Some(2));
+    assert_eq!(run(&["-r"]).0, Some(2));
+
+    // Один источник случайности: второй не отбрасывается молча
+    let (code, stderr) = run(&["--no-config", "--seed=1", "--rng=urandom"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("--rng cannot be used with --seed"), "{}", stderr);
+    assert_eq!(run(&["passphrase", "--seed=1", "--rng=urandom"]).0, Some(2));
+    let (code, stderr) = run(&["--no-config", "--rng=getrandom", "--random-source=/dev/urandom"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("--random-source cannot be used with --rng"), "{}", stderr);
+}
+
+#[test]
+fn test_unsatisfiable_settings_exit_code() {
+    // Разбор проходит, но набор символов оказывается пустым
+    let (code, stderr) = run(&["-s", "-A", "-0", "-v", "-r", "bcdfghjklmnpqrstvwxz"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("character set is empty"));
+    // Подсказка про --help здесь ни к чему: флаги сами по себе верные
+    assert!(!stderr.contains("--help"));
+
+    let (code, stderr) = run(&["--no-config", "-s", "-c", "-n", "-y", "2"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("cannot contain 3 required character classes"));
+}
+
+#[test]
+fn test_io_error_exit_code() {
+    let (code, stderr) = run(&["--no-config", "--rng", "file:/nonexistent/pwgen-rs-entropy"]);
+    assert_eq!(code, Some(4));
+    assert!(stderr.contains("/nonexistent/pwgen-rs-entropy"));
+
+    let (code, _) = run(&["--no-config", "-o", "/nonexistent/dir/passwords.txt"]);
+    assert_eq!(code, Some(4));
+}
+
+#[test]
+fn test_failed_run_leaves_no_output_file() {
+    let path = std::env::temp_dir().join(format!("pwgen-rs-failed-{}.txt", std::process::id()));
+    let file = path.to_str().unwrap();
+    // Невыполнимые настройки: файл даже не создается
+    let (code, _) = run(&["--no-config", "-s", "-A", "-0", "-r", "abcdefghijklmnopqrstuvwxyz", "-o", file, "8", "1"]);
+    assert_eq!(code, Some(2));
+    assert!(!path.exists());
+    // Ошибка источника случайности уже после создания файла
+    let (code, _) = run(&["--no-config", "--rng", "file:/nonexistent/pwgen-rs-entropy", "-o", file, "8", "1"]);
+    assert_eq!(code, Some(4));
+    assert!(!path.exists());
+    // Следующий запуск без --force проходит
+    assert_eq!(run(&["--no-config", "-o", file, "8", "1"]).0, Some(0));
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 9);
+    // Существующий файл с --force не удаляется, даже если запуск не удался
+    let (code, _) = run(&["--no-config", "--force", "--rng", "file:/nonexistent/pwgen-rs-entropy", "-o", file]);
+    assert_eq!(code, Some(4));
+    assert!(path.exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_self_test_passes_by_default() {
+    // Свежая сборка без настроек должна проходить все проверки
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "--self-test"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(!stdout.contains("FAIL"), "{}", stdout);
+    assert!(stdout.contains("Self-test passed"), "{}", stdout);
+}
+
+#[test]
+fn test_unknown_gpg_recipient() {
+    // Пустая связка ключей: получателя в ней заведомо нет
+    let home = std::env::temp_dir().join(format!("pwgen-rs-gnupg-{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    let path = home.join("passwords.gpg");
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "--encrypt-to", "nobody@example.invalid", "-o", path.to_str().unwrap()])
+        .env("GNUPGHOME", &home)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Без gpg проверять нечего
+    if !stderr.contains("cannot run gpg") {
+        assert_eq!(output.status.code(), Some(2), "{}", stderr);
+        assert!(stderr.contains("invalid --encrypt-to value 'nobody@example.invalid'"), "{}", stderr);
+        assert!(!path.exists());
+    }
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn test_output_file_matches_pipe() {
+    let path = std::env::temp_dir().join(format!("pwgen-rs-lines-{}.txt", std::process::id()));
+    let file = path.to_str().unwrap();
+    // Как `pwgen 8 5 > file`: по паролю в строке
+    assert_eq!(run(&["--no-config", "-o", file, "8", "5"]).0, Some(0));
+    let text = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 5, "{:?}", text);
+    assert!(lines.iter().all(|line| line.len() == 8), "{:?}", text);
+    std::fs::remove_file(&path).unwrap();
+    // Столбцы только по явному -C или --per-line
+    assert_eq!(run(&["--no-config", "--per-line=5", "-o", file, "8", "5"]).0, Some(0));
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(text.lines().count(), 1, "{:?}", text);
+    assert_eq!(text.split_whitespace().count(), 5);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_full_disk_exit_code() {
+    use std::fs::File;
+
+    // Записи с NUL не кончаются переводом строки, но ошибка все равно видна
+    for args in [&["-s", "8", "3"][..], &["-z", "-s", "8", "3"], &["-z", "--porcelain", "-s", "8", "3"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+            .arg("--no-config")
+            .args(args)
+            .stdout(File::create("/dev/full").unwrap())
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(4), "{:?}", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("No space left on device"), "{:?}: {}", args, stderr);
+    }
+}
+
+#[test]
+fn test_success_exit_code() {
+    assert_eq!(run(&["12", "3"]).0, Some(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_unicode_argument_is_usage_error() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .arg("-r")
+        .arg(OsStr::from_bytes(b"a\xff"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not valid Unicode"));
+}
+
+#[test]
+fn test_malformed_pwgen_opts_is_usage_error() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .env("PWGEN_OPTS", "-r \"ab")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unbalanced \" quote"));
+}
+
+#[test]
+fn test_interactive_needs_terminal() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "-i", "16"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("needs a terminal on stdin"));
+}
+
+#[test]
+fn test_porcelain_output() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "--porcelain", "12", "7"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.ends_with('\n'));
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 7);
+    for line in lines {
+        assert_eq!(line.len(), 12, "{:?}", line);
+        assert!(!line.contains(' '), "{:?}", line);
+    }
+}
+
+#[test]
+fn test_null_output() {
+    use std::io::{BufRead, BufReader};
+
+    for extra in [&[][..], &["--porcelain"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+            .args(["--no-config", "-z", "-sy", "12", "7"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(0));
+        assert!(!output.stdout.contains(&b'\n'));
+        let records: Vec<Vec<u8>> = BufReader::new(&output.stdout[..]).split(0).map(Result::unwrap).collect();
+        assert_eq!(records.len(), 7);
+        for record in records {
+            assert_eq!(record.len(), 12, "{:?}", record);
+        }
+    }
+}
+
+#[test]
+fn test_color() {
+    let stdout = |args: &[&str], no_color: Option<&str>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"));
+        command.args(["--no-config", "-sy", "12", "6"]).args(args).env_remove("NO_COLOR");
+        if let Some(value) = no_color {
+            command.env("NO_COLOR", value);
+        }
+        let output = command.output().unwrap();
+        assert_eq!(output.status.code(), Some(0));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    // В канал по умолчанию без цвета, с --color always - с цветом
+    assert!(!stdout(&[], None).contains('\x1b'));
+    assert!(stdout(&["--color", "always"], None).contains("\x1b["));
+    assert!(stdout(&["--color", "always"], Some("")).contains("\x1b["));
+    // NO_COLOR выключает цвет даже при --color always
+    assert!(!stdout(&["--color", "always"], Some("1")).contains('\x1b'));
+    // Машиночитаемый вывод цвета не получает
+    for args in [&["--color=always", "--porcelain"][..], &["--color=always", "-z"], &["--color=always", "--format=csv"]] {
+        assert!(!stdout(args, None).contains('\x1b'), "{:?}", args);
+    }
+}
+
+#[test]
+fn test_qr() {
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+            .args(["--no-config", "--seed=3", "-s", "12"])
+            .args(args)
+            .output()
+            .unwrap();
+        (output.status.code(), String::from_utf8(output.stdout).unwrap())
+    };
+    let (code, password) = run(&["1"]);
+    assert_eq!(code, Some(0));
+    // Пароль, затем код версии 1: 21 модуль и рамка по 4, по две строки модулей в строке текста
+    let (code, out) = run(&["--qr", "--qr-format", "utf8"]);
+    assert_eq!(code, Some(0));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[0], password.trim_end());
+    assert_eq!(lines.len(), 1 + 15);
+    assert!(lines[1..].iter().all(|line| line.chars().count() == 29), "{}", out);
+    let (code, out) = run(&["--qr-only", "--qr-format", "utf8"]);
+    assert_eq!(code, Some(0));
+    assert_eq!(out, lines[1..].iter().map(|line| format!("{}\n", line)).collect::<String>());
+    // Несколько паролей - только с --pick
+    assert_eq!(run(&["--qr", "3"]).0, Some(2));
+    assert_eq!(run(&["--qr", "--pick", "2", "3"]).0, Some(0));
+}
+
+#[test]
+fn test_unique_sort() {
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+            .args(["--no-config", "--seed=5", "-s", "--include-only=0123456789", "2"])
+            .args(args)
+            .output()
+            .unwrap();
+        (output.status.code(), String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+    };
+    // Все 100 двузначных паролей по порядку
+    let (code, out, _) = run(&["--unique", "--sort", "100"]);
+    assert_eq!(code, Some(0));
+    let expected: String = (0..100).map(|n| format!("{:02}\n", n)).collect();
+    assert_eq!(out, expected);
+    // Больше, чем бывает: ошибка до генерации, stdout пуст
+    let (code, out, stderr) = run(&["--unique", "101"]);
+    assert_eq!(code, Some(2));
+    assert_eq!(out, "");
+    assert!(stderr.contains("give only 100 different passwords, fewer than 101"), "{}", stderr);
+}
+
+#[test]
+fn test_show_entropy() {
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+            .args(["--no-config", "--seed=9", "-s", "--show-entropy"])
+            .args(args)
+            .output()
+            .unwrap();
+        (output.status.code(), String::from_utf8(output.stdout).unwrap())
+    };
+    let (code, out) = run(&["16", "3"]);
+    assert_eq!(code, Some(0));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[..3].iter().all(|line| line.len() == 16), "{}", out);
+    assert_eq!(lines[3], "Entropy: \u{2248}95.3 bits per password, \u{2248}285.8 bits for all 3");
+
+    // Разные длины: пометка у каждого пароля
+    let (code, out) = run(&["--length-range", "8-12", "-N", "5"]);
+    assert_eq!(code, Some(0));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 6);
+    for line in &lines[..5] {
+        let (password, label) = line.split_once(' ').unwrap();
+        let bits = password.len() as f64 * 62f64.log2();
+        assert_eq!(label, format!("(\u{2248}{:.1} bits)", bits));
+    }
+    assert!(lines[5].starts_with("Entropy: \u{2248}") && lines[5].ends_with(" bits for all 5"), "{}", out);
+}
+
+#[test]
+fn test_closed_pipe_exits_quietly() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    // Вывод намного больше буфера канала, так что запись упрется в закрытый конец
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "--porcelain", "64", "5000"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut first = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut first).unwrap();
+    assert_eq!(first.trim_end().len(), 64);
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_help_to_closed_pipe_exits_quietly() {
+    use std::process::Stdio;
+
+    // Канал закрыт раньше, чем справка в него пишется: `pwgen --help | head -0`
+    for args in [
+        &["--help"][..],
+        &["passphrase", "--help"],
+        &["check", "--help"],
+        &["completions", "--help"],
+        &["mangen", "--help"],
+    ] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        drop(child.stdout.take());
+        let output = child.wait_with_output().unwrap();
+        assert_eq!(output.status.code(), Some(0), "{:?}", args);
+        assert!(output.stderr.is_empty(), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+}
+
+#[test]
+fn test_dry_run_report() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "--dry-run", "-s", "-B", "-r", "a-f", "12", "5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Mode:       secure (-s)\n\
+         Length:     12\n\
+         Count:      5\n\
+         Lowercase:  ghijkmnopqrstuvwxyz (19)\n\
+         Uppercase:  ACEFHJKLMNPRTUVWXY (18)\n\
+         Digits:     3479 (4)\n\
+         Symbols:    none\n\
+         Charset:    41 characters\n\
+         Required:   uppercase, digit\n\
+         Entropy:    64.3 bits per password (12 x log2 41)\n"
+    );
+
+    // Пустой набор: отчет печатается, но код выхода - ошибка
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "--dry-run", "-s", "-A", "-0", "-r", "a-z"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Charset:    0 characters"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("character set is empty"));
+}
+
+#[test]
+fn test_syllable_file() {
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/syllables.txt");
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "--porcelain", "-A", "-0", "--syllable-file", fixture, "-r", "ё", "9", "20"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 20);
+    for line in stdout.lines() {
+        assert_eq!(line.chars().count(), 9, "{:?}", line);
+        // Слог "ёж" выпал вместе с ё
+        assert!(line.chars().all(|c| "кармидостлуня".contains(c)), "{:?}", line);
+    }
+
+    // Без гласных не остается ни одного слога
+    let (code, stderr) = run(&["--no-config", "--syllable-file", fixture, "-r", "аиоуяё"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("no syllables left"), "{}", stderr);
+}
+
+#[test]
+fn test_profanity_file() {
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/profanity.txt");
+    let generate = |filter: &[&str]| {
+        let mut args = vec!["--no-config", "--porcelain", "--seed", "584"];
+        args.extend_from_slice(filter);
+        args.extend(["10", "100"]);
+        let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs")).args(&args).output().unwrap();
+        assert_eq!(output.status.code(), Some(0));
+        String::from_utf8(output.stdout).unwrap().lines().map(str::to_string).collect::<Vec<_>>()
+    };
+    let unfiltered = generate(&[]);
+    let filtered = generate(&["--profanity-file", fixture]);
+    assert_eq!(filtered.len(), 100);
+    // В списке "ch", в таблице --leet у c и h нет похожих символов
+    assert!(filtered.iter().all(|line| !line.to_lowercase().contains("ch")), "{:?}", filtered);
+
+    // Чистые пароли проходят без изменений: до первого отброшенного пароля
+    // вывод совпадает с выводом без фильтра
+    let first = unfiltered.iter().position(|line| line.to_lowercase().contains("ch")).unwrap();
+    assert!(first > 0);
+    assert_eq!(filtered[..first], unfiltered[..first]);
+
+    // Ошибка в файле - ошибка настроек
+    let (code, stderr) = run(&["--no-config", "--profanity-file", concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("one word per line"), "{}", stderr);
+}
+
+#[test]
+fn test_bits_length() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["--no-config", "--porcelain", "-s", "-y", "--bits", "96", "-N", "5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    // 14 x log2 94 = 91.8 бит, 15 символов - 98.3
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "Length: 15 characters, 98.3 bits per password (--bits 96)\n");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 5);
+    assert!(stdout.lines().all(|line| line.chars().count() == 15), "{}", stdout);
+
+    // Длину считает набор после -r: 16 символов по 4 бита
+    let (code, stderr) = run(&["--no-config", "-s", "-A", "-r", "ghijklmnopqrstuvwxyz", "--bits", "64", "-N", "1"]);
+    assert_eq!(code, Some(0));
+    assert!(stderr.starts_with("Length: 16 characters, 64.0 bits per password"), "{}", stderr);
+}
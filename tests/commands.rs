@@ -0,0 +1,163 @@
+// Подкоманды generate, passphrase и check
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn run(args: &[&str], stdin: &str) -> (Option<i32>, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .env_remove("PWGEN_OPTS")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_generate_is_the_default_command() {
+    let plain = run(&["--no-config", "--seed=5", "-s", "12", "4"], "");
+    let generate = run(&["generate", "--no-config", "--seed=5", "-s", "12", "4"], "");
+    assert_eq!(plain.0, Some(0));
+    assert_eq!(generate, plain);
+    assert_eq!(plain.1.lines().count(), 4);
+
+    let (code, stdout, _) = run(&["generate", "--help"], "");
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("passphrase"));
+}
+
+#[test]
+fn test_passphrase() {
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/wordlist.txt");
+    let words = ["anchor", "bright", "castle", "lantern", "meadow", "pillow"];
+    let (code, stdout, _) = run(&["passphrase", "4", "2", "--seed", "1", "--wordlist", fixture], "");
+    assert_eq!(code, Some(0));
+    let phrases: Vec<&str> = stdout.lines().collect();
+    assert_eq!(phrases.len(), 2);
+    for phrase in phrases {
+        assert_eq!(phrase.split('-').count(), 4, "{}", phrase);
+        assert!(phrase.split('-').all(|word| words.contains(&word)), "{}", phrase);
+    }
+
+    // Один список на выбор
+    let (code, _, stderr) = run(&["passphrase", "--lang", "en", "--wordlist", fixture], "");
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("cannot be used with --wordlist"), "{}", stderr);
+
+    let (code, stdout, _) = run(&["passphrase", "--help"], "");
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with("Usage: pwgen passphrase"));
+
+    let (code, _, stderr) = run(&["passphrase", "-y"], "");
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("pwgen passphrase --help"), "{}", stderr);
+
+    // Разделитель, заглавные, вставки и вывод - те же, что у pwgen --words
+    let style = ["--separator", "_", "--capitalize-words", "--inject-digit", "--inject-symbol", "--show-entropy"];
+    let common = [&["--seed", "3", "--wordlist", fixture][..], &style].concat();
+    let subcommand = run(&[&["passphrase", "4", "3"][..], &common].concat(), "");
+    let words = run(&[&["--no-config", "--words", "4", "-N", "3"][..], &common].concat(), "");
+    assert_eq!(subcommand.0, Some(0), "{}", subcommand.2);
+    assert_eq!(subcommand.1, words.1);
+    assert!(subcommand.1.contains("bits"), "{}", subcommand.1);
+    let (code, stdout, _) = run(&["passphrase", "--seed=3", "--wordlist", fixture, "--format", "json"], "");
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("\"mode\": \"passphrase\""), "{}", stdout);
+}
+
+#[test]
+fn test_check() {
+    let args = ["check", "--no-config", "-c", "-n", "-y", "10"];
+    let (code, stdout, _) = run(&args, "Secret#123x\n");
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "line 1: ok\n");
+
+    let (code, stdout, _) = run(&args, "Secret#123x\nswordfish\n");
+    assert_eq!(code, Some(1));
+    assert!(stdout.starts_with("line 1: ok\nline 2: too short (9 < 10 characters)"), "{}", stdout);
+    assert!(!stdout.contains("swordfish"));
+
+    let (code, stdout, _) = run(&["check", "--help"], "");
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with("Usage: pwgen check"));
+
+    // Количество паролей для проверки не имеет смысла
+    let (code, _, stderr) = run(&["check", "--no-config", "10", "3"], "");
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("pwgen check --help"), "{}", stderr);
+}
+
+#[test]
+fn test_mangen() {
+    let (code, stdout, _) = run(&["mangen"], "");
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with(".TH PWGEN 1"), "{}", stdout);
+    assert!(stdout.contains(".SH OPTIONS"));
+
+    assert_eq!(run(&["mangen", "extra"], "").0, Some(2));
+}
+
+#[test]
+fn test_wordlist() {
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/wordlist.txt");
+    let words = ["anchor", "bright", "castle", "lantern", "meadow", "pillow"];
+    let (code, stdout, stderr) = run(&["--no-config", "--wordlist", fixture, "--words", "3", "-N", "10"], "");
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout.lines().count(), 10);
+    for phrase in stdout.lines() {
+        assert!(phrase.split('-').all(|word| words.contains(&word)), "{}", phrase);
+    }
+    // Шесть слов после удаления повторов: 2.6 бита на слово
+    assert!(stderr.contains("has only 6 words, 2.6 bits per word"), "{}", stderr);
+
+    // Тот же список из stdin
+    let text = std::fs::read_to_string(fixture).unwrap();
+    let (code, stdout, _) = run(&["--no-config", "--wordlist", "-", "-N", "4", "--separator", " "], &text);
+    assert_eq!(code, Some(0));
+    for phrase in stdout.lines() {
+        assert_eq!(phrase.split(' ').count(), 5, "{}", phrase);
+        assert!(phrase.split(' ').all(|word| words.contains(&word)), "{}", phrase);
+    }
+
+    let (code, _, stderr) = run(&["--no-config", "--wordlist", "-"], "same\nsame\n\n");
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("fewer than 2 distinct words"), "{}", stderr);
+
+    // check сам читает пароли из stdin
+    let (code, _, stderr) = run(&["check", "--no-config", "--wordlist", "-"], "");
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("--wordlist - cannot be used with check"), "{}", stderr);
+}
+
+#[test]
+fn test_like_stdin() {
+    // Старый пароль из stdin: форма та же, символы новые, сам он нигде не печатается
+    let old = "Tr0ub4dor&3";
+    let args = ["--no-config", "--porcelain", "--like", "-", "-N", "20"];
+    let (code, stdout, stderr) = run(&args, &format!("{}\n", old));
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout.lines().count(), 20);
+    for line in stdout.lines() {
+        assert_eq!(line.len(), old.len(), "{}", line);
+        for (new, old) in line.chars().zip(old.chars()) {
+            assert_ne!(new, old, "{}", line);
+            assert_eq!(new.is_ascii_uppercase(), old.is_ascii_uppercase(), "{}", line);
+            assert_eq!(new.is_ascii_lowercase(), old.is_ascii_lowercase(), "{}", line);
+            assert_eq!(new.is_ascii_digit(), old.is_ascii_digit(), "{}", line);
+        }
+    }
+
+    let (code, _, stderr) = run(&["--no-config", "--like", "-"], "");
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("expected a password to imitate on stdin"), "{}", stderr);
+    let (code, _, stderr) = run(&["check", "--no-config", "--like", "-"], "x\n");
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("--like - cannot be used with check"), "{}", stderr);
+}
@@ -0,0 +1,42 @@
+// Скрипты дополнения сравниваются с образцами в tests/fixtures. После
+// изменения таблицы опций образцы обновляются:
+//
+//     cargo run -- completions bash > tests/fixtures/pwgen.bash
+//     cargo run -- completions zsh > tests/fixtures/_pwgen
+use std::process::Command;
+
+fn completions(shell: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["completions", shell])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_bash_snapshot() {
+    assert_eq!(completions("bash"), include_str!("fixtures/pwgen.bash"));
+}
+
+#[test]
+fn test_zsh_snapshot() {
+    assert_eq!(completions("zsh"), include_str!("fixtures/_pwgen"));
+}
+
+#[test]
+fn test_fish_and_powershell() {
+    assert!(completions("fish").contains("complete -c pwgen"));
+    assert!(completions("powershell").contains("Register-ArgumentCompleter"));
+}
+
+#[test]
+fn test_unknown_shell_is_usage_error() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["completions", "tcsh"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("pwgen completions --help"), "{}", stderr);
+}
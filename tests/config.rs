@@ -0,0 +1,60 @@
+// Файл настроек в $XDG_CONFIG_HOME/pwgen/config.toml
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn config_home(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pwgen-rs-{}-{}", std::process::id(), name));
+    fs::create_dir_all(dir.join("pwgen")).unwrap();
+    fs::write(dir.join("pwgen").join("config.toml"), contents).unwrap();
+    dir
+}
+
+fn run(config_home: &Path, args: &[&str]) -> (Option<i32>, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .env("XDG_CONFIG_HOME", config_home)
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_config_file_sets_defaults() {
+    let dir = config_home("config-defaults", "length = 20\nsecure = true\nsymbols = true\n");
+
+    let (code, stdout, _) = run(&dir, &[]);
+    assert_eq!(code, Some(0));
+    let password = stdout.trim();
+    assert_eq!(password.len(), 20);
+    assert!(password.bytes().any(|c| c.is_ascii_punctuation()), "{}", password);
+
+    // Флаги командной строки важнее файла
+    let (_, stdout, _) = run(&dir, &["12"]);
+    assert_eq!(stdout.trim().len(), 12);
+
+    // --no-config: файл не читается
+    let (_, stdout, _) = run(&dir, &["--no-config"]);
+    assert_eq!(stdout.trim().len(), 8);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_config_file_error() {
+    let dir = config_home("config-error", "length = 20\ncount = many\n");
+
+    // Ошибка в файле - ошибка настроек, как и в флагах
+    let (code, _, stderr) = run(&dir, &[]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("config.toml:2: count: invalid value many"), "{}", stderr);
+
+    // Сломанный файл не мешает работе с --no-config
+    assert_eq!(run(&dir, &["--no-config"]).0, Some(0));
+
+    fs::remove_dir_all(dir).unwrap();
+}
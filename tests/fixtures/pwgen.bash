@@ -0,0 +1,55 @@
+# bash completion for pwgen, generated by 'pwgen completions bash'
+_pwgen() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}"
+    case "${COMP_WORDS[1]}" in
+        completions)
+            if [[ $COMP_CWORD -eq 2 ]]; then
+                COMPREPLY=($(compgen -W "bash zsh fish powershell" -- "$cur"))
+            fi
+            return ;;
+        passphrase)
+            case "$prev" in
+                --wordlist)
+                    COMPREPLY=($(compgen -f -- "$cur"))
+                    return ;;
+                --format)
+                    COMPREPLY=($(compgen -W "text json jsonl csv" -- "$cur"))
+                    return ;;
+                --rng)
+                    COMPREPLY=($(compgen -W "auto getrandom urandom file:" -- "$cur"))
+                    return ;;
+                --lang|--separator|--seed)
+                    return ;;
+            esac
+            COMPREPLY=($(compgen -W "--lang --wordlist --separator --capitalize-words --inject-digit --inject-symbol --show-entropy --format --rng --seed -h --help" -- "$cur"))
+            return ;;
+    esac
+    case "$prev" in
+        --profanity-file|--homoglyph-file|--charset-file|--syllable-file|--wordlist|-H|--sha1|--random-source|-o|--output)
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return ;;
+        --charset)
+            COMPREPLY=($(compgen -W "hex base32 base58 alnum printable" -- "$cur"))
+            return ;;
+        --rng)
+            COMPREPLY=($(compgen -W "auto getrandom urandom file:" -- "$cur"))
+            return ;;
+        --qr-format)
+            COMPREPLY=($(compgen -W "ansi utf8 png:" -- "$cur"))
+            return ;;
+        --format)
+            COMPREPLY=($(compgen -W "text json jsonl csv" -- "$cur"))
+            return ;;
+        --color)
+            COMPREPLY=($(compgen -W "auto always never" -- "$cur"))
+            return ;;
+        -L|--length|--length-range|--bits|--prefix|--suffix|--group|--weights|--min-lower|--min-upper|--min-digits|--min-symbols|--max-lower|--max-upper|--max-digits|--max-symbols|--classes|--template|--blocks|--like|-N|--num-passwords|--count|--case|-r|--remove-chars|--include-only|--require-chars|--preset|--memorable-model|--syllables|--words|--words-acrostic|--separator|--lang|--ambiguous-chars|--seed|--encrypt-to|--pick|--clear-after|--columns|--per-line)
+            return ;;
+    esac
+    local words="-c --capitalize -L --length --length-range --bits --prefix --suffix --length-includes-affixes --group --group-counts-length --weights --min-lower --min-upper --min-digits --min-symbols --max-lower --max-upper --max-digits --max-symbols --classes --template --blocks --like --balanced -N --num-passwords --count -A --no-capitalize -n --numerals -0 --no-numerals -y --symbols --shell-safe --url-safe --alpha-only --digits-only --pin --mobile-friendly --no-shift --layout-safe --no-leading-digit --no-leading-symbol --no-edge-symbols --no-repeat --no-sequences --no-keyboard-walks --no-dictionary-words --no-profanity --profanity-file --case --homoglyph-strict --homoglyph-file -r --remove-chars --include-only --require-chars --charset --preset --charset-file -s --secure --memorable-model --syllable-file --syllables --leet --words --words-acrostic --separator --capitalize-words --inject-digit --inject-symbol --wordlist --lang -B --ambiguous --ambiguous-chars -H --sha1 --random-source --rng --seed --fast-rng --verbose -o --output --force --encrypt-to --copy -i --interactive --mnemonic --phonetic --annotate --qr --qr-format --qr-only --pick --clear-after --lock-memory --no-config --self-test --dry-run -h --help -C --columns --per-line --numbered --porcelain --unique --sort --show-entropy -z --null --format --csv-safe --color -1 -v --no-vowels"
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        words="generate passphrase check completions mangen $words"
+    fi
+    COMPREPLY=($(compgen -W "$words" -- "$cur"))
+}
+complete -F _pwgen pwgen pwgen-rs
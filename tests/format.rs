@@ -0,0 +1,401 @@
+// Машиночитаемый вывод --format. JSON и CSV разбираются небольшими
+// парсерами ниже, а не поиском подстрок, чтобы проверить и экранирование.
+use std::collections::BTreeMap;
+use std::process::Command;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(map) => map.get(key).unwrap_or_else(|| panic!("no {:?} in {:?}", key, self)),
+            _ => panic!("not an object: {:?}", self),
+        }
+    }
+
+    fn str(&self) -> &str {
+        match self {
+            Json::String(s) => s,
+            _ => panic!("not a string: {:?}", self),
+        }
+    }
+
+    fn number(&self) -> f64 {
+        match self {
+            Json::Number(n) => *n,
+            _ => panic!("not a number: {:?}", self),
+        }
+    }
+
+    fn array(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => panic!("not an array: {:?}", self),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_space();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            other => Err(format!("expected {:?}, got {:?}", expected, other)),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("bad literal, expected {}", word));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_space();
+        match self.chars.peek().copied() {
+            Some('n') => self.literal("null", Json::Null),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => {
+                self.chars.next();
+                let mut items = Vec::new();
+                self.skip_space();
+                if self.chars.peek() == Some(&']') {
+                    self.chars.next();
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_space();
+                    match self.chars.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Json::Array(items)),
+                        other => return Err(format!("bad array separator {:?}", other)),
+                    }
+                }
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut map = BTreeMap::new();
+                self.skip_space();
+                if self.chars.peek() == Some(&'}') {
+                    self.chars.next();
+                    return Ok(Json::Object(map));
+                }
+                loop {
+                    self.skip_space();
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    map.insert(key, self.value()?);
+                    self.skip_space();
+                    match self.chars.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Json::Object(map)),
+                        other => return Err(format!("bad object separator {:?}", other)),
+                    }
+                }
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                        break;
+                    }
+                    number.push(c);
+                    self.chars.next();
+                }
+                number.parse().map(Json::Number).map_err(|e| format!("{}: {}", number, e))
+            }
+            other => Err(format!("unexpected {:?}", other)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.chars.next() != Some('"') {
+            return Err("expected a string".to_string());
+        }
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.chars.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let hex: String = (0..4).filter_map(|_| self.chars.next()).collect();
+                        let code = u32::from_str_radix(&hex, 16).map_err(|e| e.to_string())?;
+                        s.push(char::from_u32(code).ok_or("bad \\u escape")?);
+                    }
+                    other => return Err(format!("bad escape {:?}", other)),
+                },
+                Some(c) if (c as u32) < 0x20 => return Err(format!("unescaped control character {:?}", c)),
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+}
+
+fn parse_json(text: &str) -> Result<Json, String> {
+    let mut parser = Parser { chars: text.chars().peekable() };
+    let value = parser.value()?;
+    parser.skip_space();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("trailing {:?}", c)),
+    }
+}
+
+fn run(args: &[&str]) -> (Option<i32>, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .env_remove("PWGEN_OPTS")
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_json_round_trip() {
+    let args = ["--no-config", "--seed=7", "-s", "-y", "16", "20"];
+    let (code, text, _) = run(&args);
+    assert_eq!(code, Some(0));
+    let (code, stdout, _) = run(&[&args[..], &["--format", "json"]].concat());
+    assert_eq!(code, Some(0));
+    let document = parse_json(&stdout).unwrap();
+
+    let settings = document.get("settings");
+    assert_eq!(settings.get("mode").str(), "secure");
+    assert_eq!(settings.get("length").number(), 16.0);
+    assert_eq!(settings.get("count").number(), 20.0);
+    assert_eq!(settings.get("charset_size").number(), 94.0);
+    assert_eq!(settings.get("flags").get("symbols"), &Json::Bool(true));
+    assert_eq!(settings.get("flags").get("seeded"), &Json::Bool(true));
+    assert_eq!(settings.get("flags").get("url_safe"), &Json::Bool(false));
+    assert_eq!(settings.get("charset"), &Json::Null);
+
+    // Те же пароли, что и в тексте; с -y среди 20 почти наверняка есть " или \
+    let expected: Vec<&str> = text.split_whitespace().collect();
+    let passwords = document.get("passwords").array();
+    assert_eq!(passwords.len(), 20);
+    for (entry, expected) in passwords.iter().zip(expected) {
+        assert_eq!(entry.get("password").str(), expected);
+        assert_eq!(entry.get("length").number(), 16.0);
+        assert!((entry.get("entropy_bits").number() - 16.0 * 94f64.log2()).abs() < 0.01);
+        assert_eq!(entry.get("mode").str(), "secure");
+    }
+}
+
+#[test]
+fn test_json_mode_settings() {
+    // Режим, который сужает набор, виден в настройках
+    let (code, stdout, _) = run(&["--no-config", "--seed=5", "--url-safe", "--format=json", "-N", "2"]);
+    assert_eq!(code, Some(0));
+    let settings = parse_json(&stdout).unwrap().get("settings").clone();
+    assert_eq!(settings.get("flags").get("url_safe"), &Json::Bool(true));
+    let (code, stdout, _) = run(&["--no-config", "--seed=5", "--charset=hex", "--format=json", "-N", "2"]);
+    assert_eq!(code, Some(0));
+    let settings = parse_json(&stdout).unwrap().get("settings").clone();
+    assert_eq!(settings.get("charset").str(), "hex");
+    assert_eq!(settings.get("include_only"), &Json::Null);
+}
+
+#[cfg(feature = "wordlist-en")]
+#[test]
+fn test_json_passphrase_and_columns() {
+    // Столбцы в JSON не нужны и пропускаются, а у фраз нет набора символов
+    let (code, stdout, _) = run(&["--no-config", "--seed=3", "--words", "4", "-C", "--format=json", "-N", "3"]);
+    assert_eq!(code, Some(0));
+    let document = parse_json(&stdout).unwrap();
+    assert_eq!(document.get("settings").get("mode").str(), "passphrase");
+    assert_eq!(document.get("settings").get("charset_size"), &Json::Null);
+    assert_eq!(document.get("settings").get("length"), &Json::Null);
+    assert_eq!(document.get("settings").get("words").number(), 4.0);
+    let passwords = document.get("passwords").array();
+    assert_eq!(passwords.len(), 3);
+    for entry in passwords {
+        assert_eq!(entry.get("password").str().split('-').count(), 4);
+        assert!(entry.get("entropy_bits").number() > 40.0);
+    }
+}
+
+#[test]
+fn test_json_mnemonic() {
+    // Фраза - поле пароля, по слову на символ
+    let args = ["--no-config", "--seed=7", "-s", "10", "3", "--format", "json", "--mnemonic"];
+    let (code, stdout, _) = run(&args);
+    assert_eq!(code, Some(0));
+    let document = parse_json(&stdout).unwrap();
+    for entry in document.get("passwords").array() {
+        let password = entry.get("password").str();
+        let words: Vec<&str> = entry.get("mnemonic").str().split(' ').collect();
+        assert_eq!(words.len(), password.chars().count());
+        // Слово буквы начинается с нее, в том же регистре
+        for (c, word) in password.chars().zip(words).filter(|(c, _)| c.is_ascii_alphabetic()) {
+            assert!(word.starts_with(c), "{} {}", c, word);
+        }
+    }
+}
+
+#[test]
+fn test_json_annotations() {
+    let args = ["--no-config", "--seed=7", "-s", "-y", "16", "20", "--format", "json", "--annotate", "--phonetic"];
+    let (code, stdout, _) = run(&args);
+    assert_eq!(code, Some(0));
+    let document = parse_json(&stdout).unwrap();
+    for entry in document.get("passwords").array() {
+        let password: Vec<char> = entry.get("password").str().chars().collect();
+        assert_eq!(entry.get("phonetic").str().split(' ').count(), password.len());
+        // Пометка на каждом похожем символе и только на нем
+        let ambiguous = "B8G6I1l0OQDS5Z2'`\"|!,.;:";
+        let expected: Vec<usize> = (1..=password.len()).filter(|&i| ambiguous.contains(password[i - 1])).collect();
+        let notes = entry.get("annotations").array();
+        let positions: Vec<usize> = notes.iter().map(|note| note.get("position").number() as usize).collect();
+        assert_eq!(positions, expected);
+        for (note, position) in notes.iter().zip(positions) {
+            assert_eq!(note.get("character").str(), password[position - 1].to_string());
+            assert!(!note.get("description").str().is_empty());
+            assert!(!note.get("confused_with").str().is_empty());
+        }
+    }
+}
+
+#[test]
+fn test_format_errors() {
+    let (code, _, stderr) = run(&["--no-config", "--format", "yaml"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("--format"), "{}", stderr);
+    let (code, _, stderr) = run(&["--no-config", "--format", "json", "--porcelain"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("--porcelain"), "{}", stderr);
+}
+
+#[test]
+fn test_jsonl_lines() {
+    let (code, stdout, _) = run(&["--no-config", "--seed=7", "-s", "-y", "16", "20", "--format", "jsonl"]);
+    assert_eq!(code, Some(0));
+    let (_, text, _) = run(&["--no-config", "--seed=7", "-s", "-y", "16", "20"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 20);
+    for (line, expected) in lines.iter().zip(text.split_whitespace()) {
+        let entry = parse_json(line).unwrap();
+        assert_eq!(entry.get("password").str(), expected);
+        assert!((entry.get("entropy_bits").number() - 16.0 * 94f64.log2()).abs() < 0.01);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_jsonl_into_head() {
+    use std::process::Stdio;
+
+    // Строк намного больше, чем вмещает канал: запись упрется в закрытый head
+    let mut pwgen = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .env_remove("PWGEN_OPTS")
+        .args(["--no-config", "--format", "jsonl", "-s", "32", "1000000"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let head = Command::new("head")
+        .arg("-3")
+        .stdin(Stdio::from(pwgen.stdout.take().unwrap()))
+        .output()
+        .unwrap();
+    let output = pwgen.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8(head.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    for line in lines {
+        let entry = parse_json(line).unwrap();
+        assert_eq!(entry.get("length").number(), 32.0);
+    }
+}
+
+// Строки CSV по RFC 4180: поля в кавычках могут содержать запятые и
+// удвоенные кавычки
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let mut fields = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, quoted) {
+                ('"', false) => quoted = true,
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    fields.last_mut().unwrap().push('"');
+                }
+                ('"', true) => quoted = false,
+                (',', false) => fields.push(String::new()),
+                (c, _) => fields.last_mut().unwrap().push(c),
+            }
+        }
+        assert!(!quoted, "unterminated quote in {:?}", line);
+        rows.push(fields);
+    }
+    rows
+}
+
+#[test]
+fn test_csv_quoting() {
+    // Только запятые, кавычки и a, и каждый пароль начинается с =
+    let args = ["--no-config", "--seed=1", "-s", "--include-only", ",\"a", "--prefix", "=", "6", "3"];
+    let (_, text, _) = run(&args);
+    let (code, stdout, _) = run(&[&args[..], &["--format", "csv"]].concat());
+    assert_eq!(code, Some(0));
+    let rows = parse_csv(&stdout);
+    assert_eq!(rows[0], ["index", "password", "length", "entropy_bits", "mode"]);
+    assert_eq!(rows.len(), 4);
+    for (i, (row, expected)) in rows[1..].iter().zip(text.split_whitespace()).enumerate() {
+        assert_eq!(row.len(), 5, "{:?}", row);
+        assert_eq!(row[0], (i + 1).to_string());
+        assert_eq!(row[1], expected);
+        assert!(row[1].starts_with('=') && row[1].contains([',', '"']), "{}", row[1]);
+        assert_eq!(row[2], "7");
+        assert_eq!(row[3], "9.51");
+        assert_eq!(row[4], "secure");
+    }
+
+    // --csv-safe ставит апостроф перед =, длина остается прежней
+    let (code, stdout, _) = run(&[&args[..], &["--format", "csv", "--csv-safe"]].concat());
+    assert_eq!(code, Some(0));
+    for (row, expected) in parse_csv(&stdout)[1..].iter().zip(text.split_whitespace()) {
+        assert_eq!(row[1], format!("'{}", expected));
+        assert_eq!(row[2], "7");
+    }
+}
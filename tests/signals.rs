@@ -0,0 +1,40 @@
+// Прерывание сигналом во время генерации большого пакета
+#![cfg(unix)]
+
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+unsafe extern "C" {
+    fn kill(pid: i32, sig: i32) -> i32;
+}
+
+fn interrupt_with(signum: i32) -> Option<i32> {
+    // Пакет, который заведомо не успеет сгенерироваться
+    let child = Command::new(env!("CARGO_BIN_EXE_pwgen-rs"))
+        .args(["-s", "16", "5000000"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    thread::sleep(Duration::from_millis(300));
+    // SAFETY: pid принадлежит нашему дочернему процессу
+    assert_eq!(unsafe { kill(child.id() as i32, signum) }, 0);
+
+    let output = child.wait_with_output().unwrap();
+    // Прерванный запуск не выдает ни паролей, ни сообщений об ошибке
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+    output.status.code()
+}
+
+#[test]
+fn test_sigint_exit_code() {
+    assert_eq!(interrupt_with(2), Some(130));
+}
+
+#[test]
+fn test_sigterm_exit_code() {
+    assert_eq!(interrupt_with(15), Some(143));
+}